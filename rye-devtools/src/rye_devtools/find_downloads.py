@@ -62,8 +62,15 @@ class CPythonFinder(Finder):
     HIDDEN_FLAVORS = [
         "debug",
         "noopt",
+        "install_only_stripped",
         "install_only",
     ]
+    # flavors that are published as separate, smaller artifacts next to the
+    # full builds.  These are rendered into their own tables.
+    BUILD_FLAVORS = [
+        "install_only",
+        "install_only_stripped",
+    ]
     SPECIAL_TRIPLES = {
         "macos": "x86_64-apple-darwin",
         "linux64": "x86_64-unknown-linux-gnu",
@@ -118,10 +125,17 @@ class CPythonFinder(Finder):
         await self.fetch_indygreg_checksums(downloads, n=20)
         return downloads
 
+    @classmethod
+    def build_flavor(cls, download: PythonDownload) -> str:
+        """Return the build flavor (`full`, `install_only`, ...) of a download."""
+        if download.triple.flavor in cls.BUILD_FLAVORS:
+            return download.triple.flavor
+        return "full"
+
     async def fetch_indygreg_downloads(self, pages: int = 100) -> list[PythonDownload]:
         """Fetch all the indygreg downloads from the release API."""
         results: dict[
-            Version, dict[tuple[str, str, str | None], list[PythonDownload]]
+            str, dict[Version, dict[tuple[str, str, str | None], list[PythonDownload]]]
         ] = {}
 
         for page in range(1, pages):
@@ -134,28 +148,31 @@ class CPythonFinder(Finder):
                 for asset in row["assets"]:
                     url = asset["browser_download_url"]
                     download = self.parse_download_url(url)
-                    if download is not None:
-                        (
-                            results.setdefault(download.version, {})
-                            # For now, we only group by arch and platform, because Rust's PythonVersion doesn't have a notion
-                            # of environment. Flavor will never be used to sort download choices and must not be included in grouping.
-                            .setdefault(
-                                (
-                                    download.triple.arch,
-                                    download.triple.platform,
-                                    download.triple.environment,
-                                ),
-                                [],
-                            )
-                            .append(download)
+                    if download is None:
+                        continue
+                    (
+                        results.setdefault(self.build_flavor(download), {})
+                        .setdefault(download.version, {})
+                        # For now, we only group by arch and platform, because Rust's PythonVersion doesn't have a notion
+                        # of environment. Flavor will never be used to sort download choices and must not be included in grouping.
+                        .setdefault(
+                            (
+                                download.triple.arch,
+                                download.triple.platform,
+                                download.triple.environment,
+                            ),
+                            [],
                         )
+                        .append(download)
+                    )
 
         downloads = []
-        for version, platform_downloads in results.items():
-            for flavors in platform_downloads.values():
-                best = self.pick_best_download(flavors)
-                if best is not None:
-                    downloads.append(best)
+        for grouped in results.values():
+            for version, platform_downloads in grouped.items():
+                for flavors in platform_downloads.values():
+                    best = self.pick_best_download(flavors)
+                    if best is not None:
+                        downloads.append(best)
         return downloads
 
     @classmethod
@@ -391,18 +408,27 @@ def render(downloads: list[PythonDownload]):
         "// To regenerate, run `rye run find-downloads > rye/src/sources/generated/python_downloads.inc` from the root of the repository."
     )
     print("use std::borrow::Cow;")
-    print("pub const PYTHON_VERSIONS: &[(PythonVersion, &str, Option<&str>)] = &[")
-
-    for download in downloads:
-        triple = download.triple
-        version = download.version
-        sha256 = to_rust_option(download.sha256)
-        env = to_rust_cow_option(triple.environment)
-        print(
-            f'    (PythonVersion {{ name: Cow::Borrowed("{download.implementation}"), arch: Cow::Borrowed("{triple.arch}"), os: Cow::Borrowed("{triple.platform}"), environment: {env}, major: {version.major}, minor: {version.minor}, patch: {version.patch}, suffix: None }}, "{download.url}", {sha256}),'
-        )
 
-    print("];")
+    def render_versions(name: str, downloads: list[PythonDownload]):
+        print(f"pub const {name}: &[(PythonVersion, &str, Option<&str>)] = &[")
+        for download in downloads:
+            triple = download.triple
+            version = download.version
+            sha256 = to_rust_option(download.sha256)
+            env = to_rust_cow_option(triple.environment)
+            print(
+                f'    (PythonVersion {{ name: Cow::Borrowed("{download.implementation}"), arch: Cow::Borrowed("{triple.arch}"), os: Cow::Borrowed("{triple.platform}"), environment: {env}, major: {version.major}, minor: {version.minor}, patch: {version.patch}, suffix: None }}, "{download.url}", {sha256}),'
+            )
+        print("];")
+
+    for name, flavor in [
+        ("PYTHON_VERSIONS", "full"),
+        ("PYTHON_INSTALL_ONLY_VERSIONS", "install_only"),
+        ("PYTHON_STRIPPED_VERSIONS", "install_only_stripped"),
+    ]:
+        render_versions(
+            name, [d for d in downloads if CPythonFinder.build_flavor(d) == flavor]
+        )
 
 
 async def async_main():
@@ -38,3 +38,32 @@ def test_batched():
 )
 def test_parse_triplets(input, expected):
     assert CPythonFinder.parse_triple(input) == expected
+
+
+@pytest.mark.parametrize(
+    "filename, flavor",
+    [
+        (
+            "cpython-3.12.3%2B20240415-x86_64-unknown-linux-gnu-install_only.tar.gz",
+            "install_only",
+        ),
+        (
+            "cpython-3.12.3%2B20240415-x86_64-unknown-linux-gnu-install_only_stripped.tar.gz",
+            "install_only_stripped",
+        ),
+        (
+            "cpython-3.12.3%2B20240415-x86_64-unknown-linux-gnu-pgo%2Blto-full.tar.zst",
+            "pgo+lto",
+        ),
+    ],
+)
+def test_parse_download_url(filename, flavor):
+    url = f"https://github.com/indygreg/python-build-standalone/releases/download/20240415/{filename}"
+    download = CPythonFinder.parse_download_url(url)
+    assert download is not None
+    assert download.triple.flavor == flavor
+
+
+def test_parse_download_url_skips_split_archives():
+    url = "https://github.com/indygreg/python-build-standalone/releases/download/20240415/cpython-3.12.3%2B20240415-x86_64-unknown-linux-gnu-pgo%2Blto-full.tar.zst.001"
+    assert CPythonFinder.parse_download_url(url) is None
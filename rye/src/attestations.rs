@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error};
+use pep508_rs::Requirement;
+
+use crate::bootstrap::ensure_self_venv;
+use crate::platform::get_app_dir;
+use crate::pyproject::get_current_venv_python_version;
+use crate::utils::{get_venv_python_bin, CommandOutput, IoPathContext};
+use crate::uv::{UvBuilder, UvInstallOptions};
+
+/// The version of `pypi-attestations` used to sign and verify artifacts.
+///
+/// It pulls in sigstore and its dependencies, which is why it is installed
+/// into its own virtualenv on first use instead of the self venv.
+const ATTESTATIONS_REQUIREMENT: &str = "pypi-attestations==0.0.21";
+
+/// The suffix of the attestation file written next to an artifact.
+pub const ATTESTATION_SUFFIX: &str = ".publish.attestation";
+
+/// Creates PEP 740 attestations for the given distribution files.
+///
+/// Signing uses the ambient OIDC credentials of the CI (or an interactive
+/// sigstore login) and writes a `.publish.attestation` file next to each
+/// artifact.
+pub fn sign_artifacts(files: &[PathBuf], output: CommandOutput) -> Result<(), Error> {
+    let mut sign_cmd = Command::new(ensure_attestations_env(output)?);
+    sign_cmd.arg("-mpypi_attestations").arg("sign").args(files);
+    if output == CommandOutput::Quiet {
+        sign_cmd.stdout(Stdio::null());
+        sign_cmd.stderr(Stdio::null());
+    }
+    let status = sign_cmd.status()?;
+    if !status.success() {
+        bail!("failed to create attestations");
+    }
+    Ok(())
+}
+
+/// Verifies the attestations next to the given distribution files against
+/// the expected signing identity.
+pub fn verify_artifacts(
+    files: &[PathBuf],
+    identity: &str,
+    output: CommandOutput,
+) -> Result<bool, Error> {
+    for file in files {
+        let attestation = attestation_path(file);
+        if !attestation.is_file() {
+            bail!("no attestation found for {}", file.display());
+        }
+    }
+    let mut verify_cmd = Command::new(ensure_attestations_env(output)?);
+    verify_cmd
+        .arg("-mpypi_attestations")
+        .arg("verify")
+        .arg("attestation")
+        .arg("--identity")
+        .arg(identity)
+        .args(files);
+    if output == CommandOutput::Quiet {
+        verify_cmd.stdout(Stdio::null());
+        verify_cmd.stderr(Stdio::null());
+    }
+    Ok(verify_cmd.status()?.success())
+}
+
+/// Returns the path of the attestation file for an artifact.
+pub fn attestation_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(ATTESTATION_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Returns the Python of the virtualenv with `pypi-attestations` installed.
+fn ensure_attestations_env(output: CommandOutput) -> Result<PathBuf, Error> {
+    let env_dir = get_app_dir().join("attestations");
+    let marker = env_dir.join("rye-attestations.txt");
+    if fs::read_to_string(&marker).map_or(false, |x| x == ATTESTATIONS_REQUIREMENT) {
+        return Ok(get_venv_python_bin(&env_dir));
+    }
+
+    echo!(if output, "Installing {}", ATTESTATIONS_REQUIREMENT);
+    let self_venv = ensure_self_venv(output)?;
+    let py_ver = get_current_venv_python_version(&self_venv)
+        .context("unable to determine the Python version of the self venv")?;
+    let uv = UvBuilder::new()
+        .with_output(output.quieter())
+        .ensure_exists()?
+        .venv(&env_dir, &get_venv_python_bin(&self_venv), &py_ver, None)?
+        .with_output(output.quieter());
+    uv.install(
+        &Requirement::from_str(ATTESTATIONS_REQUIREMENT)?,
+        UvInstallOptions::default(),
+    )?;
+    fs::write(&marker, ATTESTATIONS_REQUIREMENT)
+        .path_context(&marker, "unable to write attestations marker")?;
+    Ok(get_venv_python_bin(&env_dir))
+}
@@ -9,16 +9,21 @@ use anyhow::{anyhow, bail, Context, Error};
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
 use once_cell::sync::Lazy;
-use tempfile::tempdir_in;
+use same_file::is_same_file;
 
 use crate::config::Config;
 use crate::piptools::LATEST_PIP;
 use crate::platform::{
-    get_app_dir, get_canonical_py_path, get_python_bin_within, get_toolchain_python_bin,
-    list_known_toolchains,
+    get_app_dir, get_canonical_py_path, get_python_bin_within, get_toolchain_archive_cache_dir,
+    get_toolchain_python_bin, list_known_toolchains, register_toolchain_in_registry,
+    TOOLCHAIN_FLAVOR_FILENAME,
 };
 use crate::pyproject::latest_available_python_version;
-use crate::sources::py::{get_download_url, PythonVersion, PythonVersionRequest};
+use crate::sources::py::{
+    get_download_url, get_flavor_download_url, BuildFlavor, PythonVersion, PythonVersionRequest,
+};
+use crate::timings;
+use crate::utils::filelock::FileLock;
 use crate::utils::{check_checksum, symlink_file, unpack_archive, CommandOutput, IoPathContext};
 use crate::uv::UvBuilder;
 
@@ -34,7 +39,7 @@ pub const SELF_PYTHON_TARGET_VERSION: PythonVersionRequest = PythonVersionReques
     suffix: None,
 };
 
-const SELF_VERSION: u64 = 17;
+pub const SELF_VERSION: u64 = 20;
 
 const SELF_REQUIREMENTS: &str = r#"
 build==1.1.1
@@ -43,13 +48,17 @@ charset-normalizer==3.3.2
 click==8.1.7
 distlib==0.3.8
 filelock==3.12.2
+id==1.5.0
 idna==3.4
-packaging==23.1
+mypy==1.9.0
+mypy-extensions==1.0.0
+packaging==24.2
 platformdirs==4.0.0
 pyproject_hooks==1.0.0
 requests==2.31.0
 tomli==2.0.1
-twine==4.0.2
+twine==6.1.0
+typing-extensions==4.10.0
 unearth==0.14.0
 urllib3==2.0.7
 virtualenv==20.25.0
@@ -59,14 +68,16 @@ ruff==0.3.0
 static FORCED_TO_UPDATE: AtomicBool = AtomicBool::new(false);
 
 fn is_up_to_date() -> bool {
-    static UP_TO_UPDATE: Lazy<bool> = Lazy::new(|| {
-        fs::read_to_string(get_app_dir().join("self").join("tool-version.txt"))
-            .ok()
-            .map_or(false, |x| x.parse() == Ok(SELF_VERSION))
-    });
+    static UP_TO_UPDATE: Lazy<bool> = Lazy::new(read_is_up_to_date);
     *UP_TO_UPDATE || FORCED_TO_UPDATE.load(atomic::Ordering::Relaxed)
 }
 
+fn read_is_up_to_date() -> bool {
+    fs::read_to_string(get_app_dir().join("self").join("tool-version.txt"))
+        .ok()
+        .map_or(false, |x| x.parse() == Ok(SELF_VERSION))
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum SelfVenvStatus {
     NotUpToDate,
@@ -101,6 +112,18 @@ pub fn ensure_self_venv_with_toolchain(
 ) -> Result<PathBuf, Error> {
     let app_dir = get_app_dir();
 
+    if let Ok(venv_dir) = get_self_venv_status() {
+        return Ok(venv_dir);
+    }
+
+    // another rye process might be bootstrapping at the same time, in which
+    // case the internals are up to date once it's done.
+    let _lock = FileLock::acquire("self", "bootstrapping rye internals", output)?;
+    if read_is_up_to_date() && app_dir.join("self").is_dir() {
+        FORCED_TO_UPDATE.store(true, atomic::Ordering::Relaxed);
+        return Ok(app_dir.join("self"));
+    }
+
     let venv_dir = match get_self_venv_status() {
         Ok(venv_dir) => return Ok(venv_dir),
         Err((venv_dir, SelfVenvStatus::DoesNotExist)) => venv_dir,
@@ -171,7 +194,10 @@ pub fn ensure_self_venv_with_toolchain(
             this = env::current_exe()?;
         }
 
-        update_core_shims(&shims, &this)?;
+        {
+            let _phase = timings::phase("shim update");
+            update_core_shims(&shims, &this)?;
+        }
 
         uv_venv.write_tool_version(SELF_VERSION)?;
     }
@@ -181,57 +207,92 @@ pub fn ensure_self_venv_with_toolchain(
     Ok(venv_dir)
 }
 
-pub fn update_core_shims(shims: &Path, this: &Path) -> Result<(), Error> {
+/// Links the `python` shims and the configured extra shims to rye.
+///
+/// Returns the names of all the shims that point to rye.
+pub fn update_core_shims(shims: &Path, this: &Path) -> Result<Vec<String>, Error> {
     #[cfg(unix)]
-    {
-        let py_shim = shims.join("python");
-        let py3_shim = shims.join("python3");
+    let core_shims = ["python", "python3"];
+    #[cfg(windows)]
+    let core_shims = ["python", "pythonw", "python3"];
+
+    let mut rv = Vec::new();
+    for name in core_shims {
+        link_shim(this, &shims.join(name).with_extension(EXE_EXTENSION))?;
+        rv.push(name.to_string());
+    }
+    rv.extend(update_extra_shims(shims, this)?);
+
+    Ok(rv)
+}
+
+/// Creates the extra shims configured in `shims.extra`.
+///
+/// The names of the created shims are recorded so that shims which are no
+/// longer configured can be removed again.  Shims that exist but were not
+/// created by rye (like the shims of tools) are left alone.
+fn update_extra_shims(shims: &Path, this: &Path) -> Result<Vec<String>, Error> {
+    let record = get_app_dir().join("extra-shims.txt");
+    let previous = fs::read_to_string(&record).unwrap_or_default();
+    let previous = previous.lines().collect::<Vec<_>>();
+    let configured = Config::current().extra_shims()?;
+
+    for name in &previous {
+        if !configured.iter().any(|x| x == name) {
+            let shim = shims.join(name).with_extension(EXE_EXTENSION);
+            fs::remove_file(&shim).ok();
+        }
+    }
+
+    let mut linked = Vec::new();
+    for name in configured {
+        let shim = shims.join(&name).with_extension(EXE_EXTENSION);
+        if fs::symlink_metadata(&shim).is_ok()
+            && !previous.contains(&name.as_str())
+            && !is_same_file(&shim, this).unwrap_or(false)
+        {
+            warn!(
+                "not creating shim {} as {} already exists",
+                name,
+                shim.display()
+            );
+            continue;
+        }
+        link_shim(this, &shim)?;
+        linked.push(name);
+    }
 
+    fs::write(&record, linked.join("\n")).path_context(&record, "unable to record extra shims")?;
+    Ok(linked)
+}
+
+/// Places the rye executable at the path of a shim.
+fn link_shim(this: &Path, shim: &Path) -> Result<(), Error> {
+    fs::remove_file(shim).ok();
+
+    #[cfg(unix)]
+    {
         // on linux we cannot symlink at all, as this will misreport.  We will try to do
         // hardlinks and if that fails, we fall back to copying the entire file over.  This
         // for instance is needed when the rye executable is placed on a different volume
         // than ~/.rye/shims
         if cfg!(target_os = "linux") {
-            fs::remove_file(&py_shim).ok();
-            if fs::hard_link(this, &py_shim).is_err() {
-                fs::copy(this, &py_shim).path_context(&py_shim, "tried to copy python shim")?;
-            }
-            fs::remove_file(&py3_shim).ok();
-            if fs::hard_link(this, &py3_shim).is_err() {
-                fs::copy(this, &py3_shim).path_context(&py_shim, "tried to copy python3 shim")?;
+            if fs::hard_link(this, shim).is_err() {
+                fs::copy(this, shim).path_context(shim, "tried to copy shim")?;
             }
 
         // on other unices we always use symlinks
         } else {
-            fs::remove_file(&py_shim).ok();
-            symlink_file(this, &py_shim).path_context(&py_shim, "tried to symlink python shim")?;
-            fs::remove_file(&py3_shim).ok();
-            symlink_file(this, &py3_shim)
-                .path_context(&py3_shim, "tried to symlink python3 shim")?;
+            symlink_file(this, shim).path_context(shim, "tried to symlink shim")?;
         }
     }
 
+    // on windows we need privileges to symlink.  Not everyone might have that, so we
+    // fall back to hardlinks.
     #[cfg(windows)]
     {
-        let py_shim = shims.join("python.exe");
-        let pyw_shim = shims.join("pythonw.exe");
-        let py3_shim = shims.join("python3.exe");
-
-        // on windows we need privileges to symlink.  Not everyone might have that, so we
-        // fall back to hardlinks.
-        fs::remove_file(&py_shim).ok();
-        if symlink_file(this, &py_shim).is_err() {
-            fs::hard_link(this, &py_shim).path_context(&py_shim, "tried to symlink python shim")?;
-        }
-        fs::remove_file(&py3_shim).ok();
-        if symlink_file(this, &py3_shim).is_err() {
-            fs::hard_link(this, &py3_shim)
-                .path_context(&py3_shim, "tried to symlink python3 shim")?;
-        }
-        fs::remove_file(&pyw_shim).ok();
-        if symlink_file(this, &pyw_shim).is_err() {
-            fs::hard_link(this, &pyw_shim)
-                .path_context(&pyw_shim, "tried to symlink pythonw shim")?;
+        if symlink_file(this, shim).is_err() {
+            fs::hard_link(this, shim).path_context(shim, "tried to symlink shim")?;
         }
     }
 
@@ -243,8 +304,8 @@ pub fn get_pip_runner(venv: &Path) -> Result<PathBuf, Error> {
     Ok(get_pip_module(venv)?.join("__pip-runner__.py"))
 }
 
-/// Returns the pip module for the self venv
-pub fn get_pip_module(venv: &Path) -> Result<PathBuf, Error> {
+/// Returns the site-packages folder of a venv
+pub fn get_site_packages(venv: &Path) -> Result<PathBuf, Error> {
     let mut rv = venv.to_path_buf();
     rv.push("lib");
     #[cfg(windows)]
@@ -280,10 +341,14 @@ pub fn get_pip_module(venv: &Path) -> Result<PathBuf, Error> {
             bail!("no site-packages in venv");
         }
     }
-    rv.push("pip");
     Ok(rv)
 }
 
+/// Returns the pip module for the self venv
+pub fn get_pip_module(venv: &Path) -> Result<PathBuf, Error> {
+    Ok(get_site_packages(venv)?.join("pip"))
+}
+
 /// we only support cpython 3.9 to 3.12
 pub fn is_self_compatible_toolchain(version: &PythonVersion) -> bool {
     version.name == "cpython" && version.major == 3 && version.minor >= 9 && version.minor <= 12
@@ -391,10 +456,44 @@ pub fn fetch(
             }
         }
     }
-    let (version, url, sha256) = match get_download_url(version) {
+    let (version, url, checksum) = match get_download_url(version) {
         Some(result) => result,
         None => bail!("unknown version {}", version),
     };
+    let with_build_info = options
+        .build_info
+        .unwrap_or_else(|| Config::current().fetch_with_build_info());
+    // build flavors only exist for the CPython builds.
+    let flavor = match version.name.as_ref() {
+        "cpython" => Config::current().toolchain_build_flavor(),
+        _ => Some(BuildFlavor::Full),
+    };
+    let (url, checksum, fetched_flavor) = select_flavor_download(
+        &version,
+        (url, checksum),
+        flavor,
+        with_build_info,
+        get_flavor_download_url,
+    )?;
+    if !with_build_info && flavor.unwrap_or_default() != fetched_flavor {
+        echo!(
+            if options.output,
+            "No {} build of {} available, using the full build",
+            flavor.unwrap_or_default(),
+            version
+        );
+    }
+
+    // concurrent fetches of the same toolchain wait for each other, the
+    // toolchain is then found to be installed below.
+    let _lock = match options.target_path {
+        Some(_) => None,
+        None => Some(FileLock::acquire(
+            &format!("toolchain-{}", version),
+            &format!("installing {}", version),
+            options.output,
+        )?),
+    };
 
     let target_dir = match options.target_path {
         Some(ref target_dir) => {
@@ -413,8 +512,6 @@ pub fn fetch(
                             target_dir.display()
                         );
                     }
-                    fs::remove_dir_all(target_dir)
-                        .path_context(target_dir, "could not remove target directory")?;
                 } else {
                     bail!("target directory '{}' exists", target_dir.display());
                 }
@@ -429,10 +526,7 @@ pub fn fetch(
                     echo!(if verbose options.output, "Python version already downloaded. Skipping.");
                     return Ok(version);
                 }
-                echo!(if options.output, "Removing the existing Python version");
-                fs::remove_dir_all(&target_dir).with_context(|| {
-                    format!("failed to remove target folder {}", target_dir.display())
-                })?;
+                echo!(if options.output, "Replacing the existing Python version");
             }
             echo!(if verbose options.output, "target dir: {}", target_dir.display());
             Cow::Owned(target_dir)
@@ -440,16 +534,15 @@ pub fn fetch(
     };
 
     echo!(if verbose options.output, "download url: {}", url);
-    echo!(if options.output, "{} {}", style("Downloading").cyan(), version);
-    let archive_buffer = download_url(url, options.output)?;
-
-    if let Some(sha256) = sha256 {
-        echo!(if options.output, "{} {}", style("Checking").cyan(), "checksum");
-        check_checksum(&archive_buffer, sha256)
-            .with_context(|| format!("Checksum check of {} failed", &url))?;
-    } else {
-        echo!(if options.output, "Checksum check skipped (no hash available)");
-    }
+    let archive_buffer = match checksum {
+        Some(checksum) => download_toolchain_archive(&version, url, checksum, options.output)?,
+        None => {
+            echo!(if options.output, "{} {}", style("Downloading").cyan(), version);
+            let archive_buffer = download_url(url, options.output)?;
+            echo!(if options.output, "Checksum check skipped (no hash available)");
+            archive_buffer
+        }
+    };
 
     echo!(if options.output, "{}", style("Unpacking").cyan());
 
@@ -460,12 +553,23 @@ pub fn fetch(
         fs::create_dir_all(parent).path_context(&target_dir, "failed to create target folder")?;
     }
 
-    let with_build_info = options
-        .build_info
-        .unwrap_or_else(|| Config::current().fetch_with_build_info());
-    let temp_dir = tempdir_in(parent).context("temporary unpack location")?;
+    // the toolchain is unpacked next to the target and only moved into place
+    // once complete, so an interrupted fetch never leaves a broken toolchain.
+    let dir_name = target_dir
+        .file_name()
+        .ok_or_else(|| anyhow!("invalid target directory"))?
+        .to_string_lossy();
+    let partial_prefix = format!(".{}.partial-", dir_name);
+    if options.target_path.is_none() {
+        // with the lock held these can only be left over from killed fetches.
+        remove_partial_dirs(parent, &partial_prefix);
+    }
+    let temp_dir = tempfile::Builder::new()
+        .prefix(&partial_prefix)
+        .tempdir_in(parent)
+        .context("temporary unpack location")?;
 
-    unpack_archive(&archive_buffer, temp_dir.path(), 1).with_context(|| {
+    unpack_archive(&archive_buffer, temp_dir.path(), 1, options.output).with_context(|| {
         format!(
             "unpacking of downloaded tarball {} to '{}' failed",
             &url,
@@ -474,25 +578,139 @@ pub fn fetch(
     })?;
 
     // if we want to retain build infos or the installation has no build infos, then move
-    // the folder into the permanent location
-    if with_build_info || !installation_has_build_info(temp_dir.path()) {
-        let temp_dir = temp_dir.into_path();
-        fs::rename(&temp_dir, &target_dir).map_err(|err| {
-            fs::remove_dir_all(&temp_dir).ok();
-            err
-        })
-
-    // otherwise move the contents of the `install` folder over.
+    // the folder into the permanent location, otherwise move the `install` folder over.
+    let staged_dir = if with_build_info || !installation_has_build_info(temp_dir.path()) {
+        temp_dir.path().to_path_buf()
     } else {
-        fs::rename(temp_dir.path().join("install"), &target_dir)
+        temp_dir.path().join("install")
+    };
+
+    // remember the flavor of managed toolchains so that it can be reported.
+    if options.target_path.is_none() {
+        let flavor_path = staged_dir.join(TOOLCHAIN_FLAVOR_FILENAME);
+        fs::write(&flavor_path, fetched_flavor.as_str())
+            .path_context(&flavor_path, "unable to record toolchain build flavor")?;
     }
-    .path_context(&target_dir, "unable to persist download")?;
+
+    replace_dir(&staged_dir, &target_dir)
+        .path_context(&target_dir, "unable to persist download")?;
 
     echo!(if options.output, "{} {}", style("Downloaded").green(), version);
 
+    if let Err(err) = register_toolchain_in_registry(&version) {
+        warn!(
+            "unable to register {} in the Windows registry: {}",
+            version, err
+        );
+    }
+
     Ok(version)
 }
 
+/// Downloads a toolchain archive with a known checksum.
+///
+/// Verified archives are kept in the toolchain archive cache so that
+/// fetching the same toolchain again does not need to download it.
+fn download_toolchain_archive(
+    version: &PythonVersion,
+    url: &str,
+    checksum: &str,
+    output: CommandOutput,
+) -> Result<Vec<u8>, Error> {
+    let cache_path = get_toolchain_archive_cache_dir().join(url.rsplit('/').next().unwrap_or(url));
+    if let Ok(archive_buffer) = fs::read(&cache_path) {
+        if check_checksum(&archive_buffer, checksum, output).is_ok() {
+            echo!(if verbose output, "using cached archive {}", cache_path.display());
+            return Ok(archive_buffer);
+        }
+        fs::remove_file(&cache_path).ok();
+    }
+
+    echo!(if output, "{} {}", style("Downloading").cyan(), version);
+    let archive_buffer = download_url(url, output)?;
+    echo!(if output, "{} {}", style("Checking").cyan(), "checksum");
+    check_checksum(&archive_buffer, checksum, output)
+        .with_context(|| format!("Checksum check of {} failed", &url))?;
+
+    // the cache is an optimization, failing to write it is not an error
+    if let Some(parent) = cache_path.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+    fs::write(&cache_path, &archive_buffer).ok();
+    Ok(archive_buffer)
+}
+
+/// Picks which build of a toolchain is downloaded.
+///
+/// Only full builds come with build info and debug symbols, so if they are
+/// requested together with another flavor this fails rather than silently
+/// falling back.  If the version has no build of the requested flavor the
+/// full build is used.  `find` looks up the download of a flavor.
+fn select_flavor_download<'a, F>(
+    version: &PythonVersion,
+    full_download: (&'a str, Option<&'a str>),
+    flavor: Option<BuildFlavor>,
+    with_build_info: bool,
+    find: F,
+) -> Result<(&'a str, Option<&'a str>, BuildFlavor), Error>
+where
+    F: FnOnce(&PythonVersion, BuildFlavor) -> Option<(&'a str, Option<&'a str>)>,
+{
+    let (url, checksum) = full_download;
+    if with_build_info {
+        if let Some(flavor) = flavor.filter(|x| *x != BuildFlavor::Full) {
+            bail!(
+                "build info and debug symbols are only available in full builds, but \
+                 toolchain.build-flavor is set to '{}'. Set it to 'full' to fetch them.",
+                flavor
+            );
+        }
+        return Ok((url, checksum, BuildFlavor::Full));
+    }
+    match flavor.unwrap_or_default() {
+        BuildFlavor::Full => Ok((url, checksum, BuildFlavor::Full)),
+        flavor => Ok(match find(version, flavor) {
+            Some((url, checksum)) => (url, checksum, flavor),
+            None => (url, checksum, BuildFlavor::Full),
+        }),
+    }
+}
+
+/// Moves a directory into place, replacing an existing one.
+///
+/// The existing directory is only moved aside, so it is restored if the
+/// new one cannot be moved into place.
+fn replace_dir(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+    if !dst.exists() {
+        return fs::rename(src, dst);
+    }
+    let mut old_name = std::ffi::OsString::from(".");
+    old_name.push(dst.file_name().unwrap_or_default());
+    old_name.push(".old");
+    let old = dst.with_file_name(old_name);
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+    fs::rename(dst, &old)?;
+    if let Err(err) = fs::rename(src, dst) {
+        fs::rename(&old, dst).ok();
+        return Err(err);
+    }
+    fs::remove_dir_all(&old).ok();
+    Ok(())
+}
+
+/// Removes the leftovers of interrupted fetches.
+fn remove_partial_dirs(parent: &Path, prefix: &str) {
+    if let Ok(entries) = parent.read_dir() {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(prefix) {
+                fs::remove_dir_all(entry.path()).ok();
+            }
+        }
+    }
+}
+
 fn installation_has_build_info(p: &Path) -> bool {
     let mut has_install = false;
     let mut has_build = false;
@@ -516,6 +734,18 @@ pub fn download_url(url: &str, output: CommandOutput) -> Result<Vec<u8>, Error>
 }
 
 pub fn download_url_ignore_404(url: &str, output: CommandOutput) -> Result<Option<Vec<u8>>, Error> {
+    download_url_with_credentials(url, None, output)
+}
+
+/// Downloads a URL, optionally authenticating with credentials.
+///
+/// With a username the credentials are sent via basic auth, otherwise the
+/// password is sent as bearer token.  Returns `None` on 404.
+pub fn download_url_with_credentials(
+    url: &str,
+    credentials: Option<&(Option<String>, String)>,
+    output: CommandOutput,
+) -> Result<Option<Vec<u8>>, Error> {
     // for now we only allow HTTPS downloads.
     if !url.starts_with("https://") {
         bail!("Refusing insecure download");
@@ -528,6 +758,20 @@ pub fn download_url_ignore_404(url: &str, output: CommandOutput) -> Result<Optio
     handle.progress(true)?;
     handle.follow_location(true)?;
 
+    // curl only sends these to the original host, not after redirects.
+    match credentials {
+        Some((Some(username), password)) => {
+            handle.username(username)?;
+            handle.password(password)?;
+        }
+        Some((None, token)) => {
+            let mut headers = curl::easy::List::new();
+            headers.append(&format!("Authorization: Bearer {}", token))?;
+            handle.http_headers(headers)?;
+        }
+        None => {}
+    }
+
     // we only do https requests here, so we always set an https proxy
     if let Some(proxy) = config.https_proxy_url() {
         handle.proxy(&proxy)?;
@@ -622,3 +866,92 @@ fn validate_shared_libraries(py: &Path) -> Result<(), Error> {
         Visit https://rye-up.com/guide/faq/#missing-shared-libraries-on-linux for next steps."
     );
 }
+
+#[test]
+fn test_select_flavor_download() {
+    let version: PythonVersion = "cpython-x86_64-linux@3.12.3".parse().unwrap();
+    let old_version: PythonVersion = "cpython-x86_64-linux@3.12.1".parse().unwrap();
+    let full = ("cpython-3.12.3-pgo+lto-full.tar.zst", Some("full-sha"));
+    // only the newer version has a stripped build
+    let find = |v: &PythonVersion, flavor: BuildFlavor| match (v.patch, flavor) {
+        (3, BuildFlavor::InstallOnly) => Some(("cpython-3.12.3-install_only.tar.gz", None)),
+        (3, BuildFlavor::InstallOnlyStripped) => Some((
+            "cpython-3.12.3-install_only_stripped.tar.gz",
+            Some("stripped-sha"),
+        )),
+        _ => None,
+    };
+    let select = |version, flavor, with_build_info| {
+        select_flavor_download(version, full, flavor, with_build_info, find)
+    };
+
+    assert_eq!(
+        select(&version, Some(BuildFlavor::InstallOnlyStripped), false).unwrap(),
+        (
+            "cpython-3.12.3-install_only_stripped.tar.gz",
+            Some("stripped-sha"),
+            BuildFlavor::InstallOnlyStripped
+        )
+    );
+    assert_eq!(
+        select(&version, Some(BuildFlavor::InstallOnly), false).unwrap(),
+        (
+            "cpython-3.12.3-install_only.tar.gz",
+            None,
+            BuildFlavor::InstallOnly
+        )
+    );
+    assert_eq!(
+        select(&version, Some(BuildFlavor::Full), false).unwrap(),
+        (full.0, full.1, BuildFlavor::Full)
+    );
+    assert_eq!(
+        select(&version, None, false).unwrap(),
+        select(&version, Some(BuildFlavor::default()), false).unwrap()
+    );
+
+    // versions without a build of the flavor fall back to the full build
+    assert_eq!(
+        select(&old_version, Some(BuildFlavor::InstallOnlyStripped), false).unwrap(),
+        (full.0, full.1, BuildFlavor::Full)
+    );
+
+    // build info is only in the full builds
+    assert_eq!(
+        select(&version, None, true).unwrap(),
+        (full.0, full.1, BuildFlavor::Full)
+    );
+    let err = select(&version, Some(BuildFlavor::InstallOnlyStripped), true).unwrap_err();
+    assert!(
+        err.to_string().contains("only available in full builds"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_replace_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("cpython@3.12.2");
+    let staged = dir.path().join(".cpython@3.12.2.partial-1");
+
+    fs::create_dir(&staged).unwrap();
+    fs::write(staged.join("a"), "1").unwrap();
+    replace_dir(&staged, &target).unwrap();
+    assert_eq!(fs::read_to_string(target.join("a")).unwrap(), "1");
+
+    fs::create_dir(&staged).unwrap();
+    fs::write(staged.join("b"), "2").unwrap();
+    replace_dir(&staged, &target).unwrap();
+    assert!(!target.join("a").exists());
+    assert_eq!(fs::read_to_string(target.join("b")).unwrap(), "2");
+
+    // if the new directory cannot be moved into place the old one stays
+    assert!(replace_dir(&staged, &target).is_err());
+    assert_eq!(fs::read_to_string(target.join("b")).unwrap(), "2");
+
+    fs::create_dir(&staged).unwrap();
+    remove_partial_dirs(dir.path(), ".cpython@3.12.2.partial-");
+    assert!(!staged.exists());
+    assert!(target.is_dir());
+}
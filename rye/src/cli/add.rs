@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashSet};
 use std::env;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
@@ -5,6 +6,7 @@ use std::str::FromStr;
 
 use anyhow::{anyhow, bail, Context, Error};
 use clap::{Parser, ValueEnum};
+use console::{style, Term};
 use pep440_rs::{Operator, Version, VersionSpecifier, VersionSpecifiers};
 use pep508_rs::{Requirement, VersionOrUrl};
 use serde::Deserialize;
@@ -13,13 +15,20 @@ use url::Url;
 use crate::bootstrap::ensure_self_venv;
 use crate::config::Config;
 use crate::consts::VENV_BIN;
-use crate::pyproject::{BuildSystem, DependencyKind, ExpandedSources, PyProject};
+use crate::index;
+use crate::pyproject::{
+    normalize_package_name, read_venv_marker, BuildSystem, DependencyKind, ExpandedSources,
+    PyProject, SourceRef,
+};
 use crate::sources::py::PythonVersion;
-use crate::sync::{autosync, sync, SyncOptions};
-use crate::utils::{format_requirement, get_venv_python_bin, set_proxy_variables, CommandOutput};
+use crate::sync::{autosync, sync, sync_dry_run, SyncMode, SyncOptions};
+use crate::utils::{
+    format_requirement, get_venv_python_bin, set_proxy_variables, tui_theme, CommandOutput,
+};
 use crate::uv::UvBuilder;
 
 const PACKAGE_FINDER_SCRIPT: &str = r#"
+import os
 import sys
 import json
 from unearth.finder import PackageFinder
@@ -28,8 +37,8 @@ from packaging.version import Version
 
 py_ver = sys.argv[1]
 package = sys.argv[2]
-sources = json.loads(sys.argv[3])
-pre = len(sys.argv) > 4 and sys.argv[4] == "--pre"
+sources = json.loads(os.environ["__RYE_SOURCES"])
+pre = len(sys.argv) > 3 and sys.argv[3] == "--pre"
 
 finder = PackageFinder(
     index_urls=[x[0] for x in sources["index_urls"]],
@@ -45,6 +54,116 @@ if not pre:
 print(json.dumps([x.as_json() for x in choices]))
 "#;
 
+/// The number of versions offered by `rye add --interactive`.
+const INTERACTIVE_VERSIONS: usize = 15;
+
+const IMPORT_SCAN_SCRIPT: &str = r#"
+import os
+import ast
+import sys
+import json
+
+root = sys.argv[1]
+found = {}
+local = set()
+
+for dirpath, dirnames, filenames in os.walk(root):
+    dirnames[:] = sorted(
+        x for x in dirnames
+        if not x.startswith(".")
+        and x not in ("__pycache__", "node_modules")
+        and not os.path.isfile(os.path.join(dirpath, x, "pyvenv.cfg"))
+    )
+    local.update(dirnames)
+    for filename in sorted(filenames):
+        if not filename.endswith(".py"):
+            continue
+        local.add(filename[:-3])
+        path = os.path.join(dirpath, filename)
+        try:
+            with open(path, "rb") as f:
+                tree = ast.parse(f.read(), path)
+        except (SyntaxError, ValueError, OSError):
+            continue
+        for node in ast.walk(tree):
+            if isinstance(node, ast.Import):
+                names = [x.name for x in node.names]
+            elif isinstance(node, ast.ImportFrom) and not node.level and node.module:
+                names = [node.module]
+            else:
+                continue
+            for name in names:
+                location = "%s:%d" % (os.path.relpath(path, root), node.lineno)
+                found.setdefault(name.split(".")[0], location)
+
+ignored = local | set(sys.stdlib_module_names) | {"__future__"}
+print(json.dumps([[k, v] for k, v in sorted(found.items()) if k not in ignored]))
+"#;
+
+/// Import names of common packages that differ from their distribution name.
+///
+/// Any other import is assumed to be provided by the distribution of the same
+/// name (with underscores replaced by dashes).
+const IMPORT_NAME_MAPPING: &[(&str, &str)] = &[
+    ("Bio", "biopython"),
+    ("Crypto", "pycryptodome"),
+    ("MySQLdb", "mysqlclient"),
+    ("OpenGL", "PyOpenGL"),
+    ("OpenSSL", "pyOpenSSL"),
+    ("PIL", "pillow"),
+    ("Xlib", "python-xlib"),
+    ("attr", "attrs"),
+    ("bs4", "beautifulsoup4"),
+    ("cv2", "opencv-python"),
+    ("dateutil", "python-dateutil"),
+    ("discord", "discord.py"),
+    ("dns", "dnspython"),
+    ("docx", "python-docx"),
+    ("dotenv", "python-dotenv"),
+    ("faiss", "faiss-cpu"),
+    ("fitz", "PyMuPDF"),
+    ("gi", "PyGObject"),
+    ("git", "GitPython"),
+    ("github", "PyGithub"),
+    ("gitlab", "python-gitlab"),
+    ("jose", "python-jose"),
+    ("jwt", "PyJWT"),
+    ("kafka", "kafka-python"),
+    ("ldap", "python-ldap"),
+    ("magic", "python-magic"),
+    ("memcache", "python-memcached"),
+    ("mpl_toolkits", "matplotlib"),
+    ("multipart", "python-multipart"),
+    ("nacl", "PyNaCl"),
+    ("pkg_resources", "setuptools"),
+    ("pptx", "python-pptx"),
+    ("pythoncom", "pywin32"),
+    ("ruamel", "ruamel.yaml"),
+    ("serial", "pyserial"),
+    ("skimage", "scikit-image"),
+    ("sklearn", "scikit-learn"),
+    ("slugify", "python-slugify"),
+    ("socketio", "python-socketio"),
+    ("telegram", "python-telegram-bot"),
+    ("umap", "umap-learn"),
+    ("usb", "pyusb"),
+    ("websocket", "websocket-client"),
+    ("win32api", "pywin32"),
+    ("win32con", "pywin32"),
+    ("wx", "wxPython"),
+    ("yaml", "PyYAML"),
+    ("zmq", "pyzmq"),
+];
+
+/// A release on the package index as offered by `rye add --interactive`.
+#[derive(Debug, Default)]
+struct IndexVersion {
+    upload_time: Option<String>,
+    yanked: bool,
+    requires_python: Option<String>,
+    metadata_url: Option<Url>,
+}
+
 #[derive(Deserialize, Debug)]
 struct Match {
     name: String,
@@ -98,18 +217,68 @@ enum Pin {
     TildeEqual,
     #[value(alias = ">=", alias = "ge", alias = "gte")]
     GreaterThanEqual,
+    #[value(alias = "none", alias = "*")]
+    Unbounded,
+}
+
+impl Pin {
+    /// Returns the operator to pin with, `None` if no bound is added.
+    fn operator(self) -> Option<Operator> {
+        match self {
+            Pin::Equal => Some(Operator::Equal),
+            Pin::TildeEqual => Some(Operator::TildeEqual),
+            Pin::GreaterThanEqual => Some(Operator::GreaterThanEqual),
+            Pin::Unbounded => None,
+        }
+    }
 }
 
-impl From<Pin> for Operator {
-    fn from(value: Pin) -> Self {
+impl From<Option<Operator>> for Pin {
+    fn from(value: Option<Operator>) -> Self {
         match value {
-            Pin::Equal => Operator::Equal,
-            Pin::TildeEqual => Operator::TildeEqual,
-            Pin::GreaterThanEqual => Operator::GreaterThanEqual,
+            Some(Operator::Equal) => Pin::Equal,
+            Some(Operator::TildeEqual) => Pin::TildeEqual,
+            None => Pin::Unbounded,
+            Some(_) => Pin::GreaterThanEqual,
         }
     }
 }
 
+/// Picks the pin for new dependencies.
+///
+/// `--pin` wins over `tool.rye.dependency-operator` of the project, which wins
+/// over `default.dependency-operator` of the config.
+fn resolve_default_pin(
+    pin: Option<Pin>,
+    project_operator: Option<&str>,
+    config_operator: Option<Operator>,
+) -> Result<Pin, Error> {
+    match (pin, project_operator) {
+        (Some(pin), _) => Ok(pin),
+        (None, Some(value)) => Pin::from_str(value, false).map_err(|_| {
+            anyhow!(
+                "invalid tool.rye.dependency-operator '{}' (expected '>=', '~=', '==' or 'unbounded')",
+                value
+            )
+        }),
+        (None, None) => Ok(Pin::from(config_operator)),
+    }
+}
+
+/// Creates the version specifier that pins a dependency to a version.
+///
+/// Local versions cannot be used with anything but `==` and `~=` needs at
+/// least two release segments, so those fall back to `==` and `>=`.
+fn pin_specifier(operator: &Operator, version: &Version) -> Result<VersionSpecifier, Error> {
+    let operator = match *operator {
+        _ if version.is_local() => Operator::Equal,
+        Operator::TildeEqual if version.release.len() < 2 => Operator::GreaterThanEqual,
+        ref other => other.clone(),
+    };
+    VersionSpecifier::new(operator, version.clone(), false)
+        .map_err(|msg| anyhow!("invalid version specifier: {}", msg))
+}
+
 impl ReqExtras {
     /// Return true if any path, url, features or similar are set
     /// (anything specific for 1 requirement).
@@ -152,11 +321,23 @@ impl ReqExtras {
                 )),
             };
         } else if let Some(ref path) = self.path {
+            let pyproject = PyProject::discover()?;
+
+            // Members of the workspace are always installed (as editable), so they
+            // are referenced by name only.
+            if let Some(workspace) = pyproject.workspace().filter(|_| !self.absolute) {
+                let abs_path = env::current_dir()?.join(path);
+                if workspace.is_member(&abs_path.canonicalize().unwrap_or(abs_path))
+                    && req.version_or_url.is_none()
+                {
+                    return self.apply_features(req);
+                }
+            }
+
             // For hatchling build backend, it use {root:uri} for file relative path,
             // but this not supported by pip-tools,
             // and use ${PROJECT_ROOT} will cause error in hatchling, so force absolute path.
-            let is_hatchling =
-                PyProject::discover()?.build_backend() == Some(BuildSystem::Hatchling);
+            let is_hatchling = pyproject.build_backend() == Some(BuildSystem::Hatchling);
             let file_url = if self.absolute || is_hatchling {
                 Url::from_file_path(env::current_dir()?.join(path))
                     .map_err(|_| anyhow!("unable to interpret '{}' as path", path.display()))?
@@ -178,6 +359,10 @@ impl ReqExtras {
                 None => Some(pep508_rs::VersionOrUrl::Url(file_url)),
             };
         }
+        self.apply_features(req)
+    }
+
+    fn apply_features(&self, req: &mut Requirement) -> Result<(), Error> {
         for feature in self.features.iter().flat_map(|x| x.split(',')) {
             let feature = feature.trim();
             let extras = req.extras.get_or_insert_with(Vec::new);
@@ -193,10 +378,16 @@ impl ReqExtras {
 #[derive(Parser, Debug)]
 pub struct Args {
     /// The package to add as PEP 508 requirement string. e.g. 'flask==2.2.3'
-    #[arg(required = true)]
+    #[arg(required_unless_present = "scan")]
     requirements: Vec<String>,
+    /// Scan the sources for imports of packages that are not declared and offer to add them.
+    #[arg(long, conflicts_with_all = ["requirements", "excluded", "index", "interactive"])]
+    scan: bool,
     #[command(flatten)]
     req_extras: ReqExtras,
+    /// Install the local path dependency as editable.
+    #[arg(short, long, conflicts_with = "git", conflicts_with = "url")]
+    editable: bool,
     /// Add this as dev dependency.
     #[arg(long)]
     dev: bool,
@@ -206,18 +397,42 @@ pub struct Args {
     /// Add this to an optional dependency group.
     #[arg(long, conflicts_with = "dev", conflicts_with = "excluded")]
     optional: Option<String>,
+    /// Install the package from this source (for instance `pytorch-cu121`).
+    ///
+    /// Well-known sources are added to the project if it does not declare them.
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with = "git",
+        conflicts_with = "url",
+        conflicts_with = "path"
+    )]
+    index: Option<String>,
     /// Include pre-releases when finding a package version.
     #[arg(long)]
     pre: bool,
     /// Overrides the pin operator
-    #[arg(long)]
+    #[arg(long, visible_alias = "bounds")]
     pin: Option<Pin>,
+    /// Pick the version, the pin operator and the extras from the package index.
+    #[arg(
+        short,
+        long,
+        conflicts_with = "git",
+        conflicts_with = "url",
+        conflicts_with = "path",
+        conflicts_with = "excluded"
+    )]
+    interactive: bool,
     /// Runs `sync` even if auto-sync is disabled.
     #[arg(long)]
     sync: bool,
     /// Does not run `sync` even if auto-sync is enabled.
     #[arg(long, conflicts_with = "sync")]
     no_sync: bool,
+    /// Print the resolved requirements and the changes a sync would make without applying them.
+    #[arg(long, conflicts_with = "sync")]
+    dry_run: bool,
     /// Enables verbose diagnostics.
     #[arg(short, long)]
     verbose: bool,
@@ -226,7 +441,7 @@ pub struct Args {
     quiet: bool,
 }
 
-pub fn execute(cmd: Args) -> Result<(), Error> {
+pub fn execute(mut cmd: Args) -> Result<(), Error> {
     let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
     let self_venv = ensure_self_venv(output).context("error bootstrapping venv")?;
     let python_path = self_venv.join(VENV_BIN).join("python");
@@ -243,15 +458,58 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
     } else {
         DependencyKind::Normal
     };
-    let default_operator = match cmd.pin {
-        Some(pin) => Operator::from(pin),
-        None => Config::current().default_dependency_operator(),
-    };
+    let default_pin = resolve_default_pin(
+        cmd.pin,
+        pyproject_toml.dependency_operator(),
+        cfg.default_dependency_operator(),
+    )?;
+    let default_operator = default_pin.operator();
+
+    if cmd.scan {
+        if cmd.req_extras.has_specifiers() {
+            bail!("path/url/git/features is not compatible with --scan");
+        }
+        if !cmd.dry_run && !Term::stderr().is_term() {
+            bail!("--scan requires an interactive terminal (or --dry-run)");
+        }
+        cmd.requirements = scan_for_undeclared_imports(&pyproject_toml, &python_path, cmd.dry_run)?;
+        if cmd.requirements.is_empty() {
+            echo!(if output, "No undeclared imports found");
+            return Ok(());
+        }
+    }
+
+    // a local project can also be added by passing its path rather than a name.
+    if cmd.req_extras.path.is_none() && cmd.requirements.len() == 1 {
+        if let Some(name) = get_local_project_name(&cmd.requirements[0])? {
+            cmd.req_extras.path = Some(PathBuf::from(&cmd.requirements[0]));
+            cmd.requirements[0] = name;
+        }
+    }
+    if cmd.editable && cmd.req_extras.path.is_none() {
+        bail!("--editable can only be used with local path dependencies");
+    }
 
     if cmd.req_extras.has_specifiers() && cmd.requirements.len() != 1 {
         bail!("path/url/git/features is not compatible with passing multiple requirements: expected one requirement.")
     }
 
+    // the source has to be known before the requirements are resolved.
+    let mut added_source = None;
+    if let Some(ref index) = cmd.index {
+        if !pyproject_toml.sources()?.iter().any(|x| x.name == *index) {
+            let source = SourceRef::well_known(index).ok_or_else(|| {
+                anyhow!(
+                    "unknown index '{}'. Declare it in tool.rye.sources or use one of: {}",
+                    index,
+                    SourceRef::well_known_names().collect::<Vec<_>>().join(", ")
+                )
+            })?;
+            pyproject_toml.add_source(&source)?;
+            added_source = Some(source);
+        }
+    }
+
     let mut requirements = Vec::new();
     for str_requirement in &cmd.requirements {
         let mut requirement = Requirement::from_str(str_requirement)?;
@@ -259,17 +517,52 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         requirements.push(requirement);
     }
 
-    if !cmd.excluded {
-        if cfg.use_uv() {
-            sync(SyncOptions::python_only().pyproject(None))
-                .context("failed to sync ahead of add")?;
+    // requirements picked without a bound interactively are still resolved
+    // for their name, the version is dropped again afterwards.
+    let mut unbounded = vec![false; requirements.len()];
+    if cmd.interactive {
+        if !Term::stderr().is_term() {
+            bail!("--interactive requires an interactive terminal");
+        }
+        for (requirement, unbounded) in requirements.iter_mut().zip(unbounded.iter_mut()) {
+            if requirement.version_or_url.is_none() {
+                *unbounded = select_interactively(
+                    &pyproject_toml,
+                    &py_ver,
+                    requirement,
+                    cmd.pre,
+                    cmd.pin,
+                    default_pin,
+                )? == Pin::Unbounded;
+            }
+        }
+    }
+
+    // workspace members are referenced by name only and must not be resolved
+    // against the package index.
+    let is_workspace_member =
+        cmd.req_extras.path.is_some() && requirements.iter().all(|x| x.version_or_url.is_none());
+
+    // a dry run must not create the virtualenv, so resolving with uv is only
+    // possible if there is already a compatible one.
+    let use_uv = cfg.use_uv()
+        && (!cmd.dry_run
+            || read_venv_marker(&pyproject_toml.venv_path())
+                .map_or(false, |x| x.is_compatible(&py_ver)));
+
+    if !cmd.excluded && !is_workspace_member {
+        if use_uv {
+            if !cmd.dry_run {
+                sync(SyncOptions::python_only().pyproject(None))
+                    .context("failed to sync ahead of add")?;
+            }
             resolve_requirements_with_uv(
                 &pyproject_toml,
                 &py_ver,
                 &mut requirements,
                 cmd.pre,
                 output,
-                &default_operator,
+                default_operator.as_ref(),
             )?;
         } else {
             for requirement in &mut requirements {
@@ -280,19 +573,52 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
                     requirement,
                     cmd.pre,
                     output,
-                    &default_operator,
+                    default_operator.as_ref(),
                 )?;
             }
         }
     }
+    for (requirement, unbounded) in requirements.iter_mut().zip(unbounded) {
+        if unbounded {
+            requirement.version_or_url = None;
+        }
+    }
 
     for requirement in &requirements {
         pyproject_toml.add_dependency(requirement, &dep_kind)?;
+        if cmd.editable && requirement.version_or_url.is_some() {
+            pyproject_toml.set_editable_dependency(&requirement.name, true);
+        }
+    }
+
+    if cmd.dry_run {
+        if let Some(ref source) = added_source {
+            echo!("Would add source {} ({})", source.name, source.url);
+        }
+        for ref requirement in requirements {
+            echo!(
+                "Would add {} as {} dependency",
+                format_requirement(requirement),
+                &dep_kind
+            );
+        }
+        return sync_dry_run(
+            &pyproject_toml,
+            SyncOptions {
+                output,
+                dev: true,
+                mode: SyncMode::Regular,
+                ..SyncOptions::default()
+            },
+        );
     }
 
     pyproject_toml.save()?;
 
     if output != CommandOutput::Quiet {
+        if let Some(ref source) = added_source {
+            echo!("Added source {} ({})", source.name, source.url);
+        }
         for ref requirement in requirements {
             echo!(
                 "Added {} as {} dependency",
@@ -309,6 +635,96 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
     Ok(())
 }
 
+/// Finds the imports of third-party packages which are not declared as
+/// dependencies and asks which of them to add.
+///
+/// The `src` folder is scanned if the project has one, otherwise the entire
+/// project.  Imports of the standard library and of modules of the project
+/// itself are ignored.  Returns the names of the distributions to add.
+fn scan_for_undeclared_imports(
+    pyproject_toml: &PyProject,
+    python_path: &Path,
+    accept_all: bool,
+) -> Result<Vec<String>, Error> {
+    let root = pyproject_toml.root_path();
+    let src = root.join("src");
+    let scan_root = if src.is_dir() { src.as_path() } else { &*root };
+
+    let scan = Command::new(python_path)
+        .arg("-c")
+        .arg(IMPORT_SCAN_SCRIPT)
+        .arg(scan_root)
+        .stdout(Stdio::piped())
+        .output()?;
+    if !scan.status.success() {
+        bail!(
+            "failed to scan imports\n{}",
+            String::from_utf8_lossy(&scan.stderr)
+        );
+    }
+    let imports: Vec<(String, String)> = serde_json::from_slice(&scan.stdout)?;
+
+    let mut declared = pyproject_toml
+        .extras()
+        .into_iter()
+        .map(|x| DependencyKind::Optional(x.into()))
+        .chain([DependencyKind::Normal, DependencyKind::Dev])
+        .flat_map(|kind| pyproject_toml.iter_dependencies(kind).collect::<Vec<_>>())
+        .filter_map(|dep| dep.expand(|name| env::var(name).ok()).ok())
+        .map(|req| normalize_package_name(&req.name))
+        .collect::<HashSet<_>>();
+    if let Some(workspace) = pyproject_toml.workspace() {
+        for project in workspace.iter_projects() {
+            declared.insert(project?.normalized_name()?);
+        }
+    } else if let Some(name) = pyproject_toml.name() {
+        declared.insert(normalize_package_name(name));
+    }
+
+    let mut rv = Vec::new();
+    for (module, location) in imports {
+        let name = match IMPORT_NAME_MAPPING.iter().find(|x| x.0 == module) {
+            Some((_, name)) => name.to_string(),
+            None => module.replace('_', "-"),
+        };
+        if !declared.insert(normalize_package_name(&name)) {
+            continue;
+        }
+        let prompt = if name == module {
+            format!("Add {} (imported in {})?", name, location)
+        } else {
+            format!("Add {} (imported as {} in {})?", name, module, location)
+        };
+        if accept_all
+            || dialoguer::Confirm::with_theme(tui_theme())
+                .with_prompt(prompt)
+                .default(true)
+                .interact()?
+        {
+            rv.push(name);
+        }
+    }
+    Ok(rv)
+}
+
+/// If the given requirement string points to a local project, returns its name.
+fn get_local_project_name(requirement: &str) -> Result<Option<String>, Error> {
+    let path = Path::new(requirement);
+    let looks_like_path = requirement.starts_with('.')
+        || path.is_absolute()
+        || requirement.contains('/')
+        || requirement.contains(std::path::MAIN_SEPARATOR);
+    let toml_path = path.join("pyproject.toml");
+    if !looks_like_path || !toml_path.is_file() {
+        return Ok(None);
+    }
+    let project = PyProject::load(&toml_path)?;
+    match project.name() {
+        Some(name) => Ok(Some(name.to_string())),
+        None => bail!("project at '{}' has no name", path.display()),
+    }
+}
+
 fn resolve_requirements_with_unearth(
     pyproject_toml: &PyProject,
     python_path: &PathBuf,
@@ -316,7 +732,7 @@ fn resolve_requirements_with_unearth(
     requirement: &mut Requirement,
     pre: bool,
     output: CommandOutput,
-    default_operator: &Operator,
+    default_operator: Option<&Operator>,
 ) -> Result<(), Error> {
     let matches = find_best_matches_with_unearth(
         pyproject_toml,
@@ -378,33 +794,184 @@ fn resolve_requirements_with_unearth(
         }
     }
     let m = matches.into_iter().next().unwrap();
-    if m.version.is_some() && requirement.version_or_url.is_none() {
+    if let (Some(default_operator), Some(_), None) =
+        (default_operator, &m.version, &requirement.version_or_url)
+    {
         let version = Version::from_str(m.version.as_ref().unwrap())
             .map_err(|msg| anyhow!("invalid version: {}", msg))?;
         requirement.version_or_url = Some(VersionOrUrl::VersionSpecifier(
-            VersionSpecifiers::from_iter(Some(
-                VersionSpecifier::new(
-                    // local versions or versions with only one component cannot
-                    // use ~= but need to use ==.
-                    match *default_operator {
-                        _ if version.is_local() => Operator::Equal,
-                        Operator::TildeEqual if version.release.len() < 2 => {
-                            Operator::GreaterThanEqual
-                        }
-                        ref other => other.clone(),
-                    },
-                    Version::from_str(m.version.as_ref().unwrap())
-                        .map_err(|msg| anyhow!("invalid version: {}", msg))?,
-                    false,
-                )
-                .map_err(|msg| anyhow!("invalid version specifier: {}", msg))?,
-            )),
+            VersionSpecifiers::from_iter(Some(pin_specifier(default_operator, &version)?)),
         ));
     }
     requirement.name = m.name;
     Ok(())
 }
 
+/// Lets the user pick version, pin operator and extras of a requirement.
+fn select_interactively(
+    pyproject: &PyProject,
+    py_ver: &PythonVersion,
+    requirement: &mut Requirement,
+    pre: bool,
+    pin: Option<Pin>,
+    default_pin: Pin,
+) -> Result<Pin, Error> {
+    let sources = ExpandedSources::from_sources(&pyproject.sources()?)?;
+    let mut versions = find_index_versions(&sources, &requirement.name)?
+        .into_iter()
+        .filter(|(version, _)| pre || !version.any_prerelease())
+        .collect::<Vec<_>>();
+    if versions.is_empty() {
+        bail!("did not find package '{}'", requirement.name);
+    }
+    versions.sort_by(|a, b| b.0.cmp(&a.0));
+    versions.truncate(INTERACTIVE_VERSIONS);
+
+    let python_version = Version::from(py_ver.clone());
+    let is_compatible = |info: &IndexVersion| {
+        info.requires_python
+            .as_ref()
+            .and_then(|x| VersionSpecifiers::from_str(x).ok())
+            .map_or(true, |x| x.contains(&python_version))
+    };
+    let labels = versions
+        .iter()
+        .map(|(version, info)| {
+            let mut label = format!(
+                "{:<16} {}",
+                version.to_string(),
+                info.upload_time
+                    .as_deref()
+                    .map_or("", |x| &x[..x.len().min(10)])
+            );
+            if info.yanked {
+                label.push_str(&format!(" {}", style("(yanked)").red()));
+            }
+            if !is_compatible(info) {
+                label.push_str(&format!(
+                    " {}",
+                    style(format!(
+                        "(requires Python {})",
+                        info.requires_python.as_deref().unwrap_or_default()
+                    ))
+                    .yellow()
+                ));
+            }
+            label
+        })
+        .collect::<Vec<_>>();
+    let default = versions
+        .iter()
+        .position(|(_, info)| !info.yanked && is_compatible(info))
+        .unwrap_or(0);
+    let idx = dialoguer::Select::with_theme(tui_theme())
+        .with_prompt(format!("Version of {}", requirement.name))
+        .items(&labels)
+        .default(default)
+        .interact()?;
+    let (version, info) = &versions[idx];
+
+    let pin = match pin {
+        Some(pin) => pin,
+        None => {
+            let pins = [
+                Pin::GreaterThanEqual,
+                Pin::TildeEqual,
+                Pin::Equal,
+                Pin::Unbounded,
+            ];
+            let idx = dialoguer::Select::with_theme(tui_theme())
+                .with_prompt("Pin operator")
+                .items(&[
+                    format!(">={} (at least this version)", version),
+                    format!("~={} (compatible release)", version),
+                    format!("=={} (exactly this version)", version),
+                    "no version bound".to_string(),
+                ])
+                .default(pins.iter().position(|x| *x == default_pin).unwrap_or(0))
+                .interact()?;
+            pins[idx]
+        }
+    };
+    if let Some(operator) = pin.operator() {
+        requirement.version_or_url = Some(VersionOrUrl::VersionSpecifier(
+            VersionSpecifiers::from_iter(Some(pin_specifier(&operator, version)?)),
+        ));
+    }
+
+    // extras are only known if the index serves the metadata of the wheels.
+    let extras = match info.metadata_url {
+        Some(ref url) => index::fetch_extras(url, &sources).unwrap_or_default(),
+        None => Vec::new(),
+    };
+    if !extras.is_empty() {
+        let selected = requirement.extras.clone().unwrap_or_default();
+        let defaults = extras
+            .iter()
+            .map(|x| selected.contains(x))
+            .collect::<Vec<_>>();
+        let picked = dialoguer::MultiSelect::with_theme(tui_theme())
+            .with_prompt("Extras (space to toggle)")
+            .items(&extras)
+            .defaults(&defaults)
+            .interact()?;
+        let picked = picked
+            .into_iter()
+            .map(|idx| extras[idx].clone())
+            .collect::<Vec<_>>();
+        requirement.extras = if picked.is_empty() {
+            None
+        } else {
+            Some(picked)
+        };
+    }
+
+    Ok(pin)
+}
+
+/// Collects the releases of a package from the indexes of the sources.
+///
+/// A release counts as yanked if all of its files are.  Responses are cached
+/// in the index cache and revalidated on later queries.
+fn find_index_versions(
+    sources: &ExpandedSources,
+    name: &str,
+) -> Result<Vec<(Version, IndexVersion)>, Error> {
+    let name = normalize_package_name(name);
+    let mut versions = BTreeMap::<Version, IndexVersion>::new();
+    for index in index::index_urls(sources) {
+        let (url, page) = match index::fetch_project_page(&[index], sources, &name)? {
+            Some(rv) => rv,
+            None => continue,
+        };
+        for file in page.files {
+            let version = match file.version() {
+                Some(version) => version,
+                None => continue,
+            };
+            let info = versions.entry(version).or_insert_with(|| IndexVersion {
+                yanked: true,
+                ..Default::default()
+            });
+            if let Some(upload_time) = file.upload_time.as_ref() {
+                if info.upload_time.as_ref().map_or(true, |x| upload_time < x) {
+                    info.upload_time = Some(upload_time.clone());
+                }
+            }
+            if file.yanked().is_none() {
+                info.yanked = false;
+            }
+            if info.requires_python.is_none() {
+                info.requires_python = file.requires_python.clone();
+            }
+            if info.metadata_url.is_none() {
+                info.metadata_url = file.metadata_url(&url);
+            }
+        }
+    }
+    Ok(versions.into_iter().collect())
+}
+
 fn find_best_matches_with_unearth(
     pyproject: &PyProject,
     python_path: &PathBuf,
@@ -423,7 +990,7 @@ fn find_best_matches_with_unearth(
             None => "".into(),
         })
         .arg(&format_requirement(requirement).to_string())
-        .arg(serde_json::to_string(&sources)?);
+        .env("__RYE_SOURCES", serde_json::to_string(&sources)?);
     if pre {
         unearth.arg("--pre");
     }
@@ -447,7 +1014,7 @@ fn resolve_requirements_with_uv(
     requirements: &mut [Requirement],
     pre: bool,
     output: CommandOutput,
-    default_operator: &Operator,
+    default_operator: Option<&Operator>,
 ) -> Result<(), Error> {
     let venv_path = pyproject_toml.venv_path();
     let py_bin = get_venv_python_bin(&venv_path);
@@ -459,8 +1026,12 @@ fn resolve_requirements_with_uv(
         .ensure_exists()?
         .venv(&venv_path, &py_bin, py_ver, None)?;
 
+    let exclude_newer = match pyproject_toml.exclude_newer()? {
+        Some(exclude_newer) => Some(exclude_newer),
+        None => env::var("__RYE_UV_EXCLUDE_NEWER").ok(),
+    };
     for req in requirements {
-        let mut new_req = uv.resolve(py_ver, req, pre, env::var("__RYE_UV_EXCLUDE_NEWER").ok())?;
+        let mut new_req = uv.resolve(py_ver, req, pre, exclude_newer.clone())?;
 
         // if a version or URL is already provided we just use the normalized package name but
         // retain all old information.
@@ -469,22 +1040,20 @@ fn resolve_requirements_with_uv(
             continue;
         }
 
+        let default_operator = match default_operator {
+            Some(default_operator) => default_operator,
+            None => {
+                // unbounded dependencies only keep the name.
+                req.name = new_req.name;
+                continue;
+            }
+        };
         if let Some(ref mut version_or_url) = new_req.version_or_url {
             if let VersionOrUrl::VersionSpecifier(ref mut specs) = version_or_url {
                 *version_or_url = VersionOrUrl::VersionSpecifier(VersionSpecifiers::from_iter({
                     let mut new_specs = Vec::new();
                     for spec in specs.iter() {
-                        let op = match *default_operator {
-                            _ if spec.version().is_local() => Operator::Equal,
-                            Operator::TildeEqual if spec.version().release.len() < 2 => {
-                                Operator::GreaterThanEqual
-                            }
-                            ref other => other.clone(),
-                        };
-                        new_specs.push(
-                            VersionSpecifier::new(op, spec.version().clone(), false)
-                                .map_err(|msg| anyhow!("invalid version specifier: {}", msg))?,
-                        );
+                        new_specs.push(pin_specifier(default_operator, spec.version())?);
                     }
                     new_specs
                 }));
@@ -498,3 +1067,70 @@ fn resolve_requirements_with_uv(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(operator: Operator, version: &str) -> String {
+        pin_specifier(&operator, &version.parse().unwrap())
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_pin_operators() {
+        for (value, pin, operator) in [
+            ("==", Pin::Equal, Some(Operator::Equal)),
+            ("~=", Pin::TildeEqual, Some(Operator::TildeEqual)),
+            (
+                ">=",
+                Pin::GreaterThanEqual,
+                Some(Operator::GreaterThanEqual),
+            ),
+            ("unbounded", Pin::Unbounded, None),
+            ("*", Pin::Unbounded, None),
+        ] {
+            assert_eq!(Pin::from_str(value, false).unwrap(), pin);
+            assert_eq!(pin.operator(), operator);
+            assert_eq!(Pin::from(operator), pin);
+        }
+        assert_eq!(Pin::from(Some(Operator::LessThan)), Pin::GreaterThanEqual);
+        assert!(Pin::from_str("<", false).is_err());
+    }
+
+    #[test]
+    fn test_resolve_default_pin() {
+        // --pin wins over the project and the config
+        assert_eq!(
+            resolve_default_pin(Some(Pin::Equal), Some("~="), None).unwrap(),
+            Pin::Equal
+        );
+        // the project wins over the config
+        assert_eq!(
+            resolve_default_pin(None, Some("unbounded"), Some(Operator::Equal)).unwrap(),
+            Pin::Unbounded
+        );
+        assert_eq!(
+            resolve_default_pin(None, None, Some(Operator::TildeEqual)).unwrap(),
+            Pin::TildeEqual
+        );
+        assert_eq!(
+            resolve_default_pin(None, None, None).unwrap(),
+            Pin::Unbounded
+        );
+        assert!(resolve_default_pin(None, Some("<"), None).is_err());
+    }
+
+    #[test]
+    fn test_pin_specifier() {
+        assert_eq!(spec(Operator::GreaterThanEqual, "1.2.3"), ">=1.2.3");
+        assert_eq!(spec(Operator::TildeEqual, "1.2.3"), "~=1.2.3");
+        assert_eq!(spec(Operator::Equal, "1.2.3"), "==1.2.3");
+        // ~= needs at least two release segments
+        assert_eq!(spec(Operator::TildeEqual, "2"), ">=2");
+        // local versions can only be pinned exactly
+        assert_eq!(spec(Operator::GreaterThanEqual, "2.1.0+cpu"), "==2.1.0+cpu");
+        assert_eq!(spec(Operator::TildeEqual, "2.1.0+cpu"), "==2.1.0+cpu");
+    }
+}
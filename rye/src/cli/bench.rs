@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::env::consts::EXE_EXTENSION;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use console::style;
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::consts::VENV_BIN;
+use crate::pyproject::{locate_projects, PyProject};
+use crate::sync::autosync;
+use crate::utils::{CommandOutput, IoPathContext, QuietExit};
+
+/// Run the benchmarks of the project.
+///
+/// This runs pytest-benchmark unless a different runner is configured in
+/// `tool.rye.bench.runner`.  Results are stored in `.rye/benchmarks`.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Perform the operation on all packages
+    #[arg(short, long)]
+    all: bool,
+    /// Perform the operation on a specific package
+    #[arg(short, long)]
+    package: Vec<String>,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Compare against a stored run (`last`, a run id or a git ref)
+    #[arg(long, value_name = "RUN")]
+    compare: Option<String>,
+    /// Fail if a benchmark got slower by more than this percentage
+    #[arg(long, value_name = "PERCENT", default_value_t = 10.0)]
+    threshold: f64,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Extra arguments to the benchmark runner
+    #[arg(last = true)]
+    extra_args: Vec<OsString>,
+}
+
+/// The parts of a pytest-benchmark JSON file that rye looks at.
+#[derive(Deserialize)]
+struct BenchmarkRun {
+    #[serde(default)]
+    commit_info: Option<CommitInfo>,
+    benchmarks: Vec<Benchmark>,
+}
+
+#[derive(Deserialize)]
+struct CommitInfo {
+    id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Benchmark {
+    fullname: String,
+    stats: BenchmarkStats,
+}
+
+#[derive(Deserialize)]
+struct BenchmarkStats {
+    mean: f64,
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let project = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let venv_bin = project.venv_path().join(VENV_BIN);
+    let projects = locate_projects(project, cmd.all, &cmd.package[..])?;
+
+    if Config::current().autosync() {
+        autosync(&projects[0], output)?;
+    }
+
+    let mut failed_with = None;
+    for (idx, project) in projects.iter().enumerate() {
+        if output != CommandOutput::Quiet {
+            if idx > 0 {
+                echo!();
+            }
+            echo!(
+                "Running benchmarks for {} ({})",
+                style(project.name().unwrap_or("<unknown>")).cyan(),
+                style(project.root_path().display()).dim()
+            );
+        }
+
+        let results_dir = project.root_path().join(".rye").join("benchmarks");
+        fs::create_dir_all(&results_dir)
+            .path_context(&results_dir, "unable to create benchmark directory")?;
+
+        // the baseline needs to be picked before this run is stored.
+        let baseline = match cmd.compare {
+            Some(ref target) => Some(find_baseline(&results_dir, project, target)?),
+            None => None,
+        };
+
+        let run_id = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let results = results_dir.join(format!("{}.json", run_id));
+        let mut bench_cmd = match project.bench_runner() {
+            Some(runner) => {
+                let bin = venv_bin.join(&runner[0]).with_extension(EXE_EXTENSION);
+                let mut bench_cmd = Command::new(if bin.is_file() {
+                    bin.into_os_string()
+                } else {
+                    OsString::from(&runner[0])
+                });
+                bench_cmd.args(&runner[1..]);
+                bench_cmd
+            }
+            None => {
+                let pytest = venv_bin.join("pytest").with_extension(EXE_EXTENSION);
+                if !pytest.is_file() {
+                    bail!("pytest-benchmark not installed. Run `rye add --dev pytest-benchmark`");
+                }
+                let mut bench_cmd = Command::new(pytest);
+                bench_cmd
+                    .arg("--benchmark-only")
+                    .arg("--benchmark-json")
+                    .arg(&results);
+                if output == CommandOutput::Quiet {
+                    bench_cmd.arg("-q");
+                }
+                bench_cmd
+            }
+        };
+        bench_cmd
+            .args(&cmd.extra_args)
+            .env("RYE_BENCHMARK_JSON", &results)
+            .current_dir(project.root_path());
+
+        let status = bench_cmd.status()?;
+        if !status.success() {
+            failed_with = Some(status.code().unwrap_or(1));
+            continue;
+        }
+        if !results.is_file() {
+            bail!(
+                "benchmark runner did not write results to {}",
+                results.display()
+            );
+        }
+        echo!(if output, "Stored results as run {}", style(run_id).cyan());
+
+        if let Some((name, baseline)) = baseline {
+            let current = load_run(&results)?;
+            echo!(if output, "Comparing against {}", style(name).cyan());
+            if !compare_runs(&baseline, &current, cmd.threshold) {
+                failed_with.get_or_insert(1);
+            }
+        }
+    }
+
+    if let Some(code) = failed_with {
+        Err(Error::new(QuietExit(code)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Finds the stored run to compare against.
+///
+/// This is either `last` for the most recent run, the id of a run or a git ref
+/// which matches the commit a run was recorded for.
+fn find_baseline(
+    results_dir: &Path,
+    project: &PyProject,
+    target: &str,
+) -> Result<(String, BenchmarkRun), Error> {
+    let mut runs = fs::read_dir(results_dir)
+        .path_context(results_dir, "unable to read benchmark directory")?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |x| x == "json"))
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_str()?.parse::<u64>().ok()?;
+            Some((id, path))
+        })
+        .collect::<Vec<_>>();
+    runs.sort();
+
+    if target == "last" {
+        let (id, path) = runs.last().context("no previous benchmark run stored")?;
+        return Ok((format!("run {}", id), load_run(path)?));
+    }
+    if let Some((id, path)) = runs.iter().find(|(id, _)| id.to_string() == target) {
+        return Ok((format!("run {}", id), load_run(path)?));
+    }
+
+    let rev_parse = Command::new("git")
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(format!("{}^{{commit}}", target))
+        .current_dir(project.root_path())
+        .output()
+        .context("unable to run git")?;
+    if !rev_parse.status.success() {
+        bail!(
+            "'{}' is neither a stored benchmark run nor a git ref",
+            target
+        );
+    }
+    let commit = String::from_utf8_lossy(&rev_parse.stdout)
+        .trim()
+        .to_string();
+    for (id, path) in runs.iter().rev() {
+        let run = load_run(path)?;
+        if run.commit_info.as_ref().and_then(|x| x.id.as_deref()) == Some(commit.as_str()) {
+            return Ok((format!("run {} ({})", id, target), run));
+        }
+    }
+    bail!("no stored benchmark run for {} ({})", target, &commit[..12]);
+}
+
+fn load_run(path: &Path) -> Result<BenchmarkRun, Error> {
+    let contents = fs::read(path).path_context(path, "unable to read benchmark results")?;
+    serde_json::from_slice(&contents)
+        .with_context(|| format!("invalid benchmark results in {}", path.display()))
+}
+
+/// Prints how the benchmarks changed and returns `false` if any of them
+/// regressed by more than the threshold.
+fn compare_runs(baseline: &BenchmarkRun, current: &BenchmarkRun, threshold: f64) -> bool {
+    let old = baseline
+        .benchmarks
+        .iter()
+        .map(|x| (x.fullname.as_str(), x.stats.mean))
+        .collect::<HashMap<_, _>>();
+
+    let mut ok = true;
+    for bench in &current.benchmarks {
+        let new = bench.stats.mean;
+        match old.get(bench.fullname.as_str()) {
+            Some(&old) if old > 0.0 => {
+                let change = (new - old) / old * 100.0;
+                let line = format!(
+                    "{}: {} -> {} ({:+.1}%)",
+                    bench.fullname,
+                    format_duration(old),
+                    format_duration(new),
+                    change
+                );
+                if change > threshold {
+                    ok = false;
+                    echo!(
+                        "  {} {}",
+                        style(line).red(),
+                        style("regression").red().bold()
+                    );
+                } else if change < -threshold {
+                    echo!("  {}", style(line).green());
+                } else {
+                    echo!("  {}", line);
+                }
+            }
+            _ => {
+                echo!("  {}: {} (new)", bench.fullname, format_duration(new));
+            }
+        }
+    }
+    ok
+}
+
+fn format_duration(secs: f64) -> String {
+    if secs >= 1.0 {
+        format!("{:.2}s", secs)
+    } else if secs >= 1e-3 {
+        format!("{:.2}ms", secs * 1e3)
+    } else if secs >= 1e-6 {
+        format!("{:.2}us", secs * 1e6)
+    } else {
+        format!("{:.2}ns", secs * 1e9)
+    }
+}
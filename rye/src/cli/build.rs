@@ -1,14 +1,44 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::time::SystemTime;
 
-use anyhow::{bail, Error};
+use anyhow::{bail, Context, Error};
 use clap::Parser;
 use console::style;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tempfile::tempdir;
 
-use crate::bootstrap::ensure_self_venv;
-use crate::pyproject::{locate_projects, PyProject};
-use crate::utils::{get_venv_python_bin, CommandOutput, IoPathContext};
+use crate::bootstrap::{fetch, FetchOptions};
+use crate::hooks::run_hook;
+use crate::platform::{get_app_dir, get_toolchain_python_bin};
+use crate::pyproject::{locate_projects, ExpandedSources, PyProject};
+use crate::sources::py::{PythonVersion, PythonVersionRequest};
+use crate::utils::dist_check::{check_dist, Problem};
+use crate::utils::{get_venv_python_bin, unpack_archive, CommandOutput, IoPathContext};
+use crate::uv::{UvBuilder, UvPackageUpgrade};
+
+/// The version of `build` that is installed into the build environments.
+const BUILD_REQUIREMENT: &str = "build==1.1.1";
+
+/// The lockfile with the pinned build requirements of a project.
+const BUILD_LOCKFILE: &str = "build-requirements.lock";
+
+/// Marker that is written once a build environment was installed.
+const BUILD_ENV_MARKER: &str = "rye-build-env-complete";
+
+/// Environment variables that would make packages from outside the build
+/// environment visible to the build.
+const LEAKING_BUILD_ENV_VARS: &[&str] = &[
+    "PYTHONPATH",
+    "PYTHONHOME",
+    "PYTHONSTARTUP",
+    "PYTHONUSERBASE",
+    "VIRTUAL_ENV",
+    "CONDA_PREFIX",
+];
 
 /// Builds a package for distribution.
 #[derive(Parser, Debug)]
@@ -34,6 +64,12 @@ pub struct Args {
     /// Clean the output directory first
     #[arg(short, long)]
     clean: bool,
+    /// Build wheels for these Python versions (comma separated)
+    #[arg(long, value_delimiter = ',', value_name = "VERSIONS")]
+    python: Vec<PythonVersionRequest>,
+    /// Validate the metadata and contents of the built distributions
+    #[arg(long)]
+    check: bool,
     /// Enables verbose diagnostics.
     #[arg(short, long)]
     verbose: bool,
@@ -42,9 +78,24 @@ pub struct Args {
     quiet: bool,
 }
 
+/// Describes the artifacts of a build with `--python`.
+#[derive(Serialize)]
+struct Manifest {
+    artifacts: Vec<Artifact>,
+}
+
+#[derive(Serialize)]
+struct Artifact {
+    project: String,
+    filename: String,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    python: Option<String>,
+    sha256: String,
+}
+
 pub fn execute(cmd: Args) -> Result<(), Error> {
     let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
-    let venv = ensure_self_venv(output)?;
     let project = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
 
     let out = match cmd.out {
@@ -63,22 +114,34 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
 
     let projects = locate_projects(project, cmd.all, &cmd.package[..])?;
 
+    if !cmd.python.is_empty() {
+        return build_matrix(&cmd, &out, projects, output);
+    }
+
     for project in projects {
         // skip over virtual packages on build
         if project.is_virtual() {
             continue;
         }
 
+        run_hook(&project, "pre-build", output)?;
+        write_vcs_version(&project, output)?;
+
         echo!(
             if output,
             "building {}",
             style(project.normalized_name()?).cyan()
         );
 
-        let mut build_cmd = Command::new(get_venv_python_bin(&venv));
+        let py_ver = fetch(
+            &project.venv_python_version()?.into(),
+            FetchOptions::with_output(output),
+        )?;
+        let python = hermetic_build_env(&project, &py_ver, output)?;
+
+        let before = list_dist_files(&out);
+        let mut build_cmd = build_command(&python);
         build_cmd
-            .arg("-mbuild")
-            .env("NO_COLOR", "1")
             .arg("--outdir")
             .arg(&out)
             .arg(&*project.root_path());
@@ -99,7 +162,351 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         if !status.success() {
             bail!("failed to build dist");
         }
+
+        if cmd.check {
+            let built = list_dist_files(&out)
+                .into_iter()
+                .filter(|(path, modified)| before.get(path) != Some(modified))
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>();
+            check_artifacts(&project, &built, output)?;
+        }
     }
 
     Ok(())
 }
+
+/// Builds an sdist and then, out of tree from that sdist, one wheel per
+/// requested Python version.
+///
+/// Pure Python projects end up with the same wheel for every version.  All
+/// artifacts are listed in a `manifest.json` in the output directory.
+fn build_matrix(
+    cmd: &Args,
+    out: &Path,
+    projects: Vec<PyProject>,
+    output: CommandOutput,
+) -> Result<(), Error> {
+    let versions = cmd
+        .python
+        .iter()
+        .map(|version| fetch(version, FetchOptions::with_output(output)))
+        .collect::<Result<Vec<_>, _>>()?;
+    fs::create_dir_all(out).path_context(out, "unable to create output directory")?;
+
+    let mut artifacts = Vec::new();
+    for project in projects {
+        if project.is_virtual() {
+            continue;
+        }
+        run_hook(&project, "pre-build", output)?;
+        write_vcs_version(&project, output)?;
+        let name = project.normalized_name()?;
+        echo!(if output, "building {}", style(&name).cyan());
+        let first_artifact = artifacts.len();
+
+        let scratch = tempdir()?;
+        let sdist_dir = scratch.path().join("sdist");
+        run_build(
+            &hermetic_build_env(&project, &versions[0], output)?,
+            &project.root_path(),
+            &sdist_dir,
+            "--sdist",
+            output,
+        )?;
+        let sdist = single_file(&sdist_dir)?;
+        let src_dir = scratch.path().join("src");
+        unpack_archive(
+            &fs::read(&sdist).path_context(&sdist, "unable to read sdist")?,
+            &src_dir,
+            1,
+            CommandOutput::Quiet,
+        )
+        .context("unable to unpack sdist")?;
+        if cmd.sdist || !cmd.wheel {
+            artifacts.push(move_artifact(&sdist, out, &name, "sdist", None)?);
+        }
+
+        if cmd.wheel || !cmd.sdist {
+            for py_ver in &versions {
+                echo!(
+                    if output,
+                    "building wheel for {} with {}",
+                    style(&name).cyan(),
+                    style(py_ver).cyan()
+                );
+                let wheel_dir = scratch.path().join(format!("wheel-{}", py_ver));
+                run_build(
+                    &hermetic_build_env(&project, py_ver, output)?,
+                    &src_dir,
+                    &wheel_dir,
+                    "--wheel",
+                    output,
+                )?;
+                let wheel = single_file(&wheel_dir)?;
+                artifacts.push(move_artifact(&wheel, out, &name, "wheel", Some(py_ver))?);
+            }
+        }
+
+        if cmd.check {
+            let built = artifacts[first_artifact..]
+                .iter()
+                .map(|artifact| out.join(&artifact.filename))
+                .collect::<Vec<_>>();
+            check_artifacts(&project, &built, output)?;
+        }
+    }
+
+    // a pure Python wheel is the same for all versions, only list it once.
+    artifacts.dedup_by(|a, b| a.filename == b.filename);
+    let manifest = out.join("manifest.json");
+    fs::write(
+        &manifest,
+        serde_json::to_string_pretty(&Manifest { artifacts })?,
+    )
+    .path_context(&manifest, "unable to write build manifest")?;
+    echo!(
+        if output,
+        "wrote manifest to {}",
+        style(manifest.display()).cyan()
+    );
+    Ok(())
+}
+
+/// Updates the version file of a project that takes its version from git.
+fn write_vcs_version(project: &PyProject, output: CommandOutput) -> Result<(), Error> {
+    if let Some(version) = project.write_vcs_version_file()? {
+        echo!(
+            if verbose output,
+            "version {} (from git)",
+            style(version).cyan()
+        );
+    }
+    Ok(())
+}
+
+fn run_build(
+    python: &Path,
+    src: &Path,
+    out: &Path,
+    kind: &str,
+    output: CommandOutput,
+) -> Result<(), Error> {
+    let mut build_cmd = build_command(python);
+    build_cmd.arg("--outdir").arg(out).arg(kind).arg(src);
+    if output == CommandOutput::Quiet {
+        build_cmd.stdout(Stdio::null());
+        build_cmd.stderr(Stdio::null());
+    }
+    let status = build_cmd.status()?;
+    if !status.success() {
+        bail!("failed to build dist");
+    }
+    Ok(())
+}
+
+/// Creates the `build` invocation for the Python of a build environment.
+///
+/// The build environment already has the build requirements, so `build`
+/// must not create another one.  Nothing from the calling environment may
+/// make other packages importable during the build.
+fn build_command(python: &Path) -> Command {
+    let mut cmd = Command::new(python);
+    cmd.arg("-mbuild")
+        .arg("--no-isolation")
+        .env("NO_COLOR", "1");
+    for var in LEAKING_BUILD_ENV_VARS {
+        cmd.env_remove(var);
+    }
+    cmd.env("PYTHONNOUSERSITE", "1");
+    cmd
+}
+
+/// Returns the Python of an isolated environment with the pinned build
+/// requirements of a project.
+///
+/// The environments are cached by the Python version and the contents of the
+/// build lockfile, so building again reuses the identical environment.
+fn hermetic_build_env(
+    project: &PyProject,
+    py_ver: &PythonVersion,
+    output: CommandOutput,
+) -> Result<PathBuf, Error> {
+    let lockfile = update_build_lockfile(project, py_ver, output)?;
+    let mut hasher = Sha256::new();
+    hasher.update(py_ver.to_string().as_bytes());
+    hasher.update(b"\0");
+    hasher.update(fs::read(&lockfile).path_context(&lockfile, "unable to read build lockfile")?);
+    let env_dir = get_app_dir()
+        .join("build-envs")
+        .join(&hex::encode(hasher.finalize())[..16]);
+
+    if !env_dir.join(BUILD_ENV_MARKER).is_file() {
+        if env_dir.is_dir() {
+            fs::remove_dir_all(&env_dir)
+                .path_context(&env_dir, "unable to remove incomplete build environment")?;
+        }
+        let uv = UvBuilder::new()
+            .with_output(output.quieter())
+            .with_workdir(&project.workspace_path())
+            .with_sources(ExpandedSources::from_sources(&project.sources()?)?)
+            .ensure_exists()?
+            .venv(&env_dir, &get_toolchain_python_bin(py_ver)?, py_ver, None)?
+            .with_output(output.quieter());
+        uv.write_marker()?;
+        uv.sync(&lockfile, &[])?;
+        fs::write(env_dir.join(BUILD_ENV_MARKER), b"")
+            .path_context(&env_dir, "unable to write build environment marker")?;
+    }
+    Ok(get_venv_python_bin(&env_dir))
+}
+
+/// Makes sure the build lockfile of a project pins its current build
+/// requirements and returns its path.
+///
+/// The requirements are recorded in the lockfile, it's only resolved again
+/// once they change.
+fn update_build_lockfile(
+    project: &PyProject,
+    py_ver: &PythonVersion,
+    output: CommandOutput,
+) -> Result<PathBuf, Error> {
+    let mut requires = project.build_requires();
+    requires.push(BUILD_REQUIREMENT.to_string());
+    let requires_line = format!("# build-requires: {}", serde_json::to_string(&requires)?);
+    let lockfile = project.root_path().join(BUILD_LOCKFILE);
+    if fs::read_to_string(&lockfile).map_or(false, |x| x.lines().any(|x| x == requires_line)) {
+        return Ok(lockfile);
+    }
+
+    echo!(
+        if output,
+        "locking build requirements of {}",
+        style(project.normalized_name()?).cyan()
+    );
+    let scratch = tempdir()?;
+    let requirements_in = scratch.path().join("build-requirements.in");
+    fs::write(&requirements_in, requires.join("\n"))
+        .path_context(&requirements_in, "unable to write build requirements")?;
+    let resolved = scratch.path().join(BUILD_LOCKFILE);
+    UvBuilder::new()
+        .with_output(output.quieter())
+        .with_workdir(&project.workspace_path())
+        .with_sources(ExpandedSources::from_sources(&project.sources()?)?)
+        .ensure_exists()?
+        .lockfile(
+            py_ver,
+            &requirements_in,
+            &resolved,
+            false,
+            false,
+            None,
+            None,
+            UvPackageUpgrade::Nothing,
+            None,
+        )?;
+    let resolved =
+        fs::read_to_string(&resolved).path_context(&resolved, "unable to read build lockfile")?;
+    fs::write(
+        &lockfile,
+        format!(
+            "# generated by rye\n\
+             # pinned build requirements, `rye build` updates this when they change\n\
+             #\n\
+             {}\n\
+             \n\
+             {}",
+            requires_line, resolved
+        ),
+    )
+    .path_context(&lockfile, "unable to write build lockfile")?;
+    Ok(lockfile)
+}
+
+/// Validates built distributions and fails if any of them has errors.
+fn check_artifacts(
+    project: &PyProject,
+    paths: &[PathBuf],
+    output: CommandOutput,
+) -> Result<(), Error> {
+    let required_files = project.metadata_files();
+    let mut failed = false;
+    for path in paths {
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        let problems = check_dist(path, &required_files)?;
+        if problems.is_empty() {
+            echo!(if output, "checked {}: {}", filename, style("PASSED").green());
+        }
+        for problem in problems {
+            match problem {
+                Problem::Error(msg) => {
+                    failed = true;
+                    error!("{}: {}", filename, msg);
+                }
+                Problem::Warning(msg) => {
+                    if output != CommandOutput::Quiet {
+                        warn!("{}: {}", filename, msg);
+                    }
+                }
+            }
+        }
+    }
+    if failed {
+        bail!("distribution check failed");
+    }
+    Ok(())
+}
+
+/// Lists the distributions in the output folder with their modification time.
+fn list_dist_files(out: &Path) -> HashMap<PathBuf, Option<SystemTime>> {
+    fs::read_dir(out)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name().map_or(false, |name| {
+                let name = name.to_string_lossy();
+                name.ends_with(".whl") || name.ends_with(".tar.gz") || name.ends_with(".zip")
+            })
+        })
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|x| x.modified()).ok();
+            (path, modified)
+        })
+        .collect()
+}
+
+/// Finds the one file that a build wrote into a directory.
+fn single_file(dir: &Path) -> Result<PathBuf, Error> {
+    fs::read_dir(dir)
+        .path_context(dir, "unable to read build output")?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.is_file())
+        .with_context(|| format!("build did not produce an artifact in {}", dir.display()))
+}
+
+fn move_artifact(
+    path: &Path,
+    out: &Path,
+    project: &str,
+    kind: &'static str,
+    python: Option<&PythonVersion>,
+) -> Result<Artifact, Error> {
+    let filename = path
+        .file_name()
+        .context("artifact without file name")?
+        .to_string_lossy()
+        .into_owned();
+    let target = out.join(&filename);
+    fs::copy(path, &target).path_context(&target, "unable to copy build artifact")?;
+    let contents = fs::read(&target).path_context(&target, "unable to read build artifact")?;
+    Ok(Artifact {
+        project: project.to_string(),
+        filename,
+        kind,
+        python: python.map(|x| x.to_string()),
+        sha256: hex::encode(Sha256::digest(contents)),
+    })
+}
@@ -0,0 +1,310 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Error};
+use clap::{Parser, ValueEnum};
+use console::style;
+
+use crate::platform::{
+    get_app_dir, get_index_cache_dir, get_toolchain_archive_cache_dir, get_wheel_cache_dir,
+};
+use crate::utils::{dir_size, format_size, parse_duration, CommandOutput};
+use crate::uv::UvBuilder;
+use crate::wheel_cache::{export_wheels, import_wheels};
+
+/// Manage the caches of Rye and uv.
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: SubCommand,
+}
+
+/// Prints the folders of the caches.
+#[derive(Parser, Debug)]
+pub struct DirCommand {
+    /// Only print the folder of this cache.
+    category: Option<CacheCategory>,
+}
+
+/// Prints the size of the caches.
+#[derive(Parser, Debug)]
+pub struct SizeCommand {
+    /// Only print the size of this cache.
+    category: Option<CacheCategory>,
+}
+
+/// Removes cached files.
+///
+/// Without options all caches are cleared entirely.
+#[derive(Parser, Debug)]
+pub struct CleanCommand {
+    /// Only clean these caches.
+    #[arg(short, long = "category")]
+    categories: Vec<CacheCategory>,
+    /// Only remove entries older than this (for instance `30d`, `12h` or `2w`).
+    ///
+    /// This does not apply to uv's cache which is skipped.
+    #[arg(long, value_name = "DURATION")]
+    older_than: Option<String>,
+    /// Only remove the entries of this package from uv's cache.
+    #[arg(long = "package", value_name = "NAME")]
+    packages: Vec<String>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Adds wheels to the wheel cache.
+#[derive(Parser, Debug)]
+pub struct ImportCommand {
+    /// A tar archive created by `rye cache export` or a folder with wheels.
+    path: PathBuf,
+    /// Turns off all output.
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Writes the wheel cache into a tar archive.
+#[derive(Parser, Debug)]
+pub struct ExportCommand {
+    /// The tar archive to create.
+    path: PathBuf,
+    /// Turns off all output.
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Parser, Debug)]
+enum SubCommand {
+    Dir(DirCommand),
+    Size(SizeCommand),
+    Clean(CleanCommand),
+    Import(ImportCommand),
+    Export(ExportCommand),
+}
+
+/// The caches managed by `rye cache`.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+pub enum CacheCategory {
+    /// uv's cache of packages.
+    Uv,
+    /// Downloaded toolchain archives.
+    Toolchains,
+    /// Package index metadata.
+    Index,
+    /// The virtualenvs with pip-tools.
+    PipTools,
+    /// Wheels shared between projects and machines.
+    Wheels,
+}
+
+impl CacheCategory {
+    pub fn name(self) -> &'static str {
+        match self {
+            CacheCategory::Uv => "uv",
+            CacheCategory::Toolchains => "toolchains",
+            CacheCategory::Index => "index",
+            CacheCategory::PipTools => "pip-tools",
+            CacheCategory::Wheels => "wheels",
+        }
+    }
+
+    pub fn path(self) -> Result<PathBuf, Error> {
+        match self {
+            CacheCategory::Uv => get_uv_cache_dir(),
+            CacheCategory::Toolchains => Ok(get_toolchain_archive_cache_dir()),
+            CacheCategory::Index => Ok(get_index_cache_dir()),
+            CacheCategory::PipTools => Ok(get_app_dir().join("pip-tools")),
+            CacheCategory::Wheels => Ok(get_wheel_cache_dir()),
+        }
+    }
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    match cmd.command {
+        SubCommand::Dir(args) => dir(args),
+        SubCommand::Size(args) => size(args),
+        SubCommand::Clean(args) => clean(args),
+        SubCommand::Import(args) => import(args),
+        SubCommand::Export(args) => export(args),
+    }
+}
+
+fn dir(cmd: DirCommand) -> Result<(), Error> {
+    if let Some(category) = cmd.category {
+        echo!("{}", category.path()?.display());
+        return Ok(());
+    }
+    for category in CacheCategory::value_variants() {
+        echo!(
+            "{}: {}",
+            style(category.name()).cyan(),
+            category.path()?.display()
+        );
+    }
+    Ok(())
+}
+
+fn size(cmd: SizeCommand) -> Result<(), Error> {
+    if let Some(category) = cmd.category {
+        echo!("{}", format_size(dir_size(&category.path()?)));
+        return Ok(());
+    }
+    let mut total = 0;
+    for category in CacheCategory::value_variants() {
+        let size = dir_size(&category.path()?);
+        total += size;
+        echo!("{}: {}", style(category.name()).cyan(), format_size(size));
+    }
+    echo!("{}: {}", style("total").bold(), format_size(total));
+    Ok(())
+}
+
+fn clean(cmd: CleanCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let older_than = cmd.older_than.as_deref().map(parse_duration).transpose()?;
+    let categories = if !cmd.categories.is_empty() {
+        cmd.categories
+    } else if !cmd.packages.is_empty() {
+        vec![CacheCategory::Uv]
+    } else {
+        CacheCategory::value_variants().to_vec()
+    };
+
+    if !cmd.packages.is_empty() && categories.iter().any(|x| *x != CacheCategory::Uv) {
+        bail!("--package only applies to uv's cache");
+    }
+    if older_than.is_some() && categories == [CacheCategory::Uv] {
+        bail!("--older-than does not apply to uv's cache");
+    }
+
+    for category in categories {
+        match category {
+            CacheCategory::Uv if older_than.is_some() => {
+                echo!(if output, "{}: skipped (--older-than)", style(category.name()).cyan());
+            }
+            CacheCategory::Uv => clean_uv_cache(&cmd.packages, output)?,
+            _ => {
+                let (count, size) = clean_dir(&category.path()?, older_than)?;
+                echo!(
+                    if output,
+                    "{}: removed {} {} ({})",
+                    style(category.name()).cyan(),
+                    count,
+                    if count == 1 { "entry" } else { "entries" },
+                    format_size(size)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn import(cmd: ImportCommand) -> Result<(), Error> {
+    let count = import_wheels(&cmd.path)?;
+    if !cmd.quiet {
+        echo!(
+            "Imported {} {} into the wheel cache",
+            count,
+            if count == 1 { "wheel" } else { "wheels" }
+        );
+    }
+    Ok(())
+}
+
+fn export(cmd: ExportCommand) -> Result<(), Error> {
+    let count = export_wheels(&cmd.path)?;
+    if !cmd.quiet {
+        echo!(
+            "Exported {} {} to {}",
+            count,
+            if count == 1 { "wheel" } else { "wheels" },
+            cmd.path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Removes the entries of a folder, optionally only those older than a duration.
+///
+/// Returns the number of removed entries and their size.
+fn clean_dir(path: &Path, older_than: Option<Duration>) -> Result<(usize, u64), Error> {
+    let cutoff = older_than.and_then(|x| SystemTime::now().checked_sub(x));
+    let mut count = 0;
+    let mut size = 0;
+    for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if let Some(cutoff) = cutoff {
+            if metadata.modified()? > cutoff {
+                continue;
+            }
+        }
+        if metadata.is_dir() {
+            size += dir_size(&path);
+            fs::remove_dir_all(&path)
+        } else {
+            size += metadata.len();
+            fs::remove_file(&path)
+        }
+        .with_context(|| format!("failed to remove {}", path.display()))?;
+        count += 1;
+    }
+    Ok((count, size))
+}
+
+fn get_uv_cache_dir() -> Result<PathBuf, Error> {
+    let uv = UvBuilder::new()
+        .with_output(CommandOutput::Quiet)
+        .ensure_exists()?;
+    let out = uv
+        .cmd()
+        .arg("cache")
+        .arg("dir")
+        .stderr(Stdio::null())
+        .output()
+        .context("unable to determine uv's cache folder")?;
+    if !out.status.success() {
+        bail!("unable to determine uv's cache folder");
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&out.stdout).trim().to_string(),
+    ))
+}
+
+fn clean_uv_cache(packages: &[String], output: CommandOutput) -> Result<(), Error> {
+    let uv = UvBuilder::new()
+        .with_output(output.quieter())
+        .ensure_exists()?;
+    let size_before = dir_size(&get_uv_cache_dir()?);
+    let status = uv
+        .cmd()
+        .arg("cache")
+        .arg("clean")
+        .args(packages)
+        .stdout(Stdio::null())
+        .stderr(if output == CommandOutput::Verbose {
+            Stdio::inherit()
+        } else {
+            Stdio::null()
+        })
+        .status()
+        .context("unable to run uv")?;
+    if !status.success() {
+        bail!("failed to clean uv's cache");
+    }
+    let size_after = dir_size(&get_uv_cache_dir()?);
+    echo!(
+        if output,
+        "{}: removed {}",
+        style(CacheCategory::Uv.name()).cyan(),
+        format_size(size_before.saturating_sub(size_after))
+    );
+    Ok(())
+}
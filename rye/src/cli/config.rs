@@ -6,13 +6,18 @@ use anyhow::Context;
 use anyhow::Error;
 use clap::Parser;
 use clap::ValueEnum;
+use console::style;
 use serde::Serialize;
 use toml_edit::value;
 use toml_edit::Item;
 use toml_edit::Table;
 use toml_edit::Value;
 
-use crate::config::Config;
+use crate::config::{config_json_schema, Config, ConfigOrigin, CONFIG_KEYS};
+use crate::credentials::{
+    list_source_credentials, remove_source_credentials, set_source_credentials,
+};
+use crate::utils::tui_theme;
 
 #[derive(ValueEnum, Copy, Clone, Serialize, Debug, PartialEq)]
 #[value(rename_all = "snake_case")]
@@ -27,9 +32,16 @@ enum Format {
 /// of the set options (`--set`, `--set-int`, `--set-bool`, or `--unset`).
 /// Each of the set operations takes a key=value pair. All of these can
 /// be supplied multiple times.
+///
+/// Reads return the effective value which can be overridden by the
+/// project (`rye.toml` or `[tool.rye.config]`) and `RYE_BEHAVIOR_*`
+/// environment variables.  `--show-origin` shows where values come from.
 #[derive(Parser, Debug)]
-#[command(arg_required_else_help(true))]
+#[command(arg_required_else_help(true), args_conflicts_with_subcommands(true))]
 pub struct Args {
+    #[command(subcommand)]
+    command: Option<SubCommand>,
+
     /// Print the path to the config.
     #[arg(long)]
     show_path: bool,
@@ -38,6 +50,53 @@ pub struct Args {
     action: Action,
 }
 
+#[derive(Parser, Debug)]
+enum SubCommand {
+    Credentials(CredentialsCommand),
+}
+
+/// Manages credentials for package sources.
+///
+/// Passwords are stored in the keyring of the operating system and are
+/// injected into uv, pip and twine when they are invoked.  They are never
+/// written to the config, lockfiles or logs.
+#[derive(Parser, Debug)]
+pub struct CredentialsCommand {
+    #[command(subcommand)]
+    command: CredentialsSubCommand,
+}
+
+#[derive(Parser, Debug)]
+enum CredentialsSubCommand {
+    Set(CredentialsSetCommand),
+    Remove(CredentialsRemoveCommand),
+    List(CredentialsListCommand),
+}
+
+/// Stores the password for a source in the keyring.
+#[derive(Parser, Debug)]
+pub struct CredentialsSetCommand {
+    /// The name of the source (or publish repository).
+    source: String,
+    /// The username to authenticate with.
+    #[arg(short, long)]
+    username: Option<String>,
+    /// Read the password from stdin rather than prompting for it.
+    #[arg(long)]
+    password_stdin: bool,
+}
+
+/// Removes the stored credentials for a source.
+#[derive(Parser, Debug)]
+pub struct CredentialsRemoveCommand {
+    /// The name of the source (or publish repository).
+    source: String,
+}
+
+/// Lists all sources with stored credentials.
+#[derive(Parser, Debug)]
+pub struct CredentialsListCommand {}
+
 #[derive(Parser, Debug)]
 #[group(required = false, multiple = true, conflicts_with = "show_path")]
 pub struct Action {
@@ -59,32 +118,68 @@ pub struct Action {
     /// Remove a config key.
     #[arg(long)]
     unset: Vec<String>,
+    /// Show where values come from (lists all set keys without `--get`).
+    #[arg(long)]
+    show_origin: bool,
+    /// List all known config keys with their value, default and description.
+    #[arg(long, conflicts_with_all = ["get", "show_origin"])]
+    list: bool,
+    /// Print a JSON schema of the config file.
+    #[arg(long, conflicts_with_all = ["get", "show_origin", "list"])]
+    schema: bool,
 }
 
 pub fn execute(cmd: Args) -> Result<(), Error> {
+    if let Some(SubCommand::Credentials(cmd)) = cmd.command {
+        return match cmd.command {
+            CredentialsSubCommand::Set(args) => set_credentials(args),
+            CredentialsSubCommand::Remove(args) => remove_credentials(args),
+            CredentialsSubCommand::List(_) => list_credentials(),
+        };
+    }
+
     let mut config = Config::current();
-    let doc = Arc::make_mut(&mut config).doc_mut();
 
     if cmd.show_path {
         echo!("{}", config.path().display());
         return Ok(());
     }
 
+    if cmd.action.schema {
+        echo!("{}", serde_json::to_string_pretty(&config_json_schema())?);
+        return Ok(());
+    } else if cmd.action.list {
+        return list_keys(&config, cmd.action.format);
+    }
+
     let mut read_as_json = BTreeMap::new();
     let mut read_as_string = Vec::new();
-    let reads = !cmd.action.get.is_empty();
-
-    for item in cmd.action.get {
-        let mut ptr = Some(doc.as_item());
-        for piece in item.split('.') {
-            ptr = ptr.as_ref().and_then(|x| x.get(piece));
-        }
+    let reads = !cmd.action.get.is_empty() || cmd.action.show_origin;
+    let show_origin = cmd.action.show_origin;
+    let keys = if cmd.action.get.is_empty() && show_origin {
+        config.origins().keys().cloned().collect()
+    } else {
+        cmd.action.get
+    };
 
-        let val = ptr.and_then(|x| x.as_value());
+    for item in keys {
+        let val = config.get_value(&item);
+        let origin = config
+            .origin(&item)
+            .map_or_else(|| "default".to_string(), |x| x.to_string());
         match cmd.action.format {
+            None if show_origin => {
+                read_as_string.push(format!("{}\t{}={}", origin, item, value_to_string(val)));
+            }
             None => {
                 read_as_string.push(value_to_string(val));
             }
+            Some(Format::Json) if show_origin => {
+                read_as_json.insert(
+                    item,
+                    serde_json::json!({"value": value_to_json(val), "origin": origin}),
+                );
+            }
             Some(Format::Json) => {
                 read_as_json.insert(item, value_to_json(val));
             }
@@ -136,6 +231,19 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         bail!("cannot mix get and set operations");
     }
 
+    let global_origin = ConfigOrigin::File(config.path().to_path_buf());
+    for key in updates
+        .iter()
+        .map(|x| x.0)
+        .chain(cmd.action.unset.iter().map(|x| x.as_str()))
+    {
+        if let Some(origin) = config.origin(key).filter(|x| **x != global_origin) {
+            warn!("{} is overridden by {}", key, origin);
+        }
+    }
+
+    let doc = Arc::make_mut(&mut config).doc_mut();
+
     for (key, new_value) in updates {
         let mut ptr = doc.as_item_mut();
         for piece in key.split('.') {
@@ -186,7 +294,108 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
     Ok(())
 }
 
-fn value_to_json(val: Option<&Value>) -> serde_json::Value {
+fn set_credentials(cmd: CredentialsSetCommand) -> Result<(), Error> {
+    let password = if cmd.password_stdin {
+        let mut password = String::new();
+        std::io::stdin()
+            .read_line(&mut password)
+            .context("failed to read password from stdin")?;
+        password.trim_end_matches(&['\r', '\n'][..]).to_string()
+    } else {
+        dialoguer::Password::with_theme(tui_theme())
+            .with_prompt(format!("Password for {}", cmd.source))
+            .report(false)
+            .interact()?
+    };
+    if password.is_empty() {
+        bail!("a password is required");
+    }
+    set_source_credentials(&cmd.source, cmd.username.as_deref(), &password)?;
+    echo!("Stored credentials for {}", style(&cmd.source).cyan());
+    Ok(())
+}
+
+fn remove_credentials(cmd: CredentialsRemoveCommand) -> Result<(), Error> {
+    if remove_source_credentials(&cmd.source)? {
+        echo!("Removed credentials for {}", style(&cmd.source).cyan());
+    } else {
+        warn!("no credentials stored for {}", cmd.source);
+    }
+    Ok(())
+}
+
+fn list_credentials() -> Result<(), Error> {
+    for source in list_source_credentials()? {
+        echo!("{}", source);
+    }
+    Ok(())
+}
+
+fn list_keys(config: &Config, format: Option<Format>) -> Result<(), Error> {
+    let mut read_as_json = BTreeMap::new();
+    for config_key in CONFIG_KEYS {
+        let item = config.get_item(config_key.key);
+        let origin = config.origin(config_key.key);
+        if format == Some(Format::Json) {
+            read_as_json.insert(
+                config_key.key,
+                serde_json::json!({
+                    "value": item_to_json(item),
+                    "default": config_key.default,
+                    "description": config_key.description,
+                    "origin": origin.map(|x| x.to_string()),
+                }),
+            );
+            continue;
+        }
+
+        let value = match item {
+            Some(item) => item_to_string(item),
+            None => style("(not set)").dim().to_string(),
+        };
+        echo!("{} = {}", style(config_key.key).cyan(), value);
+        echo!(
+            "    {}{}",
+            config_key.description,
+            config_key
+                .default
+                .map(|x| format!(" (default: {})", x))
+                .unwrap_or_default()
+        );
+    }
+    if format == Some(Format::Json) {
+        echo!("{}", serde_json::to_string_pretty(&read_as_json)?);
+    }
+    Ok(())
+}
+
+fn item_to_json(item: Option<&Item>) -> serde_json::Value {
+    match item {
+        Some(Item::ArrayOfTables(tables)) => serde_json::Value::Array(
+            tables
+                .iter()
+                .map(|x| {
+                    serde_json::Value::Object(
+                        x.iter()
+                            .map(|(k, v)| (k.to_string(), value_to_json(v.as_value())))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        ),
+        Some(item) => value_to_json(item.as_value()),
+        None => serde_json::Value::Null,
+    }
+}
+
+fn item_to_string(item: &Item) -> String {
+    match item {
+        Item::ArrayOfTables(tables) => format!("[{} tables]", tables.len()),
+        item => value_to_string(item.as_value()),
+    }
+}
+
+pub(crate) fn value_to_json(val: Option<&Value>) -> serde_json::Value {
     match val {
         Some(Value::String(s)) => serde_json::Value::String(s.value().into()),
         Some(Value::Integer(i)) => serde_json::Value::Number((*i.value()).into()),
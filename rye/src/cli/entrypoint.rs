@@ -0,0 +1,284 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Error};
+use clap::{Parser, ValueEnum};
+use console::style;
+use serde::Serialize;
+
+use crate::pyproject::{EntryPointKind, PyProject};
+use crate::utils::{CommandOutput, IoPathContext};
+
+/// Manage the entry points of the project.
+///
+/// Entry points are the commands that are installed along with the
+/// package (`project.scripts` and `project.gui-scripts`).
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: SubCommand,
+}
+
+/// Adds an entry point.
+#[derive(Parser, Debug)]
+pub struct AddCommand {
+    /// The name of the command to install.
+    name: String,
+    /// The function to invoke (for instance `my_package.cli:main`).
+    reference: String,
+    /// Add a GUI entry point (`project.gui-scripts`).
+    #[arg(long)]
+    gui: bool,
+    /// Do not check that the function exists in the project.
+    #[arg(long)]
+    no_verify: bool,
+    /// Replace an existing entry point of the same name.
+    #[arg(short, long)]
+    force: bool,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Turns off all output.
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Removes an entry point.
+#[derive(Parser, Debug)]
+pub struct RemoveCommand {
+    /// The name of the entry point.
+    name: String,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Turns off all output.
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Lists the entry points.
+#[derive(Parser, Debug)]
+pub struct ListCommand {
+    /// Request parseable output format
+    #[arg(long)]
+    format: Option<Format>,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+enum Format {
+    Json,
+}
+
+/// Output structure for entrypoint list --format=json
+#[derive(Serialize)]
+struct ListEntryPoint {
+    name: String,
+    reference: String,
+    gui: bool,
+}
+
+#[derive(Parser, Debug)]
+enum SubCommand {
+    Add(AddCommand),
+    Remove(RemoveCommand),
+    List(ListCommand),
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    match cmd.command {
+        SubCommand::Add(cmd) => add(cmd),
+        SubCommand::Remove(cmd) => remove(cmd),
+        SubCommand::List(cmd) => list(cmd),
+    }
+}
+
+fn add(cmd: AddCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, false);
+    let mut pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let kind = if cmd.gui {
+        EntryPointKind::Gui
+    } else {
+        EntryPointKind::Console
+    };
+
+    let (module, attr) = parse_reference(&cmd.reference)?;
+    if !cmd.no_verify {
+        verify_reference(&pyproject.root_path(), module, attr)?;
+    }
+
+    for other in [EntryPointKind::Console, EntryPointKind::Gui] {
+        if pyproject
+            .entry_points(other)
+            .iter()
+            .any(|(name, _)| *name == cmd.name)
+            && (!cmd.force || other != kind)
+        {
+            bail!(
+                "entry point '{}' already exists in project.{}{}",
+                cmd.name,
+                other.key(),
+                if other == kind {
+                    ". Use --force to replace it."
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+
+    pyproject.set_entry_point(kind, &cmd.name, &cmd.reference)?;
+    pyproject.save()?;
+
+    echo!(
+        if output,
+        "Added entry point {} ({})",
+        style(&cmd.name).cyan(),
+        cmd.reference
+    );
+    echo!(if output, "Run `rye sync` to install it.");
+    Ok(())
+}
+
+fn remove(cmd: RemoveCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, false);
+    let mut pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let mut removed = false;
+    for kind in [EntryPointKind::Console, EntryPointKind::Gui] {
+        removed |= pyproject.remove_entry_point(kind, &cmd.name);
+    }
+    if !removed {
+        bail!("entry point '{}' does not exist", cmd.name);
+    }
+    pyproject.save()?;
+
+    echo!(if output, "Removed entry point {}", style(&cmd.name).cyan());
+    Ok(())
+}
+
+fn list(cmd: ListCommand) -> Result<(), Error> {
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let mut entry_points = Vec::new();
+    for kind in [EntryPointKind::Console, EntryPointKind::Gui] {
+        for (name, reference) in pyproject.entry_points(kind) {
+            entry_points.push(ListEntryPoint {
+                name,
+                reference,
+                gui: kind == EntryPointKind::Gui,
+            });
+        }
+    }
+
+    if let Some(Format::Json) = cmd.format {
+        echo!("{}", serde_json::to_string_pretty(&entry_points)?);
+        return Ok(());
+    }
+
+    for entry_point in entry_points {
+        if entry_point.gui {
+            echo!(
+                "{} ({}) {}",
+                entry_point.name,
+                style(&entry_point.reference).dim(),
+                style("[gui]").dim()
+            );
+        } else {
+            echo!(
+                "{} ({})",
+                entry_point.name,
+                style(&entry_point.reference).dim()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Splits an object reference into module and attribute.
+///
+/// Extras (`module:func [extra]`) are ignored.
+fn parse_reference(reference: &str) -> Result<(&str, &str), Error> {
+    let reference = reference.split('[').next().unwrap_or_default().trim();
+    let is_dotted_name = |x: &str| {
+        !x.is_empty()
+            && x.split('.').all(|part| {
+                part.chars()
+                    .next()
+                    .map_or(false, |c| c.is_alphabetic() || c == '_')
+                    && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+            })
+    };
+    match reference.split_once(':') {
+        Some((module, attr)) if is_dotted_name(module.trim()) && is_dotted_name(attr.trim()) => {
+            Ok((module.trim(), attr.trim()))
+        }
+        _ => bail!(
+            "invalid entry point '{}', expected 'module:function'",
+            reference
+        ),
+    }
+}
+
+/// Checks that the module of an entry point exists in the project and
+/// defines the referenced attribute.
+fn verify_reference(root: &Path, module: &str, attr: &str) -> Result<(), Error> {
+    let relative = module.replace('.', "/");
+    let source_path = [root.join("src"), root.to_path_buf()]
+        .into_iter()
+        .flat_map(|base| {
+            [
+                base.join(&relative).join("__init__.py"),
+                base.join(format!("{}.py", relative)),
+            ]
+        })
+        .find(|x| x.is_file());
+    let source_path = match source_path {
+        Some(source_path) => source_path,
+        None => bail!(
+            "module '{}' not found in the project. Use --no-verify to add it anyway.",
+            module
+        ),
+    };
+
+    let source =
+        fs::read_to_string(&source_path).path_context(&source_path, "unable to read module")?;
+    let name = attr.split('.').next().unwrap_or(attr);
+    if !defines_name(&source, name) {
+        bail!(
+            "module '{}' does not define '{}'. Use --no-verify to add it anyway.",
+            module,
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Checks if Python source code defines or imports a name on the top level.
+fn defines_name(source: &str, name: &str) -> bool {
+    let is_name_end = |rest: &str| {
+        !rest
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_alphanumeric() || c == '_')
+    };
+    source.lines().any(|line| {
+        let statement = line.strip_prefix("async ").unwrap_or(line);
+        if let Some(rest) = statement
+            .strip_prefix("def ")
+            .or_else(|| statement.strip_prefix("class "))
+        {
+            rest.trim_start()
+                .strip_prefix(name)
+                .map_or(false, is_name_end)
+        } else if line.starts_with("from ") || line.starts_with("import ") {
+            line.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .any(|x| x == name)
+        } else {
+            line.strip_prefix(name).map_or(false, |rest| {
+                is_name_end(rest) && rest.trim_start().starts_with(|c| c == '=' || c == ':')
+            })
+        }
+    })
+}
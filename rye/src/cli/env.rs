@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::env::{self, join_paths, split_paths};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use clap::{Parser, ValueEnum};
+
+use crate::cli::shell::{quote_fish, quote_posix, quote_powershell};
+use crate::consts::VENV_BIN;
+use crate::pyproject::PyProject;
+use crate::sync::{sync, SyncOptions};
+use crate::tui::redirect_to_stderr;
+use crate::utils::CommandOutput;
+
+/// Prints the environment variables that activate the project's virtualenv.
+///
+/// The output is meant to be evaluated by the shell, for instance with
+/// `eval "$(rye env)"` or from a direnv layout.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The shell to emit the activation for (defaults to the one in `SHELL`).
+    #[arg(long)]
+    shell: Option<Shell>,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+    Json,
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    let shell = match cmd.shell {
+        Some(shell) => shell,
+        None => detect_shell(),
+    };
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+
+    // make sure we have the minimal virtualenv.  Nothing but the activation
+    // may go to stdout as it's evaluated by the shell.
+    {
+        let _guard = redirect_to_stderr(true);
+        let mut options = SyncOptions::python_only().pyproject(cmd.pyproject.clone());
+        options.output = CommandOutput::Quiet;
+        sync(options).context("failed to sync ahead of env")?;
+    }
+
+    let venv_path = pyproject.venv_path();
+    let venv_bin = venv_path.join(VENV_BIN);
+    let prompt = pyproject.name().unwrap_or("venv").to_string();
+
+    match shell {
+        Shell::Bash | Shell::Zsh => {
+            echo!("export VIRTUAL_ENV={}", quote_posix(&venv_path));
+            echo!(
+                "export VIRTUAL_ENV_PROMPT={}",
+                quote_posix(Path::new(&prompt))
+            );
+            echo!("export PATH={}:\"$PATH\"", quote_posix(&venv_bin));
+            echo!("unset PYTHONHOME");
+        }
+        Shell::Fish => {
+            echo!("set -gx VIRTUAL_ENV {};", quote_fish(&venv_path));
+            echo!(
+                "set -gx VIRTUAL_ENV_PROMPT {};",
+                quote_fish(Path::new(&prompt))
+            );
+            echo!("set -gx PATH {} $PATH;", quote_fish(&venv_bin));
+            echo!("set -e PYTHONHOME;");
+        }
+        Shell::Powershell => {
+            echo!("$env:VIRTUAL_ENV = {}", quote_powershell(&venv_path));
+            echo!(
+                "$env:VIRTUAL_ENV_PROMPT = {}",
+                quote_powershell(Path::new(&prompt))
+            );
+            echo!(
+                "$env:PATH = {} + [IO.Path]::PathSeparator + $env:PATH",
+                quote_powershell(&venv_bin)
+            );
+            echo!("Remove-Item Env:PYTHONHOME -ErrorAction SilentlyContinue");
+        }
+        Shell::Json => {
+            let mut paths = env::var_os("PATH")
+                .map(|x| split_paths(&x).collect::<Vec<_>>())
+                .unwrap_or_default();
+            paths.insert(0, venv_bin);
+            let path = join_paths(paths)?;
+
+            // a value of `null` means that the variable has to be unset.
+            let mut vars = BTreeMap::new();
+            vars.insert(
+                "VIRTUAL_ENV",
+                Some(venv_path.to_string_lossy().into_owned()),
+            );
+            vars.insert("VIRTUAL_ENV_PROMPT", Some(prompt));
+            vars.insert("PATH", Some(path.to_string_lossy().into_owned()));
+            vars.insert("PYTHONHOME", None);
+            serde_json::to_writer_pretty(std::io::stdout().lock(), &vars)?;
+            echo!();
+        }
+    }
+
+    Ok(())
+}
+
+fn detect_shell() -> Shell {
+    if cfg!(windows) {
+        return Shell::Powershell;
+    }
+    let shell = env::var_os("SHELL").map(PathBuf::from).unwrap_or_default();
+    match shell.file_stem().and_then(|x| x.to_str()) {
+        Some("zsh") => Shell::Zsh,
+        Some("fish") => Shell::Fish,
+        Some("pwsh") | Some("powershell") => Shell::Powershell,
+        _ => Shell::Bash,
+    }
+}
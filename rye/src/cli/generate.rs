@@ -0,0 +1,525 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Error};
+use clap::{Parser, ValueEnum};
+use minijinja::render;
+
+use crate::consts::VENV_BIN;
+use crate::hooks::hook_commands;
+use crate::pyproject::{BuildSystem, PyProject};
+use crate::utils::{CommandOutput, IoPathContext};
+
+/// The Dockerfile template.
+const DOCKERFILE_TEMPLATE: &str = include_str!("../templates/generate/Dockerfile.j2");
+
+/// The template for the .dockerignore next to the Dockerfile.
+const DOCKERIGNORE_TEMPLATE: &str = include_str!("../templates/generate/dockerignore.j2");
+
+/// The template for the devcontainer.json.
+const DEVCONTAINER_TEMPLATE: &str = include_str!("../templates/generate/devcontainer.json.j2");
+
+/// The local dev container feature that installs rye.
+const DEVCONTAINER_FEATURE: &str = include_str!("../templates/generate/devcontainer-feature.json");
+const DEVCONTAINER_INSTALL_SCRIPT: &str =
+    include_str!("../templates/generate/devcontainer-install.sh");
+
+/// The CI workflow templates.
+const GITHUB_CI_TEMPLATE: &str = include_str!("../templates/generate/github-ci.yml.j2");
+const GITLAB_CI_TEMPLATE: &str = include_str!("../templates/generate/gitlab-ci.yml.j2");
+
+/// The template for the .pre-commit-config.yaml.
+const PRE_COMMIT_TEMPLATE: &str = include_str!("../templates/generate/pre-commit-config.yaml.j2");
+
+const DEFAULT_DEVCONTAINER_IMAGE: &str = "mcr.microsoft.com/devcontainers/base:bookworm";
+
+/// The distroless image only ships the Python version of its Debian release.
+const DISTROLESS_IMAGE: &str = "gcr.io/distroless/python3-debian12:nonroot";
+const DISTROLESS_PYTHON: (u8, u8) = (3, 11);
+
+/// Generates files for building and developing the project.
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: SubCommand,
+}
+
+/// Generates a Dockerfile and .dockerignore for the project.
+///
+/// The Dockerfile is a multi-stage build that uses the project's pinned
+/// Python version and installs the dependencies from the production lockfile.
+/// If the lockfile has hashes (`rye lock --generate-hashes`) they are
+/// verified.  Re-run this command whenever the Python version or the
+/// project metadata change.
+#[derive(Parser, Debug)]
+pub struct DockerfileCommand {
+    /// Use a slim base image for the final stage.
+    #[arg(long, conflicts_with = "distroless")]
+    slim: bool,
+    /// Use a distroless base image for the final stage.
+    #[arg(long)]
+    distroless: bool,
+    /// Overwrite an existing Dockerfile and .dockerignore.
+    #[arg(short, long)]
+    force: bool,
+    /// Fail if the files are missing or not up to date instead of writing them.
+    #[arg(long, conflicts_with = "force")]
+    check: bool,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Generates a dev container configuration for the project.
+///
+/// This writes `.devcontainer/devcontainer.json` next to a local feature that
+/// installs rye and fetches the project's pinned toolchain.  The virtualenv
+/// lives in a volume and is synced when the container is created.
+#[derive(Parser, Debug)]
+pub struct DevcontainerCommand {
+    /// The base image of the dev container.
+    #[arg(long, default_value = DEFAULT_DEVCONTAINER_IMAGE)]
+    image: String,
+    /// Point VS Code at the virtualenv, both in the dev container and locally.
+    #[arg(long)]
+    vscode: bool,
+    /// Overwrite an existing dev container configuration.
+    #[arg(short, long)]
+    force: bool,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Generates a CI workflow for the project.
+///
+/// The workflow checks that the lockfiles are up to date, checks formatting,
+/// lints and runs the tests for every Python version in
+/// `tool.rye.test-matrix` (or the pinned version).  Toolchains and the package
+/// cache are cached between runs.
+#[derive(Parser, Debug)]
+pub struct CiCommand {
+    /// The CI provider to generate the workflow for.
+    #[arg(long, default_value = "github")]
+    provider: CiProvider,
+    /// Overwrite an existing workflow.
+    #[arg(short, long)]
+    force: bool,
+    /// Fail if the workflow is missing or not up to date instead of writing it.
+    #[arg(long, conflicts_with = "force")]
+    check: bool,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+enum CiProvider {
+    Github,
+    Gitlab,
+}
+
+/// Generates a pre-commit configuration that runs rye's pre-commit hook.
+///
+/// The hook runs `rye run --hook pre-commit` which checks formatting and
+/// lints the changed files unless `tool.rye.hooks.pre-commit` is configured.
+#[derive(Parser, Debug)]
+pub struct PreCommitCommand {
+    /// Overwrite an existing .pre-commit-config.yaml.
+    #[arg(short, long)]
+    force: bool,
+    /// Fail if the configuration is missing or not up to date instead of writing it.
+    #[arg(long, conflicts_with = "force")]
+    check: bool,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+#[derive(Parser, Debug)]
+enum SubCommand {
+    Ci(CiCommand),
+    Devcontainer(DevcontainerCommand),
+    Dockerfile(DockerfileCommand),
+    PreCommit(PreCommitCommand),
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    match cmd.command {
+        SubCommand::Ci(args) => ci(args),
+        SubCommand::Devcontainer(args) => devcontainer(args),
+        SubCommand::Dockerfile(args) => dockerfile(args),
+        SubCommand::PreCommit(args) => pre_commit(args),
+    }
+}
+
+fn ci(cmd: CiCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let root = pyproject.workspace_path();
+
+    let mut pythons = pyproject
+        .test_matrix()?
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    if pythons.is_empty() {
+        let py_ver = pyproject.venv_python_version()?;
+        pythons.push(format!("{}.{}", py_ver.major, py_ver.minor));
+    }
+    // in workspaces all projects are formatted, linted and tested
+    let all = if pyproject.workspace().is_some() {
+        " --all"
+    } else {
+        ""
+    };
+
+    let (path, template) = match cmd.provider {
+        CiProvider::Github => (
+            root.join(".github").join("workflows").join("ci.yml"),
+            GITHUB_CI_TEMPLATE,
+        ),
+        CiProvider::Gitlab => (root.join(".gitlab-ci.yml"), GITLAB_CI_TEMPLATE),
+    };
+    let contents = render!(
+        template,
+        rye_version => env!("CARGO_PKG_VERSION"),
+        branch => default_branch(&root),
+        pythons,
+        all,
+    );
+
+    let files = [(path, contents)];
+    if cmd.check {
+        return check_files(&files, "rye generate ci", output);
+    }
+    write_files(&files, cmd.force, output)
+}
+
+/// Returns the default branch of the origin remote, falling back to `main`.
+fn default_branch(root: &Path) -> String {
+    Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "origin/HEAD"])
+        .current_dir(root)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|x| x.status.success())
+        .and_then(|x| {
+            let branch = String::from_utf8_lossy(&x.stdout).trim().to_string();
+            branch.strip_prefix("origin/").map(|x| x.to_string())
+        })
+        .unwrap_or_else(|| "main".to_string())
+}
+
+fn dockerfile(cmd: DockerfileCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let root = pyproject.root_path();
+
+    if let Some(workspace) = pyproject.workspace() {
+        if workspace.iter_projects().nth(1).is_some() {
+            bail!("Dockerfiles are not supported for workspaces with multiple projects");
+        }
+    }
+    if pyproject.build_backend() == Some(BuildSystem::Maturin) {
+        bail!("Dockerfiles are not supported for maturin projects");
+    }
+
+    let py_ver = pyproject.venv_python_version()?;
+    if py_ver.name != "cpython" {
+        bail!(
+            "the generated Dockerfile requires CPython, but the project uses {}",
+            py_ver
+        );
+    }
+    let (builder_image, runtime_image) = if cmd.distroless {
+        if (py_ver.major, py_ver.minor) != DISTROLESS_PYTHON {
+            warn!(
+                "distroless images use Python {}.{}, not the pinned {}.{}",
+                DISTROLESS_PYTHON.0, DISTROLESS_PYTHON.1, py_ver.major, py_ver.minor
+            );
+        }
+        (
+            format!("python:{}.{}", DISTROLESS_PYTHON.0, DISTROLESS_PYTHON.1),
+            DISTROLESS_IMAGE.to_string(),
+        )
+    } else {
+        let image = format!("python:{}.{}.{}", py_ver.major, py_ver.minor, py_ver.patch);
+        let runtime_image = if cmd.slim {
+            format!("{}-slim", image)
+        } else {
+            image.clone()
+        };
+        (image, runtime_image)
+    };
+
+    let lockfile = pyproject.lockfile_path(false);
+    let lockfile_name = relative_name(&root, &lockfile)?;
+    let require_hashes = match fs::read_to_string(&lockfile) {
+        Ok(contents) => contents.contains("--hash="),
+        Err(_) => bail!("{} does not exist, run `rye lock` first", lockfile_name),
+    };
+    if !require_hashes && output != CommandOutput::Quiet {
+        warn!(
+            "{} has no hashes, lock with `rye lock --generate-hashes` to verify the packages",
+            lockfile_name
+        );
+    }
+
+    // only the files needed to build and run the project are copied
+    let name_safe = pyproject.normalized_name()?.replace('-', "_");
+    let mut copy_files = vec!["pyproject.toml".to_string()];
+    copy_files.extend(
+        pyproject
+            .metadata_files()
+            .into_iter()
+            .filter(|x| root.join(x).is_file()),
+    );
+    let mut copy_dirs = Vec::new();
+    if root.join("src").is_dir() {
+        copy_dirs.push("src".to_string());
+    } else if root.join(&name_safe).is_dir() {
+        copy_dirs.push(name_safe.clone());
+    } else if root.join(format!("{}.py", name_safe)).is_file() {
+        copy_files.push(format!("{}.py", name_safe));
+    }
+
+    let is_virtual = pyproject.is_virtual();
+    let source_path = if root.join("src").is_dir() {
+        "/app/src"
+    } else {
+        "/app"
+    };
+    let pythonpath = match (cmd.distroless, is_virtual) {
+        (true, true) => Some(format!("/app/packages:{}", source_path)),
+        (true, false) => Some("/app/packages".to_string()),
+        (false, true) => Some(source_path.to_string()),
+        (false, false) => None,
+    };
+
+    // prefer the first entry point and fall back to running the package
+    let entry_point = pyproject.console_scripts().into_iter().next();
+    let command = match (entry_point, cmd.distroless) {
+        (Some((name, _)), false) if !is_virtual => vec![name],
+        (Some((_, reference)), true) if !is_virtual => {
+            let (module, func) = reference
+                .split_once(':')
+                .map(|(m, f)| (m.trim(), f.trim()))
+                .unwrap_or((reference.as_str(), "main"));
+            vec![
+                "-c".to_string(),
+                format!(
+                    "import sys; from {} import {}; sys.exit({}())",
+                    module, func, func
+                ),
+            ]
+        }
+        (_, true) => vec!["-m".to_string(), name_safe.clone()],
+        (_, false) => vec!["python".into(), "-m".into(), name_safe.clone()],
+    };
+
+    let dockerfile = render!(
+        DOCKERFILE_TEMPLATE,
+        builder_image,
+        runtime_image,
+        distroless => cmd.distroless,
+        lockfile => lockfile_name,
+        require_hashes,
+        is_virtual,
+        copy_files,
+        copy_dirs,
+        pythonpath,
+        cmd => serde_json::to_string(&command)?,
+    );
+    let dockerignore = render!(
+        DOCKERIGNORE_TEMPLATE,
+        lockfile => lockfile_name,
+        copy_files,
+        copy_dirs,
+    );
+
+    let files = [
+        (root.join("Dockerfile"), dockerfile),
+        (root.join(".dockerignore"), dockerignore),
+    ];
+    if cmd.check {
+        return check_files(&files, "rye generate dockerfile", output);
+    }
+    write_files(&files, cmd.force, output)
+}
+
+fn devcontainer(cmd: DevcontainerCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let root = pyproject.workspace_path();
+    let name = pyproject.normalized_name()?;
+
+    let venv = match relative_name(&root, &pyproject.venv_path()) {
+        Ok(venv) => venv,
+        Err(_) => bail!(
+            "dev containers require the virtualenv inside the project (behavior.venv-in-project)"
+        ),
+    };
+    let py_ver = pyproject.venv_python_version()?;
+    let toolchain = format!(
+        "{}@{}.{}.{}",
+        py_ver.name, py_ver.major, py_ver.minor, py_ver.patch
+    );
+
+    let devcontainer = render!(
+        DEVCONTAINER_TEMPLATE,
+        name,
+        image => cmd.image,
+        rye_version => env!("CARGO_PKG_VERSION"),
+        toolchain,
+        venv,
+        vscode => cmd.vscode,
+    );
+    let folder = root.join(".devcontainer");
+    let files = [
+        (folder.join("devcontainer.json"), devcontainer),
+        (
+            folder.join("rye").join("devcontainer-feature.json"),
+            DEVCONTAINER_FEATURE.to_string(),
+        ),
+        (
+            folder.join("rye").join("install.sh"),
+            DEVCONTAINER_INSTALL_SCRIPT.to_string(),
+        ),
+    ];
+    write_files(&files, cmd.force, output)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let script = &files[2].0;
+        fs::set_permissions(script, fs::Permissions::from_mode(0o755))
+            .path_context(script, "failed to set permissions")?;
+    }
+
+    if cmd.vscode {
+        // the local settings belong to the user, they are only ever created
+        let interpreter = format!(
+            "${{workspaceFolder}}/{}/{}/{}",
+            venv,
+            VENV_BIN,
+            if cfg!(windows) {
+                "python.exe"
+            } else {
+                "python"
+            }
+        );
+        let settings = root.join(".vscode").join("settings.json");
+        if !settings.is_file() {
+            let contents = serde_json::to_string_pretty(&serde_json::json!({
+                "python.defaultInterpreterPath": interpreter,
+            }))?;
+            write_files(&[(settings, contents + "\n")], false, output)?;
+        } else if !fs::read_to_string(&settings)
+            .path_context(&settings, "unable to read VS Code settings")?
+            .contains("python.defaultInterpreterPath")
+        {
+            warn!(
+                "{} exists, add \"python.defaultInterpreterPath\": \"{}\" to it",
+                settings.display(),
+                interpreter
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn pre_commit(cmd: PreCommitCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let commands = hook_commands(&pyproject, "pre-commit");
+    if commands.is_empty() {
+        bail!("tool.rye.hooks.pre-commit is empty");
+    }
+
+    let contents = render!(PRE_COMMIT_TEMPLATE, commands);
+    let files = [(
+        pyproject.workspace_path().join(".pre-commit-config.yaml"),
+        contents,
+    )];
+    if cmd.check {
+        return check_files(&files, "rye generate pre-commit", output);
+    }
+    write_files(&files, cmd.force, output)?;
+    echo!(if output, "Run `pre-commit install` to enable the hook");
+    Ok(())
+}
+
+/// Fails if generated files are missing or differ from what would be written.
+fn check_files(
+    files: &[(PathBuf, String)],
+    command: &str,
+    output: CommandOutput,
+) -> Result<(), Error> {
+    for (path, contents) in files {
+        if fs::read_to_string(path).ok().as_deref() != Some(contents.as_str()) {
+            bail!("{} is not up to date, run `{}`", path.display(), command);
+        }
+        echo!(if output, "{} is up to date", path.display());
+    }
+    Ok(())
+}
+
+/// Writes generated files, refusing to replace existing ones unless forced.
+fn write_files(
+    files: &[(PathBuf, String)],
+    force: bool,
+    output: CommandOutput,
+) -> Result<(), Error> {
+    for (path, _) in files {
+        if path.exists() && !force {
+            bail!(
+                "{} already exists, pass --force to overwrite",
+                path.display()
+            );
+        }
+    }
+    for (path, contents) in files {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).path_context(parent, "unable to create folder")?;
+        }
+        fs::write(path, contents).path_context(path, "unable to write file")?;
+        echo!(if output, "Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Returns the path of a file relative to the project root with forward slashes.
+fn relative_name(root: &Path, path: &Path) -> Result<String, Error> {
+    let rel = path
+        .strip_prefix(root)
+        .with_context(|| format!("{} is not inside the project", path.display()))?;
+    Ok(rel.to_string_lossy().replace('\\', "/"))
+}
@@ -7,7 +7,7 @@ use std::{env, fs};
 use anyhow::{anyhow, bail, Context, Error};
 use clap::Parser;
 use configparser::ini::Ini;
-use console::style;
+use console::{style, Term};
 use license::License;
 use minijinja::{context, Environment};
 use monotrail_utils::RequirementsTxt;
@@ -16,14 +16,15 @@ use pep508_rs::Requirement;
 use serde_json::Value;
 use tempfile::tempdir;
 
-use crate::bootstrap::ensure_self_venv;
+use crate::bootstrap::{ensure_self_venv, fetch, FetchOptions};
 use crate::config::Config;
 use crate::platform::{
     get_default_author_with_fallback, get_latest_cpython_version, get_pinnable_version,
     get_python_version_request_from_pyenv_pin,
 };
-use crate::pyproject::BuildSystem;
+use crate::pyproject::{BuildSystem, PyProject};
 use crate::sources::py::PythonVersionRequest;
+use crate::utils::scaffold::{ProjectTemplate, TemplateSettings};
 use crate::utils::{
     copy_dir, escape_string, format_requirement, get_venv_python_bin, is_inside_git_work_tree,
     CommandOutput, CopyDirOptions, IoPathContext,
@@ -50,8 +51,11 @@ pub struct Args {
     /// Do not create .python-version file (requires-python will be used)
     #[arg(long)]
     no_pin: bool,
-    /// Which build system should be used (defaults to hatchling)?
+    /// Fetch the project's toolchain right away rather than on the first sync.
     #[arg(long)]
+    fetch: bool,
+    /// Which build system should be used (defaults to hatchling)?
+    #[arg(long, visible_alias = "build-backend")]
     build_system: Option<BuildSystem>,
     /// Which license should be used (SPDX identifier)?
     #[arg(long)]
@@ -71,6 +75,21 @@ pub struct Args {
     /// Python package.  It also cannot be published.
     #[arg(long = "virtual")]
     is_virtual: bool,
+    /// Scaffold the project from a template.
+    ///
+    /// This is the name of a built-in template (cli, lib, fastapi, maturin or
+    /// data-science), a path or a git URL of a native or cookiecutter template.
+    #[arg(long, conflicts_with_all = ["script", "is_virtual"])]
+    template: Option<String>,
+    /// Scaffold a data science project with notebooks and a Jupyter kernel.
+    ///
+    /// This is a shortcut for `--template data-science`.
+    #[arg(long, conflicts_with_all = ["template", "script", "is_virtual"])]
+    data_science: bool,
+    /// Sets a template variable instead of asking for it.
+    #[arg(long = "template-var", value_name = "KEY=VALUE")]
+    #[arg(value_parser = parse_template_var)]
+    template_vars: Vec<(String, String)>,
     /// Requirements files to initialize pyproject.toml with.
     #[arg(short, long, name = "REQUIREMENTS_FILE", conflicts_with = "no_import")]
     requirements: Option<Vec<PathBuf>>,
@@ -164,6 +183,24 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         bail!("pyproject.toml already exists");
     }
 
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let template_spec = match cmd.template {
+        Some(ref template) => Some(template.as_str()),
+        None if cmd.data_science => Some("data-science"),
+        None => None,
+    };
+    if template_spec.is_none() && !cmd.template_vars.is_empty() {
+        bail!("--template-var requires --template");
+    }
+    let project_template = match template_spec {
+        Some(template) => Some(ProjectTemplate::resolve(template, output)?),
+        None => None,
+    };
+    let template_settings = match project_template {
+        Some(ref template) => template.settings()?,
+        None => TemplateSettings::default(),
+    };
+
     // fail silently if it already exists or cannot be created.
     fs::create_dir_all(&dir).ok();
 
@@ -221,8 +258,6 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         fs::write(&license_file, rv).path_context(&license_file, "create license file")?;
     }
 
-    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
-
     // initialize with no metadata
     let mut metadata = Metadata::new();
 
@@ -263,6 +298,15 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
     if metadata.dependencies.is_none() {
         metadata.dependencies = Some(Vec::new())
     }
+    if let Some(ref mut dependencies) = metadata.dependencies {
+        dependencies.extend(template_settings.dependencies.iter().cloned());
+    }
+    if !template_settings.dev_dependencies.is_empty() {
+        metadata
+            .dev_dependencies
+            .get_or_insert_with(Vec::new)
+            .extend(template_settings.dev_dependencies.iter().cloned());
+    }
 
     // write .python-version
     if !cmd.no_pin && !python_version_file.is_file() {
@@ -274,25 +318,7 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
             .path_context(&python_version_file, "could not write .python-version file")?;
     }
 
-    // create a readme if one is missing
-    let with_readme = if readme.is_file() {
-        true
-    } else if !cmd.no_readme {
-        let rv = env.render_named_str(
-            "README.md",
-            README_TEMPLATE,
-            context! {
-                name => metadata.name,
-                license => metadata.license,
-            },
-        )?;
-        fs::write(&readme, rv)?;
-        true
-    } else {
-        false
-    };
-
-    let build_system = match cmd.build_system {
+    let build_system = match cmd.build_system.or(template_settings.build_system) {
         Some(build_system) => build_system,
         None => cfg.default_build_system().unwrap_or(BuildSystem::Hatchling),
     };
@@ -301,7 +327,7 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
 
     // What template are we using?
     let template = {
-        if cmd.init_type.script {
+        if cmd.init_type.script || template_settings.is_script {
             TemplateChoice::Script
         } else {
             // default value
@@ -343,6 +369,58 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         }
     }
 
+    // render the template before rye's own files so that its files take precedence
+    if let Some(ref project_template) = project_template {
+        let mut context = serde_json::Map::new();
+        let mut set = |key: &str, value: Value| {
+            context.insert(key.to_string(), value);
+        };
+        set("name", metadata.name.clone().into());
+        set("name_safe", name_safe.clone().into());
+        set("description", metadata.description.clone().into());
+        set("version", metadata.version.clone().into());
+        set(
+            "author_name",
+            metadata.author.as_ref().map(|x| x.0.clone()).into(),
+        );
+        set(
+            "author_email",
+            metadata.author.as_ref().map(|x| x.1.clone()).into(),
+        );
+        set("requires_python", metadata.requires_python.clone().into());
+        set(
+            "python",
+            format!("{}.{}", py.major, py.minor.unwrap_or_default()).into(),
+        );
+        set("license", metadata.license.clone().into());
+        set("build_system", serde_json::to_value(build_system)?);
+        set(
+            "is_script",
+            matches!(template, TemplateChoice::Script).into(),
+        );
+        let interactive = output != CommandOutput::Quiet && Term::stderr().is_term();
+        let overrides = cmd.template_vars.iter().cloned().collect();
+        project_template.render(&dir, context, &overrides, interactive, output)?;
+    }
+
+    // create a readme if one is missing
+    let with_readme = if readme.is_file() {
+        true
+    } else if !cmd.no_readme {
+        let rv = env.render_named_str(
+            "README.md",
+            README_TEMPLATE,
+            context! {
+                name => metadata.name,
+                license => metadata.license,
+            },
+        )?;
+        fs::write(&readme, rv)?;
+        true
+    } else {
+        false
+    };
+
     let gitignore = dir.join(".gitignore");
 
     // create a .gitignore if one is missing
@@ -357,31 +435,35 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         fs::write(&gitignore, rv).path_context(&gitignore, "failed to write .gitignore")?;
     }
 
-    let rv = env.render_named_str(
-        "pyproject.json",
-        TOML_TEMPLATE,
-        context! {
-            name => metadata.name,
-            name_safe => name_safe,
-            description => metadata.description,
-            version => metadata.version,
-            author => metadata.author,
-            requires_python => metadata.requires_python,
-            license => metadata.license,
-            dependencies => metadata.dependencies,
-            dev_dependencies => metadata.dev_dependencies,
-            is_script => matches!(template, TemplateChoice::Script),
-            is_virtual,
-            with_readme,
-            build_system,
-            private,
-        },
-    )?;
-    fs::write(&toml, rv).context("failed to write pyproject.toml")?;
+    // a template can bring its own pyproject.toml
+    if !toml.is_file() {
+        let rv = env.render_named_str(
+            "pyproject.json",
+            TOML_TEMPLATE,
+            context! {
+                name => metadata.name,
+                name_safe => name_safe,
+                description => metadata.description,
+                version => metadata.version,
+                author => metadata.author,
+                requires_python => metadata.requires_python,
+                license => metadata.license,
+                dependencies => metadata.dependencies,
+                dev_dependencies => metadata.dev_dependencies,
+                scripts => template_settings.scripts,
+                is_script => matches!(template, TemplateChoice::Script),
+                is_virtual,
+                with_readme,
+                build_system,
+                private,
+            },
+        )?;
+        fs::write(&toml, rv).context("failed to write pyproject.toml")?;
+    }
 
     if !is_virtual {
         let src_dir = dir.join("src");
-        if !imported_something && !src_dir.is_dir() {
+        if !imported_something && project_template.is_none() && !src_dir.is_dir() {
             let name = metadata.name.expect("project name");
             match (template, build_system) {
                 (TemplateChoice::Lib, BuildSystem::Maturin) => {
@@ -444,6 +526,12 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         }
     }
 
+    if cmd.fetch {
+        let version = PyProject::load(&toml)?.venv_python_version()?;
+        fetch(&version.into(), FetchOptions::with_output(output))
+            .context("error while fetching Python installation")?;
+    }
+
     echo!(
         if output,
         "{} Initialized {}project in {}",
@@ -679,6 +767,14 @@ fn get_setup_py_json<T: AsRef<Path>>(path: T, python: T) -> Result<Value, Error>
     }
 }
 
+/// Parses a `KEY=VALUE` template variable.
+fn parse_template_var(s: &str) -> Result<(String, String), Error> {
+    match s.split_once('=') {
+        Some((key, value)) => Ok((key.trim().to_string(), value.to_string())),
+        None => bail!("template variables must be given as KEY=VALUE"),
+    }
+}
+
 /// Import from requirements files.
 ///
 /// Unsupported as of monotrail-utils v0.0.1:
@@ -21,7 +21,7 @@ pub struct Args {
     #[arg(long)]
     include_dep: Vec<String>,
     /// Additional dependencies to install that are not declared by the main package.
-    #[arg(long)]
+    #[arg(long, visible_alias = "with")]
     extra_requirement: Vec<String>,
     /// Optionally the Python version to use.
     #[arg(short, long)]
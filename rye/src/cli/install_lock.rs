@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use console::style;
+use tempfile::tempdir;
+
+use crate::bootstrap::{ensure_self_venv, fetch, FetchOptions};
+use crate::config::Config;
+use crate::pyproject::{read_venv_marker, resolve_python_version_request, ExpandedSources};
+use crate::sources::py::PythonVersionRequest;
+use crate::sync::create_virtualenv;
+use crate::utils::{CommandOutput, IoPathContext};
+use crate::uv::{UvBuilder, UvWithVenv};
+
+/// Installs a lockfile into a virtualenv without a project.
+///
+/// This installs exactly the packages of the lockfile and removes all others.
+/// If the target folder is not a virtualenv yet, one is created.  This is
+/// useful to install an application in the runtime stage of a Docker image.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The lockfile to install.
+    lockfile: PathBuf,
+    /// The virtualenv to install into.
+    #[arg(long, value_name = "DIR")]
+    target: PathBuf,
+    /// The Python version to create the virtualenv with.
+    ///
+    /// Defaults to the default toolchain.  Ignored if the virtualenv exists.
+    #[arg(short, long)]
+    python: Option<PythonVersionRequest>,
+    /// Skip the local editable packages of the lockfile.
+    #[arg(long)]
+    no_install_project: bool,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    if !cmd.lockfile.is_file() {
+        bail!("lockfile '{}' does not exist", cmd.lockfile.display());
+    }
+    let lockfile = cmd
+        .lockfile
+        .canonicalize()
+        .path_context(&cmd.lockfile, "unable to resolve lockfile")?;
+    let workdir = lockfile
+        .parent()
+        .map(|x| x.to_path_buf())
+        .unwrap_or_default();
+    let target = std::env::current_dir()?.join(&cmd.target);
+
+    let self_venv =
+        ensure_self_venv(output).context("could not install because bootstrap failed")?;
+
+    let py_ver = if target.join("pyvenv.cfg").is_file() {
+        echo!(if output, "Reusing virtualenv in {}", style(target.display()).cyan());
+        match read_venv_marker(&target) {
+            Some(marker) => marker.python,
+            // the version is only used for the marker which foreign
+            // virtualenvs do not have.
+            None => resolve_python_version_request(Config::current().default_toolchain()?)?,
+        }
+    } else {
+        let request = match cmd.python {
+            Some(python) => python,
+            None => Config::current().default_toolchain()?,
+        };
+        let py_ver = fetch(&request, FetchOptions::with_output(output))
+            .context("failed fetching toolchain ahead of install")?;
+        echo!(
+            if output,
+            "Initializing new virtualenv in {}",
+            style(target.display()).cyan()
+        );
+        echo!(if output, "Python version: {}", style(&py_ver).cyan());
+        create_virtualenv(output, &self_venv, &py_ver, &target, "venv")
+            .context("failed creating virtualenv ahead of install")?;
+        py_ver
+    };
+
+    // local packages are referenced relative to the lockfile's folder and
+    // are typically not available where only the lockfile was copied to.
+    let tempdir = tempdir()?;
+    let lockfile = if cmd.no_install_project {
+        let contents =
+            fs::read_to_string(&lockfile).path_context(&lockfile, "unable to read lockfile")?;
+        let mut rv = String::new();
+        for line in contents.lines() {
+            if line.starts_with("-e file:") {
+                echo!(if verbose output, "Skipping local package {}", &line[3..]);
+                continue;
+            }
+            rv.push_str(line);
+            rv.push('\n');
+        }
+        let path = tempdir.path().join("requirements.lock");
+        fs::write(&path, rv).path_context(&path, "unable to write lockfile")?;
+        path
+    } else {
+        lockfile
+    };
+
+    echo!(if output, "Installing dependencies");
+    let uv = UvBuilder::new()
+        .with_output(output.quieter())
+        .with_workdir(&workdir)
+        .with_sources(ExpandedSources::from_sources(
+            &Config::current().sources()?,
+        )?)
+        .ensure_exists()?;
+    UvWithVenv::new(uv, &target, &py_ver)
+        .with_output(output)
+        .sync(&lockfile, &[])?;
+
+    echo!(if output, "Done!");
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use crate::pyproject::PyProject;
+use crate::sync::{sync, SyncOptions};
+use crate::utils::{get_venv_python_bin, CommandOutput};
+
+/// Manage the Jupyter kernel of the project.
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: SubCommand,
+}
+
+/// Registers the project's virtualenv as a Jupyter kernel.
+///
+/// The kernel is installed for the current user so that notebooks opened
+/// from any Jupyter installation can run in the project's virtualenv.  This
+/// requires `ipykernel` to be installed in the virtualenv.
+#[derive(Parser, Debug)]
+pub struct InstallCommand {
+    /// The name of the kernel (defaults to the project name).
+    #[arg(long)]
+    name: Option<String>,
+    /// The name of the kernel as shown by Jupyter.
+    #[arg(long)]
+    display_name: Option<String>,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Removes the Jupyter kernel of the project.
+#[derive(Parser, Debug)]
+pub struct UninstallCommand {
+    /// The name of the kernel (defaults to the project name).
+    #[arg(long)]
+    name: Option<String>,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+#[derive(Parser, Debug)]
+enum SubCommand {
+    Install(InstallCommand),
+    Uninstall(UninstallCommand),
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    match cmd.command {
+        SubCommand::Install(args) => install(args),
+        SubCommand::Uninstall(args) => uninstall(args),
+    }
+}
+
+fn install(cmd: InstallCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let name = kernel_name(&pyproject, cmd.name)?;
+    let display_name = cmd
+        .display_name
+        .unwrap_or_else(|| format!("{} (rye)", name));
+
+    // make sure we have the minimal virtualenv.
+    let mut options = SyncOptions::python_only().pyproject(cmd.pyproject);
+    options.output = output;
+    sync(options).context("failed to sync ahead of kernel install")?;
+
+    let python = get_venv_python_bin(&pyproject.venv_path());
+    let has_ipykernel = Command::new(&python)
+        .arg("-c")
+        .arg("import ipykernel")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("unable to run python")?
+        .success();
+    if !has_ipykernel {
+        bail!("ipykernel is not installed, add it with `rye add --dev ipykernel` and sync");
+    }
+
+    let status = Command::new(&python)
+        .arg("-m")
+        .arg("ipykernel")
+        .arg("install")
+        .arg("--user")
+        .arg("--name")
+        .arg(&name)
+        .arg("--display-name")
+        .arg(&display_name)
+        .stdout(if output == CommandOutput::Verbose {
+            Stdio::inherit()
+        } else {
+            Stdio::null()
+        })
+        .status()
+        .context("unable to run ipykernel")?;
+    if !status.success() {
+        bail!("failed to install kernel {}", name);
+    }
+    echo!(if output, "Installed Jupyter kernel {} ({})", name, display_name);
+
+    Ok(())
+}
+
+fn uninstall(cmd: UninstallCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let name = kernel_name(&pyproject, cmd.name)?;
+    let python = get_venv_python_bin(&pyproject.venv_path());
+    if !python.is_file() {
+        bail!("project has no virtualenv, run `rye sync` first");
+    }
+
+    let status = Command::new(&python)
+        .arg("-m")
+        .arg("jupyter_client.kernelspecapp")
+        .arg("remove")
+        .arg("-f")
+        .arg(&name)
+        .stdout(if output == CommandOutput::Verbose {
+            Stdio::inherit()
+        } else {
+            Stdio::null()
+        })
+        .status()
+        .context("unable to run jupyter_client")?;
+    if !status.success() {
+        bail!("failed to uninstall kernel {}", name);
+    }
+    echo!(if output, "Uninstalled Jupyter kernel {}", name);
+
+    Ok(())
+}
+
+/// Returns the kernel name, which defaults to the project name.
+fn kernel_name(pyproject: &PyProject, name: Option<String>) -> Result<String, Error> {
+    match name {
+        Some(name) => Ok(name),
+        None => pyproject.normalized_name(),
+    }
+}
@@ -1,24 +1,67 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::str::FromStr;
 
 use anyhow::{bail, Error};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use console::style;
+use pep440_rs::{Version, VersionSpecifiers};
+use serde::Serialize;
 
 use crate::bootstrap::ensure_self_venv;
 use crate::config::Config;
 use crate::consts::VENV_BIN;
-use crate::pyproject::PyProject;
+use crate::index::{self, ProjectPage};
+use crate::pyproject::{normalize_package_name, ExpandedSources, PyProject};
+use crate::sync::{list_installed_packages, InstalledPackage};
 use crate::utils::{get_venv_python_bin, CommandOutput};
 use crate::uv::{UvBuilder, UvWithVenv};
+use crate::yanked::yanked_releases_in_pages;
 
 /// Prints the currently installed packages.
+///
+/// By default the packages are printed like `pip freeze`.  In the table
+/// format packages are flagged as `direct` if the project declares them as a
+/// dependency, `dev-only` if only the dev lockfile has them, `editable` and
+/// `orphaned` if none of the lockfiles has them.  With `--outdated` installed
+/// releases that were yanked are flagged as `yanked`.
 #[derive(Parser, Debug)]
 pub struct Args {
+    /// Only list packages for which a newer version is available or whose release was yanked.
+    #[arg(long)]
+    pub(crate) outdated: bool,
+    /// Request another output format (defaults to `freeze`, or `table` with `--outdated`).
+    #[arg(long)]
+    pub(crate) format: Option<Format>,
     /// Use this pyproject.toml file
     #[arg(long, value_name = "PYPROJECT_TOML")]
     pub(crate) pyproject: Option<PathBuf>,
 }
 
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+pub(crate) enum Format {
+    /// The requirements format of `pip freeze`.
+    Freeze,
+    /// A table that flags direct, dev-only, editable and orphaned packages.
+    Table,
+    /// Machine readable list of the packages.
+    Json,
+}
+
+/// Output structure for list --format=json
+#[derive(Serialize)]
+struct ListPackage {
+    #[serde(flatten)]
+    package: InstalledPackage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest_version: Option<String>,
+    /// The reason the installed release was yanked, if it was.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    yanked: Option<String>,
+}
+
 pub fn execute(cmd: Args) -> Result<(), Error> {
     let project = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
     let python = get_venv_python_bin(&project.venv_path());
@@ -27,6 +70,102 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
     }
     let self_venv = ensure_self_venv(CommandOutput::Normal)?;
 
+    let format = cmd.format.unwrap_or(if cmd.outdated {
+        Format::Table
+    } else {
+        Format::Freeze
+    });
+    if format == Format::Freeze {
+        if cmd.outdated {
+            bail!("--outdated cannot be combined with --format=freeze");
+        }
+        return freeze(&project, &self_venv, &python);
+    }
+
+    let installed = list_installed_packages(&project)?;
+    let (latest_versions, yanked) = if cmd.outdated {
+        let sources = ExpandedSources::from_sources(&project.sources()?)?;
+        let names = installed
+            .iter()
+            .filter(|x| !x.editable)
+            .map(|x| normalize_package_name(&x.name))
+            .collect::<Vec<_>>();
+        let pages = index::fetch_project_pages(&names, &sources, CommandOutput::Normal);
+        let python_version = Version::from(project.venv_python_version()?);
+        (
+            find_latest_versions(&installed, &pages, &python_version),
+            find_yanked_packages(&installed, &pages),
+        )
+    } else {
+        (HashMap::new(), HashMap::new())
+    };
+    let packages = installed
+        .into_iter()
+        .filter_map(|package| {
+            let latest_version = latest_versions.get(&package.name).cloned();
+            let yanked = yanked.get(&package.name).cloned();
+            if cmd.outdated && latest_version.is_none() && yanked.is_none() {
+                return None;
+            }
+            Some(ListPackage {
+                package,
+                latest_version,
+                yanked,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if format == Format::Json {
+        echo!("{}", serde_json::to_string_pretty(&packages)?);
+        return Ok(());
+    }
+
+    let name_width = packages
+        .iter()
+        .map(|x| x.package.name.len())
+        .max()
+        .unwrap_or(0);
+    let version_width = packages
+        .iter()
+        .map(|x| x.package.version.len())
+        .max()
+        .unwrap_or(0);
+    for entry in packages {
+        let package = &entry.package;
+        let mut flags = Vec::new();
+        if package.direct {
+            flags.push("direct");
+        }
+        if package.dev_only {
+            flags.push("dev-only");
+        }
+        if package.editable {
+            flags.push("editable");
+        }
+        if package.orphaned {
+            flags.push("orphaned");
+        }
+        if entry.yanked.is_some() {
+            flags.push("yanked");
+        }
+        let mut line = format!(
+            "{:name_width$}  {:version_width$}",
+            package.name, package.version
+        );
+        if let Some(ref latest_version) = entry.latest_version {
+            line.push_str(&format!("  -> {}", style(latest_version).green()));
+        }
+        if !flags.is_empty() {
+            line.push_str(&format!("  {}", style(flags.join(", ")).dim()));
+        }
+        echo!("{}", line.trim_end());
+    }
+
+    Ok(())
+}
+
+/// Prints the installed packages like `pip freeze`.
+fn freeze(project: &PyProject, self_venv: &Path, python: &Path) -> Result<(), Error> {
     if Config::current().use_uv() {
         let uv = UvBuilder::new()
             .with_output(CommandOutput::Normal)
@@ -36,7 +175,7 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         } else {
             uv.venv(
                 &project.venv_path(),
-                &python,
+                python,
                 &project.venv_python_version()?,
                 None,
             )?
@@ -45,7 +184,7 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
     } else {
         let status = Command::new(self_venv.join(VENV_BIN).join("pip"))
             .arg("--python")
-            .arg(&python)
+            .arg(python)
             .arg("freeze")
             .env("PYTHONWARNINGS", "ignore")
             .env("PIP_DISABLE_PIP_VERSION_CHECK", "1")
@@ -58,3 +197,65 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Looks up which of the installed packages are yanked releases.
+///
+/// Maps the normalized names to the reason of the yank (which can be empty).
+fn find_yanked_packages(
+    installed: &[InstalledPackage],
+    pages: &HashMap<String, ProjectPage>,
+) -> HashMap<String, String> {
+    let versions = installed
+        .iter()
+        .filter(|x| !x.editable)
+        .filter_map(|x| Some((normalize_package_name(&x.name), x.version.parse().ok()?)))
+        .collect::<Vec<(String, Version)>>();
+    yanked_releases_in_pages(
+        versions.iter().map(|(name, version)| (name, version)),
+        pages,
+    )
+    .into_iter()
+    .map(|x| (x.name, x.reason.unwrap_or_default()))
+    .collect()
+}
+
+/// Looks up the latest versions of the outdated packages of the virtualenv.
+///
+/// Yanked releases and releases that do not support the Python version of the
+/// virtualenv are skipped, as are pre-releases unless the installed version is
+/// one.  The keys are the names of the installed packages.
+fn find_latest_versions(
+    installed: &[InstalledPackage],
+    pages: &HashMap<String, ProjectPage>,
+    python_version: &Version,
+) -> HashMap<String, String> {
+    let mut rv = HashMap::new();
+    for package in installed.iter().filter(|x| !x.editable) {
+        let (page, version) = match (
+            pages.get(&normalize_package_name(&package.name)),
+            package.version.parse::<Version>(),
+        ) {
+            (Some(page), Ok(version)) => (page, version),
+            _ => continue,
+        };
+        let latest = page
+            .files
+            .iter()
+            .filter(|file| file.yanked().is_none())
+            .filter(|file| {
+                file.requires_python
+                    .as_ref()
+                    .and_then(|x| VersionSpecifiers::from_str(x).ok())
+                    .map_or(true, |x| x.contains(python_version))
+            })
+            .filter_map(|file| file.version())
+            .filter(|x| version.any_prerelease() || !x.any_prerelease())
+            .max();
+        if let Some(latest) = latest {
+            if latest > version {
+                rv.insert(package.name.clone(), latest.to_string());
+            }
+        }
+    }
+    rv
+}
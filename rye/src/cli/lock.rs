@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use anyhow::Error;
 use clap::Parser;
 
-use crate::lock::LockOptions;
+use crate::lock::{parse_exclude_newer, parse_python_platform, LockOptions};
 use crate::sync::{sync, SyncMode, SyncOptions};
 use crate::utils::CommandOutput;
 
@@ -34,12 +34,30 @@ pub struct Args {
     /// Set to true to lock with sources in the lockfile.
     #[arg(long)]
     with_sources: bool,
+    /// Write the hashes of all packages into the lockfile.
+    #[arg(long)]
+    generate_hashes: bool,
+    /// Lock for another platform (for instance `linux/amd64` or `x86_64-unknown-linux-gnu`).
+    #[arg(long, alias = "platform", value_name = "PLATFORM", value_parser = parse_python_platform)]
+    python_platform: Option<String>,
+    /// Only consider packages uploaded before this date (or RFC 3339 timestamp).
+    #[arg(long, value_name = "DATE", value_parser = parse_exclude_newer)]
+    exclude_newer: Option<String>,
+    /// Resolve packages again that are locked to yanked releases.
+    #[arg(long)]
+    exclude_yanked: bool,
+    /// Show the full resolver output if the dependencies cannot be resolved.
+    #[arg(long)]
+    explain: bool,
     /// Reset prior lock options.
     #[arg(long)]
     reset: bool,
     /// Use this pyproject.toml file
     #[arg(long, value_name = "PYPROJECT_TOML")]
     pyproject: Option<PathBuf>,
+    /// Lock the named environment from `tool.rye.envs` instead of the default one.
+    #[arg(long, value_name = "NAME")]
+    env: Option<String>,
 }
 
 pub fn execute(cmd: Args) -> Result<(), Error> {
@@ -54,9 +72,16 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
             features: cmd.features,
             all_features: cmd.all_features,
             with_sources: cmd.with_sources,
+            generate_hashes: cmd.generate_hashes,
+            python_platform: cmd.python_platform,
+            exclude_newer: cmd.exclude_newer,
+            exclude_yanked: cmd.exclude_yanked,
+            explain: cmd.explain,
             reset: cmd.reset,
+            ..LockOptions::default()
         },
         pyproject: cmd.pyproject,
+        env: cmd.env,
         ..SyncOptions::default()
     })?;
     Ok(())
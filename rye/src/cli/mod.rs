@@ -1,39 +1,54 @@
-use std::env;
+use std::ffi::OsString;
 use std::path::PathBuf;
 
-use anyhow::{bail, Error};
-use clap::Parser;
+use anyhow::Error;
+use clap::{Arg, CommandFactory, FromArgMatches, Parser};
 
 mod add;
+mod bench;
 mod build;
+mod cache;
 mod config;
+mod entrypoint;
+mod env;
 mod fetch;
 mod fmt;
+mod generate;
 mod init;
 mod install;
+mod install_lock;
+mod kernel;
 mod lint;
 mod list;
 mod lock;
 mod make_req;
 mod pin;
+mod plugins;
 mod publish;
 mod remove;
 mod run;
 mod rye;
+mod scripts;
+mod shell;
 mod shim;
+mod shims;
 mod show;
 mod sync;
 mod test;
 mod toolchain;
 mod tools;
+mod typecheck;
 mod uninstall;
+mod verify_artifact;
 mod version;
+mod workspace;
 
 use git_testament::git_testament;
 
 use crate::bootstrap::{get_self_venv_status, SELF_PYTHON_TARGET_VERSION};
 use crate::config::Config;
 use crate::platform::symlinks_supported;
+use crate::plugins::{list_plugins, run_plugin, Plugin};
 use crate::pyproject::read_venv_marker;
 use crate::utils::IoPathContext;
 
@@ -56,39 +71,45 @@ struct Args {
 #[derive(Parser, Debug)]
 enum Command {
     Add(add::Args),
+    Bench(bench::Args),
     Build(build::Args),
+    Cache(cache::Args),
     Config(config::Args),
+    Entrypoint(entrypoint::Args),
+    Env(env::Args),
     Fetch(fetch::Args),
     #[command(alias = "format")]
     Fmt(fmt::Args),
+    Generate(generate::Args),
     Init(init::Args),
     Install(install::Args),
+    InstallLock(install_lock::Args),
+    Kernel(kernel::Args),
     Lock(lock::Args),
     #[command(alias = "check")]
     Lint(lint::Args),
     MakeReq(make_req::Args),
     Pin(pin::Args),
+    Plugins(plugins::Args),
     Publish(publish::Args),
     Remove(remove::Args),
     Run(run::Args),
+    Scripts(scripts::Args),
     Show(show::Args),
     Sync(sync::Args),
     Test(test::Args),
     Toolchain(toolchain::Args),
     Tools(tools::Args),
+    Typecheck(typecheck::Args),
     #[command(name = "self")]
     Rye(rye::Args),
     Uninstall(uninstall::Args),
+    VerifyArtifact(verify_artifact::Args),
     Version(version::Args),
+    Workspace(workspace::Args),
     List(list::Args),
-    #[command(hide = true)]
     Shell(shell::Args),
-}
-
-pub mod shell {
-    /// The shell command was removed.
-    #[derive(clap::Parser, Debug)]
-    pub struct Args {}
+    Shims(shims::Args),
 }
 
 pub fn execute() -> Result<(), Error> {
@@ -96,7 +117,7 @@ pub fn execute() -> Result<(), Error> {
     crate::platform::init()?;
     crate::config::load()?;
 
-    let args = env::args_os().collect::<Vec<_>>();
+    let args = std::env::args_os().collect::<Vec<_>>();
 
     // if we're shimmed, execute the shim.  This won't return.
     shim::execute_shim(&args)?;
@@ -106,14 +127,43 @@ pub fn execute() -> Result<(), Error> {
         return Ok(());
     }
 
-    let args = Args::try_parse()?;
+    // plugins are only discovered if the built-in commands do not match (as
+    // for unknown subcommands and help) so that they don't slow down the
+    // common invocations.
+    let (plugins, matches) = match Args::command().try_get_matches_from(&args) {
+        Ok(matches) => (Vec::new(), matches),
+        Err(_) => {
+            let plugins = discover_plugins();
+            let matches = command_with_plugins(&plugins).try_get_matches_from(&args)?;
+            (plugins, matches)
+        }
+    };
 
     // handle --env-file.  As this happens here this cannot influence `RYE_HOME` or
     // the behavior of the shims.
-    for env_file in &args.env_file {
+    for env_file in matches
+        .get_many::<PathBuf>("env_file")
+        .into_iter()
+        .flatten()
+    {
         dotenvy::from_path(env_file).path_context(env_file, "unable to load env file")?;
     }
 
+    // plugins are not known to the derived parser, dispatch them here.
+    if let Some((name, sub_matches)) = matches.subcommand() {
+        if let Some(plugin) = plugins.iter().find(|x| x.name == name) {
+            let plugin_args = sub_matches
+                .get_many::<OsString>("args")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>();
+            return run_plugin(plugin, &plugin_args);
+        }
+    }
+
+    let args = Args::from_arg_matches(&matches)?;
+
     let cmd = if args.version {
         return print_version();
     } else if let Some(cmd) = args.command {
@@ -124,41 +174,81 @@ pub fn execute() -> Result<(), Error> {
 
     match cmd {
         Command::Add(cmd) => add::execute(cmd),
+        Command::Bench(cmd) => bench::execute(cmd),
         Command::Build(cmd) => build::execute(cmd),
+        Command::Cache(cmd) => cache::execute(cmd),
         Command::Config(cmd) => config::execute(cmd),
+        Command::Entrypoint(cmd) => entrypoint::execute(cmd),
+        Command::Env(cmd) => env::execute(cmd),
         Command::Fetch(cmd) => fetch::execute(cmd),
         Command::Fmt(cmd) => fmt::execute(cmd),
+        Command::Generate(cmd) => generate::execute(cmd),
         Command::Init(cmd) => init::execute(cmd),
         Command::Install(cmd) => install::execute(cmd),
+        Command::InstallLock(cmd) => install_lock::execute(cmd),
+        Command::Kernel(cmd) => kernel::execute(cmd),
         Command::Lock(cmd) => lock::execute(cmd),
         Command::Lint(cmd) => lint::execute(cmd),
         Command::MakeReq(cmd) => make_req::execute(cmd),
         Command::Pin(cmd) => pin::execute(cmd),
+        Command::Plugins(cmd) => plugins::execute(cmd),
         Command::Publish(cmd) => publish::execute(cmd),
         Command::Remove(cmd) => remove::execute(cmd),
         Command::Run(cmd) => run::execute(cmd),
+        Command::Scripts(cmd) => scripts::execute(cmd),
         Command::Show(cmd) => show::execute(cmd),
         Command::Sync(cmd) => sync::execute(cmd),
         Command::Test(cmd) => test::execute(cmd),
         Command::Toolchain(cmd) => toolchain::execute(cmd),
         Command::Tools(cmd) => tools::execute(cmd),
+        Command::Typecheck(cmd) => typecheck::execute(cmd),
         Command::Rye(cmd) => rye::execute(cmd),
         Command::Uninstall(cmd) => uninstall::execute(cmd),
+        Command::VerifyArtifact(cmd) => verify_artifact::execute(cmd),
         Command::Version(cmd) => version::execute(cmd),
+        Command::Workspace(cmd) => workspace::execute(cmd),
         Command::List(cmd) => list::execute(cmd),
-        Command::Shell(..) => {
-            bail!(
-                "unknown command. The shell command was removed. Activate the virtualenv with '{}' instead.",
-                if cfg!(windows) {
-                    ".venv\\Scripts\\activate"
-                } else {
-                    ". .venv/bin/activate"
-                }
-            );
-        }
+        Command::Shell(cmd) => shell::execute(cmd),
+        Command::Shims(cmd) => shims::execute(cmd),
     }
 }
 
+/// Returns the command line interface including all plugins.
+pub fn command() -> clap::Command {
+    command_with_plugins(&discover_plugins())
+}
+
+/// Discovers the plugins that do not collide with a built-in command.
+fn discover_plugins() -> Vec<Plugin> {
+    let cmd = Args::command();
+    list_plugins()
+        .into_iter()
+        .filter(|plugin| {
+            plugin.name != "help"
+                && !cmd.get_subcommands().any(|x| {
+                    x.get_name() == plugin.name || x.get_all_aliases().any(|x| x == plugin.name)
+                })
+        })
+        .collect()
+}
+
+fn command_with_plugins(plugins: &[Plugin]) -> clap::Command {
+    plugins.iter().fold(Args::command(), |cmd, plugin| {
+        cmd.subcommand(
+            clap::Command::new(plugin.name.clone())
+                .about(plugin.about())
+                .disable_help_flag(true)
+                .arg(
+                    Arg::new("args")
+                        .num_args(0..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true)
+                        .value_parser(clap::value_parser!(OsString)),
+                ),
+        )
+    })
+}
+
 fn print_version() -> Result<(), Error> {
     echo!("rye {}", env!("CARGO_PKG_VERSION"));
     echo!("commit: {}", TESTAMENT.commit);
@@ -3,14 +3,18 @@ use std::fs;
 use std::path::PathBuf;
 
 use anyhow::Context;
-use anyhow::{anyhow, Error};
+use anyhow::{anyhow, bail, Error};
 use clap::Parser;
+use pep440_rs::Version;
 
+use crate::bootstrap::{fetch, FetchOptions};
 use crate::platform::get_pinnable_version;
 use crate::pyproject::DiscoveryUnsuccessful;
-use crate::pyproject::PyProject;
-use crate::sources::py::PythonVersionRequest;
-use crate::utils::IoPathContext;
+use crate::pyproject::{
+    latest_available_python_version, resolve_python_version_request, PyProject,
+};
+use crate::sources::py::{PythonVersion, PythonVersionRequest};
+use crate::utils::{CommandOutput, IoPathContext};
 
 /// Pins a Python version to this project.
 ///
@@ -18,6 +22,10 @@ use crate::utils::IoPathContext;
 /// Additionally it will update `requires-python` in the `pyproject.toml`
 /// if it's lower than the current version.  This can be disabled by passing
 /// `--no-update-requires-python`.
+///
+/// At the root of a workspace the version has to satisfy the
+/// `requires-python` of all members.  With `--strict` this also applies to
+/// the project's own `requires-python` if it is not updated.
 #[derive(Parser, Debug)]
 pub struct Args {
     /// The version of Python to pin.
@@ -25,9 +33,21 @@ pub struct Args {
     /// Issue a relaxed pin
     #[arg(long)]
     relaxed: bool,
+    /// Fail if the version is outside of the project's requires-python.
+    #[arg(long)]
+    strict: bool,
     /// Prevent updating requires-python in the pyproject.toml.
     #[arg(long)]
     no_update_requires_python: bool,
+    /// Always set requires-python to the pinned version, even if that raises it.
+    #[arg(long, conflicts_with = "no_update_requires_python")]
+    sync_requires_python: bool,
+    /// Also pin all members when run at the root of a workspace.
+    #[arg(long)]
+    propagate: bool,
+    /// Fetch the pinned toolchain right away rather than on the next sync.
+    #[arg(long)]
+    fetch: bool,
     /// Use this pyproject.toml file
     #[arg(long, value_name = "PYPROJECT_TOML")]
     pyproject: Option<PathBuf>,
@@ -40,6 +60,7 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         .with_context(|| format!("'{}' is not a valid version", cmd.version))?;
     let to_write = get_pinnable_version(&req, cmd.relaxed)
         .ok_or_else(|| anyhow!("unsupported/unknown version for this platform"))?;
+    let new_version = to_write.parse::<PythonVersionRequest>()?;
 
     let pyproject = match PyProject::load_or_discover(cmd.pyproject.as_deref()) {
         Ok(proj) => Some(proj),
@@ -53,26 +74,140 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         }
     };
 
-    let version_file = match pyproject {
-        Some(ref proj) => proj.root_path().join(".python-version"),
-        None => env::current_dir()?.join(".python-version"),
-    };
-    fs::write(&version_file, format!("{}\n", to_write))
-        .path_context(&version_file, "failed to write .python-version file")?;
-
-    if !cmd.no_update_requires_python {
-        if let Some(mut pyproject_toml) = pyproject {
-            let new_version = to_write.parse::<PythonVersionRequest>()?;
-            if let Some(curr_version) = pyproject_toml.target_python_version() {
-                if new_version < curr_version {
-                    pyproject_toml.set_target_python_version(&new_version);
-                    pyproject_toml.save()?;
+    // the projects that are pinned: the project itself and with --propagate
+    // all other members of the workspace.
+    let mut targets = Vec::new();
+    if let Some(proj) = pyproject {
+        if cmd.propagate {
+            let workspace = match proj.workspace() {
+                Some(workspace) if proj.is_workspace_root() => workspace.clone(),
+                _ => bail!("--propagate can only be used at the root of a workspace"),
+            };
+            for member in workspace.iter_projects() {
+                let member = member?;
+                if member.root_path() != proj.root_path() {
+                    targets.push(member);
                 }
             }
         }
+        targets.insert(0, proj);
+    } else if cmd.propagate {
+        bail!("--propagate can only be used at the root of a workspace");
     }
 
-    echo!("pinned {} in {}", to_write, version_file.display());
+    let version = PythonVersion::try_from(new_version.clone())
+        .ok()
+        .or_else(|| latest_available_python_version(&new_version));
 
+    // the workspace root pins the Python version of the shared virtualenv.
+    if let Some(proj) = targets.first() {
+        if let Some(workspace) = proj.workspace().filter(|_| proj.is_workspace_root()) {
+            if let Some(ref version) = version {
+                // members whose requires-python is updated below accept the pin.
+                let updated = targets
+                    .iter()
+                    .filter(|x| updates_requires_python(&cmd, x, &new_version))
+                    .filter_map(|x| x.normalized_name().ok())
+                    .collect::<Vec<_>>();
+                let unsupported = workspace
+                    .members_requires_python()?
+                    .into_iter()
+                    .filter(|(name, _)| !updated.contains(name))
+                    .filter(|(_, specifiers)| !specifiers.contains(&Version::from(version.clone())))
+                    .map(|(name, specifiers)| format!("{} requires Python {}", name, specifiers))
+                    .collect::<Vec<_>>();
+                if !unsupported.is_empty() {
+                    bail!(
+                        "cannot pin Python {} as it is not supported by all workspace \
+                         members:\n  {}",
+                        version,
+                        unsupported.join("\n  ")
+                    );
+                }
+            }
+        }
+    }
+
+    // unless requires-python is updated, a strict pin has to satisfy it.
+    if cmd.strict {
+        if let Some(ref version) = version {
+            for proj in &targets {
+                if updates_requires_python(&cmd, proj, &new_version) {
+                    continue;
+                }
+                if let Some(specifiers) = proj.requires_python() {
+                    if !specifiers.contains(&Version::from(version.clone())) {
+                        bail!(
+                            "cannot pin Python {} as {} requires Python {}",
+                            version,
+                            proj.normalized_name()?,
+                            specifiers
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    if targets.is_empty() {
+        let version_file = env::current_dir()?.join(".python-version");
+        fs::write(&version_file, format!("{}\n", to_write))
+            .path_context(&version_file, "failed to write .python-version file")?;
+        echo!("pinned {} in {}", to_write, version_file.display());
+        if cmd.fetch {
+            fetch_pinned(resolve_python_version_request(new_version)?)?;
+        }
+        return Ok(());
+    }
+
+    let project_file = targets[0].toml_path().into_owned();
+    for mut pyproject_toml in targets {
+        let version_file = pyproject_toml.root_path().join(".python-version");
+        fs::write(&version_file, format!("{}\n", to_write))
+            .path_context(&version_file, "failed to write .python-version file")?;
+
+        if updates_requires_python(&cmd, &pyproject_toml, &new_version) {
+            pyproject_toml.set_target_python_version(&new_version);
+            pyproject_toml.save()?;
+        }
+
+        echo!("pinned {} in {}", to_write, version_file.display());
+    }
+
+    if cmd.fetch {
+        // the project is loaded again so that the version it ends up using
+        // (eg: the latest one matching a relaxed pin) is fetched.
+        fetch_pinned(PyProject::load(&project_file)?.venv_python_version()?)?;
+    }
+
+    Ok(())
+}
+
+/// Fetches the toolchain of a pin so that the next sync does not have to.
+fn fetch_pinned(version: PythonVersion) -> Result<(), Error> {
+    fetch(
+        &version.into(),
+        FetchOptions::with_output(CommandOutput::Normal),
+    )
+    .context("error while fetching Python installation")?;
     Ok(())
 }
+
+/// Checks if pinning a version updates the `requires-python` of a project.
+fn updates_requires_python(
+    cmd: &Args,
+    project: &PyProject,
+    new_version: &PythonVersionRequest,
+) -> bool {
+    if cmd.no_update_requires_python {
+        return false;
+    }
+    if cmd.sync_requires_python {
+        // virtual projects do not need to declare a requires-python.
+        return !project.is_virtual() || project.requires_python().is_some();
+    }
+    project
+        .requires_python_lower_bound()
+        .or_else(|| project.target_python_version())
+        .map_or(false, |curr_version| *new_version < curr_version)
+}
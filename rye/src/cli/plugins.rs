@@ -0,0 +1,103 @@
+use anyhow::{Context, Error};
+use clap::Parser;
+use console::style;
+use pep508_rs::Requirement;
+
+use crate::plugins::{install, list_plugins, uninstall, PluginSource};
+use crate::utils::CommandOutput;
+
+/// Manage plugins that extend rye with custom commands.
+///
+/// Executables named `rye-<name>` on the `PATH` and packages in the plugins
+/// virtualenv that register a `rye.plugins` entry point are available as
+/// `rye <name>`.
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: SubCommand,
+}
+
+/// List all discovered plugins.
+#[derive(Parser, Debug)]
+pub struct ListCommand {
+    /// Show where the plugins come from.
+    #[arg(short = 'd', long)]
+    details: bool,
+}
+
+/// Install a package with plugins into the plugins virtualenv.
+#[derive(Parser, Debug)]
+pub struct InstallCommand {
+    /// The package to install.
+    requirement: String,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Uninstall a package from the plugins virtualenv.
+#[derive(Parser, Debug)]
+pub struct UninstallCommand {
+    /// The package to uninstall.
+    name: String,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+#[derive(Parser, Debug)]
+enum SubCommand {
+    List(ListCommand),
+    Install(InstallCommand),
+    Uninstall(UninstallCommand),
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    match cmd.command {
+        SubCommand::List(args) => list(args),
+        SubCommand::Install(args) => {
+            let output = CommandOutput::from_quiet_and_verbose(args.quiet, args.verbose);
+            let requirement = args
+                .requirement
+                .parse::<Requirement>()
+                .with_context(|| format!("failed to parse requirement '{}'", args.requirement))?;
+            install(&requirement, output)
+        }
+        SubCommand::Uninstall(args) => {
+            let output = CommandOutput::from_quiet_and_verbose(args.quiet, args.verbose);
+            uninstall(&args.name, output)
+        }
+    }
+}
+
+fn list(cmd: ListCommand) -> Result<(), Error> {
+    for plugin in list_plugins() {
+        if !cmd.details {
+            echo!("{}", style(&plugin.name).cyan());
+            continue;
+        }
+        match plugin.source {
+            PluginSource::Executable(ref path) => {
+                echo!("{} ({})", style(&plugin.name).cyan(), path.display());
+            }
+            PluginSource::EntryPoint {
+                ref distribution,
+                ref target,
+            } => {
+                echo!(
+                    "{} ({}, {})",
+                    style(&plugin.name).cyan(),
+                    distribution,
+                    target
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,5 +1,7 @@
+use std::env;
+use std::fs;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 use age::{
@@ -7,20 +9,34 @@ use age::{
     Decryptor, Encryptor,
 };
 use anyhow::{bail, Context, Error};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use console::style;
+use globset::Glob;
+use serde::{Deserialize, Serialize};
 use toml_edit::{Item, Table};
 use url::Url;
 
+use crate::attestations::{attestation_path, sign_artifacts, ATTESTATION_SUFFIX};
 use crate::bootstrap::ensure_self_venv;
-use crate::platform::{get_credentials, write_credentials};
-use crate::pyproject::PyProject;
-use crate::utils::{escape_string, get_venv_python_bin, tui_theme, CommandOutput};
+use crate::config::Config;
+use crate::credentials::get_source_credentials;
+use crate::hooks::run_hook;
+use crate::platform::{get_credentials, get_credentials_filepath, write_credentials};
+use crate::pyproject::{normalize_package_name, PyProject};
+use crate::upload::{upload_files, UploadOptions};
+use crate::utils::{escape_string, get_venv_python_bin, tui_theme, CommandOutput, IoPathContext};
 
 /// Publish packages to a package repository.
 #[derive(Parser, Debug)]
 pub struct Args {
     /// The distribution files to upload to the repository (defaults to <workspace-root>/dist/*).
     dist: Option<Vec<PathBuf>>,
+    /// Only upload the distribution files whose names match one of these globs.
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+    /// Do not upload the distribution files whose names match one of these globs.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
     /// The repository to publish to.
     #[arg(short, long, default_value = "pypi")]
     repository: String,
@@ -39,15 +55,24 @@ pub struct Args {
     /// GPG identity used to sign files.
     #[arg(short, long)]
     identity: Option<String>,
+    /// Create and upload PEP 740 attestations for the files.
+    #[arg(long)]
+    attestations: bool,
     /// Path to alternate CA bundle.
     #[arg(long)]
     cert: Option<PathBuf>,
     /// Skip files that have already been published (only applies to repositories supporting this feature)
     #[arg(long)]
     skip_existing: bool,
+    /// Use PyPI Trusted Publishing from GitHub Actions or GitLab CI.
+    #[arg(long, value_name = "MODE", default_value = "auto")]
+    trusted_publishing: TrustedPublishing,
     /// Skip prompts.
     #[arg(short, long)]
     yes: bool,
+    /// Show which files would be uploaded where without uploading them.
+    #[arg(long)]
+    dry_run: bool,
     /// Enables verbose diagnostics.
     #[arg(short, long)]
     verbose: bool,
@@ -56,9 +81,67 @@ pub struct Args {
     quiet: bool,
 }
 
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+enum TrustedPublishing {
+    /// Always use trusted publishing and fail if that is not possible.
+    Always,
+    /// Use trusted publishing when running in a supported CI without credentials.
+    Auto,
+    /// Never use trusted publishing.
+    Never,
+}
+
+/// A CI environment that can hand out OIDC tokens.
+enum OidcProvider {
+    GitHubActions {
+        request_url: String,
+        request_token: String,
+    },
+    GitLab {
+        id_token: String,
+    },
+}
+
+impl OidcProvider {
+    fn name(&self) -> &'static str {
+        match self {
+            OidcProvider::GitHubActions { .. } => "GitHub Actions",
+            OidcProvider::GitLab { .. } => "GitLab CI",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AudienceResponse {
+    audience: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubTokenResponse {
+    value: String,
+}
+
+#[derive(Serialize)]
+struct MintTokenRequest<'a> {
+    token: &'a str,
+}
+
+#[derive(Deserialize)]
+struct MintTokenResponse {
+    token: Option<String>,
+    message: Option<String>,
+    #[serde(default)]
+    errors: Vec<MintTokenError>,
+}
+
+#[derive(Deserialize)]
+struct MintTokenError {
+    description: String,
+}
+
 pub fn execute(cmd: Args) -> Result<(), Error> {
     let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
-    let venv = ensure_self_venv(output)?;
 
     // Get the files to publish.
     let files = match cmd.dist {
@@ -68,14 +151,45 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
             if project.is_virtual() {
                 bail!("virtual packages cannot be published");
             }
-            vec![project.workspace_path().join("dist").join("*")]
+            let dist = project.workspace_path().join("dist");
+            match project.vcs_version()? {
+                Some(version) => {
+                    if version.local.is_some() {
+                        bail!(
+                            "version {} is not a tagged release, tag the commit before publishing",
+                            version
+                        );
+                    }
+                    // only publish the builds of the current version, not older ones
+                    let name = project.normalized_name()?.replace('-', "_");
+                    vec![dist.join(format!("{}-{}[-.]*", name, version))]
+                }
+                None => vec![dist.join("*")],
+            }
         }
     };
+    let files = select_dist_files(expand_dist_files(&files)?, &cmd.include, &cmd.exclude)?;
+    if files.is_empty() {
+        bail!("no distribution files found to publish");
+    }
+
+    let repositories = match PyProject::discover() {
+        Ok(project) => {
+            if !cmd.dry_run {
+                run_hook(&project, "pre-publish", output)?;
+            }
+            project.repositories()?
+        }
+        Err(_) => Config::current().repositories()?,
+    };
+    let configured_repository = repositories.into_iter().find(|x| x.name == cmd.repository);
 
     // a. Get token from arguments and offer encryption, then store in credentials file.
     // b. Get token from ~/.rye/credentials keyed by provided repository and provide decryption option.
     // c. Otherwise prompt for token and provide encryption option, storing the result in credentials.
+    // Credentials stored in the keyring via `rye config credentials set` take precedence over b.
     let repository = &cmd.repository;
+    let keyring_credentials = get_source_credentials(repository)?;
     let mut credentials = get_credentials()?;
     credentials
         .entry(repository)
@@ -83,6 +197,9 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
 
     let repository_url = match cmd.repository_url {
         Some(url) => url,
+        None if configured_repository.is_some() => {
+            configured_repository.as_ref().unwrap().expand_url()?
+        }
         None => {
             let default_repository_url = Url::parse("https://upload.pypi.org/legacy/")?;
             credentials
@@ -101,54 +218,201 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         bail!("invalid pypi url {} (use -h for help)", repository_url);
     }
 
-    let username = match cmd.username {
-        Some(username) => username,
-        None => credentials
-            .get(repository)
-            .and_then(|table| table.get("username"))
-            .map(|username| username.to_string())
-            .map(escape_string)
-            .unwrap_or("__token__".to_string()),
+    let trusted_provider = match cmd.trusted_publishing {
+        TrustedPublishing::Never => None,
+        // explicitly provided credentials always win in auto mode.
+        TrustedPublishing::Auto
+            if cmd.token.is_some()
+                || cmd.username.is_some()
+                || configured_repository
+                    .as_ref()
+                    .map_or(false, |x| x.password_env.is_some()) =>
+        {
+            None
+        }
+        TrustedPublishing::Auto => detect_oidc_provider(),
+        TrustedPublishing::Always => Some(detect_oidc_provider().context(
+            "trusted publishing requires GitHub Actions (with `id-token: write` \
+             permission) or GitLab CI (with a PYPI_ID_TOKEN id token)",
+        )?),
     };
 
-    let token = if let Some(token) = cmd.token {
-        let secret = Secret::new(token);
-        let maybe_encrypted = maybe_encrypt(&secret, cmd.yes)?;
-        let maybe_encoded = maybe_encode(&secret, &maybe_encrypted);
-        credentials[repository]["token"] = Item::Value(maybe_encoded.expose_secret().into());
-
-        secret
-    } else if let Some(token) = credentials
-        .get(repository)
-        .and_then(|table| table.get("token"))
-        .map(|token| token.to_string())
-        .map(escape_string)
-    {
-        let secret = Secret::new(token);
+    let cert = cmd
+        .cert
+        .or_else(|| configured_repository.as_ref()?.cert.clone());
+    let skip_existing = cmd.skip_existing
+        || configured_repository
+            .as_ref()
+            .map_or(false, |x| x.skip_existing);
+
+    if cmd.dry_run {
+        let credentials_source = if let Some(ref provider) = trusted_provider {
+            format!("trusted publishing ({})", provider.name())
+        } else if cmd.token.is_some() {
+            "--token argument".to_string()
+        } else if let Some(var) = configured_repository
+            .as_ref()
+            .and_then(|x| x.password_env.as_deref())
+        {
+            format!("environment variable {}", var)
+        } else if keyring_credentials.is_some() {
+            "keyring".to_string()
+        } else if credentials
+            .get(repository)
+            .and_then(|table| table.get("token"))
+            .is_some()
+        {
+            format!("credentials file {}", get_credentials_filepath()?.display())
+        } else {
+            "none, you would be prompted for a token".to_string()
+        };
+        echo!(
+            "Would publish to {} ({})",
+            style(repository).cyan(),
+            repository_url
+        );
+        echo!("Credentials: {}", credentials_source);
+        for file in &files {
+            let filename = file.file_name().unwrap_or_default().to_string_lossy();
+            if !skip_existing {
+                echo!("  {}", filename);
+                continue;
+            }
+            match is_published(&repository_url, &filename) {
+                Ok(true) => echo!("  {} (already published, would skip)", filename),
+                Ok(false) => echo!("  {}", filename),
+                Err(err) => echo!("  {} (unable to check if published: {})", filename, err),
+            }
+        }
+        return Ok(());
+    }
 
-        maybe_decrypt(&secret, cmd.yes)?
+    let trusted_token = match trusted_provider {
+        Some(provider) => match mint_trusted_publishing_token(&repository_url, &provider) {
+            Ok(token) => Some(token),
+            Err(err) if cmd.trusted_publishing == TrustedPublishing::Auto => {
+                warn!(
+                    "trusted publishing failed, falling back to credentials: {}",
+                    err
+                );
+                None
+            }
+            Err(err) => return Err(err),
+        },
+        None => None,
+    };
+
+    // short-lived tokens from trusted publishing are never stored.
+    let (username, token) = if let Some(token) = trusted_token {
+        echo!(if output, "Using trusted publishing");
+        ("__token__".to_string(), token)
     } else {
-        echo!("No access token found, generate one at: https://pypi.org/manage/account/token/");
-        let token = if !cmd.yes {
-            prompt_for_token()?
+        let username = match cmd
+            .username
+            .or_else(|| configured_repository.as_ref()?.username.clone())
+        {
+            Some(username) => username,
+            None => keyring_credentials
+                .as_ref()
+                .and_then(|(username, _)| username.clone())
+                .or_else(|| {
+                    credentials
+                        .get(repository)
+                        .and_then(|table| table.get("username"))
+                        .map(|username| username.to_string())
+                        .map(escape_string)
+                })
+                .unwrap_or("__token__".to_string()),
+        };
+
+        let token = if let Some(token) = cmd.token {
+            let secret = Secret::new(token);
+            let maybe_encrypted = maybe_encrypt(&secret, cmd.yes)?;
+            let maybe_encoded = maybe_encode(&secret, &maybe_encrypted);
+            credentials[repository]["token"] = Item::Value(maybe_encoded.expose_secret().into());
+
+            secret
+        } else if let Some(var) = configured_repository
+            .as_ref()
+            .and_then(|x| x.password_env.as_deref())
+        {
+            // secrets from the environment are never stored.
+            Secret::new(
+                env::var(var)
+                    .with_context(|| format!("environment variable {} is not set", var))?,
+            )
+        } else if let Some((_, password)) = keyring_credentials {
+            Secret::new(password)
+        } else if let Some(token) = credentials
+            .get(repository)
+            .and_then(|table| table.get("token"))
+            .map(|token| token.to_string())
+            .map(escape_string)
+        {
+            let secret = Secret::new(token);
+
+            maybe_decrypt(&secret, cmd.yes)?
         } else {
-            "".to_string()
+            echo!("No access token found, generate one at: https://pypi.org/manage/account/token/");
+            let token = if !cmd.yes {
+                prompt_for_token()?
+            } else {
+                "".to_string()
+            };
+            if token.is_empty() {
+                bail!("an access token is required")
+            }
+            let secret = Secret::new(token);
+            let maybe_encrypted = maybe_encrypt(&secret, cmd.yes)?;
+            let maybe_encoded = maybe_encode(&secret, &maybe_encrypted);
+            credentials[repository]["token"] = Item::Value(maybe_encoded.expose_secret().into());
+
+            secret
         };
-        if token.is_empty() {
-            bail!("an access token is required")
-        }
-        let secret = Secret::new(token);
-        let maybe_encrypted = maybe_encrypt(&secret, cmd.yes)?;
-        let maybe_encoded = maybe_encode(&secret, &maybe_encrypted);
-        credentials[repository]["token"] = Item::Value(maybe_encoded.expose_secret().into());
 
-        secret
+        credentials[repository]["repository-url"] = Item::Value(repository_url.to_string().into());
+        credentials[repository]["username"] = Item::Value(username.clone().into());
+        write_credentials(&credentials)?;
+
+        (username, token)
     };
 
-    credentials[repository]["repository-url"] = Item::Value(repository_url.to_string().into());
-    credentials[repository]["username"] = Item::Value(username.clone().into());
-    write_credentials(&credentials)?;
+    // twine cannot send extra headers, so those repositories are uploaded to
+    // by rye itself.
+    let headers = configured_repository
+        .as_ref()
+        .map(|x| x.expand_headers())
+        .unwrap_or_default();
+    if !headers.is_empty() {
+        if cmd.sign || cmd.attestations {
+            bail!("signing is not supported for repositories with custom headers");
+        }
+        return upload_files(
+            &repository_url,
+            &files,
+            &UploadOptions {
+                username: &username,
+                password: token.expose_secret(),
+                headers: &headers,
+                cert: cert.as_deref(),
+                skip_existing,
+            },
+            output,
+        );
+    }
+
+    let files = if cmd.attestations {
+        sign_artifacts(&files, output)?;
+        let attestations = files
+            .iter()
+            .map(|x| attestation_path(x))
+            .collect::<Vec<_>>();
+        files.into_iter().chain(attestations).collect()
+    } else {
+        files
+    };
 
+    let venv = ensure_self_venv(output)?;
     let mut publish_cmd = Command::new(get_venv_python_bin(&venv));
     publish_cmd
         .arg("-mtwine")
@@ -157,20 +421,23 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         .args(files)
         .arg("--username")
         .arg(username)
-        .arg("--password")
-        .arg(token.expose_secret())
         .arg("--repository-url")
-        .arg(repository_url.to_string());
+        .arg(repository_url.to_string())
+        // pass the secret via the environment so it does not show up in process listings
+        .env("TWINE_PASSWORD", token.expose_secret());
     if cmd.sign {
         publish_cmd.arg("--sign");
     }
     if let Some(identity) = cmd.identity {
         publish_cmd.arg("--identity").arg(identity);
     }
-    if let Some(cert) = cmd.cert {
+    if cmd.attestations {
+        publish_cmd.arg("--attestations");
+    }
+    if let Some(cert) = cert {
         publish_cmd.arg("--cert").arg(cert);
     }
-    if cmd.skip_existing {
+    if skip_existing {
         publish_cmd.arg("--skip-existing");
     }
 
@@ -187,6 +454,229 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
     Ok(())
 }
 
+/// Detects if rye runs in a CI environment that supports trusted publishing.
+fn detect_oidc_provider() -> Option<OidcProvider> {
+    if let (Ok(request_url), Ok(request_token)) = (
+        env::var("ACTIONS_ID_TOKEN_REQUEST_URL"),
+        env::var("ACTIONS_ID_TOKEN_REQUEST_TOKEN"),
+    ) {
+        return Some(OidcProvider::GitHubActions {
+            request_url,
+            request_token,
+        });
+    }
+    if env::var_os("GITLAB_CI").is_some() {
+        if let Ok(id_token) = env::var("PYPI_ID_TOKEN") {
+            return Some(OidcProvider::GitLab { id_token });
+        }
+    }
+    None
+}
+
+/// Exchanges the OIDC token of the CI for a short-lived upload token.
+///
+/// See <https://docs.pypi.org/trusted-publishers/using-a-publisher/>.
+fn mint_trusted_publishing_token(
+    repository_url: &Url,
+    provider: &OidcProvider,
+) -> Result<Secret<String>, Error> {
+    // the upload endpoint of PyPI lives on a different host than the API.
+    let host = repository_url
+        .host_str()
+        .context("repository url has no host")?;
+    let index = format!(
+        "{}://{}",
+        repository_url.scheme(),
+        host.strip_prefix("upload.").unwrap_or(host)
+    );
+
+    let (status, body) = http_request(&format!("{}/_/oidc/audience", index), None, None)?;
+    if status != 200 {
+        bail!("{} does not support trusted publishing", index);
+    }
+    let audience = serde_json::from_slice::<AudienceResponse>(&body)
+        .context("invalid audience response")?
+        .audience;
+
+    let id_token = match provider {
+        OidcProvider::GitHubActions {
+            request_url,
+            request_token,
+        } => {
+            let mut url = Url::parse(request_url).context("invalid OIDC token request url")?;
+            url.query_pairs_mut().append_pair("audience", &audience);
+            let (status, body) = http_request(url.as_str(), Some(request_token), None)?;
+            if status != 200 {
+                bail!(
+                    "unable to request OIDC token from GitHub (status {})",
+                    status
+                );
+            }
+            serde_json::from_slice::<GitHubTokenResponse>(&body)
+                .context("invalid OIDC token response")?
+                .value
+        }
+        OidcProvider::GitLab { id_token } => id_token.clone(),
+    };
+
+    let request = serde_json::to_vec(&MintTokenRequest { token: &id_token })?;
+    let (status, body) = http_request(
+        &format!("{}/_/oidc/mint-token", index),
+        None,
+        Some(&request),
+    )?;
+    let response = serde_json::from_slice::<MintTokenResponse>(&body)
+        .with_context(|| format!("invalid mint-token response (status {})", status))?;
+    match response.token {
+        Some(token) if status == 200 => Ok(Secret::new(token)),
+        _ => {
+            let mut reasons = response
+                .errors
+                .into_iter()
+                .map(|x| x.description)
+                .collect::<Vec<_>>();
+            if reasons.is_empty() {
+                reasons.extend(response.message);
+            }
+            bail!("unable to mint upload token: {}", reasons.join("; "))
+        }
+    }
+}
+
+/// Performs a GET (or a JSON POST if a body is given) and returns the
+/// status code with the response body.
+fn http_request(
+    url: &str,
+    bearer: Option<&str>,
+    body: Option<&[u8]>,
+) -> Result<(u32, Vec<u8>), Error> {
+    let mut handle = curl::easy::Easy::new();
+    handle.url(url)?;
+    if let Some(proxy) = Config::current().https_proxy_url() {
+        handle.proxy(&proxy)?;
+    }
+    #[cfg(windows)]
+    {
+        handle.ssl_options(curl::easy::SslOpt::new().no_revoke(true))?;
+    }
+
+    let mut headers = curl::easy::List::new();
+    if let Some(bearer) = bearer {
+        headers.append(&format!("Authorization: bearer {}", bearer))?;
+    }
+    if let Some(body) = body {
+        headers.append("Content-Type: application/json")?;
+        handle.post(true)?;
+        handle.post_fields_copy(body)?;
+    }
+    handle.http_headers(headers)?;
+
+    let mut response = Vec::new();
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|data| {
+            response.extend_from_slice(data);
+            Ok(data.len())
+        })?;
+        transfer
+            .perform()
+            .with_context(|| format!("request to {} failed", url))?;
+    }
+    Ok((handle.response_code()?, response))
+}
+
+/// Expands glob patterns in the file names of the given paths.
+///
+/// Signatures and attestations are skipped as they are not distributions.
+fn expand_dist_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for path in paths {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !file_name.contains(['*', '?', '[']) {
+            files.push(path.clone());
+            continue;
+        }
+        let matcher = Glob::new(&file_name)?.compile_matcher();
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut matches = fs::read_dir(dir)
+            .path_context(dir, "unable to enumerate distribution files")?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                path.is_file()
+                    && matcher.is_match(&*name)
+                    && !name.ends_with(".asc")
+                    && !name.ends_with(ATTESTATION_SUFFIX)
+            })
+            .collect::<Vec<_>>();
+        matches.sort();
+        files.extend(matches);
+    }
+    Ok(files)
+}
+
+/// Filters distribution files by globs matched against their file names.
+fn select_dist_files(
+    files: Vec<PathBuf>,
+    include: &[String],
+    exclude: &[String],
+) -> Result<Vec<PathBuf>, Error> {
+    let compile = |patterns: &[String]| {
+        patterns
+            .iter()
+            .map(|x| Ok(Glob::new(x)?.compile_matcher()))
+            .collect::<Result<Vec<_>, Error>>()
+    };
+    let include = compile(include)?;
+    let exclude = compile(exclude)?;
+    Ok(files
+        .into_iter()
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            (include.is_empty() || include.iter().any(|x| x.is_match(&*name)))
+                && !exclude.iter().any(|x| x.is_match(&*name))
+        })
+        .collect())
+}
+
+/// Checks if a distribution file is already listed on the repository's index.
+///
+/// The simple index is expected next to the upload url, except for PyPI and
+/// TestPyPI which serve it from a different host.
+fn is_published(repository_url: &Url, filename: &str) -> Result<bool, Error> {
+    let name = match filename.strip_suffix(".whl") {
+        Some(stem) => stem.split('-').next(),
+        None => filename
+            .strip_suffix(".tar.gz")
+            .or_else(|| filename.strip_suffix(".zip"))
+            .and_then(|stem| Some(stem.rsplit_once('-')?.0)),
+    }
+    .context("not a distribution file name")?;
+
+    let index = match repository_url.host_str() {
+        Some(host) if host.starts_with("upload.") => Url::parse(&format!(
+            "{}://{}/simple/",
+            repository_url.scheme(),
+            host.trim_start_matches("upload.")
+        ))?,
+        _ => {
+            let mut url = repository_url.clone();
+            if !url.path().ends_with('/') {
+                url.set_path(&format!("{}/", url.path()));
+            }
+            url.join("simple/")?
+        }
+    };
+    let url = index.join(&format!("{}/", normalize_package_name(name)))?;
+    let (status, body) = http_request(url.as_str(), None, None)?;
+    match status {
+        200 => Ok(String::from_utf8_lossy(&body).contains(filename)),
+        404 => Ok(false),
+        _ => bail!("{} responded with status {}", url, status),
+    }
+}
+
 fn prompt_for_token() -> Result<String, Error> {
     eprint!("Access token: ");
     let token = get_trimmed_user_input().context("failed to read provided token")?;
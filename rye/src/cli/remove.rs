@@ -1,20 +1,51 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
 use std::str::FromStr;
 
 use anyhow::Error;
 use clap::Parser;
+use console::style;
+use once_cell::sync::Lazy;
 use pep508_rs::Requirement;
+use regex::Regex;
 
 use crate::config::Config;
-use crate::pyproject::{DependencyKind, PyProject};
-use crate::sync::autosync;
+use crate::pyproject::{normalize_package_name, DependencyKind, PyProject};
+use crate::sync::{
+    autosync, find_orphaned_dists, installed_modules, sync_dry_run, SyncMode, SyncOptions,
+};
 use crate::utils::{format_requirement, CommandOutput};
 
+static IMPORT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?m)^[ \t]*(?:from[ \t]+([A-Za-z_]\w*)[\w.]*[ \t]+import\b|import[ \t]+([^#;\n]+))",
+    )
+    .unwrap()
+});
+
+/// Directories that are never scanned for imports.
+const SKIPPED_DIRS: &[&str] = &[
+    "__pycache__",
+    "build",
+    "dist",
+    "node_modules",
+    "site-packages",
+];
+
 /// Removes a package from this project.
 #[derive(Parser, Debug)]
 pub struct Args {
     /// The packages to remove.
-    #[arg(required = true)]
+    #[arg(required_unless_present = "unused")]
     requirements: Vec<String>,
+    /// Remove regular and optional dependencies that are never imported.
+    ///
+    /// The source files of the project are scanned for imports.  This is a
+    /// heuristic so it's recommended to combine this with `--dry-run` first.
+    #[arg(long, conflicts_with_all = ["requirements", "dev", "optional"])]
+    unused: bool,
     /// Remove this from dev dependencies.
     #[arg(long)]
     dev: bool,
@@ -27,6 +58,9 @@ pub struct Args {
     /// Does not run `sync` even if auto-sync is enabled.
     #[arg(long, conflicts_with = "sync")]
     no_sync: bool,
+    /// Print the changes a sync would make without applying them.
+    #[arg(long, conflicts_with = "sync")]
+    dry_run: bool,
     /// Enables verbose diagnostics.
     #[arg(short, long)]
     verbose: bool,
@@ -52,10 +86,48 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
                 DependencyKind::Normal
             },
         )? {
+            pyproject_toml.set_editable_dependency(&removed.name, false);
             removed_packages.push(removed);
         }
     }
 
+    if cmd.unused {
+        for (requirement, kind) in find_unused_dependencies(&pyproject_toml, output)? {
+            if let Some(removed) = pyproject_toml.remove_dependency(&requirement, kind)? {
+                pyproject_toml.set_editable_dependency(&removed.name, false);
+                removed_packages.push(removed);
+            }
+        }
+        if removed_packages.is_empty() {
+            echo!(if output, "No unused dependencies found");
+        }
+        let orphans = find_orphaned_dists(&pyproject_toml)?;
+        if !orphans.is_empty() {
+            echo!(
+                if output,
+                "Installed packages not required by the lockfile:"
+            );
+            for (name, version) in orphans {
+                echo!(if output, "  {}=={}", style(name).red(), version);
+            }
+        }
+    }
+
+    if cmd.dry_run {
+        for requirement in removed_packages {
+            echo!("Would remove {}", format_requirement(&requirement));
+        }
+        return sync_dry_run(
+            &pyproject_toml,
+            SyncOptions {
+                output,
+                dev: true,
+                mode: SyncMode::Regular,
+                ..SyncOptions::default()
+            },
+        );
+    }
+
     pyproject_toml.save()?;
 
     if output != CommandOutput::Quiet {
@@ -70,3 +142,94 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Finds regular and optional dependencies whose modules are never imported.
+fn find_unused_dependencies(
+    pyproject: &PyProject,
+    output: CommandOutput,
+) -> Result<Vec<(Requirement, DependencyKind<'static>)>, Error> {
+    let venv = pyproject.venv_path();
+    if !venv.is_dir() {
+        warn!("virtualenv is not synced, guessing module names from package names");
+    }
+    let modules = installed_modules(&venv);
+    let imports = find_imports(&pyproject.root_path(), &venv);
+    echo!(
+        if verbose output,
+        "Found {} imported top level modules",
+        imports.len()
+    );
+
+    let own_name = pyproject.normalized_name().ok();
+    let mut kinds = vec![DependencyKind::Normal];
+    let mut extras = pyproject.extras().into_iter().collect::<Vec<_>>();
+    extras.sort();
+    kinds.extend(
+        extras
+            .into_iter()
+            .map(|x| DependencyKind::Optional(Cow::Owned(x.to_string()))),
+    );
+
+    let mut rv = Vec::new();
+    for kind in kinds {
+        for dep in pyproject.iter_dependencies(kind.clone()) {
+            let req = match dep.expand(|name| std::env::var(name).ok()) {
+                Ok(req) => req,
+                Err(_) => continue,
+            };
+            let name = normalize_package_name(&req.name);
+            if own_name.as_deref() == Some(name.as_str()) {
+                continue;
+            }
+            let used = match modules.get(&name) {
+                Some(provided) if !provided.is_empty() => {
+                    provided.iter().any(|x| imports.contains(x))
+                }
+                _ => imports.contains(&name.replace('-', "_")),
+            };
+            if !used {
+                rv.push((req, kind.clone()));
+            }
+        }
+    }
+    Ok(rv)
+}
+
+/// Collects the top level modules imported by the python files below a folder.
+fn find_imports(root: &Path, venv: &Path) -> HashSet<String> {
+    let mut rv = HashSet::new();
+    let walker = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            if !entry.file_type().is_dir() || entry.depth() == 0 {
+                return true;
+            }
+            let name = entry.file_name().to_string_lossy();
+            !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) && entry.path() != venv
+        });
+    for entry in walker.filter_map(|x| x.ok()) {
+        if entry.path().extension().map_or(true, |x| x != "py") {
+            continue;
+        }
+        let contents = match fs::read_to_string(entry.path()) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+        for m in IMPORT_RE.captures_iter(&contents) {
+            if let Some(module) = m.get(1) {
+                rv.insert(module.as_str().to_string());
+            } else if let Some(names) = m.get(2) {
+                for name in names.as_str().split(',') {
+                    if let Some(module) = name
+                        .split_whitespace()
+                        .next()
+                        .and_then(|x| x.split('.').next())
+                    {
+                        rv.insert(module.to_string());
+                    }
+                }
+            }
+        }
+    }
+    rv
+}
@@ -1,17 +1,29 @@
 use std::collections::HashMap;
 use std::env::{self, join_paths, split_paths};
 use std::ffi::OsString;
-use std::path::PathBuf;
-use std::process::{Command, ExitStatus};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::str::FromStr;
+use std::thread;
 
 use anyhow::{bail, Context, Error};
 use clap::Parser;
-use console::style;
+use console::{style, Color};
+use pep508_rs::Requirement;
 
+use crate::hooks::run_hook_on_files;
+use crate::inline_script::{ensure_script_env, ScriptMetadata};
+use crate::overlay::{activate_overlay, find_overlay_command};
 use crate::pyproject::{PyProject, Script};
 use crate::sync::{sync, SyncOptions};
 use crate::tui::redirect_to_stderr;
-use crate::utils::{exec_spawn, get_venv_python_bin, success_status, IoPathContext};
+use crate::utils::builtins::{is_builtin, run_builtin, split_env_builtin};
+use crate::utils::signals::{self, exit_code, in_own_process_group, spawn_and_wait};
+use crate::utils::watch::watch_and_rerun;
+use crate::utils::{
+    exec_spawn, get_venv_python_bin, success_status, CommandOutput, IoPathContext, QuietExit,
+};
 
 /// Runs a command installed into this package.
 #[derive(Parser, Debug)]
@@ -26,8 +38,64 @@ pub struct Args {
     /// Use this pyproject.toml file
     #[arg(long, value_name = "PYPROJECT_TOML")]
     pyproject: Option<PathBuf>,
+    /// Run in the named environment from `tool.rye.envs` instead of the default one.
+    #[arg(long, value_name = "NAME")]
+    env: Option<String>,
+    /// Re-run the command whenever a file in this path changes
+    #[arg(long, value_name = "PATH")]
+    watch: Vec<PathBuf>,
+    /// Make an extra package available without adding it to the project
+    #[arg(long = "with", value_name = "PACKAGE")]
+    with_packages: Vec<String>,
+    /// Run a hook from `tool.rye.hooks` on the given files instead of a command
+    #[arg(long, value_name = "HOOK", conflicts_with_all = ["list", "watch", "with_packages", "isolated"])]
+    hook: Option<String>,
+    /// Run the command as a single-file script with inline metadata (PEP 723).
+    #[arg(long, conflicts_with_all = ["list", "pyproject", "env", "watch", "hook"])]
+    script: bool,
+    /// Run without the inherited environment variables and user site-packages, best-effort offline.
+    #[arg(long)]
+    isolated: bool,
+    /// Keep this environment variable when running isolated (`PREFIX_*` keeps all with the prefix).
+    #[arg(long, value_name = "NAME", requires = "isolated")]
+    allow_env: Vec<String>,
+    /// Do not try to keep isolated runs offline.
+    #[arg(long, requires = "isolated")]
+    online: bool,
 }
 
+/// Environment variables that are kept when running isolated.
+const ISOLATED_ENV_ALLOWLIST: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "USERNAME",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "LANG",
+    "LC_*",
+    "TZ",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "NO_COLOR",
+    "RYE_HOME",
+    // required for processes to work on Windows
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+];
+
+/// Points all proxy aware clients to a port where nothing listens.
+const UNREACHABLE_PROXY: &str = "http://127.0.0.1:9";
+
 #[derive(Parser, Debug)]
 enum Cmd {
     #[command(external_subcommand)]
@@ -36,11 +104,32 @@ enum Cmd {
 
 pub fn execute(cmd: Args) -> Result<(), Error> {
     let _guard = redirect_to_stderr(true);
-    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+
+    // scripts bring their own environment and don't need a project.
+    if cmd.script {
+        return run_inline_script(&cmd);
+    }
+
+    let pyproject =
+        PyProject::load_or_discover(cmd.pyproject.as_deref())?.with_env(cmd.env.as_deref())?;
+
+    // the hook's commands and scripts sync on their own if they need to, which
+    // keeps hooks that only format and lint fast.
+    if let Some(ref hook) = cmd.hook {
+        let files = match cmd.cmd {
+            Some(Cmd::External(files)) => files,
+            None => Vec::new(),
+        };
+        return run_hook_on_files(&pyproject, hook, &files, CommandOutput::Normal);
+    }
 
     // make sure we have the minimal virtualenv.
-    sync(SyncOptions::python_only().pyproject(cmd.pyproject))
-        .context("failed to sync ahead of run")?;
+    sync(
+        SyncOptions::python_only()
+            .pyproject(cmd.pyproject.clone())
+            .env(cmd.env.clone()),
+    )
+    .context("failed to sync ahead of run")?;
 
     if cmd.list || cmd.cmd.is_none() {
         return list_scripts(&pyproject);
@@ -50,10 +139,176 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         None => unreachable!(),
     };
 
+    if !cmd.watch.is_empty() {
+        let rye = env::current_exe()?;
+        match watch_and_rerun(&cmd.watch, || {
+            let mut rye_cmd = Command::new(&rye);
+            rye_cmd.arg("run");
+            if let Some(ref pyproject) = cmd.pyproject {
+                rye_cmd.arg("--pyproject").arg(pyproject);
+            }
+            if let Some(ref env) = cmd.env {
+                rye_cmd.arg("--env").arg(env);
+            }
+            for package in &cmd.with_packages {
+                rye_cmd.arg("--with").arg(package);
+            }
+            if cmd.isolated {
+                rye_cmd.arg("--isolated");
+                for name in &cmd.allow_env {
+                    rye_cmd.arg("--allow-env").arg(name);
+                }
+                if cmd.online {
+                    rye_cmd.arg("--online");
+                }
+            }
+            rye_cmd.args(&args);
+            rye_cmd
+        })? {}
+    }
+
+    if !cmd.with_packages.is_empty() {
+        activate_overlay(&pyproject, &cmd.with_packages, CommandOutput::Normal)?;
+    }
+
+    // everything that is spawned from here on, including the commands of
+    // chained and parallel scripts, inherits the environment of this process.
+    if cmd.isolated {
+        isolate_environment(&cmd.allow_env, cmd.online);
+    }
+
+    run_script_dependencies(&pyproject, &args[0].to_string_lossy())?;
     invoke_script(&pyproject, args, true)?;
     unreachable!();
 }
 
+/// Strips this process' environment for isolated runs.
+///
+/// Only the allowlisted variables are kept, names and prefixes are matched
+/// case-insensitively.  User site-packages are disabled and unless `online` is
+/// set, proxy aware clients as well as pip and uv are kept offline.  This is
+/// best effort and does not sandbox the process.
+fn isolate_environment(allow_env: &[String], online: bool) {
+    let is_allowed = |name: &str| {
+        ISOLATED_ENV_ALLOWLIST
+            .iter()
+            .copied()
+            .chain(allow_env.iter().map(|x| x.as_str()))
+            .any(|pattern| {
+                let (prefix, exact) = match pattern.strip_suffix('*') {
+                    Some(prefix) => (prefix, false),
+                    None => (pattern, true),
+                };
+                (!exact || name.len() == prefix.len())
+                    && name
+                        .get(..prefix.len())
+                        .map_or(false, |x| x.eq_ignore_ascii_case(prefix))
+            })
+    };
+    for (name, _) in env::vars_os() {
+        let name_str = name.to_string_lossy();
+        // windows keeps the per drive working directories in `=C:` style
+        // variables which cannot be removed.
+        if name_str.is_empty() || name_str.contains('=') {
+            continue;
+        }
+        if !is_allowed(&name_str) {
+            env::remove_var(name);
+        }
+    }
+
+    env::set_var("PYTHONNOUSERSITE", "1");
+    if !online {
+        for name in [
+            "HTTP_PROXY",
+            "HTTPS_PROXY",
+            "ALL_PROXY",
+            "http_proxy",
+            "https_proxy",
+            "all_proxy",
+        ] {
+            env::set_var(name, UNREACHABLE_PROXY);
+        }
+        env::set_var("PIP_NO_INDEX", "1");
+        env::set_var("UV_OFFLINE", "1");
+    }
+}
+
+/// Runs a single-file script in an environment with its inline dependencies.
+fn run_inline_script(cmd: &Args) -> Result<(), Error> {
+    let args = match cmd.cmd {
+        Some(Cmd::External(ref args)) => args,
+        None => bail!("no script given"),
+    };
+    let script = Path::new(&args[0]);
+    let metadata = ScriptMetadata::from_path(script)?;
+    let extra_requirements = cmd
+        .with_packages
+        .iter()
+        .map(|x| Requirement::from_str(x).with_context(|| format!("invalid package '{}'", x)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let venv = ensure_script_env(&metadata, &extra_requirements, CommandOutput::Normal)?;
+
+    if cmd.isolated {
+        isolate_environment(&cmd.allow_env, cmd.online);
+    }
+
+    let mut python = Command::new(get_venv_python_bin(&venv));
+    python.args(args);
+    python.env("VIRTUAL_ENV", &venv);
+    python.env_remove("PYTHONHOME");
+    match exec_spawn(&mut python)? {}
+}
+
+/// Runs the scripts a script depends on.
+///
+/// Every dependency runs only once, after all of its own dependencies.
+fn run_script_dependencies(pyproject: &PyProject, name: &str) -> Result<(), Error> {
+    let mut order = Vec::new();
+    collect_script_dependencies(pyproject, name, &mut Vec::new(), &mut order)?;
+    // the last entry is the script itself
+    order.pop();
+    for dep in order {
+        let status = invoke_script(pyproject, vec![OsString::from(&dep)], false)?;
+        if !status.success() {
+            error!(
+                "script '{}' failed with {} (dependency of '{}')",
+                dep, status, name
+            );
+            return Err(QuietExit(exit_code(&status)).into());
+        }
+    }
+    Ok(())
+}
+
+fn collect_script_dependencies(
+    pyproject: &PyProject,
+    name: &str,
+    stack: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> Result<(), Error> {
+    if order.iter().any(|x| x == name) {
+        return Ok(());
+    }
+    if stack.iter().any(|x| x == name) {
+        bail!(
+            "cyclic script dependency: {} -> {}",
+            stack.join(" -> "),
+            name
+        );
+    }
+    stack.push(name.to_string());
+    for dep in pyproject.get_script_options(name).depends {
+        if pyproject.get_script_cmd(&dep).is_none() {
+            bail!("script '{}' depends on unknown script '{}'", name, dep);
+        }
+        collect_script_dependencies(pyproject, &dep, stack, order)?;
+    }
+    stack.pop();
+    order.push(name.to_string());
+    Ok(())
+}
+
 fn invoke_script(
     pyproject: &PyProject,
     mut args: Vec<OsString>,
@@ -61,11 +316,19 @@ fn invoke_script(
 ) -> Result<ExitStatus, Error> {
     let venv_bin = pyproject.venv_bin_path();
     let mut env_overrides = None;
+    let options = pyproject.get_script_options(&args[0].to_string_lossy());
+    let script_root = pyproject
+        .script_root_path(&args[0].to_string_lossy())
+        .into_owned();
 
     match pyproject.get_script_cmd(&args[0].to_string_lossy()) {
-        Some(Script::Call(entry, env_vars, env_file)) => {
+        Some(Script::Call(entry, env_vars, env_files)) => {
             let py = OsString::from(get_venv_python_bin(&pyproject.venv_path()));
-            env_overrides = Some(load_env_vars(pyproject, env_file, env_vars)?);
+            env_overrides = Some(load_env_vars(
+                pyproject,
+                resolve_paths(&script_root, env_files),
+                env_vars,
+            )?);
             args = if let Some((module, func)) = entry.split_once(':') {
                 if module.is_empty() || func.is_empty() {
                     bail!("Python callable must be in the form <module_name>:<callable_name> or <module_name>")
@@ -87,11 +350,15 @@ fn invoke_script(
             .chain(args.into_iter().skip(1))
             .collect();
         }
-        Some(Script::Cmd(script_args, env_vars, env_file)) => {
+        Some(Script::Cmd(script_args, env_vars, env_files)) => {
             if script_args.is_empty() {
                 bail!("script has no arguments");
             }
-            env_overrides = Some(load_env_vars(pyproject, env_file, env_vars)?);
+            env_overrides = Some(load_env_vars(
+                pyproject,
+                resolve_paths(&script_root, env_files),
+                env_vars,
+            )?);
             let script_target = venv_bin.join(&script_args[0]);
             if script_target.is_file() {
                 args = Some(script_target.as_os_str().to_owned())
@@ -108,6 +375,7 @@ fn invoke_script(
             }
         }
         Some(Script::External(_)) => {
+            env_overrides = Some(load_env_vars(pyproject, Vec::new(), HashMap::new())?);
             args[0] = venv_bin.join(&args[0]).into();
         }
         Some(Script::Chain(commands)) => {
@@ -121,7 +389,8 @@ fn invoke_script(
                     if !exec {
                         return Ok(status);
                     } else {
-                        bail!("script failed with {}", status);
+                        error!("script failed with {}", status);
+                        return Err(QuietExit(exit_code(&status)).into());
                     }
                 }
             }
@@ -130,9 +399,45 @@ fn invoke_script(
             }
             return Ok(success_status());
         }
-        None => {
-            bail!("invalid or unknown script '{}'", args[0].to_string_lossy());
+        Some(Script::Parallel(commands)) => {
+            if args.len() != 1 {
+                bail!("extra arguments to parallel commands are not allowed");
+            }
+            let status = invoke_parallel(pyproject, commands)?;
+            if exec {
+                std::process::exit(exit_code(&status));
+            }
+            return Ok(status);
         }
+        // builtins are also allowed directly, for instance as part of a chain.
+        None if is_builtin(&args[0]) => {
+            env_overrides = Some(load_env_vars(pyproject, Vec::new(), HashMap::new())?);
+        }
+        None => match find_overlay_command(&args[0].to_string_lossy()) {
+            // tools that were added with `--with`
+            Some(path) => {
+                env_overrides = Some(load_env_vars(pyproject, Vec::new(), HashMap::new())?);
+                args[0] = path.into();
+            }
+            None => bail!("invalid or unknown script '{}'", args[0].to_string_lossy()),
+        },
+    }
+
+    if args[0] == "@env" {
+        let (vars, rest) = split_env_builtin(&args)?;
+        env_overrides.get_or_insert_with(HashMap::new).extend(vars);
+        args = rest;
+    }
+    if is_builtin(&args[0]) {
+        let working_dir = match options.working_dir {
+            Some(working_dir) => script_root.join(working_dir),
+            None => env::current_dir()?,
+        };
+        run_builtin(&args, &working_dir)?;
+        if exec {
+            std::process::exit(0);
+        }
+        return Ok(success_status());
     }
 
     let mut cmd = Command::new(&args[0]);
@@ -150,20 +455,122 @@ fn invoke_script(
         cmd.envs(env_overrides.iter());
     }
     cmd.env_remove("PYTHONHOME");
+    if let Some(working_dir) = options.working_dir {
+        cmd.current_dir(script_root.join(working_dir));
+    }
 
     if exec {
         match exec_spawn(&mut cmd)? {};
     } else {
-        Ok(cmd.status()?)
+        spawn_and_wait(&mut cmd)
+    }
+}
+
+/// Runs several commands at once.
+///
+/// Every line of output is prefixed with the command it came from.  All
+/// commands run to completion and the status of the first failed command
+/// is returned.
+fn invoke_parallel(pyproject: &PyProject, commands: Vec<Vec<String>>) -> Result<ExitStatus, Error> {
+    const COLORS: &[Color] = &[
+        Color::Cyan,
+        Color::Magenta,
+        Color::Yellow,
+        Color::Green,
+        Color::Blue,
+        Color::Red,
+    ];
+
+    let rye = env::current_exe()?;
+    let names = commands.iter().map(|x| x.join(" ")).collect::<Vec<_>>();
+    let width = names.iter().map(|x| x.len()).max().unwrap_or(0);
+
+    thread::scope(|scope| -> Result<ExitStatus, Error> {
+        let mut children = Vec::new();
+        for (idx, (args, name)) in commands.iter().zip(&names).enumerate() {
+            let mut child = Command::new(&rye);
+            child
+                .arg("run")
+                .arg("--pyproject")
+                .arg(&*pyproject.toml_path());
+            if let Some(env) = pyproject.env() {
+                child.arg("--env").arg(&env.name);
+            }
+            let spawned = in_own_process_group(&mut child)
+                .args(args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .with_context(|| format!("unable to start '{}'", name));
+            let mut child = match spawned {
+                Ok(child) => child,
+                Err(err) => {
+                    // the commands that already started would keep the output
+                    // forwarding (and with it rye) alive until they exit
+                    for (_, mut child, _tracked) in children {
+                        signals::kill_process_group(&mut child);
+                    }
+                    return Err(err);
+                }
+            };
+            let prefix = style(format!("{:width$} |", name, width = width))
+                .fg(COLORS[idx % COLORS.len()])
+                .to_string();
+            if let Some(stdout) = child.stdout.take() {
+                let prefix = prefix.clone();
+                scope.spawn(move || forward_lines(stdout, &prefix, false));
+            }
+            if let Some(stderr) = child.stderr.take() {
+                scope.spawn(move || forward_lines(stderr, &prefix, true));
+            }
+            let tracked = signals::track(&child, true);
+            children.push((name, child, tracked));
+        }
+
+        let mut rv = success_status();
+        for (name, mut child, _tracked) in children {
+            let status = child.wait()?;
+            if !status.success() {
+                elog!("{} failed with {}", name, status);
+                if rv.success() {
+                    rv = status;
+                }
+            }
+        }
+        Ok(rv)
+    })
+}
+
+/// Forwards the output of a child line by line with a prefix.
+pub fn forward_lines<R: Read>(reader: R, prefix: &str, to_stderr: bool) {
+    for line in BufReader::new(reader).split(b'\n').map_while(Result::ok) {
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches('\r');
+        if to_stderr {
+            writeln!(io::stderr().lock(), "{} {}", prefix, line).ok();
+        } else {
+            writeln!(io::stdout().lock(), "{} {}", prefix, line).ok();
+        }
     }
 }
 
+/// Makes the env-files of a script relative to the project declaring it.
+fn resolve_paths(root: &Path, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.into_iter().map(|x| root.join(x)).collect()
+}
+
+/// Loads the environment variables of a script.
+///
+/// The project wide env files are loaded first, followed by the env files of
+/// the script.  Later files override values of earlier ones and all of them
+/// override the variables from `env`.
 fn load_env_vars(
     pyproject: &PyProject,
-    env_file: Option<PathBuf>,
+    env_files: Vec<PathBuf>,
     mut env_vars: HashMap<String, String>,
 ) -> Result<HashMap<String, String>, Error> {
-    if let Some(ref env_file) = env_file {
+    for env_file in pyproject.env_files().into_iter().chain(env_files) {
         let env_file = pyproject.root_path().join(env_file);
         for item in
             dotenvy::from_path_iter(&env_file).path_context(&env_file, "could not load env-file")?
@@ -188,8 +595,18 @@ fn list_scripts(pyproject: &PyProject) -> Result<(), Error> {
     for (name, script) in scripts {
         if matches!(script, Script::External(_)) {
             echo!("{}", name);
-        } else {
-            echo!("{} ({})", name, style(script).dim());
+            continue;
+        }
+        echo!("{} ({})", name, style(script).dim());
+        let options = pyproject.get_script_options(&name);
+        if let Some(description) = options.description {
+            echo!("  {}", description);
+        }
+        if !options.depends.is_empty() {
+            echo!(
+                "  {}",
+                style(format!("depends on: {}", options.depends.join(", "))).dim()
+            );
         }
     }
     Ok(())
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::env::consts::{ARCH, EXE_EXTENSION, OS};
 use std::env::{join_paths, split_paths};
 use std::path::{Path, PathBuf};
@@ -7,22 +8,38 @@ use std::sync::Arc;
 use std::{env, fs};
 
 use anyhow::{anyhow, bail, Context, Error};
-use clap::{CommandFactory, Parser};
-use clap_complete::Shell;
+use clap::{Parser, ValueEnum};
 use console::style;
 use minijinja::render;
 use self_replace::self_delete_outside_path;
+use serde::Serialize;
 use tempfile::tempdir;
+use toml_edit::Item;
+use url::Url;
 
 use crate::bootstrap::{
-    download_url, download_url_ignore_404, ensure_self_venv_with_toolchain,
-    is_self_compatible_toolchain, update_core_shims, SELF_PYTHON_TARGET_VERSION,
+    download_url_with_credentials, ensure_self_venv_with_toolchain, is_self_compatible_toolchain,
+    update_core_shims, SELF_PYTHON_TARGET_VERSION, SELF_VERSION,
 };
+use crate::cli::cache::CacheCategory;
+use crate::cli::config::value_to_json;
+use crate::cli::shim::list_shim_projects;
 use crate::cli::toolchain::register_toolchain;
-use crate::config::Config;
-use crate::platform::{get_app_dir, symlinks_supported};
+use crate::completion::{
+    list_completion_values, write_completion_script, CompletionShell, CompletionValues,
+};
+use crate::config::{Config, ConfigOrigin};
+use crate::credentials::get_source_credentials;
+use crate::installer::list_installed_tools;
+use crate::platform::{
+    get_app_dir, get_canonical_py_path, list_known_toolchains, symlinks_supported,
+};
+use crate::pyproject::{get_current_venv_python_version, read_venv_marker, PyProject};
 use crate::sources::py::{get_download_url, PythonVersionRequest};
-use crate::utils::{check_checksum, toml, tui_theme, CommandOutput, IoPathContext, QuietExit};
+use crate::utils::{
+    check_checksum, dir_size, format_size, toml, tui_theme, CommandOutput, IoPathContext, QuietExit,
+};
+use crate::uv::{Uv, UvBuilder};
 
 #[cfg(windows)]
 const DEFAULT_HOME: &str = "%USERPROFILE%\\.rye";
@@ -30,6 +47,8 @@ const DEFAULT_HOME: &str = "%USERPROFILE%\\.rye";
 const DEFAULT_HOME: &str = "$HOME/.rye";
 
 const GITHUB_REPO: &str = "https://github.com/astral-sh/rye";
+/// The name under which credentials for `self.update-url` are stored.
+const UPDATE_CREDENTIALS: &str = "self-update";
 const UNIX_ENV_FILE: &str = r#"
 # rye shell setup
 {%- if custom_home %}
@@ -53,19 +72,36 @@ pub struct Args {
 }
 
 /// Generates a completion script for a shell.
+///
+/// Besides commands and options this completes the scripts and dependencies
+/// of the current project, installed toolchains and installed tools.
 #[derive(Parser, Debug)]
 pub struct CompletionCommand {
     /// The shell to generate a completion script for (defaults to 'bash').
     #[arg(short, long)]
-    shell: Option<Shell>,
+    shell: Option<CompletionShell>,
+    /// The shell to generate a completion script for (same as `--shell`).
+    #[arg(value_name = "SHELL", conflicts_with = "shell")]
+    shell_name: Option<CompletionShell>,
+}
+
+/// Prints the values for dynamic completions (used by completion scripts).
+#[derive(Parser, Debug)]
+pub struct CompleteValuesCommand {
+    /// The kind of values to print.
+    kind: CompletionValues,
 }
 
 /// Performs an update of rye.
 ///
 /// This can install updates from the latest release binaries or trigger a manual
-/// compilation of Rye if Rust is installed.
+/// compilation of Rye if Rust is installed.  The previous binary is kept so that
+/// the update can be reverted with `rye self rollback`.
 #[derive(Parser, Debug)]
 pub struct UpdateCommand {
+    /// The release channel to update from (defaults to `self.update-channel` or 'stable').
+    #[arg(long, conflicts_with_all = ["version", "tag", "rev", "branch"])]
+    channel: Option<UpdateChannel>,
     /// Update to a specific version.
     #[arg(long)]
     version: Option<String>,
@@ -81,8 +117,28 @@ pub struct UpdateCommand {
     /// Force reinstallation
     #[arg(long)]
     force: bool,
+    /// Do not require a checksum for the downloaded binary.
+    #[arg(long)]
+    no_verify: bool,
 }
 
+/// The release channels rye can be updated from.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+enum UpdateChannel {
+    /// The latest release.
+    Stable,
+    /// The main branch, compiled with cargo.
+    Nightly,
+}
+
+/// Reverts the last update of rye.
+///
+/// This restores the binary that was replaced by the last `rye self update`
+/// and does not need network access.  Rolling back again undoes the rollback.
+#[derive(Parser, Debug)]
+pub struct RollbackCommand {}
+
 /// Triggers the initial installation of Rye.
 ///
 /// This command is executed by the installation step to move Rye
@@ -152,6 +208,107 @@ enum InstallMode {
     AutoInstall,
 }
 
+/// Shows a report of what Rye manages on this machine.
+///
+/// This only aggregates local data and never uploads anything.  Projects are
+/// those that shims were recently invoked in (with `behavior.shim-cache`).
+#[derive(Parser, Debug)]
+pub struct StatsCommand {
+    /// Request parseable output format rather than lines.
+    #[arg(long)]
+    format: Option<Format>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Serialize, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+enum Format {
+    Json,
+}
+
+#[derive(Serialize, Debug)]
+struct Stats {
+    projects: Vec<PathBuf>,
+    toolchains: Vec<SizeEntry>,
+    tools: Vec<SizeEntry>,
+    caches: Vec<SizeEntry>,
+    total_size: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct SizeEntry {
+    name: String,
+    size: u64,
+}
+
+/// Shows the uv binary Rye uses or installs it again.
+///
+/// Rye verifies the binary before use and downloads it again if it was
+/// damaged.  `--reinstall` forces that in case the check misses something.
+#[derive(Parser, Debug)]
+pub struct UvCommand {
+    /// Download and install uv again.
+    #[arg(long)]
+    reinstall: bool,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Prints a report about Rye and its environment for bug reports.
+///
+/// Passwords, tokens and credentials in URLs are redacted and the home
+/// folder is abbreviated so that the report can be pasted into an issue.
+#[derive(Parser, Debug)]
+pub struct InfoCommand {
+    /// Request parseable output format rather than lines.
+    #[arg(long)]
+    format: Option<Format>,
+}
+
+#[derive(Serialize, Debug)]
+struct Info {
+    version: String,
+    commit: String,
+    platform: PlatformInfo,
+    self_python: Option<String>,
+    self_venv_version: Option<u64>,
+    self_venv_target_version: u64,
+    uv: Option<String>,
+    symlink_support: bool,
+    config_path: String,
+    config: BTreeMap<String, ConfigEntry>,
+    project: Option<ProjectInfo>,
+}
+
+#[derive(Serialize, Debug)]
+struct PlatformInfo {
+    os: &'static str,
+    arch: &'static str,
+    libc: Option<&'static str>,
+}
+
+#[derive(Serialize, Debug)]
+struct ConfigEntry {
+    value: serde_json::Value,
+    origin: String,
+}
+
+#[derive(Serialize, Debug)]
+struct ProjectInfo {
+    path: String,
+    name: Option<String>,
+    workspace: Option<String>,
+    is_virtual: bool,
+    python: Option<String>,
+    venv: String,
+    venv_python: Option<String>,
+    lockfiles: Vec<String>,
+}
+
 /// Uninstalls rye again.
 #[derive(Parser, Debug)]
 pub struct UninstallCommand {
@@ -163,7 +320,13 @@ pub struct UninstallCommand {
 #[derive(Parser, Debug)]
 enum SubCommand {
     Completion(CompletionCommand),
+    #[command(hide = true)]
+    CompleteValues(CompleteValuesCommand),
     Update(UpdateCommand),
+    Rollback(RollbackCommand),
+    Stats(StatsCommand),
+    Info(InfoCommand),
+    Uv(UvCommand),
     #[command(hide = true)]
     Install(InstallCommand),
     Uninstall(UninstallCommand),
@@ -172,116 +335,207 @@ enum SubCommand {
 pub fn execute(cmd: Args) -> Result<(), Error> {
     match cmd.command {
         SubCommand::Completion(args) => completion(args),
+        SubCommand::CompleteValues(args) => complete_values(args),
         SubCommand::Update(args) => update(args),
+        SubCommand::Rollback(_) => rollback(),
+        SubCommand::Stats(args) => stats(args),
+        SubCommand::Info(args) => info(args),
+        SubCommand::Uv(args) => uv(args),
         SubCommand::Install(args) => install(args),
         SubCommand::Uninstall(args) => uninstall(args),
     }
 }
 
 fn completion(args: CompletionCommand) -> Result<(), Error> {
-    clap_complete::generate(
-        args.shell.unwrap_or(Shell::Bash),
-        &mut super::Args::command(),
-        "rye",
+    write_completion_script(
+        args.shell
+            .or(args.shell_name)
+            .unwrap_or(CompletionShell::Bash),
+        &mut super::command(),
         &mut std::io::stdout(),
-    );
+    )
+}
 
+fn complete_values(args: CompleteValuesCommand) -> Result<(), Error> {
+    for value in list_completion_values(args.kind) {
+        echo!("{}", value);
+    }
     Ok(())
 }
 
+/// Where `rye self update` gets the new binary from.
+#[derive(Debug, PartialEq)]
+enum UpdateSource {
+    /// A release binary downloaded from a URL.
+    Release { version: String, url: String },
+    /// A build of a git ref with `cargo install`.  `kind` is the flag that
+    /// selects the ref (`--rev`, `--tag` or `--branch`).
+    Git { kind: &'static str, name: String },
+}
+
+impl UpdateCommand {
+    /// Picks what to update to.
+    ///
+    /// `url_template` is the `self.update-url` config key which replaces the
+    /// GitHub release URLs.
+    fn source(&self, channel: UpdateChannel, url_template: Option<&str>) -> UpdateSource {
+        for (kind, name) in [
+            ("--rev", &self.rev),
+            ("--tag", &self.tag),
+            ("--branch", &self.branch),
+        ] {
+            if let Some(name) = name {
+                return UpdateSource::Git {
+                    kind,
+                    name: name.clone(),
+                };
+            }
+        }
+
+        let version = match (self.version.as_deref(), channel) {
+            (Some(version), _) => version,
+            (None, UpdateChannel::Stable) => "latest",
+            // no release binaries are published for the main branch, so
+            // nightly updates are compiled from it.
+            (None, UpdateChannel::Nightly) => {
+                return UpdateSource::Git {
+                    kind: "--branch",
+                    name: "main".into(),
+                }
+            }
+        };
+        let target = format!("{ARCH}-{OS}");
+        let ext = if cfg!(unix) { ".gz" } else { ".exe" };
+        let url = match url_template {
+            Some(template) => template
+                .replace("{version}", version)
+                .replace("{target}", &target)
+                .replace("{ext}", ext),
+            None if version == "latest" => {
+                format!("{GITHUB_REPO}/releases/latest/download/rye-{target}{ext}")
+            }
+            None => format!("{GITHUB_REPO}/releases/download/{version}/rye-{target}{ext}"),
+        };
+        UpdateSource::Release {
+            version: version.to_string(),
+            url,
+        }
+    }
+}
+
 fn update(args: UpdateCommand) -> Result<(), Error> {
     // make sure to read the exe before self_replace as otherwise we might read
     // a bad executable name on Linux where the move is picked up.
     let current_exe = env::current_exe()?;
 
-    // git based installation with cargo
-    if args.rev.is_some() || args.tag.is_some() || args.branch.is_some() {
-        let mut cmd = Command::new("cargo");
-        let tmp = tempdir()?;
-        cmd.arg("install")
-            .arg("--git")
-            .arg("https://github.com/astral-sh/rye")
-            .arg("--root")
-            .env(
-                "PATH",
-                join_paths(
-                    Some(tmp.path().join("bin"))
-                        .into_iter()
-                        .chain(split_paths(&env::var_os("PATH").unwrap_or_default())),
-                )?,
-            )
-            .arg(tmp.path());
-        if let Some(ref rev) = args.rev {
-            cmd.arg("--rev");
-            cmd.arg(rev);
-        } else if let Some(ref tag) = args.tag {
-            cmd.arg("--tag");
-            cmd.arg(tag);
-        } else if let Some(ref branch) = args.branch {
-            cmd.arg("--branch");
-            cmd.arg(branch);
-        }
-        if args.force {
-            cmd.arg("--force");
-        }
-        cmd.arg("rye");
-        let status = cmd.status().context("unable to update via cargo-install")?;
-        if !status.success() {
-            bail!("failed to self-update via cargo-install");
-        }
-        update_exe_and_shims(
-            &tmp.path()
+    let config = Config::current();
+    let channel = match args.channel {
+        Some(channel) => channel,
+        None => match config.update_channel() {
+            Some(channel) => UpdateChannel::from_str(&channel, true)
+                .map_err(|_| anyhow!("invalid update channel '{}'", channel))?,
+            None => UpdateChannel::Stable,
+        },
+    };
+    let url_template = config.update_url();
+
+    // the new binary is only kept in a temporary location until it replaced
+    // the current one.
+    let tmp = tempdir()?;
+    let new_exe = match args.source(channel, url_template.as_deref()) {
+        // git based installation with cargo
+        UpdateSource::Git { kind, name } => {
+            if which::which("cargo").is_err() {
+                bail!(
+                    "compiling rye from git requires cargo, install Rust from https://rustup.rs/"
+                );
+            }
+            let mut cmd = Command::new("cargo");
+            cmd.arg("install")
+                .arg("--git")
+                .arg(GITHUB_REPO)
+                .arg("--root")
+                .env(
+                    "PATH",
+                    join_paths(
+                        Some(tmp.path().join("bin"))
+                            .into_iter()
+                            .chain(split_paths(&env::var_os("PATH").unwrap_or_default())),
+                    )?,
+                )
+                .arg(tmp.path())
+                .arg(kind)
+                .arg(&name);
+            if args.force {
+                cmd.arg("--force");
+            }
+            cmd.arg("rye");
+            let status = cmd.status().context("unable to update via cargo-install")?;
+            if !status.success() {
+                bail!("failed to self-update via cargo-install");
+            }
+            tmp.path()
                 .join("bin")
                 .join("rye")
-                .with_extension(EXE_EXTENSION),
-        )
-    } else {
-        let version = args.version.as_deref().unwrap_or("latest");
-        echo!("Updating to {version}");
-        let binary = format!("rye-{ARCH}-{OS}");
-        let ext = if cfg!(unix) { ".gz" } else { ".exe" };
-        let url = if version == "latest" {
-            format!("{GITHUB_REPO}/releases/latest/download/{binary}{ext}")
-        } else {
-            format!("{GITHUB_REPO}/releases/download/{version}/{binary}{ext}")
-        };
-        let sha256_url = format!("{}.sha256", url);
-        let bytes = download_url(&url, CommandOutput::Normal)
-            .with_context(|| format!("could not download release {version} for this platform"))?;
-        if let Some(sha256_bytes) = download_url_ignore_404(&sha256_url, CommandOutput::Normal)? {
-            let checksum = String::from_utf8_lossy(&sha256_bytes);
-            echo!("Checking checksum");
-            check_checksum(&bytes, checksum.trim())
-                .with_context(|| format!("hash check of {} failed", url))?;
-        } else {
-            echo!("Checksum check skipped (no hash available)");
+                .with_extension(EXE_EXTENSION)
         }
+        UpdateSource::Release { version, url } => {
+            echo!("Updating to {version}");
+            let credentials = match url_template {
+                Some(_) => get_source_credentials(UPDATE_CREDENTIALS)?,
+                None => None,
+            };
+            let sha256_url = format!("{}.sha256", url);
+            let bytes =
+                download_url_with_credentials(&url, credentials.as_ref(), CommandOutput::Normal)
+                    .and_then(|x| x.ok_or_else(|| anyhow!("Failed to download: 404 not found")))
+                    .with_context(|| {
+                        format!("could not download release {version} for this platform")
+                    })?;
+            if let Some(sha256_bytes) = download_url_with_credentials(
+                &sha256_url,
+                credentials.as_ref(),
+                CommandOutput::Normal,
+            )? {
+                let checksum = String::from_utf8_lossy(&sha256_bytes);
+                echo!("Checking checksum");
+                check_checksum(&bytes, checksum.trim(), CommandOutput::Normal)
+                    .with_context(|| format!("hash check of {} failed", url))?;
+            } else if args.no_verify {
+                echo!("Checksum check skipped (no hash available)");
+            } else {
+                bail!(
+                    "no checksum available for release {version}, \
+                     pass --no-verify to update anyway"
+                );
+            }
 
-        let tmp = tempfile::NamedTempFile::new()?;
+            let new_exe = tmp.path().join("rye").with_extension(EXE_EXTENSION);
 
-        // unix currently comes compressed, windows comes uncompressed
-        #[cfg(unix)]
-        {
-            use std::io::Read;
-            let mut decoder = flate2::bufread::GzDecoder::new(&bytes[..]);
-            let mut rv = Vec::new();
-            decoder.read_to_end(&mut rv)?;
-            fs::write(tmp.path(), rv)?;
-        }
-        #[cfg(windows)]
-        {
-            fs::write(tmp.path(), bytes)?;
+            // unix currently comes compressed, windows comes uncompressed
+            #[cfg(unix)]
+            {
+                use std::io::Read;
+                let mut decoder = flate2::bufread::GzDecoder::new(&bytes[..]);
+                let mut rv = Vec::new();
+                decoder.read_to_end(&mut rv)?;
+                fs::write(&new_exe, rv)?;
+            }
+            #[cfg(windows)]
+            {
+                fs::write(&new_exe, bytes)?;
+            }
+            new_exe
         }
-        update_exe_and_shims(tmp.path())
-    }
-    .context(
-        "Unable to perform update. This can happen because files are in use. \
-         Please stop running Python interpreters and retry the update.",
-    )?;
+    };
 
-    echo!("Validate updated installation");
-    validate_updated_exe(&current_exe)
-        .context("unable to perform validation of updated installation")?;
+    backup_current_exe(&current_exe, &get_previous_exe_path())?;
+    replace_and_validate(
+        &new_exe,
+        &get_previous_exe_path(),
+        update_exe_and_shims,
+        || validate_updated_exe(&current_exe),
+    )?;
 
     echo!("Updated!");
     echo!();
@@ -290,6 +544,38 @@ fn update(args: UpdateCommand) -> Result<(), Error> {
     Ok(())
 }
 
+/// Replaces the current binary with `new_exe` and validates the result.
+///
+/// If the new binary does not work, the backup at `previous_exe` is put back
+/// so that a broken download does not leave a broken installation behind.
+fn replace_and_validate<R, V>(
+    new_exe: &Path,
+    previous_exe: &Path,
+    replace: R,
+    validate: V,
+) -> Result<(), Error>
+where
+    R: Fn(&Path) -> Result<(), Error>,
+    V: FnOnce() -> Result<(), Error>,
+{
+    replace(new_exe).context(
+        "Unable to perform update. This can happen because files are in use. \
+         Please stop running Python interpreters and retry the update.",
+    )?;
+
+    echo!("Validate updated installation");
+    if let Err(err) = validate() {
+        warn!("the updated installation does not work, restoring the previous version");
+        // the backup stays in place so that it is not replaced by itself.
+        let tmp = tempfile::NamedTempFile::new()?;
+        fs::copy(previous_exe, tmp.path()).path_context(previous_exe, "unable to read binary")?;
+        replace(tmp.path()).context("unable to restore the previous version")?;
+        return Err(err.context("unable to perform validation of updated installation"));
+    }
+
+    Ok(())
+}
+
 fn validate_updated_exe(rye: &Path) -> Result<(), Error> {
     let folder = tempfile::tempdir()?;
 
@@ -327,6 +613,361 @@ fn validate_updated_exe(rye: &Path) -> Result<(), Error> {
     Ok(())
 }
 
+fn rollback() -> Result<(), Error> {
+    let current_exe = env::current_exe()?;
+    let previous_version =
+        swap_with_previous_exe(&current_exe, &get_previous_exe_path(), update_exe_and_shims)?;
+    echo!(
+        "Rolled back from {} to {}",
+        style(env!("CARGO_PKG_VERSION")).cyan(),
+        style(previous_version).cyan()
+    );
+    Ok(())
+}
+
+/// Replaces the current binary with the one kept at `previous_exe`.
+///
+/// The current binary is kept in its place, so that rolling back again undoes
+/// the rollback.  Returns the version that was rolled back to.
+fn swap_with_previous_exe<R>(
+    current_exe: &Path,
+    previous_exe: &Path,
+    replace: R,
+) -> Result<String, Error>
+where
+    R: FnOnce(&Path) -> Result<(), Error>,
+{
+    if !previous_exe.is_file() {
+        bail!("no previous version of rye to roll back to");
+    }
+    let previous_version = fs::read_to_string(previous_exe.with_extension("version"))
+        .map(|x| x.trim().to_string())
+        .unwrap_or_else(|_| "unknown".into());
+
+    // the previous binary is replaced by the current one below, so it
+    // needs to be moved out of the way first.
+    let tmp = tempfile::NamedTempFile::new()?;
+    fs::copy(previous_exe, tmp.path()).path_context(previous_exe, "unable to read binary")?;
+    backup_current_exe(current_exe, previous_exe)?;
+    replace(tmp.path()).context(
+        "Unable to perform rollback. This can happen because files are in use. \
+         Please stop running Python interpreters and retry the rollback.",
+    )?;
+    Ok(previous_version)
+}
+
+fn stats(args: StatsCommand) -> Result<(), Error> {
+    let mut toolchains = Vec::new();
+    for (version, _) in list_known_toolchains()? {
+        toolchains.push(SizeEntry {
+            name: version.to_string(),
+            size: dir_size(&get_canonical_py_path(&version)?),
+        });
+    }
+    toolchains.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut tools = list_installed_tools()?
+        .into_iter()
+        .map(|(name, info)| SizeEntry {
+            name,
+            size: info.disk_size(),
+        })
+        .collect::<Vec<_>>();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // uv's cache folder can only be determined by running uv.  If uv was
+    // never installed it is not downloaded just for this report.
+    let caches = CacheCategory::value_variants()
+        .iter()
+        .filter(|x| **x != CacheCategory::Uv || get_app_dir().join("uv").is_dir())
+        .filter_map(|category| {
+            Some(SizeEntry {
+                name: category.name().to_string(),
+                size: dir_size(&category.path().ok()?),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let total_size = toolchains
+        .iter()
+        .chain(tools.iter())
+        .chain(caches.iter())
+        .map(|x| x.size)
+        .sum();
+    let stats = Stats {
+        projects: list_shim_projects(),
+        toolchains,
+        tools,
+        caches,
+        total_size,
+    };
+
+    if let Some(Format::Json) = args.format {
+        serde_json::to_writer_pretty(std::io::stdout().lock(), &stats)?;
+        echo!();
+        return Ok(());
+    }
+
+    echo!("{} ({})", style("projects").bold(), stats.projects.len());
+    for project in &stats.projects {
+        echo!("  {}", project.display());
+    }
+    for (title, entries) in [
+        ("toolchains", &stats.toolchains),
+        ("tools", &stats.tools),
+        ("caches", &stats.caches),
+    ] {
+        echo!(
+            "{} ({}, {})",
+            style(title).bold(),
+            entries.len(),
+            format_size(entries.iter().map(|x| x.size).sum())
+        );
+        for entry in entries {
+            echo!(
+                "  {}: {}",
+                style(&entry.name).cyan(),
+                format_size(entry.size)
+            );
+        }
+    }
+    echo!(
+        "{}: {}",
+        style("total").bold(),
+        format_size(stats.total_size)
+    );
+    Ok(())
+}
+
+fn info(args: InfoCommand) -> Result<(), Error> {
+    let config = Config::current();
+    let self_venv = get_app_dir().join("self");
+    let info = Info {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        commit: super::TESTAMENT.commit.to_string(),
+        platform: PlatformInfo {
+            os: OS,
+            arch: ARCH,
+            libc: if cfg!(target_env = "musl") {
+                Some("musl")
+            } else if cfg!(target_env = "gnu") {
+                Some("glibc")
+            } else {
+                None
+            },
+        },
+        self_python: read_venv_marker(&self_venv).map(|x| x.python.to_string()),
+        self_venv_version: fs::read_to_string(self_venv.join("tool-version.txt"))
+            .ok()
+            .and_then(|x| x.trim().parse().ok()),
+        self_venv_target_version: SELF_VERSION,
+        uv: Uv::installed_version().unwrap_or_else(|err| Some(format!("broken ({})", err))),
+        symlink_support: symlinks_supported(),
+        config_path: redact_home(config.path()),
+        config: config
+            .origins()
+            .iter()
+            .map(|(key, origin)| {
+                let mut value = match config.get_item(key) {
+                    Some(Item::ArrayOfTables(tables)) => serde_json::Value::Array(
+                        tables
+                            .iter()
+                            .map(|x| value_to_json(Some(&x.clone().into_inline_table().into())))
+                            .collect(),
+                    ),
+                    item => value_to_json(item.and_then(|x| x.as_value())),
+                };
+                redact_json(key, &mut value);
+                let origin = match origin {
+                    ConfigOrigin::File(path) => format!("file:{}", redact_home(path)),
+                    ConfigOrigin::PyProject(path) => format!("pyproject:{}", redact_home(path)),
+                    ConfigOrigin::Env(var) => format!("env:{}", var),
+                };
+                (key.clone(), ConfigEntry { value, origin })
+            })
+            .collect(),
+        project: PyProject::discover().ok().map(|project| ProjectInfo {
+            path: redact_home(&project.root_path()),
+            name: project.name().map(|x| x.to_string()),
+            workspace: project
+                .workspace()
+                .filter(|_| !project.is_workspace_root())
+                .map(|x| redact_home(&x.path())),
+            is_virtual: project.is_virtual(),
+            python: project.target_python_version().map(|x| x.to_string()),
+            venv: redact_home(&project.venv_path()),
+            venv_python: get_current_venv_python_version(&project.venv_path())
+                .map(|x| x.to_string()),
+            lockfiles: [false, true]
+                .into_iter()
+                .map(|dev| project.lockfile_path(dev))
+                .filter(|x| x.is_file())
+                .map(|x| redact_home(&x))
+                .collect(),
+        }),
+    };
+
+    if let Some(Format::Json) = args.format {
+        serde_json::to_writer_pretty(std::io::stdout().lock(), &info)?;
+        echo!();
+        return Ok(());
+    }
+
+    echo!("rye {}", info.version);
+    echo!("commit: {}", info.commit);
+    match info.platform.libc {
+        Some(libc) => echo!(
+            "platform: {} ({}, {})",
+            info.platform.os,
+            info.platform.arch,
+            libc
+        ),
+        None => echo!("platform: {} ({})", info.platform.os, info.platform.arch),
+    }
+    echo!(
+        "self-python: {}",
+        info.self_python.as_deref().unwrap_or("not bootstrapped")
+    );
+    match info.self_venv_version {
+        Some(version) if version == info.self_venv_target_version => {
+            echo!("self-venv: version {} (up to date)", version);
+        }
+        Some(version) => echo!(
+            "self-venv: version {} (outdated, expected {})",
+            version,
+            info.self_venv_target_version
+        ),
+        None => echo!("self-venv: not bootstrapped"),
+    }
+    echo!("uv: {}", info.uv.as_deref().unwrap_or("not installed"));
+    echo!("symlink support: {}", info.symlink_support);
+
+    echo!();
+    echo!("{} ({})", style("config").bold(), info.config_path);
+    if info.config.is_empty() {
+        echo!("  no keys set");
+    }
+    for (key, entry) in &info.config {
+        echo!("  {} = {} ({})", key, entry.value, entry.origin);
+    }
+
+    echo!();
+    match info.project {
+        Some(project) => {
+            echo!("{} ({})", style("project").bold(), project.path);
+            echo!("  name: {}", project.name.as_deref().unwrap_or("unknown"));
+            if let Some(workspace) = project.workspace {
+                echo!("  workspace: {}", workspace);
+            }
+            echo!("  virtual: {}", project.is_virtual);
+            echo!(
+                "  python: {}",
+                project.python.as_deref().unwrap_or("not pinned")
+            );
+            echo!(
+                "  venv: {} ({})",
+                project.venv,
+                project.venv_python.as_deref().unwrap_or("not synced")
+            );
+            echo!(
+                "  lockfiles: {}",
+                if project.lockfiles.is_empty() {
+                    "none".to_string()
+                } else {
+                    project.lockfiles.join(", ")
+                }
+            );
+        }
+        None => echo!("{}: none", style("project").bold()),
+    }
+    Ok(())
+}
+
+fn uv(args: UvCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(args.quiet, args.verbose);
+    if args.reinstall {
+        Uv::reinstall(output)?;
+    } else {
+        // makes sure uv is installed and intact
+        UvBuilder::new().with_output(output).ensure_exists()?;
+    }
+    let version = Uv::installed_version()?.ok_or_else(|| anyhow!("uv is not installed"))?;
+    if args.reinstall {
+        echo!(if output, "Reinstalled {}", style(&version).cyan());
+    }
+    echo!(if output, "path: {}", Uv::installed_path()?.display());
+    echo!(if output, "version: {}", version);
+    Ok(())
+}
+
+/// Abbreviates the home folder in a path to `~`.
+fn redact_home(path: &Path) -> String {
+    match home::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) => Path::new("~").join(rest).display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+/// Redacts secrets from a config value.
+///
+/// Values of keys that look like they hold secrets are replaced entirely,
+/// for URLs only the password is removed.
+fn redact_json(key: &str, value: &mut serde_json::Value) {
+    let key = key.rsplit('.').next().unwrap_or(key);
+    if ["password", "token", "secret"]
+        .iter()
+        .any(|x| key.contains(x))
+        && !value.is_null()
+    {
+        *value = serde_json::Value::String("****".into());
+        return;
+    }
+    match value {
+        serde_json::Value::String(s) => {
+            if let Ok(mut url) = Url::parse(s) {
+                if url.password().is_some() {
+                    url.set_password(Some("****")).ok();
+                    *s = url.to_string();
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                redact_json(key, item);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                redact_json(key, item);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the path where the binary replaced by an update is kept.
+fn get_previous_exe_path() -> PathBuf {
+    get_app_dir()
+        .join("previous")
+        .join("rye")
+        .with_extension(EXE_EXTENSION)
+}
+
+/// Keeps a copy of the current binary at `previous_exe` for `rye self rollback`.
+fn backup_current_exe(current_exe: &Path, previous_exe: &Path) -> Result<(), Error> {
+    if let Some(parent) = previous_exe.parent() {
+        fs::create_dir_all(parent).path_context(parent, "unable to create folder")?;
+    }
+    fs::copy(current_exe, previous_exe)
+        .path_context(previous_exe, "unable to keep the previous version")?;
+    fs::write(
+        previous_exe.with_extension("version"),
+        env!("CARGO_PKG_VERSION"),
+    )
+    .path_context(&previous_exe, "unable to keep the previous version")?;
+    Ok(())
+}
+
 fn update_exe_and_shims(new_exe: &Path) -> Result<(), Error> {
     let app_dir = get_app_dir().canonicalize()?;
     let current_exe = env::current_exe()?.canonicalize()?;
@@ -393,6 +1034,7 @@ fn uninstall(args: UninstallCommand) -> Result<(), Error> {
         remove_dir_all_if_exists(&app_dir.join("pip-tools"))?;
         remove_dir_all_if_exists(&app_dir.join("uv"))?;
         remove_dir_all_if_exists(&app_dir.join("tools"))?;
+        remove_dir_all_if_exists(&app_dir.join("previous"))?;
 
         // special deleting logic if we are placed in the app dir and the shim deletion
         // did not succeed.  This is likely the case on windows where we then use the
@@ -795,3 +1437,183 @@ pub fn auto_self_install() -> Result<bool, Error> {
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(args: &[&str], channel: UpdateChannel, url_template: Option<&str>) -> UpdateSource {
+        UpdateCommand::try_parse_from(Some("update").into_iter().chain(args.iter().copied()))
+            .unwrap()
+            .source(channel, url_template)
+    }
+
+    fn release(version: &str, url: String) -> UpdateSource {
+        UpdateSource::Release {
+            version: version.to_string(),
+            url,
+        }
+    }
+
+    #[test]
+    fn test_update_source_release() {
+        let target = format!("{ARCH}-{OS}");
+        let ext = if cfg!(unix) { ".gz" } else { ".exe" };
+        assert_eq!(
+            source(&[], UpdateChannel::Stable, None),
+            release(
+                "latest",
+                format!("{GITHUB_REPO}/releases/latest/download/rye-{target}{ext}")
+            )
+        );
+        let pinned = release(
+            "0.30.0",
+            format!("{GITHUB_REPO}/releases/download/0.30.0/rye-{target}{ext}"),
+        );
+        assert_eq!(
+            source(&["--version", "0.30.0"], UpdateChannel::Stable, None),
+            pinned
+        );
+        // a pinned version wins over the configured channel
+        assert_eq!(
+            source(&["--version", "0.30.0"], UpdateChannel::Nightly, None),
+            pinned
+        );
+        assert_eq!(
+            source(
+                &[],
+                UpdateChannel::Stable,
+                Some("https://mirror.invalid/rye/{version}/rye-{target}{ext}")
+            ),
+            release(
+                "latest",
+                format!("https://mirror.invalid/rye/latest/rye-{target}{ext}")
+            )
+        );
+    }
+
+    #[test]
+    fn test_update_source_custom_url() {
+        let target = format!("{ARCH}-{OS}");
+        let ext = if cfg!(unix) { ".gz" } else { ".exe" };
+        let template = "https://mirror.invalid/rye/{version}/rye-{target}{ext}?v={version}";
+        assert_eq!(
+            source(
+                &["--version", "0.30.0"],
+                UpdateChannel::Stable,
+                Some(template)
+            ),
+            release(
+                "0.30.0",
+                format!("https://mirror.invalid/rye/0.30.0/rye-{target}{ext}?v=0.30.0")
+            )
+        );
+        // git sources do not use the download URL
+        assert_eq!(
+            source(&["--branch", "main"], UpdateChannel::Stable, Some(template)),
+            UpdateSource::Git {
+                kind: "--branch",
+                name: "main".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_update_credentials_require_https() {
+        let credentials = (Some("user".to_string()), "hunter2".to_string());
+        let err = download_url_with_credentials(
+            "http://mirror.invalid/rye/latest/rye",
+            Some(&credentials),
+            CommandOutput::Quiet,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Refusing insecure download");
+    }
+
+    #[test]
+    fn test_update_source_git() {
+        assert_eq!(
+            source(&[], UpdateChannel::Nightly, None),
+            UpdateSource::Git {
+                kind: "--branch",
+                name: "main".into()
+            }
+        );
+        assert_eq!(
+            source(&["--tag", "0.30.0"], UpdateChannel::Stable, None),
+            UpdateSource::Git {
+                kind: "--tag",
+                name: "0.30.0".into()
+            }
+        );
+        assert_eq!(
+            source(&["--rev", "abc123"], UpdateChannel::Nightly, None),
+            UpdateSource::Git {
+                kind: "--rev",
+                name: "abc123".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_replace_and_validate_restores_previous_exe() {
+        let dir = tempfile::tempdir().unwrap();
+        let current_exe = dir.path().join("rye");
+        let previous_exe = dir.path().join("previous");
+        let new_exe = dir.path().join("new");
+        fs::write(&current_exe, "old").unwrap();
+        fs::write(&previous_exe, "old").unwrap();
+        fs::write(&new_exe, "new").unwrap();
+        let replace = |path: &Path| -> Result<(), Error> {
+            fs::copy(path, &current_exe)?;
+            Ok(())
+        };
+
+        let err = replace_and_validate(&new_exe, &previous_exe, replace, || {
+            bail!("failed to run python shim in test project")
+        })
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("failed to run python shim"));
+        assert_eq!(fs::read_to_string(&current_exe).unwrap(), "old");
+
+        replace_and_validate(&new_exe, &previous_exe, replace, || Ok(())).unwrap();
+        assert_eq!(fs::read_to_string(&current_exe).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_rollback_swaps_binaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let current_exe = dir.path().join("rye");
+        let previous_exe = dir.path().join("previous").join("rye");
+        let replace = |path: &Path| -> Result<(), Error> {
+            fs::copy(path, &current_exe)?;
+            Ok(())
+        };
+        fs::write(&current_exe, "new").unwrap();
+
+        let err = swap_with_previous_exe(&current_exe, &previous_exe, replace).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no previous version of rye to roll back to"
+        );
+
+        backup_current_exe(&current_exe, &previous_exe).unwrap();
+        fs::write(&previous_exe, "old").unwrap();
+        fs::write(previous_exe.with_extension("version"), "0.32.0").unwrap();
+        assert_eq!(
+            swap_with_previous_exe(&current_exe, &previous_exe, replace).unwrap(),
+            "0.32.0"
+        );
+        assert_eq!(fs::read_to_string(&current_exe).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&previous_exe).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(previous_exe.with_extension("version")).unwrap(),
+            env!("CARGO_PKG_VERSION")
+        );
+
+        // rolling back again undoes the rollback
+        swap_with_previous_exe(&current_exe, &previous_exe, replace).unwrap();
+        assert_eq!(fs::read_to_string(&current_exe).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&previous_exe).unwrap(), "old");
+    }
+}
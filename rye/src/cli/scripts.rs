@@ -0,0 +1,174 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Error};
+use clap::{Parser, ValueEnum};
+use console::style;
+use serde::Serialize;
+use toml_edit::{InlineTable, Value};
+
+use crate::pyproject::PyProject;
+use crate::utils::CommandOutput;
+
+/// Manage the scripts of the project.
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: SubCommand,
+}
+
+/// Adds a script to `tool.rye.scripts`.
+#[derive(Parser, Debug)]
+pub struct AddCommand {
+    /// The name of the script.
+    name: String,
+    /// The command to run (for instance `"uvicorn app:app --reload"`).
+    cmd: String,
+    /// Call a Python function (`module:function`) instead of running a command.
+    #[arg(long)]
+    call: bool,
+    /// A description of the script shown by `rye run --list`.
+    #[arg(long)]
+    description: Option<String>,
+    /// Replace an existing script of the same name.
+    #[arg(short, long)]
+    force: bool,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Turns off all output.
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Removes a script from `tool.rye.scripts`.
+#[derive(Parser, Debug)]
+pub struct RemoveCommand {
+    /// The name of the script.
+    name: String,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Turns off all output.
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Lists the scripts of `tool.rye.scripts`.
+#[derive(Parser, Debug)]
+pub struct ListCommand {
+    /// Request parseable output format
+    #[arg(long)]
+    format: Option<Format>,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+enum Format {
+    Json,
+}
+
+/// Output structure for scripts list --format=json
+#[derive(Serialize)]
+struct ListScript {
+    name: String,
+    cmd: String,
+    description: Option<String>,
+    depends: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+enum SubCommand {
+    Add(AddCommand),
+    Remove(RemoveCommand),
+    List(ListCommand),
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    match cmd.command {
+        SubCommand::Add(cmd) => add(cmd),
+        SubCommand::Remove(cmd) => remove(cmd),
+        SubCommand::List(cmd) => list(cmd),
+    }
+}
+
+fn add(cmd: AddCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, false);
+    let mut pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    if !cmd.force && pyproject.declared_scripts().contains(&cmd.name) {
+        bail!(
+            "script '{}' already exists. Use --force to replace it.",
+            cmd.name
+        );
+    }
+    if !cmd.call && shlex::split(&cmd.cmd).map_or(true, |x| x.is_empty()) {
+        bail!("invalid command '{}'", cmd.cmd);
+    }
+
+    let script = if cmd.call || cmd.description.is_some() {
+        let mut table = InlineTable::new();
+        table.insert(if cmd.call { "call" } else { "cmd" }, cmd.cmd.into());
+        if let Some(description) = cmd.description {
+            table.insert("description", description.into());
+        }
+        Value::InlineTable(table)
+    } else {
+        cmd.cmd.into()
+    };
+    pyproject.set_script(&cmd.name, script)?;
+    pyproject.save()?;
+
+    echo!(if output, "Added script {}", style(&cmd.name).cyan());
+    Ok(())
+}
+
+fn remove(cmd: RemoveCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, false);
+    let mut pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    if !pyproject.remove_script(&cmd.name) {
+        bail!("script '{}' does not exist", cmd.name);
+    }
+    pyproject.save()?;
+
+    echo!(if output, "Removed script {}", style(&cmd.name).cyan());
+    Ok(())
+}
+
+fn list(cmd: ListCommand) -> Result<(), Error> {
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let scripts = pyproject
+        .declared_scripts()
+        .into_iter()
+        .filter_map(|name| {
+            let script = pyproject.get_declared_script_cmd(&name)?;
+            let options = pyproject.get_script_options(&name);
+            Some(ListScript {
+                cmd: script.to_string(),
+                description: options.description,
+                depends: options.depends,
+                name,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(Format::Json) = cmd.format {
+        echo!("{}", serde_json::to_string_pretty(&scripts)?);
+        return Ok(());
+    }
+
+    for script in scripts {
+        echo!("{} ({})", script.name, style(&script.cmd).dim());
+        if let Some(description) = script.description {
+            echo!("  {}", description);
+        }
+        if !script.depends.is_empty() {
+            echo!(
+                "  {}",
+                style(format!("depends on: {}", script.depends.join(", "))).dim()
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use std::env::{self, join_paths, split_paths};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use console::style;
+use same_file::is_same_file;
+use tempfile::tempdir;
+
+use crate::consts::VENV_BIN;
+use crate::pyproject::PyProject;
+use crate::sync::{sync, SyncOptions};
+use crate::utils::{IoPathContext, QuietExit};
+
+/// Spawns a shell with the virtualenv activated.
+///
+/// The shell is picked from `SHELL` (or `COMSPEC` on Windows).  Leave it
+/// again with `exit`.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Do not show banner
+    #[arg(long)]
+    no_banner: bool,
+    /// Allow nested invocations.
+    #[arg(long)]
+    allow_nested: bool,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+}
+
+/// The shells that rye knows how to activate a virtualenv in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+    Cmd,
+    Other,
+}
+
+impl ShellKind {
+    fn from_path(path: &Path) -> ShellKind {
+        let name = path
+            .file_stem()
+            .map(|x| x.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match name.as_str() {
+            "bash" => ShellKind::Bash,
+            "zsh" => ShellKind::Zsh,
+            "fish" => ShellKind::Fish,
+            "pwsh" | "powershell" => ShellKind::PowerShell,
+            "cmd" => ShellKind::Cmd,
+            _ => ShellKind::Other,
+        }
+    }
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let venv_path = pyproject.venv_path().into_owned();
+
+    if !cmd.allow_nested {
+        if let Some(active) = env::var_os("RYE_SHELL") {
+            if is_same_file(&active, &venv_path).unwrap_or(false) {
+                bail!("already inside a rye shell for this project");
+            }
+            bail!(
+                "already inside a rye shell for {}. Leave it with 'exit' or pass --allow-nested",
+                Path::new(&active).display()
+            );
+        }
+    }
+
+    // make sure we have the minimal virtualenv.
+    sync(SyncOptions::python_only().pyproject(cmd.pyproject.clone()))
+        .context("failed to sync ahead of shell")?;
+
+    let shell = get_shell()?;
+    let kind = ShellKind::from_path(Path::new(&shell));
+    let venv_bin = venv_path.join(VENV_BIN);
+
+    // the activation scripts are sourced after the user's shell configuration
+    // so that they can also update the prompt.  The scratch folder has to stay
+    // alive until the shell exits.
+    let scratch = tempdir()?;
+    let mut shell_cmd = Command::new(&shell);
+    match kind {
+        ShellKind::Bash => {
+            let rcfile = scratch.path().join("bashrc");
+            fs::write(
+                &rcfile,
+                format!(
+                    "[ -f ~/.bashrc ] && . ~/.bashrc\n. {}\n",
+                    quote_posix(&venv_bin.join("activate"))
+                ),
+            )
+            .path_context(&rcfile, "unable to write shell configuration")?;
+            shell_cmd.arg("--rcfile").arg(&rcfile).arg("-i");
+        }
+        ShellKind::Zsh => {
+            let zdotdir = env::var_os("ZDOTDIR")
+                .or_else(|| env::var_os("HOME"))
+                .map(PathBuf::from)
+                .unwrap_or_default();
+            // zsh reads every startup file from the current ZDOTDIR, so the
+            // scratch .zshenv switches back to it after the user's one ran.
+            let zdotdir = quote_posix(&zdotdir);
+            for (name, contents) in [
+                (
+                    ".zshenv",
+                    format!(
+                        "_rye_zdotdir=\"$ZDOTDIR\"\nZDOTDIR={}\n\
+                         [ -f \"$ZDOTDIR/.zshenv\" ] && . \"$ZDOTDIR/.zshenv\"\n\
+                         ZDOTDIR=\"$_rye_zdotdir\"\nunset _rye_zdotdir\n",
+                        zdotdir
+                    ),
+                ),
+                (
+                    ".zshrc",
+                    format!(
+                        "ZDOTDIR={}\n[ -f \"$ZDOTDIR/.zshrc\" ] && . \"$ZDOTDIR/.zshrc\"\n. {}\n",
+                        zdotdir,
+                        quote_posix(&venv_bin.join("activate"))
+                    ),
+                ),
+            ] {
+                let path = scratch.path().join(name);
+                fs::write(&path, contents)
+                    .path_context(&path, "unable to write shell configuration")?;
+            }
+            shell_cmd.env("ZDOTDIR", scratch.path()).arg("-i");
+        }
+        ShellKind::Fish => {
+            shell_cmd.arg("-i").arg("-C").arg(format!(
+                "source {}",
+                quote_fish(&venv_bin.join("activate.fish"))
+            ));
+        }
+        ShellKind::PowerShell => {
+            let script = ["Activate.ps1", "activate.ps1"]
+                .iter()
+                .map(|x| venv_bin.join(x))
+                .find(|x| x.is_file())
+                .unwrap_or_else(|| venv_bin.join("Activate.ps1"));
+            shell_cmd
+                .arg("-NoLogo")
+                .arg("-NoExit")
+                .arg("-Command")
+                .arg(format!(". {}", quote_powershell(&script)));
+        }
+        ShellKind::Cmd => {
+            shell_cmd.arg("/k").arg(venv_bin.join("activate.bat"));
+        }
+        ShellKind::Other => {
+            shell_cmd.env("VIRTUAL_ENV", &venv_path);
+            if let Some(path) = env::var_os("PATH") {
+                let mut paths = split_paths(&path).collect::<Vec<_>>();
+                paths.insert(0, venv_bin.clone());
+                shell_cmd.env("PATH", join_paths(paths)?);
+            } else {
+                shell_cmd.env("PATH", &venv_bin);
+            }
+        }
+    }
+    shell_cmd.env("RYE_SHELL", &venv_path);
+    shell_cmd.env_remove("PYTHONHOME");
+
+    if !cmd.no_banner {
+        echo!(
+            "Spawning virtualenv shell from {}",
+            style(venv_path.display()).cyan()
+        );
+        echo!("Leave shell with 'exit'");
+    }
+
+    // the shell handles interrupts itself
+    crate::disable_ctrlc_handler();
+    let status = shell_cmd
+        .status()
+        .with_context(|| format!("unable to spawn {}", Path::new(&shell).display()))?;
+    drop(scratch);
+
+    if !status.success() {
+        return Err(Error::new(QuietExit(status.code().unwrap_or(1))));
+    }
+    Ok(())
+}
+
+fn get_shell() -> Result<OsString, Error> {
+    if cfg!(windows) {
+        // cmd.exe sets PROMPT, PowerShell does not.
+        if env::var_os("PROMPT").is_none() {
+            return Ok(OsString::from("powershell.exe"));
+        }
+        env::var_os("COMSPEC").context("unable to determine the shell (COMSPEC not set)")
+    } else {
+        Ok(env::var_os("SHELL").unwrap_or_else(|| OsString::from("/bin/sh")))
+    }
+}
+
+pub fn quote_posix(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+pub fn quote_fish(path: &Path) -> String {
+    format!(
+        "'{}'",
+        path.to_string_lossy()
+            .replace('\\', "\\\\")
+            .replace('\'', "\\'")
+    )
+}
+
+pub fn quote_powershell(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "''"))
+}
@@ -1,22 +1,32 @@
 use std::borrow::Cow;
 use std::convert::Infallible;
-use std::env;
 use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+use std::{env, fs};
 
 use anyhow::{anyhow, bail, Context, Error};
 use same_file::is_same_file;
+use serde::{Deserialize, Serialize};
 use std::process::Command;
 
 use crate::bootstrap::{ensure_self_venv, get_pip_runner};
 use crate::config::Config;
 use crate::consts::VENV_BIN;
-use crate::platform::{get_python_version_request_from_pyenv_pin, get_toolchain_python_bin};
+use crate::platform::{
+    get_app_dir, get_python_version_request_from_pyenv_pin, get_toolchain_python_bin,
+};
 use crate::pyproject::{latest_available_python_version, PyProject};
 use crate::sources::py::PythonVersionRequest;
 use crate::sync::{sync, SyncOptions};
 use crate::tui::redirect_to_stderr;
-use crate::utils::{exec_spawn, get_venv_python_bin, CommandOutput};
+use crate::utils::{
+    exec_spawn, get_short_executable_name, get_venv_python_bin, is_executable, CommandOutput,
+};
+
+/// The maximum number of shim targets that are remembered.
+const SHIM_CACHE_SIZE: usize = 256;
 
 fn detect_shim(args: &[OsString]) -> Option<String> {
     // Shims are detected if the executable is linked into
@@ -179,6 +189,20 @@ fn is_python_shim(target: &str) -> bool {
     matches_shim(target, "python") || matches_shim(target, "python3")
 }
 
+/// Returns the requested version of a versioned python shim like `python3.11`.
+fn get_versioned_python_shim(target: &str) -> Option<PythonVersionRequest> {
+    let name = if cfg!(windows) {
+        target.strip_suffix(".exe").unwrap_or(target)
+    } else {
+        target
+    };
+    let version = name.strip_prefix("python")?;
+    if !version.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    PythonVersionRequest::from_str(version).ok()
+}
+
 /// Figures out where a shim should point to.
 fn get_shim_target(
     target: &str,
@@ -279,6 +303,17 @@ fn get_shim_target(
             args.remove(1);
         }
         return Ok(Some(args));
+
+    // versioned shims (like python3.11) resolve to an installed toolchain
+    } else if let Some(version_request) = get_versioned_python_shim(target) {
+        if let Some(py_ver) = latest_available_python_version(&version_request) {
+            let py = get_toolchain_python_bin(&py_ver)?;
+            if py.is_file() {
+                let mut args = args.to_vec();
+                args[0] = py.into();
+                return Ok(Some(args));
+            }
+        }
     }
 
     // if we make it this far, we did not find a shim in the project, look for
@@ -312,12 +347,186 @@ fn matches_shim(s: &str, reference: &str) -> bool {
     .eq_ignore_ascii_case(reference)
 }
 
+/// A shim target resolved within a project.
+///
+/// It is reused for the same shim and directory as long as none of the files
+/// that influence the resolution were created, removed or modified.  This
+/// skips the project discovery and sync which otherwise happen on every
+/// invocation of a shim.
+#[derive(Serialize, Deserialize)]
+struct ShimCacheEntry {
+    dir: PathBuf,
+    shim: String,
+    target: PathBuf,
+    validators: Vec<(PathBuf, Option<u128>)>,
+    /// The root of the project the shim was invoked in.
+    #[serde(default)]
+    project: Option<PathBuf>,
+    /// The value of `behavior.venv-in-project` the target was resolved with.
+    /// It can also come from the environment, which no validator covers.
+    #[serde(default)]
+    venv_in_project: Option<bool>,
+}
+
+fn get_shim_cache_path() -> PathBuf {
+    get_app_dir().join("shim-cache.json")
+}
+
+fn get_mtime(path: &Path) -> Option<u128> {
+    fs::metadata(path)
+        .and_then(|x| x.modified())
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_nanos())
+}
+
+fn load_shim_cache() -> Vec<ShimCacheEntry> {
+    fs::read(get_shim_cache_path())
+        .ok()
+        .and_then(|x| serde_json::from_slice(&x).ok())
+        .unwrap_or_default()
+}
+
+fn lookup_shim_cache(dir: &Path, shim: &str) -> Option<PathBuf> {
+    let venv_in_project = Config::current().venv_in_project();
+    load_shim_cache()
+        .into_iter()
+        .find(|x| x.dir == dir && x.shim == shim)
+        .filter(|x| {
+            x.venv_in_project == Some(venv_in_project)
+                && x.validators
+                    .iter()
+                    .all(|(path, mtime)| get_mtime(path) == *mtime)
+        })
+        .map(|x| x.target)
+}
+
+/// Remembers the target of a shim.  This is best effort, failing to write the
+/// cache only means that the next invocation resolves the shim again.
+fn update_shim_cache(dir: &Path, shim: &str, target: &Path, pyproject: &PyProject) {
+    // a project file, config layer or version pin in any of the parent
+    // folders can change which project (and interpreter) is picked.
+    let mut validators = Vec::new();
+    for path in dir
+        .ancestors()
+        .flat_map(|x| {
+            [
+                x.join("pyproject.toml"),
+                x.join("rye.toml"),
+                x.join(".python-version"),
+            ]
+        })
+        .chain([
+            pyproject.venv_path().join("rye-venv.json"),
+            target.to_path_buf(),
+            Config::current().path().to_path_buf(),
+        ])
+    {
+        let mtime = get_mtime(&path);
+        validators.push((path, mtime));
+    }
+
+    let mut entries = load_shim_cache();
+    entries.retain(|x| !(x.dir == dir && x.shim == shim));
+    entries.insert(
+        0,
+        ShimCacheEntry {
+            dir: dir.to_path_buf(),
+            shim: shim.to_string(),
+            target: target.to_path_buf(),
+            validators,
+            project: Some(pyproject.root_path().to_path_buf()),
+            venv_in_project: Some(Config::current().venv_in_project()),
+        },
+    );
+    entries.truncate(SHIM_CACHE_SIZE);
+
+    // write to a temporary file first so that concurrently invoked shims
+    // never see a partially written cache.
+    let path = get_shim_cache_path();
+    let tmp = path.with_extension(format!("json.{}", std::process::id()));
+    if let Ok(contents) = serde_json::to_vec(&entries) {
+        if fs::write(&tmp, contents).is_ok() && fs::rename(&tmp, &path).is_err() {
+            fs::remove_file(&tmp).ok();
+        }
+    }
+}
+
+/// Returns the projects that shims were recently invoked in.
+pub fn list_shim_projects() -> Vec<PathBuf> {
+    let mut rv = load_shim_cache()
+        .into_iter()
+        .filter_map(|x| x.project)
+        .collect::<Vec<_>>();
+    rv.sort();
+    rv.dedup();
+    rv
+}
+
+/// Returns the names of the shims that resolve into the virtualenv of a project.
+///
+/// Unlike invoking a shim this does not sync the project first.
+pub fn list_project_shims(pyproject: &PyProject) -> Vec<String> {
+    let folder = pyproject.venv_path().join(VENV_BIN);
+    if !folder.is_dir() {
+        return Vec::new();
+    }
+    let mut rv = fs::read_dir(get_app_dir().join("shims"))
+        .ok()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_executable(path))
+        .map(|path| get_short_executable_name(&path))
+        .filter(|name| {
+            // pip is always provided for projects, see `get_shim_target`
+            matches_shim(name, "pip")
+                || matches_shim(name, "pip3")
+                || (cfg!(windows) && matches_shim(name, "python3"))
+                || which::which_in_global(name, Some(&folder))
+                    .map_or(false, |mut x| x.next().is_some())
+        })
+        .collect::<Vec<_>>();
+    rv.sort();
+    rv
+}
+
+/// Removes all remembered shim targets.
+pub fn clear_shim_cache() {
+    fs::remove_file(get_shim_cache_path()).ok();
+}
+
 /// This replaces ourselves with the shim target for when the
 /// executable is invoked as a shim executable.
 pub fn execute_shim(args: &[OsString]) -> Result<(), Error> {
     if let Some(shim_name) = detect_shim(args) {
+        let cwd = env::current_dir().ok();
+        let explicit_python = is_python_shim(&shim_name)
+            && args
+                .get(1)
+                .and_then(|x| x.to_str())
+                .map_or(false, |x| x.starts_with('+'));
+        let cache_dir = cwd.filter(|_| Config::current().shim_cache() && !explicit_python);
+
+        if let Some(ref dir) = cache_dir {
+            if let Some(target) = lookup_shim_cache(dir, &shim_name) {
+                let mut args = args.to_vec();
+                args[0] = target.into();
+                match spawn_shim(args)? {}
+            }
+        }
+
         let pyproject = PyProject::discover().ok();
         if let Some(args) = get_shim_target(&shim_name, args, pyproject.as_ref())? {
+            // only targets within the virtualenv are remembered, everything
+            // else depends on more than the project files.
+            if let (Some(dir), Some(pyproject)) = (cache_dir, pyproject.as_ref()) {
+                let target = Path::new(&args[0]);
+                if target.starts_with(pyproject.venv_path().join(VENV_BIN)) {
+                    update_shim_cache(&dir, &shim_name, target, pyproject);
+                }
+            }
             match spawn_shim(args)? {}
         } else if is_python_shim(&shim_name) {
             if pyproject.is_some() {
@@ -0,0 +1,66 @@
+use std::env;
+use std::env::consts::EXE_EXTENSION;
+use std::fs;
+
+use anyhow::Error;
+use clap::Parser;
+use console::style;
+
+use crate::bootstrap::update_core_shims;
+use crate::cli::shim::clear_shim_cache;
+use crate::platform::get_app_dir;
+use crate::utils::{CommandOutput, IoPathContext};
+
+/// Helper utility to manage the shims.
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: SubCommand,
+}
+
+/// Recreates the shims, including the extra shims from `shims.extra`.
+#[derive(Parser, Debug)]
+pub struct SyncCommand {
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+#[derive(Parser, Debug)]
+enum SubCommand {
+    Sync(SyncCommand),
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    match cmd.command {
+        SubCommand::Sync(args) => sync(args),
+    }
+}
+
+fn sync(cmd: SyncCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let shims = get_app_dir().join("shims");
+    if !shims.is_dir() {
+        fs::create_dir_all(&shims).path_context(&shims, "tried to create shim folder")?;
+    }
+
+    // if rye is itself installed into the shims folder, we want to
+    // use that.  Otherwise we fall back to the current executable
+    let mut this = shims.join("rye").with_extension(EXE_EXTENSION);
+    if !this.is_file() {
+        this = env::current_exe()?;
+    }
+    let linked = update_core_shims(&shims, &this)?;
+    clear_shim_cache();
+
+    if output != CommandOutput::Quiet {
+        echo!("Updated shims in {}", style(shims.display()).dim());
+        for name in linked {
+            echo!("  - {}", style(name).cyan());
+        }
+    }
+    Ok(())
+}
@@ -2,10 +2,22 @@ use std::path::Path;
 use std::path::PathBuf;
 
 use anyhow::Error;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use console::style;
+use serde::Serialize;
 
+use crate::cli::shim::list_project_shims;
+use crate::consts::VENV_BIN;
+use crate::platform::get_toolchain_build_flavor;
 use crate::pyproject::{get_current_venv_python_version, PyProject};
+use crate::sources::py::PythonVersion;
+use crate::sync::{venv_status, VenvStatus};
+use crate::utils::{dir_size, format_size, get_venv_python_bin};
+
+/// Version of the JSON output of `rye show --format json`.
+///
+/// Fields may be added without a bump, removals or changes in meaning bump it.
+const SCHEMA_VERSION: u32 = 1;
 
 /// Prints the current state of the project.
 #[derive(Parser, Debug)]
@@ -13,24 +25,85 @@ pub struct Args {
     /// Print the installed dependencies from the venv
     #[arg(long)]
     installed_deps: bool,
+    /// Request parseable output format
+    #[arg(long, conflicts_with = "installed_deps")]
+    format: Option<Format>,
     /// Use this pyproject.toml file
     #[arg(long, value_name = "PYPROJECT_TOML")]
     pyproject: Option<PathBuf>,
 }
 
+#[derive(ValueEnum, Copy, Clone, Serialize, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+enum Format {
+    Json,
+}
+
+/// Output structure for show --format=json
+#[derive(Serialize)]
+struct ProjectInfo {
+    schema_version: u32,
+    name: Option<String>,
+    version: Option<String>,
+    root: PathBuf,
+    pyproject: PathBuf,
+    #[serde(rename = "virtual")]
+    is_virtual: bool,
+    workspace: Option<PathBuf>,
+    target_python: Option<String>,
+    lockfiles: Lockfiles,
+    venv: VenvInfo,
+}
+
+#[derive(Serialize)]
+struct Lockfiles {
+    production: PathBuf,
+    dev: PathBuf,
+}
+
+#[derive(Serialize)]
+struct VenvInfo {
+    path: PathBuf,
+    status: VenvStatus,
+    python: PathBuf,
+    toolchain: PythonVersion,
+    toolchain_flavor: Option<&'static str>,
+    python_version: String,
+    site_packages: Option<PathBuf>,
+    bin: PathBuf,
+    size: Option<u64>,
+    shims: Vec<String>,
+}
+
 pub fn execute(cmd: Args) -> Result<(), Error> {
     if cmd.installed_deps {
         warn!("--installed-deps is deprecated, use `rye list`");
         return crate::cli::list::execute(crate::cli::list::Args {
+            outdated: false,
+            format: Some(crate::cli::list::Format::Freeze),
             pyproject: cmd.pyproject,
         });
     }
 
     let project = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    if let Some(Format::Json) = cmd.format {
+        return print_json(&project);
+    }
+
     echo!(
         "project: {}",
         style(project.name().unwrap_or("<unnamed>")).yellow()
     );
+    match project.vcs_version() {
+        Ok(Some(version)) => echo!("version: {} (from git)", style(version).cyan()),
+        Ok(None) => {
+            if let Some(version) = project.declared_version() {
+                echo!("version: {}", style(version).cyan());
+            }
+        }
+        Err(err) => echo!("version: {}", style(err).red()),
+    }
     echo!("path: {}", style(project.root_path().display()).cyan());
     echo!("venv: {}", style(project.venv_path().display()).cyan());
     if let Some(ver) = project.target_python_version() {
@@ -44,8 +117,49 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
             }
         }
     }
+    match venv_status(&project) {
+        Ok(VenvStatus::InSync) => echo!("venv status: {}", style("in sync").green()),
+        Ok(VenvStatus::Outdated) => echo!(
+            "venv status: {}",
+            style("out of sync with the lockfile (run `rye sync`)").red()
+        ),
+        Ok(VenvStatus::PythonMismatch) => echo!(
+            "venv status: {}",
+            style("python mismatch (run `rye sync`)").red()
+        ),
+        Ok(VenvStatus::Unmanaged) => echo!("venv status: {}", style("not managed by rye").red()),
+        Ok(VenvStatus::Missing) => echo!("venv status: {}", style("missing").yellow()),
+        Err(err) => echo!("venv status: {}", style(err).red()),
+    }
+    let venv = project.venv_path();
+    if venv.is_dir() {
+        echo!("venv size: {}", style(format_size(dir_size(&venv))).cyan());
+    }
+    if let Some(toolchain) = get_current_venv_python_version(&venv) {
+        match get_toolchain_build_flavor(&toolchain) {
+            Some(flavor) => echo!("toolchain: {} ({})", style(&toolchain).cyan(), flavor),
+            None => echo!("toolchain: {}", style(&toolchain).cyan()),
+        }
+    }
+    let shims = list_project_shims(&project);
+    if !shims.is_empty() {
+        echo!("shims: {}", style(shims.join(", ")).cyan());
+    }
     echo!("virtual: {}", style(project.is_virtual()).cyan());
 
+    let envs = project.envs()?;
+    if !envs.is_empty() {
+        echo!("environments:");
+        for env in envs {
+            let env_project = project.clone().with_env(Some(&env.name))?;
+            echo!(
+                "  {} ({})",
+                style(&env.name).cyan(),
+                style(env_project.venv_path().display()).dim()
+            );
+        }
+    }
+
     if let Some(workspace) = project.workspace() {
         echo!(
             "workspace: {}",
@@ -87,3 +201,63 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
 
     Ok(())
 }
+
+fn print_json(project: &PyProject) -> Result<(), Error> {
+    let venv = project.venv_path();
+    // the venv describes what the editor finds on disk, so prefer the python
+    // of the last sync over the one the project asks for.
+    let toolchain = match get_current_venv_python_version(&venv) {
+        Some(ver) => ver,
+        None => project.venv_python_version()?,
+    };
+    let workspace_path = project.workspace_path();
+    let info = ProjectInfo {
+        schema_version: SCHEMA_VERSION,
+        name: project.name().map(|x| x.to_string()),
+        version: match project.vcs_version()? {
+            Some(version) => Some(version.to_string()),
+            None => project.declared_version().map(|x| x.to_string()),
+        },
+        root: project.root_path().into_owned(),
+        pyproject: project.toml_path().into_owned(),
+        is_virtual: project.is_virtual(),
+        workspace: project.workspace().map(|_| workspace_path.to_path_buf()),
+        target_python: project.target_python_version().map(|x| x.to_string()),
+        lockfiles: Lockfiles {
+            production: project.lockfile_path(false),
+            dev: project.lockfile_path(true),
+        },
+        venv: VenvInfo {
+            path: venv.to_path_buf(),
+            status: venv_status(project)?,
+            python: get_venv_python_bin(&venv),
+            python_version: format!(
+                "{}.{}.{}",
+                toolchain.major, toolchain.minor, toolchain.patch
+            ),
+            site_packages: find_site_packages(&venv),
+            bin: venv.join(VENV_BIN),
+            size: venv.is_dir().then(|| dir_size(&venv)),
+            shims: list_project_shims(project),
+            toolchain_flavor: get_toolchain_build_flavor(&toolchain).map(|x| x.as_str()),
+            toolchain,
+        },
+    };
+    serde_json::to_writer_pretty(std::io::stdout().lock(), &info)?;
+    echo!();
+    Ok(())
+}
+
+/// Locates the site-packages folder of a virtualenv if it exists.
+fn find_site_packages(venv: &Path) -> Option<PathBuf> {
+    if cfg!(windows) {
+        let rv = venv.join("Lib").join("site-packages");
+        return Some(rv).filter(|x| x.is_dir());
+    }
+    // there is a single folder within lib, pythonX.Y or pypyX.Y
+    std::fs::read_dir(venv.join("lib"))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("site-packages"))
+        .find(|x| x.is_dir())
+}
@@ -1,10 +1,13 @@
 use std::path::PathBuf;
 
-use anyhow::Error;
+use anyhow::{bail, Error};
 use clap::Parser;
 
-use crate::lock::LockOptions;
-use crate::sync::{sync, SyncMode, SyncOptions};
+use crate::lock::{parse_exclude_newer, parse_python_platform, LockOptions};
+use crate::pyproject::PyProject;
+use crate::sync::{sync, sync_check, sync_dry_run, SyncMode, SyncOptions, SyncScope, SyncSnapshot};
+use crate::timings;
+use crate::tui::Summary;
 use crate::utils::CommandOutput;
 
 /// Updates the virtualenv based on the pyproject.toml
@@ -13,9 +16,38 @@ pub struct Args {
     /// Force the environment to be re-created
     #[arg(short, long)]
     force: bool,
+    /// Rebuild the virtualenv from the lockfiles without updating them.
+    ///
+    /// The previous virtualenv is restored if this fails.
+    #[arg(
+        long,
+        conflicts_with = "force",
+        conflicts_with = "update",
+        conflicts_with = "update_all",
+        conflicts_with = "check",
+        conflicts_with = "dry_run"
+    )]
+    recreate: bool,
     /// Do not include dev dependencies.
     #[arg(long)]
     no_dev: bool,
+    /// Only install the dev dependencies and what they depend on.
+    #[arg(long, conflicts_with = "no_dev")]
+    only_dev: bool,
+    /// Only install a dependency group (`dev` or an optional dependency section).
+    #[arg(long, value_name = "GROUP", conflicts_with = "only_dev")]
+    only: Option<String>,
+    /// Only install a workspace member and what it depends on.
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with = "only_dev",
+        conflicts_with = "only"
+    )]
+    member: Option<String>,
+    /// Install the dependencies but not the projects themselves.
+    #[arg(long)]
+    no_install_project: bool,
     /// Do not update the lockfile.
     #[arg(long)]
     no_lock: bool,
@@ -25,6 +57,15 @@ pub struct Args {
     /// Turns off all output.
     #[arg(short, long, conflicts_with = "verbose")]
     quiet: bool,
+    /// Only print a summary of the changes once done (for CI logs).
+    #[arg(
+        long,
+        conflicts_with = "verbose",
+        conflicts_with = "quiet",
+        conflicts_with = "check",
+        conflicts_with = "dry_run"
+    )]
+    summary: bool,
     /// Update a specific package.
     #[arg(long)]
     update: Vec<String>,
@@ -43,26 +84,72 @@ pub struct Args {
     /// Set to true to lock with sources in the lockfile.
     #[arg(long)]
     with_sources: bool,
+    /// Write the hashes of all packages into the lockfile.
+    #[arg(long)]
+    generate_hashes: bool,
+    /// Lock for another platform (for instance `linux/amd64` or `x86_64-unknown-linux-gnu`).
+    #[arg(long, alias = "platform", value_name = "PLATFORM", value_parser = parse_python_platform)]
+    python_platform: Option<String>,
+    /// Only consider packages uploaded before this date (or RFC 3339 timestamp).
+    #[arg(long, value_name = "DATE", value_parser = parse_exclude_newer)]
+    exclude_newer: Option<String>,
+    /// Resolve packages again that are locked to yanked releases.
+    #[arg(long)]
+    exclude_yanked: bool,
+    /// Show the full resolver output if the dependencies cannot be resolved.
+    #[arg(long)]
+    explain: bool,
     /// Use this pyproject.toml file
     #[arg(long, value_name = "PYPROJECT_TOML")]
     pyproject: Option<PathBuf>,
+    /// Sync the named environment from `tool.rye.envs` instead of the default one.
+    #[arg(long, value_name = "NAME")]
+    env: Option<String>,
+    /// Recreate the virtualenv without prompting if its toolchain changed.
+    #[arg(short, long)]
+    yes: bool,
     /// Do not reuse (reset) prior lock options.
     #[arg(long)]
     reset: bool,
+    /// Verify that the lockfiles and the virtualenv are up to date without changing them.
+    #[arg(
+        long,
+        conflicts_with = "force",
+        conflicts_with = "no_lock",
+        conflicts_with = "update",
+        conflicts_with = "update_all"
+    )]
+    check: bool,
+    /// Print the changes to the lockfiles and the virtualenv without applying them.
+    #[arg(long, conflicts_with = "check", conflicts_with = "force")]
+    dry_run: bool,
+    /// Resolve up to this many lockfiles at once.
+    #[arg(short, long, default_value_t = 1, value_parser = parse_jobs)]
+    jobs: usize,
+    /// Print how long the phases of the sync took, or write them to a Chrome trace file.
+    #[arg(
+        long,
+        value_name = "TRACE_FILE",
+        num_args = 0..=1,
+        conflicts_with = "check",
+        conflicts_with = "dry_run"
+    )]
+    timings: Option<Option<PathBuf>>,
 }
 
 pub fn execute(cmd: Args) -> Result<(), Error> {
-    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
-    sync(SyncOptions {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet || cmd.summary, cmd.verbose);
+    let options = SyncOptions {
         output,
-        dev: !cmd.no_dev,
-        mode: if cmd.force {
+        // other dependency groups are part of the production lockfile
+        dev: !cmd.no_dev && cmd.only.as_deref().map_or(true, |x| x == "dev"),
+        mode: if cmd.force || cmd.recreate {
             SyncMode::Full
         } else {
             SyncMode::Regular
         },
-        force: cmd.force,
-        no_lock: cmd.no_lock,
+        force: cmd.force || cmd.recreate,
+        no_lock: cmd.no_lock || cmd.recreate,
         lock_options: LockOptions {
             update: cmd.update,
             update_all: cmd.update_all,
@@ -70,9 +157,66 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
             features: cmd.features,
             all_features: cmd.all_features,
             with_sources: cmd.with_sources,
+            generate_hashes: cmd.generate_hashes,
+            python_platform: cmd.python_platform,
+            exclude_newer: cmd.exclude_newer,
+            exclude_yanked: cmd.exclude_yanked,
+            explain: cmd.explain,
             reset: cmd.reset,
+            ..LockOptions::default()
         },
         pyproject: cmd.pyproject,
-    })?;
+        env: cmd.env,
+        yes: cmd.yes,
+        scope: match (cmd.only.as_deref(), cmd.member) {
+            (_, Some(member)) => SyncScope::Member(member),
+            _ if cmd.only_dev => SyncScope::OnlyDev,
+            (Some("dev"), _) => SyncScope::OnlyDev,
+            (Some(group), _) => SyncScope::Only(group.to_string()),
+            (None, _) => SyncScope::All,
+        },
+        no_install_project: cmd.no_install_project,
+        jobs: cmd.jobs,
+    };
+    if cmd.check {
+        sync_check(options)
+    } else if cmd.dry_run {
+        let pyproject = PyProject::load_or_discover(options.pyproject.as_deref())?
+            .with_env(options.env.as_deref())?;
+        sync_dry_run(&pyproject, options)
+    } else {
+        if cmd.timings.is_some() {
+            timings::enable();
+        }
+        let rv = if cmd.summary {
+            sync_with_summary(options)
+        } else {
+            sync(options)
+        };
+        match cmd.timings {
+            Some(Some(trace)) => timings::write_trace(&trace)?,
+            Some(None) => timings::print_breakdown(),
+            None => {}
+        }
+        rv
+    }
+}
+
+/// Parses the number of things to run at once.
+pub fn parse_jobs(s: &str) -> Result<usize, Error> {
+    match s.parse() {
+        Ok(jobs) if jobs > 0 => Ok(jobs),
+        _ => bail!("expected a positive number"),
+    }
+}
+
+fn sync_with_summary(options: SyncOptions) -> Result<(), Error> {
+    let mut summary = Summary::start();
+    let pyproject = PyProject::load_or_discover(options.pyproject.as_deref())?
+        .with_env(options.env.as_deref())?;
+    let before = SyncSnapshot::capture(&pyproject);
+    sync(options)?;
+    SyncSnapshot::capture(&pyproject).summarize(&before, &mut summary);
+    summary.print();
     Ok(())
 }
@@ -1,22 +1,33 @@
+use std::env;
 use std::env::consts::EXE_EXTENSION;
 use std::ffi::OsString;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use anyhow::{bail, Error};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use console::style;
 use same_file::is_same_file;
+use sha2::{Digest, Sha256};
 
+use crate::bootstrap::{ensure_self_venv, fetch, FetchOptions};
 use crate::config::Config;
 use crate::consts::VENV_BIN;
-use crate::pyproject::{locate_projects, normalize_package_name, DependencyKind, PyProject};
-use crate::sync::autosync;
-use crate::utils::{CommandOutput, QuietExit};
+use crate::platform::get_app_dir;
+use crate::pyproject::{
+    locate_projects, normalize_package_name, read_venv_marker, DependencyKind, ExpandedSources,
+    PyProject,
+};
+use crate::sources::py::PythonVersionRequest;
+use crate::sync::{autosync, create_virtualenv, install_lockfile};
+use crate::utils::watch::watch_and_rerun;
+use crate::utils::{get_venv_python_bin, CommandOutput, IoPathContext, QuietExit};
 
 /// Run the tests on the project.
 ///
-/// Today this will always run `pytest` for all projects.
+/// This runs `pytest` unless a different runner is configured in
+/// `tool.rye.test.runner`.
 #[derive(Parser, Debug)]
 pub struct Args {
     /// Perform the operation on all packages
@@ -37,16 +48,77 @@ pub struct Args {
     /// Turns off all output.
     #[arg(short, long, conflicts_with = "verbose")]
     quiet: bool,
-    /// Extra arguments to pytest
+    /// Re-run the tests whenever a file in the tested projects changes
+    #[arg(short, long)]
+    watch: bool,
+    /// Measure code coverage with coverage.py and print a report
+    #[arg(long)]
+    coverage: bool,
+    /// The coverage reports to write (defaults to a report in the terminal)
+    #[arg(long, value_name = "FORMAT", requires = "coverage")]
+    coverage_report: Vec<CoverageReport>,
+    /// Run the tests against every Python version in `tool.rye.test-matrix`
+    #[arg(long)]
+    all_pythons: bool,
+    /// Extra arguments to the test runner
     #[arg(last = true)]
     extra_args: Vec<OsString>,
 }
 
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+enum CoverageReport {
+    Term,
+    Xml,
+    Html,
+}
+
 pub fn execute(cmd: Args) -> Result<(), Error> {
     let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
     let project = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
 
-    let mut failed_with = None;
+    if cmd.watch {
+        let paths = locate_projects(project, cmd.all, &cmd.package[..])?
+            .iter()
+            .map(|x| x.root_path().to_path_buf())
+            .collect::<Vec<_>>();
+        let rye = env::current_exe()?;
+        match watch_and_rerun(&paths, || {
+            let mut rye_cmd = Command::new(&rye);
+            rye_cmd.arg("test");
+            if cmd.all {
+                rye_cmd.arg("--all");
+            }
+            for package in &cmd.package {
+                rye_cmd.arg("--package").arg(package);
+            }
+            if let Some(ref pyproject) = cmd.pyproject {
+                rye_cmd.arg("--pyproject").arg(pyproject);
+            }
+            if cmd.no_capture {
+                rye_cmd.arg("--no-capture");
+            }
+            if cmd.verbose {
+                rye_cmd.arg("--verbose");
+            }
+            if cmd.quiet {
+                rye_cmd.arg("--quiet");
+            }
+            if cmd.coverage {
+                rye_cmd.arg("--coverage");
+            }
+            for format in &cmd.coverage_report {
+                rye_cmd
+                    .arg("--coverage-report")
+                    .arg(format.to_possible_value().unwrap().get_name());
+            }
+            if cmd.all_pythons {
+                rye_cmd.arg("--all-pythons");
+            }
+            rye_cmd.arg("--").args(&cmd.extra_args);
+            rye_cmd
+        })? {}
+    }
 
     // when working with workspaces we always want to know what other projects exist.
     // for that we locate all those projects and their paths.  This is later used to
@@ -61,28 +133,77 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         vec![project.root_path().to_path_buf()]
     };
 
-    let pytest = project
-        .venv_path()
-        .join(VENV_BIN)
-        .join("pytest")
-        .with_extension(EXE_EXTENSION);
+    let venv_bin = project.venv_path().join(VENV_BIN);
+    let pytest = venv_bin.join("pytest").with_extension(EXE_EXTENSION);
 
     let projects = locate_projects(project, cmd.all, &cmd.package[..])?;
 
-    if !pytest.is_file() {
-        let has_pytest = has_pytest_dependency(&projects)?;
-        if has_pytest {
-            if Config::current().autosync() {
-                autosync(&projects[0], output)?;
-            } else {
-                bail!("pytest not installed but in dependencies. Run `rye sync`.")
-            }
+    // make sure the tests run against the current state of the project.
+    if Config::current().autosync() {
+        autosync(&projects[0], output)?;
+    }
+
+    let runners = projects
+        .iter()
+        .map(|x| x.test_runner().unwrap_or_else(|| vec!["pytest".into()]))
+        .collect::<Vec<_>>();
+    if runners.iter().any(|x| is_pytest(x)) && !pytest.is_file() {
+        if has_pytest_dependency(&projects)? {
+            bail!("pytest not installed but in dependencies. Run `rye sync`.")
         } else {
             bail!("pytest not installed. Run `rye add --dev pytest`");
         }
     }
+    if cmd.coverage
+        && !venv_bin
+            .join("coverage")
+            .with_extension(EXE_EXTENSION)
+            .is_file()
+    {
+        bail!("coverage not installed. Run `rye add --dev coverage`");
+    }
 
-    for (idx, project) in projects.iter().enumerate() {
+    let failed_with = if cmd.all_pythons {
+        run_matrix(&cmd, &projects, &runners, &project_roots, output)?
+    } else {
+        run_tests(
+            &cmd,
+            &projects,
+            &runners,
+            &project_roots,
+            &projects[0].venv_path(),
+            output,
+        )?
+    };
+
+    if let Some(code) = failed_with {
+        Err(Error::new(QuietExit(code)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Runs the tests of the given projects in a virtualenv.
+///
+/// Returns the exit code of the first failing test run.
+fn run_tests(
+    cmd: &Args,
+    projects: &[PyProject],
+    runners: &[Vec<String>],
+    project_roots: &[PathBuf],
+    venv: &Path,
+    output: CommandOutput,
+) -> Result<Option<i32>, Error> {
+    let venv_bin = venv.join(VENV_BIN);
+    let python = get_venv_python_bin(venv);
+    let workspace_path = projects[0].workspace_path();
+    let mut failed_with = None;
+
+    // with multiple projects the coverage data of all of them is combined
+    // into a single report in the workspace root.
+    let combine_coverage = cmd.coverage && projects.len() > 1;
+
+    for (idx, (project, runner)) in projects.iter().zip(runners).enumerate() {
         if output != CommandOutput::Quiet {
             if idx > 0 {
                 echo!();
@@ -94,42 +215,242 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
             );
         }
 
-        let mut pytest_cmd = Command::new(&pytest);
-        if cmd.no_capture {
-            pytest_cmd.arg("--capture=no");
-        }
-        match output {
-            CommandOutput::Normal => {}
-            CommandOutput::Verbose => {
-                pytest_cmd.arg("-v");
+        let mut test_cmd = if cmd.coverage {
+            // coverage.py needs to run the runner as a module
+            let mut test_cmd = Command::new(&python);
+            test_cmd.arg("-m").arg("coverage").arg("run");
+            if runner[0] == "python" {
+                test_cmd.args(&runner[1..]);
+            } else {
+                test_cmd.arg("-m").args(&runner);
+            }
+            test_cmd
+        } else {
+            let bin = venv_bin.join(&runner[0]).with_extension(EXE_EXTENSION);
+            let mut test_cmd = Command::new(if bin.is_file() {
+                bin.into_os_string()
+            } else {
+                OsString::from(&runner[0])
+            });
+            test_cmd.args(&runner[1..]);
+            test_cmd
+        };
+
+        if is_pytest(runner) {
+            if cmd.no_capture {
+                test_cmd.arg("--capture=no");
             }
-            CommandOutput::Quiet => {
-                pytest_cmd.arg("-q");
+            match output {
+                CommandOutput::Normal => {}
+                CommandOutput::Verbose => {
+                    test_cmd.arg("-v");
+                }
+                CommandOutput::Quiet => {
+                    test_cmd.arg("-q");
+                }
             }
         }
-        pytest_cmd.args(&cmd.extra_args);
-        pytest_cmd
-            .arg("--rootdir")
-            .arg(project.root_path().as_os_str())
-            .current_dir(project.root_path());
+        test_cmd.args(&cmd.extra_args);
+        if is_pytest(runner) {
+            test_cmd
+                .arg("--rootdir")
+                .arg(project.root_path().as_os_str());
 
-        // always ignore projects that are nested but not selected.
-        for path in &project_roots {
-            if !is_same_file(path, project.root_path()).unwrap_or(false) {
-                pytest_cmd.arg("--ignore").arg(path.as_os_str());
+            // always ignore projects that are nested but not selected.
+            for path in project_roots {
+                if !is_same_file(path, project.root_path()).unwrap_or(false) {
+                    test_cmd.arg("--ignore").arg(path.as_os_str());
+                }
             }
         }
+        test_cmd.current_dir(project.root_path());
+        if combine_coverage {
+            test_cmd.env(
+                "COVERAGE_FILE",
+                workspace_path.join(format!(".coverage.{}", project.normalized_name()?)),
+            );
+        }
 
-        let status = pytest_cmd.status()?;
+        let status = test_cmd.status()?;
         if !status.success() {
             failed_with = Some(status.code().unwrap_or(1));
         }
+
+        if cmd.coverage && !combine_coverage {
+            if let Some(code) = coverage_reports(cmd, &python, &project.root_path())? {
+                failed_with.get_or_insert(code);
+            }
+        }
     }
 
-    if let Some(code) = failed_with {
-        Err(Error::new(QuietExit(code)))
+    if combine_coverage {
+        if output != CommandOutput::Quiet {
+            echo!();
+            echo!(
+                "Combining coverage for {}",
+                style(workspace_path.display()).dim()
+            );
+        }
+        // paths are remapped according to `[tool.coverage.paths]` of the
+        // workspace root which coverage.py picks up by itself.
+        let status = Command::new(&python)
+            .arg("-m")
+            .arg("coverage")
+            .arg("combine")
+            .arg("--quiet")
+            .env_remove("COVERAGE_FILE")
+            .current_dir(&*workspace_path)
+            .status()?;
+        if !status.success() {
+            failed_with.get_or_insert(status.code().unwrap_or(1));
+        } else if let Some(code) = coverage_reports(cmd, &python, &workspace_path)? {
+            failed_with.get_or_insert(code);
+        }
+    }
+
+    Ok(failed_with)
+}
+
+/// Writes the requested coverage reports for the data file in `dir`.
+///
+/// Returns the exit code of the first report that failed.
+fn coverage_reports(cmd: &Args, python: &Path, dir: &Path) -> Result<Option<i32>, Error> {
+    let formats = if cmd.coverage_report.is_empty() {
+        &[CoverageReport::Term][..]
     } else {
-        Ok(())
+        &cmd.coverage_report[..]
+    };
+    for format in formats {
+        let status = Command::new(python)
+            .arg("-m")
+            .arg("coverage")
+            .arg(match format {
+                CoverageReport::Term => "report",
+                CoverageReport::Xml => "xml",
+                CoverageReport::Html => "html",
+            })
+            .env_remove("COVERAGE_FILE")
+            .current_dir(dir)
+            .status()?;
+        if !status.success() {
+            return Ok(Some(status.code().unwrap_or(1)));
+        }
+    }
+    Ok(None)
+}
+
+/// Runs the tests against every Python version of the test matrix.
+///
+/// Every version gets its own virtualenv in the rye cache which is synced
+/// against the lockfile of the project before the tests run in it.
+fn run_matrix(
+    cmd: &Args,
+    projects: &[PyProject],
+    runners: &[Vec<String>],
+    project_roots: &[PathBuf],
+    output: CommandOutput,
+) -> Result<Option<i32>, Error> {
+    let project = &projects[0];
+    let versions = project.test_matrix()?;
+    if versions.is_empty() {
+        bail!("no Python versions configured in tool.rye.test-matrix");
+    }
+
+    let workspace_path = project.workspace_path();
+    let lockfile = match workspace_path.join("requirements-dev.lock") {
+        dev_lockfile if dev_lockfile.is_file() => dev_lockfile,
+        _ => workspace_path.join("requirements.lock"),
+    };
+    if !lockfile.is_file() {
+        bail!("no lockfile found. Run `rye lock` first.");
+    }
+    let self_venv = ensure_self_venv(output)?;
+    let sources = ExpandedSources::from_sources(&project.sources()?)?;
+    let matrix_dir = get_app_dir()
+        .join("test-matrix")
+        .join(&hex::encode(Sha256::digest(workspace_path.to_string_lossy().as_bytes()))[..16]);
+
+    let mut results = Vec::new();
+    for version in versions {
+        echo!(if output, "");
+        echo!(
+            if output,
+            "Testing with Python {}",
+            style(&version).cyan()
+        );
+        let rv = prepare_matrix_venv(
+            project,
+            &version,
+            &matrix_dir,
+            &lockfile,
+            &self_venv,
+            &sources,
+            output,
+        )
+        .and_then(|venv| run_tests(cmd, projects, runners, project_roots, &venv, output));
+        results.push((version, rv));
+    }
+
+    echo!(if output, "");
+    echo!(if output, "Test matrix:");
+    let mut failed_with = None;
+    for (version, rv) in results {
+        let result = match rv {
+            Ok(None) => style("passed".to_string()).green(),
+            Ok(Some(code)) => {
+                failed_with.get_or_insert(code);
+                style("failed".to_string()).red()
+            }
+            Err(err) => {
+                failed_with.get_or_insert(1);
+                style(format!("error: {}", err)).red()
+            }
+        };
+        echo!(if output, "  {}: {}", version, result);
+    }
+    Ok(failed_with)
+}
+
+/// Creates or reuses the virtualenv for a Python version of the test matrix
+/// and syncs it against the lockfile.
+fn prepare_matrix_venv(
+    project: &PyProject,
+    version: &PythonVersionRequest,
+    matrix_dir: &Path,
+    lockfile: &Path,
+    self_venv: &Path,
+    sources: &ExpandedSources,
+    output: CommandOutput,
+) -> Result<PathBuf, Error> {
+    let py_ver = fetch(version, FetchOptions::with_output(output))?;
+    let venv = matrix_dir.join(py_ver.to_string());
+    if read_venv_marker(&venv).map_or(true, |x| x.python != py_ver) {
+        if venv.is_dir() {
+            fs::remove_dir_all(&venv)
+                .path_context(&venv, "failed to delete existing virtualenv")?;
+        }
+        let prompt = project.name().unwrap_or("venv");
+        create_virtualenv(output, self_venv, &py_ver, &venv, prompt)?;
+    }
+    install_lockfile(
+        project,
+        &venv,
+        &py_ver,
+        lockfile,
+        self_venv,
+        sources.clone(),
+        &[],
+        output.quieter(),
+    )?;
+    Ok(venv)
+}
+
+/// Is this runner pytest which understands our extra arguments?
+fn is_pytest(runner: &[String]) -> bool {
+    match runner {
+        [bin, ..] if bin == "pytest" => true,
+        [python, flag, module, ..] => python == "python" && flag == "-m" && module == "pytest",
+        _ => false,
     }
 }
 
@@ -1,5 +1,6 @@
 use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::env;
 use std::env::consts::{ARCH, OS};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -12,12 +13,21 @@ use console::style;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::bootstrap::{fetch, FetchOptions};
+use crate::cli::shim::list_shim_projects;
+use crate::config::Config;
 use crate::installer::list_installed_tools;
 use crate::piptools::get_pip_tools_venv_path;
-use crate::platform::{get_app_dir, get_canonical_py_path, list_known_toolchains};
-use crate::pyproject::read_venv_marker;
-use crate::sources::py::{iter_downloadable, PythonVersion};
-use crate::utils::{symlink_file, IoPathContext};
+use crate::platform::{
+    find_pyenv_pin, find_toolchain_upgrades, get_app_dir, get_canonical_py_path,
+    get_toolchain_python_bin, list_known_toolchains, unregister_toolchain_from_registry,
+};
+use crate::pyproject::{
+    read_venv_marker, resolve_python_version_request, DiscoveryUnsuccessful, PyProject,
+    PythonVersionSource,
+};
+use crate::sources::py::{iter_downloadable, PythonVersion, PythonVersionRequest};
+use crate::utils::{symlink_file, CommandOutput, IoPathContext};
 
 const INSPECT_SCRIPT: &str = r#"
 import json
@@ -79,6 +89,41 @@ pub struct ListCommand {
     format: Option<Format>,
 }
 
+/// Upgrades installed toolchains to newer patch releases.
+///
+/// The newer releases are fetched and the previous ones are removed unless
+/// they are still in use.  Exact pins of the previous versions in the
+/// `.python-version` of the current project and of projects that shims were
+/// recently used in are rewritten.  Relaxed pins (like `3.11`) pick up the
+/// new release automatically.
+#[derive(Parser, Debug)]
+pub struct UpgradeCommand {
+    /// Only show which toolchains would be upgraded.
+    #[arg(long)]
+    dry_run: bool,
+    /// Keep the previous toolchains around.
+    #[arg(long)]
+    keep_old: bool,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Explains which toolchain is used here and why.
+///
+/// Within a project this is the toolchain of its virtualenv, elsewhere the
+/// one global shims resolve to.  All sources that were considered are
+/// listed in order of precedence.
+#[derive(Parser, Debug)]
+pub struct WhichCommand {
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+}
+
 #[derive(ValueEnum, Copy, Clone, Serialize, Debug, PartialEq)]
 #[value(rename_all = "snake_case")]
 #[serde(rename_all = "snake_case")]
@@ -92,6 +137,8 @@ enum SubCommand {
     List(ListCommand),
     Register(RegisterCommand),
     Remove(RemoveCommand),
+    Upgrade(UpgradeCommand),
+    Which(WhichCommand),
 }
 
 pub fn execute(cmd: Args) -> Result<(), Error> {
@@ -100,9 +147,167 @@ pub fn execute(cmd: Args) -> Result<(), Error> {
         SubCommand::Fetch(args) => crate::cli::fetch::execute(args),
         SubCommand::List(args) => list(args),
         SubCommand::Remove(args) => remove(args),
+        SubCommand::Upgrade(args) => upgrade(args),
+        SubCommand::Which(args) => which(args),
     }
 }
 
+fn upgrade(cmd: UpgradeCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let upgrades = find_toolchain_upgrades()?;
+    if upgrades.is_empty() {
+        echo!(if output, "All toolchains are up to date");
+        return Ok(());
+    }
+
+    let mut projects = list_shim_projects();
+    if let Ok(pyproject) = PyProject::discover() {
+        projects.push(pyproject.root_path().to_path_buf());
+    }
+    projects.sort();
+    projects.dedup();
+
+    for (old, new) in upgrades {
+        if cmd.dry_run {
+            echo!(if output, "{} -> {}", style(&old).cyan(), style(&new).green());
+            continue;
+        }
+
+        fetch(&new.clone().into(), FetchOptions::with_output(output))?;
+        echo!(if output, "Upgraded {} to {}", style(&old).cyan(), style(&new).green());
+
+        let mut in_use = Vec::new();
+        for project in &projects {
+            if rewrite_pin(project, &old, &new)? {
+                echo!(if output, "  updated pin in {}", project.display());
+            }
+            // the virtualenv needs to be recreated to move to the new release.
+            if let Ok(pyproject) = PyProject::load(&project.join("pyproject.toml")) {
+                let venv = pyproject.venv_path();
+                if read_venv_marker(&venv).map_or(false, |x| x.python == old) {
+                    in_use.push(project.clone());
+                }
+            }
+        }
+
+        if cmd.keep_old {
+            continue;
+        }
+        if !in_use.is_empty() {
+            warn!(
+                "keeping {} as it is used by the virtualenv of {} (run `rye sync` there)",
+                old,
+                in_use
+                    .iter()
+                    .map(|x| x.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        } else if let Err(err) = check_in_use(&old) {
+            warn!("keeping {}: {}", old, err);
+        } else {
+            let path = get_canonical_py_path(&old)?;
+            fs::remove_dir_all(&path).path_context(&path, "failed to remove toolchain")?;
+            if let Err(err) = unregister_toolchain_from_registry(&old) {
+                warn!(
+                    "unable to remove {} from the Windows registry: {}",
+                    old, err
+                );
+            }
+            echo!(if verbose output, "  removed {}", old);
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrites an exact pin of a toolchain in a project's `.python-version`.
+///
+/// Returns `true` if the pin was changed.
+fn rewrite_pin(project: &Path, old: &PythonVersion, new: &PythonVersion) -> Result<bool, Error> {
+    let version_file = project.join(".python-version");
+    let contents = match fs::read_to_string(&version_file) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(false),
+    };
+    let pinned = match contents.trim().parse::<PythonVersionRequest>() {
+        Ok(pinned) => pinned,
+        Err(_) => return Ok(false),
+    };
+    if PythonVersion::try_from(pinned).ok().as_ref() != Some(old) {
+        return Ok(false);
+    }
+    // keep the form of the pin, which omits the cpython@ prefix by default.
+    let new_pin = new.to_string();
+    let new_pin = match new_pin.strip_prefix("cpython@") {
+        Some(rest) if !contents.trim().starts_with("cpython@") => rest.to_string(),
+        _ => new_pin,
+    };
+    fs::write(&version_file, format!("{}\n", new_pin))
+        .path_context(&version_file, "failed to write .python-version file")?;
+    Ok(true)
+}
+
+fn which(cmd: WhichCommand) -> Result<(), Error> {
+    let pyproject = match PyProject::load_or_discover(cmd.pyproject.as_deref()) {
+        Ok(pyproject) => Some(pyproject),
+        Err(err) if err.is::<DiscoveryUnsuccessful>() => None,
+        Err(err) => return Err(err),
+    };
+
+    let candidates = match pyproject {
+        Some(ref pyproject) => {
+            echo!(
+                "project {} uses the virtualenv in {}",
+                style(pyproject.name().unwrap_or("<unnamed>")).cyan(),
+                pyproject.venv_path().display()
+            );
+            pyproject.venv_python_version_sources()
+        }
+        None => {
+            let config = Config::current();
+            if !config.global_python() {
+                echo!(
+                    "no project found and global shims are disabled (behavior.global-python), \
+                     so `python` is not resolved by Rye"
+                );
+                return Ok(());
+            }
+            echo!("no project found, global shims are used");
+            let mut rv = Vec::new();
+            if let Some((path, version)) = find_pyenv_pin(&env::current_dir()?) {
+                rv.push((PythonVersionSource::PinFile(path), version));
+            }
+            rv.push((
+                PythonVersionSource::Config(config.origin("default.toolchain").cloned()),
+                config.default_toolchain()?,
+            ));
+            rv
+        }
+    };
+
+    let (source, request) = candidates
+        .first()
+        .ok_or_else(|| anyhow!("could not determine a target Python version"))?;
+    let version = resolve_python_version_request(request.clone())?;
+    let py = get_toolchain_python_bin(&version)?;
+    if py.is_file() {
+        echo!("{} ({})", style(&version).green(), py.display());
+    } else {
+        echo!(
+            "{} (not installed, install with `rye fetch {}`)",
+            style(&version).yellow(),
+            version
+        );
+    }
+    echo!("  decided by {} ({})", source, request);
+    for (source, request) in &candidates[1..] {
+        echo!("  overrides {} ({})", style(source).dim(), request);
+    }
+
+    Ok(())
+}
+
 fn register(cmd: RegisterCommand) -> Result<(), Error> {
     let target_version = register_toolchain(&cmd.path, cmd.name.as_deref(), |_| Ok(()))?;
     echo!("Registered {} as {}", cmd.path.display(), target_version);
@@ -127,7 +332,13 @@ fn check_in_use(ver: &PythonVersion) -> Result<(), Error> {
     for (tool, info) in &installed_tools {
         if let Some(ref venv_marker) = info.venv_marker {
             if &venv_marker.python == ver {
-                bail!("toolchain {} is still in use by tool {}", ver, tool);
+                bail!(
+                    "toolchain {} is still in use by tool {} (move it to another \
+                     toolchain with `rye tools reinstall {} --python <VERSION>`)",
+                    ver,
+                    tool,
+                    tool
+                );
             }
         }
     }
@@ -152,6 +363,13 @@ pub fn remove(cmd: RemoveCommand) -> Result<(), Error> {
     } else {
         echo!("Toolchain is not installed");
     }
+
+    if let Err(err) = unregister_toolchain_from_registry(&ver) {
+        warn!(
+            "unable to remove {} from the Windows registry: {}",
+            ver, err
+        );
+    }
     Ok(())
 }
 
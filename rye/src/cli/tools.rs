@@ -1,8 +1,21 @@
-use anyhow::Error;
-use clap::Parser;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{bail, Context, Error};
+use clap::{Parser, ValueEnum};
 use console::style;
+use pep508_rs::Requirement;
+use serde::Serialize;
 
-use crate::installer::list_installed_tools;
+use crate::config::Config;
+use crate::consts::VENV_BIN;
+use crate::installer::{
+    ensure_cached_tool, get_tools_manifest_path, inject, list_installed_tools, read_tools_manifest,
+    reinstall, sync_tools, upgrade,
+};
+use crate::sources::py::PythonVersionRequest;
+use crate::utils::{civil_date, exec_spawn, format_size, CommandOutput};
 
 /// Helper utility to manage global tools.
 #[derive(Parser, Debug)]
@@ -20,6 +33,135 @@ pub struct ListCommand {
     /// Show the version of tools.
     #[arg(short = 'v', long)]
     include_version: bool,
+    /// Show the requested requirement, Python version, install date and size.
+    #[arg(short = 'd', long)]
+    details: bool,
+    /// Request parseable output format
+    #[arg(long)]
+    format: Option<Format>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Serialize, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+enum Format {
+    Json,
+}
+
+#[derive(Serialize, Debug)]
+struct ListTool {
+    name: String,
+    version: Option<String>,
+    requirement: Option<String>,
+    extra_requirements: Vec<String>,
+    python: Option<String>,
+    requested_python: Option<String>,
+    scripts: Vec<String>,
+    installed_at: Option<u64>,
+    size: u64,
+}
+
+/// Install additional packages into an installed tool.
+#[derive(Parser, Debug)]
+pub struct InjectCommand {
+    /// The installed tool.
+    name: String,
+    /// The packages to install into the tool's environment.
+    #[arg(required = true)]
+    packages: Vec<String>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Reinstall tools, optionally with a different Python version.
+#[derive(Parser, Debug)]
+pub struct ReinstallCommand {
+    /// The tool to reinstall.
+    #[arg(required_unless_present = "all")]
+    name: Option<String>,
+    /// Reinstall all installed tools.
+    #[arg(short, long, conflicts_with = "name")]
+    all: bool,
+    /// The Python version to use (defaults to the one recorded for the tool).
+    #[arg(short, long)]
+    python: Option<PythonVersionRequest>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Install and uninstall tools to match the tools manifest.
+///
+/// The manifest is read from `tools.toml` in the rye home folder.
+#[derive(Parser, Debug)]
+pub struct SyncCommand {
+    /// Use this tools manifest instead of the default one.
+    #[arg(long, value_name = "TOOLS_TOML")]
+    manifest: Option<PathBuf>,
+    /// Do not uninstall tools that are not declared in the manifest.
+    #[arg(long)]
+    no_prune: bool,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Upgrade installed tools within their requested version range.
+#[derive(Parser, Debug)]
+pub struct UpgradeCommand {
+    /// The tool to upgrade.
+    #[arg(required_unless_present = "all")]
+    name: Option<String>,
+    /// Upgrade all installed tools.
+    #[arg(short, long, conflicts_with = "name")]
+    all: bool,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Run a tool without installing it.
+///
+/// The tool is installed into a cached environment that is reused by later
+/// invocations, but no shims are created.
+#[derive(Parser, Debug)]
+pub struct RunCommand {
+    /// The tool to run (`name`, `name@version` or a requirement).
+    tool: String,
+    /// The package that provides the tool if it is named differently.
+    #[arg(long, value_name = "PACKAGE")]
+    from: Option<String>,
+    /// Additional packages to install alongside the tool.
+    #[arg(long = "with", value_name = "PACKAGE")]
+    with_packages: Vec<String>,
+    /// Optionally the Python version to use.
+    #[arg(short, long)]
+    python: Option<String>,
+    /// Reinstall the tool instead of reusing the cached environment.
+    #[arg(long)]
+    refresh: bool,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Arguments passed to the tool.
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<OsString>,
 }
 
 #[derive(Parser, Debug)]
@@ -27,14 +169,24 @@ pub struct ListCommand {
 enum SubCommand {
     Install(crate::cli::install::Args),
     Uninstall(crate::cli::uninstall::Args),
+    Inject(InjectCommand),
     List(ListCommand),
+    Reinstall(ReinstallCommand),
+    Run(RunCommand),
+    Sync(SyncCommand),
+    Upgrade(UpgradeCommand),
 }
 
 pub fn execute(cmd: Args) -> Result<(), Error> {
     match cmd.command {
         SubCommand::Install(args) => crate::cli::install::execute(args),
         SubCommand::Uninstall(args) => crate::cli::uninstall::execute(args),
+        SubCommand::Inject(args) => inject_packages(args),
         SubCommand::List(args) => list_tools(args),
+        SubCommand::Reinstall(args) => reinstall_tools(args),
+        SubCommand::Run(args) => run_tool(args),
+        SubCommand::Sync(args) => sync_manifest(args),
+        SubCommand::Upgrade(args) => upgrade_tools(args),
     }
 }
 
@@ -42,6 +194,31 @@ fn list_tools(cmd: ListCommand) -> Result<(), Error> {
     let mut tools = list_installed_tools()?.into_iter().collect::<Vec<_>>();
     tools.sort_by_key(|(tool, _)| tool.clone());
 
+    if let Some(Format::Json) = cmd.format {
+        let json_tools = tools
+            .into_iter()
+            .map(|(tool, mut info)| {
+                info.scripts.sort();
+                let size = info.disk_size();
+                let metadata = info.metadata.unwrap_or_default();
+                ListTool {
+                    name: tool,
+                    version: Some(info.version).filter(|_| info.valid),
+                    requirement: Some(metadata.requirement).filter(|x| !x.is_empty()),
+                    extra_requirements: metadata.extra_requirements,
+                    python: info.venv_marker.map(|x| x.python.to_string()),
+                    requested_python: metadata.python,
+                    scripts: info.scripts,
+                    installed_at: info.installed_at,
+                    size,
+                }
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_writer_pretty(std::io::stdout().lock(), &json_tools)?;
+        echo!();
+        return Ok(());
+    }
+
     for (tool, mut info) in tools {
         if !info.valid {
             echo!("{} ({})", style(tool).red(), style("seems broken").red());
@@ -56,6 +233,21 @@ fn list_tools(cmd: ListCommand) -> Result<(), Error> {
         } else {
             echo!("{}", style(tool).cyan());
         }
+        if cmd.details {
+            if let Some(ref metadata) = info.metadata {
+                echo!("  requested: {}", metadata.requirement);
+                for extra in &metadata.extra_requirements {
+                    echo!("  with: {}", extra);
+                }
+            }
+            if let Some(ref venv) = info.venv_marker {
+                echo!("  python: {}", venv.python);
+            }
+            if let Some(installed_at) = info.installed_at {
+                echo!("  installed: {}", format_date(installed_at));
+            }
+            echo!("  size: {}", format_size(info.disk_size()));
+        }
         if cmd.include_scripts {
             info.scripts.sort();
             for script in info.scripts {
@@ -66,3 +258,145 @@ fn list_tools(cmd: ListCommand) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Formats a unix timestamp as a `YYYY-MM-DD` date (in UTC).
+fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_date(timestamp);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn inject_packages(cmd: InjectCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let requirements = cmd
+        .packages
+        .iter()
+        .map(|x| parse_tool_spec(x))
+        .collect::<Result<Vec<_>, _>>()?;
+    inject(&cmd.name, &requirements, output)
+}
+
+fn sync_manifest(cmd: SyncCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let manifest = cmd.manifest.unwrap_or_else(get_tools_manifest_path);
+    if !manifest.is_file() {
+        bail!("tools manifest {} does not exist", manifest.display());
+    }
+    let specs = read_tools_manifest(&manifest)?;
+    sync_tools(&specs, !cmd.no_prune, output)
+}
+
+fn upgrade_tools(cmd: UpgradeCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    for_each_tool(cmd.name, "upgrade", |tool| upgrade(tool, output))
+}
+
+fn reinstall_tools(cmd: ReinstallCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    for_each_tool(cmd.name, "reinstall", |tool| {
+        reinstall(tool, cmd.python.as_ref(), output)
+    })
+}
+
+/// Runs an operation for one tool or, if no name is given, for all installed
+/// tools.  Failures are reported but do not stop the other tools.
+fn for_each_tool<F>(name: Option<String>, action: &str, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(&str) -> Result<(), Error>,
+{
+    let tools = match name {
+        Some(name) => vec![name],
+        None => {
+            let mut tools = list_installed_tools()?.into_keys().collect::<Vec<_>>();
+            tools.sort();
+            tools
+        }
+    };
+
+    let mut failed = Vec::new();
+    for tool in tools {
+        if let Err(err) = f(&tool) {
+            error!("failed to {} {}: {:#}", action, tool, err);
+            failed.push(tool);
+        }
+    }
+    if !failed.is_empty() {
+        bail!("unable to {} {}", action, failed.join(", "));
+    }
+    Ok(())
+}
+
+fn run_tool(cmd: RunCommand) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let (requirement, command) = match cmd.from {
+        Some(ref from) => (parse_tool_spec(from)?, cmd.tool.clone()),
+        None => {
+            let requirement = parse_tool_spec(&cmd.tool)?;
+            let command = requirement.name.clone();
+            (requirement, command)
+        }
+    };
+    let extra_requirements = cmd
+        .with_packages
+        .iter()
+        .map(|x| parse_tool_spec(x))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let py_ver: PythonVersionRequest = match cmd.python {
+        Some(ref py) => py.parse()?,
+        None => Config::current()
+            .default_toolchain()
+            .unwrap_or(PythonVersionRequest {
+                name: None,
+                arch: None,
+                os: None,
+                environment: None,
+                major: 3,
+                minor: None,
+                patch: None,
+                suffix: None,
+            }),
+    };
+
+    let venv = ensure_cached_tool(
+        &requirement,
+        &extra_requirements,
+        &py_ver,
+        cmd.refresh,
+        output,
+    )?;
+    let mut script = venv.join(VENV_BIN).join(&command);
+    if cfg!(windows) && script.extension().is_none() {
+        script.set_extension("exe");
+    }
+    if !script.is_file() {
+        bail!(
+            "{} does not provide a command named '{}' (use --from to pick the package)",
+            requirement.name,
+            command
+        );
+    }
+
+    let mut tool_cmd = Command::new(script);
+    tool_cmd.args(&cmd.args);
+    match exec_spawn(&mut tool_cmd)? {}
+}
+
+/// Parses a tool requirement where `name@version` is a shorthand for
+/// `name==version`.
+fn parse_tool_spec(spec: &str) -> Result<Requirement, Error> {
+    let spec = match spec.split_once('@') {
+        Some((name, version))
+            if !name.trim().is_empty()
+                && !version.contains("://")
+                && name
+                    .trim()
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "-_.[],".contains(c)) =>
+        {
+            format!("{}=={}", name.trim(), version.trim())
+        }
+        _ => spec.to_string(),
+    };
+    spec.parse::<Requirement>()
+        .with_context(|| format!("failed to parse tool '{}'", spec))
+}
@@ -0,0 +1,143 @@
+use std::env::consts::EXE_EXTENSION;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{bail, Error};
+use clap::{Parser, ValueEnum};
+use console::style;
+
+use crate::bootstrap::ensure_self_venv;
+use crate::consts::VENV_BIN;
+use crate::pyproject::{locate_projects, PyProject};
+use crate::utils::{get_venv_python_bin, CommandOutput, QuietExit};
+
+/// Run the type checker on the project.
+///
+/// This invokes mypy unless a different type checker is configured.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// List of files or directories to limit the operation to
+    paths: Vec<PathBuf>,
+    /// Perform the operation on all packages
+    #[arg(short, long)]
+    all: bool,
+    /// Perform the operation on a specific package
+    #[arg(short, long)]
+    package: Vec<String>,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// The type checker to use (overrides `tool.rye.typecheck.checker`)
+    #[arg(long)]
+    checker: Option<Checker>,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Extra arguments to the type checker
+    #[arg(last = true)]
+    extra_args: Vec<OsString>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+enum Checker {
+    Mypy,
+    Pyright,
+}
+
+impl Checker {
+    fn name(self) -> &'static str {
+        match self {
+            Checker::Mypy => "mypy",
+            Checker::Pyright => "pyright",
+        }
+    }
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    let project = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let checker = match cmd.checker {
+        Some(checker) => checker,
+        None => match project.typecheck_checker().as_deref() {
+            None | Some("mypy") => Checker::Mypy,
+            Some("pyright") => Checker::Pyright,
+            Some(other) => bail!("unknown type checker '{}'", other),
+        },
+    };
+
+    // a type checker installed into the project's virtualenv wins over the
+    // version that ships with rye.
+    let venv = project.venv_path().into_owned();
+    let project_bin = venv
+        .join(VENV_BIN)
+        .join(checker.name())
+        .with_extension(EXE_EXTENSION);
+    let bin = if project_bin.is_file() {
+        project_bin
+    } else if checker == Checker::Mypy {
+        ensure_self_venv(output)?
+            .join(VENV_BIN)
+            .join("mypy")
+            .with_extension(EXE_EXTENSION)
+    } else {
+        bail!("pyright not installed. Run `rye add --dev pyright`");
+    };
+
+    let projects = if cmd.paths.is_empty() {
+        locate_projects(project, cmd.all, &cmd.package[..])?
+    } else {
+        vec![project]
+    };
+
+    let mut failed_with = None;
+    for (idx, project) in projects.iter().enumerate() {
+        if output != CommandOutput::Quiet && projects.len() > 1 {
+            if idx > 0 {
+                echo!();
+            }
+            echo!(
+                "Type checking {} ({})",
+                style(project.name().unwrap_or("<unknown>")).cyan(),
+                style(project.root_path().display()).dim()
+            );
+        }
+
+        // the type checker runs in the project root so that it picks up the
+        // configuration from the project's pyproject.toml.
+        let mut checker_cmd = Command::new(&bin);
+        let python = get_venv_python_bin(&venv);
+        if python.is_file() {
+            checker_cmd
+                .arg(match checker {
+                    Checker::Mypy => "--python-executable",
+                    Checker::Pyright => "--pythonpath",
+                })
+                .arg(&python);
+        }
+        if output == CommandOutput::Verbose {
+            checker_cmd.arg("--verbose");
+        }
+        checker_cmd.args(&cmd.extra_args);
+        if cmd.paths.is_empty() {
+            checker_cmd.arg(".").current_dir(project.root_path());
+        } else {
+            checker_cmd.args(&cmd.paths);
+        }
+
+        let status = checker_cmd.status()?;
+        if !status.success() {
+            failed_with = Some(status.code().unwrap_or(1));
+        }
+    }
+
+    if let Some(code) = failed_with {
+        Err(Error::new(QuietExit(code)))
+    } else {
+        Ok(())
+    }
+}
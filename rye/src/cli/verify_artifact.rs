@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use anyhow::Error;
+use clap::Parser;
+use console::style;
+
+use crate::attestations::verify_artifacts;
+use crate::utils::{CommandOutput, QuietExit};
+
+/// Verifies the attestations of distribution files.
+///
+/// The PEP 740 attestation of each file is expected next to it (as written by
+/// `rye publish --attestations`).
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The distribution files to verify.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// The identity that must have signed the files (an email or a workflow URL).
+    #[arg(long)]
+    identity: String,
+    /// Enables verbose diagnostics.
+    #[arg(short, long)]
+    verbose: bool,
+    /// Turns off all output.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, cmd.verbose);
+    if !verify_artifacts(&cmd.files, &cmd.identity, output)? {
+        return Err(Error::new(QuietExit(1)));
+    }
+    echo!(
+        if output,
+        "{} signed by {}",
+        style("verified").green(),
+        style(&cmd.identity).cyan()
+    );
+    Ok(())
+}
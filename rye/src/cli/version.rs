@@ -1,9 +1,21 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::pyproject::PyProject;
-use anyhow::{anyhow, bail, Error};
+use crate::utils::{civil_date, is_inside_git_work_tree, IoPathContext};
+use anyhow::{anyhow, bail, Context, Error};
 use clap::{Parser, ValueEnum};
-use pep440_rs::Version;
+use pep440_rs::{PreRelease, Version};
+use regex::Regex;
+
+/// The default pattern for files listed in `tool.rye.version-files`.
+const DEFAULT_VERSION_PATTERN: &str = r#"(?m)^__version__\s*(?::\s*str\s*)?=\s*["']([^"']+)["']"#;
+
+/// The CalVer scheme used if the project does not configure one.
+const DEFAULT_CALVER_SCHEME: &str = "YYYY.MM.MICRO";
 
 /// Get or set project version
 #[derive(Parser, Debug)]
@@ -11,69 +23,341 @@ pub struct Args {
     /// The version to set
     version: Option<String>,
     /// The version bump to apply
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "version")]
     bump: Option<Bump>,
+    /// Turn the new version into a pre-release of the given kind.
+    #[arg(long, conflicts_with = "version")]
+    pre: Option<Pre>,
+    /// Commit the changed files to git.
+    #[arg(long)]
+    commit: bool,
+    /// Commit the changed files to git and tag the commit (implies `--commit`).
+    #[arg(long)]
+    tag: bool,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Copy, Clone, ValueEnum)]
 pub enum Bump {
     Major,
     Minor,
     Patch,
+    Post,
+    Dev,
+    Calver,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Pre {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+impl From<Pre> for PreRelease {
+    fn from(value: Pre) -> Self {
+        match value {
+            Pre::Alpha => PreRelease::Alpha,
+            Pre::Beta => PreRelease::Beta,
+            Pre::Rc => PreRelease::Rc,
+        }
+    }
+}
+
+impl From<PreRelease> for Pre {
+    fn from(value: PreRelease) -> Self {
+        match value {
+            PreRelease::Alpha => Pre::Alpha,
+            PreRelease::Beta => Pre::Beta,
+            PreRelease::Rc => Pre::Rc,
+        }
+    }
 }
 
 pub fn execute(cmd: Args) -> Result<(), Error> {
     let mut pyproject_toml = PyProject::discover()?;
-    match cmd.version {
-        Some(version) => {
-            let version =
-                Version::from_str(&version).map_err(|msg| anyhow!("invalid version: {}", msg))?;
-            if pyproject_toml
-                .dynamic()
-                .unwrap()
-                .contains(&"version".to_string())
-            {
-                bail!("unsupported set dynamic version");
-            } else {
-                pyproject_toml.set_version(&version);
-                pyproject_toml.save()?;
+    let is_dynamic = pyproject_toml
+        .dynamic()
+        .unwrap()
+        .contains(&"version".to_string());
 
-                echo!("version set to {}", version);
-            }
+    let version = match cmd.version {
+        Some(version) => {
+            Version::from_str(&version).map_err(|msg| anyhow!("invalid version: {}", msg))?
         }
         None => {
             let mut version = pyproject_toml.version()?;
-            match cmd.bump {
-                Some(bump) => bump_version(&mut version, bump, &mut pyproject_toml)?,
-                None => echo!("{}", version),
+            if cmd.bump.is_none() && cmd.pre.is_none() {
+                if cmd.commit || cmd.tag {
+                    bail!("--commit and --tag require a version or --bump");
+                }
+                echo!("{}", version);
+                return Ok(());
+            }
+            if let Some(bump) = cmd.bump {
+                bump_version(&mut version, bump, cmd.pre.is_some(), &pyproject_toml)?;
+            }
+            if let Some(pre) = cmd.pre {
+                set_pre_release(&mut version, pre, cmd.bump.is_some())?;
             }
+            version
         }
+    };
+
+    // a version derived from git can only be changed by tagging a commit.
+    if pyproject_toml.vcs_version_source()?.is_some() {
+        if !cmd.tag {
+            bail!("the version is derived from git tags, pass --tag to tag the current commit");
+        }
+        if version.local.is_some() || version.is_dev() {
+            bail!("{} is not a release version and cannot be tagged", version);
+        }
+        create_tag(&pyproject_toml.root_path(), &version)?;
+        pyproject_toml.write_vcs_version_file()?;
+        return Ok(());
+    }
+
+    let version_files = pyproject_toml.version_files();
+    if is_dynamic && version_files.is_empty() {
+        bail!("unsupported set dynamic version");
+    }
+
+    let mut changed_files = Vec::new();
+    if !is_dynamic {
+        pyproject_toml.set_version(&version);
+        pyproject_toml.save()?;
+        changed_files.push(pyproject_toml.toml_path().into_owned());
+    }
+    for (path, pattern) in version_files {
+        update_version_file(&path, pattern.as_deref(), &version)?;
+        changed_files.push(path);
+    }
+
+    if cmd.bump.is_some() || cmd.pre.is_some() {
+        echo!("version bumped to {}", version);
+    } else {
+        echo!("version set to {}", version);
     }
+
+    if cmd.commit || cmd.tag {
+        commit_version(&pyproject_toml, &version, &changed_files, cmd.tag)?;
+    }
+
     Ok(())
 }
 
-fn bump_version(version: &mut Version, bump: Bump, pyproject: &mut PyProject) -> Result<(), Error> {
-    if version.is_post() {
-        version.post = None;
-    }
-    if version.is_dev() {
+fn bump_version(
+    version: &mut Version,
+    bump: Bump,
+    keep_pre: bool,
+    pyproject: &PyProject,
+) -> Result<(), Error> {
+    // local versions are never released, they only come from git builds
+    version.local = None;
+    let index = match bump {
+        Bump::Major => 0,
+        Bump::Minor => 1,
+        Bump::Patch => 2,
+        Bump::Post => {
+            version.dev = None;
+            version.post = Some(version.post.map_or(1, |x| x + 1));
+            return Ok(());
+        }
+        Bump::Dev => {
+            version.post = None;
+            if let Some(dev) = version.dev {
+                version.dev = Some(dev + 1);
+            } else {
+                if let Some((kind, num)) = version.pre {
+                    version.pre = Some((kind, num + 1));
+                } else {
+                    bump_release(version, 2);
+                }
+                version.dev = Some(0);
+            }
+            return Ok(());
+        }
+        Bump::Calver => {
+            let scheme = pyproject.calver_scheme().unwrap_or(DEFAULT_CALVER_SCHEME);
+            version.release = bump_calver(&version.release, scheme, today()?)?;
+            version.pre = None;
+            version.post = None;
+            version.dev = None;
+            return Ok(());
+        }
+    };
+
+    version.post = None;
+    if !keep_pre && (version.is_dev() || version.is_pre()) {
         version.dev = None;
-        warn!("dev version will be bumped to release version");
+        version.pre = None;
+        warn!("pre-release version will be bumped to release version");
     } else {
-        let index = bump as usize;
-        if version.release.get(index).is_none() {
-            version.release.resize(index + 1, 0);
+        version.dev = None;
+        version.pre = None;
+        bump_release(version, index);
+    }
+
+    Ok(())
+}
+
+/// Increments a release component and resets all following ones.
+fn bump_release(version: &mut Version, index: usize) {
+    if version.release.len() <= index {
+        version.release.resize(index + 1, 0);
+    }
+    version.release[index] += 1;
+    for i in index + 1..version.release.len() {
+        version.release[i] = 0;
+    }
+}
+
+/// Turns a version into a pre-release of the given kind.
+///
+/// After a release bump this starts a new pre-release series, otherwise the
+/// current one is advanced.
+fn set_pre_release(version: &mut Version, pre: Pre, bumped: bool) -> Result<(), Error> {
+    version.local = None;
+    version.post = None;
+    version.dev = None;
+    version.pre = match version.pre {
+        Some((kind, num)) if !bumped => {
+            let current = Pre::from(kind);
+            if pre < current {
+                bail!(
+                    "cannot go back from {} to a {} pre-release",
+                    version,
+                    pre.to_possible_value().unwrap().get_name()
+                );
+            } else if pre == current {
+                Some((kind, num + 1))
+            } else {
+                Some((pre.into(), 1))
+            }
         }
-        version.release[index] += 1;
-        for i in index + 1..version.release.len() {
-            version.release[i] = 0;
+        Some(_) => Some((pre.into(), 1)),
+        None if bumped => Some((pre.into(), 1)),
+        None => bail!(
+            "{} is a final release, pass --bump to start a pre-release",
+            version
+        ),
+    };
+    Ok(())
+}
+
+/// Returns today's date (in UTC).
+fn today() -> Result<(i64, u32, u32), Error> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("system clock is before the unix epoch")?;
+    Ok(civil_date(now.as_secs()))
+}
+
+/// Computes the next CalVer release for a scheme like `YYYY.MM.MICRO`.
+///
+/// The supported segments are `YYYY`, `YY`, `MM`, `DD` and `MICRO`.  The
+/// micro segment counts up while the date segments stay the same.
+fn bump_calver(
+    current: &[u64],
+    scheme: &str,
+    (year, month, day): (i64, u32, u32),
+) -> Result<Vec<u64>, Error> {
+    let mut rv = Vec::new();
+    let mut micro_index = None;
+    for segment in scheme.split('.') {
+        rv.push(match segment {
+            "YYYY" => year as u64,
+            "YY" => (year - 2000) as u64,
+            "MM" => month as u64,
+            "DD" => day as u64,
+            "MICRO" => {
+                if micro_index.is_some() {
+                    bail!("CalVer scheme '{}' has more than one MICRO segment", scheme);
+                }
+                micro_index = Some(rv.len());
+                0
+            }
+            other => bail!("unsupported CalVer segment '{}' in '{}'", other, scheme),
+        });
+    }
+
+    let same_date = rv.iter().enumerate().all(|(idx, value)| {
+        Some(idx) == micro_index || current.get(idx).map_or(false, |x| x == value)
+    });
+    if same_date {
+        match micro_index {
+            Some(idx) => rv[idx] = current.get(idx).map_or(0, |x| x + 1),
+            None => bail!(
+                "version is already at today's date, add a MICRO segment to the CalVer scheme"
+            ),
         }
     }
+    Ok(rv)
+}
 
-    pyproject.set_version(version);
-    pyproject.save().unwrap();
+/// Replaces the version in a file listed in `tool.rye.version-files`.
+fn update_version_file(path: &Path, pattern: Option<&str>, version: &Version) -> Result<(), Error> {
+    let pattern = pattern.unwrap_or(DEFAULT_VERSION_PATTERN);
+    let re =
+        Regex::new(pattern).with_context(|| format!("invalid version pattern '{}'", pattern))?;
+    let contents = fs::read_to_string(path).path_context(path, "unable to read version file")?;
+    let m = match re.captures(&contents).and_then(|x| x.get(1)) {
+        Some(m) => m,
+        None => bail!("no version found in {}", path.display()),
+    };
+    let new_contents = format!(
+        "{}{}{}",
+        &contents[..m.start()],
+        version,
+        &contents[m.end()..]
+    );
+    fs::write(path, new_contents).path_context(path, "unable to write version file")?;
+    Ok(())
+}
 
-    echo!("version bumped to {}", version);
+/// Commits the changed files and optionally tags the commit.
+fn commit_version(
+    pyproject: &PyProject,
+    version: &Version,
+    files: &[PathBuf],
+    tag: bool,
+) -> Result<(), Error> {
+    let root = pyproject.root_path().to_path_buf();
+    if !is_inside_git_work_tree(&root) {
+        bail!("project is not inside a git repository");
+    }
 
+    let status = Command::new("git")
+        .arg("commit")
+        .arg("-m")
+        .arg(format!("Bump version to {}", version))
+        .arg("--")
+        .args(files)
+        .current_dir(&root)
+        .status()
+        .context("unable to run git")?;
+    if !status.success() {
+        bail!("failed to commit version change");
+    }
+
+    if tag {
+        create_tag(&root, version)?;
+    }
+
+    Ok(())
+}
+
+/// Tags the current commit with the version.
+fn create_tag(root: &Path, version: &Version) -> Result<(), Error> {
+    let tag_name = format!("v{}", version);
+    let status = Command::new("git")
+        .arg("tag")
+        .arg(&tag_name)
+        .current_dir(root)
+        .status()
+        .context("unable to run git")?;
+    if !status.success() {
+        bail!("failed to create tag {}", tag_name);
+    }
+    echo!("tagged {}", tag_name);
     Ok(())
 }
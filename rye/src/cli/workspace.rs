@@ -0,0 +1,537 @@
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+use anyhow::{Context, Error};
+use clap::{Parser, ValueEnum};
+use console::style;
+use globset::Glob;
+use pep440_rs::Version;
+use serde::Serialize;
+
+use crate::cli::run::forward_lines;
+use crate::cli::sync::parse_jobs;
+use crate::config::Config;
+use crate::pyproject::{
+    locate_projects, project_dependency_graph, sort_projects_topologically, topological_order,
+    PyProject,
+};
+use crate::sources::py::{matches_version, PythonVersion, PythonVersionRequest};
+use crate::sync::{sync, SyncMode, SyncOptions};
+use crate::tui::redirect_to_stderr;
+use crate::utils::signals::{self, in_own_process_group};
+use crate::utils::{CommandOutput, QuietExit};
+
+/// Runs a command across the members of a workspace.
+///
+/// Members are visited in the order of their dependencies on each other:
+/// a member always comes after the members it depends on.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// Perform the operation on a specific package
+    #[arg(short, long)]
+    package: Vec<String>,
+    /// Only include members whose name or path matches this glob
+    #[arg(long)]
+    filter: Vec<String>,
+    /// Stop after the first member for which the command fails.
+    #[arg(long)]
+    fail_fast: bool,
+    /// Run the command in up to this many members at once.
+    #[arg(short, long, default_value_t = 1, value_parser = parse_jobs)]
+    jobs: usize,
+    /// Use this pyproject.toml file
+    #[arg(long, value_name = "PYPROJECT_TOML")]
+    pyproject: Option<PathBuf>,
+    /// Turns off all output.
+    #[arg(short, long)]
+    quiet: bool,
+    #[command(subcommand)]
+    command: SubCommand,
+}
+
+/// Arguments passed through to the command.
+#[derive(Parser, Debug)]
+struct ForwardArgs {
+    /// Arguments for the command.
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<OsString>,
+}
+
+/// Lists the members of the workspace.
+#[derive(Parser, Debug)]
+struct ListCommand {
+    /// Also show the dependencies between members
+    #[arg(long)]
+    graph: bool,
+    /// Request parseable output format
+    #[arg(long)]
+    format: Option<Format>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+enum Format {
+    Json,
+    Dot,
+}
+
+/// Output structure for workspace list --format=json
+#[derive(Serialize)]
+struct ListOutput {
+    python: Option<String>,
+    members: Vec<ListMember>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cycle: Option<Vec<String>>,
+    conflicts: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ListMember {
+    name: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requires_python: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    python_pin: Option<String>,
+    dependencies: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+enum SubCommand {
+    List(ListCommand),
+    /// Syncs the virtualenv shared by the workspace.
+    Sync(ForwardArgs),
+    /// Runs a script or command in every member.
+    Run(ForwardArgs),
+    /// Runs the tests of every member.
+    Test(ForwardArgs),
+    /// Runs the code formatter on every member.
+    Fmt(ForwardArgs),
+    /// Runs the linter on every member.
+    Lint(ForwardArgs),
+    /// Runs the type checker on every member.
+    Typecheck(ForwardArgs),
+}
+
+impl SubCommand {
+    fn name(&self) -> &'static str {
+        match self {
+            SubCommand::List(..) => "list",
+            SubCommand::Sync(..) => "sync",
+            SubCommand::Run(..) => "run",
+            SubCommand::Test(..) => "test",
+            SubCommand::Fmt(..) => "fmt",
+            SubCommand::Lint(..) => "lint",
+            SubCommand::Typecheck(..) => "typecheck",
+        }
+    }
+
+    fn args(&self) -> &[OsString] {
+        match self {
+            SubCommand::List(..) => &[],
+            SubCommand::Sync(args)
+            | SubCommand::Run(args)
+            | SubCommand::Test(args)
+            | SubCommand::Fmt(args)
+            | SubCommand::Lint(args)
+            | SubCommand::Typecheck(args) => &args.args,
+        }
+    }
+
+    /// Does the command sync the virtualenv before it runs?
+    fn syncs_venv(&self) -> bool {
+        matches!(self, SubCommand::Test(..)) && Config::current().autosync()
+    }
+}
+
+pub fn execute(cmd: Args) -> Result<(), Error> {
+    let output = CommandOutput::from_quiet_and_verbose(cmd.quiet, false);
+    let project = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
+    let rye = env::current_exe()?;
+    let workspace_toml = project.workspace_path().join("pyproject.toml");
+
+    if let SubCommand::List(ref args) = cmd.command {
+        let workspace_path = project.workspace_path().to_path_buf();
+        let python = project.venv_python_version().ok();
+        let projects = select_projects(&cmd, project)?;
+        return list(args, &workspace_path, python.as_ref(), projects);
+    }
+
+    // all members share a single virtualenv, so there is only one thing to
+    // sync.  What runs in parallel there are its lockfile resolutions.
+    if let SubCommand::Sync(ref args) = cmd.command {
+        let mut sync_cmd = Command::new(&rye);
+        sync_cmd.arg("sync");
+        if cmd.jobs > 1 {
+            sync_cmd.arg("--jobs").arg(cmd.jobs.to_string());
+        }
+        let status = sync_cmd
+            .args(&args.args)
+            .current_dir(project.workspace_path())
+            .status()
+            .context("unable to run rye sync")?;
+        if !status.success() {
+            return Err(Error::new(QuietExit(status.code().unwrap_or(1))));
+        }
+        return Ok(());
+    }
+
+    let projects = select_projects(&cmd, project)?;
+    let projects = sort_projects_topologically(projects)?;
+
+    let failed = if cmd.jobs > 1 && projects.len() > 1 {
+        // sync the shared virtualenv up front so that the members do not wait
+        // for the first one to create it.  Commands that would sync it
+        // themselves skip that then.
+        {
+            let _guard = redirect_to_stderr(true);
+            let options = if cmd.command.syncs_venv() {
+                SyncOptions {
+                    output,
+                    dev: true,
+                    mode: SyncMode::Regular,
+                    pyproject: Some(workspace_toml),
+                    jobs: cmd.jobs,
+                    ..SyncOptions::default()
+                }
+            } else {
+                SyncOptions {
+                    output,
+                    ..SyncOptions::python_only().pyproject(Some(workspace_toml))
+                }
+            };
+            sync(options).context("failed to sync ahead of running members in parallel")?;
+        }
+        run_parallel(&cmd, &rye, &projects)?
+    } else {
+        run_serial(&cmd, &rye, &projects, output)?
+    };
+
+    if let Some((_, code)) = failed.last() {
+        if output != CommandOutput::Quiet {
+            echo!();
+            echo!(
+                "{} failed for: {}",
+                style(format!("rye {}", cmd.command.name())).red(),
+                failed
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        Err(Error::new(QuietExit(*code)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Runs the command in one member after another.
+///
+/// Returns the members for which the command failed with its exit code.
+fn run_serial(
+    cmd: &Args,
+    rye: &Path,
+    projects: &[PyProject],
+    output: CommandOutput,
+) -> Result<Vec<(String, i32)>, Error> {
+    let mut failed = Vec::new();
+    for (idx, project) in projects.iter().enumerate() {
+        let name = project.name().unwrap_or("<unknown>");
+        if output != CommandOutput::Quiet {
+            if idx > 0 {
+                echo!();
+            }
+            echo!(
+                "Running `rye {}` for {} ({})",
+                cmd.command.name(),
+                style(name).cyan(),
+                style(project.root_path().display()).dim()
+            );
+        }
+
+        let status = Command::new(rye)
+            .arg(cmd.command.name())
+            .args(cmd.command.args())
+            .current_dir(project.root_path())
+            .status()
+            .with_context(|| format!("unable to run rye {}", cmd.command.name()))?;
+        if !status.success() {
+            failed.push((name.to_string(), status.code().unwrap_or(1)));
+            if cmd.fail_fast {
+                break;
+            }
+        }
+    }
+    Ok(failed)
+}
+
+/// The members that still have to run in [`run_parallel`].
+struct Schedule {
+    /// Indexes of the members that did not start yet, in dependency order.
+    pending: Vec<usize>,
+    /// Names of the members that are done.
+    done: HashSet<String>,
+    failed: Vec<(String, i32)>,
+    error: Option<Error>,
+}
+
+/// Runs the command in several members at once.
+///
+/// Up to `--jobs` workers take the next member whose dependencies are done,
+/// so members that do not depend on each other run concurrently.  Every line
+/// of output is prefixed with the name of the member it came from.  Returns
+/// the members for which the command failed with its exit code.
+fn run_parallel(
+    cmd: &Args,
+    rye: &Path,
+    projects: &[PyProject],
+) -> Result<Vec<(String, i32)>, Error> {
+    let graph = project_dependency_graph(projects)?;
+    let names = projects
+        .iter()
+        .map(|x| x.normalized_name())
+        .collect::<Result<Vec<_>, _>>()?;
+    let width = names.iter().map(|x| x.len()).max().unwrap_or(0);
+    let schedule = Mutex::new(Schedule {
+        pending: (0..projects.len()).collect(),
+        done: HashSet::new(),
+        failed: Vec::new(),
+        error: None,
+    });
+    let changed = Condvar::new();
+
+    let worker = || loop {
+        let idx = {
+            let mut schedule = schedule.lock().unwrap();
+            loop {
+                if schedule.pending.is_empty()
+                    || schedule.error.is_some()
+                    || (cmd.fail_fast && !schedule.failed.is_empty())
+                {
+                    return;
+                }
+                let ready = schedule.pending.iter().position(|&idx| {
+                    graph[&names[idx]]
+                        .iter()
+                        .all(|dep| schedule.done.contains(dep))
+                });
+                match ready {
+                    Some(pos) => break schedule.pending.remove(pos),
+                    None => schedule = changed.wait(schedule).unwrap(),
+                }
+            }
+        };
+
+        let prefix = style(format!("{:width$} |", names[idx], width = width))
+            .cyan()
+            .to_string();
+        let result = run_prefixed(cmd, rye, &projects[idx], &prefix);
+        let mut schedule = schedule.lock().unwrap();
+        schedule.done.insert(names[idx].clone());
+        match result {
+            Ok(status) if !status.success() => {
+                let name = projects[idx].name().unwrap_or("<unknown>").to_string();
+                schedule.failed.push((name, status.code().unwrap_or(1)));
+            }
+            Ok(_) => {}
+            Err(err) => {
+                schedule.error.get_or_insert(err);
+            }
+        }
+        changed.notify_all();
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..cmd.jobs.min(projects.len()) {
+            scope.spawn(worker);
+        }
+    });
+
+    let schedule = schedule.into_inner().unwrap();
+    match schedule.error {
+        Some(err) => Err(err),
+        None => Ok(schedule.failed),
+    }
+}
+
+/// Runs the command in a member and prefixes every line of its output.
+fn run_prefixed(
+    cmd: &Args,
+    rye: &Path,
+    project: &PyProject,
+    prefix: &str,
+) -> Result<ExitStatus, Error> {
+    let mut child = Command::new(rye);
+    child
+        .arg(cmd.command.name())
+        .args(cmd.command.args())
+        .current_dir(project.root_path());
+    if cmd.command.syncs_venv() {
+        // the virtualenv was synced before any member started.
+        child.env("RYE_BEHAVIOR_AUTOSYNC", "0");
+    }
+    let mut child = in_own_process_group(&mut child)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("unable to run rye {}", cmd.command.name()))?;
+    let _tracked = signals::track(&child, true);
+    thread::scope(|scope| {
+        if let Some(stdout) = child.stdout.take() {
+            scope.spawn(move || forward_lines(stdout, prefix, false));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            scope.spawn(move || forward_lines(stderr, prefix, true));
+        }
+        Ok(child.wait()?)
+    })
+}
+
+/// Finds the members selected with `--package` and `--filter`.
+fn select_projects(cmd: &Args, project: PyProject) -> Result<Vec<PyProject>, Error> {
+    let workspace_path = project.workspace_path().to_path_buf();
+    let filters = cmd
+        .filter
+        .iter()
+        .map(|x| Ok(Glob::new(x)?.compile_matcher()))
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(
+        locate_projects(project, cmd.package.is_empty(), &cmd.package)?
+            .into_iter()
+            .filter(|project| {
+                filters.is_empty()
+                    || filters.iter().any(|filter| {
+                        project.name().map_or(false, |name| filter.is_match(name))
+                            || project
+                                .root_path()
+                                .strip_prefix(&workspace_path)
+                                .map_or(false, |path| filter.is_match(path))
+                    })
+            })
+            .collect(),
+    )
+}
+
+fn list(
+    cmd: &ListCommand,
+    workspace_path: &Path,
+    python: Option<&PythonVersion>,
+    projects: Vec<PyProject>,
+) -> Result<(), Error> {
+    let graph = project_dependency_graph(&projects)?;
+    let (order, cycle) = match topological_order(&graph) {
+        Ok(order) => (order, None),
+        Err(cycle) => (graph.keys().cloned().collect(), Some(cycle)),
+    };
+
+    let mut members = Vec::new();
+    let mut conflicts = Vec::new();
+    for name in order {
+        let project = match projects
+            .iter()
+            .find(|x| x.normalized_name().ok().as_ref() == Some(&name))
+        {
+            Some(project) => project,
+            None => continue,
+        };
+        let path = project
+            .root_path()
+            .strip_prefix(workspace_path)
+            .ok()
+            .map(|x| x.to_string_lossy().into_owned())
+            .filter(|x| !x.is_empty())
+            .unwrap_or_else(|| ".".into());
+        let requires_python = project.requires_python();
+        let python_pin = if project.root_path() != workspace_path {
+            fs::read_to_string(project.root_path().join(".python-version"))
+                .ok()
+                .and_then(|x| x.trim().parse::<PythonVersionRequest>().ok())
+        } else {
+            None
+        };
+
+        // all members share the virtualenv of the workspace, so they all need
+        // to agree with its Python version.
+        if let Some(python) = python {
+            if let Some(ref pin) = python_pin {
+                if !matches_version(pin, python) {
+                    conflicts.push(format!(
+                        "{} pins Python {} but the workspace uses {}",
+                        name, pin, python
+                    ));
+                }
+            }
+            if let Some(ref requires_python) = requires_python {
+                if !requires_python.contains(&Version::from(python.clone())) {
+                    conflicts.push(format!(
+                        "{} requires Python {} but the workspace uses {}",
+                        name, requires_python, python
+                    ));
+                }
+            }
+        }
+
+        members.push(ListMember {
+            dependencies: graph.get(&name).into_iter().flatten().cloned().collect(),
+            name,
+            path,
+            requires_python: requires_python.map(|x| x.to_string()),
+            python_pin: python_pin.map(|x| x.to_string()),
+        });
+    }
+
+    match cmd.format {
+        Some(Format::Json) => {
+            let output = ListOutput {
+                python: python.map(|x| x.to_string()),
+                members,
+                cycle,
+                conflicts,
+            };
+            serde_json::to_writer_pretty(std::io::stdout().lock(), &output)?;
+            echo!();
+            return Ok(());
+        }
+        Some(Format::Dot) => {
+            echo!("digraph workspace {{");
+            for member in &members {
+                echo!("  {:?};", member.name);
+                for dep in &member.dependencies {
+                    echo!("  {:?} -> {:?};", member.name, dep);
+                }
+            }
+            echo!("}}");
+        }
+        None => {
+            for member in &members {
+                echo!(
+                    "{} ({})",
+                    style(&member.name).cyan(),
+                    style(&member.path).dim()
+                );
+                if cmd.graph {
+                    for dep in &member.dependencies {
+                        echo!("  -> {}", dep);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(cycle) = cycle {
+        warn!("dependency cycle between members: {}", cycle.join(", "));
+    }
+    for conflict in conflicts {
+        warn!("{}", conflict);
+    }
+
+    Ok(())
+}
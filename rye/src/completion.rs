@@ -0,0 +1,409 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+
+use anyhow::Error;
+use clap::{ArgAction, ValueEnum};
+
+use crate::platform::{get_app_dir, list_known_toolchains};
+use crate::pyproject::{normalize_package_name, DependencyKind, PyProject};
+
+/// The shells completion scripts can be generated for.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+pub enum CompletionShell {
+    Bash,
+    Elvish,
+    Fish,
+    Nushell,
+    Powershell,
+    Zsh,
+}
+
+/// The kinds of values that are completed by asking rye at completion time.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+pub enum CompletionValues {
+    /// The scripts of the current project.
+    Scripts,
+    /// The dependencies of the current project.
+    Dependencies,
+    /// The installed toolchains.
+    Toolchains,
+    /// The installed global tools.
+    Tools,
+}
+
+impl CompletionValues {
+    fn name(self) -> &'static str {
+        match self {
+            CompletionValues::Scripts => "scripts",
+            CompletionValues::Dependencies => "dependencies",
+            CompletionValues::Toolchains => "toolchains",
+            CompletionValues::Tools => "tools",
+        }
+    }
+}
+
+/// The positional arguments that get dynamic completions.
+///
+/// Each entry is the command path, the values to complete and if the values
+/// are completed for all positional arguments or only the first one.
+const DYNAMIC_COMPLETIONS: &[(&str, CompletionValues, bool)] = &[
+    ("run", CompletionValues::Scripts, false),
+    ("remove", CompletionValues::Dependencies, true),
+    ("pin", CompletionValues::Toolchains, false),
+    ("toolchain remove", CompletionValues::Toolchains, false),
+    ("uninstall", CompletionValues::Tools, true),
+    ("tools uninstall", CompletionValues::Tools, true),
+    ("tools inject", CompletionValues::Tools, false),
+    ("tools reinstall", CompletionValues::Tools, false),
+    ("tools upgrade", CompletionValues::Tools, false),
+];
+
+/// Writes the completion script for a shell.
+///
+/// For all shells but nushell the script generated by `clap_complete` is
+/// extended with a wrapper that asks `rye self complete-values` for the
+/// values listed in [`DYNAMIC_COMPLETIONS`].
+pub fn write_completion_script(
+    shell: CompletionShell,
+    cmd: &mut clap::Command,
+    out: &mut dyn Write,
+) -> Result<(), Error> {
+    let clap_shell = match shell {
+        CompletionShell::Bash => clap_complete::Shell::Bash,
+        CompletionShell::Elvish => clap_complete::Shell::Elvish,
+        CompletionShell::Fish => clap_complete::Shell::Fish,
+        CompletionShell::Powershell => clap_complete::Shell::PowerShell,
+        CompletionShell::Zsh => clap_complete::Shell::Zsh,
+        CompletionShell::Nushell => {
+            out.write_all(nushell_script(cmd).as_bytes())?;
+            return Ok(());
+        }
+    };
+
+    let mut script = Vec::new();
+    clap_complete::generate(clap_shell, cmd, "rye", &mut script);
+    let script = String::from_utf8(script)?;
+    let script = match shell {
+        CompletionShell::Bash => script + &bash_dynamic(),
+        CompletionShell::Zsh => script + &zsh_dynamic(),
+        CompletionShell::Fish => script + &fish_dynamic(),
+        CompletionShell::Powershell => powershell_dynamic(&script),
+        _ => script,
+    };
+    out.write_all(script.as_bytes())?;
+    Ok(())
+}
+
+/// Lists the values for dynamic completions.
+///
+/// Errors (like not being in a project) result in no values as nothing but
+/// values must be printed while the shell completes.
+pub fn list_completion_values(kind: CompletionValues) -> Vec<String> {
+    let mut rv = BTreeSet::new();
+    match kind {
+        CompletionValues::Scripts => {
+            if let Ok(project) = PyProject::discover() {
+                rv.extend(project.list_scripts());
+            }
+        }
+        CompletionValues::Dependencies => {
+            if let Ok(project) = PyProject::discover() {
+                let kinds = [DependencyKind::Normal, DependencyKind::Dev]
+                    .into_iter()
+                    .chain(
+                        project
+                            .extras()
+                            .into_iter()
+                            .map(|x| DependencyKind::Optional(Cow::Borrowed(x))),
+                    )
+                    .collect::<Vec<_>>();
+                for kind in kinds {
+                    for dep in project.iter_dependencies(kind) {
+                        if let Ok(req) = dep.expand(|name| std::env::var(name).ok()) {
+                            rv.insert(req.name);
+                        }
+                    }
+                }
+            }
+        }
+        CompletionValues::Toolchains => {
+            if let Ok(toolchains) = list_known_toolchains() {
+                rv.extend(
+                    toolchains
+                        .into_iter()
+                        .map(|(version, _)| version.to_string()),
+                );
+            }
+        }
+        CompletionValues::Tools => {
+            let tool_dir = get_app_dir().join("tools");
+            for entry in fs::read_dir(tool_dir).into_iter().flatten().flatten() {
+                if entry.path().is_dir() {
+                    rv.insert(normalize_package_name(&entry.file_name().to_string_lossy()));
+                }
+            }
+        }
+    }
+    rv.into_iter().collect()
+}
+
+/// The arms of a `case` statement (bash and zsh) that set `kind` based on
+/// the words before the cursor.
+fn case_arms() -> String {
+    let mut rv = String::new();
+    for (path, kind, repeat) in DYNAMIC_COMPLETIONS {
+        let _ = write!(rv, "        \"{}\"", path);
+        if *repeat {
+            let _ = write!(rv, "|\"{} \"*", path);
+        }
+        let _ = writeln!(rv, ") kind={} ;;", kind.name());
+    }
+    rv
+}
+
+fn bash_dynamic() -> String {
+    let mut rv = String::from(
+        r#"
+_rye_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[*]:1:COMP_CWORD-1}" kind=""
+    case "$prev" in
+"#,
+    );
+    rv.push_str(&case_arms());
+    rv.push_str(
+        r#"    esac
+    if [[ -n "$kind" && "$cur" != -* ]]; then
+        COMPREPLY=( $(compgen -W "$(rye self complete-values "$kind" 2>/dev/null)" -- "$cur") )
+        return 0
+    fi
+    _rye "$@"
+}
+
+complete -F _rye_dynamic -o bashdefault -o default rye
+"#,
+    );
+    rv
+}
+
+fn zsh_dynamic() -> String {
+    let mut rv = String::from(
+        r#"
+_rye_dynamic() {
+    local kind=""
+    case "${words[2,CURRENT-1]}" in
+"#,
+    );
+    rv.push_str(&case_arms());
+    rv.push_str(
+        r#"    esac
+    if [[ -n "$kind" && "$PREFIX" != -* ]]; then
+        local -a values
+        values=(${(f)"$(rye self complete-values $kind 2>/dev/null)"})
+        compadd -a values
+        return
+    fi
+    _rye "$@"
+}
+
+compdef _rye_dynamic rye
+"#,
+    );
+    rv
+}
+
+fn fish_dynamic() -> String {
+    let mut rv = String::from(
+        r#"
+function __rye_dynamic_is
+    set -l tokens (commandline -opc)
+    set -l args (string join ' ' $tokens[2..-1])
+    test "$args" = "$argv[1]"; or begin; test "$argv[2]" = repeat; and string match -q -- "$argv[1] *" "$args"; end
+end
+
+"#,
+    );
+    for (path, kind, repeat) in DYNAMIC_COMPLETIONS {
+        let _ = writeln!(
+            rv,
+            "complete -c rye -n '__rye_dynamic_is \"{}\"{}' -f -a '(rye self complete-values {} 2>/dev/null)'",
+            path,
+            if *repeat { " repeat" } else { "" },
+            kind.name()
+        );
+    }
+    rv
+}
+
+fn powershell_dynamic(script: &str) -> String {
+    // the completer generated by clap is kept and invoked for everything
+    // that is not completed dynamically.
+    let mut rv = script.replacen(
+        "Register-ArgumentCompleter -Native -CommandName 'rye' -ScriptBlock {",
+        "$global:__ryeStaticCompleter = {",
+        1,
+    );
+    rv.push_str(
+        r#"
+Register-ArgumentCompleter -Native -CommandName 'rye' -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $words = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object { $_.ToString() })
+    if ($wordToComplete -ne '') {
+        $words = @($words | Select-Object -SkipLast 1)
+    }
+    $prev = $words -join ' '
+    $kind = switch -Wildcard ($prev) {
+"#,
+    );
+    for (path, kind, repeat) in DYNAMIC_COMPLETIONS {
+        let _ = writeln!(rv, "        '{}' {{ '{}'; break }}", path, kind.name());
+        if *repeat {
+            let _ = writeln!(rv, "        '{} *' {{ '{}'; break }}", path, kind.name());
+        }
+    }
+    rv.push_str(
+        r#"    }
+    if ($kind -and -not $wordToComplete.StartsWith('-')) {
+        rye self complete-values $kind 2>$null | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
+            [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+        }
+        return
+    }
+    & $global:__ryeStaticCompleter $wordToComplete $commandAst $cursorPosition
+}
+"#,
+    );
+    rv
+}
+
+/// Generates nushell `extern` definitions for all commands.
+fn nushell_script(cmd: &mut clap::Command) -> String {
+    cmd.build();
+    let mut rv = String::from("module rye-completions {\n");
+    for (_, kind, _) in DYNAMIC_COMPLETIONS {
+        let def = format!(
+            "  def \"nu-complete rye {0}\" [] {{\n    ^rye self complete-values {0} | lines\n  }}\n\n",
+            kind.name()
+        );
+        if !rv.contains(&def) {
+            rv.push_str(&def);
+        }
+    }
+    write_nushell_extern(&mut rv, cmd, "rye");
+    rv.push_str("}\n\nexport use rye-completions *\n");
+    rv
+}
+
+fn write_nushell_extern(rv: &mut String, cmd: &clap::Command, path: &str) {
+    let dynamic = path
+        .strip_prefix("rye ")
+        .and_then(|path| DYNAMIC_COMPLETIONS.iter().find(|x| x.0 == path));
+
+    let mut defs = String::new();
+    let mut params = String::new();
+    let mut seen_optional = false;
+    for arg in cmd.get_arguments().filter(|x| !x.is_hide_set()) {
+        let help = arg
+            .get_help()
+            .map(|x| format!("  # {}", x.to_string().lines().next().unwrap_or("")))
+            .unwrap_or_default();
+        let values = arg
+            .get_possible_values()
+            .iter()
+            .filter(|x| !x.is_hide_set())
+            .map(|x| format!("\"{}\"", x.get_name()))
+            .collect::<Vec<_>>();
+        let completer = if let (Some((_, kind, _)), true) = (dynamic, arg.is_positional()) {
+            format!("@\"nu-complete rye {}\"", kind.name())
+        } else if !values.is_empty() {
+            let name = format!("nu-complete {} {}", path, arg.get_id());
+            let _ = writeln!(
+                defs,
+                "  def \"{}\" [] {{\n    [{}]\n  }}\n",
+                name,
+                values.join(" ")
+            );
+            format!("@\"{}\"", name)
+        } else {
+            String::new()
+        };
+
+        if arg.is_positional() {
+            let name = arg.get_id().as_str().replace('_', "-");
+            let multiple = matches!(arg.get_action(), ArgAction::Append)
+                || arg.get_num_args().map_or(false, |x| x.max_values() > 1);
+            if multiple {
+                let _ = writeln!(params, "    ...{}: string{}{}", name, completer, help);
+                continue;
+            }
+            // nushell does not allow required parameters after optional ones
+            let optional = seen_optional || !arg.is_required_set();
+            seen_optional |= optional;
+            let _ = writeln!(
+                params,
+                "    {}{}: string{}{}",
+                name,
+                if optional { "?" } else { "" },
+                completer,
+                help
+            );
+            continue;
+        }
+
+        let mut flag = match (arg.get_long(), arg.get_short()) {
+            (Some(long), Some(short)) => format!("--{}(-{})", long, short),
+            (Some(long), None) => format!("--{}", long),
+            (None, Some(short)) => format!("-{}", short),
+            (None, None) => continue,
+        };
+        if arg.get_action().takes_values() {
+            let _ = write!(flag, ": string{}", completer);
+        }
+        let _ = writeln!(params, "    {}{}", flag, help);
+    }
+
+    rv.push_str(&defs);
+    if let Some(about) = cmd.get_about() {
+        let _ = writeln!(rv, "  # {}", about.to_string().lines().next().unwrap_or(""));
+    }
+    let _ = writeln!(rv, "  export extern \"{}\" [", path);
+    rv.push_str(&params);
+    rv.push_str("  ]\n\n");
+
+    for sub in cmd.get_subcommands() {
+        if sub.is_hide_set() || sub.get_name() == "help" {
+            continue;
+        }
+        write_nushell_extern(rv, sub, &format!("{} {}", path, sub.get_name()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nushell_script() {
+        let mut cmd = clap::Command::new("rye").subcommand(
+            clap::Command::new("run")
+                .about("Runs a command")
+                .arg(clap::Arg::new("script"))
+                .arg(
+                    clap::Arg::new("list")
+                        .long("list")
+                        .short('l')
+                        .action(ArgAction::SetTrue),
+                ),
+        );
+        let script = nushell_script(&mut cmd);
+        assert!(script.contains("export extern \"rye run\" ["));
+        assert!(script.contains("    script?: string@\"nu-complete rye scripts\"\n"));
+        assert!(script.contains("    --list(-l)\n"));
+        assert!(script.contains("^rye self complete-values tools | lines"));
+    }
+}
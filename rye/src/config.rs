@@ -1,40 +1,362 @@
-use std::fs;
+use std::collections::BTreeMap;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::{env, fmt, fs};
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use once_cell::sync::Lazy;
 use pep440_rs::Operator;
 use regex::Regex;
-use toml_edit::DocumentMut;
+use toml_edit::{DocumentMut, ImDocument, Item, Table, TableLike, Value};
 
 use crate::platform::{get_app_dir, get_latest_cpython_version};
-use crate::pyproject::{BuildSystem, SourceRef, SourceRefType};
-use crate::sources::py::PythonVersionRequest;
+use crate::pyproject::{BuildSystem, RepositoryRef, SourceRef, SourceRefType};
+use crate::sources::py::{BuildFlavor, PythonVersionRequest};
 use crate::utils::{toml, IoPathContext};
 
 static CONFIG: Mutex<Option<Arc<Config>>> = Mutex::new(None);
 static AUTHOR_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^\s*(.*?)\s*<\s*(.*?)\s*>\s*$").unwrap());
 
+/// The prefix of environment variables that override `behavior` keys.
+const BEHAVIOR_ENV_PREFIX: &str = "RYE_BEHAVIOR_";
+
+/// The type of the value of a config key.
+#[derive(Copy, Clone, Debug)]
+pub enum ConfigType {
+    String,
+    Bool,
+    Choice(&'static [&'static str]),
+    StringArray,
+    TableArray,
+}
+
+impl ConfigType {
+    fn matches(self, item: &Item) -> bool {
+        match self {
+            ConfigType::String => item.is_str(),
+            ConfigType::Bool => item.is_bool(),
+            ConfigType::Choice(choices) => item.as_str().map_or(false, |x| choices.contains(&x)),
+            ConfigType::StringArray => item
+                .as_array()
+                .map_or(false, |x| x.iter().all(|x| x.is_str())),
+            ConfigType::TableArray => {
+                item.is_array_of_tables()
+                    || item
+                        .as_array()
+                        .map_or(false, |x| x.iter().all(|x| x.is_inline_table()))
+            }
+        }
+    }
+
+    fn json_schema(self) -> serde_json::Value {
+        match self {
+            ConfigType::String => serde_json::json!({"type": "string"}),
+            ConfigType::Bool => serde_json::json!({"type": "boolean"}),
+            ConfigType::Choice(choices) => serde_json::json!({"enum": choices}),
+            ConfigType::StringArray => {
+                serde_json::json!({"type": "array", "items": {"type": "string"}})
+            }
+            ConfigType::TableArray => {
+                serde_json::json!({"type": "array", "items": {"type": "object"}})
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConfigType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigType::String => write!(f, "a string"),
+            ConfigType::Bool => write!(f, "a boolean"),
+            ConfigType::Choice(choices) => {
+                write!(f, "one of ")?;
+                for (idx, choice) in choices.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "'{}'", choice)?;
+                }
+                Ok(())
+            }
+            ConfigType::StringArray => write!(f, "an array of strings"),
+            ConfigType::TableArray => write!(f, "an array of tables"),
+        }
+    }
+}
+
+/// A key of the config file.
+#[derive(Debug)]
+pub struct ConfigKey {
+    /// The dotted key.
+    pub key: &'static str,
+    pub ty: ConfigType,
+    /// The default value in human readable form.
+    pub default: Option<&'static str>,
+    pub description: &'static str,
+}
+
+/// All keys of the config file.
+pub const CONFIG_KEYS: &[ConfigKey] = &[
+    ConfigKey {
+        key: "default.requires-python",
+        ty: ConfigType::String,
+        default: Some(">= 3.8"),
+        description: "The `project.requires-python` of new projects",
+    },
+    ConfigKey {
+        key: "default.toolchain",
+        ty: ConfigType::String,
+        default: Some("latest CPython"),
+        description: "The toolchain used when a project does not pin one",
+    },
+    ConfigKey {
+        key: "default.build-system",
+        ty: ConfigType::Choice(&["hatchling", "setuptools", "flit", "pdm", "maturin"]),
+        default: Some("hatchling"),
+        description: "The build system of new projects",
+    },
+    ConfigKey {
+        key: "default.license",
+        ty: ConfigType::String,
+        default: None,
+        description: "The license of new projects",
+    },
+    ConfigKey {
+        key: "default.author",
+        ty: ConfigType::String,
+        default: Some("from git"),
+        description: "The author (`Name <email>`) of new projects",
+    },
+    ConfigKey {
+        key: "default.dependency-operator",
+        ty: ConfigType::Choice(&[">=", "~=", "==", "unbounded"]),
+        default: Some(">="),
+        description: "The operator of dependencies added by `rye add`",
+    },
+    ConfigKey {
+        key: "behavior.force-rye-managed",
+        ty: ConfigType::Bool,
+        default: Some("false"),
+        description: "Assume that all projects are managed by Rye",
+    },
+    ConfigKey {
+        key: "behavior.global-python",
+        ty: ConfigType::Bool,
+        default: Some("false"),
+        description: "Resolve the `python` shim to a toolchain outside of virtualenvs",
+    },
+    ConfigKey {
+        key: "behavior.use-uv",
+        ty: ConfigType::Bool,
+        default: Some("false"),
+        description: "Use uv instead of pip-tools",
+    },
+    ConfigKey {
+        key: "behavior.autosync",
+        ty: ConfigType::Bool,
+        default: Some("same as behavior.use-uv"),
+        description: "Sync automatically after `rye add` and `rye remove`",
+    },
+    ConfigKey {
+        key: "behavior.venv-mark-sync-ignore",
+        ty: ConfigType::Bool,
+        default: Some("true"),
+        description: "Mark the `.venv` to not be synchronized to cloud storage",
+    },
+    ConfigKey {
+        key: "behavior.venv-in-project",
+        ty: ConfigType::Bool,
+        default: Some("true"),
+        description: "Place the virtualenv in the project rather than in `~/.rye/venvs`",
+    },
+    ConfigKey {
+        key: "behavior.shim-cache",
+        ty: ConfigType::Bool,
+        default: Some("true"),
+        description: "Remember where shims resolve to within projects",
+    },
+    ConfigKey {
+        key: "behavior.windows-registry",
+        ty: ConfigType::Bool,
+        default: Some("false"),
+        description: "Register toolchains and virtualenvs in the Windows registry",
+    },
+    ConfigKey {
+        key: "behavior.toolchain-upgrade-check",
+        ty: ConfigType::Bool,
+        default: Some("false"),
+        description: "Check for newer toolchain patch releases once per day on sync",
+    },
+    ConfigKey {
+        key: "behavior.fetch-with-build-info",
+        ty: ConfigType::Bool,
+        default: Some("false"),
+        description: "Fetch interpreters with build information",
+    },
+    ConfigKey {
+        key: "toolchain.build-flavor",
+        ty: ConfigType::Choice(&["install_only_stripped", "install_only", "full"]),
+        default: Some("install_only_stripped"),
+        description: "The kind of CPython build to fetch",
+    },
+    ConfigKey {
+        key: "proxy.http",
+        ty: ConfigType::String,
+        default: None,
+        description: "The proxy for HTTP (overridden by `http_proxy`)",
+    },
+    ConfigKey {
+        key: "proxy.https",
+        ty: ConfigType::String,
+        default: None,
+        description: "The proxy for HTTPS (overridden by `https_proxy`)",
+    },
+    ConfigKey {
+        key: "shims.extra",
+        ty: ConfigType::StringArray,
+        default: Some("[]"),
+        description: "Extra shims to create next to `python`",
+    },
+    ConfigKey {
+        key: "self.update-channel",
+        ty: ConfigType::Choice(&["stable", "nightly"]),
+        default: Some("stable"),
+        description: "The release channel of `rye self update`",
+    },
+    ConfigKey {
+        key: "self.update-url",
+        ty: ConfigType::String,
+        default: None,
+        description: "URL template for binaries downloaded by `rye self update`",
+    },
+    ConfigKey {
+        key: "sources",
+        ty: ConfigType::TableArray,
+        default: None,
+        description: "Additional package sources",
+    },
+    ConfigKey {
+        key: "repositories",
+        ty: ConfigType::TableArray,
+        default: None,
+        description: "The repositories for `rye publish --repository`",
+    },
+];
+
+/// Legacy spellings of keys that are still accepted.
+const LEGACY_CONFIG_KEYS: &[(&str, &str)] = &[
+    ("default.dependency_operator", "default.dependency-operator"),
+    ("behavior.force_rye_managed", "behavior.force-rye-managed"),
+];
+
+/// Looks up a known config key, also accepting legacy spellings.
+pub fn find_config_key(key: &str) -> Option<&'static ConfigKey> {
+    let key = LEGACY_CONFIG_KEYS
+        .iter()
+        .find(|x| x.0 == key)
+        .map_or(key, |x| x.1);
+    CONFIG_KEYS.iter().find(|x| x.key == key)
+}
+
+/// Returns a JSON schema of the config file.
+pub fn config_json_schema() -> serde_json::Value {
+    let mut properties = serde_json::Map::new();
+    for config_key in CONFIG_KEYS {
+        let mut schema = config_key.ty.json_schema();
+        schema["description"] = match config_key.default {
+            Some(default) => format!("{} (default: {})", config_key.description, default),
+            None => config_key.description.to_string(),
+        }
+        .into();
+        match config_key.key.split_once('.') {
+            Some((section, key)) => {
+                let section = properties.entry(section).or_insert_with(|| {
+                    serde_json::json!({
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {},
+                    })
+                });
+                section["properties"][key] = schema;
+            }
+            None => {
+                properties.insert(config_key.key.to_string(), schema);
+            }
+        }
+    }
+    serde_json::json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Rye config.toml",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": properties,
+    })
+}
+
+/// Loads the config.
+///
+/// The effective config is layered: the global `config.toml` is overridden
+/// by the `[tool.rye.config]` section of `pyproject.toml` files and by
+/// `rye.toml` files in the current folder or its parents (nearer ones win),
+/// which in turn are overridden by `RYE_BEHAVIOR_*` environment variables.
 pub fn load() -> Result<(), Error> {
     let cfg_path = get_app_dir().join("config.toml");
-    let cfg = if cfg_path.is_file() {
-        Config::from_path(&cfg_path)?
+    let mut cfg = if cfg_path.is_file() {
+        let contents =
+            fs::read_to_string(&cfg_path).path_context(&cfg_path, "failed to read config")?;
+        for problem in check_config(&contents, &cfg_path, &[]) {
+            warn!("{}", problem);
+        }
+        Config::from_contents(&contents, &cfg_path)?
     } else {
         Config {
             doc: DocumentMut::new(),
+            file_doc: DocumentMut::new(),
             path: cfg_path,
+            origins: BTreeMap::new(),
         }
     };
+    if let Ok(here) = env::current_dir() {
+        for (origin, table) in find_project_configs(&here)? {
+            cfg.apply_layer(&table, origin);
+        }
+    }
+    cfg.apply_env(env::vars());
     *CONFIG.lock().unwrap() = Some(Arc::new(cfg));
     Ok(())
 }
 
+/// Where the effective value of a config key comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigOrigin {
+    /// The global `config.toml` or a project's `rye.toml`.
+    File(PathBuf),
+    /// The `[tool.rye.config]` section of a `pyproject.toml`.
+    PyProject(PathBuf),
+    /// An environment variable.
+    Env(String),
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigOrigin::File(path) => write!(f, "file:{}", path.display()),
+            ConfigOrigin::PyProject(path) => write!(f, "pyproject:{}", path.display()),
+            ConfigOrigin::Env(var) => write!(f, "env:{}", var),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Config {
+    /// The effective config with all layers applied.
     doc: DocumentMut,
+    /// The global config file which is what gets modified and saved.
+    file_doc: DocumentMut,
     path: PathBuf,
+    origins: BTreeMap<String, ConfigOrigin>,
 }
 
 impl Config {
@@ -48,9 +370,70 @@ impl Config {
             .clone()
     }
 
-    /// Returns a clone of the internal doc.
+    /// Returns the doc of the global config file for modifications.
     pub fn doc_mut(&mut self) -> &mut DocumentMut {
-        &mut self.doc
+        &mut self.file_doc
+    }
+
+    /// Returns the effective item of a dotted config key.
+    pub fn get_item(&self, key: &str) -> Option<&Item> {
+        let mut ptr = Some(self.doc.as_item());
+        for piece in key.split('.') {
+            ptr = ptr.and_then(|x| x.get(piece));
+        }
+        ptr
+    }
+
+    /// Returns the effective value of a dotted config key.
+    pub fn get_value(&self, key: &str) -> Option<&Value> {
+        self.get_item(key).and_then(|x| x.as_value())
+    }
+
+    /// Returns where the effective value of a dotted config key comes from.
+    pub fn origin(&self, key: &str) -> Option<&ConfigOrigin> {
+        self.origins.get(key)
+    }
+
+    /// Returns all keys that are set together with their origin.
+    pub fn origins(&self) -> &BTreeMap<String, ConfigOrigin> {
+        &self.origins
+    }
+
+    /// Applies a layer of config on top of the effective config.
+    fn apply_layer(&mut self, table: &dyn TableLike, origin: ConfigOrigin) {
+        merge_table(
+            self.doc.as_table_mut(),
+            table,
+            "",
+            &origin,
+            &mut self.origins,
+        );
+    }
+
+    /// Applies `RYE_BEHAVIOR_*` environment variables.
+    ///
+    /// `RYE_BEHAVIOR_USE_UV=1` for instance overrides `behavior.use-uv`.
+    fn apply_env(&mut self, vars: impl Iterator<Item = (String, String)>) {
+        for (var, value) in vars {
+            let key = match var.strip_prefix(BEHAVIOR_ENV_PREFIX) {
+                Some(key) if !key.is_empty() => key.to_ascii_lowercase().replace('_', "-"),
+                _ => continue,
+            };
+            if find_config_key(&format!("behavior.{}", key)).is_none() {
+                warn!("unknown config key 'behavior.{}' (from {})", key, var);
+                continue;
+            }
+            let value = match value.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Value::from(true),
+                "0" | "false" | "no" | "off" => Value::from(false),
+                _ => Value::from(value),
+            };
+            let mut behavior = Table::new();
+            behavior.insert(&key, Item::Value(value));
+            let mut layer = Table::new();
+            layer.insert("behavior", Item::Table(behavior));
+            self.apply_layer(&layer, ConfigOrigin::Env(var));
+        }
     }
 
     /// Saves changes back.
@@ -59,7 +442,7 @@ impl Config {
         if let Some(parent) = self.path.parent() {
             fs::create_dir_all(parent).ok();
         }
-        fs::write(&self.path, self.doc.to_string())
+        fs::write(&self.path, self.file_doc.to_string())
             .path_context(&self.path, "failed to save config")?;
         Ok(())
     }
@@ -72,12 +455,21 @@ impl Config {
     /// Loads a config from a path.
     pub fn from_path(path: &Path) -> Result<Config, Error> {
         let contents = fs::read_to_string(path).path_context(path, "failed to read config")?;
-        Ok(Config {
-            doc: contents
-                .parse::<DocumentMut>()
-                .path_context(path, "failed to parse config")?,
+        Config::from_contents(&contents, path)
+    }
+
+    fn from_contents(contents: &str, path: &Path) -> Result<Config, Error> {
+        let file_doc = contents
+            .parse::<DocumentMut>()
+            .path_context(path, "failed to parse config")?;
+        let mut rv = Config {
+            doc: DocumentMut::new(),
+            file_doc: file_doc.clone(),
             path: path.to_path_buf(),
-        })
+            origins: BTreeMap::new(),
+        };
+        rv.apply_layer(file_doc.as_table(), ConfigOrigin::File(path.to_path_buf()));
+        Ok(rv)
     }
 
     /// Returns the default lower bound Python.
@@ -152,7 +544,9 @@ impl Config {
     }
 
     /// Should dependencies added by default by pinned with ~= or ==
-    pub fn default_dependency_operator(&self) -> Operator {
+    ///
+    /// `None` means that dependencies are added without a version bound.
+    pub fn default_dependency_operator(&self) -> Option<Operator> {
         self.doc
             .get("default")
             .and_then(|x| {
@@ -161,11 +555,12 @@ impl Config {
                     .or_else(|| x.get("dependency_operator"))
             })
             .and_then(|x| x.as_str())
-            .map_or(Operator::GreaterThanEqual, |x| match x {
-                "==" => Operator::Equal,
-                "~=" => Operator::TildeEqual,
-                ">=" => Operator::GreaterThanEqual,
-                _ => Operator::GreaterThanEqual,
+            .map_or(Some(Operator::GreaterThanEqual), |x| match x {
+                "==" => Some(Operator::Equal),
+                "~=" => Some(Operator::TildeEqual),
+                ">=" => Some(Operator::GreaterThanEqual),
+                "unbounded" => None,
+                _ => Some(Operator::GreaterThanEqual),
             })
     }
 
@@ -191,6 +586,67 @@ impl Config {
             .unwrap_or(false)
     }
 
+    /// Returns the names of the extra shims to create next to `python`.
+    pub fn extra_shims(&self) -> Result<Vec<String>, Error> {
+        let mut rv = Vec::new();
+        for name in self
+            .doc
+            .get("shims")
+            .and_then(|x| x.get("extra"))
+            .and_then(|x| x.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let name = name
+                .as_str()
+                .context("invalid value for shims.extra in config.toml")?;
+            if name.is_empty()
+                || name.contains(['/', '\\'])
+                || ["rye", "python", "python3", "pythonw"].contains(&name)
+            {
+                bail!("invalid shim name '{}' in config.toml", name);
+            }
+            rv.push(name.to_string());
+        }
+        Ok(rv)
+    }
+
+    /// Check for newer patch releases of installed toolchains on sync?
+    pub fn toolchain_upgrade_check(&self) -> bool {
+        self.doc
+            .get("behavior")
+            .and_then(|x| x.get("toolchain-upgrade-check"))
+            .and_then(|x| x.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Remember where shims resolve to within projects.
+    pub fn shim_cache(&self) -> bool {
+        self.doc
+            .get("behavior")
+            .and_then(|x| x.get("shim-cache"))
+            .and_then(|x| x.as_bool())
+            .unwrap_or(true)
+    }
+
+    /// Register toolchains and virtualenvs in the Windows registry (PEP 514).
+    pub fn windows_registry(&self) -> bool {
+        self.doc
+            .get("behavior")
+            .and_then(|x| x.get("windows-registry"))
+            .and_then(|x| x.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Place the virtualenv in the project rather than in `~/.rye/venvs`.
+    pub fn venv_in_project(&self) -> bool {
+        self.doc
+            .get("behavior")
+            .and_then(|x| x.get("venv-in-project"))
+            .and_then(|x| x.as_bool())
+            .unwrap_or(true)
+    }
+
     /// Mark the `.venv` to not sync to cloud storage
     pub fn venv_mark_sync_ignore(&self) -> bool {
         self.doc
@@ -251,6 +707,19 @@ impl Config {
         Ok(rv)
     }
 
+    /// Returns the repositories that can be published to.
+    pub fn repositories(&self) -> Result<Vec<RepositoryRef>, Error> {
+        let mut rv = Vec::new();
+        if let Some(repositories) = self.doc.get("repositories").map(|x| toml::iter_tables(x)) {
+            for repository in repositories {
+                let repository =
+                    repository.context("invalid value for repository in config.toml")?;
+                rv.push(RepositoryRef::from_toml_table(repository)?);
+            }
+        }
+        Ok(rv)
+    }
+
     /// Enable autosync.
     pub fn autosync(&self) -> bool {
         self.doc
@@ -269,6 +738,26 @@ impl Config {
             .unwrap_or(false)
     }
 
+    /// The release channel `rye self update` updates from.
+    pub fn update_channel(&self) -> Option<String> {
+        self.doc
+            .get("self")
+            .and_then(|x| x.get("update-channel"))
+            .and_then(|x| x.as_str())
+            .map(|x| x.to_string())
+    }
+
+    /// The URL template `rye self update` downloads binaries from.
+    ///
+    /// Supports the `{version}`, `{target}` and `{ext}` placeholders.
+    pub fn update_url(&self) -> Option<String> {
+        self.doc
+            .get("self")
+            .and_then(|x| x.get("update-url"))
+            .and_then(|x| x.as_str())
+            .map(|x| x.to_string())
+    }
+
     /// Fetches python installations with build info if possible.
     ///
     /// This used to be the default behavior in Rye prior to 0.31.
@@ -279,6 +768,194 @@ impl Config {
             .and_then(|x| x.as_bool())
             .unwrap_or(false)
     }
+
+    /// The explicitly configured build flavor of fetched CPython toolchains.
+    ///
+    /// If not configured stripped builds are used where available.
+    pub fn toolchain_build_flavor(&self) -> Option<BuildFlavor> {
+        self.doc
+            .get("toolchain")
+            .and_then(|x| x.get("build-flavor"))
+            .and_then(|x| x.as_str())
+            .and_then(|x| x.parse().ok())
+    }
+}
+
+/// Finds the config layers of projects, outermost first.
+///
+/// Within a folder `rye.toml` wins over `[tool.rye.config]`.
+fn find_project_configs(here: &Path) -> Result<Vec<(ConfigOrigin, Table)>, Error> {
+    let mut rv = Vec::new();
+    for dir in here.ancestors() {
+        let rye_toml = dir.join("rye.toml");
+        if rye_toml.is_file() {
+            let contents =
+                fs::read_to_string(&rye_toml).path_context(&rye_toml, "failed to read config")?;
+            let doc = contents
+                .parse::<DocumentMut>()
+                .path_context(&rye_toml, "failed to parse config")?;
+            for problem in check_config(&contents, &rye_toml, &[]) {
+                warn!("{}", problem);
+            }
+            rv.push((ConfigOrigin::File(rye_toml), doc.as_table().clone()));
+        }
+
+        // broken pyproject.toml files are reported once the project is loaded
+        let pyproject_toml = dir.join("pyproject.toml");
+        let contents = match fs::read_to_string(&pyproject_toml) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+        let doc = match contents.parse::<DocumentMut>() {
+            Ok(doc) => doc,
+            Err(_) => continue,
+        };
+        let table = match doc
+            .get("tool")
+            .and_then(|x| x.get("rye"))
+            .and_then(|x| x.get("config"))
+        {
+            Some(Item::Table(table)) => table.clone(),
+            Some(Item::Value(Value::InlineTable(table))) => table.clone().into_table(),
+            _ => continue,
+        };
+        for problem in check_config(&contents, &pyproject_toml, &["tool", "rye", "config"]) {
+            warn!("{}", problem);
+        }
+        rv.push((ConfigOrigin::PyProject(pyproject_toml), table));
+    }
+    rv.reverse();
+    Ok(rv)
+}
+
+/// Checks a config against [`CONFIG_KEYS`].
+///
+/// Returns a message for every unknown key and invalid value, starting with
+/// its location (`path:line:column`).  `table` is the path to the config
+/// table within the document.
+fn check_config(contents: &str, path: &Path, table: &[&str]) -> Vec<String> {
+    let mut rv = Vec::new();
+    let doc = match ImDocument::parse(contents) {
+        Ok(doc) => doc,
+        Err(_) => return rv,
+    };
+    let mut ptr: &dyn TableLike = doc.as_table();
+    for piece in table {
+        match ptr.get(piece).and_then(|x| x.as_table_like()) {
+            Some(table) => ptr = table,
+            None => return rv,
+        }
+    }
+    check_table(ptr, "", contents, path, &mut rv);
+    rv
+}
+
+fn check_table(
+    table: &dyn TableLike,
+    prefix: &str,
+    contents: &str,
+    path: &Path,
+    rv: &mut Vec<String>,
+) {
+    for (key, item) in table.iter() {
+        let full_key = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        let key_span = table.get_key_value(key).and_then(|x| x.0.span());
+        if let Some(config_key) = find_config_key(&full_key) {
+            if !config_key.ty.matches(item) {
+                rv.push(format!(
+                    "{}: invalid value for '{}', expected {}",
+                    format_location(path, contents, item.span().or(key_span)),
+                    full_key,
+                    config_key.ty
+                ));
+            }
+            continue;
+        }
+
+        let section_prefix = format!("{}.", full_key);
+        if prefix.is_empty()
+            && CONFIG_KEYS
+                .iter()
+                .any(|x| x.key.starts_with(&section_prefix))
+        {
+            match item.as_table_like() {
+                Some(section) => check_table(section, &full_key, contents, path, rv),
+                None => rv.push(format!(
+                    "{}: invalid value for '{}', expected a table",
+                    format_location(path, contents, item.span().or(key_span)),
+                    full_key
+                )),
+            }
+            continue;
+        }
+
+        let normalized = full_key.to_ascii_lowercase().replace('_', "-");
+        let suggestion = CONFIG_KEYS
+            .iter()
+            .find(|x| x.key == normalized)
+            .map(|x| format!(" (did you mean '{}'?)", x.key))
+            .unwrap_or_default();
+        rv.push(format!(
+            "{}: unknown config key '{}'{}",
+            format_location(path, contents, key_span),
+            full_key,
+            suggestion
+        ));
+    }
+}
+
+/// Formats a location in a file as `path:line:column`.
+fn format_location(path: &Path, contents: &str, span: Option<Range<usize>>) -> String {
+    match span {
+        Some(span) if span.start <= contents.len() => {
+            let before = &contents[..span.start];
+            let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+            format!(
+                "{}:{}:{}",
+                path.display(),
+                before.matches('\n').count() + 1,
+                before[line_start..].chars().count() + 1
+            )
+        }
+        _ => path.display().to_string(),
+    }
+}
+
+/// Merges a layer of config into a table, recording the origin of every value.
+fn merge_table(
+    base: &mut dyn TableLike,
+    overlay: &dyn TableLike,
+    prefix: &str,
+    origin: &ConfigOrigin,
+    origins: &mut BTreeMap<String, ConfigOrigin>,
+) {
+    for (key, item) in overlay.iter() {
+        let path = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        if let Some(overlay_table) = item.as_table_like() {
+            if base.get(key).and_then(|x| x.as_table_like()).is_none() {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                base.insert(key, Item::Table(table));
+                origins.remove(&path);
+            }
+            if let Some(base_table) = base.get_mut(key).and_then(|x| x.as_table_like_mut()) {
+                merge_table(base_table, overlay_table, &path, origin, origins);
+            }
+        } else {
+            base.insert(key, item.clone());
+            let nested = format!("{}.", path);
+            origins.retain(|k, _| !k.starts_with(&nested));
+            origins.insert(path, origin.clone());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -358,6 +1035,29 @@ author = "John Doe <john@example.com>""#,
         assert_eq!(email, Some("john@example.com".to_string()));
     }
 
+    #[test]
+    fn test_default_dependency_operator() {
+        for (value, operator) in [
+            ("==", Some(Operator::Equal)),
+            ("~=", Some(Operator::TildeEqual)),
+            (">=", Some(Operator::GreaterThanEqual)),
+            ("unbounded", None),
+            ("bogus", Some(Operator::GreaterThanEqual)),
+        ] {
+            let (cfg_path, _temp_dir) =
+                setup_config(&format!("[default]\ndependency-operator = '{}'", value));
+            let cfg = Config::from_path(&cfg_path).expect("Failed to load config");
+            assert_eq!(cfg.default_dependency_operator(), operator, "{}", value);
+        }
+
+        let (cfg_path, _temp_dir) = setup_config("");
+        let cfg = Config::from_path(&cfg_path).expect("Failed to load config");
+        assert_eq!(
+            cfg.default_dependency_operator(),
+            Some(Operator::GreaterThanEqual)
+        );
+    }
+
     #[test]
     fn test_global_python() {
         let (cfg_path, _temp_dir) = setup_config("[behavior]\nglobal-python = true");
@@ -372,6 +1072,17 @@ author = "John Doe <john@example.com>""#,
         assert!(cfg.force_rye_managed());
     }
 
+    #[test]
+    fn test_extra_shims() {
+        let (cfg_path, _temp_dir) = setup_config("[shims]\nextra = [\"pip\", \"python3.11\"]");
+        let cfg = Config::from_path(&cfg_path).expect("Failed to load config");
+        assert_eq!(cfg.extra_shims().unwrap(), vec!["pip", "python3.11"]);
+
+        let (cfg_path, _temp_dir) = setup_config("[shims]\nextra = [\"../pip\"]");
+        let cfg = Config::from_path(&cfg_path).expect("Failed to load config");
+        assert!(cfg.extra_shims().is_err());
+    }
+
     #[test]
     fn test_venv_mark_sync_ignore() {
         let (cfg_path, _temp_dir) = setup_config("[behavior]\nvenv-mark-sync-ignore = false");
@@ -379,6 +1090,22 @@ author = "John Doe <john@example.com>""#,
         assert!(!cfg.venv_mark_sync_ignore());
     }
 
+    #[test]
+    fn test_update_url() {
+        let (cfg_path, _temp_dir) = setup_config("");
+        let cfg = Config::from_path(&cfg_path).expect("Failed to load config");
+        assert_eq!(cfg.update_url(), None);
+
+        let (cfg_path, _temp_dir) = setup_config(
+            "[self]\nupdate-url = 'https://mirror.example.com/rye/{version}/rye-{target}{ext}'",
+        );
+        let cfg = Config::from_path(&cfg_path).expect("Failed to load config");
+        assert_eq!(
+            cfg.update_url(),
+            Some("https://mirror.example.com/rye/{version}/rye-{target}{ext}".to_string())
+        );
+    }
+
     #[test]
     fn test_http_proxy_url() {
         let (cfg_path, _temp_dir) = setup_config("[proxy]\nhttp = 'http://proxy.example.com'");
@@ -416,4 +1143,91 @@ author = "John Doe <john@example.com>""#,
         // Assuming cfg!(windows) is false in this test environment
         assert!(cfg.use_uv());
     }
+
+    #[test]
+    fn test_project_layer() {
+        let (cfg_path, temp_dir) = setup_config("[behavior]\nuse-uv = true\nautosync = false\n");
+        let mut cfg = Config::from_path(&cfg_path).expect("Failed to load config");
+        let pyproject_path = temp_dir.path().join("pyproject.toml");
+        fs::write(
+            &pyproject_path,
+            "[project]\nname = 'foo'\n\n[tool.rye.config]\nbehavior = { autosync = true }\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("rye.toml"),
+            "[default]\nlicense = 'MIT'\n",
+        )
+        .unwrap();
+        for (origin, table) in find_project_configs(temp_dir.path()).unwrap() {
+            cfg.apply_layer(&table, origin);
+        }
+
+        assert!(cfg.use_uv());
+        assert!(cfg.autosync());
+        assert_eq!(cfg.default_license(), Some("MIT".to_string()));
+        assert_eq!(
+            cfg.origin("behavior.use-uv"),
+            Some(&ConfigOrigin::File(cfg_path.clone()))
+        );
+        assert_eq!(
+            cfg.origin("behavior.autosync"),
+            Some(&ConfigOrigin::PyProject(pyproject_path))
+        );
+
+        // the global config file is not affected
+        assert!(cfg.file_doc.get("default").is_none());
+    }
+
+    #[test]
+    fn test_check_config() {
+        let contents = "[behavior]\nuse_uv = true\nglobal-python = 'yes'\n\n[default]\nlicense = 'MIT'\ndependency_operator = '=='\n\n[unknown]\n";
+        let path = Path::new("config.toml");
+        assert_eq!(
+            check_config(contents, path, &[]),
+            vec![
+                "config.toml:2:1: unknown config key 'behavior.use_uv' (did you mean 'behavior.use-uv'?)",
+                "config.toml:3:17: invalid value for 'behavior.global-python', expected a boolean",
+                "config.toml:9:2: unknown config key 'unknown'",
+            ]
+        );
+
+        let contents =
+            "[project]\nname = 'foo'\n\n[tool.rye.config]\ndefault = { build-system = 'poetry' }\n";
+        assert_eq!(
+            check_config(contents, Path::new("pyproject.toml"), &["tool", "rye", "config"]),
+            vec!["pyproject.toml:5:28: invalid value for 'default.build-system', expected one of 'hatchling', 'setuptools', 'flit', 'pdm', 'maturin'"]
+        );
+    }
+
+    #[test]
+    fn test_config_json_schema() {
+        let schema = config_json_schema();
+        assert_eq!(
+            schema["properties"]["behavior"]["properties"]["use-uv"]["type"],
+            "boolean"
+        );
+        assert_eq!(schema["properties"]["sources"]["type"], "array");
+    }
+
+    #[test]
+    fn test_env_layer() {
+        let (cfg_path, _temp_dir) = setup_config("[behavior]\nuse-uv = true\n");
+        let mut cfg = Config::from_path(&cfg_path).expect("Failed to load config");
+        cfg.apply_env(
+            [
+                ("RYE_BEHAVIOR_USE_UV".to_string(), "0".to_string()),
+                ("RYE_BEHAVIOR_GLOBAL_PYTHON".to_string(), "yes".to_string()),
+                ("RYE_HOME".to_string(), "/tmp".to_string()),
+            ]
+            .into_iter(),
+        );
+        assert!(!cfg.use_uv());
+        assert!(cfg.global_python());
+        assert_eq!(
+            cfg.origin("behavior.use-uv"),
+            Some(&ConfigOrigin::Env("RYE_BEHAVIOR_USE_UV".to_string()))
+        );
+        assert_eq!(cfg.origins().len(), 2);
+    }
 }
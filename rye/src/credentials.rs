@@ -0,0 +1,374 @@
+use anyhow::{Context, Error};
+use toml_edit::{value, Item, Table};
+
+use crate::platform::{get_credentials, get_credentials_filepath, write_credentials};
+
+/// The service name under which all secrets are stored in the keyring.
+const KEYRING_SERVICE: &str = "rye";
+
+/// Returns the keyring account for a source.
+fn keyring_account(source: &str) -> String {
+    format!("source:{}", source)
+}
+
+/// Stores the password for a source in the keyring.
+///
+/// The username (if provided) is not considered secret and is recorded
+/// in the `[sources]` table of the credentials file together with a marker
+/// that indicates that a secret exists in the keyring.
+pub fn set_source_credentials(
+    source: &str,
+    username: Option<&str>,
+    password: &str,
+) -> Result<(), Error> {
+    keyring::set(&keyring_account(source), password).with_context(|| {
+        format!(
+            "unable to store password for source '{}' in keyring",
+            source
+        )
+    })?;
+
+    let mut credentials = get_credentials()?;
+    let sources = credentials
+        .entry("sources")
+        .or_insert_with(|| {
+            let mut tbl = Table::new();
+            tbl.set_implicit(true);
+            Item::Table(tbl)
+        })
+        .as_table_mut()
+        .context("invalid sources table in credentials")?;
+    let mut entry = Table::new();
+    if let Some(username) = username {
+        entry["username"] = value(username);
+    }
+    entry["keyring"] = value(true);
+    sources.insert(source, Item::Table(entry));
+    write_credentials(&credentials)
+}
+
+/// Removes the stored credentials for a source.
+///
+/// Returns `true` if there were credentials to remove.
+pub fn remove_source_credentials(source: &str) -> Result<bool, Error> {
+    let mut credentials = get_credentials()?;
+    let removed = credentials
+        .get_mut("sources")
+        .and_then(|x| x.as_table_like_mut())
+        .and_then(|x| x.remove(source))
+        .is_some();
+    if removed {
+        write_credentials(&credentials)?;
+    }
+
+    let deleted = keyring::delete(&keyring_account(source))
+        .with_context(|| format!("unable to remove password for source '{}'", source))?;
+    Ok(removed || deleted)
+}
+
+/// Returns the names of all sources with credentials in the keyring.
+pub fn list_source_credentials() -> Result<Vec<String>, Error> {
+    let credentials = get_credentials()?;
+    Ok(credentials
+        .get("sources")
+        .and_then(|x| x.as_table_like())
+        .map(|x| x.iter().map(|(key, _)| key.to_string()).collect())
+        .unwrap_or_default())
+}
+
+/// Looks up the credentials for a source.
+///
+/// The keyring is only consulted for sources that were registered with
+/// `rye config credentials set`.  Returns the username (if one was stored)
+/// and the password.
+pub fn get_source_credentials(source: &str) -> Result<Option<(Option<String>, String)>, Error> {
+    if !get_credentials_filepath()?.is_file() {
+        return Ok(None);
+    }
+    let credentials = get_credentials()?;
+    let entry = match credentials
+        .get("sources")
+        .and_then(|x| x.get(source))
+        .and_then(|x| x.as_table_like())
+    {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    if !entry
+        .get("keyring")
+        .and_then(|x| x.as_bool())
+        .unwrap_or(false)
+    {
+        return Ok(None);
+    }
+    let username = entry
+        .get("username")
+        .and_then(|x| x.as_str())
+        .map(|x| x.to_string());
+    let password = keyring::get(&keyring_account(source)).with_context(|| {
+        format!(
+            "unable to read password for source '{}' from keyring",
+            source
+        )
+    })?;
+    Ok(password.map(|password| (username, password)))
+}
+
+/// Access to the keyring of the operating system.
+///
+/// On macOS this is the login keychain (through `security`), on Linux the
+/// secret service (through `secret-tool`) and on Windows the credential
+/// manager.  Secrets are always passed through stdin or memory, never as
+/// command line arguments.
+#[cfg(target_os = "macos")]
+mod keyring {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    use anyhow::{bail, Context, Error};
+
+    use super::KEYRING_SERVICE;
+
+    /// The exit code of `security` if an item does not exist.
+    const ERR_SEC_ITEM_NOT_FOUND: i32 = 44;
+
+    /// Quotes an argument for the interactive mode of `security`.
+    fn quote(value: &str) -> String {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+
+    pub fn get(account: &str) -> Result<Option<String>, Error> {
+        let output = Command::new("security")
+            .args([
+                "find-generic-password",
+                "-s",
+                KEYRING_SERVICE,
+                "-a",
+                account,
+                "-w",
+            ])
+            .stdin(Stdio::null())
+            .output()
+            .context("unable to run security")?;
+        if output.status.code() == Some(ERR_SEC_ITEM_NOT_FOUND) {
+            return Ok(None);
+        } else if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let mut password = String::from_utf8(output.stdout).context("password is not utf-8")?;
+        if password.ends_with('\n') {
+            password.pop();
+        }
+        Ok(Some(password))
+    }
+
+    pub fn set(account: &str, password: &str) -> Result<(), Error> {
+        // `security -i` reads the command from stdin so that the password
+        // does not show up in the process list.
+        let mut child = Command::new("security")
+            .arg("-i")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("unable to run security")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(
+                stdin,
+                "add-generic-password -U -s {} -a {} -w {}",
+                quote(KEYRING_SERVICE),
+                quote(account),
+                quote(password)
+            )?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() || !output.stderr.is_empty() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(())
+    }
+
+    pub fn delete(account: &str) -> Result<bool, Error> {
+        let output = Command::new("security")
+            .args([
+                "delete-generic-password",
+                "-s",
+                KEYRING_SERVICE,
+                "-a",
+                account,
+            ])
+            .stdin(Stdio::null())
+            .output()
+            .context("unable to run security")?;
+        if output.status.code() == Some(ERR_SEC_ITEM_NOT_FOUND) {
+            Ok(false)
+        } else if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        } else {
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod keyring {
+    use std::io::Write;
+    use std::process::{Command, Output, Stdio};
+
+    use anyhow::{bail, Context, Error};
+
+    use super::KEYRING_SERVICE;
+
+    fn secret_tool(args: &[&str], stdin: Option<&str>) -> Result<Output, Error> {
+        let mut child = Command::new("secret-tool")
+            .args(args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(
+                "unable to run secret-tool. Install libsecret (eg: the libsecret-tools \
+                 package) to store passwords in the keyring",
+            )?;
+        if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(data.as_bytes())?;
+        }
+        Ok(child.wait_with_output()?)
+    }
+
+    pub fn get(account: &str) -> Result<Option<String>, Error> {
+        let output = secret_tool(
+            &["lookup", "service", KEYRING_SERVICE, "username", account],
+            None,
+        )?;
+        if !output.status.success() {
+            // a missing secret fails without a message
+            if output.stderr.is_empty() {
+                return Ok(None);
+            }
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(Some(
+            String::from_utf8(output.stdout).context("password is not utf-8")?,
+        ))
+    }
+
+    pub fn set(account: &str, password: &str) -> Result<(), Error> {
+        let label = format!("{} ({})", account, KEYRING_SERVICE);
+        let output = secret_tool(
+            &[
+                "store",
+                "--label",
+                &label,
+                "service",
+                KEYRING_SERVICE,
+                "username",
+                account,
+            ],
+            Some(password),
+        )?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(())
+    }
+
+    pub fn delete(account: &str) -> Result<bool, Error> {
+        if get(account)?.is_none() {
+            return Ok(false);
+        }
+        let output = secret_tool(
+            &["clear", "service", KEYRING_SERVICE, "username", account],
+            None,
+        )?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(windows)]
+mod keyring {
+    use std::ffi::OsStr;
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::{mem, ptr, slice};
+
+    use anyhow::{Context, Error};
+    use winapi::shared::winerror::ERROR_NOT_FOUND;
+    use winapi::um::errhandlingapi::GetLastError;
+    use winapi::um::wincred::{
+        CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_ENTERPRISE,
+        CRED_TYPE_GENERIC, PCREDENTIALW,
+    };
+
+    use super::KEYRING_SERVICE;
+
+    fn to_wide(value: &str) -> Vec<u16> {
+        OsStr::new(value).encode_wide().chain(Some(0)).collect()
+    }
+
+    /// The target name of the credential in the credential manager, which is
+    /// `{account}.rye`.
+    fn target_name(account: &str) -> Vec<u16> {
+        to_wide(&format!("{}.{}", account, KEYRING_SERVICE))
+    }
+
+    pub fn get(account: &str) -> Result<Option<String>, Error> {
+        let target = target_name(account);
+        let mut cred: PCREDENTIALW = ptr::null_mut();
+        if unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut cred) } == 0 {
+            return match unsafe { GetLastError() } {
+                ERROR_NOT_FOUND => Ok(None),
+                code => Err(io::Error::from_raw_os_error(code as i32).into()),
+            };
+        }
+        let blob = unsafe {
+            slice::from_raw_parts((*cred).CredentialBlob, (*cred).CredentialBlobSize as usize)
+        };
+        let wide = blob
+            .chunks_exact(2)
+            .map(|x| u16::from_le_bytes([x[0], x[1]]))
+            .collect::<Vec<_>>();
+        unsafe { CredFree(cred as *mut _) };
+        String::from_utf16(&wide)
+            .map(Some)
+            .context("password is not valid unicode")
+    }
+
+    pub fn set(account: &str, password: &str) -> Result<(), Error> {
+        let mut target = target_name(account);
+        let mut username = to_wide(account);
+        let mut blob = password
+            .encode_utf16()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<u8>>();
+        let mut cred: CREDENTIALW = unsafe { mem::zeroed() };
+        cred.Type = CRED_TYPE_GENERIC;
+        cred.TargetName = target.as_mut_ptr();
+        cred.UserName = username.as_mut_ptr();
+        cred.CredentialBlobSize = blob.len() as u32;
+        cred.CredentialBlob = blob.as_mut_ptr();
+        cred.Persist = CRED_PERSIST_ENTERPRISE;
+        if unsafe { CredWriteW(&mut cred, 0) } == 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    pub fn delete(account: &str) -> Result<bool, Error> {
+        let target = target_name(account);
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            return match unsafe { GetLastError() } {
+                ERROR_NOT_FOUND => Ok(false),
+                code => Err(io::Error::from_raw_os_error(code as i32).into()),
+            };
+        }
+        Ok(true)
+    }
+}
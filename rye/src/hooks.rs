@@ -0,0 +1,114 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Error};
+use console::style;
+
+use crate::pyproject::PyProject;
+use crate::utils::CommandOutput;
+
+/// Rye commands a hook can refer to unless a script of the same name exists.
+const BUILTIN_HOOK_COMMANDS: &[&str] = &["fmt", "lint", "typecheck"];
+
+/// What the `pre-commit` hook runs unless the project configures it.
+const DEFAULT_PRE_COMMIT: &[&str] = &["fmt", "lint"];
+
+/// Returns the scripts and commands that run for a hook.
+pub fn hook_commands(pyproject: &PyProject, hook: &str) -> Vec<String> {
+    let scripts = pyproject.hook_scripts(hook);
+    if scripts.is_empty() && hook == "pre-commit" {
+        DEFAULT_PRE_COMMIT.iter().map(|x| x.to_string()).collect()
+    } else {
+        scripts
+    }
+}
+
+/// Runs the scripts registered for a lifecycle hook.
+///
+/// Hooks are declared in `[tool.rye.hooks]` and refer to scripts from
+/// `[tool.rye.scripts]` which are invoked via `rye run` so that they run
+/// inside the project's virtualenv.
+pub fn run_hook(pyproject: &PyProject, hook: &str, output: CommandOutput) -> Result<(), Error> {
+    run_hook_on_files(pyproject, hook, &[], output)
+}
+
+/// Runs a hook for a set of files, as done by `rye run --hook`.
+///
+/// The built-in `fmt` (which only checks), `lint` and `typecheck` commands
+/// use the tools of rye's own virtualenv.  `fmt` and `lint` are limited to the
+/// Python files among `files`, scripts get all of them as arguments.
+pub fn run_hook_on_files(
+    pyproject: &PyProject,
+    hook: &str,
+    files: &[OsString],
+    output: CommandOutput,
+) -> Result<(), Error> {
+    let scripts = hook_commands(pyproject, hook);
+    if scripts.is_empty() {
+        return Ok(());
+    }
+    // files are relative to where the hook was started, commands run in the project
+    let cwd = env::current_dir()?;
+    let files = files
+        .iter()
+        .map(|x| cwd.join(x).into_os_string())
+        .collect::<Vec<_>>();
+    let python_files = files
+        .iter()
+        .filter(|x| {
+            Path::new(x)
+                .extension()
+                .map_or(false, |ext| ext == "py" || ext == "pyi")
+        })
+        .collect::<Vec<_>>();
+
+    let rye = env::current_exe()?;
+    for script in scripts {
+        let is_builtin = BUILTIN_HOOK_COMMANDS.contains(&script.as_str())
+            && pyproject.get_script_cmd(&script).is_none();
+        let limit_to_files = is_builtin && script != "typecheck";
+        if limit_to_files && !files.is_empty() && python_files.is_empty() {
+            continue;
+        }
+
+        echo!(
+            if output,
+            "Running {} hook: {}",
+            hook,
+            style(&script).cyan()
+        );
+        let mut cmd = Command::new(&rye);
+        if is_builtin {
+            cmd.arg(&script);
+            if script == "fmt" {
+                cmd.arg("--check");
+            }
+            cmd.arg("--pyproject").arg(&*pyproject.toml_path());
+            if limit_to_files && !python_files.is_empty() {
+                // like ruff's own hooks, respect excludes for explicitly passed files
+                cmd.args(&python_files).arg("--").arg("--force-exclude");
+            }
+        } else {
+            cmd.arg("run")
+                .arg("--pyproject")
+                .arg(&*pyproject.toml_path())
+                .arg(&script)
+                .args(&files);
+        }
+        cmd.current_dir(pyproject.root_path());
+        if output == CommandOutput::Quiet {
+            cmd.stdout(Stdio::null());
+            cmd.stderr(Stdio::null());
+        }
+        let status = cmd
+            .status()
+            .with_context(|| format!("unable to run {} hook '{}'", hook, script))?;
+        if !status.success() {
+            bail!("{} hook '{}' failed with {}", hook, script, status);
+        }
+    }
+
+    Ok(())
+}
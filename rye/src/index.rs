@@ -0,0 +1,364 @@
+//! Queries against package indexes.
+//!
+//! All requests go through [`fetch_cached`] which keeps the responses in the
+//! index cache and revalidates them with their `ETag` or `Last-Modified`
+//! header, so unchanged pages are not downloaded again.  Project pages are
+//! requested in the JSON flavor of the simple API (PEP 691).
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Error};
+use pep440_rs::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::config::Config;
+use crate::platform::get_index_cache_dir;
+use crate::pyproject::ExpandedSources;
+use crate::utils::{run_parallel, CommandOutput};
+
+/// The content type of project pages of the JSON simple API.
+const SIMPLE_JSON: &str = "application/vnd.pypi.simple.v1+json";
+
+/// The number of index requests that are made at once.
+pub const CONCURRENT_REQUESTS: usize = 8;
+
+/// A response as stored in the index cache.
+#[derive(Serialize, Deserialize, Debug)]
+struct CachedResponse {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    content_type: String,
+    text: String,
+}
+
+/// A project page of the JSON simple API.
+#[derive(Deserialize, Debug, Default)]
+pub struct ProjectPage {
+    pub files: Vec<ProjectFile>,
+}
+
+/// A file on a project page.
+#[derive(Deserialize, Debug)]
+pub struct ProjectFile {
+    pub filename: String,
+    pub url: String,
+    #[serde(default, rename = "requires-python")]
+    pub requires_python: Option<String>,
+    #[serde(default, rename = "upload-time")]
+    pub upload_time: Option<String>,
+    /// The digests of the file by hash algorithm.
+    #[serde(default)]
+    pub hashes: HashMap<String, String>,
+    #[serde(default)]
+    yanked: serde_json::Value,
+    #[serde(default, rename = "core-metadata")]
+    core_metadata: serde_json::Value,
+    #[serde(default, rename = "dist-info-metadata")]
+    dist_info_metadata: serde_json::Value,
+}
+
+impl ProjectFile {
+    /// Returns the yank status: `None` if the file is not yanked, otherwise
+    /// the reason (which can be empty).
+    pub fn yanked(&self) -> Option<&str> {
+        match self.yanked {
+            serde_json::Value::String(ref reason) => Some(reason),
+            serde_json::Value::Bool(true) => Some(""),
+            _ => None,
+        }
+    }
+
+    /// Returns the version of the file.
+    pub fn version(&self) -> Option<Version> {
+        let filename = &self.filename;
+        let version = match filename.strip_suffix(".whl") {
+            Some(stem) => stem.split('-').nth(1)?,
+            None => {
+                let stem = [".tar.gz", ".zip", ".tar.bz2", ".tgz"]
+                    .iter()
+                    .find_map(|ext| filename.strip_suffix(ext))?;
+                stem.rsplit_once('-')?.1
+            }
+        };
+        version.parse().ok()
+    }
+
+    /// Returns the URL of the metadata of a wheel if the index serves it (PEP 658).
+    pub fn metadata_url(&self, page_url: &Url) -> Option<Url> {
+        let served = |x: &serde_json::Value| {
+            !matches!(x, serde_json::Value::Null) && *x != serde_json::Value::Bool(false)
+        };
+        if !self.filename.ends_with(".whl")
+            || !(served(&self.core_metadata) || served(&self.dist_info_metadata))
+        {
+            return None;
+        }
+        let mut url = page_url.join(&self.url).ok()?;
+        url.set_fragment(None);
+        url.set_path(&format!("{}.metadata", url.path()));
+        Some(url)
+    }
+}
+
+/// Returns the index URLs of the sources, falling back to PyPI.
+pub fn index_urls(sources: &ExpandedSources) -> Vec<Url> {
+    let mut rv = sources
+        .index_urls
+        .iter()
+        .map(|(url, _)| url.clone())
+        .collect::<Vec<_>>();
+    if rv.is_empty() {
+        rv.push(Url::parse("https://pypi.org/simple/").unwrap());
+    }
+    rv
+}
+
+/// Returns the URL of the project page of a package on an index.
+pub fn project_url(index: &Url, name: &str) -> Result<Url, Error> {
+    let mut index = index.clone();
+    if !index.path().ends_with('/') {
+        index.set_path(&format!("{}/", index.path()));
+    }
+    Ok(index.join(&format!("{}/", name))?)
+}
+
+/// Fetches the project page of a package from the first index that knows it.
+///
+/// The package name must be normalized.  Returns the page together with its
+/// URL, or `None` if no index knows the package.
+pub fn fetch_project_page(
+    indexes: &[Url],
+    sources: &ExpandedSources,
+    name: &str,
+) -> Result<Option<(Url, ProjectPage)>, Error> {
+    for index in indexes {
+        let url = project_url(index, name)?;
+        let response = match fetch_cached(&url, Some(SIMPLE_JSON), sources)? {
+            Some(response) => response,
+            None => continue,
+        };
+        if !response.content_type.contains("json") {
+            bail!(
+                "index at {} does not support the JSON simple API (PEP 691)",
+                url.host_str().unwrap_or_default()
+            );
+        }
+        let page = serde_json::from_str(&response.text).context("invalid project page on index")?;
+        return Ok(Some((url, page)));
+    }
+    Ok(None)
+}
+
+/// Fetches the project pages of many packages at once.
+///
+/// Packages that cannot be looked up are left out, the failure is reported
+/// in verbose mode.
+pub fn fetch_project_pages<'a, I>(
+    names: I,
+    sources: &ExpandedSources,
+    output: CommandOutput,
+) -> HashMap<String, ProjectPage>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let indexes = index_urls(sources);
+    let mut rv = HashMap::new();
+    for (name, result) in run_parallel(CONCURRENT_REQUESTS, names.into_iter().collect(), |name| {
+        (name, fetch_project_page(&indexes, sources, name))
+    }) {
+        match result {
+            Ok(Some((_, page))) => {
+                rv.insert(name.clone(), page);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                echo!(if verbose output, "Unable to look up {} on the index: {}", name, err);
+            }
+        }
+    }
+    rv
+}
+
+/// Fetches the core metadata of a wheel and returns the extras it provides.
+pub fn fetch_extras(url: &Url, sources: &ExpandedSources) -> Result<Vec<String>, Error> {
+    let response = match fetch_cached(url, None, sources)? {
+        Some(response) => response,
+        None => bail!("metadata not found"),
+    };
+    Ok(response
+        .text
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.strip_prefix("Provides-Extra:"))
+        .map(|x| x.trim().to_string())
+        .collect())
+}
+
+/// Returns the path of the cache entry for a request.
+fn cache_path(url: &Url, accept: Option<&str>) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}\n{}", url, accept.unwrap_or("None")).as_bytes());
+    get_index_cache_dir().join(format!("{}.json", hex::encode(hasher.finalize())))
+}
+
+/// Downloads a file listed on a project page.
+///
+/// Files are immutable, so unlike pages they are not kept in the index cache.
+pub fn fetch_file(url: &Url, sources: &ExpandedSources) -> Result<Vec<u8>, Error> {
+    let mut handle = new_handle(url, sources)?;
+    let mut body = Vec::new();
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|data| {
+            body.extend_from_slice(data);
+            Ok(data.len())
+        })?;
+        transfer.perform()?;
+    }
+    match handle.response_code()? {
+        200 => Ok(body),
+        status => bail!("index responded with status {}", status),
+    }
+}
+
+/// Creates a request for a URL of an index with the proxy and TLS settings
+/// of the sources.
+fn new_handle(url: &Url, sources: &ExpandedSources) -> Result<curl::easy::Easy, Error> {
+    let mut handle = curl::easy::Easy::new();
+    handle.url(url.as_str())?;
+    handle.follow_location(true)?;
+    let config = Config::current();
+    let proxy = if url.scheme() == "https" {
+        config.https_proxy_url()
+    } else {
+        config.http_proxy_url()
+    };
+    if let Some(proxy) = proxy {
+        handle.proxy(&proxy)?;
+    }
+    if url
+        .host_str()
+        .map_or(false, |host| sources.trusted_hosts.contains(host))
+    {
+        handle.ssl_verify_peer(false)?;
+        handle.ssl_verify_host(false)?;
+    }
+    #[cfg(windows)]
+    {
+        handle.ssl_options(curl::easy::SslOpt::new().no_revoke(true))?;
+    }
+    Ok(handle)
+}
+
+/// Performs a GET request through the index cache.
+///
+/// Cached responses are revalidated and reused if the index reports them as
+/// unchanged.  Returns `None` on 404.
+fn fetch_cached(
+    url: &Url,
+    accept: Option<&str>,
+    sources: &ExpandedSources,
+) -> Result<Option<CachedResponse>, Error> {
+    let path = cache_path(url, accept);
+    let cached = fs::read(&path)
+        .ok()
+        .and_then(|x| serde_json::from_slice::<CachedResponse>(&x).ok());
+
+    let mut handle = new_handle(url, sources)?;
+    let mut headers = curl::easy::List::new();
+    if let Some(accept) = accept {
+        headers.append(&format!("Accept: {}", accept))?;
+    }
+    if let Some(ref cached) = cached {
+        if let Some(ref etag) = cached.etag {
+            headers.append(&format!("If-None-Match: {}", etag))?;
+        }
+        if let Some(ref last_modified) = cached.last_modified {
+            headers.append(&format!("If-Modified-Since: {}", last_modified))?;
+        }
+    }
+    handle.http_headers(headers)?;
+
+    let mut body = Vec::new();
+    let mut etag = None;
+    let mut last_modified = None;
+    {
+        let mut transfer = handle.transfer();
+        transfer.header_function(|header| {
+            let header = String::from_utf8_lossy(header);
+            if let Some((name, value)) = header.split_once(':') {
+                let value = Some(value.trim().to_string());
+                if name.eq_ignore_ascii_case("etag") {
+                    etag = value;
+                } else if name.eq_ignore_ascii_case("last-modified") {
+                    last_modified = value;
+                }
+            }
+            true
+        })?;
+        transfer.write_function(|data| {
+            body.extend_from_slice(data);
+            Ok(data.len())
+        })?;
+        transfer.perform()?;
+    }
+
+    match handle.response_code()? {
+        304 if cached.is_some() => {
+            // touch the entry so that cache pruning keeps it
+            if let Ok(file) = fs::File::options().append(true).open(&path) {
+                file.set_modified(std::time::SystemTime::now()).ok();
+            }
+            return Ok(cached);
+        }
+        200 => {}
+        404 => return Ok(None),
+        status => bail!("index responded with status {}", status),
+    }
+
+    let response = CachedResponse {
+        etag,
+        last_modified,
+        content_type: handle.content_type()?.unwrap_or("").to_string(),
+        text: String::from_utf8_lossy(&body).into_owned(),
+    };
+    if response.etag.is_some() || response.last_modified.is_some() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).ok();
+        }
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        if fs::write(&tmp, serde_json::to_vec(&response)?).is_ok() {
+            fs::rename(&tmp, &path).ok();
+        }
+    }
+    Ok(Some(response))
+}
+
+#[test]
+fn test_project_file() {
+    let page: ProjectPage = serde_json::from_str(
+        r#"{"files": [
+            {"filename": "foo-1.0.tar.gz", "url": "/files/foo-1.0.tar.gz", "hashes": {}},
+            {"filename": "foo_bar-1.1-py3-none-any.whl", "url": "../../files/foo_bar-1.1-py3-none-any.whl#sha256=abc",
+             "hashes": {}, "yanked": true, "core-metadata": {"sha256": "def"}},
+            {"filename": "foo-bar-2.0rc1.tar.gz", "url": "/files/foo-bar-2.0rc1.tar.gz", "hashes": {},
+             "yanked": "broken build", "requires-python": ">=3.8"}
+        ]}"#,
+    )
+    .unwrap();
+    let page_url = Url::parse("https://example.com/simple/foo/").unwrap();
+    assert_eq!(page.files[0].yanked(), None);
+    assert_eq!(page.files[1].yanked(), Some(""));
+    assert_eq!(page.files[2].yanked(), Some("broken build"));
+    assert_eq!(page.files[0].version(), Some("1.0".parse().unwrap()));
+    assert_eq!(page.files[1].version(), Some("1.1".parse().unwrap()));
+    assert_eq!(page.files[2].version(), Some("2.0rc1".parse().unwrap()));
+    assert_eq!(page.files[0].metadata_url(&page_url), None);
+    assert_eq!(
+        page.files[1].metadata_url(&page_url).unwrap().as_str(),
+        "https://example.com/files/foo_bar-1.1-py3-none-any.whl.metadata"
+    );
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error};
+use once_cell::sync::Lazy;
+use pep440_rs::{Version, VersionSpecifiers};
+use pep508_rs::Requirement;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use toml_edit::DocumentMut;
+
+use crate::bootstrap::{fetch, FetchOptions};
+use crate::config::Config;
+use crate::platform::{get_app_dir, get_toolchain_python_bin};
+use crate::pyproject::{lower_bound_python_version, ExpandedSources};
+use crate::sources::py::PythonVersionRequest;
+use crate::utils::{CommandOutput, IoPathContext};
+use crate::uv::{UvBuilder, UvInstallOptions};
+
+/// Marker that is written once all dependencies of a script were installed.
+const SCRIPT_ENV_MARKER: &str = "rye-script-env-complete";
+
+/// Matches an inline metadata block as specified by PEP 723.
+static BLOCK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^# /// (?P<type>[a-zA-Z0-9-]+)$\n(?P<content>(?:^#(?:| .*)$\n)+)^# ///$")
+        .unwrap()
+});
+
+/// The `script` metadata embedded into a single-file script.
+#[derive(Debug, Default)]
+pub struct ScriptMetadata {
+    pub requires_python: Option<VersionSpecifiers>,
+    pub dependencies: Vec<Requirement>,
+}
+
+impl ScriptMetadata {
+    /// Reads the inline metadata of a script.
+    ///
+    /// Scripts without a `script` block have no requirements.
+    pub fn from_path(path: &Path) -> Result<ScriptMetadata, Error> {
+        let source = fs::read_to_string(path).path_context(path, "unable to read script")?;
+        ScriptMetadata::parse(&source).path_context(path, "invalid inline script metadata")
+    }
+
+    /// Parses the inline metadata from the source of a script.
+    pub fn parse(source: &str) -> Result<ScriptMetadata, Error> {
+        let source = source.replace("\r\n", "\n");
+        let mut blocks = BLOCK_RE
+            .captures_iter(&source)
+            .filter(|m| &m["type"] == "script");
+        let block = match blocks.next() {
+            Some(block) => block,
+            None => return Ok(ScriptMetadata::default()),
+        };
+        if blocks.next().is_some() {
+            bail!("multiple script blocks");
+        }
+
+        let content = block["content"]
+            .lines()
+            .map(|line| {
+                line.strip_prefix("# ")
+                    .unwrap_or_else(|| line.trim_start_matches('#'))
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let doc = content.parse::<DocumentMut>()?;
+
+        let requires_python = match doc.get("requires-python") {
+            Some(value) => Some(
+                value
+                    .as_str()
+                    .context("requires-python must be a string")?
+                    .parse::<VersionSpecifiers>()
+                    .context("invalid requires-python")?,
+            ),
+            None => None,
+        };
+        let dependencies = match doc.get("dependencies") {
+            Some(value) => value
+                .as_array()
+                .context("dependencies must be an array")?
+                .iter()
+                .map(|x| {
+                    let req = x.as_str().context("dependencies must be strings")?;
+                    Requirement::from_str(req)
+                        .with_context(|| format!("invalid dependency '{}'", req))
+                })
+                .collect::<Result<Vec<_>, Error>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(ScriptMetadata {
+            requires_python,
+            dependencies,
+        })
+    }
+
+    /// Picks the Python version the script should run with.
+    ///
+    /// This is the default toolchain unless `requires-python` excludes it, in
+    /// which case the lowest version it allows is used.
+    fn python_version_request(&self) -> Result<PythonVersionRequest, Error> {
+        let default = Config::current()
+            .default_toolchain()
+            .unwrap_or(PythonVersionRequest {
+                name: None,
+                arch: None,
+                os: None,
+                environment: None,
+                major: 3,
+                minor: None,
+                patch: None,
+                suffix: None,
+            });
+        let specifiers = match self.requires_python {
+            Some(ref specifiers) => specifiers,
+            None => return Ok(default),
+        };
+        if default.minor.is_some() && specifiers.contains(&Version::from(default.clone())) {
+            Ok(default)
+        } else {
+            lower_bound_python_version(specifiers).with_context(|| {
+                format!(
+                    "no Python version satisfies requires-python '{}'",
+                    specifiers
+                )
+            })
+        }
+    }
+}
+
+/// Provisions the environment for a single-file script.
+///
+/// The environment lives in the rye cache and is keyed by the Python version
+/// and the dependencies, so running the script again reuses it.  Returns the
+/// path to the virtualenv.
+pub fn ensure_script_env(
+    metadata: &ScriptMetadata,
+    extra_requirements: &[Requirement],
+    output: CommandOutput,
+) -> Result<PathBuf, Error> {
+    let py_ver = fetch(
+        &metadata.python_version_request()?,
+        FetchOptions::with_output(output),
+    )?;
+    let mut requirements = metadata
+        .dependencies
+        .iter()
+        .chain(extra_requirements)
+        .cloned()
+        .collect::<Vec<_>>();
+    requirements.sort_by_cached_key(|x| x.to_string());
+
+    let mut hasher = Sha256::new();
+    hasher.update(py_ver.to_string().as_bytes());
+    for req in &requirements {
+        hasher.update(b"\0");
+        hasher.update(req.to_string().as_bytes());
+    }
+    let venv = get_app_dir()
+        .join("script-envs")
+        .join(&hex::encode(hasher.finalize())[..16]);
+
+    if venv.join(SCRIPT_ENV_MARKER).is_file() {
+        return Ok(venv);
+    }
+    if venv.is_dir() {
+        fs::remove_dir_all(&venv).path_context(&venv, "unable to remove incomplete environment")?;
+    }
+
+    let config = Config::current();
+    let uv = UvBuilder::new()
+        .with_output(output.quieter())
+        .with_sources(ExpandedSources::from_sources(&config.sources()?)?)
+        .ensure_exists()?
+        .venv(&venv, &get_toolchain_python_bin(&py_ver)?, &py_ver, None)?
+        .with_output(output.quieter());
+    let mut requirements = requirements.into_iter();
+    if let Some(first) = requirements.next() {
+        echo!(if output, "Installing script dependencies");
+        uv.install(
+            &first,
+            UvInstallOptions {
+                importlib_workaround: py_ver.major == 3 && py_ver.minor == 7,
+                extras: requirements.collect(),
+            },
+        )?;
+    }
+    fs::write(venv.join(SCRIPT_ENV_MARKER), b"")
+        .path_context(&venv, "unable to write script environment marker")?;
+    Ok(venv)
+}
+
+#[test]
+fn test_parse_script_metadata() {
+    let metadata = ScriptMetadata::parse(
+        "#!/usr/bin/env python\n\
+         # /// script\n\
+         # requires-python = \">=3.11\"\n\
+         # dependencies = [\n\
+         #   \"requests<3\",\n\
+         #   \"rich\",\n\
+         # ]\n\
+         # ///\n\
+         \n\
+         import requests\n",
+    )
+    .unwrap();
+    assert_eq!(metadata.requires_python.unwrap().to_string(), ">=3.11");
+    assert_eq!(
+        metadata
+            .dependencies
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>(),
+        vec!["requests<3", "rich"]
+    );
+
+    let metadata = ScriptMetadata::parse("print('hello')\n").unwrap();
+    assert!(metadata.requires_python.is_none());
+    assert!(metadata.dependencies.is_empty());
+
+    let block = "# /// script\n# dependencies = []\n# ///\n";
+    assert!(ScriptMetadata::parse(&format!("{}\nimport os\n\n{}", block, block)).is_err());
+}
@@ -1,6 +1,7 @@
 use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{env, fs};
 
 use anyhow::{bail, Context, Error};
@@ -9,18 +10,21 @@ use once_cell::sync::Lazy;
 use pep508_rs::{Requirement, VersionOrUrl};
 use regex::Regex;
 use same_file::is_same_file;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use toml_edit::{DocumentMut, Item};
 use url::Url;
 
 use crate::bootstrap::{ensure_self_venv, fetch, FetchOptions};
 use crate::config::Config;
 use crate::consts::VENV_BIN;
-use crate::platform::get_app_dir;
+use crate::platform::{get_app_dir, get_toolchain_python_bin};
 use crate::pyproject::{normalize_package_name, read_venv_marker, ExpandedSources};
 use crate::sources::py::PythonVersionRequest;
 use crate::sync::{create_virtualenv, VenvMarker};
 use crate::utils::{
-    get_short_executable_name, get_venv_python_bin, is_executable, symlink_file, CommandOutput,
-    IoPathContext,
+    dir_size, get_short_executable_name, get_venv_python_bin, is_executable, symlink_file,
+    CommandOutput, IoPathContext,
 };
 use crate::uv::{UvBuilder, UvInstallOptions};
 
@@ -67,6 +71,12 @@ while to_resolve:
 
 print(json.dumps(result))
 "#;
+/// The file in a tool's virtualenv that records how it was installed.
+const TOOL_METADATA: &str = "rye-tool.json";
+
+/// Marker that is written once a cached tool environment is complete.
+const TOOL_CACHE_MARKER: &str = "rye-tool-complete";
+
 static SUCCESSFULLY_DOWNLOADED_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new("(?m)^Successfully downloaded (.*?)$").unwrap());
 
@@ -76,24 +86,36 @@ pub struct ToolInfo {
     pub scripts: Vec<String>,
     pub venv_marker: Option<VenvMarker>,
     pub valid: bool,
+    pub path: PathBuf,
+    pub metadata: Option<ToolMetadata>,
+    /// When the tool was installed in seconds since the unix epoch.
+    pub installed_at: Option<u64>,
 }
 
 impl ToolInfo {
-    pub fn new(
-        version: String,
-        scripts: Vec<String>,
-        venv_marker: Option<VenvMarker>,
-        valid: bool,
-    ) -> Self {
-        Self {
-            version,
-            scripts,
-            venv_marker,
-            valid,
-        }
+    /// Calculates the size of the tool's virtualenv on disk in bytes.
+    pub fn disk_size(&self) -> u64 {
+        dir_size(&self.path)
     }
 }
 
+/// Records how a tool was installed so that it can be upgraded later.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ToolMetadata {
+    /// The requirement as it was requested.
+    pub requirement: String,
+    #[serde(default)]
+    pub extra_requirements: Vec<String>,
+    #[serde(default)]
+    pub include_deps: Vec<String>,
+    /// The Python version that was requested for the tool.
+    #[serde(default)]
+    pub python: Option<String>,
+    /// When the tool was installed in seconds since the unix epoch.
+    #[serde(default)]
+    pub installed_at: Option<u64>,
+}
+
 const TOOL_VERSION_SCRIPT: &str = r#"
 import sys
 from importlib.metadata import version
@@ -131,6 +153,7 @@ pub fn install(
     uninstall_helper(&target_venv_path, &shim_dir)?;
 
     // make sure we have a compatible python version
+    let requested_python = py_ver.to_string();
     let py_ver = fetch(py_ver, FetchOptions::with_output(output))?;
 
     create_virtualenv(
@@ -194,6 +217,20 @@ pub fn install(
             bail!("tool installation failed");
         }
     };
+    let metadata = ToolMetadata {
+        requirement: requirement.to_string(),
+        extra_requirements: extra_requirements.iter().map(|x| x.to_string()).collect(),
+        include_deps: include_deps.clone(),
+        python: Some(requested_python),
+        installed_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|x| x.as_secs()),
+    };
+    let metadata_path = target_venv_path.join(TOOL_METADATA);
+    fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?)
+        .path_context(&metadata_path, "unable to write tool metadata")?;
+
     let out = Command::new(py)
         .arg("-c")
         .arg(FIND_SCRIPT_SCRIPT)
@@ -373,27 +410,464 @@ pub fn list_installed_tools() -> Result<HashMap<String, ToolInfo>, Error> {
             }
         }
 
-        let output = Command::new(target_venv_bin_path.join("python"))
-            .arg("-c")
-            .arg(TOOL_VERSION_SCRIPT)
-            .arg(tool_name.clone())
-            .stdout(Stdio::piped())
-            .output();
+        let output = get_tool_version(&folder.path(), &tool_name);
         let valid = output.is_ok();
-        let tool_version = match output {
-            Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_string(),
-            Err(_) => String::new(),
-        };
+        let tool_version = output.unwrap_or_default();
+
+        // tools installed by older versions of rye did not record when they
+        // were installed, fall back to the age of the virtualenv.
+        let metadata = read_tool_metadata(&folder.path());
+        let installed_at = metadata.as_ref().and_then(|x| x.installed_at).or_else(|| {
+            fs::metadata(folder.path().join("pyvenv.cfg"))
+                .and_then(|x| x.modified())
+                .ok()
+                .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+                .map(|x| x.as_secs())
+        });
 
         rv.insert(
             tool_name,
-            ToolInfo::new(tool_version, scripts, venv_marker, valid),
+            ToolInfo {
+                version: tool_version,
+                scripts,
+                venv_marker,
+                valid,
+                path: folder.path(),
+                metadata,
+                installed_at,
+            },
         );
     }
 
     Ok(rv)
 }
 
+/// Installs a tool into a cached environment without linking any shims.
+///
+/// The environment is keyed by the Python version and the requirements, so
+/// running the same tool again reuses it.  With `refresh` the environment is
+/// recreated, which picks up new releases of unpinned tools.
+pub fn ensure_cached_tool(
+    requirement: &Requirement,
+    extra_requirements: &[Requirement],
+    py_ver: &PythonVersionRequest,
+    refresh: bool,
+    output: CommandOutput,
+) -> Result<PathBuf, Error> {
+    let py_ver = fetch(py_ver, FetchOptions::with_output(output))?;
+    let mut extras = extra_requirements
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    extras.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(py_ver.to_string().as_bytes());
+    for req in std::iter::once(requirement.to_string()).chain(extras) {
+        hasher.update(b"\0");
+        hasher.update(req.as_bytes());
+    }
+    let venv = get_app_dir()
+        .join("tool-cache")
+        .join(&hex::encode(hasher.finalize())[..16]);
+
+    if !refresh && venv.join(TOOL_CACHE_MARKER).is_file() {
+        return Ok(venv);
+    }
+    if venv.is_dir() {
+        fs::remove_dir_all(&venv).path_context(&venv, "unable to remove cached tool")?;
+    }
+
+    echo!(if output, "Installing {}", style(requirement).cyan());
+    let config = Config::current();
+    UvBuilder::new()
+        .with_output(output.quieter())
+        .with_sources(ExpandedSources::from_sources(&config.sources()?)?)
+        .ensure_exists()?
+        .venv(&venv, &get_toolchain_python_bin(&py_ver)?, &py_ver, None)?
+        .with_output(output.quieter())
+        .install(
+            requirement,
+            UvInstallOptions {
+                importlib_workaround: py_ver.major == 3 && py_ver.minor == 7,
+                extras: extra_requirements.to_vec(),
+            },
+        )?;
+    fs::write(venv.join(TOOL_CACHE_MARKER), b"")
+        .path_context(&venv, "unable to write tool cache marker")?;
+    Ok(venv)
+}
+
+/// Upgrades a tool within the originally requested version range.
+pub fn upgrade(package: &str, output: CommandOutput) -> Result<(), Error> {
+    let (old_version, new_version) = reinstall_tool(package, None, output)?;
+    if output != CommandOutput::Quiet {
+        match (old_version, new_version) {
+            (Some(old), Some(new)) if old == new => {
+                echo!("{} is up to date ({})", style(package).cyan(), old);
+            }
+            (old, new) => {
+                echo!(
+                    "Upgraded {}: {} -> {}",
+                    style(package).cyan(),
+                    old.as_deref().unwrap_or("unknown"),
+                    style(new.as_deref().unwrap_or("unknown")).green()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reinstalls a tool, optionally with a different Python version.
+///
+/// This is used to move tools off a toolchain before it is removed.
+pub fn reinstall(
+    package: &str,
+    python: Option<&PythonVersionRequest>,
+    output: CommandOutput,
+) -> Result<(), Error> {
+    let (_, new_version) = reinstall_tool(package, python, output)?;
+    if output != CommandOutput::Quiet {
+        let target_venv_path = get_app_dir()
+            .join("tools")
+            .join(normalize_package_name(package));
+        echo!(
+            "Reinstalled {} {} ({})",
+            style(package).cyan(),
+            new_version.as_deref().unwrap_or("unknown"),
+            read_venv_marker(&target_venv_path)
+                .map_or("unknown".to_string(), |x| x.python.to_string())
+        );
+    }
+    Ok(())
+}
+
+/// Reinstalls a tool with the recorded requirement, extra requirements,
+/// included dependencies and Python version.
+///
+/// Returns the versions of the tool before and after.
+fn reinstall_tool(
+    package: &str,
+    python: Option<&PythonVersionRequest>,
+    output: CommandOutput,
+) -> Result<(Option<String>, Option<String>), Error> {
+    let target_venv_path = get_app_dir()
+        .join("tools")
+        .join(normalize_package_name(package));
+    if !target_venv_path.is_dir() {
+        bail!("{} is not installed", package);
+    }
+
+    // tools installed by older versions of rye did not record their metadata
+    let metadata = read_tool_metadata(&target_venv_path).unwrap_or_else(|| ToolMetadata {
+        requirement: package.to_string(),
+        ..ToolMetadata::default()
+    });
+    let requirement = metadata
+        .requirement
+        .parse::<Requirement>()
+        .with_context(|| format!("invalid recorded requirement '{}'", metadata.requirement))?;
+    let extra_requirements = metadata
+        .extra_requirements
+        .iter()
+        .map(|x| x.parse::<Requirement>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let py_ver = match (python, metadata.python) {
+        (Some(python), _) => python.clone(),
+        (None, Some(python)) => python.parse()?,
+        (None, None) => match read_venv_marker(&target_venv_path) {
+            Some(marker) => PythonVersionRequest::from(marker.python),
+            None => Config::current().default_toolchain()?,
+        },
+    };
+
+    let old_version = get_tool_version(&target_venv_path, &requirement.name).ok();
+    install(
+        requirement.clone(),
+        &py_ver,
+        true,
+        &metadata.include_deps,
+        &extra_requirements,
+        output.quieter(),
+    )?;
+    let new_version = get_tool_version(&target_venv_path, &requirement.name).ok();
+    Ok((old_version, new_version))
+}
+
+/// Installs additional packages into the virtualenv of an installed tool.
+///
+/// The packages are recorded as extra requirements of the tool so that they
+/// are kept when the tool is upgraded.
+pub fn inject(
+    package: &str,
+    requirements: &[Requirement],
+    output: CommandOutput,
+) -> Result<(), Error> {
+    let target_venv_path = get_app_dir()
+        .join("tools")
+        .join(normalize_package_name(package));
+    if !target_venv_path.is_dir() {
+        bail!("{} is not installed", package);
+    }
+    let py_ver = read_venv_marker(&target_venv_path)
+        .with_context(|| format!("virtualenv of {} is broken, reinstall it", package))?
+        .python;
+
+    let (first, rest) = requirements.split_first().context("no packages given")?;
+    UvBuilder::new()
+        .with_output(output.quieter())
+        .with_sources(ExpandedSources::from_sources(
+            &Config::current().sources()?,
+        )?)
+        .ensure_exists()?
+        .venv(
+            &target_venv_path,
+            &get_venv_python_bin(&target_venv_path),
+            &py_ver,
+            None,
+        )?
+        .with_output(output)
+        .install(
+            first,
+            UvInstallOptions {
+                importlib_workaround: false,
+                extras: rest.to_vec(),
+            },
+        )?;
+
+    let mut metadata = read_tool_metadata(&target_venv_path).unwrap_or_else(|| ToolMetadata {
+        requirement: package.to_string(),
+        ..ToolMetadata::default()
+    });
+    for requirement in requirements {
+        let name = normalize_package_name(&requirement.name);
+        metadata.extra_requirements.retain(|x| {
+            x.parse::<Requirement>()
+                .map_or(true, |x| normalize_package_name(&x.name) != name)
+        });
+        metadata.extra_requirements.push(requirement.to_string());
+    }
+    let metadata_path = target_venv_path.join(TOOL_METADATA);
+    fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?)
+        .path_context(&metadata_path, "unable to write tool metadata")?;
+
+    if output != CommandOutput::Quiet {
+        for requirement in requirements {
+            echo!(
+                "Injected {} into {}",
+                style(requirement).cyan(),
+                style(package).cyan()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A tool declared in the `tools.toml` manifest.
+#[derive(Debug)]
+pub struct ToolSpec {
+    pub requirement: Requirement,
+    pub extra_requirements: Vec<Requirement>,
+    pub include_deps: Vec<String>,
+    pub python: Option<PythonVersionRequest>,
+}
+
+impl ToolSpec {
+    /// Checks if a tool was installed exactly as declared.
+    fn matches(&self, metadata: &ToolMetadata) -> bool {
+        let mut extras = self
+            .extra_requirements
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        let mut installed_extras = metadata.extra_requirements.clone();
+        extras.sort();
+        installed_extras.sort();
+        let mut include_deps = self
+            .include_deps
+            .iter()
+            .map(|x| normalize_package_name(x))
+            .collect::<Vec<_>>();
+        let mut installed_include_deps = metadata.include_deps.clone();
+        include_deps.sort();
+        installed_include_deps.sort();
+
+        metadata.requirement == self.requirement.to_string()
+            && extras == installed_extras
+            && include_deps == installed_include_deps
+            && self
+                .python
+                .as_ref()
+                .map_or(true, |x| metadata.python.as_deref() == Some(&x.to_string()))
+    }
+}
+
+/// Returns the path of the global tools manifest.
+pub fn get_tools_manifest_path() -> PathBuf {
+    get_app_dir().join("tools.toml")
+}
+
+/// Reads the tools declared in a tools manifest.
+///
+/// Each key in the `tools` table is a package name, the value is either a
+/// version (`"==1.0"`, `"1.0"` or `"*"`) or a table with the keys `version`,
+/// `extras`, `with`, `include-deps` and `python`.
+pub fn read_tools_manifest(path: &Path) -> Result<Vec<ToolSpec>, Error> {
+    let contents = fs::read_to_string(path).path_context(path, "unable to read tools manifest")?;
+    let doc = contents
+        .parse::<DocumentMut>()
+        .path_context(path, "unable to parse tools manifest")?;
+    let tools = match doc.get("tools").and_then(|x| x.as_table_like()) {
+        Some(tools) => tools,
+        None => return Ok(Vec::new()),
+    };
+    tools
+        .iter()
+        .map(|(name, value)| {
+            parse_tool_spec(name, value)
+                .with_context(|| format!("invalid tool '{}' in {}", name, path.display()))
+        })
+        .collect()
+}
+
+fn parse_tool_spec(name: &str, value: &Item) -> Result<ToolSpec, Error> {
+    let (version, table) = if let Some(version) = value.as_str() {
+        (Some(version), None)
+    } else if let Some(table) = value.as_table_like() {
+        (table.get("version").and_then(|x| x.as_str()), Some(table))
+    } else {
+        bail!("expected a version or a table");
+    };
+    let get_list = |key: &str| -> Vec<String> {
+        table
+            .and_then(|x| x.get(key))
+            .and_then(|x| x.as_array())
+            .map(|x| {
+                x.iter()
+                    .filter_map(|x| x.as_str())
+                    .map(|x| x.to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut requirement = name.to_string();
+    let extras = get_list("extras");
+    if !extras.is_empty() {
+        requirement.push('[');
+        requirement.push_str(&extras.join(","));
+        requirement.push(']');
+    }
+    match version.map(|x| x.trim()) {
+        None | Some("") | Some("*") => {}
+        Some(version) if version.starts_with(|c: char| c.is_ascii_digit()) => {
+            requirement.push_str("==");
+            requirement.push_str(version);
+        }
+        Some(version) => requirement.push_str(version),
+    }
+
+    Ok(ToolSpec {
+        requirement: requirement
+            .parse()
+            .with_context(|| format!("invalid requirement '{}'", requirement))?,
+        extra_requirements: get_list("with")
+            .iter()
+            .map(|x| x.parse::<Requirement>())
+            .collect::<Result<_, _>>()?,
+        include_deps: get_list("include-deps"),
+        python: table
+            .and_then(|x| x.get("python"))
+            .and_then(|x| x.as_str())
+            .map(|x| x.parse::<PythonVersionRequest>())
+            .transpose()?,
+    })
+}
+
+/// Installs and reinstalls tools so that they match the given declarations.
+///
+/// Tools are only reinstalled if their recorded requirement, extra
+/// requirements, included dependencies or Python version differ.  With
+/// `prune` tools that are not declared are uninstalled.
+pub fn sync_tools(specs: &[ToolSpec], prune: bool, output: CommandOutput) -> Result<(), Error> {
+    let installed = list_installed_tools()?;
+    let mut failed = Vec::new();
+
+    for spec in specs {
+        let name = normalize_package_name(&spec.requirement.name);
+        let current = installed.get(&name);
+        if current.map_or(false, |x| {
+            x.valid && x.metadata.as_ref().map_or(false, |x| spec.matches(x))
+        }) {
+            echo!(if verbose output, "{} is up to date", style(&name).cyan());
+            continue;
+        }
+
+        echo!(
+            if output,
+            "{} {}",
+            if current.is_some() {
+                "Reinstalling"
+            } else {
+                "Installing"
+            },
+            style(&spec.requirement).cyan()
+        );
+        let py_ver = match spec.python {
+            Some(ref python) => python.clone(),
+            None => Config::current().default_toolchain()?,
+        };
+        if let Err(err) = install(
+            spec.requirement.clone(),
+            &py_ver,
+            true,
+            &spec.include_deps,
+            &spec.extra_requirements,
+            output.quieter(),
+        ) {
+            error!("failed to install {}: {:#}", name, err);
+            failed.push(name);
+        }
+    }
+
+    if prune {
+        let mut unlisted = installed
+            .keys()
+            .filter(|name| {
+                !specs
+                    .iter()
+                    .any(|x| &&normalize_package_name(&x.requirement.name) == name)
+            })
+            .collect::<Vec<_>>();
+        unlisted.sort();
+        for name in unlisted {
+            uninstall(name, output)?;
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!("unable to install {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+/// Reads the metadata recorded when a tool was installed.
+pub fn read_tool_metadata(target_venv_path: &Path) -> Option<ToolMetadata> {
+    let contents = fs::read(target_venv_path.join(TOOL_METADATA)).ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+fn get_tool_version(target_venv_path: &Path, tool_name: &str) -> std::io::Result<String> {
+    let output = Command::new(target_venv_path.join(VENV_BIN).join("python"))
+        .arg("-c")
+        .arg(TOOL_VERSION_SCRIPT)
+        .arg(tool_name)
+        .stdout(Stdio::piped())
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 fn uninstall_helper(target_venv_path: &Path, shim_dir: &Path) -> Result<(), Error> {
     let target_venv_bin_path = target_venv_path.join(VENV_BIN);
     if !target_venv_bin_path.is_dir() {
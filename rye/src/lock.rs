@@ -2,14 +2,15 @@ use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::io::{BufWriter, Write};
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::sync::Arc;
 use std::{env, fmt, fs};
 
 use anyhow::{anyhow, bail, Context, Error};
 use minijinja::render;
 use once_cell::sync::Lazy;
-use pep508_rs::Requirement;
+use pep440_rs::Operator;
+use pep508_rs::{Requirement, VersionOrUrl};
 use regex::Regex;
 use serde::Serialize;
 use tempfile::NamedTempFile;
@@ -18,15 +19,28 @@ use url::Url;
 use crate::config::Config;
 use crate::piptools::{get_pip_compile, get_pip_tools_version, PipToolsVersion};
 use crate::pyproject::{
-    normalize_package_name, DependencyKind, ExpandedSources, PyProject, Workspace,
+    normalize_package_name, DependencyKind, DependencyRef, ExpandedSources, PyProject, Workspace,
 };
 use crate::sources::py::PythonVersion;
+use crate::sync::read_locked_packages;
 use crate::utils::{set_proxy_variables, CommandOutput, IoPathContext};
 use crate::uv::{UvBuilder, UvPackageUpgrade};
+use crate::yanked::{find_yanked_releases, YankedRelease};
 
 static FILE_EDITABLE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^-e (file://.*?)\s*$").unwrap());
 static DEP_COMMENT_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^    # (?:(via)|(?:via (.*?))|(?:  (.*?)))$").unwrap());
+static GIT_REQ_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\S+) @ git\+(\S+?)(\s*;[^#]*?)?(?:\s+# (\S+))?\s*$").unwrap());
+static GIT_COMMIT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[0-9a-f]{40}$").unwrap());
+/// Matches the package names in the error output of the resolvers, which is
+/// where they follow the phrases below or are followed by a version constraint.
+static RESOLVER_PACKAGE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:versions? of|depends on) ([a-z0-9](?:[a-z0-9._-]*[a-z0-9])?)|\b([a-z0-9](?:[a-z0-9._-]*[a-z0-9])?)(?:\[[^\]]*\])?\s*(?:===|==|!=|>=|<=|~=|<|>)",
+    )
+    .unwrap()
+});
 static REQUIREMENTS_HEADER: &str = r#"# generated by rye
 # use `rye lock` or `rye sync` to update this lockfile
 #
@@ -35,10 +49,29 @@ static REQUIREMENTS_HEADER: &str = r#"# generated by rye
 #   features: {{ lock_options.features|tojson }}
 #   all-features: {{ lock_options.all_features|tojson }}
 #   with-sources: {{ lock_options.with_sources|tojson }}
+{%- if lock_options.generate_hashes %}
+#   generate-hashes: true
+{%- endif %}
+{%- if lock_options.python_platform %}
+#   python-platform: {{ lock_options.python_platform|tojson }}
+{%- endif %}
+{%- if lock_options.exclude_newer %}
+#   exclude-newer: {{ lock_options.exclude_newer|tojson }}
+{%- endif %}
+{%- if lock_options.exclude_yanked %}
+#   exclude-yanked: true
+{%- endif %}
+{%- if lock_options.overrides %}
+#   overrides: {{ lock_options.overrides|tojson }}
+{%- endif %}
 
 "#;
-static PARAM_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^#   (pre|features|all-features|with-sources):\s*(.*?)$").unwrap());
+static PARAM_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^#   (pre|features|all-features|with-sources|generate-hashes|python-platform|exclude-newer|exclude-yanked):\s*(.*?)$",
+    )
+    .unwrap()
+});
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LockMode {
@@ -74,6 +107,19 @@ pub struct LockOptions {
     pub all_features: bool,
     /// Should locking happen with sources?
     pub with_sources: bool,
+    /// Should the lockfile contain the hashes of all packages?
+    pub generate_hashes: bool,
+    /// Lock for this platform instead of the current one.
+    pub python_platform: Option<String>,
+    /// Only consider packages uploaded before this RFC 3339 timestamp.
+    pub exclude_newer: Option<String>,
+    /// Re-resolve packages that are locked to yanked releases.
+    pub exclude_yanked: bool,
+    /// Explain resolution failures with the full resolver output.
+    pub explain: bool,
+    /// The requirements from `tool.rye.override`.  These are recorded in the
+    /// lockfile but always taken from the project.
+    pub overrides: Vec<String>,
     /// Do not reuse (reset) prior lock options.
     pub reset: bool,
 }
@@ -114,6 +160,22 @@ impl LockOptions {
                     "with-sources" => {
                         rv.with_sources = rv.with_sources || serde_json::from_str(value)?
                     }
+                    "generate-hashes" => {
+                        rv.generate_hashes = rv.generate_hashes || serde_json::from_str(value)?
+                    }
+                    "python-platform" => {
+                        if rv.python_platform.is_none() {
+                            rv.python_platform = serde_json::from_str(value)?;
+                        }
+                    }
+                    "exclude-newer" => {
+                        if rv.exclude_newer.is_none() {
+                            rv.exclude_newer = serde_json::from_str(value)?;
+                        }
+                    }
+                    "exclude-yanked" => {
+                        rv.exclude_yanked = rv.exclude_yanked || serde_json::from_str(value)?
+                    }
                     _ => unreachable!(),
                 }
             }
@@ -128,6 +190,10 @@ impl LockOptions {
 }
 
 /// Creates lockfiles for all projects in the workspace.
+///
+/// If `modified_project` is provided it's used instead of the version of that
+/// project on disk.
+#[allow(clippy::too_many_arguments)]
 pub fn update_workspace_lockfile(
     py_ver: &PythonVersion,
     workspace: &Arc<Workspace>,
@@ -136,17 +202,24 @@ pub fn update_workspace_lockfile(
     output: CommandOutput,
     sources: &ExpandedSources,
     lock_options: &LockOptions,
+    modified_project: Option<&PyProject>,
 ) -> Result<(), Error> {
     echo!(if output, "Generating {} lockfile: {}", lock_mode, lockfile.display());
 
-    let lock_options = restore_lock_options(lockfile, lock_options)?;
+    let mut lock_options = restore_lock_options(lockfile, lock_options)?;
+    lock_options.to_mut().overrides = workspace.overrides()?;
     let features_by_project = collect_workspace_features(&lock_options);
     let mut req_file = NamedTempFile::new()?;
 
     let mut local_projects = HashMap::new();
     let mut projects = Vec::new();
     for pyproject_result in workspace.iter_projects() {
-        let pyproject = pyproject_result?;
+        let mut pyproject = pyproject_result?;
+        if let Some(modified_project) = modified_project {
+            if modified_project.toml_path() == pyproject.toml_path() {
+                pyproject = modified_project.clone();
+            }
+        }
         let rel_url = make_relative_url(&pyproject.root_path(), &workspace.path())?;
         let applicable_extras = format_project_extras(features_by_project.as_ref(), &pyproject)?;
 
@@ -188,12 +261,135 @@ pub fn update_workspace_lockfile(
         sources,
         &lock_options,
         &exclusions,
+        &workspace.allowed_yanked()?,
         true,
-    )?;
+    )
+    .map_err(|err| explain_resolution_error(err, &projects, lock_mode, lock_options.explain))?;
 
     Ok(())
 }
 
+/// The resolver could not generate a lockfile.
+///
+/// This holds what the resolver printed so that the failure can be explained
+/// in terms of the dependencies the user declared.
+#[derive(Debug)]
+pub struct ResolutionError {
+    pub output: String,
+}
+
+impl fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.output.trim_end())
+    }
+}
+
+impl std::error::Error for ResolutionError {}
+
+/// Maps a resolution failure back to the declared dependencies.
+///
+/// Lists the direct dependencies the resolver mentioned together with the
+/// project and group that declares them and suggests a constraint to relax.
+/// Other errors, and failures that mention no direct dependency, are passed
+/// through unchanged.
+fn explain_resolution_error(
+    err: Error,
+    projects: &[PyProject],
+    lock_mode: LockMode,
+    explain: bool,
+) -> Error {
+    let output = match err.downcast_ref::<ResolutionError>() {
+        Some(err) => &err.output,
+        None => return err,
+    };
+    let mut mentioned = HashMap::new();
+    for m in RESOLVER_PACKAGE_RE.captures_iter(output) {
+        if let Some(name) = m.get(1).or_else(|| m.get(2)) {
+            mentioned.insert(normalize_package_name(name.as_str()), name.start());
+        }
+    }
+
+    let mut involved = Vec::new();
+    for project in projects {
+        let mut kinds = vec![DependencyKind::Normal];
+        if lock_mode == LockMode::Dev {
+            kinds.push(DependencyKind::Dev);
+        }
+        kinds.extend(
+            project
+                .extras()
+                .into_iter()
+                .map(|x| DependencyKind::Optional(x.into())),
+        );
+        for kind in kinds {
+            for dep in project.iter_dependencies(kind.clone()) {
+                let req = match dep.expand(|_| Some("VARIABLE".into())) {
+                    Ok(req) => req,
+                    Err(_) => continue,
+                };
+                if let Some(&pos) = mentioned.get(&normalize_package_name(&req.name)) {
+                    involved.push((
+                        pos,
+                        has_upper_bound(&req),
+                        format!(
+                            "{} ({}, {})",
+                            dep,
+                            project.name().unwrap_or("<unnamed>"),
+                            kind
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+    if involved.is_empty() {
+        return err;
+    }
+
+    // the resolver explains the conflict step by step, the requirement it
+    // names last is the one that made it unsatisfiable.  Upper bounds are
+    // the usual suspects.
+    involved.sort();
+    involved.dedup();
+    let suggestion = involved
+        .iter()
+        .rev()
+        .find(|(_, upper_bound, _)| *upper_bound)
+        .unwrap_or(involved.last().unwrap());
+    let mut msg = format!(
+        "no solution found for the {} dependencies, the conflict involves:",
+        lock_mode
+    );
+    for (_, _, dep) in &involved {
+        msg.push_str(&format!("\n  {}", dep));
+    }
+    msg.push_str(&format!("\nconsider relaxing {}", suggestion.2));
+    if explain {
+        msg.push_str(&format!("\n\nresolver output:\n{}", output.trim_end()));
+    } else {
+        msg.push_str("\n(pass --explain to see the full resolver output)");
+    }
+    anyhow!(msg)
+}
+
+/// Checks if a requirement limits the versions of a package from above.
+fn has_upper_bound(req: &Requirement) -> bool {
+    match req.version_or_url {
+        Some(VersionOrUrl::VersionSpecifier(ref specifiers)) => specifiers.iter().any(|x| {
+            matches!(
+                x.operator(),
+                Operator::Equal
+                    | Operator::EqualStar
+                    | Operator::ExactEqual
+                    | Operator::TildeEqual
+                    | Operator::LessThan
+                    | Operator::LessThanEqual
+            )
+        }),
+        _ => false,
+    }
+}
+
 /// Tries to restore the lock options from the given lockfile.
 fn restore_lock_options<'o>(
     lockfile: &Path,
@@ -302,8 +498,41 @@ fn dump_dependencies(
                 continue;
             }
         }
-        writeln!(out, "{}", dep)?;
+        dump_dependency(pyproject, &dep, out)?;
+    }
+    Ok(())
+}
+
+/// Writes a single dependency.
+///
+/// Local path dependencies that are marked as editable are written as `-e`
+/// reference relative to the workspace so that the lockfile stays portable.
+fn dump_dependency(
+    pyproject: &PyProject,
+    dep: &DependencyRef,
+    out: &mut fs::File,
+) -> Result<(), Error> {
+    let editables = pyproject.editable_dependencies();
+    if !editables.is_empty() {
+        let workspace_root = pyproject.workspace_path();
+        if let Ok(req) = dep.expand(|name: &str| {
+            if name == "PROJECT_ROOT" {
+                Some(make_project_root_fragment(&workspace_root))
+            } else {
+                std::env::var(name).ok()
+            }
+        }) {
+            if let Some(VersionOrUrl::Url(ref url)) = req.version_or_url {
+                if editables.contains(&normalize_package_name(&req.name)) {
+                    if let Ok(path) = url.to_file_path() {
+                        writeln!(out, "-e {}", make_relative_url(&path, &workspace_root)?)?;
+                        return Ok(());
+                    }
+                }
+            }
+        }
     }
+    writeln!(out, "{}", dep)?;
     Ok(())
 }
 
@@ -319,7 +548,8 @@ pub fn update_single_project_lockfile(
 ) -> Result<(), Error> {
     echo!(if output, "Generating {} lockfile: {}", lock_mode, lockfile.display());
 
-    let lock_options = restore_lock_options(lockfile, lock_options)?;
+    let mut lock_options = restore_lock_options(lockfile, lock_options)?;
+    lock_options.to_mut().overrides = pyproject.overrides()?;
     let mut req_file = NamedTempFile::new()?;
 
     // virtual packages are themselves not installed
@@ -335,11 +565,11 @@ pub fn update_single_project_lockfile(
     }
 
     for dep in pyproject.iter_dependencies(DependencyKind::Normal) {
-        writeln!(req_file, "{}", dep)?;
+        dump_dependency(pyproject, &dep, req_file.as_file_mut())?;
     }
     if lock_mode == LockMode::Dev {
         for dep in pyproject.iter_dependencies(DependencyKind::Dev) {
-            writeln!(req_file, "{}", dep)?;
+            dump_dependency(pyproject, &dep, req_file.as_file_mut())?;
         }
     }
 
@@ -355,14 +585,102 @@ pub fn update_single_project_lockfile(
         sources,
         &lock_options,
         &exclusions,
+        &pyproject.allowed_yanked()?,
         false,
-    )?;
+    )
+    .map_err(|err| {
+        explain_resolution_error(
+            err,
+            std::slice::from_ref(pyproject),
+            lock_mode,
+            lock_options.explain,
+        )
+    })?;
 
     Ok(())
 }
 
+/// Generates the lockfile and checks it for yanked releases.
+///
+/// Yanked releases are only locked if they are explicitly required or the
+/// lockfile already had them.  With `exclude_yanked` the latter are resolved
+/// again, otherwise they are warned about.
 #[allow(clippy::too_many_arguments)]
 fn generate_lockfile(
+    output: CommandOutput,
+    py_ver: &PythonVersion,
+    workspace_path: &Path,
+    requirements_file_in: &Path,
+    lockfile: &Path,
+    sources: &ExpandedSources,
+    lock_options: &LockOptions,
+    exclusions: &HashSet<Requirement>,
+    allowed_yanked: &[Requirement],
+    no_deps: bool,
+) -> Result<(), Error> {
+    let resolve = |lock_options: &LockOptions| {
+        resolve_lockfile(
+            output,
+            py_ver,
+            workspace_path,
+            requirements_file_in,
+            lockfile,
+            sources,
+            lock_options,
+            exclusions,
+            no_deps,
+        )
+    };
+    let find_yanked = || -> Result<Vec<YankedRelease>, Error> {
+        let locked = read_locked_packages(workspace_path, lockfile)?;
+        Ok(find_yanked_releases(
+            locked
+                .iter()
+                .filter_map(|(name, version)| Some((name, version.as_ref()?))),
+            sources,
+            output,
+        )
+        .into_iter()
+        .filter(|x| !x.is_allowed(allowed_yanked))
+        .collect())
+    };
+
+    resolve(lock_options)?;
+    let mut yanked = find_yanked()?;
+    if !yanked.is_empty() && lock_options.exclude_yanked {
+        echo!(if output, "Resolving again to avoid yanked releases");
+        let mut lock_options = lock_options.clone();
+        lock_options
+            .update
+            .extend(yanked.iter().map(|x| x.name.clone()));
+        resolve(&lock_options)?;
+        yanked = find_yanked()?;
+        if let Some(release) = yanked.first() {
+            bail!(
+                "{}=={} is yanked but required by the dependencies, add it to \
+                 tool.rye.allow-yanked to lock it anyway",
+                release.name,
+                release.version
+            );
+        }
+    }
+    for release in yanked {
+        warn!(
+            "{} is locked to {} which was yanked{}",
+            release.name,
+            release.version,
+            match release.reason {
+                Some(ref reason) => format!(" ({})", reason),
+                None => "".to_string(),
+            }
+        );
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn resolve_lockfile(
     output: CommandOutput,
     py_ver: &PythonVersion,
     workspace_path: &Path,
@@ -387,6 +705,14 @@ fn generate_lockfile(
     };
 
     if use_uv {
+        let overrides = if lock_options.overrides.is_empty() {
+            None
+        } else {
+            let path = scratch.path().join("overrides.txt");
+            fs::write(&path, lock_options.overrides.join("\n"))
+                .path_context(&path, "unable to write overrides")?;
+            Some(path)
+        };
         let upgrade = {
             if lock_options.update_all {
                 UvPackageUpgrade::All
@@ -407,10 +733,25 @@ fn generate_lockfile(
                 requirements_file_in,
                 &requirements_file,
                 lock_options.pre,
-                env::var("__RYE_UV_EXCLUDE_NEWER").ok(),
+                lock_options.generate_hashes,
+                lock_options.python_platform.clone(),
+                lock_options
+                    .exclude_newer
+                    .clone()
+                    .or_else(|| env::var("__RYE_UV_EXCLUDE_NEWER").ok()),
                 upgrade,
+                overrides.as_deref(),
             )?;
     } else {
+        if lock_options.python_platform.is_some() {
+            bail!("locking for another platform requires uv");
+        }
+        if lock_options.exclude_newer.is_some() {
+            bail!("locking with exclude-newer requires uv");
+        }
+        if !lock_options.overrides.is_empty() {
+            bail!("locking with overrides (tool.rye.override) requires uv");
+        }
         let mut cmd = Command::new(get_pip_compile(py_ver, output)?);
         // legacy pip tools requires some extra parameters
         if get_pip_tools_version(py_ver) == PipToolsVersion::Legacy {
@@ -431,6 +772,9 @@ fn generate_lockfile(
         if lock_options.pre {
             cmd.arg("--pre");
         }
+        if lock_options.generate_hashes {
+            cmd.arg("--generate-hashes");
+        }
 
         cmd.arg(if output == CommandOutput::Verbose {
             "--verbose"
@@ -453,10 +797,24 @@ fn generate_lockfile(
         }
         sources.add_as_pip_args(&mut cmd);
         set_proxy_variables(&mut cmd);
-        let status = cmd.status().context("unable to run pip-compile")?;
-        if !status.success() {
-            bail!("failed to generate lockfile");
-        };
+        if output == CommandOutput::Verbose {
+            let status = cmd.status().context("unable to run pip-compile")?;
+            if !status.success() {
+                bail!("failed to generate lockfile");
+            }
+        } else {
+            let rv = cmd
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::piped())
+                .output()
+                .context("unable to run pip-compile")?;
+            if !rv.status.success() {
+                return Err(ResolutionError {
+                    output: String::from_utf8_lossy(&rv.stderr).into_owned(),
+                }
+                .into());
+            }
+        }
     };
 
     finalize_lockfile(
@@ -479,6 +837,11 @@ fn finalize_lockfile(
     sources: &ExpandedSources,
     lock_options: &LockOptions,
 ) -> Result<(), Error> {
+    let previous_git_pins = if out.is_file() {
+        read_git_pins(&fs::read_to_string(out).path_context(out, "unable to read lockfile")?)
+    } else {
+        HashMap::new()
+    };
     let mut rv =
         BufWriter::new(fs::File::create(out).path_context(out, "unable to finalize lockfile")?);
     lock_options.write_header(&mut rv)?;
@@ -489,10 +852,20 @@ fn finalize_lockfile(
         writeln!(rv)?;
     }
 
+    // the hashes of an excluded requirement are dropped along with it
+    let mut skip_hashes = false;
     for line in fs::read_to_string(generated)
         .path_context(generated, "unable to parse resolver output")?
         .lines()
     {
+        if line.trim_start().starts_with("--hash=") {
+            if !skip_hashes {
+                writeln!(rv, "{}", line)?;
+            }
+            continue;
+        }
+        skip_hashes = false;
+
         // we deal with this explicitly.
         if line.trim().is_empty()
             || line.starts_with("--index-url ")
@@ -509,19 +882,24 @@ fn finalize_lockfile(
                 writeln!(rv, "-e {}", rel_url)?;
                 continue;
             }
-        } else if let Ok(ref req) = line.trim().parse::<Requirement>() {
+        } else if let Ok(ref req) = strip_continuation(line).parse::<Requirement>() {
             // TODO: this does not evaluate markers
             if exclusions.iter().any(|x| {
                 normalize_package_name(&x.name) == normalize_package_name(&req.name)
                     && (x.version_or_url.is_none() || x.version_or_url == req.version_or_url)
             }) {
                 // skip exclusions
-                writeln!(rv, "# {} (excluded)", line)?;
+                writeln!(rv, "# {} (excluded)", strip_continuation(line))?;
+                skip_hashes = true;
+                continue;
+            }
+            if let Some(pinned) = pin_git_requirement(line, &previous_git_pins, lock_options) {
+                writeln!(rv, "{}", pinned)?;
                 continue;
             }
         } else if let Some(m) = DEP_COMMENT_RE.captures(line) {
             if let Some(dep) = m.get(2).or_else(|| m.get(3)).map(|x| x.as_str()) {
-                if !dep.starts_with("-r ") {
+                if !dep.starts_with("-r ") && !dep.starts_with("--override ") {
                     // we cannot tell today based on the output where this comes from.  This
                     // can show up because it's a root dependency, because it's a dev dependency
                     // or in some cases just because we declared it as a duplicate.
@@ -537,6 +915,224 @@ fn finalize_lockfile(
     Ok(())
 }
 
+/// Strips the line continuation that precedes the hashes of a requirement.
+pub fn strip_continuation(line: &str) -> &str {
+    line.trim().trim_end_matches('\\').trim_end()
+}
+
+/// A git requirement that was pinned to a commit in a lockfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitPin {
+    /// The repository URL (without `git+` prefix and revision).
+    pub repo: String,
+    /// The revision that was requested (branch, tag or `HEAD`).
+    pub requested: Option<String>,
+    /// The commit the revision was resolved to.
+    pub commit: String,
+}
+
+/// Splits the URL part of a `git+` requirement into repository, revision and fragment.
+fn split_git_url(url: &str) -> (&str, Option<&str>, &str) {
+    let (url, fragment) = match url.find('#') {
+        Some(idx) => url.split_at(idx),
+        None => (url, ""),
+    };
+    // the revision is separated with an @ in the path.  Make sure we do not
+    // pick up the @ of the userinfo.
+    let path_start = url
+        .find("://")
+        .and_then(|idx| url[idx + 3..].find('/').map(|x| x + idx + 3))
+        .unwrap_or(0);
+    match url[path_start..].rfind('@') {
+        Some(idx) => (
+            &url[..path_start + idx],
+            Some(&url[path_start + idx + 1..]),
+            fragment,
+        ),
+        None => (url, None, fragment),
+    }
+}
+
+/// Reads all git requirements from a lockfile keyed by normalized package name.
+pub fn read_git_pins(lockfile: &str) -> HashMap<String, GitPin> {
+    let mut rv = HashMap::new();
+    for line in lockfile.lines() {
+        if let Some(m) = GIT_REQ_RE.captures(line) {
+            let (repo, rev, _) = split_git_url(&m[2]);
+            if let Some(rev) = rev.filter(|x| GIT_COMMIT_RE.is_match(x)) {
+                rv.insert(
+                    normalize_package_name(&m[1]),
+                    GitPin {
+                        repo: repo.to_string(),
+                        requested: m.get(4).map(|x| x.as_str().to_string()),
+                        commit: rev.to_string(),
+                    },
+                );
+            }
+        }
+    }
+    rv
+}
+
+/// Resolves a git revision to a commit hash with `git ls-remote`.
+fn resolve_git_rev(repo: &str, rev: &str) -> Result<Option<String>, Error> {
+    let output = Command::new("git")
+        .arg("ls-remote")
+        .arg(repo)
+        .arg(rev)
+        .output()
+        .context("unable to run git ls-remote")?;
+    if !output.status.success() {
+        bail!(
+            "failed to resolve revision '{}' of {}: {}",
+            rev,
+            repo,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut rv = None;
+    for (commit, reference) in stdout.lines().filter_map(|x| x.split_once('\t')) {
+        // annotated tags are listed twice, the peeled one points to the commit.
+        if reference.ends_with("^{}") {
+            return Ok(Some(commit.to_string()));
+        } else if rv.is_none() {
+            rv = Some(commit.to_string());
+        }
+    }
+    Ok(rv)
+}
+
+/// Pins a git requirement line from the resolver output to a commit.
+///
+/// The requested revision is retained as comment so that later locks can keep
+/// the commit stable unless the package is explicitly updated.  Returns `None`
+/// if the line is not a git requirement or it is already pinned.
+fn pin_git_requirement(
+    line: &str,
+    previous: &HashMap<String, GitPin>,
+    lock_options: &LockOptions,
+) -> Option<String> {
+    let m = GIT_REQ_RE.captures(line)?;
+    let name = &m[1];
+    let (repo, rev, fragment) = split_git_url(&m[2]);
+    if rev.map_or(false, |x| GIT_COMMIT_RE.is_match(x)) {
+        return None;
+    }
+    let requested = rev.unwrap_or("HEAD");
+    let normalized_name = normalize_package_name(name);
+    let update = lock_options.update_all
+        || lock_options
+            .update
+            .iter()
+            .any(|x| normalize_package_name(x) == normalized_name);
+
+    let commit = match previous.get(&normalized_name) {
+        Some(pin) if !update && pin.repo == repo && pin.requested.as_deref() == Some(requested) => {
+            pin.commit.clone()
+        }
+        _ => match resolve_git_rev(repo, requested) {
+            Ok(Some(commit)) => commit,
+            Ok(None) => return None,
+            Err(err) => {
+                warn!("unable to pin {} to a commit: {}", name, err);
+                return None;
+            }
+        },
+    };
+
+    Some(format!(
+        "{} @ git+{}@{}{}{}  # {}",
+        name,
+        repo,
+        commit,
+        fragment,
+        m.get(3).map_or("", |x| x.as_str()),
+        requested
+    ))
+}
+
+/// Parses the platform to lock for.
+///
+/// Besides the platforms that uv understands (`linux`, `macos`, `windows` or
+/// a target triple like `x86_64-unknown-linux-gnu`) the docker style
+/// `os/arch` notation is accepted, for instance `linux/amd64`.
+pub fn parse_python_platform(s: &str) -> Result<String, Error> {
+    let (os, arch) = match s.split_once('/') {
+        Some(rv) => rv,
+        None => return Ok(s.to_string()),
+    };
+    // the variant (as in `linux/arm64/v8`) does not matter for wheels.
+    let arch = match arch.split('/').next().unwrap_or(arch) {
+        "amd64" | "x86_64" => "x86_64",
+        "arm64" | "aarch64" => "aarch64",
+        other => bail!("unsupported architecture '{}' in platform '{}'", other, s),
+    };
+    Ok(match os {
+        "linux" => format!("{}-unknown-linux-gnu", arch),
+        "darwin" | "macos" => format!("{}-apple-darwin", arch),
+        "windows" => format!("{}-pc-windows-msvc", arch),
+        other => bail!(
+            "unsupported operating system '{}' in platform '{}'",
+            other,
+            s
+        ),
+    })
+}
+
+/// Parses the cutoff for packages to consider when locking.
+///
+/// This is an RFC 3339 timestamp or a plain date which stands for midnight
+/// UTC of that day.
+pub fn parse_exclude_newer(s: &str) -> Result<String, Error> {
+    static DATE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap());
+    static TIMESTAMP_RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^\d{4}-\d{2}-\d{2}[Tt ]\d{2}:\d{2}:\d{2}(\.\d+)?([Zz]|[+-]\d{2}:\d{2})$")
+            .unwrap()
+    });
+    if DATE_RE.is_match(s) {
+        Ok(format!("{}T00:00:00Z", s))
+    } else if TIMESTAMP_RE.is_match(s) {
+        Ok(s.to_string())
+    } else {
+        bail!(
+            "invalid date '{}' (expected a date like 2024-01-01 or an RFC 3339 timestamp)",
+            s
+        )
+    }
+}
+
+/// Checks if a platform passed to [`parse_python_platform`] describes the
+/// current machine.
+pub fn is_host_platform(platform: &str) -> bool {
+    let (arch, os) = match platform.split_once('-') {
+        Some((arch, os)) => (Some(arch), os),
+        None => (None, platform),
+    };
+    let os_matches = match env::consts::OS {
+        "linux" => os.contains("linux"),
+        "macos" => os == "macos" || os.ends_with("darwin"),
+        "windows" => os.contains("windows"),
+        _ => false,
+    };
+    let arch_matches = match arch {
+        Some("i686") => env::consts::ARCH == "x86",
+        Some(arch) => env::consts::ARCH == arch,
+        None => true,
+    };
+    os_matches && arch_matches
+}
+
+/// Returns the platform a lockfile was generated for.
+///
+/// This is `None` for lockfiles that were generated for the current platform.
+pub fn read_locked_python_platform(lockfile: &Path) -> Result<Option<String>, Error> {
+    let contents =
+        fs::read_to_string(lockfile).path_context(lockfile, "unable to read lockfile")?;
+    let lock_options = LockOptions::restore(&contents, &LockOptions::default())?;
+    Ok(lock_options.python_platform.clone())
+}
+
 pub fn make_project_root_fragment(root: &Path) -> String {
     // XXX: ${PROJECT_ROOT} is supposed to be used in the context of file:///
     // so let's make sure it is url escaped.  This is pretty hacky but
@@ -547,7 +1143,7 @@ pub fn make_project_root_fragment(root: &Path) -> String {
         .replace(' ', "%20")
 }
 
-fn make_relative_url(path: &Path, base: &Path) -> Result<String, Error> {
+pub fn make_relative_url(path: &Path, base: &Path) -> Result<String, Error> {
     // TODO: consider using ${PROJECT_ROOT} here which is what pdm does or make-req prints
     let rv = pathdiff::diff_paths(path, base).ok_or_else(|| {
         anyhow!(
@@ -585,3 +1181,113 @@ fn test_make_relativec_url() {
         "file:."
     );
 }
+
+#[test]
+fn test_git_pins() {
+    assert_eq!(
+        split_git_url("ssh://git@github.com/pallets/flask.git@main#subdirectory=src"),
+        (
+            "ssh://git@github.com/pallets/flask.git",
+            Some("main"),
+            "#subdirectory=src"
+        )
+    );
+    assert_eq!(
+        split_git_url("https://github.com/pallets/flask"),
+        ("https://github.com/pallets/flask", None, "")
+    );
+
+    let pins = read_git_pins(
+        "flask @ git+https://github.com/pallets/flask@9b44bf2818d8e3cde422ad7f43fb33dfc6737289  # main\n\
+         werkzeug==3.0.1\n",
+    );
+    assert_eq!(
+        pins.get("flask"),
+        Some(&GitPin {
+            repo: "https://github.com/pallets/flask".into(),
+            requested: Some("main".into()),
+            commit: "9b44bf2818d8e3cde422ad7f43fb33dfc6737289".into(),
+        })
+    );
+
+    // previously pinned commits are retained unless updated
+    let pinned = pin_git_requirement(
+        "flask @ git+https://github.com/pallets/flask@main",
+        &pins,
+        &LockOptions::default(),
+    );
+    assert_eq!(
+        pinned.as_deref(),
+        Some("flask @ git+https://github.com/pallets/flask@9b44bf2818d8e3cde422ad7f43fb33dfc6737289  # main")
+    );
+}
+
+#[test]
+fn test_parse_python_platform() {
+    assert_eq!(
+        parse_python_platform("linux/amd64").unwrap(),
+        "x86_64-unknown-linux-gnu"
+    );
+    assert_eq!(
+        parse_python_platform("linux/arm64/v8").unwrap(),
+        "aarch64-unknown-linux-gnu"
+    );
+    assert_eq!(
+        parse_python_platform("darwin/arm64").unwrap(),
+        "aarch64-apple-darwin"
+    );
+    assert_eq!(parse_python_platform("linux").unwrap(), "linux");
+    assert!(parse_python_platform("linux/s390x").is_err());
+    assert!(parse_python_platform("plan9/amd64").is_err());
+
+    let host_os = match env::consts::OS {
+        "macos" => "apple-darwin",
+        "windows" => "pc-windows-msvc",
+        _ => "unknown-linux-gnu",
+    };
+    assert!(is_host_platform(&format!(
+        "{}-{}",
+        env::consts::ARCH,
+        host_os
+    )));
+    assert!(!is_host_platform("riscv64-unknown-none"));
+}
+
+#[test]
+fn test_parse_exclude_newer() {
+    assert_eq!(
+        parse_exclude_newer("2024-01-01").unwrap(),
+        "2024-01-01T00:00:00Z"
+    );
+    assert_eq!(
+        parse_exclude_newer("2023-11-18T12:00:00Z").unwrap(),
+        "2023-11-18T12:00:00Z"
+    );
+    assert_eq!(
+        parse_exclude_newer("2023-11-18T12:00:00.5+01:00").unwrap(),
+        "2023-11-18T12:00:00.5+01:00"
+    );
+    assert!(parse_exclude_newer("yesterday").is_err());
+    assert!(parse_exclude_newer("2024-01-01T12:00").is_err());
+}
+
+#[test]
+fn test_resolver_package_names() {
+    let output = "Because flask==3.0.0 depends on werkzeug>=3.0.0 and you require \
+                  flask==3.0.0, we can conclude that you require werkzeug>=3.0.0.\n\
+                  And because you require werkzeug<2, we can conclude that the \
+                  requirements are unsatisfiable.";
+    let names = RESOLVER_PACKAGE_RE
+        .captures_iter(output)
+        .filter_map(|m| m.get(1).or_else(|| m.get(2)))
+        .map(|x| x.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["flask", "werkzeug", "flask", "werkzeug", "werkzeug"]
+    );
+
+    assert!(has_upper_bound(&"werkzeug<2".parse().unwrap()));
+    assert!(has_upper_bound(&"flask==3.0.0".parse().unwrap()));
+    assert!(!has_upper_bound(&"flask>=3".parse().unwrap()));
+}
@@ -6,19 +6,32 @@ use crate::utils::QuietExit;
 #[macro_use]
 mod tui;
 
+mod attestations;
 mod bootstrap;
 mod cli;
+mod completion;
 mod config;
 mod consts;
+mod credentials;
+mod hooks;
+mod index;
+mod inline_script;
 mod installer;
 mod lock;
+mod overlay;
 mod piptools;
 mod platform;
+mod plugins;
 mod pyproject;
 mod sources;
 mod sync;
+mod timings;
+mod upload;
 mod utils;
 mod uv;
+mod vcs;
+mod wheel_cache;
+mod yanked;
 
 static SHOW_CONTINUE_PROMPT: AtomicBool = AtomicBool::new(false);
 static DISABLE_CTRLC_HANDLER: AtomicBool = AtomicBool::new(false);
@@ -38,6 +51,11 @@ pub fn main() {
 
     ctrlc::set_handler(move || {
         if !DISABLE_CTRLC_HANDLER.load(Ordering::Relaxed) {
+            // children we wait on shut down first and report how they ended
+            if crate::utils::signals::forward_interrupt() {
+                return;
+            }
+            crate::utils::watch::stop_current();
             let term = console::Term::stderr();
             term.show_cursor().ok();
             term.flush().ok();
@@ -0,0 +1,121 @@
+use std::env::{self, join_paths, split_paths};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Error};
+use pep508_rs::Requirement;
+use sha2::{Digest, Sha256};
+
+use crate::bootstrap::get_site_packages;
+use crate::platform::get_app_dir;
+use crate::pyproject::{ExpandedSources, PyProject};
+use crate::utils::{get_venv_python_bin, CommandOutput, IoPathContext};
+use crate::uv::{UvBuilder, UvInstallOptions};
+
+/// Marker that is written once all packages were installed into an overlay.
+const OVERLAY_MARKER: &str = "rye-overlay-complete";
+
+/// The bin folder of the overlay activated for this process.
+static OVERLAY_BIN: OnceLock<PathBuf> = OnceLock::new();
+
+/// Installs extra packages into an overlay on top of the project's virtualenv
+/// and activates it for all commands spawned afterwards.
+///
+/// The overlay is a separate virtualenv in the rye cache so that neither the
+/// project's virtualenv nor its lockfiles are modified.  Overlays are reused
+/// for the same set of packages.  The packages are made visible to the
+/// project's Python via `PYTHONPATH` and the overlay's own Python sees the
+/// packages of the project through a `.pth` file.
+pub fn activate_overlay(
+    pyproject: &PyProject,
+    packages: &[String],
+    output: CommandOutput,
+) -> Result<(), Error> {
+    let venv = pyproject.venv_path();
+    let py_ver = pyproject.venv_python_version()?;
+    let requirements = packages
+        .iter()
+        .map(|x| Requirement::from_str(x).with_context(|| format!("invalid package '{}'", x)))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(venv.to_string_lossy().as_bytes());
+    hasher.update(format!("\0{}\0", py_ver).as_bytes());
+    let mut names = requirements
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    for name in &names {
+        hasher.update(name.as_bytes());
+        hasher.update(b"\0");
+    }
+    let overlay = get_app_dir()
+        .join("run-with")
+        .join(&hex::encode(hasher.finalize())[..16]);
+
+    if !overlay.join(OVERLAY_MARKER).is_file() {
+        echo!(if output, "Installing {} into overlay", names.join(", "));
+        if overlay.is_dir() {
+            fs::remove_dir_all(&overlay)
+                .path_context(&overlay, "unable to remove incomplete overlay")?;
+        }
+        let mut requirements = requirements.into_iter();
+        let first = requirements.next().context("no packages given")?;
+        UvBuilder::new()
+            .with_output(output.quieter())
+            .with_workdir(&pyproject.workspace_path())
+            .with_sources(ExpandedSources::from_sources(&pyproject.sources()?)?)
+            .ensure_exists()?
+            .venv(&overlay, &get_venv_python_bin(&venv), &py_ver, None)?
+            .with_output(output.quieter())
+            .install(
+                &first,
+                UvInstallOptions {
+                    importlib_workaround: false,
+                    extras: requirements.collect(),
+                },
+            )?;
+        let pth = get_site_packages(&overlay)?.join("_rye_overlay.pth");
+        fs::write(
+            &pth,
+            format!(
+                "import site; site.addsitedir({:?})\n",
+                get_site_packages(&venv)?
+            ),
+        )
+        .path_context(&pth, "unable to write overlay path file")?;
+        fs::write(overlay.join(OVERLAY_MARKER), b"")
+            .path_context(&overlay, "unable to write overlay marker")?;
+    }
+
+    // the project's virtualenv is put in front of the PATH when a command is
+    // invoked, so tools from the overlay only win if the project lacks them.
+    let bin = overlay.join(if cfg!(windows) { "Scripts" } else { "bin" });
+    let mut paths = env::var_os("PATH")
+        .map(|x| split_paths(&x).collect::<Vec<_>>())
+        .unwrap_or_default();
+    paths.insert(0, bin.clone());
+    env::set_var("PATH", join_paths(paths)?);
+
+    let mut python_paths = env::var_os("PYTHONPATH")
+        .map(|x| split_paths(&x).collect::<Vec<_>>())
+        .unwrap_or_default();
+    python_paths.insert(0, get_site_packages(&overlay)?);
+    env::set_var("PYTHONPATH", join_paths(python_paths)?);
+
+    OVERLAY_BIN.set(bin).ok();
+    Ok(())
+}
+
+/// Looks up a command that is only installed in the active overlay.
+pub fn find_overlay_command(name: &str) -> Option<PathBuf> {
+    let bin = OVERLAY_BIN.get()?;
+    let mut path = bin.join(name);
+    if cfg!(windows) && path.extension().is_none() {
+        path.set_extension("exe");
+    }
+    path.is_file().then_some(path)
+}
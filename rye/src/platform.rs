@@ -4,10 +4,11 @@ use std::sync::Mutex;
 use std::{env, fs};
 
 use anyhow::{anyhow, Context, Error};
+use sha2::{Digest, Sha256};
 
 use crate::config::Config;
 use crate::pyproject::latest_available_python_version;
-use crate::sources::py::{PythonVersion, PythonVersionRequest};
+use crate::sources::py::{get_download_url, BuildFlavor, PythonVersion, PythonVersionRequest};
 use crate::utils::IoPathContext;
 
 static APP_DIR: Mutex<Option<&'static PathBuf>> = Mutex::new(None);
@@ -60,6 +61,40 @@ pub fn get_canonical_py_path(version: &PythonVersion) -> Result<PathBuf, Error>
     Ok(rv)
 }
 
+/// The file within a toolchain folder that records its build flavor.
+pub const TOOLCHAIN_FLAVOR_FILENAME: &str = "rye-build-flavor";
+
+/// Returns the build flavor a toolchain was fetched as.
+///
+/// This is unknown for toolchains that were registered or fetched by older
+/// versions of Rye.
+pub fn get_toolchain_build_flavor(version: &PythonVersion) -> Option<BuildFlavor> {
+    fs::read_to_string(
+        get_canonical_py_path(version)
+            .ok()?
+            .join(TOOLCHAIN_FLAVOR_FILENAME),
+    )
+    .ok()?
+    .trim()
+    .parse()
+    .ok()
+}
+
+/// Returns the folder where downloaded toolchain archives are cached.
+pub fn get_toolchain_archive_cache_dir() -> PathBuf {
+    get_app_dir().join("cache").join("toolchains")
+}
+
+/// Returns the folder where package index metadata is cached.
+pub fn get_index_cache_dir() -> PathBuf {
+    get_app_dir().join("cache").join("index")
+}
+
+/// Returns the folder of the wheels shared between projects and machines.
+pub fn get_wheel_cache_dir() -> PathBuf {
+    get_app_dir().join("cache").join("wheels")
+}
+
 /// Returns the path of the python binary for the given version.
 pub fn get_toolchain_python_bin(version: &PythonVersion) -> Result<PathBuf, Error> {
     let p = get_canonical_py_path(version)?;
@@ -86,6 +121,28 @@ pub fn get_toolchain_python_bin(version: &PythonVersion) -> Result<PathBuf, Erro
     Ok(get_python_bin_within(&p))
 }
 
+/// Returns a fingerprint of the installed toolchain.
+///
+/// This changes when the toolchain is re-downloaded or replaced, in which case
+/// virtualenvs created from it refer to stale paths.  `None` is returned if the
+/// toolchain is not installed.
+pub fn get_toolchain_fingerprint(version: &PythonVersion) -> Option<String> {
+    let py_bin = get_toolchain_python_bin(version)
+        .ok()?
+        .canonicalize()
+        .ok()?;
+    let metadata = fs::metadata(&py_bin).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|x| x.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |x| x.as_nanos());
+    let mut hasher = Sha256::new();
+    hasher.update(py_bin.to_string_lossy().as_bytes());
+    hasher.update(format!("\0{}\0{}\0{}", version, metadata.len(), mtime).as_bytes());
+    Some(hex::encode(&hasher.finalize()[..8]))
+}
+
 /// Returns the path to the python binary within the path.
 pub fn get_python_bin_within(path: &Path) -> PathBuf {
     let mut path = path.to_path_buf();
@@ -177,6 +234,35 @@ pub fn list_known_toolchains() -> Result<Vec<(PythonVersion, PathBuf)>, Error> {
     Ok(rv)
 }
 
+/// Finds installed toolchains for which a newer patch release can be downloaded.
+///
+/// Returns pairs of the installed and the newest available version.
+pub fn find_toolchain_upgrades() -> Result<Vec<(PythonVersion, PythonVersion)>, Error> {
+    let installed = list_known_toolchains()?;
+    let mut rv = Vec::new();
+    for (version, _) in &installed {
+        // registered toolchains are links and were not downloaded by us.
+        if !get_canonical_py_path(version)?.is_dir() {
+            continue;
+        }
+        if let Some(latest) = find_toolchain_upgrade(version) {
+            rv.push((version.clone(), latest));
+        }
+    }
+    rv.sort();
+    Ok(rv)
+}
+
+/// Returns the newest downloadable patch release of a toolchain if it is
+/// newer than the given version.
+fn find_toolchain_upgrade(version: &PythonVersion) -> Option<PythonVersion> {
+    let mut request = PythonVersionRequest::from(version.clone());
+    request.patch = None;
+    get_download_url(&request)
+        .map(|(latest, _, _)| latest)
+        .filter(|latest| latest.patch > version.patch)
+}
+
 /// Returns the default author from git or the config.
 pub fn get_default_author_with_fallback(dir: &PathBuf) -> Option<(String, String)> {
     let (mut name, mut email) = Config::current().default_author();
@@ -212,13 +298,20 @@ pub fn get_default_author_with_fallback(dir: &PathBuf) -> Option<(String, String
 
 /// Reads the current `.python-version` file.
 pub fn get_python_version_request_from_pyenv_pin(root: &Path) -> Option<PythonVersionRequest> {
+    find_pyenv_pin(root).map(|(_, ver)| ver)
+}
+
+/// Finds the nearest `.python-version` file in a folder or its parents.
+///
+/// Returns the path of the file together with the requested version.
+pub fn find_pyenv_pin(root: &Path) -> Option<(PathBuf, PythonVersionRequest)> {
     let mut here = root.to_owned();
 
     loop {
         here.push(".python-version");
         if let Ok(contents) = fs::read_to_string(&here) {
             let ver = contents.trim().parse().ok()?;
-            return Some(ver);
+            return Some((here, ver));
         }
 
         // pop filename
@@ -289,3 +382,137 @@ pub fn default_environment() -> Option<&'static str> {
         None
     }
 }
+
+/// Registers a toolchain with the Windows registry (PEP 514) if enabled.
+///
+/// This is a no-op on other platforms.
+pub fn register_toolchain_in_registry(version: &PythonVersion) -> Result<(), Error> {
+    #[cfg(windows)]
+    {
+        if Config::current().windows_registry() {
+            let py_bin = get_toolchain_python_bin(version)?;
+            let install_path = py_bin.parent().unwrap_or(&py_bin);
+            let windowed = install_path.join("pythonw.exe");
+            crate::utils::windows::register_pep514(&crate::utils::windows::Pep514Entry {
+                tag: &version.to_string(),
+                display_name: &format!(
+                    "{} {}.{}.{} (Rye)",
+                    version.name, version.major, version.minor, version.patch
+                ),
+                version: format!("{}.{}.{}", version.major, version.minor, version.patch),
+                sys_version: format!("{}.{}", version.major, version.minor),
+                sys_architecture: pep514_architecture(version),
+                install_path,
+                executable: &py_bin,
+                windowed_executable: Some(&windowed).filter(|x| x.is_file()).map(|x| x.as_path()),
+            })?;
+        }
+    }
+    let _ = version;
+    Ok(())
+}
+
+/// Registers a project virtualenv with the Windows registry (PEP 514) if enabled.
+///
+/// This is a no-op on other platforms.
+pub fn register_venv_in_registry(
+    venv: &Path,
+    name: &str,
+    py_ver: &PythonVersion,
+) -> Result<(), Error> {
+    #[cfg(windows)]
+    {
+        if Config::current().windows_registry() {
+            let tag = pep514_venv_tag(venv, name);
+            let bin_dir = venv.join(crate::consts::VENV_BIN);
+            let windowed = bin_dir.join("pythonw.exe");
+            crate::utils::windows::register_pep514(&crate::utils::windows::Pep514Entry {
+                tag: &tag,
+                display_name: &format!("{} ({}, Rye)", name, py_ver),
+                version: format!("{}.{}.{}", py_ver.major, py_ver.minor, py_ver.patch),
+                sys_version: format!("{}.{}", py_ver.major, py_ver.minor),
+                sys_architecture: pep514_architecture(py_ver),
+                install_path: venv,
+                executable: &bin_dir.join("python.exe"),
+                windowed_executable: Some(&windowed).filter(|x| x.is_file()).map(|x| x.as_path()),
+            })?;
+        }
+    }
+    let _ = (venv, name, py_ver);
+    Ok(())
+}
+
+/// Removes a toolchain from the Windows registry (PEP 514).
+///
+/// Other entries whose executable is gone are cleaned up as well.  This happens
+/// even if registration is disabled so that no stale entries are left behind.
+pub fn unregister_toolchain_from_registry(version: &PythonVersion) -> Result<(), Error> {
+    #[cfg(windows)]
+    {
+        crate::utils::windows::unregister_pep514(&version.to_string())?;
+        crate::utils::windows::prune_pep514()?;
+    }
+    let _ = version;
+    Ok(())
+}
+
+/// The PEP 514 tag of a virtualenv.
+///
+/// The tag needs to be stable for a virtualenv but unique across projects of
+/// the same name.
+#[cfg(any(windows, test))]
+fn pep514_venv_tag(venv: &Path, name: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(venv.to_string_lossy().as_bytes());
+    format!("venv-{}-{}", name, &hex::encode(hasher.finalize())[..8])
+}
+
+#[cfg(any(windows, test))]
+fn pep514_architecture(version: &PythonVersion) -> &'static str {
+    match &*version.arch {
+        "x86" => "32bit",
+        _ => "64bit",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_toolchain_upgrade() {
+        let upgrade = |version: &str| find_toolchain_upgrade(&version.parse().unwrap());
+        let version = |version: &str| Some(version.parse::<PythonVersion>().unwrap());
+        assert_eq!(
+            upgrade("cpython-x86_64-linux-gnu@3.12.0"),
+            version("cpython-x86_64-linux-gnu@3.12.2")
+        );
+        // the platform of the installed toolchain is kept
+        assert_eq!(
+            upgrade("cpython-aarch64-macos@3.12.1"),
+            version("cpython-aarch64-macos@3.12.2")
+        );
+        // never a new minor release and nothing for the newest one
+        assert_eq!(upgrade("cpython-x86_64-linux-gnu@3.12.2"), None);
+        assert_eq!(upgrade("cpython-x86_64-linux-gnu@3.13.0"), None);
+    }
+
+    #[test]
+    fn test_pep514_venv_tag() {
+        let tag = pep514_venv_tag(Path::new("/work/foo/.venv"), "foo");
+        assert!(tag.starts_with("venv-foo-"));
+        assert_eq!(tag.len(), "venv-foo-".len() + 8);
+        assert_eq!(tag, pep514_venv_tag(Path::new("/work/foo/.venv"), "foo"));
+        assert_ne!(tag, pep514_venv_tag(Path::new("/other/foo/.venv"), "foo"));
+    }
+
+    #[test]
+    fn test_pep514_architecture() {
+        let mut version: PythonVersion = "cpython-x86_64-windows@3.12.2".parse().unwrap();
+        assert_eq!(pep514_architecture(&version), "64bit");
+        version.arch = "x86".into();
+        assert_eq!(pep514_architecture(&version), "32bit");
+        version.arch = "aarch64".into();
+        assert_eq!(pep514_architecture(&version), "64bit");
+    }
+}
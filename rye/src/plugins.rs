@@ -0,0 +1,381 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{bail, Context, Error};
+use console::style;
+use pep508_rs::Requirement;
+use serde::Serialize;
+
+use crate::bootstrap::{
+    ensure_self_venv, fetch, get_site_packages, FetchOptions, SELF_PYTHON_TARGET_VERSION,
+};
+use crate::config::Config;
+use crate::consts::VENV_BIN;
+use crate::platform::get_app_dir;
+use crate::pyproject::{normalize_package_name, ExpandedSources, PyProject};
+use crate::sync::create_virtualenv;
+use crate::utils::{get_venv_python_bin, is_executable, CommandOutput};
+use crate::uv::{UvBuilder, UvInstallOptions};
+
+/// The prefix of executables that are exposed as rye subcommands.
+const PLUGIN_PREFIX: &str = "rye-";
+
+/// The entry point group that plugins in the plugins virtualenv register.
+const ENTRY_POINT_GROUP: &str = "rye.plugins";
+
+const RUN_ENTRY_POINT_SCRIPT: &str = r#"
+import sys
+import importlib
+
+name = sys.argv.pop(1)
+target = sys.argv.pop(1)
+module, _, attrs = target.partition(":")
+obj = importlib.import_module(module.strip())
+for attr in attrs.strip().split("."):
+    if attr:
+        obj = getattr(obj, attr)
+sys.argv[0] = "rye-" + name
+sys.exit(obj())
+"#;
+
+/// Where a plugin comes from.
+#[derive(Debug, Clone)]
+pub enum PluginSource {
+    /// A `rye-<name>` executable on the `PATH`.
+    Executable(PathBuf),
+    /// An entry point (`module:attr`) in the plugins virtualenv.
+    EntryPoint {
+        distribution: String,
+        target: String,
+    },
+}
+
+/// A plugin that is exposed as `rye <name>`.
+#[derive(Debug, Clone)]
+pub struct Plugin {
+    pub name: String,
+    pub source: PluginSource,
+}
+
+impl Plugin {
+    /// A short description of the plugin for the help output.
+    pub fn about(&self) -> String {
+        match self.source {
+            PluginSource::Executable(ref path) => format!("Plugin ({})", path.display()),
+            PluginSource::EntryPoint {
+                ref distribution, ..
+            } => format!("Plugin (from {})", distribution),
+        }
+    }
+}
+
+/// The project context handed to plugins.
+#[derive(Serialize, Debug)]
+struct PluginContext {
+    rye_version: &'static str,
+    rye_bin: Option<PathBuf>,
+    rye_home: PathBuf,
+    plugin: String,
+    project: Option<ProjectContext>,
+}
+
+#[derive(Serialize, Debug)]
+struct ProjectContext {
+    name: Option<String>,
+    root: PathBuf,
+    pyproject: PathBuf,
+    workspace_root: PathBuf,
+    venv: PathBuf,
+    is_virtual: bool,
+}
+
+/// Returns the path of the virtualenv that holds Python plugins.
+pub fn get_plugins_venv() -> PathBuf {
+    get_app_dir().join("plugins")
+}
+
+/// Discovers all plugins.
+///
+/// Executables on the `PATH` take precedence over entry points, and earlier
+/// `PATH` entries take precedence over later ones.
+pub fn list_plugins() -> Vec<Plugin> {
+    let mut rv = BTreeMap::new();
+    for plugin in find_executables().into_iter().chain(find_entry_points()) {
+        rv.entry(plugin.name.clone()).or_insert(plugin);
+    }
+    rv.into_values().collect()
+}
+
+fn find_executables() -> Vec<Plugin> {
+    let mut rv = Vec::new();
+    let path = match env::var_os("PATH") {
+        Some(path) => path,
+        None => return rv,
+    };
+    for dir in env::split_paths(&path) {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let filename = entry.file_name();
+            let filename = filename.to_string_lossy();
+            let name = match filename.strip_prefix(PLUGIN_PREFIX) {
+                Some(name) => name,
+                None => continue,
+            };
+            #[cfg(windows)]
+            let name = match name.rsplit_once('.') {
+                Some((name, ext)) if ["exe", "bat", "cmd"].contains(&ext) => name,
+                _ => continue,
+            };
+            if name.is_empty() || !path.is_file() || !is_executable(&path) {
+                continue;
+            }
+            rv.push(Plugin {
+                name: name.to_string(),
+                source: PluginSource::Executable(path),
+            });
+        }
+    }
+    rv
+}
+
+fn find_entry_points() -> Vec<Plugin> {
+    let mut rv = Vec::new();
+    let venv = get_plugins_venv();
+    if !venv.is_dir() {
+        return rv;
+    }
+    let site_packages = match get_site_packages(&venv) {
+        Ok(site_packages) => site_packages,
+        Err(_) => return rv,
+    };
+    for entry in fs::read_dir(site_packages).into_iter().flatten().flatten() {
+        let filename = entry.file_name();
+        let filename = filename.to_string_lossy();
+        let distribution = match filename.strip_suffix(".dist-info") {
+            Some(dist) => dist.split('-').next().unwrap_or(dist).to_string(),
+            None => continue,
+        };
+        if let Ok(contents) = fs::read_to_string(entry.path().join("entry_points.txt")) {
+            for (name, target) in parse_entry_points(&contents, ENTRY_POINT_GROUP) {
+                rv.push(Plugin {
+                    name,
+                    source: PluginSource::EntryPoint {
+                        distribution: distribution.clone(),
+                        target,
+                    },
+                });
+            }
+        }
+    }
+    rv
+}
+
+/// Parses the entry points of a group from an `entry_points.txt` file.
+fn parse_entry_points(contents: &str, group: &str) -> Vec<(String, String)> {
+    let mut rv = Vec::new();
+    let mut in_group = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            in_group = section.trim() == group;
+        } else if in_group {
+            if let Some((name, target)) = line.split_once('=') {
+                // extras (`module:attr [extra]`) are not relevant for running
+                let target = target.split('[').next().unwrap_or("").trim();
+                rv.push((name.trim().to_string(), target.to_string()));
+            }
+        }
+    }
+    rv
+}
+
+/// Runs a plugin and exits with its status code.
+///
+/// The project context is passed as `RYE_*` environment variables and as a
+/// single JSON document in `RYE_CONTEXT`.  Stdin is inherited so that plugins
+/// can read input and prompt the user.
+pub fn run_plugin(plugin: &Plugin, args: &[OsString]) -> Result<(), Error> {
+    let mut cmd = match plugin.source {
+        PluginSource::Executable(ref path) => Command::new(path),
+        PluginSource::EntryPoint { ref target, .. } => {
+            let mut cmd = Command::new(get_venv_python_bin(&get_plugins_venv()));
+            cmd.arg("-c")
+                .arg(RUN_ENTRY_POINT_SCRIPT)
+                .arg(&plugin.name)
+                .arg(target);
+            cmd
+        }
+    };
+    cmd.args(args);
+
+    let context = PluginContext {
+        rye_version: env!("CARGO_PKG_VERSION"),
+        rye_bin: env::current_exe().ok(),
+        rye_home: get_app_dir().to_path_buf(),
+        plugin: plugin.name.clone(),
+        project: PyProject::discover().ok().map(|project| ProjectContext {
+            name: project.name().map(|x| x.to_string()),
+            root: project.root_path().into_owned(),
+            pyproject: project.toml_path().into_owned(),
+            workspace_root: project.workspace_path().into_owned(),
+            venv: project.venv_path().into_owned(),
+            is_virtual: project.is_virtual(),
+        }),
+    };
+
+    cmd.env("RYE_PLUGIN", &context.plugin)
+        .env("RYE_VERSION", context.rye_version)
+        .env("RYE_HOME", &context.rye_home)
+        .env("RYE_CONTEXT", serde_json::to_string(&context)?);
+    if let Some(ref rye_bin) = context.rye_bin {
+        cmd.env("RYE_BIN", rye_bin);
+    }
+    if let Some(ref project) = context.project {
+        cmd.env("RYE_PROJECT_ROOT", &project.root)
+            .env("RYE_PYPROJECT", &project.pyproject)
+            .env("RYE_WORKSPACE_ROOT", &project.workspace_root)
+            .env("RYE_VENV", &project.venv);
+    }
+
+    // the plugin is responsible for handling ctrl-c from here on
+    crate::disable_ctrlc_handler();
+    let status = cmd
+        .status()
+        .with_context(|| format!("unable to run plugin {}", plugin.name))?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// Installs a package with plugins into the plugins virtualenv.
+pub fn install(requirement: &Requirement, output: CommandOutput) -> Result<(), Error> {
+    let sources = ExpandedSources::from_sources(&Config::current().sources()?)?;
+    let self_venv = ensure_self_venv(output)?;
+    let venv = get_plugins_venv();
+    let py = get_venv_python_bin(&venv);
+    let py_ver = fetch(
+        &SELF_PYTHON_TARGET_VERSION,
+        FetchOptions::with_output(output),
+    )?;
+    if !py.is_file() {
+        create_virtualenv(output, &self_venv, &py_ver, &venv, "rye-plugins")?;
+    }
+
+    if Config::current().use_uv() {
+        UvBuilder::new()
+            .with_output(output.quieter())
+            .with_sources(sources)
+            .ensure_exists()?
+            .venv(&venv, &py, &py_ver, None)?
+            .with_output(output)
+            .install(
+                requirement,
+                UvInstallOptions {
+                    importlib_workaround: false,
+                    extras: Vec::new(),
+                },
+            )?;
+    } else {
+        let mut cmd = Command::new(self_venv.join(VENV_BIN).join("pip"));
+        cmd.arg("--python")
+            .arg(&py)
+            .arg("install")
+            .env("PYTHONWARNINGS", "ignore")
+            .env("PIP_DISABLE_PIP_VERSION_CHECK", "1");
+        sources.add_as_pip_args(&mut cmd);
+        if output == CommandOutput::Verbose {
+            cmd.arg("--verbose");
+        } else if output == CommandOutput::Quiet {
+            cmd.arg("-q");
+        }
+        cmd.arg("--").arg(requirement.to_string());
+        let status = cmd.status()?;
+        if !status.success() {
+            bail!("plugin installation failed");
+        }
+    }
+
+    if output != CommandOutput::Quiet {
+        let name = normalize_package_name(&requirement.name);
+        let plugins = list_plugins()
+            .into_iter()
+            .filter(|plugin| match plugin.source {
+                PluginSource::EntryPoint {
+                    ref distribution, ..
+                } => normalize_package_name(distribution) == name,
+                _ => false,
+            })
+            .collect::<Vec<_>>();
+        if plugins.is_empty() {
+            warn!("installed package did not register any plugins");
+        } else {
+            echo!("Installed plugins:");
+            for plugin in plugins {
+                echo!("  - {}", style(&plugin.name).cyan());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Uninstalls a package from the plugins virtualenv.
+pub fn uninstall(package: &str, output: CommandOutput) -> Result<(), Error> {
+    let venv = get_plugins_venv();
+    let py = get_venv_python_bin(&venv);
+    if !py.is_file() {
+        echo!(if output, "{} is not installed", style(package).cyan());
+        return Ok(());
+    }
+
+    let self_venv = ensure_self_venv(output)?;
+    let mut cmd = Command::new(self_venv.join(VENV_BIN).join("pip"));
+    cmd.arg("--python")
+        .arg(&py)
+        .arg("uninstall")
+        .arg("-y")
+        .env("PYTHONWARNINGS", "ignore")
+        .env("PIP_DISABLE_PIP_VERSION_CHECK", "1");
+    if output != CommandOutput::Verbose {
+        cmd.arg("-q");
+    }
+    cmd.arg("--").arg(package);
+    let status = cmd.status()?;
+    if !status.success() {
+        bail!("failed to uninstall {}", package);
+    }
+    echo!(if output, "Uninstalled {}", style(package).cyan());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry_points() {
+        let contents = "\
+[console_scripts]
+foo = foo.cli:main
+
+[rye.plugins]
+deploy = acme_rye.deploy:main
+audit=acme_rye.audit:cli.run [extra]
+";
+        assert_eq!(
+            parse_entry_points(contents, ENTRY_POINT_GROUP),
+            vec![
+                ("deploy".to_string(), "acme_rye.deploy:main".to_string()),
+                ("audit".to_string(), "acme_rye.audit:cli.run".to_string()),
+            ]
+        );
+        assert!(
+            parse_entry_points("[console_scripts]\nfoo = foo:main\n", ENTRY_POINT_GROUP).is_empty()
+        );
+    }
+}
@@ -356,7 +356,52 @@ pub fn matches_version(req: &PythonVersionRequest, v: &PythonVersion) -> bool {
     true
 }
 
+/// The artifact that is downloaded for a portable CPython build.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum BuildFlavor {
+    /// The full build including debug symbols and build information.
+    Full,
+    /// Just the installation, including debug symbols.
+    InstallOnly,
+    /// Just the installation without debug symbols.
+    #[default]
+    InstallOnlyStripped,
+}
+
+impl BuildFlavor {
+    /// Returns the name of the flavor as used in the config.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildFlavor::Full => "full",
+            BuildFlavor::InstallOnly => "install_only",
+            BuildFlavor::InstallOnlyStripped => "install_only_stripped",
+        }
+    }
+}
+
+impl FromStr for BuildFlavor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(BuildFlavor::Full),
+            "install_only" => Ok(BuildFlavor::InstallOnly),
+            "install_only_stripped" => Ok(BuildFlavor::InstallOnlyStripped),
+            _ => Err(anyhow!("unknown build flavor '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for BuildFlavor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Given a version, platform and architecture returns the download URL.
+///
+/// The checksum is either a plain sha256 hex digest or prefixed with its
+/// algorithm (eg: `sha512:...`).
 pub fn get_download_url(
     requested_version: &PythonVersionRequest,
 ) -> Option<(PythonVersion, &'static str, Option<&'static str>)> {
@@ -368,6 +413,24 @@ pub fn get_download_url(
     None
 }
 
+/// Returns the download URL of a specific build flavor of an exact version.
+///
+/// The smaller install only builds are not available for all versions and
+/// platforms, in which case `None` is returned.
+pub fn get_flavor_download_url(
+    version: &PythonVersion,
+    flavor: BuildFlavor,
+) -> Option<(&'static str, Option<&'static str>)> {
+    match flavor {
+        BuildFlavor::Full => downloads::PYTHON_VERSIONS,
+        BuildFlavor::InstallOnly => downloads::PYTHON_INSTALL_ONLY_VERSIONS,
+        BuildFlavor::InstallOnlyStripped => downloads::PYTHON_STRIPPED_VERSIONS,
+    }
+    .iter()
+    .find(|(it_version, _, _)| it_version == version)
+    .map(|(_, url, sha256)| (*url, *sha256))
+}
+
 /// Returns an iterator over downloadable installations.
 pub fn iter_downloadable<'s>(
     os: &'s str,
@@ -497,3 +560,52 @@ fn test_get_download_url() {
         assert_eq!(url, Some((PythonVersion { name: "cpython".into(), arch: "x86_64".into(), os: "linux".into(), environment: Some("musl".into()), major: 3, minor: 12, patch: 1, suffix: None }, "https://github.com/indygreg/python-build-standalone/releases/download/20240107/cpython-3.12.1%2B20240107-x86_64-unknown-linux-musl-lto-full.tar.zst", Some("c4b07a02d8f0986b56e010a67132e5eeba1def4991c6c06ed184f831a484a06f"))));
     }
 }
+
+#[test]
+fn test_parse_build_flavor() {
+    for flavor in [
+        BuildFlavor::Full,
+        BuildFlavor::InstallOnly,
+        BuildFlavor::InstallOnlyStripped,
+    ] {
+        assert_eq!(flavor.as_str().parse::<BuildFlavor>().unwrap(), flavor);
+    }
+    assert!("stripped".parse::<BuildFlavor>().is_err());
+    assert_eq!(BuildFlavor::default(), BuildFlavor::InstallOnlyStripped);
+}
+
+#[test]
+fn test_flavor_downloads_match_full_builds() {
+    for (flavor, downloads) in [
+        (
+            BuildFlavor::InstallOnly,
+            downloads::PYTHON_INSTALL_ONLY_VERSIONS,
+        ),
+        (
+            BuildFlavor::InstallOnlyStripped,
+            downloads::PYTHON_STRIPPED_VERSIONS,
+        ),
+    ] {
+        for (version, url, _) in downloads {
+            assert!(url.contains(&format!("-{}.", flavor)), "{}", url);
+            assert!(
+                downloads::PYTHON_VERSIONS
+                    .iter()
+                    .any(|(it_version, _, _)| it_version == version),
+                "{} build of {} has no full build",
+                flavor,
+                version
+            );
+        }
+    }
+}
+
+#[test]
+#[ignore = "python_downloads.inc needs to be regenerated with `rye run find-downloads`"]
+fn test_stripped_download_for_current_version() {
+    let (version, _, _) = get_download_url(&"cpython-x86_64-linux-gnu@3.12".parse().unwrap())
+        .expect("no cpython 3.12 download");
+    let (url, _) = get_flavor_download_url(&version, BuildFlavor::InstallOnlyStripped)
+        .expect("no stripped build of the latest cpython 3.12");
+    assert!(url.ends_with("-install_only_stripped.tar.gz"), "{}", url);
+}
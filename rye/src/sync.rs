@@ -1,29 +1,62 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::{env, fs};
+use std::time::{Duration, SystemTime};
+use std::{env, fmt, fs, thread};
 
-use anyhow::{bail, Context, Error};
-use console::style;
+use anyhow::{anyhow, bail, Context, Error};
+use console::{style, Term};
+use pep440_rs::{Operator, Version};
+use pep508_rs::{Requirement, VersionOrUrl};
 use same_file::is_same_file;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tempfile::tempdir;
+use url::Url;
 
 use crate::bootstrap::{ensure_self_venv, fetch, get_pip_module, FetchOptions};
 use crate::config::Config;
 use crate::consts::VENV_BIN;
+use crate::hooks::run_hook;
 use crate::lock::{
-    make_project_root_fragment, update_single_project_lockfile, update_workspace_lockfile,
-    LockMode, LockOptions,
+    is_host_platform, make_project_root_fragment, make_relative_url, read_git_pins,
+    read_locked_python_platform, strip_continuation, update_single_project_lockfile,
+    update_workspace_lockfile, LockMode, LockOptions,
 };
 use crate::piptools::{get_pip_sync, get_pip_tools_venv_path};
-use crate::platform::get_toolchain_python_bin;
-use crate::pyproject::{read_venv_marker, write_venv_marker, ExpandedSources, PyProject};
+use crate::platform::{
+    find_toolchain_upgrades, get_app_dir, get_toolchain_fingerprint, get_toolchain_python_bin,
+    register_venv_in_registry,
+};
+use crate::pyproject::{
+    normalize_package_name, read_venv_marker, write_venv_marker, DependencyKind, ExpandedSources,
+    PyProject,
+};
 use crate::sources::py::PythonVersion;
+use crate::sources::uv::{UvDownload, UvRequest};
+use crate::timings;
+use crate::tui::Summary;
+use crate::utils::filelock::FileLock;
 use crate::utils::{
-    get_venv_python_bin, set_proxy_variables, symlink_dir, update_venv_sync_marker, CommandOutput,
-    IoPathContext,
+    get_venv_python_bin, set_proxy_variables, symlink_dir, tui_theme, update_venv_sync_marker,
+    CommandOutput, IoPathContext, QuietExit,
 };
 use crate::uv::UvBuilder;
+use crate::wheel_cache::{fill_wheel_cache, use_wheel_cache};
+
+/// How often `behavior.toolchain-upgrade-check` looks for upgrades.
+const TOOLCHAIN_UPGRADE_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Name of the file in the virtualenv that records the inputs and options of
+/// the last sync.
+const SYNC_STATE_FILENAME: &str = "rye-sync-state.json";
+
+/// Name of the file in the virtualenv that holds the build inputs of the
+/// installed local projects.
+const EDITABLE_HASHES_FILENAME: &str = "rye-editable-hashes.json";
+
+/// Files of a local project which influence its editable install.
+const EDITABLE_BUILD_INPUTS: &[&str] = &["pyproject.toml", "setup.py", "setup.cfg", "MANIFEST.in"];
 
 /// Controls the sync mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
@@ -39,6 +72,21 @@ pub enum SyncMode {
     Full,
 }
 
+/// Restricts which of the locked packages are installed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SyncScope {
+    /// Everything in the lockfile.
+    #[default]
+    All,
+    /// Only the dev dependencies and what they depend on.
+    OnlyDev,
+    /// Only an optional dependency group and what it depends on.
+    Only(String),
+    /// Only a workspace member and what it depends on, including the other
+    /// members it depends on.
+    Member(String),
+}
+
 /// Updates the virtualenv based on the pyproject.toml
 #[derive(Debug, Default)]
 pub struct SyncOptions {
@@ -56,6 +104,16 @@ pub struct SyncOptions {
     pub lock_options: LockOptions,
     /// Explicit pyproject location (Only usable by PythonOnly mode)
     pub pyproject: Option<PathBuf>,
+    /// The named environment to sync.
+    pub env: Option<String>,
+    /// Recreate the virtualenv without prompting if its toolchain changed.
+    pub yes: bool,
+    /// Which of the locked packages are installed.
+    pub scope: SyncScope,
+    /// Do not install the local projects themselves.
+    pub no_install_project: bool,
+    /// How many lockfiles may be resolved at once.
+    pub jobs: usize,
 }
 
 impl SyncOptions {
@@ -71,6 +129,77 @@ impl SyncOptions {
         self.pyproject = pyproject;
         self
     }
+
+    pub fn env(mut self, env: Option<String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Applies the settings of the project's selected environment.
+    fn apply_env(&mut self, pyproject: &PyProject) {
+        if let Some(env) = pyproject.env() {
+            self.lock_options
+                .features
+                .extend(env.features.iter().cloned());
+            self.dev = self.dev && env.dev;
+            if self.lock_options.python_platform.is_none() {
+                self.lock_options.python_platform = env.python_platform.clone();
+            }
+        }
+    }
+
+    /// Makes sure the lockfiles contain the packages of the selected scope.
+    ///
+    /// The dependency group picked with `--only` is enabled as a feature
+    /// of all projects that define it.
+    fn apply_scope(&mut self, pyproject: &PyProject) -> Result<(), Error> {
+        match self.scope {
+            SyncScope::All => {}
+            SyncScope::OnlyDev => {
+                if !self.dev {
+                    bail!("cannot sync only dev dependencies if they are excluded");
+                }
+            }
+            SyncScope::Only(ref group) => {
+                let mut found = false;
+                for_each_project(pyproject, |project| {
+                    if project.is_virtual() || !project.extras().contains(group.as_str()) {
+                        return Ok(());
+                    }
+                    found = true;
+                    let feature = if project.is_workspace_root() {
+                        group.clone()
+                    } else {
+                        format!("{}/{}", project.normalized_name()?, group)
+                    };
+                    if !self.lock_options.all_features
+                        && !self.lock_options.features.contains(&feature)
+                    {
+                        self.lock_options.features.push(feature);
+                    }
+                    Ok(())
+                })?;
+                if !found {
+                    bail!("no project defines the dependency group '{}'", group);
+                }
+            }
+            SyncScope::Member(ref name) => {
+                if pyproject.workspace().is_none() {
+                    bail!("cannot sync a single member outside of a workspace");
+                }
+                let name = normalize_package_name(name);
+                let mut found = false;
+                for_each_project(pyproject, |project| {
+                    found |= project.normalized_name()? == name;
+                    Ok(())
+                })?;
+                if !found {
+                    bail!("no workspace member named '{}'", name);
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Config written into the virtualenv for sync purposes.
@@ -78,6 +207,9 @@ impl SyncOptions {
 pub struct VenvMarker {
     pub python: PythonVersion,
     pub venv_path: Option<PathBuf>,
+    /// Fingerprint of the toolchain the virtualenv was created from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<String>,
 }
 
 impl VenvMarker {
@@ -88,13 +220,30 @@ impl VenvMarker {
 
 /// Synchronizes a project's virtualenv.
 pub fn sync(mut cmd: SyncOptions) -> Result<(), Error> {
-    let pyproject = PyProject::load_or_discover(cmd.pyproject.as_deref())?;
-    let lockfile = pyproject.workspace_path().join("requirements.lock");
-    let dev_lockfile = pyproject.workspace_path().join("requirements-dev.lock");
+    let pyproject =
+        PyProject::load_or_discover(cmd.pyproject.as_deref())?.with_env(cmd.env.as_deref())?;
+    cmd.apply_env(&pyproject);
+    cmd.apply_scope(&pyproject)?;
+    let lockfile = pyproject.lockfile_path(false);
+    let dev_lockfile = pyproject.lockfile_path(true);
     let venv = pyproject.venv_path();
     let py_ver = pyproject.venv_python_version()?;
     let output = cmd.output;
 
+    // all members of a workspace share the virtualenv, so its Python version
+    // has to satisfy every member.
+    if let Some(workspace) = pyproject.workspace() {
+        workspace.requires_python()?;
+        let unsupported = workspace.unsupported_by_members(&py_ver)?;
+        if !unsupported.is_empty() {
+            bail!(
+                "Python {} is not supported by all workspace members:\n  {}",
+                py_ver,
+                unsupported.join("\n  ")
+            );
+        }
+    }
+
     if cmd.pyproject.is_some()
         && cmd.mode != SyncMode::PythonOnly
         && !pyproject.toml_path().ends_with("pyproject.toml")
@@ -107,9 +256,37 @@ pub fn sync(mut cmd: SyncOptions) -> Result<(), Error> {
     if pyproject.lock_with_sources() {
         cmd.lock_options.with_sources = true;
     }
+    if cmd.lock_options.exclude_newer.is_none() {
+        cmd.lock_options.exclude_newer = pyproject.exclude_newer()?;
+    }
+
+    // if none of the inputs changed since the last sync there is nothing to
+    // do.  This only reads files, so it goes before everything else.
+    if is_venv_up_to_date(&pyproject, &venv, &py_ver, &cmd)? {
+        echo!(if output, "Virtualenv is up to date");
+        return Ok(());
+    }
 
     // ensure we are bootstrapped
-    let self_venv = ensure_self_venv(output).context("could not sync because bootstrap failed")?;
+    let self_venv = {
+        let _phase = timings::phase("bootstrap");
+        ensure_self_venv(output).context("could not sync because bootstrap failed")?
+    };
+
+    if output != CommandOutput::Quiet && Config::current().toolchain_upgrade_check() {
+        check_toolchain_upgrades();
+    }
+
+    // concurrent syncs of the same virtualenv wait for each other.  Only
+    // checking that the virtualenv exists (as the shims do) needs no lock.
+    let _lock = if cmd.mode != SyncMode::PythonOnly || !venv.is_dir() {
+        Some(FileLock::for_venv(&venv, output)?)
+    } else {
+        None
+    };
+
+    // a recreation that was interrupted left the old virtualenv behind.
+    VenvBackup::recover(&venv)?;
 
     let mut recreate = cmd.mode == SyncMode::Full;
     if venv.is_dir() {
@@ -122,6 +299,30 @@ pub fn sync(mut cmd: SyncOptions) -> Result<(), Error> {
                     py_ver
                 );
                 recreate = true;
+            } else if marker.toolchain.is_some()
+                && get_toolchain_fingerprint(&py_ver) != marker.toolchain
+            {
+                // the toolchain was re-downloaded or replaced underneath the
+                // virtualenv which still points to the old one.
+                if cmd.yes
+                    || !Term::stderr().is_term()
+                    || dialoguer::Confirm::with_theme(tui_theme())
+                        .with_prompt(format!(
+                            "Toolchain {} changed since the virtualenv was created. Recreate it?",
+                            py_ver
+                        ))
+                        .default(true)
+                        .interact()?
+                {
+                    echo!(
+                        if cmd.output,
+                        "Toolchain {} changed, recreating.",
+                        py_ver
+                    );
+                    recreate = true;
+                } else {
+                    warn!("virtualenv refers to a toolchain that changed since it was created");
+                }
             } else if let Some(ref venv_path) = marker.venv_path {
                 // for virtualenvs that have a location identifier, check if we need to
                 // recreate it.  On IO error we know that one of the paths is gone, so
@@ -148,13 +349,19 @@ pub fn sync(mut cmd: SyncOptions) -> Result<(), Error> {
     }
 
     // make sure we have a compatible python version
-    let py_ver = fetch(&py_ver.into(), FetchOptions::with_output(output))
-        .context("failed fetching toolchain ahead of sync")?;
+    let py_ver = {
+        let _phase = timings::phase("toolchain check");
+        fetch(&py_ver.into(), FetchOptions::with_output(output))
+            .context("failed fetching toolchain ahead of sync")?
+    };
 
-    // kill the virtualenv if it's there and we need to get rid of it.
-    if recreate && venv.is_dir() {
-        fs::remove_dir_all(&venv).path_context(&venv, "failed to delete existing virtualenv")?;
-    }
+    // move the virtualenv out of the way if we need to get rid of it.  It's
+    // only deleted once the new one was successfully installed.
+    let mut backup = if recreate && venv.is_dir() {
+        Some(VenvBackup::new(&venv)?)
+    } else {
+        None
+    };
 
     if venv.is_dir() {
         // we only care about this output if regular syncs are used
@@ -169,8 +376,21 @@ pub fn sync(mut cmd: SyncOptions) -> Result<(), Error> {
         );
         echo!(if output, "Python version: {}", style(&py_ver).cyan());
         let prompt = pyproject.name().unwrap_or("venv");
+        let _phase = timings::phase("create virtualenv");
         create_virtualenv(output, &self_venv, &py_ver, &venv, prompt)
             .context("failed creating virtualenv ahead of sync")?;
+        if let Err(err) = register_venv_in_registry(&venv, prompt, &py_ver) {
+            warn!(
+                "unable to register virtualenv in the Windows registry: {}",
+                err
+            );
+        }
+    }
+
+    // projects that take their version from git need their version file
+    // before they are installed.
+    if cmd.mode != SyncMode::PythonOnly {
+        write_vcs_version_files(&pyproject)?;
     }
 
     // prepare necessary utilities for pip-sync.  This is a super crude
@@ -178,6 +398,18 @@ pub fn sync(mut cmd: SyncOptions) -> Result<(), Error> {
     // into a folder all by itself and place a second file in there which we
     // can pass to pip-sync to install the local package.
     if recreate || cmd.mode != SyncMode::PythonOnly {
+        // another sync might have finished while we were waiting for the lock.
+        if !recreate && is_venv_up_to_date(&pyproject, &venv, &py_ver, &cmd)? {
+            echo!(if output, "Virtualenv is up to date");
+            return Ok(());
+        }
+
+        let run_hooks = matches!(cmd.mode, SyncMode::Regular | SyncMode::Full);
+        if run_hooks {
+            let _phase = timings::phase("pre-sync hook");
+            run_hook(&pyproject, "pre-sync", output)?;
+        }
+
         let sources = ExpandedSources::from_sources(&pyproject.sources()?)?;
         if cmd.no_lock {
             let lockfile = if cmd.dev { &dev_lockfile } else { &lockfile };
@@ -187,128 +419,1674 @@ pub fn sync(mut cmd: SyncOptions) -> Result<(), Error> {
                     lockfile.display()
                 );
             }
-        } else if let Some(workspace) = pyproject.workspace() {
-            // make sure we have an up-to-date lockfile
-            update_workspace_lockfile(
-                &py_ver,
-                workspace,
-                LockMode::Production,
-                &lockfile,
-                cmd.output,
-                &sources,
-                &cmd.lock_options,
-            )
-            .context("could not write production lockfile for workspace")?;
-            update_workspace_lockfile(
-                &py_ver,
-                workspace,
-                LockMode::Dev,
-                &dev_lockfile,
-                cmd.output,
-                &sources,
-                &cmd.lock_options,
-            )
-            .context("could not write dev lockfile for workspace")?;
         } else {
-            // make sure we have an up-to-date lockfile
-            update_single_project_lockfile(
-                &py_ver,
+            // make sure we have up-to-date lockfiles
+            let lock = |lock_mode: LockMode| -> Result<(), Error> {
+                let lockfile = match lock_mode {
+                    LockMode::Production => &lockfile,
+                    LockMode::Dev => &dev_lockfile,
+                };
+                let _phase = timings::phase(format!("resolve ({})", lock_mode));
+                if let Some(workspace) = pyproject.workspace() {
+                    update_workspace_lockfile(
+                        &py_ver,
+                        workspace,
+                        lock_mode,
+                        lockfile,
+                        cmd.output,
+                        &sources,
+                        &cmd.lock_options,
+                        None,
+                    )
+                    .with_context(|| {
+                        format!("could not write {} lockfile for workspace", lock_mode)
+                    })
+                } else {
+                    update_single_project_lockfile(
+                        &py_ver,
+                        &pyproject,
+                        lock_mode,
+                        lockfile,
+                        cmd.output,
+                        &sources,
+                        &cmd.lock_options,
+                    )
+                    .with_context(|| format!("could not write {} lockfile for project", lock_mode))
+                }
+            };
+            // the production and dev lockfiles do not depend on each other.
+            if cmd.jobs > 1 {
+                thread::scope(|scope| {
+                    let dev = scope.spawn(|| lock(LockMode::Dev));
+                    let production = lock(LockMode::Production);
+                    production.and(dev.join().unwrap())
+                })?;
+            } else {
+                lock(LockMode::Production)?;
+                lock(LockMode::Dev)?;
+            }
+        }
+
+        // run pip install with the lockfile.
+        if cmd.mode != SyncMode::LockOnly {
+            echo!(if output, "Installing dependencies");
+
+            let dev = cmd.dev && dev_lockfile.is_file();
+            let target_lockfile = if dev { dev_lockfile } else { lockfile };
+            if let Some(platform) = read_locked_python_platform(&target_lockfile)? {
+                if !is_host_platform(&platform) {
+                    bail!(
+                        "{} was locked for {} which is not this platform. Use `rye lock` to only update the lockfile.",
+                        target_lockfile.display(),
+                        platform
+                    );
+                }
+            }
+            let scope_dir = tempdir()?;
+            let target_lockfile = scope_lockfile(
                 &pyproject,
-                LockMode::Production,
-                &lockfile,
-                cmd.output,
-                &sources,
-                &cmd.lock_options,
-            )
-            .context("could not write production lockfile for project")?;
-            update_single_project_lockfile(
+                &target_lockfile,
+                &cmd.scope,
+                dev,
+                cmd.no_install_project,
+                scope_dir.path(),
+            )?;
+
+            let mut sources = sources;
+            fill_wheel_cache(&sources, &target_lockfile, &py_ver, output)?;
+            if use_wheel_cache(
+                &mut sources,
+                &target_lockfile,
                 &py_ver,
+                &scope_dir.path().join("wheels"),
+            )? {
+                echo!(if verbose output, "All packages are in the wheel cache, not using the indexes");
+            }
+
+            let mut reinstall = find_outdated_git_checkouts(&venv, &target_lockfile)?;
+            let editable_hashes = {
+                let _phase = timings::phase("editable check");
+                compute_editable_hashes(&pyproject)?
+            };
+            let installed_hashes = read_editable_hashes(&venv);
+            let mut unchanged = HashSet::new();
+            for (name, hash) in &editable_hashes {
+                if installed_hashes.get(name) != Some(hash) {
+                    echo!(if verbose output, "Rebuilding {} as its build inputs changed", name);
+                    reinstall.push(name.clone());
+                } else {
+                    unchanged.insert(name.as_str());
+                }
+            }
+            let target_lockfile = pin_unchanged_editables(
                 &pyproject,
-                LockMode::Dev,
-                &dev_lockfile,
-                cmd.output,
-                &sources,
-                &cmd.lock_options,
+                &venv,
+                &target_lockfile,
+                &unchanged,
+                scope_dir.path(),
+            )?;
+            {
+                let _phase = timings::phase("install");
+                install_lockfile(
+                    &pyproject,
+                    &venv,
+                    &py_ver,
+                    &target_lockfile,
+                    &self_venv,
+                    sources,
+                    &reinstall,
+                    output,
+                )?;
+            }
+            let editable_hashes_path = venv.join(EDITABLE_HASHES_FILENAME);
+            fs::write(
+                &editable_hashes_path,
+                serde_json::to_string(&editable_hashes)?,
             )
-            .context("could not write dev lockfile for project")?;
+            .path_context(&editable_hashes_path, "unable to write editable hashes")?;
+
+            SyncState::new(&pyproject, &py_ver, &cmd)?.write(&venv)?;
+            if let Some(backup) = backup.take() {
+                backup.commit();
+            }
+
+            if run_hooks {
+                let _phase = timings::phase("post-sync hook");
+                run_hook(&pyproject, "post-sync", output)?;
+            }
+        };
+    }
+
+    if let Some(backup) = backup.take() {
+        backup.commit();
+    }
+    if cmd.mode != SyncMode::PythonOnly {
+        echo!(if output, "Done!");
+    }
+
+    Ok(())
+}
+
+/// Keeps the previous virtualenv around while it's being recreated.
+///
+/// Virtualenvs cannot be relocated (the scripts in them refer to their
+/// absolute path), so rather than building the new one elsewhere and swapping
+/// it in, the old one is moved aside and restored if the recreation fails.
+struct VenvBackup {
+    venv: PathBuf,
+    backup: PathBuf,
+    committed: bool,
+}
+
+impl VenvBackup {
+    fn backup_path(venv: &Path) -> PathBuf {
+        let mut filename = venv.file_name().unwrap_or_default().to_os_string();
+        filename.push(".rye-backup");
+        venv.with_file_name(filename)
+    }
+
+    /// Moves the virtualenv aside.
+    fn new(venv: &Path) -> Result<VenvBackup, Error> {
+        let backup = Self::backup_path(venv);
+        if backup.is_dir() {
+            fs::remove_dir_all(&backup)
+                .path_context(&backup, "failed to delete old virtualenv backup")?;
         }
+        fs::rename(venv, &backup).path_context(venv, "failed to move existing virtualenv")?;
+        Ok(VenvBackup {
+            venv: venv.to_path_buf(),
+            backup,
+            committed: false,
+        })
+    }
 
-        // run pip install with the lockfile.
-        if cmd.mode != SyncMode::LockOnly {
-            echo!(if output, "Installing dependencies");
+    /// Restores the backup of an interrupted recreation.
+    fn recover(venv: &Path) -> Result<(), Error> {
+        let backup = Self::backup_path(venv);
+        if !backup.is_dir() {
+            return Ok(());
+        }
+        if venv.join(SYNC_STATE_FILENAME).is_file() {
+            // the recreation went through, the backup is just left over.
+            fs::remove_dir_all(&backup)
+                .path_context(&backup, "failed to delete old virtualenv backup")?;
+        } else {
+            warn!("restoring virtualenv after an interrupted recreation");
+            if venv.is_dir() {
+                fs::remove_dir_all(venv)
+                    .path_context(venv, "failed to delete incomplete virtualenv")?;
+            }
+            fs::rename(&backup, venv).path_context(venv, "failed to restore virtualenv")?;
+        }
+        Ok(())
+    }
 
-            let target_lockfile = if cmd.dev && dev_lockfile.is_file() {
-                dev_lockfile
-            } else {
-                lockfile
+    /// Deletes the old virtualenv.
+    fn commit(mut self) {
+        self.committed = true;
+        fs::remove_dir_all(&self.backup).ok();
+    }
+}
+
+impl Drop for VenvBackup {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        if self.venv.is_dir() {
+            fs::remove_dir_all(&self.venv).ok();
+        }
+        if fs::rename(&self.backup, &self.venv).is_ok() {
+            warn!("recreating the virtualenv failed, restored the previous one");
+        }
+    }
+}
+
+/// Installs the packages of a lockfile into a virtualenv.
+///
+/// Packages in `reinstall` are removed first so that they are installed again
+/// even if their version did not change.
+#[allow(clippy::too_many_arguments)]
+pub fn install_lockfile(
+    pyproject: &PyProject,
+    venv: &Path,
+    py_ver: &PythonVersion,
+    lockfile: &Path,
+    self_venv: &Path,
+    sources: ExpandedSources,
+    reinstall: &[String],
+    output: CommandOutput,
+) -> Result<(), Error> {
+    let tempdir = tempdir()?;
+    let py_path = get_venv_python_bin(venv);
+    if Config::current().use_uv() {
+        UvBuilder::new()
+            .with_output(output.quieter())
+            .with_workdir(&pyproject.workspace_path())
+            .with_sources(sources)
+            .ensure_exists()?
+            .venv(venv, &py_path, py_ver, None)?
+            .with_output(output)
+            .sync(lockfile, reinstall)?;
+    } else {
+        // pip-sync only compares versions, so git checkouts that moved to
+        // a different commit and editables whose build inputs changed need
+        // to be removed first.
+        if !reinstall.is_empty() {
+            let status = Command::new(self_venv.join(VENV_BIN).join("pip"))
+                .arg("--python")
+                .arg(&py_path)
+                .arg("uninstall")
+                .arg("-y")
+                .arg("-q")
+                .args(reinstall)
+                .env("PIP_DISABLE_PIP_VERSION_CHECK", "1")
+                .status()
+                .context("unable to uninstall packages that need to be reinstalled")?;
+            if !status.success() {
+                bail!("failed to uninstall packages that need to be reinstalled");
+            }
+        }
+
+        let mut pip_sync_cmd = Command::new(get_pip_sync(py_ver, output)?);
+        let root = pyproject.workspace_path();
+
+        // we need to run this after we have run the `get_pip_sync` command
+        // as this is what bootstraps or updates the pip tools installation.
+        // This is needed as on unix platforms we need to search the module path.
+        symlink_dir(
+            get_pip_module(&get_pip_tools_venv_path(py_ver)).context("could not locate pip")?,
+            tempdir.path().join("pip"),
+        )
+        .context("failed linking pip module into for pip-sync")?;
+
+        pip_sync_cmd
+            .env("PROJECT_ROOT", make_project_root_fragment(&root))
+            .env("PYTHONPATH", tempdir.path())
+            .current_dir(&root)
+            .arg("--python-executable")
+            .arg(&py_path)
+            .arg("--pip-args")
+            .arg("--no-deps");
+
+        if output != CommandOutput::Quiet {
+            pip_sync_cmd.env("PYTHONWARNINGS", "ignore");
+        } else if output == CommandOutput::Verbose && env::var("PIP_VERBOSE").is_err() {
+            pip_sync_cmd.env("PIP_VERBOSE", "2");
+        }
+
+        sources.add_as_pip_args(&mut pip_sync_cmd);
+
+        pip_sync_cmd.arg(lockfile);
+
+        if output == CommandOutput::Verbose {
+            pip_sync_cmd.arg("--verbose");
+        } else if output == CommandOutput::Quiet {
+            pip_sync_cmd.arg("-q");
+        }
+        set_proxy_variables(&mut pip_sync_cmd);
+        let status = pip_sync_cmd.status().context("unable to run pip-sync")?;
+
+        if !status.success() {
+            bail!("Installation of dependencies failed");
+        }
+    }
+
+    Ok(())
+}
+
+/// Hints at newer patch releases of installed toolchains.
+///
+/// This checks at most once per interval and never fails the sync.
+fn check_toolchain_upgrades() {
+    let stamp = get_app_dir().join("toolchain-upgrade-check");
+    let checked_recently = fs::metadata(&stamp)
+        .and_then(|x| x.modified())
+        .ok()
+        .and_then(|x| SystemTime::now().duration_since(x).ok())
+        .map_or(false, |x| x < TOOLCHAIN_UPGRADE_CHECK_INTERVAL);
+    if checked_recently {
+        return;
+    }
+    fs::write(&stamp, "").ok();
+
+    let upgrades = find_toolchain_upgrades().unwrap_or_default();
+    if upgrades.is_empty() {
+        return;
+    }
+    warn!(
+        "newer patch releases of toolchains are available ({}), run `rye toolchain upgrade`",
+        upgrades
+            .iter()
+            .map(|(old, new)| format!("{} -> {}", old, new))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
+/// Writes the version files of all projects that take their version from git.
+fn write_vcs_version_files(pyproject: &PyProject) -> Result<(), Error> {
+    match pyproject.workspace() {
+        Some(workspace) => {
+            for project in workspace.iter_projects() {
+                project?.write_vcs_version_file()?;
+            }
+        }
+        None => {
+            pyproject.write_vcs_version_file()?;
+        }
+    }
+    Ok(())
+}
+
+/// Computes a hash over all inputs of a sync.
+///
+/// This covers the `pyproject.toml` files, the lockfiles, the sources and
+/// the Rye, Python and uv versions.  The options of the sync are recorded
+/// next to it in [`SyncState`].
+fn compute_sync_hash(pyproject: &PyProject, py_ver: &PythonVersion) -> Result<String, Error> {
+    let mut files = Vec::new();
+    match pyproject.workspace() {
+        Some(workspace) => {
+            for project in workspace.iter_projects() {
+                files.push(project?.toml_path().into_owned());
+            }
+        }
+        None => files.push(pyproject.toml_path().into_owned()),
+    }
+    files.sort();
+    files.push(pyproject.lockfile_path(false));
+    files.push(pyproject.lockfile_path(true));
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update(b"\0");
+        if file.is_file() {
+            hasher.update(fs::read(&file).path_context(&file, "unable to read file")?);
+        }
+        hasher.update(b"\0");
+    }
+    for source in pyproject.sources()? {
+        hasher.update(format!("{}={} ({})\0", source.name, source.url, source.ty).as_bytes());
+    }
+    let uv_version = if Config::current().use_uv() {
+        UvDownload::try_from(UvRequest::default())
+            .map(|x| x.version())
+            .ok()
+    } else {
+        None
+    };
+    hasher.update(
+        format!(
+            "rye={};python={};uv={:?}",
+            env!("CARGO_PKG_VERSION"),
+            py_ver,
+            uv_version,
+        )
+        .as_bytes(),
+    );
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// What a virtualenv was last synced from.
+///
+/// If this matches for a new sync, the sync would not change anything.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct SyncState {
+    /// The hash over the inputs, see [`compute_sync_hash`].
+    hash: String,
+    /// Were the dev dependencies installed?
+    dev: bool,
+    /// The enabled features.
+    features: Vec<String>,
+    /// Were all features enabled?
+    all_features: bool,
+    /// The lockfile that was installed.
+    lockfile: PathBuf,
+    /// The remaining options that influence what gets installed.
+    options: String,
+}
+
+impl SyncState {
+    fn new(
+        pyproject: &PyProject,
+        py_ver: &PythonVersion,
+        cmd: &SyncOptions,
+    ) -> Result<Self, Error> {
+        let dev = cmd.dev && pyproject.lockfile_path(true).is_file();
+        let mut features = cmd.lock_options.features.clone();
+        features.sort();
+        features.dedup();
+        // these only affect how the lockfiles are updated, which is already
+        // covered by the hash of the lockfiles.
+        let lock_options = LockOptions {
+            update_all: false,
+            update: Vec::new(),
+            explain: false,
+            reset: false,
+            ..cmd.lock_options.clone()
+        };
+        Ok(SyncState {
+            hash: compute_sync_hash(pyproject, py_ver)?,
+            dev,
+            features,
+            all_features: cmd.lock_options.all_features,
+            lockfile: pyproject.lockfile_path(dev),
+            options: format!(
+                "no-lock={};scope={:?};no-install-project={};lock-options={}",
+                cmd.no_lock,
+                cmd.scope,
+                cmd.no_install_project,
+                serde_json::to_string(&lock_options)?,
+            ),
+        })
+    }
+
+    /// Reads the state of the last sync from the virtualenv.
+    fn read(venv: &Path) -> Option<SyncState> {
+        fs::read_to_string(venv.join(SYNC_STATE_FILENAME))
+            .ok()
+            .and_then(|x| serde_json::from_str(&x).ok())
+    }
+
+    /// Records the state in the virtualenv.
+    fn write(&self, venv: &Path) -> Result<(), Error> {
+        let path = venv.join(SYNC_STATE_FILENAME);
+        fs::write(&path, serde_json::to_string(self)?)
+            .path_context(&path, "unable to write sync state")
+    }
+}
+
+/// Checks if a regular sync would leave the virtualenv as it is.
+fn is_venv_up_to_date(
+    pyproject: &PyProject,
+    venv: &Path,
+    py_ver: &PythonVersion,
+    cmd: &SyncOptions,
+) -> Result<bool, Error> {
+    if cmd.mode != SyncMode::Regular
+        || cmd.lock_options.update_all
+        || !cmd.lock_options.update.is_empty()
+    {
+        return Ok(false);
+    }
+    let marker = match read_venv_marker(venv) {
+        Some(marker) => marker,
+        None => return Ok(false),
+    };
+    if marker.python != *py_ver
+        || (marker.toolchain.is_some() && get_toolchain_fingerprint(py_ver) != marker.toolchain)
+        || marker
+            .venv_path
+            .as_ref()
+            .map_or(false, |x| !is_same_file(venv, x).unwrap_or(false))
+    {
+        return Ok(false);
+    }
+    Ok(
+        SyncState::read(venv) == Some(SyncState::new(pyproject, py_ver, cmd)?)
+            && has_installed_packages(venv),
+    )
+}
+
+/// Checks if a virtualenv has packages installed.
+///
+/// A site-packages folder that was deleted or emptied behind Rye's back
+/// needs a sync even if the inputs did not change.
+fn has_installed_packages(venv: &Path) -> bool {
+    crate::bootstrap::get_site_packages(venv)
+        .ok()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .map_or(false, |mut entries| entries.next().is_some())
+}
+
+/// Hashes the build inputs of every local project.
+///
+/// The build inputs are the files that configure the build backend and the
+/// layout of the sources, which decides what an editable install exposes.
+/// Editables are only rebuilt if their hash changed, edits to the sources
+/// themselves are picked up without a rebuild.
+fn compute_editable_hashes(pyproject: &PyProject) -> Result<BTreeMap<String, String>, Error> {
+    let mut rv = BTreeMap::new();
+    for_each_project(pyproject, |project| {
+        // virtual projects are not installed
+        let name = match project.name() {
+            Some(name) if !project.is_virtual() => normalize_package_name(name),
+            _ => return Ok(()),
+        };
+        let root = project.root_path();
+        let mut hasher = Sha256::new();
+        for filename in EDITABLE_BUILD_INPUTS {
+            let path = root.join(filename);
+            if path.is_file() {
+                hasher.update(filename.as_bytes());
+                hasher.update(b"\0");
+                hasher.update(fs::read(&path).path_context(&path, "unable to read file")?);
+                hasher.update(b"\0");
+            }
+        }
+        let src = root.join("src");
+        let layout_root = if src.is_dir() {
+            src
+        } else {
+            root.to_path_buf()
+        };
+        let mut entries = fs::read_dir(&layout_root)
+            .path_context(&layout_root, "unable to read project folder")?
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| !name.starts_with('.'))
+            .collect::<Vec<_>>();
+        entries.sort();
+        for entry in entries {
+            hasher.update(entry.as_bytes());
+            hasher.update(b"\0");
+        }
+        rv.insert(name, hex::encode(hasher.finalize()));
+        Ok(())
+    })?;
+    Ok(rv)
+}
+
+/// Keeps the installer from rebuilding editables whose build inputs did not change.
+///
+/// Both uv and pip-sync rebuild every editable requirement.  The editables in
+/// `unchanged` that are already installed in editable mode are therefore
+/// replaced by a pin on their installed version, which the installer finds
+/// to be satisfied.  Returns the lockfile to install from.
+fn pin_unchanged_editables(
+    pyproject: &PyProject,
+    venv: &Path,
+    lockfile: &Path,
+    unchanged: &HashSet<&str>,
+    dir: &Path,
+) -> Result<PathBuf, Error> {
+    let installed = list_installed_dists(venv)
+        .into_iter()
+        .filter(|dist| unchanged.contains(dist.name.as_str()) && dist.is_editable())
+        .map(|dist| (dist.name, dist.version))
+        .collect::<HashMap<_, _>>();
+    if installed.is_empty() {
+        return Ok(lockfile.to_path_buf());
+    }
+
+    let mut local_projects = HashMap::new();
+    let workspace_path = pyproject.workspace_path();
+    for_each_project(pyproject, |project| {
+        local_projects.insert(
+            make_relative_url(&project.root_path(), &workspace_path)?,
+            project.normalized_name()?,
+        );
+        Ok(())
+    })?;
+
+    let contents =
+        fs::read_to_string(lockfile).path_context(lockfile, "unable to read lockfile")?;
+    let mut pinned = false;
+    let mut rv = String::new();
+    for line in contents.lines() {
+        let pin = line
+            .strip_prefix("-e ")
+            .map(|url| url.split('[').next().unwrap_or(url).trim())
+            .and_then(|url| local_projects.get(url))
+            .and_then(|name| Some((name, installed.get(name)?)));
+        match pin {
+            Some((name, version)) => {
+                rv.push_str(&format!("{}=={}\n", name, version));
+                pinned = true;
+            }
+            None => {
+                rv.push_str(line);
+                rv.push('\n');
+            }
+        }
+    }
+    if !pinned {
+        return Ok(lockfile.to_path_buf());
+    }
+    let path = dir.join("editables-pinned.lock");
+    fs::write(&path, rv).path_context(&path, "unable to write lockfile")?;
+    Ok(path)
+}
+
+/// Reads the build input hashes of the local projects installed into a virtualenv.
+fn read_editable_hashes(venv: &Path) -> BTreeMap<String, String> {
+    fs::read_to_string(venv.join(EDITABLE_HASHES_FILENAME))
+        .ok()
+        .and_then(|x| serde_json::from_str(&x).ok())
+        .unwrap_or_default()
+}
+
+/// The state of a project's virtualenv compared to its inputs.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VenvStatus {
+    /// There is no virtualenv.
+    Missing,
+    /// The virtualenv was not created by rye.
+    Unmanaged,
+    /// The virtualenv uses a different Python than the project asks for.
+    PythonMismatch,
+    /// The inputs changed since the last sync.
+    Outdated,
+    /// A sync would not change anything.
+    InSync,
+}
+
+/// Determines the status of the project's virtualenv without modifying it.
+///
+/// The virtualenv is in sync if none of the inputs changed since the last
+/// sync, which is compared for the options that sync was run with.
+pub fn venv_status(pyproject: &PyProject) -> Result<VenvStatus, Error> {
+    let venv = pyproject.venv_path();
+    if !venv.is_dir() {
+        return Ok(VenvStatus::Missing);
+    }
+    let marker = match read_venv_marker(&venv) {
+        Some(marker) => marker,
+        None => return Ok(VenvStatus::Unmanaged),
+    };
+    if !marker.is_compatible(&pyproject.venv_python_version()?) {
+        return Ok(VenvStatus::PythonMismatch);
+    }
+    let state = match SyncState::read(&venv) {
+        Some(state) => state,
+        None => return Ok(VenvStatus::Outdated),
+    };
+    if state.hash == compute_sync_hash(pyproject, &marker.python)?
+        && state.lockfile == pyproject.lockfile_path(state.dev)
+        && has_installed_packages(&venv)
+    {
+        Ok(VenvStatus::InSync)
+    } else {
+        Ok(VenvStatus::Outdated)
+    }
+}
+
+#[derive(Deserialize)]
+struct DirectUrl {
+    vcs_info: Option<VcsInfo>,
+    dir_info: Option<DirInfo>,
+    archive_info: Option<ArchiveInfo>,
+}
+
+#[derive(Deserialize)]
+struct ArchiveInfo {
+    /// The deprecated `<algorithm>=<digest>` form of `hashes`.
+    hash: Option<String>,
+    #[serde(default)]
+    hashes: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct DirInfo {
+    #[serde(default)]
+    editable: bool,
+}
+
+#[derive(Deserialize)]
+struct VcsInfo {
+    commit_id: Option<String>,
+}
+
+/// A distribution installed into a virtualenv.
+struct InstalledDist {
+    name: String,
+    version: String,
+    path: PathBuf,
+}
+
+impl InstalledDist {
+    fn direct_url(&self) -> Option<DirectUrl> {
+        fs::read(self.path.join("direct_url.json"))
+            .ok()
+            .and_then(|x| serde_json::from_slice::<DirectUrl>(&x).ok())
+    }
+
+    /// Returns the commit a distribution was installed from if it came from version control.
+    fn commit_id(&self) -> Option<String> {
+        self.direct_url()
+            .and_then(|x| x.vcs_info)
+            .and_then(|x| x.commit_id)
+    }
+
+    /// Returns the hashes of the archive a distribution was installed from.
+    ///
+    /// The hashes are formatted as `<algorithm>:<digest>` like in lockfiles.
+    /// They are only known if the installer recorded them.
+    fn archive_hashes(&self) -> Vec<String> {
+        let info = match self.direct_url().and_then(|x| x.archive_info) {
+            Some(info) => info,
+            None => return Vec::new(),
+        };
+        let mut rv: Vec<String> = info
+            .hashes
+            .into_iter()
+            .map(|(algorithm, digest)| format!("{}:{}", algorithm, digest))
+            .collect();
+        if let Some((algorithm, digest)) = info.hash.as_deref().and_then(|x| x.split_once('=')) {
+            rv.push(format!("{}:{}", algorithm, digest));
+        }
+        rv
+    }
+
+    /// Returns the files recorded in `RECORD` that are missing or whose
+    /// contents do not match their recorded sha256 hash.
+    fn modified_files(&self) -> Vec<String> {
+        let site_packages = match self.path.parent() {
+            Some(site_packages) => site_packages,
+            None => return Vec::new(),
+        };
+        let record = fs::read_to_string(self.path.join("RECORD")).unwrap_or_default();
+        let mut rv = Vec::new();
+        for line in record.lines() {
+            let mut fields = line.rsplitn(3, ',');
+            let (hash, path) = match (fields.nth(1), fields.next()) {
+                (Some(hash), Some(path)) => (hash, path.trim_matches('"')),
+                _ => continue,
+            };
+            let expected = match hash.strip_prefix("sha256=") {
+                Some(expected) => expected,
+                None => continue,
             };
+            let matches = fs::read(site_packages.join(path)).map_or(false, |contents| {
+                record_digest(&Sha256::digest(contents)) == expected
+            });
+            if !matches {
+                rv.push(path.to_string());
+            }
+        }
+        rv
+    }
 
-            let tempdir = tempdir()?;
-            let py_path = get_venv_python_bin(&venv);
-            if Config::current().use_uv() {
-                UvBuilder::new()
-                    .with_output(output.quieter())
-                    .with_workdir(&pyproject.workspace_path())
-                    .with_sources(sources)
-                    .ensure_exists()?
-                    .venv(&venv, &py_path, &py_ver, None)?
-                    .with_output(output)
-                    .sync(&target_lockfile)?;
+    /// Checks if a distribution was installed in editable mode.
+    fn is_editable(&self) -> bool {
+        self.direct_url()
+            .and_then(|x| x.dir_info)
+            .map_or(false, |x| x.editable)
+    }
+
+    /// Returns the top level modules a distribution provides.
+    ///
+    /// This prefers `top_level.txt` and otherwise derives the names from the
+    /// paths recorded in `RECORD`.
+    fn top_level_modules(&self) -> Vec<String> {
+        if let Ok(contents) = fs::read_to_string(self.path.join("top_level.txt")) {
+            let rv: Vec<String> = contents
+                .lines()
+                .map(|x| x.trim().replace('/', "."))
+                .filter(|x| !x.is_empty())
+                .map(|x| x.split('.').next().unwrap_or(&x).to_string())
+                .collect();
+            if !rv.is_empty() {
+                return rv;
+            }
+        }
+
+        let mut rv = Vec::new();
+        let record = fs::read_to_string(self.path.join("RECORD")).unwrap_or_default();
+        for line in record.lines() {
+            let path = line.split(',').next().unwrap_or("");
+            let mut components = path.split('/');
+            let first = match components.next() {
+                Some(first) if !first.is_empty() && !first.starts_with("..") => first,
+                _ => continue,
+            };
+            let module = if components.next().is_some() {
+                if first.ends_with(".dist-info")
+                    || first.ends_with(".data")
+                    || first == "__pycache__"
+                {
+                    continue;
+                }
+                first
             } else {
-                let mut pip_sync_cmd = Command::new(get_pip_sync(&py_ver, output)?);
-                let root = pyproject.workspace_path();
-
-                // we need to run this after we have run the `get_pip_sync` command
-                // as this is what bootstraps or updates the pip tools installation.
-                // This is needed as on unix platforms we need to search the module path.
-                symlink_dir(
-                    get_pip_module(&get_pip_tools_venv_path(&py_ver))
-                        .context("could not locate pip")?,
-                    tempdir.path().join("pip"),
-                )
-                .context("failed linking pip module into for pip-sync")?;
-
-                pip_sync_cmd
-                    .env("PROJECT_ROOT", make_project_root_fragment(&root))
-                    .env("PYTHONPATH", tempdir.path())
-                    .current_dir(&root)
-                    .arg("--python-executable")
-                    .arg(&py_path)
-                    .arg("--pip-args")
-                    .arg("--no-deps");
-
-                if output != CommandOutput::Quiet {
-                    pip_sync_cmd.env("PYTHONWARNINGS", "ignore");
-                } else if output == CommandOutput::Verbose && env::var("PIP_VERBOSE").is_err() {
-                    pip_sync_cmd.env("PIP_VERBOSE", "2");
+                match first.split_once('.') {
+                    Some((stem, ext))
+                        if matches!(ext, "py" | "so" | "pyd")
+                            || ext.ends_with(".so")
+                            || ext.ends_with(".pyd") =>
+                    {
+                        stem
+                    }
+                    _ => continue,
                 }
+            };
+            if !rv.iter().any(|x| x == module) {
+                rv.push(module.to_string());
+            }
+        }
+        rv
+    }
+}
+
+/// Encodes a digest like the hashes in `RECORD` (urlsafe base64 without padding).
+fn record_digest(digest: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut rv = String::new();
+    for chunk in digest.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |acc, (idx, byte)| {
+            acc | (*byte as u32) << (16 - 8 * idx)
+        });
+        for idx in 0..=chunk.len() {
+            rv.push(ALPHABET[(n >> (18 - 6 * idx)) as usize & 63] as char);
+        }
+    }
+    rv
+}
+
+/// Lists all distributions installed into a virtualenv.
+fn list_installed_dists(venv: &Path) -> Vec<InstalledDist> {
+    walkdir::WalkDir::new(venv.join("lib"))
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|x| x.ok())
+        .filter_map(|entry| {
+            let filename = entry.file_name().to_string_lossy();
+            let (name, version) = filename
+                .strip_suffix(".dist-info")
+                .and_then(|x| x.split_once('-'))?;
+            Some(InstalledDist {
+                name: normalize_package_name(name),
+                version: version.to_string(),
+                path: entry.path().to_path_buf(),
+            })
+        })
+        .collect()
+}
+
+/// The installed packages and the lockfiles of a project at one point in time.
+///
+/// Comparing the state before and after a sync yields the `--summary`.
+pub struct SyncSnapshot {
+    dists: HashMap<String, String>,
+    lockfiles: Vec<Option<Vec<u8>>>,
+}
+
+impl SyncSnapshot {
+    /// Captures the current state of a project.
+    pub fn capture(pyproject: &PyProject) -> SyncSnapshot {
+        SyncSnapshot {
+            dists: list_installed_dists(&pyproject.venv_path())
+                .into_iter()
+                .map(|dist| (dist.name, dist.version))
+                .collect(),
+            lockfiles: [false, true]
+                .into_iter()
+                .map(|dev| fs::read(pyproject.lockfile_path(dev)).ok())
+                .collect(),
+        }
+    }
 
-                sources.add_as_pip_args(&mut pip_sync_cmd);
+    /// Records what changed since an earlier snapshot.
+    ///
+    /// Packages that were installed in another version count as installed.
+    pub fn summarize(&self, before: &SyncSnapshot, summary: &mut Summary) {
+        summary.installed = self
+            .dists
+            .iter()
+            .filter(|(name, version)| before.dists.get(*name) != Some(*version))
+            .count();
+        summary.removed = before
+            .dists
+            .keys()
+            .filter(|name| !self.dists.contains_key(*name))
+            .count();
+        summary.lock_changed = self.lockfiles != before.lockfiles;
+    }
+}
+
+/// Finds packages installed from git that are not at the commit of the lockfile.
+fn find_outdated_git_checkouts(venv: &Path, lockfile: &Path) -> Result<Vec<String>, Error> {
+    let pins = read_git_pins(
+        &fs::read_to_string(lockfile).path_context(lockfile, "unable to read lockfile")?,
+    );
+    if pins.is_empty() {
+        return Ok(Vec::new());
+    }
 
-                pip_sync_cmd.arg(&target_lockfile);
+    Ok(list_installed_dists(venv)
+        .into_iter()
+        .filter(|dist| {
+            pins.get(&dist.name).map_or(false, |pin| {
+                dist.commit_id().as_deref() != Some(&pin.commit)
+            })
+        })
+        .map(|dist| dist.name)
+        .collect())
+}
+
+/// Returns the top level modules provided by each distribution installed into a virtualenv.
+///
+/// The keys are normalized distribution names.
+pub fn installed_modules(venv: &Path) -> BTreeMap<String, Vec<String>> {
+    list_installed_dists(venv)
+        .into_iter()
+        .map(|dist| {
+            let modules = dist.top_level_modules();
+            (dist.name, modules)
+        })
+        .collect()
+}
 
-                if output == CommandOutput::Verbose {
-                    pip_sync_cmd.arg("--verbose");
-                } else if output == CommandOutput::Quiet {
-                    pip_sync_cmd.arg("-q");
+/// Finds distributions installed into the virtualenv that no lockfile requires.
+///
+/// The dev lockfile is used if it exists as it is a superset of the production
+/// lockfile.  Returns the names and versions of the orphaned distributions.
+pub fn find_orphaned_dists(pyproject: &PyProject) -> Result<Vec<(String, String)>, Error> {
+    let venv = pyproject.venv_path();
+    let dev_lockfile = pyproject.lockfile_path(true);
+    let lockfile = if dev_lockfile.is_file() {
+        dev_lockfile
+    } else {
+        pyproject.lockfile_path(false)
+    };
+    if !venv.is_dir() || !lockfile.is_file() {
+        return Ok(Vec::new());
+    }
+
+    Ok(diff_venv(&venv, &pyproject.workspace_path(), &lockfile)?
+        .into_iter()
+        .filter_map(|change| match change {
+            InstallChange::Uninstall(name, version) => Some((name, version)),
+            _ => None,
+        })
+        .collect())
+}
+
+/// A distribution installed into a virtualenv and how the project relates to it.
+#[derive(Serialize, Debug)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    /// The project (or a workspace member) declares it as a dependency.
+    pub direct: bool,
+    /// Only the dev lockfile contains it.
+    pub dev_only: bool,
+    pub editable: bool,
+    /// None of the lockfiles contains it.
+    pub orphaned: bool,
+}
+
+/// Lists the distributions installed into the virtualenv of a project.
+///
+/// If the project was not locked yet, no package is reported as orphaned.
+pub fn list_installed_packages(pyproject: &PyProject) -> Result<Vec<InstalledPackage>, Error> {
+    let workspace_path = pyproject.workspace_path();
+    let read_locked = |dev: bool| -> Result<Option<BTreeMap<String, Option<Version>>>, Error> {
+        let lockfile = pyproject.lockfile_path(dev);
+        if lockfile.is_file() {
+            Ok(Some(read_locked_packages(&workspace_path, &lockfile)?))
+        } else {
+            Ok(None)
+        }
+    };
+    let production = read_locked(false)?;
+    let dev = read_locked(true)?;
+
+    let mut direct = HashSet::new();
+    for_each_project(pyproject, |project| {
+        let mut kinds = vec![DependencyKind::Normal, DependencyKind::Dev];
+        kinds.extend(
+            project
+                .extras()
+                .into_iter()
+                .map(|x| DependencyKind::Optional(x.into())),
+        );
+        for kind in kinds {
+            for dep in project.iter_dependencies(kind) {
+                if let Ok(req) = dep.expand(|name| env::var(name).ok()) {
+                    direct.insert(normalize_package_name(&req.name));
                 }
-                set_proxy_variables(&mut pip_sync_cmd);
-                let status = pip_sync_cmd.status().context("unable to run pip-sync")?;
+            }
+        }
+        Ok(())
+    })?;
+
+    let is_locked = |locked: &Option<BTreeMap<String, Option<Version>>>, name: &str| {
+        locked.as_ref().map_or(false, |x| x.contains_key(name))
+    };
+    let mut rv = list_installed_dists(&pyproject.venv_path())
+        .into_iter()
+        .map(|dist| InstalledPackage {
+            direct: direct.contains(&dist.name),
+            dev_only: is_locked(&dev, &dist.name) && !is_locked(&production, &dist.name),
+            editable: dist.is_editable(),
+            orphaned: (production.is_some() || dev.is_some())
+                && !is_locked(&production, &dist.name)
+                && !is_locked(&dev, &dist.name),
+            name: dist.name,
+            version: dist.version,
+        })
+        .collect::<Vec<_>>();
+    rv.sort_by(|a, b| a.name.cmp(&b.name));
+    rv.dedup_by(|a, b| a.name == b.name);
+    Ok(rv)
+}
+
+/// A change to a virtualenv that is necessary to match a lockfile.
+enum InstallChange {
+    Install(String, Option<Version>),
+    Uninstall(String, String),
+    Update(String, String, String),
+}
+
+impl fmt::Display for InstallChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstallChange::Install(name, Some(version)) => {
+                write!(f, "{} {}=={}", style("+").green(), name, version)
+            }
+            InstallChange::Install(name, None) => write!(f, "{} {}", style("+").green(), name),
+            InstallChange::Uninstall(name, version) => {
+                write!(f, "{} {}=={}", style("-").red(), name, version)
+            }
+            InstallChange::Update(name, old, new) => {
+                write!(f, "{} {} {} -> {}", style("~").yellow(), name, old, new)
+            }
+        }
+    }
+}
 
-                if !status.success() {
-                    bail!("Installation of dependencies failed");
+/// Locks into a copy of the given lockfile in the scratch folder.
+///
+/// The existing lockfile is copied first so that prior lock options and pins
+/// apply.  The pyproject is used as is, even if it has unsaved modifications.
+fn lock_to_scratch(
+    pyproject: &PyProject,
+    lock_mode: LockMode,
+    lockfile: &Path,
+    scratch: &Path,
+    output: CommandOutput,
+    sources: &ExpandedSources,
+    lock_options: &LockOptions,
+) -> Result<PathBuf, Error> {
+    let scratch_lockfile = scratch.join(lockfile.file_name().unwrap_or_default());
+    if lockfile.is_file() {
+        fs::copy(lockfile, &scratch_lockfile)
+            .path_context(&scratch_lockfile, "unable to copy lockfile")?;
+    }
+    let py_ver = pyproject.venv_python_version()?;
+    let output = if output == CommandOutput::Verbose {
+        output
+    } else {
+        CommandOutput::Quiet
+    };
+    if let Some(workspace) = pyproject.workspace() {
+        update_workspace_lockfile(
+            &py_ver,
+            workspace,
+            lock_mode,
+            &scratch_lockfile,
+            output,
+            sources,
+            lock_options,
+            Some(pyproject),
+        )?;
+    } else {
+        update_single_project_lockfile(
+            &py_ver,
+            pyproject,
+            lock_mode,
+            &scratch_lockfile,
+            output,
+            sources,
+            lock_options,
+        )?;
+    }
+    Ok(scratch_lockfile)
+}
+
+/// Verifies that lockfiles and virtualenv are up to date without modifying them.
+///
+/// The lockfiles are regenerated in a scratch location and compared to the
+/// ones on disk, then the installed packages are compared to the lockfile.
+/// Packages are compared by version (and commit for git dependencies) and
+/// their files are verified with [`verify_installed_files`].  All detected
+/// drift is printed and the function fails with a non zero exit code if
+/// there is any.
+pub fn sync_check(mut cmd: SyncOptions) -> Result<(), Error> {
+    let pyproject =
+        PyProject::load_or_discover(cmd.pyproject.as_deref())?.with_env(cmd.env.as_deref())?;
+    cmd.apply_env(&pyproject);
+    cmd.apply_scope(&pyproject)?;
+    let workspace_path = pyproject.workspace_path();
+    let lockfile = pyproject.lockfile_path(false);
+    let dev_lockfile = pyproject.lockfile_path(true);
+    let venv = pyproject.venv_path();
+    let output = cmd.output;
+    let mut lock_options = cmd.lock_options;
+    if pyproject.lock_with_sources() {
+        lock_options.with_sources = true;
+    }
+    if lock_options.exclude_newer.is_none() {
+        lock_options.exclude_newer = pyproject.exclude_newer()?;
+    }
+    let sources = ExpandedSources::from_sources(&pyproject.sources()?)?;
+    let scratch = tempdir()?;
+    let mut in_sync = true;
+
+    for (lock_mode, path) in [
+        (LockMode::Production, &lockfile),
+        (LockMode::Dev, &dev_lockfile),
+    ] {
+        if !path.is_file() {
+            echo!("Lockfile {} does not exist", style(path.display()).cyan());
+            in_sync = false;
+            continue;
+        }
+
+        let scratch_lockfile = lock_to_scratch(
+            &pyproject,
+            lock_mode,
+            path,
+            scratch.path(),
+            output,
+            &sources,
+            &lock_options,
+        )?;
+        let changes = diff_lockfiles(path, &scratch_lockfile)?;
+        if !changes.is_empty() {
+            echo!(
+                "Lockfile {} is out of date with pyproject.toml:",
+                style(path.display()).cyan()
+            );
+            for change in changes {
+                echo!("  {}", change);
+            }
+            in_sync = false;
+        }
+    }
+
+    let target_lockfile = if cmd.dev && dev_lockfile.is_file() {
+        &dev_lockfile
+    } else {
+        &lockfile
+    };
+    if !venv.is_dir() {
+        echo!("Virtualenv {} does not exist", style(venv.display()).cyan());
+        in_sync = false;
+    } else if target_lockfile.is_file() {
+        let scoped_lockfile = scope_lockfile(
+            &pyproject,
+            target_lockfile,
+            &cmd.scope,
+            target_lockfile == &dev_lockfile,
+            cmd.no_install_project,
+            scratch.path(),
+        )?;
+        let changes = diff_venv(&venv, &workspace_path, &scoped_lockfile)?;
+        if !changes.is_empty() {
+            echo!(
+                "Virtualenv {} does not match {}:",
+                style(venv.display()).cyan(),
+                style(target_lockfile.display()).cyan()
+            );
+            for change in changes {
+                echo!("  {}", change);
+            }
+            in_sync = false;
+        }
+        let problems = verify_installed_files(&venv, &scoped_lockfile)?;
+        if !problems.is_empty() {
+            echo!(
+                "Installed files in {} do not match their hashes:",
+                style(venv.display()).cyan()
+            );
+            for problem in problems {
+                echo!("  {}", problem);
+            }
+            in_sync = false;
+        }
+    }
+
+    if !in_sync {
+        return Err(QuietExit(1).into());
+    }
+    echo!(if output, "Lockfiles and virtualenv are up to date");
+    Ok(())
+}
+
+/// Prints what a sync would do without modifying lockfiles or the virtualenv.
+///
+/// The given pyproject is used as is, which allows `add` and `remove` to
+/// preview their changes without saving them.
+pub fn sync_dry_run(pyproject: &PyProject, mut cmd: SyncOptions) -> Result<(), Error> {
+    cmd.apply_env(pyproject);
+    cmd.apply_scope(pyproject)?;
+    let workspace_path = pyproject.workspace_path();
+    let lockfile = pyproject.lockfile_path(false);
+    let dev_lockfile = pyproject.lockfile_path(true);
+    let venv = pyproject.venv_path();
+    let output = cmd.output;
+    let mut lock_options = cmd.lock_options;
+    if pyproject.lock_with_sources() {
+        lock_options.with_sources = true;
+    }
+    if lock_options.exclude_newer.is_none() {
+        lock_options.exclude_newer = pyproject.exclude_newer()?;
+    }
+    let sources = ExpandedSources::from_sources(&pyproject.sources()?)?;
+    let scratch = tempdir()?;
+
+    let mut target_lockfile = None;
+    for (lock_mode, path) in [
+        (LockMode::Production, &lockfile),
+        (LockMode::Dev, &dev_lockfile),
+    ] {
+        let scratch_lockfile = if cmd.no_lock {
+            if !path.is_file() {
+                bail!(
+                    "Locking is disabled but lockfile '{}' does not exist",
+                    path.display()
+                );
+            }
+            path.to_path_buf()
+        } else {
+            let scratch_lockfile = lock_to_scratch(
+                pyproject,
+                lock_mode,
+                path,
+                scratch.path(),
+                output,
+                &sources,
+                &lock_options,
+            )?;
+            let changes = diff_lockfiles(path, &scratch_lockfile)?;
+            if changes.is_empty() {
+                echo!("Lockfile {} is up to date", style(path.display()).cyan());
+            } else {
+                echo!("Would update lockfile {}:", style(path.display()).cyan());
+                for change in changes {
+                    echo!("  {}", change);
                 }
-            };
+            }
+            scratch_lockfile
         };
+        if (lock_mode == LockMode::Dev) == cmd.dev {
+            target_lockfile = Some(scratch_lockfile);
+        }
     }
 
-    if cmd.mode != SyncMode::PythonOnly {
-        echo!(if output, "Done!");
+    if cmd.mode != SyncMode::LockOnly {
+        if let Some(target_lockfile) = target_lockfile {
+            let target_lockfile = scope_lockfile(
+                pyproject,
+                &target_lockfile,
+                &cmd.scope,
+                cmd.dev,
+                cmd.no_install_project,
+                scratch.path(),
+            )?;
+            let changes = diff_venv(&venv, &workspace_path, &target_lockfile)?;
+            if changes.is_empty() {
+                echo!("Virtualenv {} is up to date", style(venv.display()).cyan());
+            } else {
+                echo!("Would change virtualenv {}:", style(venv.display()).cyan());
+                for change in changes {
+                    echo!("  {}", change);
+                }
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Invokes a function for the project and, in a workspace, all other members.
+fn for_each_project<F>(pyproject: &PyProject, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(&PyProject) -> Result<(), Error>,
+{
+    match pyproject.workspace() {
+        Some(workspace) => {
+            for project in workspace.iter_projects() {
+                f(&project?)?;
+            }
+        }
+        None => f(pyproject)?,
+    }
+    Ok(())
+}
+
+/// A package in a lockfile along with the lines that belong to it.
+struct LockfileEntry<'a> {
+    /// The normalized name, `None` for local projects outside the workspace.
+    name: Option<String>,
+    /// Is this a local (editable) project?
+    local: bool,
+    /// The normalized names of the packages that depend on it.
+    via: Vec<String>,
+    lines: Vec<&'a str>,
+}
+
+/// Writes the part of a lockfile that is installed for a scope into `dir`.
+///
+/// For scopes that install the entire lockfile, the lockfile itself is
+/// returned.  Which packages a dependency group or workspace member needs
+/// is determined from the `# via` annotations of the lockfile.
+fn scope_lockfile(
+    pyproject: &PyProject,
+    lockfile: &Path,
+    scope: &SyncScope,
+    dev: bool,
+    no_install_project: bool,
+    dir: &Path,
+) -> Result<PathBuf, Error> {
+    if *scope == SyncScope::All && !no_install_project {
+        return Ok(lockfile.to_path_buf());
+    }
+
+    // local projects are referenced by their path relative to the workspace.
+    let mut local_projects = HashMap::new();
+    let workspace_path = pyproject.workspace_path();
+    for_each_project(pyproject, |project| {
+        local_projects.insert(
+            make_relative_url(&project.root_path(), &workspace_path)?,
+            project.normalized_name()?,
+        );
+        Ok(())
+    })?;
+
+    let contents =
+        fs::read_to_string(lockfile).path_context(lockfile, "unable to read lockfile")?;
+    let mut header = Vec::new();
+    let mut entries: Vec<LockfileEntry> = Vec::new();
+    for line in contents.lines() {
+        if line.starts_with(' ') {
+            match entries.last_mut() {
+                Some(entry) => {
+                    if let Some(parent) = line.trim().strip_prefix("# via ") {
+                        let parent = parent.split('[').next().unwrap_or(parent);
+                        entry.via.push(normalize_package_name(parent.trim()));
+                    }
+                    entry.lines.push(line);
+                }
+                None => header.push(line),
+            }
+        } else if let Some(url) = line.strip_prefix("-e ") {
+            let url = url.split('[').next().unwrap_or(url).trim();
+            entries.push(LockfileEntry {
+                name: local_projects.get(url).cloned(),
+                local: true,
+                via: Vec::new(),
+                lines: vec![line],
+            });
+        } else if let Ok(req) = strip_continuation(line).parse::<Requirement>() {
+            entries.push(LockfileEntry {
+                name: Some(normalize_package_name(&req.name)),
+                local: false,
+                via: Vec::new(),
+                lines: vec![line],
+            });
+        } else if entries.is_empty() {
+            header.push(line);
+        }
+    }
+
+    let mut roots = Vec::new();
+    let mut add_roots = |project: &PyProject, kind: DependencyKind| {
+        for dep in project.iter_dependencies(kind) {
+            if let Ok(req) = dep.expand(|name| env::var(name).ok()) {
+                roots.push(normalize_package_name(&req.name));
+            }
+        }
+    };
+    match scope {
+        SyncScope::All => {}
+        SyncScope::OnlyDev => for_each_project(pyproject, |project| {
+            add_roots(project, DependencyKind::Dev);
+            Ok(())
+        })?,
+        SyncScope::Only(ref group) => for_each_project(pyproject, |project| {
+            add_roots(project, DependencyKind::Optional(group.into()));
+            Ok(())
+        })?,
+        // the dependencies of a member are locked `via` the member.  Dev
+        // dependencies are not, and the ones of the workspace root are
+        // shared with all members.
+        SyncScope::Member(ref name) => {
+            let name = normalize_package_name(name);
+            for_each_project(pyproject, |project| {
+                let is_member = project.normalized_name()? == name;
+                if is_member {
+                    add_roots(project, DependencyKind::Normal);
+                }
+                if dev && (is_member || project.is_workspace_root()) {
+                    add_roots(project, DependencyKind::Dev);
+                }
+                Ok(())
+            })?;
+            roots.push(name);
+        }
+    }
+
+    // a package is needed if it's a root or one of the packages depending on
+    // it is needed.
+    let mut needed = roots.into_iter().collect::<HashSet<_>>();
+    loop {
+        let mut changed = false;
+        for entry in &entries {
+            if let Some(ref name) = entry.name {
+                if !needed.contains(name) && entry.via.iter().any(|x| needed.contains(x)) {
+                    needed.insert(name.clone());
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut rv = header.join("\n");
+    for entry in entries {
+        let keep = match (scope, entry.local, entry.name) {
+            (SyncScope::All, true, _) => !no_install_project,
+            (SyncScope::Member(_), true, Some(ref name)) => {
+                !no_install_project && needed.contains(name)
+            }
+            (_, true, _) => false,
+            (SyncScope::All, false, _) => true,
+            (_, false, Some(ref name)) => needed.contains(name),
+            (_, false, None) => false,
+        };
+        if keep {
+            for line in entry.lines {
+                rv.push('\n');
+                rv.push_str(line);
+            }
+        }
+    }
+    rv.push('\n');
+
+    let path = dir.join("scoped.lock");
+    fs::write(&path, rv).path_context(&path, "unable to write lockfile")?;
+    Ok(path)
+}
+
+/// Returns the requirement lines of a lockfile (comments and blank lines removed).
+fn lockfile_requirements(contents: &str) -> Vec<&str> {
+    contents
+        .lines()
+        .map(|x| strip_continuation(x.split(" #").next().unwrap_or("")))
+        .filter(|x| !x.is_empty() && !x.starts_with('#') && !x.starts_with("--hash="))
+        .collect()
+}
+
+/// Compares two lockfiles and returns the removed and added requirements.
+fn diff_lockfiles(old: &Path, new: &Path) -> Result<Vec<String>, Error> {
+    let old = if old.is_file() {
+        fs::read_to_string(old).path_context(old, "unable to read lockfile")?
+    } else {
+        String::new()
+    };
+    let new = fs::read_to_string(new).path_context(new, "unable to read lockfile")?;
+    let old_reqs = lockfile_requirements(&old);
+    let new_reqs = lockfile_requirements(&new);
+    let mut rv = Vec::new();
+    for req in &old_reqs {
+        if !new_reqs.contains(req) {
+            rv.push(format!("{} {}", style("-").red(), req));
+        }
+    }
+    for req in &new_reqs {
+        if !old_reqs.contains(req) {
+            rv.push(format!("{} {}", style("+").green(), req));
+        }
+    }
+    Ok(rv)
+}
+
+/// Reads the packages of a lockfile.
+///
+/// Maps the normalized names to the locked versions.  Editable and URL
+/// requirements have no version.
+pub fn read_locked_packages(
+    workspace_path: &Path,
+    lockfile: &Path,
+) -> Result<BTreeMap<String, Option<Version>>, Error> {
+    let contents =
+        fs::read_to_string(lockfile).path_context(lockfile, "unable to read lockfile")?;
+    let mut locked = BTreeMap::new();
+    let base_url = Url::from_directory_path(workspace_path)
+        .map_err(|_| anyhow!("unable to interpret workspace path as url"))?;
+    for line in lockfile_requirements(&contents) {
+        if let Some(editable) = line.strip_prefix("-e ") {
+            let rel = editable.split('[').next().unwrap_or(editable);
+            let path = base_url
+                .join(rel.trim_start_matches("file:"))
+                .ok()
+                .and_then(|x| x.to_file_path().ok());
+            if let Some(project) =
+                path.and_then(|x| PyProject::load(&x.join("pyproject.toml")).ok())
+            {
+                locked.insert(project.normalized_name()?, None);
+            }
+        } else if line.starts_with('-') {
+            continue;
+        } else if let Ok(req) = line.parse::<Requirement>() {
+            let version = match req.version_or_url {
+                Some(VersionOrUrl::VersionSpecifier(ref specs)) => specs
+                    .iter()
+                    .find(|x| *x.operator() == Operator::Equal)
+                    .map(|x| x.version().clone()),
+                _ => None,
+            };
+            locked.insert(normalize_package_name(&req.name), version);
+        }
+    }
+    Ok(locked)
+}
+
+/// Compares the installed packages of a virtualenv with a lockfile.
+fn diff_venv(
+    venv: &Path,
+    workspace_path: &Path,
+    lockfile: &Path,
+) -> Result<Vec<InstallChange>, Error> {
+    let contents =
+        fs::read_to_string(lockfile).path_context(lockfile, "unable to read lockfile")?;
+    let git_pins = read_git_pins(&contents);
+    let locked = read_locked_packages(workspace_path, lockfile)?;
+
+    let mut rv = Vec::new();
+    let installed = list_installed_dists(venv);
+    for dist in &installed {
+        match locked.get(&dist.name) {
+            None => rv.push(InstallChange::Uninstall(
+                dist.name.clone(),
+                dist.version.clone(),
+            )),
+            Some(Some(version)) => {
+                if dist.version.parse::<Version>().ok().as_ref() != Some(version) {
+                    rv.push(InstallChange::Update(
+                        dist.name.clone(),
+                        dist.version.clone(),
+                        version.to_string(),
+                    ));
+                }
+            }
+            Some(None) => {
+                if let Some(pin) = git_pins.get(&dist.name) {
+                    let commit = dist.commit_id();
+                    if commit.as_deref() != Some(&pin.commit) {
+                        rv.push(InstallChange::Update(
+                            dist.name.clone(),
+                            commit.unwrap_or_else(|| dist.version.clone()),
+                            pin.commit.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    for (name, version) in locked {
+        if !installed.iter().any(|x| x.name == name) {
+            rv.push(InstallChange::Install(name, version));
+        }
+    }
+
+    Ok(rv)
+}
+
+/// Reads the `--hash` entries of a lockfile by normalized package name.
+fn read_locked_hashes(contents: &str) -> HashMap<String, HashSet<String>> {
+    let mut rv = HashMap::<String, HashSet<String>>::new();
+    let mut current = None;
+    for line in contents.lines() {
+        let line = strip_continuation(line.split(" #").next().unwrap_or(""));
+        if let Some(hash) = line.strip_prefix("--hash=") {
+            if let Some(ref name) = current {
+                rv.entry(name.clone()).or_default().insert(hash.to_string());
+            }
+        } else if !line.is_empty() && !line.starts_with('#') {
+            current = line
+                .parse::<Requirement>()
+                .ok()
+                .map(|x| normalize_package_name(&x.name));
+        }
+    }
+    rv
+}
+
+/// Verifies the files of the packages installed into a virtualenv.
+///
+/// Every installed file is checked against the hash in the `RECORD` of its
+/// distribution.  The hashes in the lockfile are the ones of the archives,
+/// they are compared if the installer recorded the hash of the archive a
+/// package was installed from.
+fn verify_installed_files(venv: &Path, lockfile: &Path) -> Result<Vec<String>, Error> {
+    let contents =
+        fs::read_to_string(lockfile).path_context(lockfile, "unable to read lockfile")?;
+    let locked_hashes = read_locked_hashes(&contents);
+
+    let mut rv = Vec::new();
+    for dist in list_installed_dists(venv) {
+        if let Some(locked) = locked_hashes.get(&dist.name) {
+            let archive_hashes = dist.archive_hashes();
+            if !archive_hashes.is_empty() && !archive_hashes.iter().any(|x| locked.contains(x)) {
+                rv.push(format!(
+                    "{} {}=={} was installed from an archive with a hash that is not locked",
+                    style("!").red(),
+                    dist.name,
+                    dist.version
+                ));
+            }
+        }
+        for path in dist.modified_files() {
+            rv.push(format!(
+                "{} {}: {} was modified",
+                style("!").red(),
+                dist.name,
+                path
+            ));
+        }
+    }
+    Ok(rv)
+}
+
 /// Performs an autosync.
 pub fn autosync(pyproject: &PyProject, output: CommandOutput) -> Result<(), Error> {
     sync(SyncOptions {
@@ -319,6 +2097,11 @@ pub fn autosync(pyproject: &PyProject, output: CommandOutput) -> Result<(), Erro
         no_lock: false,
         lock_options: LockOptions::default(),
         pyproject: Some(pyproject.toml_path().to_path_buf()),
+        env: None,
+        yes: false,
+        scope: SyncScope::All,
+        no_install_project: false,
+        jobs: 1,
     })
 }
 
@@ -454,3 +2237,79 @@ fn get_site_packages(lib_dir: PathBuf) -> Result<Option<PathBuf>, Error> {
     }
     Ok(None)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_dist(files: &[(&str, &str)]) -> (tempfile::TempDir, InstalledDist) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("foo-1.0.dist-info");
+        fs::create_dir_all(&path).unwrap();
+        for (name, contents) in files {
+            fs::write(path.join(name), contents).unwrap();
+        }
+        let dist = InstalledDist {
+            name: "foo".into(),
+            version: "1.0".into(),
+            path,
+        };
+        (dir, dist)
+    }
+
+    #[test]
+    fn test_top_level_modules_from_top_level_txt() {
+        let (_dir, dist) = make_dist(&[
+            ("top_level.txt", "foo\n\nfoo_ext/sub\n"),
+            ("RECORD", "bar/__init__.py,,\n"),
+        ]);
+        assert_eq!(dist.top_level_modules(), vec!["foo", "foo_ext"]);
+    }
+
+    #[test]
+    fn test_top_level_modules_from_record() {
+        let (_dir, dist) = make_dist(&[
+            ("top_level.txt", "\n"),
+            (
+                "RECORD",
+                "foo/__init__.py,sha256=abc,10\n\
+                 foo/sub/mod.py,sha256=abc,10\n\
+                 single.py,sha256=abc,10\n\
+                 _speedups.cpython-312-x86_64-linux-gnu.so,sha256=abc,10\n\
+                 _plain.so,sha256=abc,10\n\
+                 _win.cp312-win_amd64.pyd,sha256=abc,10\n\
+                 foo-1.0.dist-info/METADATA,sha256=abc,10\n\
+                 foo-1.0.data/scripts/foo,sha256=abc,10\n\
+                 __pycache__/single.cpython-312.pyc,,\n\
+                 ../../../bin/foo,sha256=abc,10\n\
+                 foo.pth,sha256=abc,10\n\
+                 README.txt,sha256=abc,10\n",
+            ),
+        ]);
+        assert_eq!(
+            dist.top_level_modules(),
+            vec!["foo", "single", "_speedups", "_plain", "_win"]
+        );
+    }
+
+    #[test]
+    fn test_top_level_modules_without_metadata() {
+        let (_dir, dist) = make_dist(&[]);
+        assert!(dist.top_level_modules().is_empty());
+    }
+
+    #[test]
+    fn test_record_digest() {
+        // the line pip writes for an empty `__init__.py`
+        let line = "foo/__init__.py,sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU,0";
+        let expected = line.split(',').nth(1).unwrap().strip_prefix("sha256=");
+        assert_eq!(Some(record_digest(&Sha256::digest(b"")).as_str()), expected);
+        assert_eq!(
+            record_digest(&Sha256::digest(b"print(\"hello\")\n")),
+            "uAeSM2FWx7D3_gLu7yRhDS1SoQ0YEDl3REcdHcVzgYA"
+        );
+        assert_eq!(record_digest(b"\xfb"), "-w");
+        assert_eq!(record_digest(b"\xfb\xff"), "-_8");
+        assert_eq!(record_digest(b"\xfb\xff\xbf"), "-_-_");
+    }
+}
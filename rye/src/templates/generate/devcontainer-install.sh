@@ -0,0 +1,25 @@
+#!/usr/bin/env bash
+# generated by rye, installs rye for the user of the dev container
+set -euo pipefail
+
+USERNAME="${_REMOTE_USER:-root}"
+export RYE_HOME=/usr/local/rye
+
+if ! command -v curl > /dev/null; then
+    apt-get update
+    apt-get install -y --no-install-recommends curl ca-certificates
+    rm -rf /var/lib/apt/lists/*
+fi
+
+mkdir -p "$RYE_HOME"
+chown "$USERNAME" "$RYE_HOME"
+
+RYE_VERSION_ENV=""
+if [ "${VERSION:-latest}" != "latest" ]; then
+    RYE_VERSION_ENV="RYE_VERSION=$VERSION"
+fi
+su "$USERNAME" -c "curl -sSf https://rye-up.com/get | RYE_HOME=$RYE_HOME $RYE_VERSION_ENV RYE_INSTALL_OPTION=--yes bash"
+
+if [ -n "${TOOLCHAIN:-}" ]; then
+    su "$USERNAME" -c "RYE_HOME=$RYE_HOME $RYE_HOME/shims/rye fetch $TOOLCHAIN"
+fi
@@ -0,0 +1,149 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use console::style;
+
+use crate::utils::IoPathContext;
+
+static TIMINGS: Mutex<Option<Timings>> = Mutex::new(None);
+
+/// The phases recorded for `--timings`.
+struct Timings {
+    started: Instant,
+    threads: Vec<ThreadId>,
+    phases: Vec<Phase>,
+}
+
+struct Phase {
+    name: String,
+    thread: usize,
+    start: Duration,
+    duration: Duration,
+}
+
+/// Starts recording the durations of phases.
+///
+/// Until this is called [`phase`] does not record anything.
+pub fn enable() {
+    *TIMINGS.lock().unwrap() = Some(Timings {
+        started: Instant::now(),
+        threads: Vec::new(),
+        phases: Vec::new(),
+    });
+}
+
+/// Measures a phase until the returned guard is dropped.
+pub fn phase<S: Into<String>>(name: S) -> PhaseGuard {
+    let enabled = TIMINGS.lock().unwrap().is_some();
+    PhaseGuard {
+        name: if enabled { Some(name.into()) } else { None },
+        started: Instant::now(),
+    }
+}
+
+#[must_use]
+pub struct PhaseGuard {
+    name: Option<String>,
+    started: Instant,
+}
+
+impl Drop for PhaseGuard {
+    fn drop(&mut self) {
+        let name = match self.name.take() {
+            Some(name) => name,
+            None => return,
+        };
+        let mut timings = TIMINGS.lock().unwrap();
+        if let Some(ref mut timings) = *timings {
+            let thread_id = thread::current().id();
+            let thread = match timings.threads.iter().position(|x| *x == thread_id) {
+                Some(idx) => idx,
+                None => {
+                    timings.threads.push(thread_id);
+                    timings.threads.len() - 1
+                }
+            };
+            timings.phases.push(Phase {
+                name,
+                thread,
+                start: self.started.saturating_duration_since(timings.started),
+                duration: self.started.elapsed(),
+            });
+        }
+    }
+}
+
+/// Takes the recorded phases in the order they started.
+fn take_phases() -> Option<(Duration, Vec<Phase>)> {
+    let timings = TIMINGS.lock().unwrap().take()?;
+    let mut phases = timings.phases;
+    phases.sort_by_key(|x| x.start);
+    Some((timings.started.elapsed(), phases))
+}
+
+/// Prints a breakdown of the recorded phases to stderr.
+///
+/// Phases that ran within other phases are indented.
+pub fn print_breakdown() {
+    let (total, phases) = match take_phases() {
+        Some(rv) => rv,
+        None => return,
+    };
+    let width = phases.iter().map(|x| x.name.len()).max().unwrap_or(0) + 2;
+    elog!("{}", style("timings:").bold());
+    for (idx, phase) in phases.iter().enumerate() {
+        let depth = phases[..idx]
+            .iter()
+            .filter(|x| {
+                x.thread == phase.thread
+                    && x.start <= phase.start
+                    && x.start + x.duration >= phase.start + phase.duration
+            })
+            .count();
+        elog!(
+            "  {:<width$} {:>8.2}s",
+            format!("{}{}", "  ".repeat(depth), phase.name),
+            phase.duration.as_secs_f64(),
+            width = width + depth * 2
+        );
+    }
+    elog!(
+        "  {:<width$} {:>8.2}s",
+        "total",
+        total.as_secs_f64(),
+        width = width
+    );
+}
+
+/// Writes the recorded phases as a Chrome trace.
+///
+/// The file can be loaded into `chrome://tracing` or <https://ui.perfetto.dev>.
+pub fn write_trace(path: &Path) -> Result<(), Error> {
+    let (_, phases) = match take_phases() {
+        Some(rv) => rv,
+        None => return Ok(()),
+    };
+    let events = phases
+        .iter()
+        .map(|phase| {
+            serde_json::json!({
+                "name": phase.name,
+                "ph": "X",
+                "ts": phase.start.as_micros() as u64,
+                "dur": phase.duration.as_micros() as u64,
+                "pid": 1,
+                "tid": phase.thread + 1,
+            })
+        })
+        .collect::<Vec<_>>();
+    let trace = serde_json::json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms",
+    });
+    std::fs::write(path, serde_json::to_string_pretty(&trace)?)
+        .path_context(path, "failed to write trace")?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 
 static ECHO_TO_STDERR: AtomicBool = AtomicBool::new(false);
 
@@ -83,3 +84,37 @@ macro_rules! error {
         )
     }
 }
+
+/// What a command changed, printed in the `--summary` output mode.
+///
+/// In this mode the regular progress output is suppressed and only this
+/// one line is printed at the end which keeps CI logs short.
+pub struct Summary {
+    started: Instant,
+    pub installed: usize,
+    pub removed: usize,
+    pub lock_changed: bool,
+}
+
+impl Summary {
+    /// Starts timing the command.
+    pub fn start() -> Summary {
+        Summary {
+            started: Instant::now(),
+            installed: 0,
+            removed: 0,
+            lock_changed: false,
+        }
+    }
+
+    /// Prints the summary.
+    pub fn print(&self) {
+        echo!(
+            "{} installed, {} removed, lock changed: {}, done in {:.1}s",
+            self.installed,
+            self.removed,
+            if self.lock_changed { "yes" } else { "no" },
+            self.started.elapsed().as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use console::style;
+use python_pkginfo::Distribution;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::config::Config;
+use crate::utils::{CommandOutput, IoPathContext};
+
+/// The settings for an upload with [`upload_files`].
+pub struct UploadOptions<'a> {
+    pub username: &'a str,
+    pub password: &'a str,
+    pub headers: &'a [(String, String)],
+    pub cert: Option<&'a Path>,
+    pub skip_existing: bool,
+}
+
+/// Uploads distribution files with the legacy upload API.
+///
+/// This is what twine does too, but it allows sending extra headers which
+/// some repositories (like Artifactory or Nexus behind a gateway) require.
+pub fn upload_files(
+    url: &Url,
+    files: &[PathBuf],
+    options: &UploadOptions,
+    output: CommandOutput,
+) -> Result<(), Error> {
+    for file in files {
+        let filename = file
+            .file_name()
+            .context("distribution without file name")?
+            .to_string_lossy()
+            .into_owned();
+        echo!(if output, "Uploading {}", style(&filename).cyan());
+        let (status, body) = upload_file(url, file, &filename, options)?;
+        if (200..300).contains(&status) {
+            continue;
+        }
+        if options.skip_existing && is_existing_file_response(status, &body) {
+            echo!(
+                if output,
+                "Skipping {} because it appears to already exist",
+                filename
+            );
+            continue;
+        }
+        bail!(
+            "upload of {} failed with status {}: {}",
+            filename,
+            status,
+            body.trim()
+        );
+    }
+    Ok(())
+}
+
+fn upload_file(
+    url: &Url,
+    path: &Path,
+    filename: &str,
+    options: &UploadOptions,
+) -> Result<(u32, String), Error> {
+    let dist = Distribution::new(path)
+        .with_context(|| format!("unable to read metadata of {}", path.display()))?;
+    let metadata = dist.metadata();
+    let contents = fs::read(path).path_context(path, "unable to read distribution")?;
+    let (filetype, pyversion) = match filename.strip_suffix(".whl") {
+        Some(stem) => (
+            "bdist_wheel",
+            stem.rsplitn(4, '-').nth(2).unwrap_or("py3").to_string(),
+        ),
+        None => ("sdist", "source".to_string()),
+    };
+
+    let mut fields = vec![
+        (":action", "file_upload".to_string()),
+        ("protocol_version", "1".to_string()),
+        ("filetype", filetype.to_string()),
+        ("pyversion", pyversion),
+        ("sha256_digest", hex::encode(Sha256::digest(&contents))),
+        ("metadata_version", metadata.metadata_version.clone()),
+        ("name", metadata.name.clone()),
+        ("version", metadata.version.clone()),
+    ];
+    for (key, value) in [
+        ("summary", &metadata.summary),
+        ("description", &metadata.description),
+        (
+            "description_content_type",
+            &metadata.description_content_type,
+        ),
+        ("keywords", &metadata.keywords),
+        ("home_page", &metadata.home_page),
+        ("download_url", &metadata.download_url),
+        ("author", &metadata.author),
+        ("author_email", &metadata.author_email),
+        ("maintainer", &metadata.maintainer),
+        ("maintainer_email", &metadata.maintainer_email),
+        ("license", &metadata.license),
+        ("requires_python", &metadata.requires_python),
+    ] {
+        if let Some(value) = value {
+            fields.push((key, value.clone()));
+        }
+    }
+    for (key, values) in [
+        ("platform", &metadata.platforms),
+        ("supported_platform", &metadata.supported_platforms),
+        ("classifiers", &metadata.classifiers),
+        ("requires_dist", &metadata.requires_dist),
+        ("provides_extra", &metadata.provides_extras),
+        ("project_urls", &metadata.project_urls),
+    ] {
+        for value in values {
+            fields.push((key, value.clone()));
+        }
+    }
+
+    let mut form = curl::easy::Form::new();
+    for (key, value) in fields {
+        form.part(key).contents(value.as_bytes()).add()?;
+    }
+    form.part("content").buffer(filename, contents).add()?;
+
+    let mut handle = curl::easy::Easy::new();
+    handle.url(url.as_str())?;
+    handle.username(options.username)?;
+    handle.password(options.password)?;
+    if let Some(proxy) = Config::current().https_proxy_url() {
+        handle.proxy(&proxy)?;
+    }
+    if let Some(cert) = options.cert {
+        handle.cainfo(cert)?;
+    }
+    #[cfg(windows)]
+    {
+        handle.ssl_options(curl::easy::SslOpt::new().no_revoke(true))?;
+    }
+    let mut headers = curl::easy::List::new();
+    for (key, value) in options.headers {
+        headers.append(&format!("{}: {}", key, value))?;
+    }
+    handle.http_headers(headers)?;
+    handle.httppost(form)?;
+
+    let mut response = Vec::new();
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|data| {
+            response.extend_from_slice(data);
+            Ok(data.len())
+        })?;
+        transfer
+            .perform()
+            .with_context(|| format!("upload to {} failed", url))?;
+    }
+    Ok((
+        handle.response_code()?,
+        String::from_utf8_lossy(&response).into_owned(),
+    ))
+}
+
+/// Checks if a failed upload was rejected because the file already exists.
+///
+/// Repositories signal this in different ways: PyPI and pypiserver use 400
+/// or 409, Nexus uses 400 and Artifactory uses 403.
+fn is_existing_file_response(status: u32, body: &str) -> bool {
+    let body = body.to_ascii_lowercase();
+    match status {
+        409 => true,
+        400 => ["already exist", "updating asset", "cannot be updated"]
+            .iter()
+            .any(|x| body.contains(x)),
+        403 => body.contains("overwrite artifact"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_existing_file_response() {
+        assert!(is_existing_file_response(409, ""));
+        assert!(is_existing_file_response(
+            400,
+            "400 File already exists. See https://pypi.org/help/#file-name-reuse"
+        ));
+        assert!(is_existing_file_response(
+            400,
+            "Repository does not allow updating assets: pypi-hosted"
+        ));
+        assert!(is_existing_file_response(
+            403,
+            "Not enough permissions to delete/overwrite artifact"
+        ));
+        assert!(!is_existing_file_response(400, "Invalid distribution"));
+        assert!(!is_existing_file_response(500, "already exists"));
+    }
+}
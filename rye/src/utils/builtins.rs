@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Error};
+
+use crate::utils::{copy_dir, CopyDirOptions, IoPathContext};
+
+/// Checks if a script command refers to a builtin.
+///
+/// Builtins are simple commands like `@rm` or `@cp` that are interpreted by
+/// rye itself so that they behave the same on all platforms.
+pub fn is_builtin(cmd: &OsString) -> bool {
+    cmd.to_string_lossy().starts_with('@')
+}
+
+/// Splits the `KEY=VALUE` arguments of the `@env` builtin from the command.
+pub fn split_env_builtin(
+    args: &[OsString],
+) -> Result<(Vec<(String, String)>, Vec<OsString>), Error> {
+    let mut vars = Vec::new();
+    let mut iter = args.iter().skip(1).peekable();
+    while let Some(arg) = iter.peek() {
+        match arg.to_string_lossy().split_once('=') {
+            Some((key, value)) => {
+                vars.push((key.to_string(), value.to_string()));
+                iter.next();
+            }
+            None => break,
+        }
+    }
+    let rest: Vec<_> = iter.cloned().collect();
+    if rest.is_empty() {
+        bail!("@env requires a command to run");
+    }
+    Ok((vars, rest))
+}
+
+/// Runs a builtin with paths relative to `cwd`.
+pub fn run_builtin(args: &[OsString], cwd: &Path) -> Result<(), Error> {
+    let name = args[0].to_string_lossy();
+    let args = args[1..]
+        .iter()
+        .map(|x| x.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    match &*name {
+        "@echo" => {
+            echo!("{}", args.join(" "));
+        }
+        "@rm" => {
+            let (flags, paths) = parse_flags(&name, &args, "rf")?;
+            for path in paths {
+                remove(&cwd.join(path), flags.contains(&'r'), flags.contains(&'f'))?;
+            }
+        }
+        "@mkdir" => {
+            let (flags, paths) = parse_flags(&name, &args, "p")?;
+            for path in paths {
+                let path = cwd.join(path);
+                if flags.contains(&'p') {
+                    fs::create_dir_all(&path)
+                } else {
+                    fs::create_dir(&path)
+                }
+                .path_context(&path, "unable to create directory")?;
+            }
+        }
+        "@cp" | "@mv" => {
+            let (flags, paths) = parse_flags(&name, &args, if name == "@cp" { "r" } else { "" })?;
+            let (target, sources) = match paths.split_last() {
+                Some((target, sources)) if !sources.is_empty() => (cwd.join(target), sources),
+                _ => bail!("{} requires a source and a target", name),
+            };
+            for source in sources {
+                let source = cwd.join(source);
+                let target = if target.is_dir() {
+                    match source.file_name() {
+                        Some(file_name) => target.join(file_name),
+                        None => bail!("invalid source '{}'", source.display()),
+                    }
+                } else if sources.len() > 1 {
+                    bail!("target '{}' is not a directory", target.display());
+                } else {
+                    target.clone()
+                };
+                if name == "@mv" {
+                    fs::rename(&source, &target).path_context(&source, "unable to move")?;
+                } else if source.is_dir() {
+                    if !flags.contains(&'r') {
+                        bail!("'{}' is a directory (use -r)", source.display());
+                    }
+                    fs::create_dir_all(&target)
+                        .path_context(&target, "unable to create directory")?;
+                    copy_dir(&source, &target, &CopyDirOptions { exclude: vec![] })?;
+                } else {
+                    fs::copy(&source, &target).path_context(&source, "unable to copy")?;
+                }
+            }
+        }
+        _ => bail!("unknown builtin '{}'", name),
+    }
+    Ok(())
+}
+
+/// Separates single letter flags (which can be combined as in `-rf`) from
+/// the remaining arguments.
+fn parse_flags<'a>(
+    name: &str,
+    args: &'a [String],
+    allowed: &str,
+) -> Result<(HashSet<char>, Vec<&'a str>), Error> {
+    let mut flags = HashSet::new();
+    let mut rest = Vec::new();
+    let mut only_args = false;
+    for arg in args {
+        if only_args || !arg.starts_with('-') || arg == "-" {
+            rest.push(arg.as_str());
+        } else if arg == "--" {
+            only_args = true;
+        } else {
+            for flag in arg[1..].chars() {
+                if !allowed.contains(flag) {
+                    bail!("{}: unknown flag -{}", name, flag);
+                }
+                flags.insert(flag);
+            }
+        }
+    }
+    Ok((flags, rest))
+}
+
+fn remove(path: &Path, recursive: bool, force: bool) -> Result<(), Error> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => {
+            if !recursive {
+                bail!("'{}' is a directory (use -r)", path.display());
+            }
+            fs::remove_dir_all(path).path_context(path, "unable to remove directory")
+        }
+        Ok(_) => fs::remove_file(path).path_context(path, "unable to remove file"),
+        Err(_) if force => Ok(()),
+        Err(err) => Err(err).path_context(path, "unable to remove"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flags() {
+        let args = vec!["-rf".to_string(), "build".into(), "--".into(), "-x".into()];
+        let (flags, rest) = parse_flags("@rm", &args, "rf").unwrap();
+        assert!(flags.contains(&'r') && flags.contains(&'f'));
+        assert_eq!(rest, ["build", "-x"]);
+        assert!(parse_flags("@rm", &["-x".to_string()], "rf").is_err());
+    }
+
+    #[test]
+    fn test_split_env_builtin() {
+        let args = ["@env", "FOO=1", "BAR=a=b", "python", "X=1"]
+            .iter()
+            .map(OsString::from)
+            .collect::<Vec<_>>();
+        let (vars, rest) = split_env_builtin(&args).unwrap();
+        assert_eq!(
+            vars,
+            [("FOO".into(), "1".into()), ("BAR".into(), "a=b".into())]
+        );
+        assert_eq!(rest, [OsString::from("python"), OsString::from("X=1")]);
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Error};
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Blake2b512};
+use sha2::{Digest, Sha256, Sha512};
+
+/// BLAKE2b with a 256 bit digest, as used by PyPI.
+type Blake2b256 = Blake2b<U32>;
+
+/// The digest algorithms supported for verifying downloads.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Sha512,
+    Blake2b256,
+    Blake2b512,
+}
+
+impl ChecksumAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+            ChecksumAlgorithm::Blake2b256 => "blake2b_256",
+            ChecksumAlgorithm::Blake2b512 => "blake2b",
+        }
+    }
+
+    /// The length of the digests in bytes.
+    fn digest_len(&self) -> usize {
+        match self {
+            ChecksumAlgorithm::Sha256 | ChecksumAlgorithm::Blake2b256 => 32,
+            ChecksumAlgorithm::Sha512 | ChecksumAlgorithm::Blake2b512 => 64,
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A checksum as found in download indexes.
+///
+/// The canonical form is `ALGORITHM:HEX` (eg: `sha512:ab12...`), `=` is also
+/// accepted as separator.  Plain hex digests are sha256 for compatibility.
+/// BLAKE2b is supported with 256 bit (`blake2b_256`, as used by PyPI) and
+/// 512 bit (`blake2b` or `blake2b_512`) digests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub digest: Vec<u8>,
+}
+
+impl FromStr for Checksum {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (algorithm, hex_digest) = match s.split_once([':', '=']) {
+            Some((name, hex_digest)) => {
+                let algorithm = match name.to_ascii_lowercase().as_str() {
+                    "sha256" => ChecksumAlgorithm::Sha256,
+                    "sha512" => ChecksumAlgorithm::Sha512,
+                    "blake2b_256" => ChecksumAlgorithm::Blake2b256,
+                    "blake2b" | "blake2b_512" => ChecksumAlgorithm::Blake2b512,
+                    _ => bail!("unsupported checksum algorithm '{}'", name),
+                };
+                (algorithm, hex_digest)
+            }
+            None => (ChecksumAlgorithm::Sha256, s),
+        };
+        let digest = hex::decode(hex_digest)
+            .map_err(|err| anyhow!("invalid {} checksum '{}': {}", algorithm, s, err))?;
+        // truncated digests are rejected rather than verified against.
+        if digest.len() != algorithm.digest_len() {
+            bail!("invalid {} checksum '{}': bad length", algorithm, s);
+        }
+        Ok(Checksum { algorithm, digest })
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, hex::encode(&self.digest))
+    }
+}
+
+impl Checksum {
+    /// Creates a hasher producing digests comparable to this checksum.
+    pub fn hasher(&self) -> Hasher {
+        match self.algorithm {
+            ChecksumAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            ChecksumAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            ChecksumAlgorithm::Blake2b256 => Hasher::Blake2b256(Blake2b256::new()),
+            ChecksumAlgorithm::Blake2b512 => Hasher::Blake2b512(Blake2b512::new()),
+        }
+    }
+}
+
+/// Incrementally computes the digest for a [`Checksum`].
+pub enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake2b256(Blake2b256),
+    Blake2b512(Blake2b512),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+            Hasher::Blake2b256(hasher) => hasher.update(data),
+            Hasher::Blake2b512(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake2b256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake2b512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(checksum: &str, data: &[u8]) -> String {
+        let checksum: Checksum = checksum.parse().unwrap();
+        let mut hasher = checksum.hasher();
+        hasher.update(data);
+        hex::encode(hasher.finalize())
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let plain = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let checksum: Checksum = plain.parse().unwrap();
+        assert_eq!(checksum.algorithm, ChecksumAlgorithm::Sha256);
+        assert_eq!(checksum.to_string(), format!("sha256:{}", plain));
+        let checksum: Checksum = format!("sha256={}", plain).parse().unwrap();
+        assert_eq!(checksum.algorithm, ChecksumAlgorithm::Sha256);
+        let blake2b_256 = "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8";
+        let checksum: Checksum = format!("blake2b_256:{}", blake2b_256).parse().unwrap();
+        assert_eq!(checksum.algorithm, ChecksumAlgorithm::Blake2b256);
+        assert_eq!(checksum.to_string(), format!("blake2b_256:{}", blake2b_256));
+        // truncated digests and unknown output lengths are not accepted
+        assert!("blake2b_256:00ff".parse::<Checksum>().is_err());
+        assert!(format!("blake2b:{}", blake2b_256)
+            .parse::<Checksum>()
+            .is_err());
+        assert!(format!("blake2b_128:{}", &blake2b_256[..32])
+            .parse::<Checksum>()
+            .is_err());
+        assert!("md5:d41d8cd98f00b204e9800998ecf8427e"
+            .parse::<Checksum>()
+            .is_err());
+        assert!("sha512:00ff".parse::<Checksum>().is_err());
+        assert!("sha256:xyz".parse::<Checksum>().is_err());
+    }
+
+    #[test]
+    fn test_digests() {
+        assert_eq!(
+            digest(&format!("sha512:{}", "0".repeat(128)), b"abc"),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        // test vector from RFC 7693
+        assert_eq!(
+            digest(&format!("blake2b:{}", "0".repeat(128)), b"abc"),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+        assert_eq!(
+            digest(&format!("blake2b_256:{}", "0".repeat(64)), b""),
+            "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+        );
+        // spans multiple blocks including an exactly full last block
+        let data = vec![b'x'; 256];
+        let checksum: Checksum = format!("blake2b_512:{}", "0".repeat(128)).parse().unwrap();
+        let mut hasher = checksum.hasher();
+        for chunk in data.chunks(100) {
+            hasher.update(chunk);
+        }
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "26066ae992ec734e85f05f962b49e72bcb2be54fcb53bce7e7b4d7f4dc88f568\
+             62235fd16b988877db71cc5e9bb50e489e884450fdb6f74968e6da7d1e493428"
+        );
+    }
+}
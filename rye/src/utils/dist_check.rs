@@ -0,0 +1,246 @@
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Error};
+use flate2::read::GzDecoder;
+use pep440_rs::{Version, VersionSpecifiers};
+use python_pkginfo::{Distribution, Metadata};
+
+use crate::utils::IoPathContext;
+
+/// A problem found in a built distribution.
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    /// The distribution would be rejected or render incorrectly on upload.
+    Error(String),
+    /// The distribution is usable but likely not what was intended.
+    Warning(String),
+}
+
+/// Checks a wheel or sdist in the same spirit as `twine check`.
+///
+/// For sdists `required_files` lists paths (relative to the project root)
+/// that need to be contained in the archive.
+pub fn check_dist(path: &Path, required_files: &[String]) -> Result<Vec<Problem>, Error> {
+    let dist = Distribution::new(path)
+        .with_context(|| format!("unable to read metadata of {}", path.display()))?;
+    let metadata = dist.metadata();
+    let filename = path
+        .file_name()
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut problems = check_metadata(metadata);
+    if let Some(stem) = filename.strip_suffix(".whl") {
+        problems.extend(check_wheel_tags(stem, metadata.requires_python.as_deref()));
+    } else {
+        let contents = fs::read(path).path_context(path, "unable to read sdist")?;
+        let files = list_sdist_files(&filename, &contents)?;
+        for required in ["PKG-INFO", "pyproject.toml"]
+            .into_iter()
+            .chain(required_files.iter().map(|x| x.as_str()))
+        {
+            let required = required.trim_start_matches("./");
+            if !files.iter().any(|x| x == required) {
+                problems.push(Problem::Error(format!("sdist is missing {}", required)));
+            }
+        }
+    }
+    Ok(problems)
+}
+
+fn check_metadata(metadata: &Metadata) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (field, value) in [
+        ("Metadata-Version", &metadata.metadata_version),
+        ("Name", &metadata.name),
+        ("Version", &metadata.version),
+    ] {
+        if value.trim().is_empty() {
+            problems.push(Problem::Error(format!("required field {} missing", field)));
+        }
+    }
+    if metadata
+        .summary
+        .as_deref()
+        .map_or(true, |x| x.trim().is_empty())
+    {
+        problems.push(Problem::Warning("summary (description) missing".into()));
+    }
+
+    let description = match metadata.description.as_deref() {
+        Some(description) if !description.trim().is_empty() => description,
+        _ => {
+            problems.push(Problem::Warning("long_description (readme) missing".into()));
+            return problems;
+        }
+    };
+    let content_type = metadata.description_content_type.as_deref().map(|x| {
+        x.split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase()
+    });
+    match content_type.as_deref() {
+        Some("text/plain") | Some("text/markdown") => {}
+        Some("text/x-rst") => problems.extend(check_rst(description)),
+        None => {
+            problems.push(Problem::Warning(
+                "long_description_content_type missing, defaulting to text/x-rst".into(),
+            ));
+            problems.extend(check_rst(description));
+        }
+        Some(other) => problems.push(Problem::Error(format!(
+            "unsupported long_description_content_type '{}'",
+            other
+        ))),
+    }
+    problems
+}
+
+/// Finds the reStructuredText mistakes that most commonly make PyPI refuse
+/// to render a description.
+fn check_rst(description: &str) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let lines = description.lines().collect::<Vec<_>>();
+    for (idx, pair) in lines.windows(2).enumerate() {
+        let (title, underline) = (pair[0].trim_end(), pair[1].trim_end());
+        if title.trim().is_empty() || is_adornment(title) || !is_adornment(underline) {
+            continue;
+        }
+        // docutils only treats short adornments as text if they are shorter
+        // than four characters, anything else is a broken title.
+        if underline.chars().count() >= 4 && underline.chars().count() < title.chars().count() {
+            problems.push(Problem::Error(format!(
+                "long_description does not render: title underline too short (line {})",
+                idx + 2
+            )));
+        }
+    }
+    let literals = description.matches("``").count();
+    if literals % 2 != 0 {
+        problems.push(Problem::Error(
+            "long_description does not render: unterminated inline literal".into(),
+        ));
+    }
+    problems
+}
+
+fn is_adornment(line: &str) -> bool {
+    let mut chars = line.chars();
+    match chars.next() {
+        Some(first) if "=-~^\"'`*+#:._".contains(first) => chars.all(|c| c == first),
+        _ => false,
+    }
+}
+
+/// Checks that the Python tags of a wheel (given without the `.whl`
+/// extension) are compatible with the declared `requires-python`.
+fn check_wheel_tags(stem: &str, requires_python: Option<&str>) -> Vec<Problem> {
+    let requires_python = match requires_python {
+        Some(requires_python) => requires_python,
+        None => return vec![],
+    };
+    let specifiers = match VersionSpecifiers::from_str(requires_python) {
+        Ok(specifiers) => specifiers,
+        Err(_) => {
+            return vec![Problem::Error(format!(
+                "invalid requires-python '{}'",
+                requires_python
+            ))]
+        }
+    };
+    let mut parts = stem.rsplitn(4, '-');
+    let (_platform, abi, python) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(platform), Some(abi), Some(python)) => (platform, abi, python),
+        _ => {
+            return vec![Problem::Error(format!(
+                "invalid wheel filename '{}.whl'",
+                stem
+            ))]
+        }
+    };
+
+    let compatible = python.split('.').any(|tag| {
+        let digits = tag.get(2..).unwrap_or("");
+        let major = match digits.get(..1).and_then(|x| x.parse::<u32>().ok()) {
+            Some(major) => major,
+            None => return true,
+        };
+        // a tag without minor version (or a stable ABI wheel) covers all
+        // following minor versions too.
+        let minors = match digits.get(1..).and_then(|x| x.parse::<u32>().ok()) {
+            Some(minor) if abi == "abi3" => minor..=99,
+            Some(minor) => minor..=minor,
+            None => 0..=99,
+        };
+        minors.into_iter().any(|minor| {
+            (0..=30).any(|patch| {
+                Version::from_str(&format!("{}.{}.{}", major, minor, patch))
+                    .map_or(false, |version| specifiers.contains(&version))
+            })
+        })
+    });
+    if compatible {
+        vec![]
+    } else {
+        vec![Problem::Error(format!(
+            "wheel tag {}-{} does not match requires-python '{}'",
+            python, abi, requires_python
+        ))]
+    }
+}
+
+/// Lists the files of an sdist relative to its top-level directory.
+fn list_sdist_files(filename: &str, contents: &[u8]) -> Result<Vec<String>, Error> {
+    let mut names = Vec::new();
+    if filename.ends_with(".zip") {
+        let mut archive = zip::read::ZipArchive::new(Cursor::new(contents))?;
+        for idx in 0..archive.len() {
+            names.push(archive.by_index(idx)?.name().to_string());
+        }
+    } else {
+        let mut decoded = Vec::new();
+        GzDecoder::new(contents)
+            .read_to_end(&mut decoded)
+            .context("unable to decompress sdist")?;
+        let mut archive = tar::Archive::new(Cursor::new(decoded));
+        for entry in archive.entries()? {
+            names.push(entry?.path()?.to_string_lossy().into_owned());
+        }
+    }
+    Ok(names
+        .into_iter()
+        .filter_map(|name| {
+            name.split_once('/')
+                .map(|(_, rest)| rest.to_string())
+                .filter(|rest| !rest.is_empty())
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_wheel_tags() {
+        assert!(check_wheel_tags("foo-1.0-py3-none-any", Some(">=3.8")).is_empty());
+        assert!(check_wheel_tags("foo-1.0-py2.py3-none-any", Some(">=3.8")).is_empty());
+        assert!(check_wheel_tags("foo-1.0-cp312-cp312-linux_x86_64", Some(">=3.8")).is_empty());
+        assert!(check_wheel_tags("foo-1.0-cp38-abi3-linux_x86_64", Some(">=3.10")).is_empty());
+        assert!(!check_wheel_tags("foo-1.0-cp37-cp37m-linux_x86_64", Some(">=3.8")).is_empty());
+        assert!(!check_wheel_tags("foo-1.0-py2-none-any", Some(">=3.8")).is_empty());
+    }
+
+    #[test]
+    fn test_check_rst() {
+        assert!(check_rst("Title\n=====\n\nSome ``code``.").is_empty());
+        assert!(check_rst("A long title\n====\n").len() == 1);
+        assert!(check_rst("Some ``code.").len() == 1);
+        assert!(check_rst("a\n--\n").is_empty());
+    }
+}
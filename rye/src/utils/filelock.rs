@@ -0,0 +1,92 @@
+//! Advisory locks that keep concurrent rye invocations apart.
+//!
+//! Two `rye sync` runs at once (for instance from an editor and from a
+//! terminal) would otherwise extract the same toolchain or update the same
+//! virtualenv simultaneously.  The locks are held by the operating system, so
+//! they are released when rye is killed and never go stale.
+use std::env;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Error};
+use fslock::LockFile;
+use sha2::{Digest, Sha256};
+
+use crate::platform::get_app_dir;
+use crate::utils::{CommandOutput, IoPathContext};
+
+/// How long to wait for a lock unless `RYE_LOCK_TIMEOUT` says otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How often a held lock is checked again.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An acquired lock, released when dropped.
+#[must_use]
+pub struct FileLock {
+    _file: LockFile,
+}
+
+impl FileLock {
+    /// Acquires the lock of the given name in `[RYE_HOME]/locks`.
+    ///
+    /// If another rye process holds the lock this waits for it, `what`
+    /// describes what the other process is doing (eg: "installing
+    /// cpython@3.12.2").
+    pub fn acquire(name: &str, what: &str, output: CommandOutput) -> Result<FileLock, Error> {
+        let path = get_app_dir().join("locks").join(format!("{}.lock", name));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).path_context(parent, "failed to create lock folder")?;
+        }
+        let mut file = LockFile::open(&path).path_context(&path, "failed to open lock file")?;
+        if file.try_lock().path_context(&path, "failed to lock")? {
+            return Ok(FileLock { _file: file });
+        }
+
+        let timeout = lock_timeout();
+        if output != CommandOutput::Quiet {
+            elog!(
+                "Waiting for another rye process to finish {} (timeout {}s)",
+                what,
+                timeout.as_secs()
+            );
+        }
+        let started = Instant::now();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            if file.try_lock().path_context(&path, "failed to lock")? {
+                return Ok(FileLock { _file: file });
+            }
+            if started.elapsed() >= timeout {
+                bail!(
+                    "timed out after {}s waiting for another rye process to finish {} \
+                     (lock at '{}'). Set RYE_LOCK_TIMEOUT to wait longer.",
+                    timeout.as_secs(),
+                    what,
+                    path.display()
+                );
+            }
+        }
+    }
+
+    /// Acquires the lock for changes to a virtualenv.
+    pub fn for_venv(venv: &Path, output: CommandOutput) -> Result<FileLock, Error> {
+        let mut hasher = Sha256::new();
+        hasher.update(venv.to_string_lossy().as_bytes());
+        let hash = hex::encode(hasher.finalize());
+        FileLock::acquire(
+            &format!("venv-{}", &hash[..16]),
+            &format!("updating the virtualenv at '{}'", venv.display()),
+            output,
+        )
+    }
+}
+
+/// Returns how long to wait for a lock.
+fn lock_timeout() -> Duration {
+    env::var("RYE_LOCK_TIMEOUT")
+        .ok()
+        .and_then(|x| x.trim().parse().ok())
+        .map_or(DEFAULT_TIMEOUT, Duration::from_secs)
+}
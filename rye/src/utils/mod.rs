@@ -1,16 +1,18 @@
 use std::borrow::Cow;
 use std::convert::Infallible;
-use std::io::{Cursor, Read};
+use std::io::{BufRead, Cursor, Read};
 use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus, Stdio};
-use std::{fmt, fs};
+use std::sync::Mutex;
+use std::time::Duration;
+use std::{fmt, fs, thread};
 
 use anyhow::{anyhow, bail, Context, Error};
 use dialoguer::theme::{ColorfulTheme, Theme};
+use indicatif::{ProgressBar, ProgressStyle};
 use once_cell::sync::Lazy;
 use pep508_rs::{Requirement, VersionOrUrl};
 use regex::{Captures, Regex};
-use sha2::{Digest, Sha256};
 
 static ENV_VAR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$\{([A-Z0-9_]+)\}").unwrap());
 
@@ -34,9 +36,16 @@ pub(crate) mod windows;
 #[cfg(unix)]
 pub(crate) mod unix;
 
+pub(crate) mod builtins;
+pub(crate) mod checksum;
+pub(crate) mod dist_check;
+pub(crate) mod filelock;
 pub(crate) mod panic;
 pub(crate) mod ruff;
+pub(crate) mod scaffold;
+pub(crate) mod signals;
 pub(crate) mod toml;
+pub(crate) mod watch;
 
 pub trait IoPathContext {
     type Out;
@@ -260,7 +269,7 @@ impl ArchiveFormat {
         }
     }
 
-    pub fn make_decoder<'a>(self, bytes: &'a [u8]) -> Result<Box<dyn Read + 'a>, Error> {
+    pub fn make_decoder<'a, R: BufRead + 'a>(self, bytes: R) -> Result<Box<dyn Read + 'a>, Error> {
         Ok(match self {
             ArchiveFormat::TarGz => Box::new(flate2::bufread::GzDecoder::new(bytes)) as Box<_>,
             ArchiveFormat::TarBz2 => Box::new(bzip2::bufread::BzDecoder::new(bytes)) as Box<_>,
@@ -272,17 +281,68 @@ impl ArchiveFormat {
     }
 }
 
+/// Creates a progress bar for processing `len` bytes.
+///
+/// The bar is hidden for quiet output.
+fn make_bytes_progress_bar(len: u64, output: CommandOutput) -> ProgressBar {
+    if output == CommandOutput::Quiet {
+        return ProgressBar::hidden();
+    }
+    let pb = ProgressBar::new(len);
+    pb.set_style(
+        ProgressStyle::with_template(
+            "{wide_bar} {bytes:>7}/{total_bytes:7} {binary_bytes_per_sec:>11} {msg}",
+        )
+        .unwrap(),
+    );
+    pb
+}
+
+/// Reports the bytes consumed from a reader to a progress bar.
+struct ProgressReader<'a, R> {
+    inner: R,
+    pb: &'a ProgressBar,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pb.inc(n as u64);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for ProgressReader<'_, R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.pb.inc(amt as u64);
+    }
+}
+
 /// Unpacks a tarball or zip archive.
 ///
-/// Today this assumes that the tarball is zstd compressed which happens
-/// to be what the indygreg python builds use.
-pub fn unpack_archive(contents: &[u8], dst: &Path, strip_components: usize) -> Result<(), Error> {
+/// The format is detected from the contents: zstd, gzip and bzip2
+/// compressed tarballs as well as zip files are supported.  Progress is
+/// reported as the compressed bytes and entries processed so far.
+pub fn unpack_archive(
+    contents: &[u8],
+    dst: &Path,
+    strip_components: usize,
+    output: CommandOutput,
+) -> Result<(), Error> {
     let format = ArchiveFormat::peek(contents).ok_or_else(|| anyhow!("unknown archive"))?;
+    let pb = make_bytes_progress_bar(contents.len() as u64, output);
 
     if matches!(format, ArchiveFormat::Zip) {
         let mut archive = zip::read::ZipArchive::new(Cursor::new(contents))?;
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
+            pb.inc(file.compressed_size());
+            pb.set_message(format!("{}/{} entries", i + 1, archive.len()));
             let name = file
                 .enclosed_name()
                 .ok_or_else(|| anyhow!("Invalid file path in zip"))?;
@@ -317,9 +377,14 @@ pub fn unpack_archive(contents: &[u8], dst: &Path, strip_components: usize) -> R
             }
         }
     } else {
-        let mut archive = tar::Archive::new(format.make_decoder(contents)?);
-        for entry in archive.entries()? {
+        let reader = ProgressReader {
+            inner: contents,
+            pb: &pb,
+        };
+        let mut archive = tar::Archive::new(format.make_decoder(reader)?);
+        for (count, entry) in archive.entries()?.enumerate() {
             let mut entry = entry?;
+            pb.set_message(format!("{} entries", count + 1));
             let name = entry.path()?;
             let mut components = name.components();
             for _ in 0..strip_components {
@@ -337,6 +402,7 @@ pub fn unpack_archive(contents: &[u8], dst: &Path, strip_components: usize) -> R
         }
     }
 
+    pb.finish_and_clear();
     Ok(())
 }
 
@@ -354,8 +420,8 @@ pub fn exec_spawn(cmd: &mut Command) -> Result<Infallible, Error> {
     #[cfg(windows)]
     {
         cmd.stdin(Stdio::inherit());
-        let status = cmd.status()?;
-        std::process::exit(status.code().unwrap())
+        let status = signals::spawn_and_wait(cmd)?;
+        std::process::exit(signals::exit_code(&status))
     }
 }
 
@@ -408,17 +474,45 @@ pub fn success_status() -> ExitStatus {
 }
 
 /// Takes a bytes slice and compares it to a given string checksum.
-pub fn check_checksum(content: &[u8], checksum: &str) -> Result<(), Error> {
-    let mut hasher = Sha256::new();
-    hasher.update(content);
+///
+/// The checksum is either a plain sha256 hex digest or prefixed with the
+/// algorithm (eg: `sha512:...`), see [`checksum::Checksum`].
+pub fn check_checksum(content: &[u8], checksum: &str, output: CommandOutput) -> Result<(), Error> {
+    let checksum: checksum::Checksum = checksum.parse()?;
+    let pb = make_bytes_progress_bar(content.len() as u64, output);
+    let mut hasher = checksum.hasher();
+    for chunk in content.chunks(1024 * 1024) {
+        hasher.update(chunk);
+        pb.inc(chunk.len() as u64);
+    }
+    pb.finish_and_clear();
     let digest = hasher.finalize();
-    let digest = hex::encode(digest);
-    if !digest.eq_ignore_ascii_case(checksum) {
-        bail!("hash mismatch: expected {} got {}", checksum, digest);
+    if digest != checksum.digest {
+        bail!(
+            "hash mismatch: expected {} got {}:{}",
+            checksum,
+            checksum.algorithm,
+            hex::encode(digest)
+        );
     }
     Ok(())
 }
 
+/// Converts a unix timestamp into a `(year, month, day)` date (in UTC).
+pub fn civil_date(timestamp: u64) -> (i64, u32, u32) {
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
 pub fn escape_string(s: String) -> String {
     s.trim().replace(['\\', '"'], "")
 }
@@ -470,6 +564,43 @@ pub fn update_venv_sync_marker(output: CommandOutput, venv_path: &Path) {
     }
 }
 
+/// Runs `f` over all items on up to `jobs` worker threads.
+///
+/// Idle workers pick up the next pending item, so slow items do not hold
+/// up the others.  The results are returned in the order of the items.
+pub fn run_parallel<T, R, F>(jobs: usize, items: Vec<T>, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = jobs.min(items.len());
+    if workers <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some((idx, item)) => {
+                        let rv = f(item);
+                        results.lock().unwrap().push((idx, rv));
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, rv)| rv).collect()
+}
+
 #[test]
 fn test_quiet_exit_display() {
     let quiet_exit = QuietExit(0);
@@ -508,6 +639,156 @@ mod test_format_requirement {
     }
 }
 
+/// Formats a size in bytes for humans.
+pub fn format_size(bytes: u64) -> String {
+    let bytes = bytes as f64;
+    if bytes >= 1024.0 * 1024.0 * 1024.0 {
+        format!("{:.1} GiB", bytes / (1024.0 * 1024.0 * 1024.0))
+    } else if bytes >= 1024.0 * 1024.0 {
+        format!("{:.1} MiB", bytes / (1024.0 * 1024.0))
+    } else if bytes >= 1024.0 {
+        format!("{:.1} KiB", bytes / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Calculates the size of all files in a folder in bytes.
+pub fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Parses a duration like `30d`, `12h`, `2w` or `90m`.
+pub fn parse_duration(s: &str) -> Result<Duration, Error> {
+    let s = s.trim();
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let num = num
+        .parse::<u64>()
+        .with_context(|| format!("invalid duration '{}'", s))?;
+    let seconds = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => bail!(
+            "invalid duration '{}' (expected a number followed by s, m, h, d or w)",
+            s
+        ),
+    };
+    Ok(Duration::from_secs(num * seconds))
+}
+
+#[cfg(test)]
+mod test_unpack_archive {
+    use std::fs;
+    use std::io::Read;
+
+    use indicatif::ProgressBar;
+    use sha2::{Digest, Sha256};
+
+    use super::{check_checksum, unpack_archive, ArchiveFormat, CommandOutput, ProgressReader};
+
+    fn make_tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *contents).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_progress_reader_counts_compressed_bytes() {
+        let data = vec![b'x'; 100_000];
+        let archive = make_tar_gz(&[("python/bin/python3", &data[..])]);
+        let pb = ProgressBar::hidden();
+        let reader = ProgressReader {
+            inner: &archive[..],
+            pb: &pb,
+        };
+        let mut decoder = ArchiveFormat::TarGz.make_decoder(reader).unwrap();
+        let mut unpacked = Vec::new();
+        decoder.read_to_end(&mut unpacked).unwrap();
+        assert!(unpacked.len() > 100_000);
+        assert_eq!(pb.position(), archive.len() as u64);
+    }
+
+    #[test]
+    fn test_unpack_tar_gz_with_progress() {
+        let archive = make_tar_gz(&[
+            ("python/bin/python3", &b"#!/bin/sh\n"[..]),
+            ("python/lib/os.py", &b""[..]),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        unpack_archive(&archive, dir.path(), 1, CommandOutput::Normal).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("bin").join("python3")).unwrap(),
+            "#!/bin/sh\n"
+        );
+        assert!(dir.path().join("lib").join("os.py").is_file());
+    }
+
+    #[test]
+    fn test_check_checksum_in_chunks() {
+        // more than one chunk of what the progress is reported in
+        let content = (0..3 * 1024 * 1024 + 17)
+            .map(|x| (x % 251) as u8)
+            .collect::<Vec<_>>();
+        let digest = hex::encode(Sha256::digest(&content));
+        check_checksum(&content, &digest, CommandOutput::Quiet).unwrap();
+        check_checksum(
+            &content,
+            &format!("sha256:{}", digest),
+            CommandOutput::Normal,
+        )
+        .unwrap();
+        let err = check_checksum(&content[1..], &digest, CommandOutput::Quiet).unwrap_err();
+        assert!(err.to_string().starts_with("hash mismatch"), "{}", err);
+    }
+}
+
+#[cfg(test)]
+mod test_civil_date {
+    use super::civil_date;
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(951782400), (2000, 2, 29));
+        assert_eq!(civil_date(1704067199), (2023, 12, 31));
+    }
+}
+
+#[cfg(test)]
+mod test_parse_duration {
+    use super::parse_duration;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(
+            parse_duration("30d").unwrap(),
+            Duration::from_secs(30 * 86400)
+        );
+        assert_eq!(parse_duration("12h").unwrap(), Duration::from_secs(43200));
+        assert_eq!(parse_duration("2w").unwrap(), Duration::from_secs(1209600));
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3 years").is_err());
+    }
+}
+
 #[cfg(test)]
 mod test_command_output {
     use super::CommandOutput;
@@ -541,6 +822,29 @@ mod test_command_output {
     }
 }
 
+#[cfg(test)]
+mod test_run_parallel {
+    use super::run_parallel;
+
+    #[test]
+    fn test_run_parallel_keeps_order() {
+        let items: Vec<_> = (0..20).collect();
+        assert_eq!(
+            run_parallel(4, items.clone(), |x| x * 2),
+            items.iter().map(|x| x * 2).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_run_parallel_serial() {
+        assert_eq!(
+            run_parallel(1, vec!["a", "b"], str::to_uppercase),
+            ["A", "B"]
+        );
+        assert_eq!(run_parallel(0, Vec::<u32>::new(), |x| x), Vec::<u32>::new());
+    }
+}
+
 #[cfg(test)]
 mod test_expand_env_vars {
     use super::expand_env_vars;
@@ -0,0 +1,812 @@
+//! Project templates for `rye init --template`.
+//!
+//! Two formats are supported.  Native templates have a `rye-template.toml`
+//! manifest next to the files, cookiecutter templates a `cookiecutter.json`.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Error};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use minijinja::{AutoEscape, Environment};
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use tempfile::{tempdir, TempDir};
+use toml_edit::DocumentMut;
+
+use crate::pyproject::BuildSystem;
+use crate::utils::{tui_theme, CommandOutput, IoPathContext};
+
+/// The manifest of a native template.
+const MANIFEST: &str = "rye-template.toml";
+
+/// The variables file of a cookiecutter template.
+const COOKIECUTTER_JSON: &str = "cookiecutter.json";
+
+type BuiltinFiles = &'static [(&'static str, &'static str)];
+
+/// The templates that ship with rye as `(name, files)`.
+const BUILTIN_TEMPLATES: &[(&str, BuiltinFiles)] = &[
+    (
+        "cli",
+        &[
+            (
+                MANIFEST,
+                include_str!("../templates/init/cli/rye-template.toml"),
+            ),
+            (
+                "src/{{ name_safe }}/__init__.py.j2",
+                include_str!("../templates/init/cli/__init__.py.j2"),
+            ),
+            (
+                "src/{{ name_safe }}/__main__.py.j2",
+                include_str!("../templates/init/cli/__main__.py.j2"),
+            ),
+            (
+                "tests/test_cli.py.j2",
+                include_str!("../templates/init/cli/test_cli.py.j2"),
+            ),
+        ],
+    ),
+    (
+        "lib",
+        &[
+            (
+                MANIFEST,
+                include_str!("../templates/init/lib/rye-template.toml"),
+            ),
+            (
+                "src/{{ name_safe }}/__init__.py.j2",
+                include_str!("../templates/init/lib/__init__.py.j2"),
+            ),
+            (
+                "src/{{ name_safe }}/py.typed",
+                include_str!("../templates/init/lib/py.typed"),
+            ),
+            (
+                "tests/test_{{ name_safe }}.py.j2",
+                include_str!("../templates/init/lib/test_lib.py.j2"),
+            ),
+        ],
+    ),
+    (
+        "fastapi",
+        &[
+            (
+                MANIFEST,
+                include_str!("../templates/init/fastapi/rye-template.toml"),
+            ),
+            (
+                "src/{{ name_safe }}/__init__.py.j2",
+                include_str!("../templates/init/fastapi/__init__.py.j2"),
+            ),
+            (
+                "tests/test_app.py.j2",
+                include_str!("../templates/init/fastapi/test_app.py.j2"),
+            ),
+            (
+                "Dockerfile.j2",
+                include_str!("../templates/init/fastapi/Dockerfile.j2"),
+            ),
+        ],
+    ),
+    (
+        "maturin",
+        &[
+            (
+                MANIFEST,
+                include_str!("../templates/init/maturin/rye-template.toml"),
+            ),
+            (
+                "Cargo.toml.j2",
+                include_str!("../templates/init/maturin/Cargo.toml.j2"),
+            ),
+            (
+                "src/lib.rs.j2",
+                include_str!("../templates/lib/maturin/lib.rs.j2"),
+            ),
+            (
+                "python/{{ name_safe }}/__init__.py.j2",
+                include_str!("../templates/lib/maturin/__init__.py.j2"),
+            ),
+            (
+                "tests/test_{{ name_safe }}.py.j2",
+                include_str!("../templates/init/maturin/test_lowlevel.py.j2"),
+            ),
+        ],
+    ),
+    (
+        "data-science",
+        &[
+            (
+                MANIFEST,
+                include_str!("../templates/init/data-science/rye-template.toml"),
+            ),
+            (
+                "src/{{ name_safe }}/__init__.py.j2",
+                include_str!("../templates/init/data-science/__init__.py.j2"),
+            ),
+            (
+                "data/.gitignore",
+                include_str!("../templates/init/data-science/data.gitignore"),
+            ),
+            (
+                "data/raw/.gitkeep",
+                include_str!("../templates/init/data-science/gitkeep"),
+            ),
+            (
+                "data/processed/.gitkeep",
+                include_str!("../templates/init/data-science/gitkeep"),
+            ),
+            (
+                "notebooks/exploration.ipynb.j2",
+                include_str!("../templates/init/data-science/exploration.ipynb.j2"),
+            ),
+            (
+                "tests/test_data.py.j2",
+                include_str!("../templates/init/data-science/test_data.py.j2"),
+            ),
+        ],
+    ),
+];
+
+/// Returns the names of the built-in templates.
+pub fn builtin_template_names() -> impl Iterator<Item = &'static str> {
+    BUILTIN_TEMPLATES.iter().map(|x| x.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TemplateFormat {
+    Native,
+    Cookiecutter,
+}
+
+/// The settings a native template declares for the generated `pyproject.toml`.
+#[derive(Debug, Default)]
+pub struct TemplateSettings {
+    pub is_script: bool,
+    pub build_system: Option<BuildSystem>,
+    pub dependencies: Vec<String>,
+    pub dev_dependencies: Vec<String>,
+    /// Scripts for `tool.rye.scripts` as `(name, command)`.
+    pub scripts: Vec<(String, String)>,
+}
+
+/// A project template ready to be rendered.
+pub struct ProjectTemplate {
+    name: String,
+    root: PathBuf,
+    format: TemplateFormat,
+    // keeps checkouts and materialized built-in templates alive
+    _tempdir: Option<TempDir>,
+}
+
+impl ProjectTemplate {
+    /// Locates a template by built-in name, local path or git URL.
+    pub fn resolve(spec: &str, output: CommandOutput) -> Result<ProjectTemplate, Error> {
+        let (root, tempdir) = if let Some((_, files)) =
+            BUILTIN_TEMPLATES.iter().find(|x| x.0 == spec)
+        {
+            let tempdir = tempdir()?;
+            for (path, contents) in files.iter() {
+                let target = tempdir.path().join(path);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, contents).path_context(&target, "unable to write template")?;
+            }
+            (tempdir.path().to_path_buf(), Some(tempdir))
+        } else if let Some(url) = git_url(spec) {
+            let tempdir = tempdir()?;
+            let checkout = tempdir.path().join("template");
+            echo!(if output, "Cloning template {}", url);
+            let status = Command::new("git")
+                .arg("clone")
+                .arg("--depth=1")
+                .arg(&url)
+                .arg(&checkout)
+                .stdout(Stdio::null())
+                .stderr(if output == CommandOutput::Verbose {
+                    Stdio::inherit()
+                } else {
+                    Stdio::null()
+                })
+                .status()
+                .context("unable to run git")?;
+            if !status.success() {
+                bail!("failed to clone template from {}", url);
+            }
+            (checkout, Some(tempdir))
+        } else {
+            let path = Path::new(spec);
+            if !path.is_dir() {
+                bail!(
+                    "template '{}' is not a directory, git URL or built-in template ({})",
+                    spec,
+                    builtin_template_names().collect::<Vec<_>>().join(", ")
+                );
+            }
+            (path.canonicalize()?, None)
+        };
+
+        let format = if root.join(MANIFEST).is_file() {
+            TemplateFormat::Native
+        } else if root.join(COOKIECUTTER_JSON).is_file() {
+            TemplateFormat::Cookiecutter
+        } else {
+            bail!(
+                "template '{}' has neither a {} nor a {}",
+                spec,
+                MANIFEST,
+                COOKIECUTTER_JSON
+            );
+        };
+
+        Ok(ProjectTemplate {
+            name: spec.to_string(),
+            root,
+            format,
+            _tempdir: tempdir,
+        })
+    }
+
+    /// Returns the `pyproject.toml` settings of the template.
+    pub fn settings(&self) -> Result<TemplateSettings, Error> {
+        let mut rv = TemplateSettings::default();
+        if self.format != TemplateFormat::Native {
+            return Ok(rv);
+        }
+        let manifest = self.manifest()?;
+        if let Some(project) = manifest.get("project") {
+            rv.is_script = project
+                .get("script")
+                .and_then(|x| x.as_bool())
+                .unwrap_or(false);
+            if let Some(build_system) = project.get("build-system").and_then(|x| x.as_str()) {
+                rv.build_system = Some(BuildSystem::from_str(build_system)?);
+            }
+            let strings = |key: &str| -> Vec<String> {
+                project
+                    .get(key)
+                    .and_then(|x| x.as_array())
+                    .map(|x| {
+                        x.iter()
+                            .filter_map(|x| x.as_str())
+                            .map(|x| x.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+            rv.dependencies = strings("dependencies");
+            rv.dev_dependencies = strings("dev-dependencies");
+        }
+        if let Some(scripts) = manifest.get("scripts").and_then(|x| x.as_table_like()) {
+            for (name, cmd) in scripts.iter() {
+                let cmd = cmd
+                    .as_str()
+                    .ok_or_else(|| anyhow!("script '{}' must be a string", name))?;
+                rv.scripts.push((name.to_string(), cmd.to_string()));
+            }
+        }
+        Ok(rv)
+    }
+
+    /// Renders the template into a folder.
+    ///
+    /// `context` holds the values rye computed for the project (name, author
+    /// etc.).  Template variables are taken from `overrides` first, then asked
+    /// for if `interactive` is set and otherwise use their defaults.  Files
+    /// that already exist are never overwritten.
+    pub fn render(
+        &self,
+        target: &Path,
+        mut context: Map<String, Value>,
+        overrides: &BTreeMap<String, String>,
+        interactive: bool,
+        output: CommandOutput,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut env = Environment::new();
+        env.set_auto_escape_callback(|_| AutoEscape::None);
+        env.set_keep_trailing_newline(true);
+
+        let (source, context, no_render, excluded) = match self.format {
+            TemplateFormat::Native => {
+                let manifest = self.manifest()?;
+                for (key, value) in native_variables(&manifest, &context, overrides, interactive)? {
+                    context.insert(key, value);
+                }
+                let mut skip = GlobSetBuilder::new();
+                skip.add(Glob::new(MANIFEST)?);
+                for (glob, condition) in native_file_conditions(&manifest)? {
+                    let expr = env
+                        .compile_expression(&condition)
+                        .with_context(|| format!("invalid file condition '{}'", condition))?;
+                    if !expr.eval(&context)?.is_true() {
+                        skip.add(glob);
+                    }
+                }
+                (
+                    self.root.clone(),
+                    Value::Object(context),
+                    None,
+                    skip.build()?,
+                )
+            }
+            TemplateFormat::Cookiecutter => {
+                if self.root.join("hooks").is_dir() && output != CommandOutput::Quiet {
+                    warn!("cookiecutter hooks are not supported and will not run");
+                }
+                let variables = cookiecutter_variables(
+                    &env,
+                    &self.root.join(COOKIECUTTER_JSON),
+                    &context,
+                    overrides,
+                    interactive,
+                )?;
+                let mut no_render = GlobSetBuilder::new();
+                if let Some(Value::Array(globs)) = variables.get("_copy_without_render") {
+                    for glob in globs.iter().filter_map(|x| x.as_str()) {
+                        no_render.add(Glob::new(glob)?);
+                    }
+                }
+                let source = find_cookiecutter_root(&self.root)?;
+                let mut cookiecutter = Map::new();
+                cookiecutter.insert("cookiecutter".into(), Value::Object(variables));
+                (
+                    source,
+                    Value::Object(cookiecutter),
+                    Some(no_render.build()?),
+                    GlobSet::empty(),
+                )
+            }
+        };
+
+        let mut written = Vec::new();
+        let walker = walkdir::WalkDir::new(&source)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != ".git");
+        for entry in walker {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let rel_path = entry.path().strip_prefix(&source)?;
+            let rel_str = rel_path.to_string_lossy().replace('\\', "/");
+            if excluded.is_match(&rel_str) {
+                continue;
+            }
+
+            let (rel_str, render_contents) = match &no_render {
+                // native templates only render files ending in .j2
+                None => match rel_str.strip_suffix(".j2") {
+                    Some(stripped) => (stripped.to_string(), true),
+                    None => (rel_str.clone(), false),
+                },
+                Some(no_render) => (rel_str.clone(), !no_render.is_match(&rel_str)),
+            };
+            let rendered_path = if rel_str.contains("{{") || rel_str.contains("{%") {
+                env.render_str(&rel_str, &context)
+                    .with_context(|| format!("unable to render path {}", rel_str))?
+            } else {
+                rel_str
+            };
+            // paths rendering to an empty component are skipped like in cookiecutter
+            if rendered_path.split('/').any(|x| x.trim().is_empty()) {
+                continue;
+            }
+
+            let dst = target.join(&rendered_path);
+            if dst.exists() {
+                if output != CommandOutput::Quiet {
+                    warn!("not overwriting existing file {}", rendered_path);
+                }
+                continue;
+            }
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent).path_context(parent, "unable to create folder")?;
+            }
+            let bytes = fs::read(entry.path()).path_context(entry.path(), "unable to read")?;
+            match String::from_utf8(bytes) {
+                Ok(contents) if render_contents => {
+                    let rv = env
+                        .render_str(&contents, &context)
+                        .with_context(|| format!("unable to render {}", rel_path.display()))?;
+                    fs::write(&dst, rv).path_context(&dst, "unable to write file")?;
+                }
+                Ok(contents) => {
+                    fs::write(&dst, contents).path_context(&dst, "unable to write file")?
+                }
+                Err(err) => {
+                    fs::write(&dst, err.into_bytes()).path_context(&dst, "unable to write file")?
+                }
+            }
+            echo!(if verbose output, "created {}", rendered_path);
+            written.push(dst);
+        }
+
+        Ok(written)
+    }
+
+    fn manifest(&self) -> Result<DocumentMut, Error> {
+        let path = self.root.join(MANIFEST);
+        fs::read_to_string(&path)
+            .path_context(&path, "unable to read template manifest")?
+            .parse::<DocumentMut>()
+            .with_context(|| format!("invalid template manifest in {}", self.name))
+    }
+}
+
+/// Returns the URL to clone if the template refers to a git repository.
+fn git_url(spec: &str) -> Option<String> {
+    if let Some(url) = spec.strip_prefix("git+") {
+        Some(url.to_string())
+    } else if let Some(repo) = spec.strip_prefix("gh:") {
+        Some(format!("https://github.com/{}.git", repo))
+    } else if spec.starts_with("https://")
+        || spec.starts_with("http://")
+        || spec.starts_with("ssh://")
+        || spec.starts_with("git@")
+        || (spec.ends_with(".git") && !Path::new(spec).is_dir())
+    {
+        Some(spec.to_string())
+    } else {
+        None
+    }
+}
+
+/// Resolves the `[variables]` of a native template.
+fn native_variables(
+    manifest: &DocumentMut,
+    context: &Map<String, Value>,
+    overrides: &BTreeMap<String, String>,
+    interactive: bool,
+) -> Result<Vec<(String, Value)>, Error> {
+    let mut rv = Vec::new();
+    let variables = match manifest.get("variables").and_then(|x| x.as_table_like()) {
+        Some(variables) => variables,
+        None => return Ok(rv),
+    };
+    for (key, spec) in variables.iter() {
+        if context.contains_key(key) {
+            bail!("template variable '{}' shadows a built-in variable", key);
+        }
+        let default = match spec.get("default") {
+            Some(value) if value.is_bool() => Value::Bool(value.as_bool().unwrap()),
+            Some(value) => Value::String(
+                value
+                    .as_str()
+                    .map(|x| x.to_string())
+                    .or_else(|| value.as_integer().map(|x| x.to_string()))
+                    .ok_or_else(|| anyhow!("unsupported default for variable '{}'", key))?,
+            ),
+            None => Value::String(String::new()),
+        };
+        let choices = spec.get("choices").and_then(|x| x.as_array()).map(|x| {
+            x.iter()
+                .filter_map(|x| x.as_str())
+                .map(|x| Value::String(x.to_string()))
+                .collect::<Vec<_>>()
+        });
+        let prompt = spec
+            .get("prompt")
+            .and_then(|x| x.as_str())
+            .unwrap_or(key)
+            .to_string();
+        let value = resolve_variable(
+            key,
+            &prompt,
+            default,
+            choices.as_deref(),
+            overrides,
+            interactive,
+        )?;
+        rv.push((key.to_string(), value));
+    }
+    Ok(rv)
+}
+
+/// Returns the globs of conditional files with the expression that includes them.
+fn native_file_conditions(manifest: &DocumentMut) -> Result<Vec<(Glob, String)>, Error> {
+    let mut rv = Vec::new();
+    if let Some(files) = manifest.get("files").and_then(|x| x.as_table_like()) {
+        for (pattern, condition) in files.iter() {
+            let condition = condition
+                .as_str()
+                .ok_or_else(|| anyhow!("condition for '{}' must be a string", pattern))?;
+            // the condition applies to the file with and without .j2 suffix
+            let glob = Glob::new(&format!("{{{},{}.j2}}", pattern, pattern))?;
+            rv.push((glob, condition.to_string()));
+        }
+    }
+    Ok(rv)
+}
+
+/// A JSON object that remembers the order of its keys.
+struct OrderedObject(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for OrderedObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ObjectVisitor;
+
+        impl<'de> Visitor<'de> for ObjectVisitor {
+            type Value = OrderedObject;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut rv = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    rv.push(entry);
+                }
+                Ok(OrderedObject(rv))
+            }
+        }
+
+        deserializer.deserialize_map(ObjectVisitor)
+    }
+}
+
+/// Resolves the variables of a `cookiecutter.json` in the order they are declared.
+///
+/// String defaults are templates themselves and can refer to earlier variables.
+fn cookiecutter_variables(
+    env: &Environment,
+    path: &Path,
+    context: &Map<String, Value>,
+    overrides: &BTreeMap<String, String>,
+    interactive: bool,
+) -> Result<Map<String, Value>, Error> {
+    let contents = fs::read_to_string(path).path_context(path, "unable to read template")?;
+    let declared: OrderedObject =
+        serde_json::from_str(&contents).path_context(path, "invalid cookiecutter.json")?;
+
+    let mut rv = Map::new();
+    for (key, value) in declared.0 {
+        // private variables are passed through as they are
+        if key.starts_with('_') && !key.starts_with("__") {
+            rv.insert(key, value);
+            continue;
+        }
+        let render = |value: &str| -> Result<Value, Error> {
+            let mut ctx = Map::new();
+            ctx.insert("cookiecutter".into(), Value::Object(rv.clone()));
+            Ok(Value::String(env.render_str(value, &ctx).with_context(
+                || format!("unable to render default of '{}'", key),
+            )?))
+        };
+        let value = match value {
+            // the project name defaults to the one rye picked
+            Value::String(_) if key == "project_name" => {
+                context.get("name").cloned().unwrap_or(value)
+            }
+            Value::String(ref default) => render(default)?,
+            Value::Array(ref choices) => Value::Array(
+                choices
+                    .iter()
+                    .map(|x| match x {
+                        Value::String(s) => render(s),
+                        other => Ok(other.clone()),
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            other => other,
+        };
+        let value = if key.starts_with("__") {
+            value
+        } else {
+            match value {
+                Value::Array(choices) if !choices.is_empty() => resolve_variable(
+                    &key,
+                    &key,
+                    choices[0].clone(),
+                    Some(&choices),
+                    overrides,
+                    interactive,
+                )?,
+                value @ (Value::String(_) | Value::Bool(_)) => {
+                    resolve_variable(&key, &key, value, None, overrides, interactive)?
+                }
+                other => other,
+            }
+        };
+        rv.insert(key, value);
+    }
+    Ok(rv)
+}
+
+/// Determines the value of a single variable.
+fn resolve_variable(
+    key: &str,
+    prompt: &str,
+    default: Value,
+    choices: Option<&[Value]>,
+    overrides: &BTreeMap<String, String>,
+    interactive: bool,
+) -> Result<Value, Error> {
+    if let Some(value) = overrides.get(key) {
+        return match default {
+            Value::Bool(_) => match value.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "y" | "on" => Ok(Value::Bool(true)),
+                "0" | "false" | "no" | "n" | "off" => Ok(Value::Bool(false)),
+                _ => bail!("variable '{}' expects a boolean, got '{}'", key, value),
+            },
+            _ => Ok(Value::String(value.clone())),
+        };
+    }
+    if !interactive {
+        return Ok(default);
+    }
+
+    if let Some(choices) = choices {
+        let items = choices
+            .iter()
+            .map(|x| x.as_str().map(|x| x.to_string()).unwrap_or(x.to_string()))
+            .collect::<Vec<_>>();
+        let idx = dialoguer::Select::with_theme(tui_theme())
+            .with_prompt(prompt)
+            .items(&items)
+            .default(0)
+            .interact()?;
+        return Ok(choices[idx].clone());
+    }
+
+    match default {
+        Value::Bool(default) => Ok(Value::Bool(
+            dialoguer::Confirm::with_theme(tui_theme())
+                .with_prompt(prompt)
+                .default(default)
+                .interact()?,
+        )),
+        default => {
+            let default = default.as_str().unwrap_or_default().to_string();
+            Ok(Value::String(
+                dialoguer::Input::<String>::with_theme(tui_theme())
+                    .with_prompt(prompt)
+                    .default(default)
+                    .allow_empty(true)
+                    .interact_text()?,
+            ))
+        }
+    }
+}
+
+/// Finds the folder of a cookiecutter template that holds the project files.
+fn find_cookiecutter_root(root: &Path) -> Result<PathBuf, Error> {
+    fs::read_dir(root)
+        .path_context(root, "unable to read template")?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .map_or(false, |x| x.to_string_lossy().contains("cookiecutter."))
+        })
+        .ok_or_else(|| anyhow!("cookiecutter template has no {{{{cookiecutter.*}}}} folder"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_builtin(name: &str, overrides: &[(&str, &str)]) -> (TempDir, Vec<String>) {
+        let template = ProjectTemplate::resolve(name, CommandOutput::Quiet).unwrap();
+        let out = tempdir().unwrap();
+        let mut context = Map::new();
+        context.insert("name".into(), Value::String("my-app".into()));
+        context.insert("name_safe".into(), Value::String("my_app".into()));
+        context.insert("description".into(), Value::String("An app".into()));
+        context.insert("python".into(), Value::String("3.12".into()));
+        let overrides = overrides
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut files = template
+            .render(out.path(), context, &overrides, false, CommandOutput::Quiet)
+            .unwrap()
+            .into_iter()
+            .map(|x| {
+                x.strip_prefix(out.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        (out, files)
+    }
+
+    #[test]
+    fn test_builtin_cli() {
+        let (out, files) = render_builtin("cli", &[]);
+        assert_eq!(
+            files,
+            [
+                "src/my_app/__init__.py",
+                "src/my_app/__main__.py",
+                "tests/test_cli.py"
+            ]
+        );
+        let main = fs::read_to_string(out.path().join("src/my_app/__main__.py")).unwrap();
+        assert!(main.contains("from my_app import main"));
+    }
+
+    #[test]
+    fn test_conditional_files() {
+        let (_, files) = render_builtin("fastapi", &[]);
+        assert!(!files.contains(&"Dockerfile".to_string()));
+        let (out, files) = render_builtin("fastapi", &[("docker", "yes"), ("port", "9000")]);
+        assert!(files.contains(&"Dockerfile".to_string()));
+        let dockerfile = fs::read_to_string(out.path().join("Dockerfile")).unwrap();
+        assert!(dockerfile.contains("FROM python:3.12-slim"));
+        assert!(dockerfile.contains("EXPOSE 9000"));
+    }
+
+    #[test]
+    fn test_builtin_settings() {
+        let template = ProjectTemplate::resolve("maturin", CommandOutput::Quiet).unwrap();
+        let settings = template.settings().unwrap();
+        assert_eq!(settings.build_system, Some(BuildSystem::Maturin));
+        assert!(!settings.is_script);
+    }
+
+    #[test]
+    fn test_builtin_data_science() {
+        let (out, files) = render_builtin("data-science", &[]);
+        assert!(files.contains(&"data/raw/.gitkeep".to_string()));
+        assert!(files.contains(&"data/processed/.gitkeep".to_string()));
+        let notebook = fs::read_to_string(out.path().join("notebooks/exploration.ipynb")).unwrap();
+        let notebook: Value = serde_json::from_str(&notebook).unwrap();
+        assert_eq!(notebook["metadata"]["kernelspec"]["name"], "my-app");
+
+        let template = ProjectTemplate::resolve("data-science", CommandOutput::Quiet).unwrap();
+        let settings = template.settings().unwrap();
+        assert!(settings
+            .scripts
+            .contains(&("kernel".to_string(), "rye kernel install".to_string())));
+    }
+
+    #[test]
+    fn test_cookiecutter() {
+        let template_dir = tempdir().unwrap();
+        let root = template_dir.path();
+        fs::write(
+            root.join(COOKIECUTTER_JSON),
+            r#"{"project_name": "x", "project_slug": "{{ cookiecutter.project_name|replace('-', '_') }}", "license": ["MIT", "BSD"]}"#,
+        )
+        .unwrap();
+        let project_dir = root.join("{{cookiecutter.project_slug}}");
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(
+            project_dir.join("README.md"),
+            "{{ cookiecutter.project_name }} ({{ cookiecutter.license }})\n",
+        )
+        .unwrap();
+
+        let template =
+            ProjectTemplate::resolve(&root.to_string_lossy(), CommandOutput::Quiet).unwrap();
+        let out = tempdir().unwrap();
+        let mut context = Map::new();
+        context.insert("name".into(), Value::String("my-app".into()));
+        template
+            .render(
+                out.path(),
+                context,
+                &BTreeMap::new(),
+                false,
+                CommandOutput::Quiet,
+            )
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(out.path().join("README.md")).unwrap(),
+            "my-app (MIT)\n"
+        );
+    }
+}
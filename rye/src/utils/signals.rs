@@ -0,0 +1,252 @@
+//! Signal forwarding for the commands rye waits on.
+//!
+//! When rye execs a command the command receives signals directly.  Whenever
+//! rye has to stay around (chained and parallel scripts, dependencies, Windows)
+//! the children are tracked here so that termination requests reach them and
+//! rye can report how they ended.
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use anyhow::{Context, Error};
+
+/// The maximum number of children which are tracked at once.
+const MAX_CHILDREN: usize = 64;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: AtomicI32 = AtomicI32::new(0);
+
+/// The children rye currently waits on.
+///
+/// A positive value is the pid of a child in rye's own process group, a
+/// negative value is the (negated) id of a process group rye created for a
+/// child.  Either way the value can be passed to `kill` directly.  This is a
+/// plain array of atomics as it's read from within a signal handler.
+static CHILDREN: [AtomicI32; MAX_CHILDREN] = [EMPTY_SLOT; MAX_CHILDREN];
+
+/// Keeps a child registered for signal forwarding until dropped.
+pub struct TrackedChild {
+    slot: Option<usize>,
+}
+
+impl Drop for TrackedChild {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot {
+            CHILDREN[slot].store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Registers a child so that signals sent to rye are forwarded to it.
+///
+/// If `own_group` is set the child must have been spawned with
+/// [`in_own_process_group`] and the entire group is signalled.
+pub fn track(child: &Child, own_group: bool) -> TrackedChild {
+    install_handlers();
+    let target = if own_group {
+        -(child.id() as i32)
+    } else {
+        child.id() as i32
+    };
+    let slot = CHILDREN.iter().position(|slot| {
+        slot.compare_exchange(0, target, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    });
+    TrackedChild { slot }
+}
+
+/// Spawns a command and waits for it while forwarding signals.
+pub fn spawn_and_wait(cmd: &mut Command) -> Result<ExitStatus, Error> {
+    let mut child = cmd.spawn().context("unable to spawn command")?;
+    let _tracked = track(&child, false);
+    Ok(child.wait()?)
+}
+
+/// Makes a command start in a process group of its own.
+///
+/// Such a child no longer receives signals from the terminal, so it relies
+/// on rye forwarding them.  On Windows this does nothing as a new process
+/// group would also stop ctrl-c from reaching the child, console events
+/// reach all children directly and rye only waits for them (see
+/// `console_ctrl_handler`).
+pub fn in_own_process_group(cmd: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    cmd
+}
+
+/// Kills a child spawned with [`in_own_process_group`] and waits for it.
+///
+/// On unix the processes the child started are killed along with it.
+pub fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        unsafe {
+            libc::kill(-(child.id() as i32), libc::SIGKILL);
+        }
+    }
+    child.kill().ok();
+    child.wait().ok();
+}
+
+/// Forwards an interrupt to the tracked children.
+///
+/// Children in rye's process group already received the interrupt from the
+/// terminal, so only the ones in their own group are signalled.  Returns
+/// `true` if there are children rye should wait for rather than exit.
+pub fn forward_interrupt() -> bool {
+    let mut waiting = false;
+    for slot in &CHILDREN {
+        let target = slot.load(Ordering::SeqCst);
+        if target == 0 {
+            continue;
+        }
+        waiting = true;
+        #[cfg(unix)]
+        {
+            if target < 0 {
+                unsafe {
+                    libc::kill(target, libc::SIGINT);
+                }
+            }
+        }
+    }
+    waiting
+}
+
+/// Returns the exit code to report for a child's exit status.
+///
+/// A child killed by a signal is reported the way shells do, as 128 plus
+/// the number of the signal.
+pub fn exit_code(status: &ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+#[cfg(unix)]
+fn install_handlers() {
+    use std::sync::Once;
+
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| unsafe {
+        for signal in [libc::SIGTERM, libc::SIGHUP] {
+            libc::signal(
+                signal,
+                forward_signal as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+    });
+}
+
+#[cfg(windows)]
+fn install_handlers() {
+    use std::sync::Once;
+    use winapi::um::consoleapi::SetConsoleCtrlHandler;
+
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| unsafe {
+        SetConsoleCtrlHandler(Some(console_ctrl_handler), 1);
+    });
+}
+
+/// Handles ctrl-break and closing the console while rye waits on children.
+///
+/// Console events are delivered to every process attached to the console, so
+/// the children already received the event.  On ctrl-break rye keeps waiting
+/// and exits with the exit code of the children as usual.  When the console
+/// is closed Windows terminates rye as soon as the handler returns, so the
+/// handler waits for the children itself and exits with the exit code of the
+/// last one.  Ctrl-c is left to the ctrl-c handler.
+#[cfg(windows)]
+unsafe extern "system" fn console_ctrl_handler(
+    ctrl_type: winapi::shared::minwindef::DWORD,
+) -> winapi::shared::minwindef::BOOL {
+    use winapi::shared::minwindef::{FALSE, TRUE};
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{ExitProcess, GetExitCodeProcess, OpenProcess};
+    use winapi::um::synchapi::WaitForSingleObject;
+    use winapi::um::winbase::INFINITE;
+    use winapi::um::wincon::{CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT};
+    use winapi::um::winnt::{PROCESS_QUERY_LIMITED_INFORMATION, SYNCHRONIZE};
+
+    let pids = CHILDREN
+        .iter()
+        .map(|slot| slot.load(Ordering::SeqCst).unsigned_abs())
+        .filter(|pid| *pid != 0);
+    match ctrl_type {
+        CTRL_BREAK_EVENT => {
+            if pids.count() > 0 {
+                TRUE
+            } else {
+                FALSE
+            }
+        }
+        CTRL_CLOSE_EVENT => {
+            let mut exit_code = None;
+            for pid in pids {
+                let handle =
+                    OpenProcess(SYNCHRONIZE | PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid);
+                if handle.is_null() {
+                    continue;
+                }
+                WaitForSingleObject(handle, INFINITE);
+                let mut code = 0;
+                if GetExitCodeProcess(handle, &mut code) != 0 {
+                    exit_code = Some(code);
+                }
+                CloseHandle(handle);
+            }
+            match exit_code {
+                Some(code) => {
+                    ExitProcess(code);
+                    TRUE
+                }
+                None => FALSE,
+            }
+        }
+        _ => FALSE,
+    }
+}
+
+/// Forwards a termination signal to the tracked children.
+///
+/// Once rye has no children left the signal is handled the default way,
+/// which terminates rye.  Only async-signal-safe functions are used here.
+#[cfg(unix)]
+extern "C" fn forward_signal(signal: libc::c_int) {
+    let mut forwarded = false;
+    for slot in &CHILDREN {
+        let target = slot.load(Ordering::SeqCst);
+        if target != 0 {
+            unsafe {
+                libc::kill(target, signal);
+            }
+            forwarded = true;
+        }
+    }
+    if !forwarded {
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exit_code() {
+    use std::os::unix::process::ExitStatusExt;
+
+    assert_eq!(exit_code(&ExitStatus::from_raw(0)), 0);
+    assert_eq!(exit_code(&ExitStatus::from_raw(3 << 8)), 3);
+    assert_eq!(exit_code(&ExitStatus::from_raw(libc::SIGTERM)), 143);
+    assert_eq!(exit_code(&ExitStatus::from_raw(libc::SIGINT)), 130);
+}
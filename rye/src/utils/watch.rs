@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Error};
+use console::style;
+use walkdir::WalkDir;
+
+use crate::utils::signals::in_own_process_group;
+use crate::utils::IoPathContext;
+
+/// How often the watched paths are scanned for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// How long a process gets between checks while shutting down.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// How long a process gets to shut down before it's killed.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The process group of the currently running command (0 if none).
+static CURRENT_PROCESS_GROUP: AtomicU32 = AtomicU32::new(0);
+
+/// The modification time and size of every watched file.
+type Snapshot = HashMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// Runs a command and restarts it whenever a file in one of the paths changes.
+///
+/// Changes are detected by periodically scanning the paths, which works the
+/// same on every platform and file system (including network shares).  The
+/// command is started in its own process group so that the entire group
+/// can be stopped before restarting.  This only returns on error.
+pub fn watch_and_rerun<F>(paths: &[PathBuf], mut make_cmd: F) -> Result<Infallible, Error>
+where
+    F: FnMut() -> Command,
+{
+    let roots = paths
+        .iter()
+        .map(|path| {
+            path.canonicalize()
+                .path_context(path, "unable to watch path")
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut snapshot = take_snapshot(&roots);
+    loop {
+        let mut child = spawn_in_process_group(&mut make_cmd())?;
+
+        // wait for the first change, then until things settled down.
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = take_snapshot(&roots);
+            if current != snapshot {
+                snapshot = current;
+                break;
+            }
+        }
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = take_snapshot(&roots);
+            if current == snapshot {
+                break;
+            }
+            snapshot = current;
+        }
+
+        stop(&mut child)?;
+        elog!("{}", style("Change detected, restarting").dim());
+    }
+}
+
+/// Records the state of all files below the roots that are not ignored.
+fn take_snapshot(roots: &[PathBuf]) -> Snapshot {
+    let mut rv = Snapshot::new();
+    for root in roots {
+        for entry in WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !is_ignored(roots, entry.path()))
+            .filter_map(|entry| entry.ok())
+        {
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    rv.insert(
+                        entry.into_path(),
+                        (metadata.modified().ok(), metadata.len()),
+                    );
+                }
+            }
+        }
+    }
+    rv
+}
+
+/// Stops the currently running command, if any.
+///
+/// This is invoked when rye itself is interrupted, as the command does not
+/// receive the signal from the terminal when it runs in its own process group.
+pub fn stop_current() {
+    let group = CURRENT_PROCESS_GROUP.swap(0, Ordering::Relaxed);
+    if group != 0 {
+        signal_process_group(group, false);
+    }
+}
+
+/// Changes in hidden folders (like `.venv` or `.git`) and bytecode caches
+/// never cause a restart.
+fn is_ignored(roots: &[PathBuf], path: &Path) -> bool {
+    let relative = roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path);
+    relative.components().any(|component| {
+        let name = component.as_os_str().to_string_lossy();
+        name.starts_with('.') || name == "__pycache__"
+    }) || relative.extension().map_or(false, |ext| ext == "pyc")
+}
+
+fn spawn_in_process_group(cmd: &mut Command) -> Result<Child, Error> {
+    let child = in_own_process_group(cmd)
+        .spawn()
+        .context("unable to spawn command")?;
+    CURRENT_PROCESS_GROUP.store(child.id(), Ordering::Relaxed);
+    Ok(child)
+}
+
+/// Asks the process group of a child to shut down and kills it if it does
+/// not do so in time.
+fn stop(child: &mut Child) -> Result<(), Error> {
+    CURRENT_PROCESS_GROUP.store(0, Ordering::Relaxed);
+    if child.try_wait()?.is_some() {
+        return Ok(());
+    }
+
+    signal_process_group(child.id(), false);
+    let mut waited = Duration::ZERO;
+    while waited < SHUTDOWN_TIMEOUT {
+        if child.try_wait()?.is_some() {
+            return Ok(());
+        }
+        thread::sleep(DEBOUNCE);
+        waited += DEBOUNCE;
+    }
+
+    signal_process_group(child.id(), true);
+    child.kill().ok();
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn signal_process_group(group: u32, force: bool) {
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    unsafe {
+        libc::kill(-(group as libc::pid_t), signal);
+    }
+}
+
+#[cfg(windows)]
+fn signal_process_group(group: u32, _force: bool) {
+    // there are no process groups we could signal, so terminate the tree.
+    std::process::Command::new("taskkill")
+        .arg("/F")
+        .arg("/T")
+        .arg("/PID")
+        .arg(group.to_string())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .ok();
+}
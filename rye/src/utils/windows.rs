@@ -8,6 +8,19 @@ use winreg::enums::{RegType, HKEY_CURRENT_USER, KEY_READ, KEY_WRITE};
 use winreg::{RegKey, RegValue};
 
 const RYE_UNINSTALL_ENTRY: &str = r"Software\Microsoft\Windows\CurrentVersion\Uninstall\Rye";
+const RYE_PEP514_COMPANY: &str = r"Software\Python\Rye";
+
+/// An interpreter as registered under the PEP 514 keys.
+pub(crate) struct Pep514Entry<'a> {
+    pub tag: &'a str,
+    pub display_name: &'a str,
+    pub version: String,
+    pub sys_version: String,
+    pub sys_architecture: &'a str,
+    pub install_path: &'a Path,
+    pub executable: &'a Path,
+    pub windowed_executable: Option<&'a Path>,
+}
 
 pub(crate) fn add_to_path(rye_home: &Path) -> Result<(), Error> {
     let target_path = reverse_resolve_user_profile(rye_home.join("shims"));
@@ -207,6 +220,65 @@ pub(crate) fn remove_from_programs() -> Result<(), Error> {
     }
 }
 
+/// Registers an interpreter under `HKCU\Software\Python\Rye` as described
+/// by PEP 514 so that editors and the `py` launcher can discover it.
+pub(crate) fn register_pep514(entry: &Pep514Entry) -> Result<(), Error> {
+    let root = RegKey::predef(HKEY_CURRENT_USER);
+    let (company, _) = root.create_subkey(RYE_PEP514_COMPANY)?;
+    company.set_value("DisplayName", &"Rye")?;
+    company.set_value("SupportUrl", &"https://rye.astral.sh/")?;
+
+    let (tag, _) = company.create_subkey(entry.tag)?;
+    tag.set_value("DisplayName", &entry.display_name)?;
+    tag.set_value("SupportUrl", &"https://rye.astral.sh/")?;
+    tag.set_value("Version", &entry.version)?;
+    tag.set_value("SysVersion", &entry.sys_version)?;
+    tag.set_value("SysArchitecture", &entry.sys_architecture)?;
+
+    let (install_path, _) = tag.create_subkey("InstallPath")?;
+    install_path.set_value("", &entry.install_path.as_os_str())?;
+    install_path.set_value("ExecutablePath", &entry.executable.as_os_str())?;
+    if let Some(windowed) = entry.windowed_executable {
+        install_path.set_value("WindowedExecutablePath", &windowed.as_os_str())?;
+    }
+    Ok(())
+}
+
+/// Removes an interpreter from the PEP 514 keys again.
+pub(crate) fn unregister_pep514(tag: &str) -> Result<(), Error> {
+    let root = RegKey::predef(HKEY_CURRENT_USER);
+    match root.delete_subkey_all(format!(r"{}\{}", RYE_PEP514_COMPANY, tag)) {
+        Ok(()) => Ok(()),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+/// Removes all registered interpreters whose executable no longer exists.
+pub(crate) fn prune_pep514() -> Result<(), Error> {
+    let root = RegKey::predef(HKEY_CURRENT_USER);
+    let company = match root.open_subkey(RYE_PEP514_COMPANY) {
+        Ok(company) => company,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(anyhow!(e)),
+    };
+    let mut stale = Vec::new();
+    for tag in company.enum_keys() {
+        let tag = tag?;
+        let executable: Option<OsString> = company
+            .open_subkey(format!(r"{}\InstallPath", tag))
+            .and_then(|x| x.get_value("ExecutablePath"))
+            .ok();
+        if executable.map_or(true, |x| !Path::new(&x).is_file()) {
+            stale.push(tag);
+        }
+    }
+    for tag in stale {
+        unregister_pep514(&tag)?;
+    }
+    Ok(())
+}
+
 /// Convert a vector UCS-2 chars to a null-terminated UCS-2 string in bytes
 pub(crate) fn to_winreg_bytes(mut v: Vec<u16>) -> Vec<u8> {
     v.push(0);
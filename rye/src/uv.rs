@@ -1,21 +1,64 @@
 use crate::bootstrap::download_url;
-use crate::lock::make_project_root_fragment;
+use crate::lock::{make_project_root_fragment, ResolutionError};
 use crate::platform::get_app_dir;
 use crate::pyproject::{read_venv_marker, write_venv_marker, ExpandedSources};
 use crate::sources::py::PythonVersion;
 use crate::sources::uv::{UvDownload, UvRequest};
+use crate::utils::filelock::FileLock;
 use crate::utils::{
     check_checksum, set_proxy_variables, unpack_archive, update_venv_sync_marker, CommandOutput,
     IoPathContext,
 };
 use anyhow::{anyhow, Context, Error};
 use pep508_rs::Requirement;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs::{self, remove_dir_all};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::time::UNIX_EPOCH;
 use tempfile::NamedTempFile;
 
+/// Name of the file next to the uv binary that describes it.
+const UV_RECORD_FILENAME: &str = "rye-uv.json";
+
+/// The uv binary as it was installed, to detect damaged installations.
+#[derive(Serialize, Deserialize)]
+struct UvRecord {
+    size: u64,
+    sha256: String,
+    modified: u64,
+}
+
+impl UvRecord {
+    fn from_binary(uv_bin: &Path) -> Result<UvRecord, Error> {
+        let contents = fs::read(uv_bin).path_context(uv_bin, "failed to read uv binary")?;
+        let metadata = fs::metadata(uv_bin).path_context(uv_bin, "failed to read uv binary")?;
+        Ok(UvRecord {
+            size: contents.len() as u64,
+            sha256: hex::encode(Sha256::digest(&contents)),
+            modified: modified_ns(&metadata).unwrap_or_default(),
+        })
+    }
+
+    fn read(uv_dir: &Path) -> Option<UvRecord> {
+        serde_json::from_slice(&fs::read(uv_dir.join(UV_RECORD_FILENAME)).ok()?).ok()
+    }
+
+    fn write(&self, uv_dir: &Path) -> Result<(), Error> {
+        let path = uv_dir.join(UV_RECORD_FILENAME);
+        fs::write(&path, serde_json::to_string(self)?)
+            .path_context(&path, "failed to write uv record")
+    }
+}
+
+/// Returns the modification time of a file in nanoseconds since the epoch.
+fn modified_ns(metadata: &fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64)
+}
+
 #[derive(Default)]
 pub struct UvInstallOptions {
     pub importlib_workaround: bool,
@@ -33,10 +76,13 @@ pub enum UvPackageUpgrade {
 
 struct UvCompileOptions {
     pub allow_prerelease: bool,
+    pub generate_hashes: bool,
     pub exclude_newer: Option<String>,
     pub upgrade: UvPackageUpgrade,
+    pub overrides: Option<PathBuf>,
     pub no_deps: bool,
     pub no_header: bool,
+    pub python_platform: Option<String>,
 }
 
 impl UvCompileOptions {
@@ -53,10 +99,22 @@ impl UvCompileOptions {
             cmd.arg("--prerelease=allow");
         }
 
+        if self.generate_hashes {
+            cmd.arg("--generate-hashes");
+        }
+
         if let Some(dt) = self.exclude_newer {
             cmd.arg("--exclude-newer").arg(dt);
         }
 
+        if let Some(platform) = self.python_platform {
+            cmd.arg("--python-platform").arg(platform);
+        }
+
+        if let Some(overrides) = self.overrides {
+            cmd.arg("--override").arg(overrides);
+        }
+
         match self.upgrade {
             UvPackageUpgrade::All => {
                 cmd.arg("--upgrade");
@@ -75,10 +133,13 @@ impl Default for UvCompileOptions {
     fn default() -> Self {
         Self {
             allow_prerelease: false,
+            generate_hashes: false,
             exclude_newer: None,
             upgrade: UvPackageUpgrade::Nothing,
+            overrides: None,
             no_deps: false,
             no_header: false,
+            python_platform: None,
         }
     }
 }
@@ -160,35 +221,116 @@ impl Uv {
         let download = UvDownload::try_from(UvRequest::default())?;
         let base_dir = get_app_dir().join("uv");
         let uv_dir = base_dir.join(download.version());
-        let uv_bin = if cfg!(windows) {
-            let mut bin = uv_dir.join("uv");
-            bin.set_extension("exe");
-            bin
-        } else {
-            uv_dir.join("uv")
+        let uv_bin = Self::bin_path(&uv_dir);
+
+        if !Self::verify(&uv_dir, &uv_bin) {
+            // another rye process might be installing uv right now.
+            let _lock = FileLock::acquire("uv", "installing uv", output)?;
+            if !Self::verify(&uv_dir, &uv_bin) {
+                if uv_dir.exists() {
+                    warn!(
+                        "uv installation at '{}' is damaged, downloading it again",
+                        uv_dir.display()
+                    );
+                    remove_dir_all(&uv_dir)
+                        .path_context(&uv_dir, "failed to remove damaged uv installation")?;
+                }
+                Self::download(&download, &uv_dir, output)?;
+                Self::cleanup_old_versions(&base_dir, &uv_dir)?;
+            }
+        }
+
+        Ok(Uv {
+            output,
+            uv_bin,
+            workdir,
+            sources,
+        })
+    }
+
+    /// Downloads uv again, even if the installed binary looks intact.
+    pub fn reinstall(output: CommandOutput) -> Result<(), Error> {
+        let download = UvDownload::try_from(UvRequest::default())?;
+        let uv_dir = get_app_dir().join("uv").join(download.version());
+        let _lock = FileLock::acquire("uv", "installing uv", output)?;
+        if uv_dir.exists() {
+            remove_dir_all(&uv_dir).path_context(&uv_dir, "failed to remove uv installation")?;
+        }
+        Self::download(&download, &uv_dir, output)
+    }
+
+    /// Checks that the uv binary is intact.
+    ///
+    /// The binary is trusted as long as its size and modification time match
+    /// what was recorded when it was installed.  Otherwise its hash has to
+    /// match and it has to pass a smoke test.
+    fn verify(uv_dir: &Path, uv_bin: &Path) -> bool {
+        let metadata = match fs::metadata(uv_bin) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => return false,
         };
+        let recorded = UvRecord::read(uv_dir);
+        if let Some(ref recorded) = recorded {
+            if recorded.size != metadata.len() {
+                return false;
+            }
+            if modified_ns(&metadata) == Some(recorded.modified) {
+                return true;
+            }
+        }
+        let current = match UvRecord::from_binary(uv_bin) {
+            Ok(current) => current,
+            Err(_) => return false,
+        };
+        if recorded.map_or(false, |x| x.sha256 != current.sha256)
+            || Self::smoke_test(uv_bin).is_err()
+        {
+            return false;
+        }
+        // installations of older rye versions have no record yet.
+        current.write(uv_dir).ok();
+        true
+    }
 
-        if uv_dir.exists() && uv_bin.is_file() {
-            return Ok(Uv {
-                output,
-                uv_bin,
-                workdir,
-                sources,
-            });
+    /// Runs `uv --version` and returns the reported version.
+    fn smoke_test(uv_bin: &Path) -> Result<String, Error> {
+        let output = Command::new(uv_bin)
+            .arg("--version")
+            .output()
+            .path_context(uv_bin, "failed to run uv")?;
+        if !output.status.success() {
+            return Err(anyhow!("uv --version failed with {}", output.status));
         }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
 
-        Self::download(&download, &uv_dir, output)?;
-        Self::cleanup_old_versions(&base_dir, &uv_dir)?;
-        if uv_dir.exists() && uv_bin.is_file() {
-            return Ok(Uv {
-                output,
-                uv_bin,
-                workdir,
-                sources,
-            });
+    /// Returns the path of the uv binary in a version directory.
+    fn bin_path(uv_dir: &Path) -> PathBuf {
+        let mut bin = uv_dir.join("uv");
+        if cfg!(windows) {
+            bin.set_extension("exe");
         }
+        bin
+    }
 
-        Err(anyhow!("Failed to ensure uv binary is available"))
+    /// Returns the version the installed uv binary reports.
+    ///
+    /// Unlike [`UvBuilder::ensure_exists`] this never downloads uv, `None` is
+    /// returned if it was not installed yet.
+    pub fn installed_version() -> Result<Option<String>, Error> {
+        let uv_bin = Self::installed_path()?;
+        if !uv_bin.is_file() {
+            return Ok(None);
+        }
+        Self::smoke_test(&uv_bin).map(Some)
+    }
+
+    /// Returns the path where the uv binary for this platform is installed.
+    pub fn installed_path() -> Result<PathBuf, Error> {
+        let download = UvDownload::try_from(UvRequest::default())?;
+        Ok(Self::bin_path(
+            &get_app_dir().join("uv").join(download.version()),
+        ))
     }
 
     /// Remove all directories in [RYE_HOME]/uv that are not the current version.
@@ -213,7 +355,7 @@ impl Uv {
         let archive_buffer = download_url(&download.url, output)?;
 
         // All uv downloads must have a sha256 checksum
-        check_checksum(&archive_buffer, &download.sha256)
+        check_checksum(&archive_buffer, &download.sha256, output)
             .with_context(|| format!("Checksum check of {} failed", download.url))?;
 
         // Unpack the archive once we ensured that the checksum is correct
@@ -221,7 +363,7 @@ impl Uv {
         // The windows zip files don't.
         let strip = if download.url.ends_with("zip") { 0 } else { 1 };
 
-        unpack_archive(&archive_buffer, uv_dir, strip).with_context(|| {
+        unpack_archive(&archive_buffer, uv_dir, strip, output).with_context(|| {
             format!(
                 "unpacking of downloaded tarball {} to '{}' failed",
                 download.url,
@@ -229,6 +371,12 @@ impl Uv {
             )
         })?;
 
+        // record what was installed so that damage can be detected later.
+        let uv_bin = Self::bin_path(uv_dir);
+        let version = Self::smoke_test(&uv_bin).context("downloaded uv binary does not work")?;
+        echo!(if verbose output, "Installed {}", version);
+        UvRecord::from_binary(&uv_bin)?.write(uv_dir)?;
+
         Ok(())
     }
 
@@ -303,21 +451,28 @@ impl Uv {
         Ok(UvWithVenv::new(self.clone(), venv_dir, version))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn lockfile(
         &self,
         py_version: &PythonVersion,
         source: &Path,
         target: &Path,
         allow_prerelease: bool,
+        generate_hashes: bool,
+        python_platform: Option<String>,
         exclude_newer: Option<String>,
         upgrade: UvPackageUpgrade,
+        overrides: Option<&Path>,
     ) -> Result<(), Error> {
         let options = UvCompileOptions {
             allow_prerelease,
+            generate_hashes,
             exclude_newer,
             upgrade,
+            overrides: overrides.map(|x| x.to_path_buf()),
             no_deps: false,
             no_header: true,
+            python_platform,
         };
 
         let mut cmd = self.cmd();
@@ -333,18 +488,33 @@ impl Uv {
 
         cmd.arg(source);
 
-        let status = cmd.status().with_context(|| {
+        // the error is kept so it can be explained, unless the user asked to
+        // see everything uv prints as it happens.
+        let capture = self.output != CommandOutput::Verbose;
+        cmd.stdout(Stdio::inherit());
+        cmd.stderr(if capture {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        });
+        let output = cmd.output().with_context(|| {
             format!(
                 "Unable to run uv pip compile and generate {}",
                 target.to_str().unwrap_or("<unknown>")
             )
         })?;
 
-        if !status.success() {
+        if !output.status.success() {
+            if capture {
+                return Err(ResolutionError {
+                    output: String::from_utf8_lossy(&output.stderr).into_owned(),
+                }
+                .into());
+            }
             return Err(anyhow!(
                 "Failed to run uv compile {}. uv exited with status: {}",
                 target.to_str().unwrap_or("<unknown>"),
-                status
+                output.status
             ));
         }
         Ok(())
@@ -420,7 +590,8 @@ impl UvWithVenv {
         let mut req_file = NamedTempFile::new()?;
         writeln!(req_file, "{}", requirements)?;
 
-        self.venv_cmd()
+        let status = self
+            .venv_cmd()
             .arg("pip")
             .arg("install")
             .arg("--upgrade")
@@ -434,6 +605,14 @@ impl UvWithVenv {
                 )
             })?;
 
+        if !status.success() {
+            return Err(anyhow!(
+                "Failed to update requirements in venv at {}. uv exited with status: {}",
+                self.venv_path.display(),
+                status
+            ));
+        }
+
         Ok(())
     }
 
@@ -505,12 +684,19 @@ impl UvWithVenv {
     }
 
     /// Syncs the venv
-    pub fn sync(&self, lockfile: &Path) -> Result<(), Error> {
+    ///
+    /// Packages in `reinstall` are reinstalled even if they are already
+    /// installed at the locked version.
+    pub fn sync(&self, lockfile: &Path, reinstall: &[String]) -> Result<(), Error> {
         let mut cmd = self.venv_cmd();
         cmd.arg("pip").arg("sync");
 
         self.uv.sources.add_as_pip_args(&mut cmd);
 
+        for pkg in reinstall {
+            cmd.arg("--reinstall-package").arg(pkg);
+        }
+
         let status = cmd
             .arg(lockfile)
             .status()
@@ -554,8 +740,11 @@ impl UvWithVenv {
             allow_prerelease,
             exclude_newer,
             upgrade: UvPackageUpgrade::Nothing,
+            overrides: None,
+            generate_hashes: false,
             no_deps: true,
             no_header: true,
+            python_platform: None,
         };
 
         cmd.arg("pip").arg("compile");
@@ -594,3 +783,23 @@ impl UvWithVenv {
             .context("unable to parse requirement from uv.")
     }
 }
+
+#[test]
+fn test_verify_uses_record() {
+    let dir = tempfile::tempdir().unwrap();
+    let uv_bin = Uv::bin_path(dir.path());
+    fs::write(&uv_bin, b"not really uv").unwrap();
+    UvRecord::from_binary(&uv_bin)
+        .unwrap()
+        .write(dir.path())
+        .unwrap();
+    // trusted because it matches the record, uv is never run
+    assert!(Uv::verify(dir.path(), &uv_bin));
+
+    // a truncated binary no longer matches
+    fs::write(&uv_bin, b"not").unwrap();
+    assert!(!Uv::verify(dir.path(), &uv_bin));
+
+    fs::remove_file(&uv_bin).unwrap();
+    assert!(!Uv::verify(dir.path(), &uv_bin));
+}
@@ -0,0 +1,175 @@
+//! Derives project versions from git tags for `tool.rye.version = { source = "git" }`.
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Error};
+use once_cell::sync::Lazy;
+use pep440_rs::Version;
+use regex::Regex;
+
+use crate::utils::IoPathContext;
+
+/// The version a project without any version tag starts out with.
+const INITIAL_VERSION: &str = "0.1.0";
+
+static VERSION_FILE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?m)^__version__\s*=\s*["']([^"']+)["']"#).unwrap());
+
+/// Computes the version of a project from the git tags of its repository.
+///
+/// A commit with a version tag (optionally prefixed with `v`) gets that
+/// version.  Otherwise the version is a dev release of the next version,
+/// counting the commits since the tag, with the commit hash as local version
+/// (`1.2.4.dev3+gabc1234`).  Uncommitted changes add `.dirty` to it.
+pub fn get_git_version(root: &Path) -> Result<Version, Error> {
+    let describe = git(
+        root,
+        &[
+            "describe",
+            "--tags",
+            "--long",
+            "--dirty",
+            "--always",
+            "--abbrev=7",
+            "--match",
+            "v[0-9]*",
+            "--match",
+            "[0-9]*",
+        ],
+    )?;
+    parse_describe(&describe, || {
+        git(root, &["rev-list", "--count", "HEAD"])?
+            .parse()
+            .context("unexpected output from git rev-list")
+    })
+}
+
+/// Turns the output of `git describe --long --dirty --always` into a version.
+///
+/// The commit count is only requested if the repository has no version tag.
+fn parse_describe<F>(describe: &str, commit_count: F) -> Result<Version, Error>
+where
+    F: FnOnce() -> Result<u64, Error>,
+{
+    let (describe, dirty) = match describe.strip_suffix("-dirty") {
+        Some(rest) => (rest, true),
+        None => (describe, false),
+    };
+
+    let (mut version, distance, hash, tagged) = match describe.rsplitn(3, '-').collect::<Vec<_>>()[..]
+    {
+        [hash, distance, tag] if hash.starts_with('g') => {
+            let tag = tag.strip_prefix('v').unwrap_or(tag);
+            let version = Version::from_str(tag)
+                .map_err(|msg| anyhow!("tag '{}' is not a valid version: {}", tag, msg))?;
+            let distance: u64 = distance
+                .parse()
+                .with_context(|| format!("unexpected output from git describe: {}", describe))?;
+            (version, distance, &hash[1..], true)
+        }
+        _ => (
+            Version::from_str(INITIAL_VERSION).unwrap(),
+            commit_count()?,
+            describe,
+            false,
+        ),
+    };
+
+    if tagged {
+        if distance == 0 && !dirty {
+            return Ok(version);
+        }
+        // the tagged version was released already, move on to the next one
+        if let Some((kind, num)) = version.pre {
+            version.pre = Some((kind, num + 1));
+        } else if let Some(last) = version.release.last_mut() {
+            *last += 1;
+        }
+    }
+    version.post = None;
+    version.dev = Some(distance);
+
+    let mut rv = format!("{}+g{}", version, hash);
+    if dirty {
+        rv.push_str(".dirty");
+    }
+    Version::from_str(&rv).map_err(|msg| anyhow!("invalid version '{}': {}", rv, msg))
+}
+
+/// Reads the version back from a version file written by [`write_version_file`].
+pub fn read_version_file(path: &Path) -> Result<Version, Error> {
+    let contents = fs::read_to_string(path).path_context(path, "unable to read version file")?;
+    let version = VERSION_FILE_RE
+        .captures(&contents)
+        .and_then(|x| x.get(1))
+        .ok_or_else(|| anyhow!("no version found in {}", path.display()))?;
+    Version::from_str(version.as_str()).map_err(|msg| anyhow!("invalid version: {}", msg))
+}
+
+/// Writes a python module that exposes the version as `__version__`.
+///
+/// The file is left alone if it is already up to date so that builds do not
+/// see a change.
+pub fn write_version_file(path: &Path, version: &Version) -> Result<(), Error> {
+    let contents = format!(
+        "# This file is generated by rye from git, do not edit.\n\
+         __version__ = \"{}\"\n",
+        version
+    );
+    if fs::read_to_string(path).ok().as_deref() == Some(contents.as_str()) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).path_context(parent, "unable to create version file folder")?;
+    }
+    fs::write(path, contents).path_context(path, "unable to write version file")?;
+    Ok(())
+}
+
+/// Runs a git command in a folder and returns its trimmed output.
+fn git(root: &Path, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .stderr(Stdio::null())
+        .output()
+        .context("unable to run git")?;
+    if !output.status.success() {
+        bail!("unable to determine version from git in {}", root.display());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_describe;
+
+    fn parse(describe: &str) -> String {
+        parse_describe(describe, || Ok(5)).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parse_describe_tagged() {
+        assert_eq!(parse("v1.2.3-0-gabc1234"), "1.2.3");
+        assert_eq!(parse("1.2.3-0-gabc1234"), "1.2.3");
+        assert_eq!(parse("v1.0.0rc1-0-gabc1234"), "1.0.0rc1");
+    }
+
+    #[test]
+    fn test_parse_describe_distance() {
+        assert_eq!(parse("v1.2.3-3-gabc1234"), "1.2.4.dev3+gabc1234");
+        assert_eq!(parse("v1.0.0rc1-2-gabc1234"), "1.0.0rc2.dev2+gabc1234");
+        assert_eq!(
+            parse("v1.2.3-0-gabc1234-dirty"),
+            "1.2.4.dev0+gabc1234.dirty"
+        );
+    }
+
+    #[test]
+    fn test_parse_describe_untagged() {
+        assert_eq!(parse("abc1234"), "0.1.0.dev5+gabc1234");
+        assert_eq!(parse("abc1234-dirty"), "0.1.0.dev5+gabc1234.dirty");
+    }
+}
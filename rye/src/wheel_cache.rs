@@ -0,0 +1,553 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use pep440_rs::{Operator, Version};
+use pep508_rs::{Requirement, VersionOrUrl};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::index::{fetch_file, fetch_project_page, index_urls, CONCURRENT_REQUESTS};
+use crate::platform::get_wheel_cache_dir;
+use crate::pyproject::{normalize_package_name, ExpandedSources};
+use crate::sources::py::PythonVersion;
+use crate::utils::{run_parallel, CommandOutput, IoPathContext};
+
+/// A package that a lockfile pins to a version.
+#[derive(Debug, PartialEq)]
+struct Pin {
+    name: String,
+    version: Version,
+    /// The SHA-256 digests from the `--hash` options of the lockfile.
+    hashes: Vec<String>,
+}
+
+/// A wheel in the cache.
+///
+/// Wheels are stored as `<sha256>/<filename>`, so different builds with the
+/// same filename can be cached side by side and every wheel can be checked
+/// against the digest it's stored under.
+#[derive(Debug)]
+struct CachedWheel {
+    path: PathBuf,
+    filename: String,
+    sha256: String,
+}
+
+impl CachedWheel {
+    /// Checks if this is a wheel of a pinned package.
+    fn matches(&self, pin: &Pin) -> bool {
+        let mut parts = self.filename.splitn(3, '-');
+        parts.next().map(normalize_package_name).as_ref() == Some(&pin.name)
+            && parts
+                .next()
+                .and_then(|x| x.parse::<Version>().ok())
+                .as_ref()
+                == Some(&pin.version)
+    }
+
+    /// Checks that the contents of the wheel still match its digest.
+    fn verify(&self) -> Result<bool, Error> {
+        let contents = fs::read(&self.path).path_context(&self.path, "unable to read wheel")?;
+        Ok(hex::encode(Sha256::digest(&contents)) == self.sha256)
+    }
+}
+
+/// Imports wheels into the wheel cache.
+///
+/// The path is either a folder with wheels or a tar archive created by
+/// [`export_wheels`].  Wheels are stored under the digest of their contents,
+/// so a different build with the same filename is added next to the cached
+/// one.  Returns the number of added wheels.
+pub fn import_wheels(path: &Path) -> Result<usize, Error> {
+    let cache_dir = get_wheel_cache_dir();
+    fs::create_dir_all(&cache_dir).path_context(&cache_dir, "unable to create wheel cache")?;
+
+    let mut count = 0;
+    if path.is_dir() {
+        for entry in fs::read_dir(path).path_context(path, "unable to read folder")? {
+            let entry = entry?;
+            let filename = entry.file_name().to_string_lossy().into_owned();
+            if is_wheel_filename(&filename) {
+                let contents =
+                    fs::read(entry.path()).path_context(entry.path(), "unable to read wheel")?;
+                count += add_wheel(&cache_dir, &filename, &contents)? as usize;
+            }
+        }
+    } else {
+        let file = fs::File::open(path).path_context(path, "unable to open archive")?;
+        let mut archive = tar::Archive::new(file);
+        for entry in archive
+            .entries()
+            .path_context(path, "unable to read archive")?
+        {
+            let mut entry = entry?;
+            let filename = match entry.path()?.file_name() {
+                Some(filename) => filename.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            if is_wheel_filename(&filename) {
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents)?;
+                count += add_wheel(&cache_dir, &filename, &contents)? as usize;
+            }
+        }
+    }
+    Ok(count)
+}
+
+/// Exports all cached wheels into a tar archive.
+///
+/// Returns the number of exported wheels.
+pub fn export_wheels(path: &Path) -> Result<usize, Error> {
+    let wheels = list_wheels(&get_wheel_cache_dir())?;
+    let file = fs::File::create(path).path_context(path, "unable to create archive")?;
+    let mut archive = tar::Builder::new(io::BufWriter::new(file));
+    for wheel in &wheels {
+        archive
+            .append_path_with_name(&wheel.path, Path::new(&wheel.sha256).join(&wheel.filename))
+            .path_context(&wheel.path, "unable to add wheel to archive")?;
+    }
+    archive
+        .into_inner()
+        .and_then(|mut x| io::Write::flush(&mut x))
+        .path_context(path, "unable to write archive")?;
+    Ok(wheels.len())
+}
+
+/// Downloads the wheels of a lockfile that are missing from the cache.
+///
+/// This fetches the wheels the installer would otherwise download from the
+/// package indexes, so that the cache fills up with normal syncs and can be
+/// exported afterwards.  Only wheels whose digest is published by the index
+/// or listed in the lockfile are added, and they have to match it.  Failing
+/// to fetch a wheel is not an error, the installer then downloads it itself.
+/// Returns the number of added wheels.
+pub fn fill_wheel_cache(
+    sources: &ExpandedSources,
+    lockfile: &Path,
+    py_ver: &PythonVersion,
+    output: CommandOutput,
+) -> Result<usize, Error> {
+    if sources.no_index {
+        return Ok(0);
+    }
+    let cache_dir = get_wheel_cache_dir();
+    let cached = list_wheels(&cache_dir)?;
+    let contents =
+        fs::read_to_string(lockfile).path_context(lockfile, "unable to read lockfile")?;
+    let missing = read_pins(&contents)
+        .0
+        .into_iter()
+        .filter(|pin| {
+            !cached
+                .iter()
+                .any(|wheel| wheel.matches(pin) && is_compatible_wheel(&wheel.filename, py_ver))
+        })
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(0);
+    }
+    fs::create_dir_all(&cache_dir).path_context(&cache_dir, "unable to create wheel cache")?;
+
+    let indexes = index_urls(sources);
+    let mut count = 0;
+    for (pin, result) in run_parallel(CONCURRENT_REQUESTS, missing, |pin| {
+        let result = fetch_wheel(&indexes, sources, &pin, py_ver, &cache_dir);
+        (pin, result)
+    }) {
+        match result {
+            Ok(added) => count += added as usize,
+            Err(err) => {
+                echo!(
+                    if verbose output,
+                    "Unable to add {}=={} to the wheel cache: {}",
+                    pin.name,
+                    pin.version,
+                    err
+                );
+            }
+        }
+    }
+    if count > 0 {
+        echo!(if verbose output, "Added {} wheels to the wheel cache", count);
+    }
+    Ok(count)
+}
+
+/// Downloads a compatible wheel of a pinned package into the cache.
+///
+/// Returns `false` if the index has no compatible wheel with a known digest.
+fn fetch_wheel(
+    indexes: &[Url],
+    sources: &ExpandedSources,
+    pin: &Pin,
+    py_ver: &PythonVersion,
+    cache_dir: &Path,
+) -> Result<bool, Error> {
+    let (page_url, page) = match fetch_project_page(indexes, sources, &pin.name)? {
+        Some(rv) => rv,
+        None => return Ok(false),
+    };
+    let file = page.files.iter().find(|file| {
+        file.version().as_ref() == Some(&pin.version)
+            && is_compatible_wheel(&file.filename, py_ver)
+            && file
+                .hashes
+                .get("sha256")
+                .map_or(!pin.hashes.is_empty(), |digest| {
+                    pin.hashes.is_empty() || pin.hashes.contains(digest)
+                })
+    });
+    let file = match file {
+        Some(file) => file,
+        None => return Ok(false),
+    };
+
+    let url = page_url.join(&file.url)?;
+    let contents = fetch_file(&url, sources)?;
+    let digest = hex::encode(Sha256::digest(&contents));
+    if file.hashes.get("sha256").map_or(false, |x| *x != digest)
+        || (!pin.hashes.is_empty() && !pin.hashes.contains(&digest))
+    {
+        bail!("digest of {} does not match", file.filename);
+    }
+    add_wheel(cache_dir, &file.filename, &contents)
+}
+
+/// Makes the wheel cache available to an installation.
+///
+/// The cached wheels of the packages in the lockfile are checked against the
+/// digest they are stored under and, if the lockfile has hashes, against those.
+/// The wheels that pass are linked into `links_dir` which is added as a
+/// find-links source.  If the lockfile has hashes and such a wheel exists for
+/// every package, the package indexes are not consulted at all.  Returns
+/// `true` in that case.
+pub fn use_wheel_cache(
+    sources: &mut ExpandedSources,
+    lockfile: &Path,
+    py_ver: &PythonVersion,
+    links_dir: &Path,
+) -> Result<bool, Error> {
+    let cached = list_wheels(&get_wheel_cache_dir())?
+        .into_iter()
+        .filter(|x| is_compatible_wheel(&x.filename, py_ver))
+        .collect::<Vec<_>>();
+    if cached.is_empty() {
+        return Ok(false);
+    }
+
+    let contents =
+        fs::read_to_string(lockfile).path_context(lockfile, "unable to read lockfile")?;
+    let (pins, all_pinned) = read_pins(&contents);
+    let mut covered = all_pinned;
+    let mut linked = HashSet::new();
+    for pin in &pins {
+        let mut found = false;
+        for wheel in cached.iter().filter(|wheel| wheel.matches(pin)) {
+            if !pin.hashes.is_empty() && !pin.hashes.contains(&wheel.sha256) {
+                continue;
+            }
+            if !wheel.verify()? {
+                warn!(
+                    "ignoring cached wheel {} as its contents changed",
+                    wheel.path.display()
+                );
+                continue;
+            }
+            // wheels with the same filename can only be offered once.
+            if linked.insert(wheel.filename.clone()) {
+                fs::create_dir_all(links_dir).path_context(links_dir, "unable to create folder")?;
+                let target = links_dir.join(&wheel.filename);
+                if fs::hard_link(&wheel.path, &target).is_err() {
+                    fs::copy(&wheel.path, &target)
+                        .path_context(&target, "unable to link cached wheel")?;
+                }
+            }
+            found = true;
+            break;
+        }
+        // without hashes nothing guarantees that the cached wheel is the one
+        // that was locked, so the indexes stay available.
+        covered = covered && found && !pin.hashes.is_empty();
+    }
+    if linked.is_empty() {
+        return Ok(false);
+    }
+
+    let url = Url::from_directory_path(links_dir)
+        .ok()
+        .with_context(|| format!("invalid wheel cache path '{}'", links_dir.display()))?;
+    sources.find_links.push(url);
+    if covered {
+        sources.no_index = true;
+    }
+    Ok(covered)
+}
+
+/// Reads the packages a lockfile pins to a version together with their hashes.
+///
+/// The second value is `false` if some requirement is not pinned to a version,
+/// for instance because it points to a URL.  Local projects are built from
+/// their sources and never come from the cache, so editables are skipped.
+fn read_pins(contents: &str) -> (Vec<Pin>, bool) {
+    let mut pins: Vec<Pin> = Vec::new();
+    let mut all_pinned = true;
+    let mut in_pin = false;
+    for line in contents.lines() {
+        let trimmed = line.trim().trim_end_matches('\\').trim();
+        if line.starts_with(' ') {
+            if let Some(digest) = trimmed.strip_prefix("--hash=sha256:") {
+                if in_pin {
+                    if let Some(pin) = pins.last_mut() {
+                        pin.hashes.push(digest.to_string());
+                    }
+                }
+            }
+            continue;
+        }
+        in_pin = false;
+        if trimmed.is_empty() || line.starts_with(['#', '-']) {
+            continue;
+        }
+        let (requirement, hashes) = match trimmed.split_once(" --hash=") {
+            Some((requirement, hashes)) => (requirement, Some(hashes)),
+            None => (trimmed, None),
+        };
+        let pin = requirement
+            .parse::<Requirement>()
+            .ok()
+            .and_then(|req| Some((normalize_package_name(&req.name), pinned_version(&req)?)));
+        match pin {
+            Some((name, version)) => {
+                pins.push(Pin {
+                    name,
+                    version,
+                    hashes: hashes
+                        .into_iter()
+                        .flat_map(|x| x.split(" --hash="))
+                        .filter_map(|x| x.trim().strip_prefix("sha256:"))
+                        .map(|x| x.to_string())
+                        .collect(),
+                });
+                in_pin = true;
+            }
+            None => all_pinned = false,
+        }
+    }
+    (pins, all_pinned)
+}
+
+/// Returns the version a requirement is pinned to with `==`.
+fn pinned_version(req: &Requirement) -> Option<Version> {
+    match req.version_or_url {
+        Some(VersionOrUrl::VersionSpecifier(ref specifiers)) if specifiers.len() == 1 => {
+            let specifier = specifiers.iter().next()?;
+            (*specifier.operator() == Operator::Equal).then(|| specifier.version().clone())
+        }
+        _ => None,
+    }
+}
+
+/// Adds a single wheel to the cache unless it's already there.
+fn add_wheel(cache_dir: &Path, filename: &str, contents: &[u8]) -> Result<bool, Error> {
+    let digest = hex::encode(Sha256::digest(contents));
+    let dir = cache_dir.join(&digest);
+    let path = dir.join(filename);
+    if path.is_file() {
+        return Ok(false);
+    }
+    // write to a temporary file first so that installations never see
+    // a partially written wheel.
+    fs::create_dir_all(&dir).path_context(&dir, "unable to create wheel cache")?;
+    let tmp = dir.join(format!(".{}.{}.tmp", filename, std::process::id()));
+    fs::write(&tmp, contents).path_context(&tmp, "unable to write wheel")?;
+    fs::rename(&tmp, &path).path_context(&path, "unable to write wheel")?;
+    Ok(true)
+}
+
+/// Returns all cached wheels.
+fn list_wheels(cache_dir: &Path) -> Result<Vec<CachedWheel>, Error> {
+    let mut rv = Vec::new();
+    if !cache_dir.is_dir() {
+        return Ok(rv);
+    }
+    for entry in fs::read_dir(cache_dir).path_context(cache_dir, "unable to read wheel cache")? {
+        let dir = entry?.path();
+        let sha256 = match dir.file_name() {
+            Some(name) if name.len() == 64 && dir.is_dir() => name.to_string_lossy().into_owned(),
+            _ => continue,
+        };
+        for entry in fs::read_dir(&dir).path_context(&dir, "unable to read wheel cache")? {
+            let path = entry?.path();
+            let filename = match path.file_name() {
+                Some(filename) => filename.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            if is_wheel_filename(&filename) {
+                rv.push(CachedWheel {
+                    path,
+                    filename,
+                    sha256: sha256.clone(),
+                });
+            }
+        }
+    }
+    rv.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(rv)
+}
+
+fn is_wheel_filename(filename: &str) -> bool {
+    !filename.starts_with('.') && filename.ends_with(".whl") && filename.split('-').count() >= 5
+}
+
+/// Checks if a wheel can be installed for a Python version on this machine.
+fn is_compatible_wheel(filename: &str, py_ver: &PythonVersion) -> bool {
+    let parts = match filename.strip_suffix(".whl") {
+        Some(stem) => stem.rsplitn(4, '-').collect::<Vec<_>>(),
+        None => return false,
+    };
+    let (platforms, abis, pythons) = match parts[..] {
+        [platforms, abis, pythons, _] => (platforms, abis, pythons),
+        _ => return false,
+    };
+    let cpython = format!("cp{}{}", py_ver.major, py_ver.minor);
+
+    let python_ok = pythons.split('.').any(|tag| {
+        tag == "py3"
+            || tag == format!("py{}{}", py_ver.major, py_ver.minor)
+            || tag == cpython
+            || (abis == "abi3"
+                && tag
+                    .strip_prefix("cp3")
+                    .and_then(|x| x.parse::<u8>().ok())
+                    .map_or(false, |minor| minor <= py_ver.minor))
+    });
+    let abi_ok = abis
+        .split('.')
+        .any(|tag| tag == "none" || tag == "abi3" || tag.starts_with(&cpython));
+    let platform_ok = platforms
+        .split('.')
+        .any(|tag| tag == "any" || is_host_platform_tag(tag));
+    python_ok && abi_ok && platform_ok
+}
+
+fn is_host_platform_tag(tag: &str) -> bool {
+    let arch = std::env::consts::ARCH;
+    match std::env::consts::OS {
+        "linux" => {
+            let arch = if arch == "x86" { "i686" } else { arch };
+            let libc_ok = if cfg!(target_env = "musl") {
+                tag.starts_with("musllinux_")
+            } else {
+                tag.starts_with("manylinux") || tag.starts_with("linux_")
+            };
+            libc_ok && tag.ends_with(&format!("_{}", arch))
+        }
+        "macos" => {
+            let arch = if arch == "aarch64" { "arm64" } else { arch };
+            tag.starts_with("macosx_")
+                && (tag.ends_with(&format!("_{}", arch)) || tag.ends_with("_universal2"))
+        }
+        "windows" => match arch {
+            "x86_64" => tag == "win_amd64",
+            "x86" => tag == "win32",
+            "aarch64" => tag == "win_arm64",
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[test]
+fn test_is_compatible_wheel() {
+    let py_ver = PythonVersion {
+        name: "cpython".into(),
+        arch: std::env::consts::ARCH.into(),
+        os: std::env::consts::OS.into(),
+        environment: None,
+        major: 3,
+        minor: 12,
+        patch: 2,
+        suffix: None,
+    };
+    assert!(is_compatible_wheel(
+        "six-1.16.0-py2.py3-none-any.whl",
+        &py_ver
+    ));
+    assert!(is_compatible_wheel("foo-1.0-1-py3-none-any.whl", &py_ver));
+    assert!(!is_compatible_wheel("foo-1.0-cp311-cp311-any.whl", &py_ver));
+    assert!(is_compatible_wheel("foo-1.0-cp38-abi3-any.whl", &py_ver));
+    assert!(!is_compatible_wheel("foo-1.0-cp313-abi3-any.whl", &py_ver));
+    assert!(!is_compatible_wheel(
+        "foo-1.0-py3-none-plan9_mips.whl",
+        &py_ver
+    ));
+    assert!(!is_compatible_wheel("not-a-wheel.tar.gz", &py_ver));
+}
+
+#[test]
+fn test_read_pins() {
+    let (pins, all_pinned) = read_pins(
+        "# generated by rye\n\
+         -e file:.\n\
+         certifi==2024.2.2 \\\n    \
+             --hash=sha256:aaaa \\\n    \
+             --hash=sha256:bbbb\n    \
+             # via requests\n\
+         Flask==3.0.0 --hash=sha256:cccc\n\
+         six==1.16.0\n",
+    );
+    assert!(all_pinned);
+    assert_eq!(
+        pins,
+        vec![
+            Pin {
+                name: "certifi".into(),
+                version: "2024.2.2".parse().unwrap(),
+                hashes: vec!["aaaa".into(), "bbbb".into()],
+            },
+            Pin {
+                name: "flask".into(),
+                version: "3.0.0".parse().unwrap(),
+                hashes: vec!["cccc".into()],
+            },
+            Pin {
+                name: "six".into(),
+                version: "1.16.0".parse().unwrap(),
+                hashes: vec![],
+            },
+        ]
+    );
+
+    let (pins, all_pinned) = read_pins("foo @ https://example.com/foo.whl\nsix==1.16.0\n");
+    assert!(!all_pinned);
+    assert_eq!(pins.len(), 1);
+}
+
+#[test]
+fn test_add_and_list_wheels() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let filename = "Foo_Bar-1.0-py3-none-any.whl";
+    assert!(add_wheel(cache_dir.path(), filename, b"first build").unwrap());
+    assert!(!add_wheel(cache_dir.path(), filename, b"first build").unwrap());
+    assert!(add_wheel(cache_dir.path(), filename, b"second build").unwrap());
+
+    let wheels = list_wheels(cache_dir.path()).unwrap();
+    assert_eq!(wheels.len(), 2);
+    let pin = Pin {
+        name: "foo-bar".into(),
+        version: "1.0".parse().unwrap(),
+        hashes: vec![],
+    };
+    for wheel in &wheels {
+        assert_eq!(wheel.filename, filename);
+        assert!(wheel.matches(&pin));
+        assert!(wheel.verify().unwrap());
+    }
+
+    fs::write(&wheels[0].path, b"tampered").unwrap();
+    assert!(!wheels[0].verify().unwrap());
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use pep440_rs::Version;
+use pep508_rs::{Requirement, VersionOrUrl};
+
+use crate::index::{fetch_project_pages, ProjectPage};
+use crate::pyproject::{normalize_package_name, ExpandedSources};
+use crate::utils::CommandOutput;
+
+/// A release that was yanked from the package index.
+#[derive(Debug, Clone)]
+pub struct YankedRelease {
+    pub name: String,
+    pub version: Version,
+    pub reason: Option<String>,
+}
+
+impl YankedRelease {
+    /// Checks if the release was intentionally allowed.
+    ///
+    /// A requirement without a version allows all yanked releases of a package.
+    pub fn is_allowed(&self, allowed: &[Requirement]) -> bool {
+        allowed.iter().any(|req| {
+            normalize_package_name(&req.name) == self.name
+                && match req.version_or_url {
+                    Some(VersionOrUrl::VersionSpecifier(ref specifiers)) => {
+                        specifiers.contains(&self.version)
+                    }
+                    Some(VersionOrUrl::Url(_)) => false,
+                    None => true,
+                }
+        })
+    }
+}
+
+/// Finds the releases among the given packages that were yanked.
+///
+/// The package names must be normalized.  Packages are looked up on the
+/// indexes of the sources.  Failing to look up a package is not an error,
+/// the package is then treated as not yanked.
+pub fn find_yanked_releases<'a, I>(
+    packages: I,
+    sources: &ExpandedSources,
+    output: CommandOutput,
+) -> Vec<YankedRelease>
+where
+    I: IntoIterator<Item = (&'a String, &'a Version)>,
+{
+    let packages = packages.into_iter().collect::<Vec<_>>();
+    let pages = fetch_project_pages(packages.iter().map(|x| x.0), sources, output);
+    yanked_releases_in_pages(packages, &pages)
+}
+
+/// Finds the releases among the given packages that the project pages list
+/// as yanked.
+///
+/// A release only counts as yanked if all of its files are.
+pub fn yanked_releases_in_pages<'a, I>(
+    packages: I,
+    pages: &HashMap<String, ProjectPage>,
+) -> Vec<YankedRelease>
+where
+    I: IntoIterator<Item = (&'a String, &'a Version)>,
+{
+    let mut rv = Vec::new();
+    for (name, version) in packages {
+        let files = match pages.get(name) {
+            Some(page) => page
+                .files
+                .iter()
+                .filter(|file| file.version().as_ref() == Some(version))
+                .collect::<Vec<_>>(),
+            None => continue,
+        };
+        if files.is_empty() || files.iter().any(|file| file.yanked().is_none()) {
+            continue;
+        }
+        rv.push(YankedRelease {
+            name: name.clone(),
+            version: version.clone(),
+            reason: files
+                .iter()
+                .filter_map(|file| file.yanked())
+                .find(|x| !x.is_empty())
+                .map(|x| x.to_string()),
+        });
+    }
+    rv
+}
+
+#[test]
+fn test_yanked_releases_in_pages() {
+    let page: ProjectPage = serde_json::from_str(
+        r#"{"files": [
+            {"filename": "foo-1.0.tar.gz", "url": "/files/foo-1.0.tar.gz", "hashes": {}},
+            {"filename": "foo-1.1-py3-none-any.whl", "url": "/files/foo-1.1-py3-none-any.whl",
+             "hashes": {}, "yanked": true},
+            {"filename": "foo-1.1.tar.gz", "url": "/files/foo-1.1.tar.gz", "hashes": {},
+             "yanked": "broken build"},
+            {"filename": "foo-1.2-py3-none-any.whl", "url": "/files/foo-1.2-py3-none-any.whl",
+             "hashes": {}, "yanked": true},
+            {"filename": "foo-1.2.zip", "url": "/files/foo-1.2.zip", "hashes": {}, "yanked": false}
+        ]}"#,
+    )
+    .unwrap();
+    let pages = HashMap::from([("foo".to_string(), page)]);
+    let name = "foo".to_string();
+    let versions: Vec<Version> = ["1.0", "1.1", "1.2", "1.3"]
+        .iter()
+        .map(|x| x.parse().unwrap())
+        .collect();
+    let yanked = yanked_releases_in_pages(versions.iter().map(|x| (&name, x)), &pages);
+    assert_eq!(yanked.len(), 1);
+    assert_eq!(yanked[0].version, versions[1]);
+    assert_eq!(yanked[0].reason.as_deref(), Some("broken build"));
+}
@@ -248,6 +248,20 @@ impl Space {
         self.project_dir.join(".venv")
     }
 
+    /// Creates a shim of the given name for the rye binary under test.
+    ///
+    /// Shims are detected by the name of the executable and its folder.
+    #[allow(unused)]
+    pub fn shim(&self, name: &str) -> PathBuf {
+        let shims = self.tempdir.path().join("shims");
+        fs::create_dir_all(&shims).unwrap();
+        let shim = shims.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+        if !shim.is_file() {
+            fs::copy(get_bin(), &shim).unwrap();
+        }
+        shim
+    }
+
     #[allow(unused)]
     pub fn lock_rye_home(&self) -> fslock::LockFile {
         let mut lock = fslock::LockFile::open(&self.rye_home().join("lock")).unwrap();
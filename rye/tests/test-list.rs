@@ -26,6 +26,53 @@ fn test_basic_list() {
 
     ----- stderr -----
     "###);
+
+    rye_cmd_snapshot!(
+        space.rye_cmd().arg("list").arg("--format=table"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    jinja2      3.1.2  direct
+    markupsafe  2.1.3
+    my-project  0.1.0  editable
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(
+        space.rye_cmd().arg("list").arg("--format=json"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [
+      {
+        "name": "jinja2",
+        "version": "3.1.2",
+        "direct": true,
+        "dev_only": false,
+        "editable": false,
+        "orphaned": false
+      },
+      {
+        "name": "markupsafe",
+        "version": "2.1.3",
+        "direct": false,
+        "dev_only": false,
+        "editable": false,
+        "orphaned": false
+      },
+      {
+        "name": "my-project",
+        "version": "0.1.0",
+        "direct": false,
+        "dev_only": false,
+        "editable": true,
+        "orphaned": false
+      }
+    ]
+
+    ----- stderr -----
+    "###);
 }
 
 #[test]
@@ -56,3 +103,32 @@ fn test_list_not_rye_managed() {
     ----- stderr -----
     "###);
 }
+
+#[test]
+fn test_list_orphaned() {
+    let space = Space::new();
+    space.init("my-project");
+
+    space
+        .rye_cmd()
+        .arg("add")
+        .arg("jinja2")
+        .status()
+        .expect("ok");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["project"]["dependencies"] = value(toml_edit::Array::new());
+    });
+    space.rye_cmd().arg("lock").status().expect("ok");
+
+    rye_cmd_snapshot!(
+        space.rye_cmd().arg("list").arg("--format=table"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    jinja2      3.1.2  orphaned
+    markupsafe  2.1.3  orphaned
+    my-project  0.1.0  editable
+
+    ----- stderr -----
+    "###);
+}
@@ -214,3 +214,239 @@ fn test_add_explicit_version_or_url() {
      + pip==1.3.1 (from https://github.com/pypa/pip/archive/1.3.1.zip#sha1=da9234ee9982d4bbb3c72346a6de940a148ea686)
     "###);
 }
+
+#[test]
+fn test_add_unknown_index() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("add").arg("torch").arg("--index").arg("pytorch-cu999"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: unknown index 'pytorch-cu999'. Declare it in tool.rye.sources or use one of: pytorch-cpu, pytorch-cu118, pytorch-cu121, pytorch-cu124, pytorch-rocm6.1
+    "###);
+}
+
+#[test]
+fn test_add_scan_ignores_stdlib_and_local_imports() {
+    let space = Space::new();
+    space.init("my-project");
+    space.write(
+        "src/my_project/__init__.py",
+        "import os, sys\nfrom my_project import utils\nfrom . import helpers\n",
+    );
+    space.write("src/my_project/utils.py", "import json\n");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("add").arg("--scan").arg("--dry-run"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    No undeclared imports found
+
+    ----- stderr -----
+    "###);
+}
+
+/// Records the project files and the virtualenv that syncing can change.
+fn snapshot_project_state(space: &Space) -> Vec<(String, String)> {
+    let mut rv = [
+        "pyproject.toml",
+        "requirements.lock",
+        "requirements-dev.lock",
+    ]
+    .iter()
+    .map(|x| (x.to_string(), space.read_string(x)))
+    .collect::<Vec<_>>();
+    for entry in walkdir::WalkDir::new(space.venv_path()).sort_by_file_name() {
+        let entry = entry.unwrap();
+        let metadata = entry.metadata().unwrap();
+        rv.push((
+            entry.path().display().to_string(),
+            format!("{} {:?}", metadata.len(), metadata.modified().unwrap()),
+        ));
+    }
+    rv
+}
+
+#[test]
+fn test_add_dry_run_changes_nothing() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").arg("-q").status().unwrap();
+    assert!(status.success());
+    let before = snapshot_project_state(&space);
+
+    let output = space
+        .rye_cmd()
+        .arg("add")
+        .arg("flask")
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Would add flask>=3.0.0 as regular dependency"));
+    assert_eq!(snapshot_project_state(&space), before);
+}
+
+#[test]
+fn test_remove_dry_run_changes_nothing() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space
+        .rye_cmd()
+        .arg("add")
+        .arg("colorama")
+        .arg("-q")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let before = snapshot_project_state(&space);
+
+    let status = space
+        .rye_cmd()
+        .arg("remove")
+        .arg("colorama")
+        .arg("--dry-run")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(snapshot_project_state(&space), before);
+}
+
+#[test]
+fn test_add_workspace_member_by_path() {
+    let space = Space::new();
+    space.init("foo");
+    space.edit_toml("pyproject.toml", |doc| {
+        let mut members = toml_edit::Array::new();
+        members.push(".");
+        members.push("child-dep");
+        doc["tool"]["rye"]["workspace"]["members"] = value(members);
+    });
+    let status = space
+        .rye_cmd()
+        .arg("init")
+        .arg("-q")
+        .arg(space.project_path().join("child-dep"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("add").arg("./child-dep").arg("--no-sync"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Added child-dep as regular dependency
+
+    ----- stderr -----
+    "###);
+
+    // members are referenced by name only
+    let doc = space.read_toml("pyproject.toml");
+    let deps = doc["project"]["dependencies"].as_array().unwrap();
+    assert_eq!(
+        deps.iter().filter_map(|x| x.as_str()).collect::<Vec<_>>(),
+        vec!["child-dep"]
+    );
+    assert!(doc["tool"]["rye"].get("editable-dependencies").is_none());
+}
+
+#[test]
+fn test_add_editable_path() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space
+        .rye_cmd()
+        .arg("init")
+        .arg("-q")
+        .arg("--name")
+        .arg("libfoo")
+        .arg(space.project_path().join("libs/libfoo"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = space
+        .rye_cmd()
+        .arg("add")
+        .arg("./libs/libfoo")
+        .arg("--editable")
+        .arg("--no-sync")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let doc = space.read_toml("pyproject.toml");
+    let dep = doc["project"]["dependencies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|x| x.as_str())
+        .find(|x| x.starts_with("libfoo"))
+        .unwrap()
+        .to_string();
+    assert!(dep.starts_with("libfoo @ file:///"), "{}", dep);
+    assert!(dep.ends_with("/libs/libfoo"), "{}", dep);
+    let editables = doc["tool"]["rye"]["editable-dependencies"]
+        .as_array()
+        .unwrap();
+    assert_eq!(
+        editables
+            .iter()
+            .filter_map(|x| x.as_str())
+            .collect::<Vec<_>>(),
+        vec!["libfoo"]
+    );
+
+    // the lockfile references the editable relative to the workspace root
+    let status = space.rye_cmd().arg("lock").arg("-q").status().unwrap();
+    assert!(status.success());
+    let lockfile = space.read_string("requirements.lock");
+    assert!(
+        lockfile.lines().any(|line| line == "-e file:libs/libfoo"),
+        "{}",
+        lockfile
+    );
+    assert!(!lockfile.contains(&space.project_path().display().to_string()));
+}
+
+#[test]
+fn test_add_editable_requires_path() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("add").arg("flask").arg("--editable"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: --editable can only be used with local path dependencies
+    "###);
+}
+
+#[test]
+fn test_add_interactive_requires_terminal() {
+    let space = Space::new();
+    space.init("my-project");
+    let before = space.read_string("pyproject.toml");
+
+    // the test harness captures stderr, so there is no terminal to prompt on
+    rye_cmd_snapshot!(space.rye_cmd().arg("add").arg("flask").arg("--interactive"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: --interactive requires an interactive terminal
+    "###);
+    assert_eq!(space.read_string("pyproject.toml"), before);
+}
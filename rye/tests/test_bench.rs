@@ -0,0 +1,68 @@
+use insta::Settings;
+use toml_edit::value;
+
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+/// A benchmark runner that reports a fixed mean duration.
+const BENCH_SCRIPT: &str = r#"
+import json
+import os
+
+mean = float(os.environ.get("BENCH_MEAN", "0.001"))
+with open(os.environ["RYE_BENCHMARK_JSON"], "w") as f:
+    json.dump({"benchmarks": [{"fullname": "bench.py::test_sum", "stats": {"mean": mean}}]}, f)
+"#;
+
+#[test]
+fn test_bench_compare() {
+    let mut settings = Settings::clone_current();
+    settings.add_filter(r"\brun \d+\b", "run [RUN]");
+    let _guard = settings.bind_to_scope();
+
+    let space = Space::new();
+    space.init("my-project");
+    space.write("bench.py", BENCH_SCRIPT);
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["bench"]["runner"] = value("python bench.py");
+    });
+    let status = space.rye_cmd().arg("sync").arg("-q").status().unwrap();
+    assert!(status.success());
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("bench"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Virtualenv is up to date
+    Running benchmarks for my-project ([TEMP_PATH]/project)
+    Stored results as run [RUN]
+
+    ----- stderr -----
+    "###);
+
+    // the second run is twice as slow, which is above the threshold.
+    rye_cmd_snapshot!(space.rye_cmd().arg("bench").arg("--compare").arg("last").env("BENCH_MEAN", "0.002"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Virtualenv is up to date
+    Running benchmarks for my-project ([TEMP_PATH]/project)
+    Stored results as run [RUN]
+    Comparing against run [RUN]
+      bench.py::test_sum: 1.00ms -> 2.00ms (+100.0%) regression
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("bench").arg("--compare").arg("no-such-ref"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Virtualenv is up to date
+    Running benchmarks for my-project ([TEMP_PATH]/project)
+
+    ----- stderr -----
+    error: 'no-such-ref' is neither a stored benchmark run nor a git ref
+    "###);
+}
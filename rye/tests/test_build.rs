@@ -0,0 +1,83 @@
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+#[test]
+fn test_build_for_multiple_pythons() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd()
+        .arg("build")
+        .arg("--python")
+        .arg("cpython@3.11.8,cpython@3.12.2")
+        .arg("-q"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    "###);
+
+    // the pure Python wheel is the same for both versions and only listed once.
+    let manifest: serde_json::Value =
+        serde_json::from_str(&space.read_string("dist/manifest.json")).unwrap();
+    let artifacts = manifest["artifacts"].as_array().unwrap();
+    assert_eq!(artifacts.len(), 2, "{:#}", manifest);
+    assert_eq!(artifacts[0]["kind"], "sdist");
+    assert_eq!(artifacts[0]["project"], "my-project");
+    assert!(artifacts[0].get("python").is_none());
+    assert_eq!(artifacts[1]["kind"], "wheel");
+    assert_eq!(artifacts[1]["python"], "cpython@3.11.8");
+    for artifact in artifacts {
+        let filename = artifact["filename"].as_str().unwrap();
+        assert!(space.project_path().join("dist").join(filename).is_file());
+        assert_eq!(artifact["sha256"].as_str().unwrap().len(), 64);
+    }
+
+    // the build requirements are pinned for the next build.
+    assert!(space
+        .read_string("build-requirements.lock")
+        .contains("hatchling=="));
+}
+
+#[test]
+fn test_build_env_is_hermetic() {
+    let space = Space::new();
+    space.init("my-project");
+    space.write(
+        "hatch_build.py",
+        r#"import json
+import os
+
+from hatchling.builders.hooks.plugin.interface import BuildHookInterface
+
+
+class CustomBuildHook(BuildHookInterface):
+    def initialize(self, version, build_data):
+        names = ["PYTHONPATH", "VIRTUAL_ENV", "PYTHONNOUSERSITE"]
+        with open(os.path.join(self.root, "build-env.json"), "w") as f:
+            json.dump({name: os.environ.get(name) for name in names}, f)
+"#,
+    );
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["hatch"]["build"]["hooks"]["custom"] = toml_edit::table();
+    });
+
+    let status = space
+        .rye_cmd()
+        .arg("build")
+        .arg("--wheel")
+        .arg("-q")
+        .env("PYTHONPATH", space.project_path().join("leaked"))
+        .env("VIRTUAL_ENV", space.project_path().join("leaked-venv"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let env: serde_json::Value =
+        serde_json::from_str(&space.read_string("build-env.json")).unwrap();
+    assert!(env["PYTHONPATH"].is_null(), "{}", env);
+    assert!(env["VIRTUAL_ENV"].is_null(), "{}", env);
+    assert_eq!(env["PYTHONNOUSERSITE"], "1");
+}
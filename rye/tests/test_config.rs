@@ -17,7 +17,11 @@ fn test_config_empty() {
       ----- stderr -----
       Reads or modifies the global `config.toml` file
 
-      Usage: rye config [OPTIONS]
+      Usage: rye config [OPTIONS] [COMMAND]
+
+      Commands:
+        credentials  Manages credentials for package sources
+        help         Print this message or the help of the given subcommand(s)
 
       Options:
             --show-path            Print the path to the config
@@ -28,6 +32,9 @@ fn test_config_empty() {
             --set-int <SET_INT>    Sets a config key to an integer
             --set-bool <SET_BOOL>  Sets a config key to a bool
             --unset <UNSET>        Remove a config key
+            --show-origin          Show where values come from (lists all set keys without `--get`)
+            --list                 List all known config keys with their value, default and description
+            --schema               Print a JSON schema of the config file
         -h, --help                 Print help (see more with '--help')
     "###);
 }
@@ -0,0 +1,113 @@
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+#[test]
+fn test_entrypoint_add_list_remove() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("entrypoint").arg("add").arg("greet").arg("my_project:hello"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Added entry point greet (my_project:hello)
+    Run `rye sync` to install it.
+
+    ----- stderr -----
+    "###);
+    rye_cmd_snapshot!(space.rye_cmd().arg("entrypoint").arg("add").arg("greet-gui").arg("my_project:hello").arg("--gui"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Added entry point greet-gui (my_project:hello)
+    Run `rye sync` to install it.
+
+    ----- stderr -----
+    "###);
+    space.load_toml("pyproject.toml", |doc| {
+        assert_eq!(
+            doc["project"]["scripts"]["greet"].as_str(),
+            Some("my_project:hello")
+        );
+        assert_eq!(
+            doc["project"]["gui-scripts"]["greet-gui"].as_str(),
+            Some("my_project:hello")
+        );
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("entrypoint").arg("list").arg("--format=json"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [
+      {
+        "name": "greet",
+        "reference": "my_project:hello",
+        "gui": false
+      },
+      {
+        "name": "greet-gui",
+        "reference": "my_project:hello",
+        "gui": true
+      }
+    ]
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("entrypoint").arg("remove").arg("greet-gui"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Removed entry point greet-gui
+
+    ----- stderr -----
+    "###);
+    rye_cmd_snapshot!(space.rye_cmd().arg("entrypoint").arg("list"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    greet (my_project:hello)
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_entrypoint_add_verifies_reference() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("entrypoint").arg("add").arg("greet").arg("my_project"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: invalid entry point 'my_project', expected 'module:function'
+    "###);
+    rye_cmd_snapshot!(space.rye_cmd().arg("entrypoint").arg("add").arg("greet").arg("my_project.cli:main"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: module 'my_project.cli' not found in the project. Use --no-verify to add it anyway.
+    "###);
+    rye_cmd_snapshot!(space.rye_cmd().arg("entrypoint").arg("add").arg("greet").arg("my_project:main"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: module 'my_project' does not define 'main'. Use --no-verify to add it anyway.
+    "###);
+    rye_cmd_snapshot!(space.rye_cmd().arg("entrypoint").arg("add").arg("greet").arg("my_project:main").arg("--no-verify").arg("-q"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    "###);
+}
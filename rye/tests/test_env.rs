@@ -0,0 +1,67 @@
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+#[test]
+fn test_env_bash() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("env").arg("--shell").arg("bash"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    export VIRTUAL_ENV='[TEMP_PATH]/project/.venv'
+    export VIRTUAL_ENV_PROMPT='my-project'
+    export PATH='[TEMP_PATH]/project/.venv/bin':"$PATH"
+    unset PYTHONHOME
+
+    ----- stderr -----
+    "###);
+    assert!(space.venv_path().is_dir());
+}
+
+#[test]
+fn test_env_fish() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("env").arg("--shell").arg("fish"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    set -gx VIRTUAL_ENV '[TEMP_PATH]/project/.venv';
+    set -gx VIRTUAL_ENV_PROMPT 'my-project';
+    set -gx PATH '[TEMP_PATH]/project/.venv/bin' $PATH;
+    set -e PYTHONHOME;
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_env_json() {
+    let space = Space::new();
+    space.init("my-project");
+
+    let output = space
+        .rye_cmd()
+        .arg("env")
+        .arg("--shell")
+        .arg("json")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let vars: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let venv = space.venv_path();
+    assert_eq!(vars["VIRTUAL_ENV"], venv.to_str().unwrap());
+    assert_eq!(vars["VIRTUAL_ENV_PROMPT"], "my-project");
+    assert!(vars["PYTHONHOME"].is_null());
+    let path = std::env::split_paths(vars["PATH"].as_str().unwrap())
+        .next()
+        .unwrap();
+    assert_eq!(
+        path,
+        venv.join(if cfg!(windows) { "Scripts" } else { "bin" })
+    );
+}
@@ -0,0 +1,236 @@
+use insta::assert_snapshot;
+
+use crate::common::Space;
+
+mod common;
+
+/// Sets up a basic project with a pinned Python version and a lockfile.
+fn setup_project(space: &Space) {
+    space.init("my-project");
+    space.write(".python-version", "cpython@3.12.2\n");
+    space.write(
+        "requirements.lock",
+        "# generated by rye\n\
+         iniconfig==2.0.0 \\\n    \
+         --hash=sha256:b6a85871a79d2e3b22d2d1b94ac2824226a63c6b741c88f7ae975f18b6778374\n\
+         -e file:.\n",
+    );
+}
+
+/// Runs `rye generate` quietly and asserts that it succeeds.
+fn generate(space: &Space, args: &[&str]) {
+    let status = space
+        .rye_cmd()
+        .arg("generate")
+        .args(args)
+        .arg("-q")
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn test_generate_dockerfile() {
+    let space = Space::new();
+    setup_project(&space);
+    generate(&space, &["dockerfile"]);
+
+    assert_snapshot!(space.read_string("Dockerfile"), @r###"
+    # generated by rye from pyproject.toml and requirements.lock
+    # use `rye generate dockerfile` to update this file
+
+    FROM python:3.12.2 AS builder
+
+    ENV PIP_DISABLE_PIP_VERSION_CHECK=1 \
+        PIP_NO_CACHE_DIR=1 \
+        PYTHONDONTWRITEBYTECODE=1
+
+    WORKDIR /app
+    RUN python -m venv /opt/venv
+    ENV PATH="/opt/venv/bin:$PATH"
+
+    # the locked dependencies are installed first so that they are cached
+    COPY requirements.lock ./
+    RUN sed '/^-e /d' requirements.lock > requirements.txt \
+        && pip install --no-deps --require-hashes -r requirements.txt
+
+    COPY pyproject.toml README.md ./
+    COPY src ./src
+    RUN pip install --no-deps .
+
+    FROM python:3.12.2
+
+    ENV PYTHONUNBUFFERED=1
+    ENV PATH="/opt/venv/bin:$PATH"
+    COPY --from=builder /opt/venv /opt/venv
+
+    WORKDIR /app
+    USER nobody
+    CMD ["python","-m","my_project"]
+    "###);
+    assert_snapshot!(space.read_string(".dockerignore"), @r###"
+    # generated by rye, only the files needed to build the image are sent
+    *
+    !requirements.lock
+    !pyproject.toml
+    !README.md
+    !src
+    **/__pycache__
+    **/*.py[oc]
+    "###);
+
+    generate(&space, &["dockerfile", "--check"]);
+}
+
+#[test]
+fn test_generate_devcontainer() {
+    let space = Space::new();
+    setup_project(&space);
+    generate(&space, &["devcontainer"]);
+
+    let devcontainer = space
+        .read_string(".devcontainer/devcontainer.json")
+        .replace(env!("CARGO_PKG_VERSION"), "[VERSION]");
+    assert_snapshot!(devcontainer, @r###"
+    {
+      "name": "my-project",
+      "image": "mcr.microsoft.com/devcontainers/base:bookworm",
+      "features": {
+        "./rye": {
+          "version": "[VERSION]",
+          "toolchain": "cpython@3.12.2"
+        }
+      },
+      "mounts": [
+        "source=my-project-venv,target=${containerWorkspaceFolder}/.venv,type=volume"
+      ],
+      "postCreateCommand": "sudo chown \"$(id -u):$(id -g)\" .venv \u0026\u0026 rye sync"
+    }
+    "###);
+    assert!(space
+        .project_path()
+        .join(".devcontainer/rye/devcontainer-feature.json")
+        .is_file());
+    assert!(space
+        .project_path()
+        .join(".devcontainer/rye/install.sh")
+        .is_file());
+}
+
+#[test]
+fn test_generate_ci_github() {
+    let space = Space::new();
+    setup_project(&space);
+    generate(&space, &["ci"]);
+
+    let workflow = space
+        .read_string(".github/workflows/ci.yml")
+        .replace(env!("CARGO_PKG_VERSION"), "[VERSION]");
+    assert_snapshot!(workflow, @r###"
+    # generated by rye from pyproject.toml
+    # use `rye generate ci --provider github` to update this file
+    name: CI
+
+    on:
+      push:
+        branches: [main]
+      pull_request:
+
+    env:
+      RYE_VERSION: "[VERSION]"
+      RYE_INSTALL_OPTION: "--yes"
+
+    jobs:
+      check:
+        runs-on: ubuntu-latest
+        steps:
+          - uses: actions/checkout@v4
+          - name: Install rye
+            run: |
+              curl -sSf https://rye-up.com/get | bash
+              echo "$HOME/.rye/shims" >> "$GITHUB_PATH"
+          - name: Cache toolchains and packages
+            uses: actions/cache@v4
+            with:
+              path: |
+                ~/.rye/py
+                ~/.cache/uv
+              key: rye-${{ runner.os }}-${{ hashFiles('.python-version', '**/requirements*.lock') }}
+          - name: Install dependencies
+            run: rye sync --no-lock
+          - name: Check lockfiles
+            run: rye sync --check
+          - name: Check formatting
+            run: rye fmt --check
+          - name: Lint
+            run: rye lint
+
+      test:
+        runs-on: ubuntu-latest
+        strategy:
+          fail-fast: false
+          matrix:
+            python: ["3.12"]
+        steps:
+          - uses: actions/checkout@v4
+          - name: Install rye
+            run: |
+              curl -sSf https://rye-up.com/get | bash
+              echo "$HOME/.rye/shims" >> "$GITHUB_PATH"
+          - name: Cache toolchains and packages
+            uses: actions/cache@v4
+            with:
+              path: |
+                ~/.rye/py
+                ~/.cache/uv
+              key: rye-${{ runner.os }}-${{ matrix.python }}-${{ hashFiles('.python-version', '**/requirements*.lock') }}
+          - name: Pin Python
+            run: rye pin ${{ matrix.python }} --no-update-requires-python
+          - name: Install dependencies
+            run: rye sync
+          - name: Test
+            run: rye test
+    "###);
+
+    generate(&space, &["ci", "--check"]);
+}
+
+#[test]
+fn test_generate_pre_commit() {
+    let space = Space::new();
+    space.init("my-project");
+    generate(&space, &["pre-commit"]);
+
+    // without configuration the hook formats and lints
+    assert_snapshot!(space.read_string(".pre-commit-config.yaml"), @r###"
+    # generated by rye, the hook runs `tool.rye.hooks.pre-commit`
+    # use `rye generate pre-commit` to update this file
+    repos:
+      - repo: local
+        hooks:
+          - id: rye
+            name: "rye: fmt, lint"
+            entry: rye run --hook pre-commit
+            language: system
+            types_or: [python, pyi]
+            require_serial: true
+    "###);
+    generate(&space, &["pre-commit", "--check"]);
+
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["hooks"]["pre-commit"] =
+            toml_edit::value(toml_edit::Array::from_iter(["lint", "typecheck"]));
+    });
+    let status = space
+        .rye_cmd()
+        .arg("generate")
+        .arg("pre-commit")
+        .arg("--check")
+        .status()
+        .unwrap();
+    assert!(!status.success());
+    generate(&space, &["pre-commit", "--force"]);
+    assert!(space
+        .read_string(".pre-commit-config.yaml")
+        .contains("name: \"rye: lint, typecheck\""));
+}
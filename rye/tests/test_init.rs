@@ -1,3 +1,5 @@
+use insta::assert_snapshot;
+
 use crate::common::{get_bin, rye_cmd_snapshot, Space};
 
 mod common;
@@ -163,3 +165,90 @@ fn test_init_lib_and_script_incompatible() {
         error: an argument cannot be used with one or more of the other specified arguments
     "###);
 }
+
+/// Initializes a project with a build backend and returns everything but the
+/// `[project]` table of its pyproject.toml.
+fn init_with_build_backend(flag: &str, backend: &str) -> String {
+    let space = Space::new();
+    let status = space
+        .rye_cmd()
+        .arg("init")
+        .arg("--name")
+        .arg("my-project")
+        .arg("-q")
+        .arg(flag)
+        .arg(backend)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let mut doc = space.read_toml("pyproject.toml");
+    doc.remove("project");
+    doc.to_string().trim_start().to_string()
+}
+
+#[test]
+fn test_init_build_system_setuptools() {
+    assert_snapshot!(init_with_build_backend("--build-system", "setuptools"), @r###"
+    [build-system]
+    requires = ["setuptools>=61.0"]
+    build-backend = "setuptools.build_meta"
+
+    [tool.rye]
+    managed = true
+    dev-dependencies = []
+
+    [tool.setuptools.packages.find]
+    where = ["src"]
+    "###);
+}
+
+#[test]
+fn test_init_build_system_flit() {
+    assert_snapshot!(init_with_build_backend("--build-system", "flit"), @r###"
+    [build-system]
+    requires = ["flit_core>=3.4"]
+    build-backend = "flit_core.buildapi"
+
+    [tool.rye]
+    managed = true
+    dev-dependencies = []
+
+    [tool.flit.module]
+    name = "my_project"
+    "###);
+}
+
+// `--build-backend` is an alias of `--build-system`
+#[test]
+fn test_init_build_backend_pdm() {
+    assert_snapshot!(init_with_build_backend("--build-backend", "pdm"), @r###"
+    [build-system]
+    requires = ["pdm-backend"]
+    build-backend = "pdm.backend"
+
+    [tool.rye]
+    managed = true
+    dev-dependencies = []
+
+    [tool.pdm.build]
+    package-dir = "src"
+    includes = ["src/my_project"]
+    "###);
+}
+
+#[test]
+fn test_init_fetch() {
+    let space = Space::new();
+
+    // the default toolchain is installed already
+    rye_cmd_snapshot!(space.rye_cmd().arg("init").arg("--name").arg("my-project").arg("--fetch"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    success: Initialized project in [TEMP_PATH]/project
+      Run `rye sync` to get started
+
+    ----- stderr -----
+    "###);
+    assert_eq!(space.read_string(".python-version"), "3.12.2\n");
+}
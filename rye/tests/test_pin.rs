@@ -0,0 +1,78 @@
+use toml_edit::value;
+
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+#[test]
+fn test_pin_strict_outside_requires_python() {
+    let space = Space::new();
+    space.init("my-project");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["project"]["requires-python"] = value(">= 3.8, < 3.12");
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("pin").arg("--strict").arg("3.12.2"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: cannot pin Python cpython@3.12.2 as my-project requires Python >=3.8, <3.12
+    "###);
+
+    // without --strict the pin goes through
+    rye_cmd_snapshot!(space.rye_cmd().arg("pin").arg("3.12.2"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pinned 3.12.2 in [TEMP_PATH]/project/.python-version
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_pin_strict_within_requires_python() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("pin").arg("--strict").arg("3.11.1"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pinned 3.11.1 in [TEMP_PATH]/project/.python-version
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_pin_fetch() {
+    let space = Space::new();
+    space.init("my-project");
+
+    // the toolchain is already installed, so fetching it does not print anything
+    rye_cmd_snapshot!(space.rye_cmd().arg("pin").arg("--fetch").arg("3.11.8"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pinned 3.11.8 in [TEMP_PATH]/project/.python-version
+
+    ----- stderr -----
+    "###);
+
+    // a relaxed pin is written even if there is nothing to fetch for it
+    let output = space
+        .rye_cmd()
+        .arg("pin")
+        .arg("--fetch")
+        .arg("--relaxed")
+        .arg("3.11.99")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("3.11.99"), "{}", stderr);
+    assert_eq!(space.read_string(".python-version"), "3.11.99\n");
+}
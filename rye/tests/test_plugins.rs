@@ -0,0 +1,88 @@
+#![cfg(unix)]
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Stdio;
+
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+#[test]
+fn test_executable_plugin() {
+    let space = Space::new();
+    let bin_dir = space.project_path().join("plugin-bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let plugin = bin_dir.join("rye-foo");
+    fs::write(
+        &plugin,
+        "#!/bin/sh\necho \"plugin $RYE_PLUGIN called with: $*\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut paths = vec![bin_dir];
+    paths.extend(env::split_paths(&env::var_os("PATH").unwrap_or_default()));
+    let path = env::join_paths(paths).unwrap();
+
+    rye_cmd_snapshot!(space.rye_cmd().env("PATH", &path).arg("foo").arg("bar").arg("--baz"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    plugin foo called with: bar --baz
+
+    ----- stderr -----
+    "###);
+
+    let output = space
+        .rye_cmd()
+        .env("PATH", &path)
+        .arg("--help")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("  foo "), "{}", stdout);
+}
+
+#[test]
+fn test_plugin_context_and_stdin() {
+    let space = Space::new();
+    space.init("my-project");
+    let bin_dir = space.project_path().join("plugin-bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let plugin = bin_dir.join("rye-ctx");
+    fs::write(
+        &plugin,
+        "#!/bin/sh\necho \"$RYE_CONTEXT\"\nread line\necho \"got: $line\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut paths = vec![bin_dir];
+    paths.extend(env::split_paths(&env::var_os("PATH").unwrap_or_default()));
+    let path = env::join_paths(paths).unwrap();
+
+    let mut child = space
+        .rye_cmd()
+        .env("PATH", &path)
+        .arg("ctx")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"hello from stdin\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let context: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert_eq!(context["plugin"], "ctx");
+    assert_eq!(context["project"]["name"], "my-project");
+    assert_eq!(lines.next(), Some("got: hello from stdin"));
+}
@@ -1,3 +1,5 @@
+use std::process::Command;
+
 use crate::common::{rye_cmd_snapshot, Space};
 
 mod common;
@@ -29,3 +31,159 @@ fn test_publish_outside_project() {
     error: failed to publish files
     "###);
 }
+
+#[test]
+fn test_publish_dry_run() {
+    let space = Space::new();
+    space.init("my-project");
+
+    let status = space.rye_cmd().arg("build").status().unwrap();
+    assert!(status.success());
+
+    rye_cmd_snapshot!(space
+        .rye_cmd()
+        .arg("publish")
+        .arg("--dry-run")
+        .arg("--yes")
+        .arg("--token")
+        .arg("fake-token")
+        .arg("--include")
+        .arg("*.whl"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Would publish to pypi (https://upload.pypi.org/legacy/)
+    Credentials: --token argument
+      my_project-0.1.0-py3-none-any.whl
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(space
+        .rye_cmd()
+        .arg("publish")
+        .arg("--dry-run")
+        .arg("--yes")
+        .arg("--token")
+        .arg("fake-token")
+        .arg("--exclude")
+        .arg("*"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: no distribution files found to publish
+    "###);
+}
+
+/// A `publish --dry-run` of the wheel outside of any CI.
+fn publish_dry_run(space: &Space) -> Command {
+    let mut cmd = space.rye_cmd();
+    cmd.arg("publish")
+        .arg("--dry-run")
+        .arg("--yes")
+        .arg("--include")
+        .arg("*.whl");
+    for var in [
+        "ACTIONS_ID_TOKEN_REQUEST_URL",
+        "ACTIONS_ID_TOKEN_REQUEST_TOKEN",
+        "GITLAB_CI",
+        "PYPI_ID_TOKEN",
+    ] {
+        cmd.env_remove(var);
+    }
+    cmd
+}
+
+#[test]
+fn test_publish_trusted_publishing() {
+    let space = Space::new();
+    space.init("my-project");
+
+    let status = space.rye_cmd().arg("build").arg("-q").status().unwrap();
+    assert!(status.success());
+
+    // in GitHub Actions with an id token the OIDC token is exchanged
+    rye_cmd_snapshot!(publish_dry_run(&space)
+        .env("ACTIONS_ID_TOKEN_REQUEST_URL", "https://token.actions.invalid/")
+        .env("ACTIONS_ID_TOKEN_REQUEST_TOKEN", "fake-request-token"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Would publish to pypi (https://upload.pypi.org/legacy/)
+    Credentials: trusted publishing (GitHub Actions)
+      my_project-0.1.0-py3-none-any.whl
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(publish_dry_run(&space)
+        .env("GITLAB_CI", "true")
+        .env("PYPI_ID_TOKEN", "fake-id-token"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Would publish to pypi (https://upload.pypi.org/legacy/)
+    Credentials: trusted publishing (GitLab CI)
+      my_project-0.1.0-py3-none-any.whl
+
+    ----- stderr -----
+    "###);
+
+    // explicit credentials win unless trusted publishing is enforced
+    rye_cmd_snapshot!(publish_dry_run(&space)
+        .arg("--token")
+        .arg("fake-token")
+        .env("GITLAB_CI", "true")
+        .env("PYPI_ID_TOKEN", "fake-id-token"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Would publish to pypi (https://upload.pypi.org/legacy/)
+    Credentials: --token argument
+      my_project-0.1.0-py3-none-any.whl
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(publish_dry_run(&space)
+        .arg("--token")
+        .arg("fake-token")
+        .arg("--trusted-publishing")
+        .arg("always")
+        .env("GITLAB_CI", "true")
+        .env("PYPI_ID_TOKEN", "fake-id-token"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Would publish to pypi (https://upload.pypi.org/legacy/)
+    Credentials: trusted publishing (GitLab CI)
+      my_project-0.1.0-py3-none-any.whl
+
+    ----- stderr -----
+    "###);
+
+    let output = publish_dry_run(&space)
+        .arg("--trusted-publishing")
+        .arg("never")
+        .env("GITLAB_CI", "true")
+        .env("PYPI_ID_TOKEN", "fake-id-token")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("trusted publishing"), "{}", stdout);
+
+    // outside of a supported CI trusted publishing cannot be enforced
+    rye_cmd_snapshot!(publish_dry_run(&space)
+        .arg("--trusted-publishing")
+        .arg("always"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: trusted publishing requires GitHub Actions (with `id-token: write` permission) or GitLab CI (with a PYPI_ID_TOKEN id token)
+    "###);
+}
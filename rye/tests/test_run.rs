@@ -0,0 +1,331 @@
+use std::fs;
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use toml_edit::{value, Array, InlineTable};
+
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+fn add_script(space: &Space, name: &str, call: &str, depends: &[&str]) {
+    space.edit_toml("pyproject.toml", |doc| {
+        let mut script = InlineTable::new();
+        script.insert("call", call.into());
+        if !depends.is_empty() {
+            script.insert("depends", Array::from_iter(depends.iter().copied()).into());
+        }
+        doc["tool"]["rye"]["scripts"][name] = value(script);
+    });
+}
+
+#[test]
+fn test_run_script_dependencies() {
+    let space = Space::new();
+    space.init("my-project");
+    add_script(&space, "a", "builtins:print('a')", &[]);
+    add_script(&space, "b", "builtins:print('b')", &["a"]);
+    add_script(&space, "c", "builtins:print('c')", &["a", "b"]);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("c"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    a
+    b
+    c
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    "###);
+}
+
+#[test]
+fn test_run_script_dependency_cycle() {
+    let space = Space::new();
+    space.init("my-project");
+    add_script(&space, "a", "builtins:print('a')", &["b"]);
+    add_script(&space, "b", "builtins:print('b')", &["a"]);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("a"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    error: cyclic script dependency: a -> b -> a
+    "###);
+}
+
+#[test]
+fn test_run_env_files() {
+    let space = Space::new();
+    space.init("my-project");
+    space.write(".env", "FOO=1\nBAR=\"from env\"\n");
+    space.write(".env.local", "FOO=2\n");
+    space.write(".env.script", "BAZ='single quoted'\n");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["env-file"] = value(Array::from_iter([".env", ".env.local"]));
+        let mut script = InlineTable::new();
+        script.insert(
+            "call",
+            "builtins:print(*map(__import__('os').environ.get, ['FOO', 'BAR', 'BAZ']))".into(),
+        );
+        script.insert("env-file", Array::from_iter([".env.script"]).into());
+        doc["tool"]["rye"]["scripts"]["show-env"] = value(script);
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("show-env"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    2 from env single quoted
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    "###);
+}
+
+#[test]
+fn test_run_parallel() {
+    let space = Space::new();
+    space.init("my-project");
+    add_script(&space, "ok", "builtins:print('ok')", &[]);
+    add_script(&space, "fail", "sys:exit(2)", &[]);
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["scripts"]["both"]["chain-parallel"] =
+            value(Array::from_iter(["ok", "fail"]));
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("both"), @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    ok   | ok
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    fail failed with exit status: 2
+    "###);
+}
+
+#[test]
+fn test_run_builtins() {
+    let space = Space::new();
+    space.init("my-project");
+    space.write("dist/old.txt", "old");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["scripts"]["build"]["chain"] = value(Array::from_iter([
+            "@rm -rf dist",
+            "@mkdir -p dist/sub",
+            "@cp pyproject.toml dist/sub",
+            "@env GREETING=hello python -c \"import os; print(os.environ['GREETING'])\"",
+            "@echo done",
+        ]));
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("build"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hello
+    done
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    "###);
+    assert!(!space.project_path().join("dist/old.txt").exists());
+    assert!(space
+        .project_path()
+        .join("dist/sub/pyproject.toml")
+        .is_file());
+}
+
+#[test]
+fn test_run_with_overlay() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("--with").arg("six").arg("python").arg("-c").arg("import six; print(six.__name__)"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    six
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    Installing six into overlay
+    "###);
+    assert!(!space.read_string("pyproject.toml").contains("six"));
+}
+
+#[test]
+fn test_run_isolated() {
+    let space = Space::new();
+    space.init("my-project");
+    space.write(".env", "FROM_FILE=1\n");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["env-file"] = value(".env");
+    });
+    add_script(
+        &space,
+        "show-env",
+        "builtins:print(*map(__import__('os').environ.get, ['INHERITED', 'KEPT', 'FROM_FILE', 'PYTHONNOUSERSITE', 'HTTPS_PROXY']))",
+        &[],
+    );
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("--isolated").arg("--allow-env").arg("KEPT").arg("show-env").env("INHERITED", "1").env("KEPT", "1"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    None 1 1 1 http://127.0.0.1:9
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    "###);
+}
+
+#[test]
+fn test_run_isolated_allow_env_case_insensitive() {
+    let space = Space::new();
+    space.init("my-project");
+    add_script(
+        &space,
+        "show-env",
+        "builtins:print(*map(__import__('os').environ.get, ['KEPT', 'APP_DEBUG', 'OTHER', 'HTTPS_PROXY']))",
+        &[],
+    );
+
+    // exact names and prefixes follow the same matching rule
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("--isolated").arg("--online").arg("--allow-env").arg("kept").arg("--allow-env").arg("app_*").arg("show-env").env("KEPT", "1").env("APP_DEBUG", "1").env("OTHER", "1").env_remove("HTTPS_PROXY"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    1 1 None None
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    "###);
+}
+
+#[test]
+fn test_run_script_with_inline_metadata() {
+    let space = Space::new();
+    space.write(
+        "hello.py",
+        r#"# /// script
+# requires-python = ">=3.8"
+# dependencies = []
+# ///
+import sys
+print("Hello", *sys.argv[1:])
+"#,
+    );
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("--script").arg("hello.py").arg("--name").arg("World"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Hello --name World
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_run_watch_reruns_after_change() {
+    let space = Space::new();
+    space.init("my-project");
+    add_script(
+        &space,
+        "tick",
+        "builtins:open('ticks.txt', 'a').write('tick\\n')",
+        &[],
+    );
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+
+    let ticks = space.project_path().join("ticks.txt");
+    let wait_for_ticks = |expected: usize| {
+        let started = Instant::now();
+        loop {
+            let count = fs::read_to_string(&ticks)
+                .map(|x| x.lines().count())
+                .unwrap_or(0);
+            if count >= expected {
+                return count;
+            }
+            if started.elapsed() > Duration::from_secs(60) {
+                return count;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    };
+
+    let mut child = space
+        .rye_cmd()
+        .arg("run")
+        .arg("--watch")
+        .arg("src")
+        .arg("tick")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let first = wait_for_ticks(1);
+    space.write("src/my_project/changed.py", "# changed\n");
+    let second = wait_for_ticks(2);
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(first, 1);
+    assert_eq!(second, 2);
+}
+
+#[test]
+fn test_run_hook_on_files() {
+    let space = Space::new();
+    space.init("my-project");
+
+    // the default pre-commit hook only looks at Python files
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("--hook").arg("pre-commit").arg("README.md"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    "###);
+
+    space.edit_toml("pyproject.toml", |doc| {
+        let mut script = InlineTable::new();
+        script.insert(
+            "cmd",
+            Array::from_iter(["python", "-c", "import sys; print(*sys.argv[1:])"]).into(),
+        );
+        doc["tool"]["rye"]["scripts"]["check"] = value(script);
+        doc["tool"]["rye"]["hooks"]["pre-commit"] = value("check");
+    });
+
+    // scripts get all files, relative to where the hook was started
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("--hook").arg("pre-commit").arg("README.md").arg("src/my_project/__init__.py"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Running pre-commit hook: check
+    [TEMP_PATH]/project/README.md [TEMP_PATH]/project/src/my_project/__init__.py
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    "###);
+}
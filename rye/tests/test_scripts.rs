@@ -0,0 +1,110 @@
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+#[test]
+fn test_scripts_add_list_remove() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("scripts").arg("add").arg("serve").arg("flask run --debug"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Added script serve
+
+    ----- stderr -----
+    "###);
+    rye_cmd_snapshot!(space.rye_cmd().arg("scripts").arg("add").arg("http").arg("http.server").arg("--call").arg("--description").arg("Serves the folder"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Added script http
+
+    ----- stderr -----
+    "###);
+    space.load_toml("pyproject.toml", |doc| {
+        let scripts = &doc["tool"]["rye"]["scripts"];
+        assert_eq!(scripts["serve"].as_str(), Some("flask run --debug"));
+        assert_eq!(scripts["http"]["call"].as_str(), Some("http.server"));
+        assert_eq!(
+            scripts["http"]["description"].as_str(),
+            Some("Serves the folder")
+        );
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("scripts").arg("list"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    serve (flask run --debug)
+    http (http.server)
+      Serves the folder
+
+    ----- stderr -----
+    "###);
+    rye_cmd_snapshot!(space.rye_cmd().arg("scripts").arg("list").arg("--format=json"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [
+      {
+        "name": "serve",
+        "cmd": "flask run --debug",
+        "description": null,
+        "depends": []
+      },
+      {
+        "name": "http",
+        "cmd": "http.server",
+        "description": "Serves the folder",
+        "depends": []
+      }
+    ]
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("scripts").arg("remove").arg("serve"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Removed script serve
+
+    ----- stderr -----
+    "###);
+    rye_cmd_snapshot!(space.rye_cmd().arg("scripts").arg("remove").arg("serve"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: script 'serve' does not exist
+    "###);
+}
+
+#[test]
+fn test_scripts_add_existing() {
+    let space = Space::new();
+    space.init("my-project");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["scripts"]["serve"] = toml_edit::value("flask run");
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("scripts").arg("add").arg("serve").arg("flask run --debug"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: script 'serve' already exists. Use --force to replace it.
+    "###);
+    rye_cmd_snapshot!(space.rye_cmd().arg("scripts").arg("add").arg("serve").arg("flask run --debug").arg("--force"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Added script serve
+
+    ----- stderr -----
+    "###);
+}
@@ -0,0 +1,88 @@
+#![cfg(unix)]
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+/// Writes a stand-in for the user's shell that runs `body` instead of
+/// reading commands interactively.
+fn fake_shell(space: &Space, body: &str) -> PathBuf {
+    let path = space.project_path().join("fake-shell");
+    fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[test]
+fn test_shell_activates_venv() {
+    let space = Space::new();
+    space.init("my-project");
+    let shell = fake_shell(
+        &space,
+        "echo \"VIRTUAL_ENV=$VIRTUAL_ENV\"\n\
+         echo \"RYE_SHELL=$RYE_SHELL\"\n\
+         echo \"PATH=${PATH%%:*}\"",
+    );
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("shell").env("SHELL", &shell).env_remove("RYE_SHELL"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    Spawning virtualenv shell from [TEMP_PATH]/project/.venv
+    Leave shell with 'exit'
+    VIRTUAL_ENV=[TEMP_PATH]/project/.venv
+    RYE_SHELL=[TEMP_PATH]/project/.venv
+    PATH=[TEMP_PATH]/project/.venv/bin
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_shell_exit_code() {
+    let space = Space::new();
+    space.init("my-project");
+    let shell = fake_shell(&space, "exit 3");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("shell").arg("--no-banner").env("SHELL", &shell).env_remove("RYE_SHELL"), @r###"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_shell_nested() {
+    let space = Space::new();
+    space.init("my-project");
+    let shell = fake_shell(&space, "echo \"RYE_SHELL=$RYE_SHELL\"");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("shell").env("SHELL", &shell).env("RYE_SHELL", "/elsewhere/.venv"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: already inside a rye shell for /elsewhere/.venv. Leave it with 'exit' or pass --allow-nested
+    "###);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("shell").arg("--no-banner").arg("--allow-nested").env("SHELL", &shell).env("RYE_SHELL", "/elsewhere/.venv"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    RYE_SHELL=[TEMP_PATH]/project/.venv
+
+    ----- stderr -----
+    "###);
+}
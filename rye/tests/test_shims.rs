@@ -0,0 +1,32 @@
+use std::path::Path;
+
+use crate::common::Space;
+
+mod common;
+
+/// Returns the `major.minor` version of the Python a shim resolves to.
+fn shim_python_version(space: &Space, python: &Path) -> String {
+    let output = space
+        .cmd(python)
+        .arg("-c")
+        .arg("import sys; print('%d.%d' % sys.version_info[:2])")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+#[test]
+fn test_shim_cache_invalidated_by_python_version() {
+    let space = Space::new();
+    space.init("my-project");
+    space.write(".python-version", "cpython@3.12.2\n");
+    let python = space.shim("python");
+
+    // the second invocation is served from the shim cache
+    assert_eq!(shim_python_version(&space, &python), "3.12");
+    assert_eq!(shim_python_version(&space, &python), "3.12");
+
+    space.write(".python-version", "cpython@3.11.8\n");
+    assert_eq!(shim_python_version(&space, &python), "3.11");
+}
@@ -0,0 +1,144 @@
+use insta::Settings;
+
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+/// Hides the parts of `rye show` that depend on the shared rye home or the
+/// exact size of the virtualenv.
+fn show_settings() -> Settings {
+    let mut settings = Settings::clone_current();
+    settings.add_filter(r"venv size: .*", "venv size: [SIZE]");
+    settings.add_filter(r"(?m)^toolchain: (\S+).*$", "toolchain: $1");
+    settings.add_filter(r"(?m)^shims: .*\n", "");
+    settings.add_filter(r#""size": \d+"#, r#""size": [SIZE]"#);
+    settings.add_filter(
+        r#""toolchain_flavor": (null|"[^"]*")"#,
+        r#""toolchain_flavor": [FLAVOR]"#,
+    );
+    settings.add_filter(r#"(?s)"shims": \[.*?\]"#, r#""shims": [SHIMS]"#);
+    settings
+}
+
+#[test]
+fn test_show() {
+    let space = Space::new();
+    space.init("my-project");
+    let _guard = show_settings().bind_to_scope();
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("show"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    project: my-project
+    version: 0.1.0
+    path: [TEMP_PATH]/project
+    venv: [TEMP_PATH]/project/.venv
+    target python: 3.8
+    venv python: cpython@3.12.2
+    venv status: missing
+    virtual: false
+    configured sources:
+      default (index: https://pypi.org/simple/)
+
+    ----- stderr -----
+    "###);
+
+    let status = space.rye_cmd().arg("sync").arg("-q").status().unwrap();
+    assert!(status.success());
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("show"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    project: my-project
+    version: 0.1.0
+    path: [TEMP_PATH]/project
+    venv: [TEMP_PATH]/project/.venv
+    target python: 3.8
+    venv python: cpython@3.12.2
+    venv status: in sync
+    venv size: [SIZE]
+    toolchain: cpython@3.12.2
+    virtual: false
+    configured sources:
+      default (index: https://pypi.org/simple/)
+
+    ----- stderr -----
+    "###);
+
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["project"]["dependencies"] = toml_edit::value(toml_edit::Array::from_iter(["six"]));
+    });
+    let output = space.rye_cmd().arg("show").output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("venv status: out of sync with the lockfile (run `rye sync`)"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_show_json() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").arg("-q").status().unwrap();
+    assert!(status.success());
+    let _guard = show_settings().bind_to_scope();
+
+    // this output is consumed by editors, changes to it need a schema bump.
+    rye_cmd_snapshot!(space.rye_cmd().arg("show").arg("--format").arg("json"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    {
+      "schema_version": 1,
+      "name": "my-project",
+      "version": "0.1.0",
+      "root": "[TEMP_PATH]/project",
+      "pyproject": "[TEMP_PATH]/project/pyproject.toml",
+      "virtual": false,
+      "workspace": null,
+      "target_python": "3.8",
+      "lockfiles": {
+        "production": "[TEMP_PATH]/project/requirements.lock",
+        "dev": "[TEMP_PATH]/project/requirements-dev.lock"
+      },
+      "venv": {
+        "path": "[TEMP_PATH]/project/.venv",
+        "status": "in_sync",
+        "python": "[TEMP_PATH]/project/.venv/bin/python",
+        "toolchain": "cpython@3.12.2",
+        "toolchain_flavor": [FLAVOR],
+        "python_version": "3.12.2",
+        "site_packages": "[TEMP_PATH]/project/.venv/lib/python3.12/site-packages",
+        "bin": "[TEMP_PATH]/project/.venv/bin",
+        "size": [SIZE],
+        "shims": [SHIMS]
+      }
+    }
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_show_json_without_venv() {
+    let space = Space::new();
+    space.init("my-project");
+
+    let output = space
+        .rye_cmd()
+        .arg("show")
+        .arg("--format")
+        .arg("json")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(info["venv"]["status"], "missing");
+    assert!(info["venv"]["size"].is_null());
+    assert!(info["venv"]["site_packages"].is_null());
+    assert_eq!(info["venv"]["shims"], serde_json::json!([]));
+}
@@ -1,6 +1,8 @@
 use std::fs;
 
 use insta::{assert_snapshot, Settings};
+use sha2::{Digest, Sha256};
+use toml_edit::value;
 
 use crate::common::{rye_cmd_snapshot, Space};
 
@@ -42,6 +44,350 @@ fn test_empty_sync() {
     }
 }
 
+#[test]
+fn test_sync_skips_unchanged() {
+    let space = Space::new();
+    space.init("my-project");
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    Generating production lockfile: [TEMP_PATH]/project/requirements.lock
+    Generating dev lockfile: [TEMP_PATH]/project/requirements-dev.lock
+    Installing dependencies
+    Done!
+
+    ----- stderr -----
+    Built 1 editable in [EXECUTION_TIME]
+    Installed 1 package in [EXECUTION_TIME]
+     + my-project==0.1.0 (from file:[TEMP_PATH]/project)
+    "###);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Virtualenv is up to date
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_sync_unchanged_skips_lock() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+
+    // another rye process holds the virtualenv, which an unchanged sync does
+    // not need to wait for.
+    let venv = space.project_path().join(".venv");
+    let hash = hex::encode(Sha256::digest(venv.to_string_lossy().as_bytes()));
+    let mut lock = fslock::LockFile::open(
+        &space
+            .rye_home()
+            .join("locks")
+            .join(format!("venv-{}.lock", &hash[..16])),
+    )
+    .unwrap();
+    lock.lock().unwrap();
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").env("RYE_LOCK_TIMEOUT", "1"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Virtualenv is up to date
+
+    ----- stderr -----
+    "###);
+
+    // once something changed it has to wait.
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["project"]["description"] = value("changed");
+    });
+    let output = space
+        .rye_cmd()
+        .arg("sync")
+        .env("RYE_LOCK_TIMEOUT", "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("timed out after 1s"), "{}", stderr);
+    lock.unlock().unwrap();
+}
+
+#[test]
+fn test_sync_restores_emptied_site_packages() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+
+    let site_packages = walkdir::WalkDir::new(space.venv_path())
+        .into_iter()
+        .filter_map(|x| x.ok())
+        .find(|x| x.file_name() == "site-packages")
+        .expect("no site-packages")
+        .into_path();
+    fs::remove_dir_all(&site_packages).unwrap();
+    fs::create_dir_all(&site_packages).unwrap();
+
+    let output = space.rye_cmd().arg("sync").output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Virtualenv is up to date"), "{}", stdout);
+    assert!(stdout.contains("Installing dependencies"), "{}", stdout);
+}
+
+#[test]
+fn test_sync_keeps_unchanged_editables() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+
+    // a reinstall would replace the dist-info folder and lose the marker
+    let dist_info = walkdir::WalkDir::new(space.venv_path())
+        .into_iter()
+        .filter_map(|x| x.ok())
+        .find(|x| x.file_name().to_string_lossy() == "my_project-0.1.0.dist-info")
+        .expect("project is not installed")
+        .into_path();
+    fs::write(dist_info.join("MARKER"), "").unwrap();
+
+    // force a full sync without changing the build inputs
+    fs::remove_file(space.venv_path().join("rye-sync-state.json")).unwrap();
+    let output = space.rye_cmd().arg("sync").output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("Built 1 editable"), "{}", stderr);
+    assert!(dist_info.join("MARKER").is_file());
+
+    // changing the build inputs rebuilds the editable
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["project"]["description"] = value("changed");
+    });
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+    assert!(!dist_info.join("MARKER").is_file());
+}
+
+#[test]
+fn test_sync_check() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--check"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Lockfiles and virtualenv are up to date
+
+    ----- stderr -----
+    "###);
+
+    // a package that is not in the lockfile
+    let site_packages = walkdir::WalkDir::new(space.venv_path())
+        .into_iter()
+        .filter_map(|x| x.ok())
+        .find(|x| x.file_name().to_string_lossy() == "site-packages")
+        .expect("no site-packages")
+        .into_path();
+    fs::create_dir(site_packages.join("stray_package-1.0.dist-info")).unwrap();
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--check"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Virtualenv [TEMP_PATH]/project/.venv does not match [TEMP_PATH]/project/requirements-dev.lock:
+      - stray-package==1.0
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_sync_check_modified_files() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space
+        .rye_cmd()
+        .arg("add")
+        .arg("iniconfig==2.0.0")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--check"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Lockfiles and virtualenv are up to date
+
+    ----- stderr -----
+    "###);
+
+    let init_py = walkdir::WalkDir::new(space.venv_path())
+        .into_iter()
+        .filter_map(|x| x.ok())
+        .find(|x| x.path().ends_with("site-packages/iniconfig/__init__.py"))
+        .expect("iniconfig is not installed")
+        .into_path();
+    let mut contents = fs::read_to_string(&init_py).unwrap();
+    contents.push_str("\nraise SystemExit\n");
+    fs::write(&init_py, contents).unwrap();
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--check"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Installed files in [TEMP_PATH]/project/.venv do not match their hashes:
+      ! iniconfig: iniconfig/__init__.py was modified
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_sync_summary() {
+    let space = Space::new();
+    space.init("my-project");
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--summary"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    1 installed, 0 removed, lock changed: yes, done in [EXECUTION_TIME]
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--summary"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    0 installed, 0 removed, lock changed: no, done in [EXECUTION_TIME]
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_sync_timings() {
+    let space = Space::new();
+    space.init("my-project");
+    let output = space
+        .rye_cmd()
+        .arg("sync")
+        .arg("--timings")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("timings:"), "{}", stderr);
+    assert!(stderr.contains("  resolve (production)"), "{}", stderr);
+    assert!(stderr.contains("  install"), "{}", stderr);
+    assert!(stderr.contains("  total"), "{}", stderr);
+
+    let trace = space.project_path().join("trace.json");
+    let status = space
+        .rye_cmd()
+        .arg("sync")
+        .arg("--force")
+        .arg("--timings=trace.json")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let trace = fs::read_to_string(trace).unwrap();
+    assert!(trace.contains("\"traceEvents\""));
+    assert!(trace.contains("\"name\": \"install\""));
+}
+
+#[test]
+fn test_sync_jobs() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space
+        .rye_cmd()
+        .arg("sync")
+        .arg("-j")
+        .arg("2")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    for lockfile in ["requirements.lock", "requirements-dev.lock"] {
+        let contents = space.read_string(lockfile);
+        assert!(contents.contains("-e file:."), "{}", contents);
+    }
+}
+
+#[test]
+fn test_sync_waits_for_lock() {
+    let space = Space::new();
+    space.init("my-project");
+
+    // pretend that another rye process syncs the same virtualenv
+    let venv = space.project_path().join(".venv");
+    let hash = hex::encode(Sha256::digest(venv.to_string_lossy().as_bytes()));
+    let lock_dir = space.rye_home().join("locks");
+    fs::create_dir_all(&lock_dir).unwrap();
+    let mut lock =
+        fslock::LockFile::open(&lock_dir.join(format!("venv-{}.lock", &hash[..16]))).unwrap();
+    lock.lock().unwrap();
+
+    let output = space
+        .rye_cmd()
+        .arg("sync")
+        .env("RYE_LOCK_TIMEOUT", "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Waiting for another rye process to finish updating the virtualenv"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("timed out after 1s"), "{}", stderr);
+
+    lock.unlock().unwrap();
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn test_sync_hooks() {
+    let space = Space::new();
+    space.init("my-project");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["scripts"]["codegen"]["call"] = value("builtins:print('codegen')");
+        doc["tool"]["rye"]["hooks"]["pre-sync"] = value("codegen");
+    });
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    Running pre-sync hook: codegen
+    codegen
+    Generating production lockfile: [TEMP_PATH]/project/requirements.lock
+    Generating dev lockfile: [TEMP_PATH]/project/requirements-dev.lock
+    Installing dependencies
+    Done!
+
+    ----- stderr -----
+    Built 1 editable in [EXECUTION_TIME]
+    Installed 1 package in [EXECUTION_TIME]
+     + my-project==0.1.0 (from file:[TEMP_PATH]/project)
+    "###);
+}
+
 #[test]
 fn test_add_and_sync_no_auto_sync() {
     let space = Space::new();
@@ -261,3 +607,410 @@ fn test_autosync_remember() {
     werkzeug==3.0.1
     "###);
 }
+
+#[test]
+fn test_lock_exclude_newer() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space
+        .rye_cmd()
+        .arg("lock")
+        .arg("--exclude-newer")
+        .arg("2023-06-01")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let lockfile = fs::read_to_string(space.project_path().join("requirements.lock")).unwrap();
+    assert!(lockfile.contains("#   exclude-newer: \"2023-06-01T00:00:00Z\"\n"));
+
+    // the project setting is used unless the flag is passed
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["exclude-newer"] = value("2023-01-01T12:00:00Z");
+    });
+    let status = space.rye_cmd().arg("lock").arg("--reset").status().unwrap();
+    assert!(status.success());
+    let lockfile = fs::read_to_string(space.project_path().join("requirements.lock")).unwrap();
+    assert!(lockfile.contains("#   exclude-newer: \"2023-01-01T12:00:00Z\"\n"));
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("lock").arg("--exclude-newer").arg("last week"), @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: invalid value 'last week' for '--exclude-newer <DATE>': invalid date 'last week' (expected a date like 2024-01-01 or an RFC 3339 timestamp)
+
+    For more information, try '--help'.
+    "###);
+}
+
+#[test]
+fn test_lock_override() {
+    let space = Space::new();
+    space.init("my-project");
+    space.edit_toml("pyproject.toml", |doc| {
+        let mut deps = toml_edit::Array::new();
+        deps.push("flask==3.0.0");
+        doc["project"]["dependencies"] = value(deps);
+        // flask 3.0.0 requires werkzeug>=3.0.0
+        doc["tool"]["rye"]["override"]["werkzeug"] = value("==2.3.8");
+    });
+    let status = space.rye_cmd().arg("lock").status().unwrap();
+    assert!(status.success());
+    let lockfile = fs::read_to_string(space.project_path().join("requirements.lock")).unwrap();
+    assert!(lockfile.contains("#   overrides: [\"werkzeug==2.3.8\"]\n"));
+    assert!(lockfile.contains("\nwerkzeug==2.3.8\n"));
+}
+
+#[test]
+fn test_lock_explains_conflicts() {
+    let space = Space::new();
+    space.init("my-project");
+    space.edit_toml("pyproject.toml", |doc| {
+        let mut deps = toml_edit::Array::new();
+        deps.push("flask==3.0.0");
+        deps.push("werkzeug<2");
+        doc["project"]["dependencies"] = value(deps);
+    });
+    let output = space.rye_cmd().arg("lock").output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("the conflict involves:"));
+    assert!(stderr.contains("flask==3.0.0 (my-project, regular)"));
+    assert!(stderr.contains("consider relaxing werkzeug<2 (my-project, regular)"));
+    assert!(!stderr.contains("resolver output:"));
+
+    let output = space
+        .rye_cmd()
+        .arg("lock")
+        .arg("--explain")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("resolver output:"));
+}
+
+#[test]
+fn test_sync_only_unknown_group() {
+    let space = Space::new();
+    space.init("my-project");
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--only").arg("docs"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: no project defines the dependency group 'docs'
+    "###);
+}
+
+#[test]
+fn test_sync_member_outside_workspace() {
+    let space = Space::new();
+    space.init("my-project");
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--member").arg("my-project"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: cannot sync a single member outside of a workspace
+    "###);
+}
+
+#[test]
+fn test_install_lock_missing_lockfile() {
+    let space = Space::new();
+    rye_cmd_snapshot!(space.rye_cmd().arg("install-lock").arg("requirements.lock").arg("--target").arg("venv"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: lockfile 'requirements.lock' does not exist
+    "###);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_lock_never_writes_keyring_password() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let space = Space::new();
+    space.init("my-project");
+    space.edit_toml("pyproject.toml", |doc| {
+        let mut source = toml_edit::Table::new();
+        source["name"] = value("keyring-test");
+        source["url"] = value("https://pypi.example.com/simple/");
+        let mut sources = toml_edit::ArrayOfTables::new();
+        sources.push(source);
+        doc["tool"]["rye"]["sources"] = value(sources.into_array());
+        doc["tool"]["rye"]["lock-with-sources"] = value(true);
+        doc["tool"]["rye"]["virtual"] = value(true);
+    });
+
+    // register the source for the keyring and answer lookups with a fake
+    // secret-tool.
+    let credentials_path = space.rye_home().join("credentials");
+    let mut credentials: toml_edit::DocumentMut = fs::read_to_string(&credentials_path)
+        .unwrap_or_default()
+        .parse()
+        .unwrap();
+    credentials["sources"]["keyring-test"]["keyring"] = value(true);
+    credentials["sources"]["keyring-test"]["username"] = value("user");
+    fs::write(&credentials_path, credentials.to_string()).unwrap();
+    let bin_dir = space.project_path().join("fake-bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let secret_tool = bin_dir.join("secret-tool");
+    fs::write(
+        &secret_tool,
+        "#!/bin/sh\nif [ \"$1\" = lookup ]; then printf s3cr3t-pa55; exit 0; fi\nexit 1\n",
+    )
+    .unwrap();
+    fs::set_permissions(&secret_tool, fs::Permissions::from_mode(0o755)).unwrap();
+    let mut paths = vec![bin_dir];
+    paths.extend(std::env::split_paths(
+        &std::env::var_os("PATH").unwrap_or_default(),
+    ));
+    let path = std::env::join_paths(paths).unwrap();
+
+    let output = space
+        .rye_cmd()
+        .env("PATH", &path)
+        .arg("lock")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for lockfile in ["requirements.lock", "requirements-dev.lock"] {
+        let contents = space.read_string(lockfile);
+        assert!(
+            contents.contains("--extra-index-url https://user@pypi.example.com/simple/"),
+            "{}",
+            contents
+        );
+        assert!(!contents.contains("s3cr3t-pa55"), "{}", contents);
+    }
+}
+
+#[test]
+fn test_sync_named_env() {
+    let space = Space::new();
+    space.init("my-project");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["envs"]["docs"]["dev"] = value(false);
+    });
+
+    let status = space
+        .rye_cmd()
+        .arg("sync")
+        .arg("--env")
+        .arg("docs")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(space
+        .project_path()
+        .join(".venvs/docs/rye-venv.json")
+        .is_file());
+    assert!(space
+        .project_path()
+        .join("requirements.docs.lock")
+        .is_file());
+    assert!(!space.venv_path().exists());
+    assert!(!space.project_path().join("requirements.lock").exists());
+
+    // the environment is listed with its virtualenv
+    let output = space.rye_cmd().arg("show").output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("environments:\n  docs ("), "{}", stdout);
+    assert!(stdout.contains(".venvs"), "{}", stdout);
+}
+
+#[test]
+fn test_sync_unknown_env() {
+    let space = Space::new();
+    space.init("my-project");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--env").arg("docs"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: unknown environment 'docs', no environments are declared
+    "###);
+
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["envs"]["py311"]["python"] = value("3.11");
+    });
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--env").arg("docs"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: unknown environment 'docs' (declared: py311)
+    "###);
+
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["envs"]["default"]["dev"] = value(false);
+    });
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--env").arg("py311"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: invalid environment name 'default' in tool.rye.envs
+    "###);
+}
+
+#[test]
+fn test_sync_recreate() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+    let lockfile = space.read_string("requirements-dev.lock");
+    fs::write(space.venv_path().join("MARKER"), "").unwrap();
+
+    let output = space
+        .rye_cmd()
+        .arg("sync")
+        .arg("--recreate")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Initializing new virtualenv"), "{}", stdout);
+    assert!(!stdout.contains("Generating"), "{}", stdout);
+    assert!(!space.venv_path().join("MARKER").exists());
+    assert!(!space.project_path().join(".venv.rye-backup").exists());
+    assert_eq!(space.read_string("requirements-dev.lock"), lockfile);
+}
+
+#[test]
+fn test_sync_recreate_restores_on_failure() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+    fs::write(space.venv_path().join("MARKER"), "").unwrap();
+
+    // --recreate does not lock, so a missing lockfile fails the recreation
+    fs::remove_file(space.project_path().join("requirements-dev.lock")).unwrap();
+    let output = space
+        .rye_cmd()
+        .arg("sync")
+        .arg("--recreate")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Locking is disabled but lockfile"),
+        "{}",
+        stderr
+    );
+    assert!(space.venv_path().join("MARKER").is_file());
+    assert!(!space.project_path().join(".venv.rye-backup").exists());
+}
+
+#[test]
+fn test_sync_restores_interrupted_recreation() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+    fs::write(space.venv_path().join("MARKER"), "").unwrap();
+
+    // simulate a recreation that was killed halfway through
+    let backup = space.project_path().join(".venv.rye-backup");
+    fs::rename(space.venv_path(), &backup).unwrap();
+    fs::create_dir_all(space.venv_path().join("lib")).unwrap();
+
+    let output = space.rye_cmd().arg("sync").output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("restoring virtualenv after an interrupted recreation"),
+        "{}",
+        stderr
+    );
+    assert!(space.venv_path().join("MARKER").is_file());
+    assert!(!backup.exists());
+}
+
+#[test]
+fn test_sync_recreate_conflicts_with_update() {
+    let space = Space::new();
+    space.init("my-project");
+
+    let output = space
+        .rye_cmd()
+        .arg("sync")
+        .arg("--recreate")
+        .arg("--update-all")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{}", stderr);
+}
+
+#[test]
+fn test_sync_recreates_on_toolchain_change() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+    fs::write(space.venv_path().join("MARKER"), "").unwrap();
+
+    // pretend the toolchain was replaced after the virtualenv was created
+    let marker_path = space.venv_path().join("rye-venv.json");
+    let mut marker: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&marker_path).unwrap()).unwrap();
+    assert!(marker["toolchain"].is_string());
+    marker["toolchain"] = "0000000000000000".into();
+    fs::write(&marker_path, marker.to_string()).unwrap();
+
+    let output = space.rye_cmd().arg("sync").arg("--yes").output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Toolchain cpython@3.12.2 changed, recreating."),
+        "{}",
+        stdout
+    );
+    assert!(!space.venv_path().join("MARKER").exists());
+    let marker: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&marker_path).unwrap()).unwrap();
+    assert_ne!(marker["toolchain"], "0000000000000000");
+}
+
+#[test]
+fn test_sync_keeps_venv_without_toolchain_fingerprint() {
+    let space = Space::new();
+    space.init("my-project");
+    let status = space.rye_cmd().arg("sync").status().unwrap();
+    assert!(status.success());
+    fs::write(space.venv_path().join("MARKER"), "").unwrap();
+
+    // virtualenvs created by older versions do not record their toolchain
+    let marker_path = space.venv_path().join("rye-venv.json");
+    let mut marker: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&marker_path).unwrap()).unwrap();
+    marker.as_object_mut().unwrap().remove("toolchain");
+    fs::write(&marker_path, marker.to_string()).unwrap();
+
+    let output = space.rye_cmd().arg("sync").output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("recreating"), "{}", stdout);
+    assert!(space.venv_path().join("MARKER").is_file());
+}
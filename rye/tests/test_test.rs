@@ -107,6 +107,7 @@ fn test_basic_tool_behavior() {
     success: false
     exit_code: 1
     ----- stdout -----
+    Virtualenv is up to date
     Running tests for child-dep ([TEMP_PATH]/project/child-dep)
     ============================= test session starts =============================
     platform [PLATFORM] -- Python 3.12.2, pytest-7.4.3, pluggy-1.3.0
@@ -150,3 +151,128 @@ fn test_basic_tool_behavior() {
     ----- stderr -----
     "###);
 }
+
+#[test]
+fn test_custom_runner() {
+    let space = Space::new();
+    space.init("foo");
+    space.write(
+        "tests/test_foo.py",
+        "import unittest\n\nclass FooTest(unittest.TestCase):\n    def test_okay(self):\n        pass\n",
+    );
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["test"]["runner"] = value("python -m unittest discover -s tests");
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("test").arg("--").arg("-q"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    Generating production lockfile: [TEMP_PATH]/project/requirements.lock
+    Generating dev lockfile: [TEMP_PATH]/project/requirements-dev.lock
+    Installing dependencies
+    Done!
+    Running tests for foo ([TEMP_PATH]/project)
+
+    ----- stderr -----
+    Built 1 editable in [EXECUTION_TIME]
+    Installed 1 package in [EXECUTION_TIME]
+     + foo==0.1.0 (from file:[TEMP_PATH]/project)
+    ----------------------------------------------------------------------
+    Ran 1 test in [EXECUTION_TIME]
+
+    OK
+    "###);
+}
+
+#[test]
+fn test_all_pythons_requires_matrix() {
+    let space = Space::new();
+    space.init("foo");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["test"]["runner"] = value("python -m unittest");
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("test").arg("--all-pythons"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    Generating production lockfile: [TEMP_PATH]/project/requirements.lock
+    Generating dev lockfile: [TEMP_PATH]/project/requirements-dev.lock
+    Installing dependencies
+    Done!
+
+    ----- stderr -----
+    Built 1 editable in [EXECUTION_TIME]
+    Installed 1 package in [EXECUTION_TIME]
+     + foo==0.1.0 (from file:[TEMP_PATH]/project)
+    error: no Python versions configured in tool.rye.test-matrix
+    "###);
+}
+
+#[test]
+fn test_combined_coverage() {
+    let space = Space::new();
+    space.init("foo");
+    space.edit_toml("pyproject.toml", |doc| {
+        let mut deps = Array::new();
+        deps.push("coverage>=7");
+        let mut workspace_members = Array::new();
+        workspace_members.push(".");
+        workspace_members.push("child-dep");
+        doc["tool"]["rye"]["dev-dependencies"] = value(deps);
+        doc["tool"]["rye"]["workspace"]["members"] = value(workspace_members);
+        doc["tool"]["rye"]["test"]["runner"] = value("python -m unittest discover -s tests");
+    });
+    let status = space
+        .rye_cmd()
+        .arg("init")
+        .arg("-q")
+        .arg(space.project_path().join("child-dep"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    space.edit_toml("child-dep/pyproject.toml", |doc| {
+        doc["tool"]["rye"]["test"]["runner"] = value("python -m unittest discover -s tests");
+    });
+    let unittest = "import unittest\n\nclass FooTest(unittest.TestCase):\n    def test_okay(self):\n        pass\n";
+    space.write("tests/test_foo.py", unittest);
+    space.write("child-dep/tests/test_child.py", unittest);
+    let status = space.rye_cmd().arg("sync").arg("-q").status().unwrap();
+    assert!(status.success());
+
+    rye_cmd_snapshot!(space.rye_cmd()
+        .arg("test")
+        .arg("--all")
+        .arg("--coverage")
+        .arg("--coverage-report")
+        .arg("xml")
+        .arg("-q")
+        .arg("--")
+        .arg("-q"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Wrote XML report to coverage.xml
+
+    ----- stderr -----
+    ----------------------------------------------------------------------
+    Ran 1 test in [EXECUTION_TIME]
+
+    OK
+    ----------------------------------------------------------------------
+    Ran 1 test in [EXECUTION_TIME]
+
+    OK
+    "###);
+
+    // the data of both members ends up in one report in the workspace root.
+    let report = space.read_string("coverage.xml");
+    assert!(report.contains("test_foo.py"), "{}", report);
+    assert!(report.contains("test_child.py"), "{}", report);
+    assert!(!space.project_path().join("child-dep/coverage.xml").exists());
+}
@@ -1,3 +1,5 @@
+use std::fs;
+
 use crate::common::{rye_cmd_snapshot, Space};
 
 mod common;
@@ -27,7 +29,8 @@ fn test_fetch() {
     success: true
     exit_code: 0
     ----- stdout -----
-    Removing the existing Python version
+    No install_only_stripped build of cpython@3.12.1 available, using the full build
+    Replacing the existing Python version
     Downloading cpython@3.12.1
     Checking checksum
     Unpacking
@@ -36,3 +39,73 @@ fn test_fetch() {
     ----- stderr -----
     "###);
 }
+
+#[test]
+fn test_which_registered_toolchain() {
+    let space = Space::new();
+    space.init("my-project");
+
+    // Register the interpreter of an installed toolchain under a custom name.
+    let link = space.rye_home().join("py").join("which-test@3.12.2");
+    fs::remove_file(&link).ok();
+    let mut interpreter = space.rye_home().join("py").join("cpython@3.12.2");
+    #[cfg(unix)]
+    {
+        interpreter.push("bin");
+        interpreter.push("python3");
+    }
+    #[cfg(windows)]
+    {
+        interpreter.push("python.exe");
+    }
+    let status = space
+        .rye_cmd()
+        .arg("toolchain")
+        .arg("register")
+        .arg("--name=which-test")
+        .arg(&interpreter)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    space.write(".python-version", "which-test@3.12.2\n");
+
+    let output = space
+        .rye_cmd()
+        .arg("toolchain")
+        .arg("which")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("project my-project uses the virtualenv in "));
+    let resolved = lines.next().unwrap();
+    assert!(resolved.starts_with("which-test@3.12.2 ("), "{}", resolved);
+    assert!(!resolved.contains("not installed"), "{}", resolved);
+    assert!(lines
+        .next()
+        .unwrap()
+        .contains(".python-version (which-test@3.12.2)"));
+
+    fs::remove_file(&link).unwrap();
+}
+
+#[test]
+fn test_which_unknown_toolchain() {
+    let space = Space::new();
+    space.init("my-project");
+    space.write(".python-version", "unknown@3.12\n");
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("toolchain").arg("which"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    project my-project uses the virtualenv in [TEMP_PATH]/project/.venv
+
+    ----- stderr -----
+    error: Unable to determine target virtualenv Python version
+    "###);
+}
@@ -1,15 +1,17 @@
 use std::env::consts::EXE_EXTENSION;
 use std::fs;
 
+use insta::Settings;
+
 use crate::common::{rye_cmd_snapshot, Space};
 
 mod common;
 
-#[test]
-fn test_basic_tool_behavior() {
-    let space = Space::new();
-
-    // in case we left things behind from last run.
+/// Removes all installed tools.
+///
+/// All tests share the tools folder of the test rye home, so they have to
+/// hold the lock on it while they run.
+fn remove_tools(space: &Space) {
     fs::remove_dir_all(space.rye_home().join("tools")).ok();
     fs::remove_file(
         space
@@ -19,6 +21,28 @@ fn test_basic_tool_behavior() {
             .with_extension(EXE_EXTENSION),
     )
     .ok();
+}
+
+fn install_pycowsay(space: &Space, args: &[&str]) {
+    let status = space
+        .rye_cmd()
+        .arg("tools")
+        .arg("install")
+        .arg("pycowsay")
+        .args(args)
+        .arg("-q")
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn test_basic_tool_behavior() {
+    let space = Space::new();
+    let _guard = space.lock_rye_home();
+
+    // in case we left things behind from last run.
+    remove_tools(&space);
 
     rye_cmd_snapshot!(
         space.rye_cmd()
@@ -94,3 +118,353 @@ fn test_basic_tool_behavior() {
 
     assert!(!space.rye_home().join("tools").join("pycowsay").is_dir());
 }
+
+#[test]
+fn test_tools_run() {
+    let space = Space::new();
+    let _guard = space.lock_rye_home();
+    remove_tools(&space);
+
+    // the first run installs the tool into a cached environment.
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("run")
+            .arg("--refresh")
+            .arg("--from")
+            .arg("pycowsay@0.0.0.2")
+            .arg("python")
+            .arg("-c")
+            .arg("import pycowsay; print('ok')"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Installing pycowsay==0.0.0.2
+    ok
+
+    ----- stderr -----
+    "###);
+
+    // later runs reuse it.
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("run")
+            .arg("--from")
+            .arg("pycowsay@0.0.0.2")
+            .arg("python")
+            .arg("-c")
+            .arg("import pycowsay; print('ok')"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    ok
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("run")
+            .arg("--from")
+            .arg("pycowsay@0.0.0.2")
+            .arg("not-a-command"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: pycowsay does not provide a command named 'not-a-command' (use --from to pick the package)
+    "###);
+
+    // running a tool does not install it.
+    assert!(!space.rye_home().join("tools").join("pycowsay").is_dir());
+}
+
+#[test]
+fn test_tools_upgrade() {
+    let space = Space::new();
+    let _guard = space.lock_rye_home();
+    remove_tools(&space);
+    install_pycowsay(&space, &[]);
+
+    // packages newer than the cutoff of the tests are not considered.
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("upgrade")
+            .arg("pycowsay"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pycowsay is up to date (0.0.0.2)
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("upgrade")
+            .arg("not-installed"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: failed to upgrade not-installed: not-installed is not installed
+    error: unable to upgrade not-installed
+    "###);
+
+    remove_tools(&space);
+}
+
+#[test]
+fn test_tools_inject_and_install_with() {
+    let space = Space::new();
+    let _guard = space.lock_rye_home();
+    remove_tools(&space);
+    let metadata = space
+        .rye_home()
+        .join("tools")
+        .join("pycowsay")
+        .join("rye-tool.json");
+
+    install_pycowsay(&space, &[]);
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("inject")
+            .arg("pycowsay")
+            .arg("six"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Injected six into pycowsay
+
+    ----- stderr -----
+    Resolved 1 package in [EXECUTION_TIME]
+    Downloaded 1 package in [EXECUTION_TIME]
+    Installed 1 package in [EXECUTION_TIME]
+     + six==1.16.0
+    "###);
+    // injected packages are kept when the tool is upgraded.
+    assert!(fs::read_to_string(&metadata).unwrap().contains("\"six\""));
+
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("inject")
+            .arg("not-installed")
+            .arg("six"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: not-installed is not installed
+    "###);
+
+    // extra packages can also be installed right away.
+    install_pycowsay(&space, &["--force", "--with", "six"]);
+    assert!(fs::read_to_string(&metadata).unwrap().contains("\"six\""));
+    let python = space
+        .rye_home()
+        .join("tools")
+        .join("pycowsay")
+        .join(if cfg!(windows) { "Scripts" } else { "bin" })
+        .join("python");
+    let status = space
+        .cmd(python)
+        .arg("-c")
+        .arg("import six")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    remove_tools(&space);
+}
+
+#[test]
+fn test_tools_reinstall() {
+    let space = Space::new();
+    let _guard = space.lock_rye_home();
+    remove_tools(&space);
+    install_pycowsay(&space, &["-p", "cpython@3.11"]);
+
+    // the recorded Python version is used again.
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("reinstall")
+            .arg("pycowsay"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Reinstalled pycowsay 0.0.0.2 (cpython@3.11.8)
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("reinstall")
+            .arg("--all")
+            .arg("-p")
+            .arg("cpython@3.12.2"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Reinstalled pycowsay 0.0.0.2 (cpython@3.12.2)
+
+    ----- stderr -----
+    "###);
+
+    remove_tools(&space);
+}
+
+#[test]
+fn test_tools_list_details() {
+    let space = Space::new();
+    let _guard = space.lock_rye_home();
+    remove_tools(&space);
+    install_pycowsay(&space, &["-p", "cpython@3.11", "--with", "six"]);
+
+    let mut settings = Settings::clone_current();
+    settings.add_filter(r"(?m)^(  installed: ).*$", "$1[DATE]");
+    settings.add_filter(r"(?m)^(  size: ).*$", "$1[SIZE]");
+    settings.add_filter(r#"("installed_at": )\d+"#, "$1[TIMESTAMP]");
+    settings.add_filter(r#"("size": )\d+"#, "$1[SIZE]");
+    let _settings = settings.bind_to_scope();
+
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("list")
+            .arg("--details"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pycowsay
+      requested: pycowsay
+      with: six
+      python: cpython@3.11.8
+      installed: [DATE]
+      size: [SIZE]
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("list")
+            .arg("--format")
+            .arg("json"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [
+      {
+        "name": "pycowsay",
+        "version": "0.0.0.2",
+        "requirement": "pycowsay",
+        "extra_requirements": [
+          "six"
+        ],
+        "python": "cpython@3.11.8",
+        "requested_python": "cpython@3.11",
+        "scripts": [
+          "pycowsay"
+        ],
+        "installed_at": [TIMESTAMP],
+        "size": [SIZE]
+      }
+    ]
+
+    ----- stderr -----
+    "###);
+
+    remove_tools(&space);
+}
+
+#[test]
+fn test_tools_sync_manifest() {
+    let space = Space::new();
+    let _guard = space.lock_rye_home();
+    remove_tools(&space);
+    space.write("tools.toml", "[tools]\npycowsay = \"0.0.0.2\"\n");
+
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("sync")
+            .arg("--manifest")
+            .arg("tools.toml"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Installing pycowsay==0.0.0.2
+
+    ----- stderr -----
+    "###);
+
+    // tools that are installed as declared are left alone.
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("sync")
+            .arg("--manifest")
+            .arg("tools.toml"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    "###);
+
+    // tools that are no longer declared are uninstalled unless --no-prune is passed.
+    space.write("tools.toml", "[tools]\n");
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("sync")
+            .arg("--manifest")
+            .arg("tools.toml")
+            .arg("--no-prune"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    "###);
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("sync")
+            .arg("--manifest")
+            .arg("tools.toml"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Uninstalled pycowsay
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(
+        space.rye_cmd()
+            .arg("tools")
+            .arg("sync")
+            .arg("--manifest")
+            .arg("missing.toml"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: tools manifest missing.toml does not exist
+    "###);
+}
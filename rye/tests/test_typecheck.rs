@@ -0,0 +1,40 @@
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+#[test]
+fn test_typecheck() {
+    let space = Space::new();
+    space.init("my-project");
+    space.write(
+        "src/my_project/__init__.py",
+        r#"def hello() -> str:
+    return 42
+"#,
+    );
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("typecheck"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    src/my_project/__init__.py:2: error: Incompatible return value type (got "int", expected "str")  [return-value]
+    Found 1 error in 1 file (checked 1 source file)
+
+    ----- stderr -----
+    "###);
+
+    space.write(
+        "src/my_project/__init__.py",
+        r#"def hello() -> str:
+    return "Hello World"
+"#,
+    );
+    rye_cmd_snapshot!(space.rye_cmd().arg("typecheck"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Success: no issues found in 1 source file
+
+    ----- stderr -----
+    "###);
+}
@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::common::Space;
+
+mod common;
+
+/// Places the virtualenvs of the project in `~/.rye/venvs` and declares a
+/// named environment.
+fn setup_venv_outside_project(space: &Space) {
+    space.init("my-project");
+    space.write("rye.toml", "[behavior]\nvenv-in-project = false\n");
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["envs"]["docs"]["dev"] = toml_edit::value(false);
+    });
+}
+
+/// Returns the virtualenv and the environments as reported by `rye show`.
+fn show_venvs(space: &Space, dir: &Path) -> (PathBuf, Vec<String>) {
+    let output = space
+        .rye_cmd()
+        .arg("show")
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let venv = stdout
+        .lines()
+        .find_map(|x| x.strip_prefix("venv: "))
+        .map(PathBuf::from)
+        .unwrap();
+    let envs = stdout
+        .lines()
+        .skip_while(|x| *x != "environments:")
+        .skip(1)
+        .take_while(|x| x.starts_with("  "))
+        .map(|x| x.trim().to_string())
+        .collect();
+    (venv, envs)
+}
+
+#[test]
+fn test_venv_outside_project() {
+    let space = Space::new();
+    setup_venv_outside_project(&space);
+
+    let root = space.project_path().canonicalize().unwrap();
+    let mut hasher = Sha256::new();
+    hasher.update(root.to_string_lossy().as_bytes());
+    let folder = format!("project-{}", &hex::encode(hasher.finalize())[..16]);
+    let expected = space.rye_home().join("venvs").join(&folder);
+
+    let (venv, envs) = show_venvs(&space, space.project_path());
+    assert_eq!(venv, expected);
+    assert_eq!(envs, vec![format!("docs ({}-docs)", expected.display())]);
+
+    // the path only depends on the project root, not on where rye runs
+    let (venv, envs) = show_venvs(&space, &space.project_path().join("src"));
+    assert_eq!(venv, expected);
+    assert_eq!(envs, vec![format!("docs ({}-docs)", expected.display())]);
+    assert!(!space.project_path().join(".venv").exists());
+}
+
+#[test]
+fn test_venv_outside_project_named_env() {
+    let space = Space::new();
+    setup_venv_outside_project(&space);
+    let (venv, _) = show_venvs(&space, space.project_path());
+
+    let status = space
+        .rye_cmd()
+        .arg("sync")
+        .arg("-q")
+        .arg("--env")
+        .arg("docs")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let env_venv = PathBuf::from(format!("{}-docs", venv.display()));
+    assert!(env_venv.join("pyvenv.cfg").is_file());
+    assert!(!venv.exists());
+    assert!(!space.project_path().join(".venvs").exists());
+}
+
+#[test]
+fn test_venv_outside_project_shim() {
+    let space = Space::new();
+    setup_venv_outside_project(&space);
+    let (venv, _) = show_venvs(&space, space.project_path());
+
+    let status = space.rye_cmd().arg("sync").arg("-q").status().unwrap();
+    assert!(status.success());
+
+    let python = space.shim("python");
+    for _ in 0..2 {
+        let output = space
+            .cmd(&python)
+            .arg("-c")
+            .arg("import sys; print(sys.prefix)")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let prefix = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(
+            Path::new(prefix.trim()).canonicalize().unwrap(),
+            venv.canonicalize().unwrap()
+        );
+    }
+}
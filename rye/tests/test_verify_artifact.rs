@@ -0,0 +1,42 @@
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+#[test]
+fn test_verify_unsigned_artifact() {
+    let space = Space::new();
+    space.init("my-project");
+
+    let status = space.rye_cmd().arg("build").arg("-q").status().unwrap();
+    assert!(status.success());
+
+    // a wheel without an attestation next to it is rejected right away.
+    rye_cmd_snapshot!(space.rye_cmd()
+        .arg("verify-artifact")
+        .arg("dist/my_project-0.1.0-py3-none-any.whl")
+        .arg("--identity")
+        .arg("someone@example.com"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: no attestation found for dist/my_project-0.1.0-py3-none-any.whl
+    "###);
+
+    // a forged attestation does not verify either.
+    space.write(
+        "dist/my_project-0.1.0-py3-none-any.whl.publish.attestation",
+        r#"{"version": 1}"#,
+    );
+    let status = space
+        .rye_cmd()
+        .arg("verify-artifact")
+        .arg("dist/my_project-0.1.0-py3-none-any.whl")
+        .arg("--identity")
+        .arg("someone@example.com")
+        .arg("-q")
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(1));
+}
@@ -0,0 +1,248 @@
+use toml_edit::{value, Array};
+
+use crate::common::{rye_cmd_snapshot, Space};
+
+mod common;
+
+fn setup_workspace(space: &Space) {
+    space.init("foo");
+    space.edit_toml("pyproject.toml", |doc| {
+        let mut deps = Array::new();
+        deps.push("child-dep");
+        let mut workspace_members = Array::new();
+        workspace_members.push(".");
+        workspace_members.push("child-dep");
+        doc["project"]["dependencies"] = value(deps);
+        doc["tool"]["rye"]["workspace"]["members"] = value(workspace_members);
+        doc["tool"]["rye"]["scripts"]["hello"] = value("echo foo");
+    });
+    let status = space
+        .rye_cmd()
+        .arg("init")
+        .arg("-q")
+        .arg(space.project_path().join("child-dep"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    space.edit_toml("child-dep/pyproject.toml", |doc| {
+        doc["tool"]["rye"]["scripts"]["hello-child"] = value("echo child");
+    });
+}
+
+#[test]
+fn test_workspace_run_in_dependency_order() {
+    let space = Space::new();
+    setup_workspace(&space);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("workspace").arg("run").arg("--list"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Running `rye run` for child-dep ([TEMP_PATH]/project/child-dep)
+
+    Running `rye run` for foo ([TEMP_PATH]/project)
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    hello (echo foo)
+    hello-child (echo child)
+    hello (echo foo)
+    "###);
+}
+
+#[test]
+fn test_workspace_run_parallel() {
+    let space = Space::new();
+    setup_workspace(&space);
+
+    // foo depends on child-dep, so it only starts once child-dep is done
+    rye_cmd_snapshot!(space.rye_cmd().arg("workspace").arg("-j").arg("2").arg("run").arg("--list"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    child-dep | hello (echo foo)
+    child-dep | hello-child (echo child)
+    foo       | hello (echo foo)
+    "###);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("workspace").arg("-j").arg("0").arg("run"), @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+
+    ----- stderr -----
+    error: invalid value '0' for '--jobs <JOBS>': expected a positive number
+
+    For more information, try '--help'.
+    "###);
+
+    // the shared virtualenv is synced once and the lockfiles are resolved at once
+    let status = space
+        .rye_cmd()
+        .arg("workspace")
+        .arg("-j")
+        .arg("2")
+        .arg("sync")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    for lockfile in ["requirements.lock", "requirements-dev.lock"] {
+        let contents = space.read_string(lockfile);
+        assert!(contents.contains("-e file:child-dep"), "{}", contents);
+    }
+}
+
+#[test]
+fn test_workspace_filter() {
+    let space = Space::new();
+    setup_workspace(&space);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("workspace").arg("--filter").arg("child-*").arg("run").arg("--list"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Running `rye run` for child-dep ([TEMP_PATH]/project/child-dep)
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    hello (echo foo)
+    hello-child (echo child)
+    "###);
+}
+
+#[test]
+fn test_workspace_member_inherits_scripts() {
+    let space = Space::new();
+    setup_workspace(&space);
+    space.edit_toml("pyproject.toml", |doc| {
+        doc["tool"]["rye"]["scripts"]["lint"] = value("echo lint");
+    });
+    space.edit_toml("child-dep/pyproject.toml", |doc| {
+        doc["tool"]["rye"]["scripts"]["hello"] = value("echo override");
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("run").arg("--list").arg("--pyproject").arg("child-dep/pyproject.toml"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    Initializing new virtualenv in [TEMP_PATH]/project/.venv
+    Python version: cpython@3.12.2
+    hello (echo override)
+    hello-child (echo child)
+    lint (echo lint)
+    "###);
+}
+
+#[test]
+fn test_workspace_list_graph() {
+    let space = Space::new();
+    setup_workspace(&space);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("workspace").arg("list").arg("--graph"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    child-dep (child-dep)
+    foo (.)
+      -> child-dep
+
+    ----- stderr -----
+    "###);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("workspace").arg("list").arg("--format=dot"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    digraph workspace {
+      "child-dep";
+      "foo";
+      "foo" -> "child-dep";
+    }
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_workspace_list_cycle() {
+    let space = Space::new();
+    setup_workspace(&space);
+    space.edit_toml("child-dep/pyproject.toml", |doc| {
+        let mut deps = Array::new();
+        deps.push("foo");
+        doc["project"]["dependencies"] = value(deps);
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("workspace").arg("list"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    child-dep (child-dep)
+    foo (.)
+
+    ----- stderr -----
+    warning: dependency cycle between members: child-dep, foo
+    "###);
+}
+
+#[test]
+fn test_workspace_pin_outside_member_requires_python() {
+    let space = Space::new();
+    setup_workspace(&space);
+    space.edit_toml("child-dep/pyproject.toml", |doc| {
+        doc["project"]["requires-python"] = value(">= 3.12");
+    });
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("pin").arg("--relaxed").arg("3.11.1"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: cannot pin Python cpython@3.11.1 as it is not supported by all workspace members:
+      child-dep requires Python >=3.12
+    "###);
+}
+
+#[test]
+fn test_workspace_pin_propagate() {
+    let space = Space::new();
+    setup_workspace(&space);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("pin").arg("--relaxed").arg("3.11.1").arg("--sync-requires-python").arg("--propagate"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    pinned 3.11.1 in [TEMP_PATH]/project/.python-version
+    pinned 3.11.1 in [TEMP_PATH]/project/child-dep/.python-version
+
+    ----- stderr -----
+    "###);
+
+    assert_eq!(space.read_string("child-dep/.python-version"), "3.11.1\n");
+    let doc = space.read_toml("child-dep/pyproject.toml");
+    assert_eq!(doc["project"]["requires-python"].as_str(), Some(">= 3.11"));
+}
+
+#[test]
+fn test_workspace_sync_unknown_member() {
+    let space = Space::new();
+    setup_workspace(&space);
+
+    rye_cmd_snapshot!(space.rye_cmd().arg("sync").arg("--member").arg("Missing_Member"), @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    error: no workspace member named 'missing-member'
+    "###);
+}